@@ -0,0 +1,538 @@
+//! Archive input support
+//!
+//! Allows `.tar`, `.tar.gz`/`.tgz` and `.zip` release bundles to be compared like
+//! directories, and a single gzip-compressed file like that file. Only the index of
+//! an archive is read when it is opened; an entry is read when it is compared, so
+//! entries filtered out are never read and contents are not all held in memory. A
+//! single shared top-level directory is stripped so that `v1.0/src/foo.rs` lines
+//! up with `v1.1/src/foo.rs`.
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+/// Supported archive formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// Uncompressed tarball
+    Tar,
+    /// Gzip-compressed tarball
+    TarGz,
+    /// Zip archive
+    Zip,
+    /// A single gzip-compressed file
+    Gz,
+}
+
+impl ArchiveFormat {
+    /// Detect the archive format from the file extension, falling back to magic bytes
+    ///
+    /// Gzip content is a tarball only if it decompresses to a tar header.
+    pub fn detect(path: &Path) -> Result<Option<Self>> {
+        if let Some(format) = Self::from_extension(path) {
+            return Ok(Some(format));
+        }
+
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let mut header = [0u8; 262];
+        let mut file = File::open(path)
+            .with_context(|| format!("Failed to open input: {}", path.display()))?;
+        let read = read_up_to(&mut file, &mut header)?;
+
+        match Self::from_magic_bytes(&header[..read]) {
+            Some(ArchiveFormat::Gz) => {
+                file.rewind()?;
+                let mut header = [0u8; 512];
+                let read = read_up_to(&mut GzDecoder::new(BufReader::new(file)), &mut header)
+                    .with_context(|| format!("Failed to decompress input: {}", path.display()))?;
+                if looks_like_tar(&header[..read]) {
+                    Ok(Some(ArchiveFormat::TarGz))
+                } else {
+                    Ok(Some(ArchiveFormat::Gz))
+                }
+            }
+            format => Ok(format),
+        }
+    }
+
+    /// Detect the archive format from the file name
+    fn from_extension(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+
+    /// Detect the archive format from the leading bytes of a file
+    ///
+    /// Gzip magic is reported as [`ArchiveFormat::Gz`], since only the
+    /// decompressed content tells a tarball from a single file.
+    fn from_magic_bytes(header: &[u8]) -> Option<Self> {
+        if header.starts_with(&[0x1f, 0x8b]) {
+            Some(ArchiveFormat::Gz)
+        } else if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Some(ArchiveFormat::Zip)
+        } else if header.len() >= 262 && &header[257..262] == b"ustar" {
+            Some(ArchiveFormat::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// Where the content of an entry is
+#[derive(Debug, Clone, Copy)]
+enum EntryLocation {
+    /// Byte range of the tarball
+    Tar { offset: u64, size: u64 },
+    /// Index in the zip archive
+    Zip { index: usize },
+    /// The whole decompressed stream
+    Gz,
+}
+
+/// Open handle entries are read from
+enum EntryReader {
+    /// The tarball, or for a gzip-compressed one a temporary decompressed copy
+    Tar(File),
+    Zip(zip::ZipArchive<BufReader<File>>),
+    Gz,
+}
+
+/// An archive opened for reading, indexed by normalized relative path
+pub struct Archive {
+    /// Path of the archive on disk
+    pub path: PathBuf,
+    /// Detected archive format
+    pub format: ArchiveFormat,
+    /// Location of each file entry, with the common top-level directory stripped
+    entries: BTreeMap<PathBuf, EntryLocation>,
+    reader: Mutex<EntryReader>,
+}
+
+impl std::fmt::Debug for Archive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Archive")
+            .field("path", &self.path)
+            .field("format", &self.format)
+            .field("entries", &self.entries.len())
+            .finish()
+    }
+}
+
+impl Archive {
+    /// Open `path` as an archive and index its entries, returning `None` if it
+    /// is not one
+    pub fn open(path: &Path) -> Result<Option<Self>> {
+        let Some(format) = ArchiveFormat::detect(path)? else {
+            return Ok(None);
+        };
+
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open archive: {}", path.display()))?;
+        let (entries, reader) = Self::index(format, path, file)
+            .with_context(|| format!("Failed to read archive: {}", path.display()))?;
+
+        Ok(Some(Self {
+            path: path.to_path_buf(),
+            format,
+            entries: strip_common_prefix(entries),
+            reader: Mutex::new(reader),
+        }))
+    }
+
+    /// Index the file entries of an archive without reading their content
+    fn index(
+        format: ArchiveFormat,
+        path: &Path,
+        file: File,
+    ) -> Result<(BTreeMap<PathBuf, EntryLocation>, EntryReader)> {
+        match format {
+            ArchiveFormat::Tar => Self::index_tar(file),
+            ArchiveFormat::TarGz => {
+                // Gzip streams cannot be seeked, so entries are read from a
+                // decompressed copy that is deleted once the archive is dropped
+                let mut tar = tempfile::tempfile()?;
+                std::io::copy(&mut GzDecoder::new(BufReader::new(file)), &mut tar)?;
+                tar.rewind()?;
+                Self::index_tar(tar)
+            }
+            ArchiveFormat::Zip => Self::index_zip(file),
+            ArchiveFormat::Gz => {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                let name = name
+                    .strip_suffix(".gz")
+                    .or_else(|| name.strip_suffix(".GZ"))
+                    .unwrap_or(&name);
+                let entries = BTreeMap::from([(PathBuf::from(name), EntryLocation::Gz)]);
+                Ok((entries, EntryReader::Gz))
+            }
+        }
+    }
+
+    fn index_tar(file: File) -> Result<(BTreeMap<PathBuf, EntryLocation>, EntryReader)> {
+        let mut entries = BTreeMap::new();
+        let mut archive = tar::Archive::new(file);
+
+        for entry in archive.entries()? {
+            let entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            let Some(path) = normalize_entry_path(&entry.path()?) else {
+                continue;
+            };
+
+            let location = EntryLocation::Tar {
+                offset: entry.raw_file_position(),
+                size: entry.size(),
+            };
+            entries.insert(path, location);
+        }
+
+        Ok((entries, EntryReader::Tar(archive.into_inner())))
+    }
+
+    fn index_zip(file: File) -> Result<(BTreeMap<PathBuf, EntryLocation>, EntryReader)> {
+        let mut entries = BTreeMap::new();
+        let mut archive = zip::ZipArchive::new(BufReader::new(file))?;
+
+        for index in 0..archive.len() {
+            let file = archive.by_index_raw(index)?;
+            if !file.is_file() {
+                continue;
+            }
+
+            let Some(path) = file.enclosed_name().and_then(|p| normalize_entry_path(&p)) else {
+                continue;
+            };
+            entries.insert(path, EntryLocation::Zip { index });
+        }
+
+        Ok((entries, EntryReader::Zip(archive)))
+    }
+
+    /// Relative paths of the file entries, in order
+    pub fn entry_paths(&self) -> impl Iterator<Item = &Path> {
+        self.entries.keys().map(PathBuf::as_path)
+    }
+
+    /// Whether the archive has a file entry at `relative_path`
+    pub fn contains(&self, relative_path: &Path) -> bool {
+        self.entries.contains_key(relative_path)
+    }
+
+    /// Virtual path used to report an entry, e.g. `release.tar.gz/src/foo.rs`
+    pub fn virtual_path(&self, relative_path: &Path) -> PathBuf {
+        self.path.join(relative_path)
+    }
+
+    /// Read an entry addressed by its virtual path as UTF-8 text
+    pub fn read_to_string(&self, virtual_path: &Path) -> Result<String> {
+        let relative_path = virtual_path
+            .strip_prefix(&self.path)
+            .unwrap_or(virtual_path);
+        let location = *self.entries.get(relative_path).with_context(|| {
+            format!(
+                "Entry {} not found in archive {}",
+                relative_path.display(),
+                self.path.display()
+            )
+        })?;
+
+        let content = self.read_entry(location).with_context(|| {
+            format!(
+                "Failed to read {} from archive {}",
+                relative_path.display(),
+                self.path.display()
+            )
+        })?;
+        String::from_utf8(content).with_context(|| {
+            format!(
+                "Archive entry is not valid UTF-8: {}",
+                virtual_path.display()
+            )
+        })
+    }
+
+    fn read_entry(&self, location: EntryLocation) -> Result<Vec<u8>> {
+        let mut reader = self
+            .reader
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Archive reader lock poisoned"))?;
+        let mut content = Vec::new();
+        match (&mut *reader, location) {
+            (EntryReader::Tar(file), EntryLocation::Tar { offset, size }) => {
+                file.seek(SeekFrom::Start(offset))?;
+                file.take(size).read_to_end(&mut content)?;
+            }
+            (EntryReader::Zip(archive), EntryLocation::Zip { index }) => {
+                archive.by_index(index)?.read_to_end(&mut content)?;
+            }
+            (EntryReader::Gz, EntryLocation::Gz) => {
+                let file = File::open(&self.path)?;
+                GzDecoder::new(BufReader::new(file)).read_to_end(&mut content)?;
+            }
+            _ => unreachable!("entries are located in their own archive's format"),
+        }
+        Ok(content)
+    }
+}
+
+/// Read as many bytes as fit in `buffer`, short only at the end of the stream
+fn read_up_to(reader: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..])? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+/// Whether `header` starts with a tar header block: a POSIX `ustar` magic, or
+/// for older tarballs a valid header checksum
+fn looks_like_tar(header: &[u8]) -> bool {
+    if header.len() < 512 {
+        return false;
+    }
+    if &header[257..262] == b"ustar" {
+        return true;
+    }
+
+    let recorded = std::str::from_utf8(&header[148..156])
+        .ok()
+        .map(|field| field.trim_matches(|c: char| c == '\0' || c == ' '))
+        .and_then(|field| u32::from_str_radix(field, 8).ok());
+    // The checksum counts its own field as spaces
+    let computed: u32 = header[..512]
+        .iter()
+        .enumerate()
+        .map(|(i, &byte)| {
+            if (148..156).contains(&i) {
+                32
+            } else {
+                byte as u32
+            }
+        })
+        .sum();
+    recorded == Some(computed) && header[0] != 0
+}
+
+/// Normalize an archive entry path, rejecting absolute and parent-relative paths
+fn normalize_entry_path(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+
+    if normalized.as_os_str().is_empty() {
+        None
+    } else {
+        Some(normalized)
+    }
+}
+
+/// Strip a single top-level directory shared by every entry (`--strip-components=1`)
+fn strip_common_prefix<T>(entries: BTreeMap<PathBuf, T>) -> BTreeMap<PathBuf, T> {
+    let mut prefix: Option<PathBuf> = None;
+
+    for path in entries.keys() {
+        let mut components = path.components();
+        let first = components.next().map(|c| PathBuf::from(c.as_os_str()));
+
+        // A file at the root means there is no shared top-level directory
+        if components.next().is_none() {
+            return entries;
+        }
+
+        match (&prefix, first) {
+            (None, Some(first)) => prefix = Some(first),
+            (Some(existing), Some(first)) if *existing == first => {}
+            _ => return entries,
+        }
+    }
+
+    let Some(prefix) = prefix else {
+        return entries;
+    };
+
+    entries
+        .into_iter()
+        .map(|(path, content)| {
+            let stripped = path.strip_prefix(&prefix).unwrap_or(&path).to_path_buf();
+            (stripped, content)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn test_detect_format_from_extension() {
+        assert_eq!(
+            ArchiveFormat::from_extension(Path::new("release-1.0.tar.gz")),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_extension(Path::new("release.tgz")),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_extension(Path::new("release.tar")),
+            Some(ArchiveFormat::Tar)
+        );
+        assert_eq!(
+            ArchiveFormat::from_extension(Path::new("release.ZIP")),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(ArchiveFormat::from_extension(Path::new("main.rs")), None);
+    }
+
+    #[test]
+    fn test_detect_format_from_magic_bytes() {
+        assert_eq!(
+            ArchiveFormat::from_magic_bytes(&[0x1f, 0x8b, 0x08]),
+            Some(ArchiveFormat::Gz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_magic_bytes(b"PK\x03\x04rest"),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(ArchiveFormat::from_magic_bytes(b"int main() {}"), None);
+    }
+
+    #[test]
+    fn test_strip_common_prefix() {
+        let mut entries = BTreeMap::new();
+        entries.insert(PathBuf::from("v1.0/src/foo.rs"), ());
+        entries.insert(PathBuf::from("v1.0/README.md"), ());
+
+        let stripped = strip_common_prefix(entries);
+        assert!(stripped.contains_key(Path::new("src/foo.rs")));
+        assert!(stripped.contains_key(Path::new("README.md")));
+
+        let mut entries = BTreeMap::new();
+        entries.insert(PathBuf::from("a/foo.rs"), ());
+        entries.insert(PathBuf::from("b/foo.rs"), ());
+
+        let untouched = strip_common_prefix(entries);
+        assert!(untouched.contains_key(Path::new("a/foo.rs")));
+    }
+
+    #[test]
+    fn test_normalize_entry_path_rejects_traversal() {
+        assert_eq!(
+            normalize_entry_path(Path::new("./src/lib.rs")),
+            Some(PathBuf::from("src/lib.rs"))
+        );
+        assert_eq!(normalize_entry_path(Path::new("../etc/passwd")), None);
+        assert_eq!(normalize_entry_path(Path::new("/etc/passwd")), None);
+    }
+
+    #[test]
+    fn test_gzip_is_a_tarball_only_if_it_holds_one() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let gzip = |path: &Path, content: &[u8]| {
+            let mut encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+            encoder.write_all(content).unwrap();
+            encoder.finish().unwrap();
+        };
+
+        let source = "int main(void) {\n    return 0;\n}\n";
+        let single = dir.path().join("main.c.gz");
+        gzip(&single, source.as_bytes());
+        let archive = Archive::open(&single)
+            .unwrap()
+            .expect("gzip file is an input");
+        assert_eq!(archive.format, ArchiveFormat::Gz);
+        assert_eq!(
+            archive.entry_paths().collect::<Vec<_>>(),
+            vec![Path::new("main.c")]
+        );
+        assert_eq!(
+            archive
+                .read_to_string(&archive.virtual_path(Path::new("main.c")))
+                .unwrap(),
+            source
+        );
+
+        // A tarball named without its extension is still told apart by its content
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(source.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "v1/src/main.c", source.as_bytes())
+            .unwrap();
+        let unnamed = dir.path().join("release");
+        gzip(&unnamed, &builder.into_inner().unwrap());
+        let archive = Archive::open(&unnamed)
+            .unwrap()
+            .expect("tarball is an input");
+        assert_eq!(archive.format, ArchiveFormat::TarGz);
+        assert!(archive.contains(Path::new("src/main.c")));
+    }
+
+    #[test]
+    fn test_fixture_archives_align_after_stripping() {
+        let source = Archive::open(&fixture("release-1.0.tar.gz"))
+            .unwrap()
+            .expect("tar.gz fixture should be detected as an archive");
+        let target = Archive::open(&fixture("release-1.1.zip"))
+            .unwrap()
+            .expect("zip fixture should be detected as an archive");
+
+        assert_eq!(source.format, ArchiveFormat::TarGz);
+        assert_eq!(target.format, ArchiveFormat::Zip);
+
+        let shared: Vec<&Path> = source
+            .entry_paths()
+            .filter(|path| target.contains(path))
+            .collect();
+        assert!(shared.contains(&Path::new("src/math.c")));
+
+        let source_text = source
+            .read_to_string(&source.virtual_path(Path::new("src/math.c")))
+            .unwrap();
+        let target_text = target
+            .read_to_string(&target.virtual_path(Path::new("src/math.c")))
+            .unwrap();
+        assert!(source_text.contains("int add"));
+        assert_ne!(source_text, target_text);
+    }
+}
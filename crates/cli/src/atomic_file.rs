@@ -0,0 +1,97 @@
+//! Atomic output file writes
+//!
+//! Reports written with `--output` are first written to a temporary file next
+//! to the destination, flushed to disk and then renamed over it. A crash or a
+//! full disk mid-write therefore never leaves a truncated report behind for CI
+//! to pick up: the destination either keeps its old content or gets the new one.
+
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Write `content` to `path` atomically, creating parent directories as needed
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(parent)
+        .with_context(|| format!("Failed to create output directory: {}", parent.display()))?;
+
+    let temp_path = temp_path_for(path);
+    let result = write_and_sync(&temp_path, content).and_then(|()| {
+        fs::rename(&temp_path, path)
+            .with_context(|| format!("Failed to move output into place: {}", path.display()))
+    });
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+        return result;
+    }
+
+    // Persist the rename itself; not supported for directories on every platform
+    if let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
+    }
+
+    Ok(())
+}
+
+fn write_and_sync(temp_path: &Path, content: &[u8]) -> Result<()> {
+    let mut file = File::create(temp_path)
+        .with_context(|| format!("Failed to create temporary file: {}", temp_path.display()))?;
+    file.write_all(content)
+        .with_context(|| format!("Failed to write output to: {}", temp_path.display()))?;
+    file.sync_all()
+        .with_context(|| format!("Failed to flush output to disk: {}", temp_path.display()))?;
+    Ok(())
+}
+
+/// Hidden sibling of `path`, so the final rename stays on the same filesystem
+fn temp_path_for(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "output".to_string());
+    path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{OutputFormat, RefactoringDetail};
+    use crate::output::{ComparisonStats, OutputFormatter, ScoreFormat};
+
+    #[test]
+    fn test_json_report_written_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("reports").join("diff.json");
+
+        let stats = ComparisonStats::default();
+        let json = OutputFormatter::format_comparison_results(
+            &[],
+            &OutputFormat::Json,
+            Some(&stats),
+            true,
+            RefactoringDetail::Standard,
+            ScoreFormat::default(),
+        )
+        .unwrap();
+        write_atomic(&path, json.as_bytes()).unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(written["results"].as_array().unwrap().is_empty());
+        assert!(written["stats"].is_object());
+
+        // Overwriting keeps a single, complete file and no temporary leftovers
+        write_atomic(&path, b"{}").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}");
+        let entries: Vec<_> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, vec![std::ffi::OsString::from("diff.json")]);
+    }
+}
@@ -0,0 +1,200 @@
+//! Baseline of accepted findings
+//!
+//! Like a linter baseline, the file lists findings a team has accepted, so
+//! `compare --fail-on-changes` only fails on findings that are not in it. A
+//! finding is one function change. Its id hashes the change type, the file
+//! path relative to the compared root and the function names, so it survives
+//! line shifts and checkouts in different directories.
+
+use crate::atomic_file::write_atomic;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use smart_diff_parser::Change;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Baseline file used when `--baseline` is not given
+pub const DEFAULT_BASELINE_PATH: &str = ".smartdiff/baseline.json";
+
+/// A finding that can fail a gate, with a stable id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub id: String,
+    pub description: String,
+}
+
+impl Finding {
+    /// Findings for the function changes of one compared file pair
+    ///
+    /// A key seen twice in the same file, such as two changed overloads, gets
+    /// an occurrence suffix so the findings keep distinct ids.
+    pub fn from_changes(file: &Path, changes: &[Change]) -> Vec<Self> {
+        let file = file.to_string_lossy().replace('\\', "/");
+        let mut occurrences: HashMap<String, usize> = HashMap::new();
+
+        changes
+            .iter()
+            .map(|change| {
+                let source = change.source.as_ref().map(|element| element.name.as_str());
+                let target = change.target.as_ref().map(|element| element.name.as_str());
+                let key = format!(
+                    "{:?}\0{}\0{}\0{}",
+                    change.change_type,
+                    file,
+                    source.unwrap_or_default(),
+                    target.unwrap_or_default()
+                );
+
+                let occurrence = occurrences.entry(key.clone()).or_insert(0);
+                *occurrence += 1;
+                let key = match *occurrence {
+                    1 => key,
+                    n => format!("{}\0{}", key, n),
+                };
+
+                let name = match (source, target) {
+                    (Some(source), Some(target)) if source != target => {
+                        format!("{} -> {}", source, target)
+                    }
+                    (Some(name), _) | (None, Some(name)) => name.to_string(),
+                    (None, None) => String::new(),
+                };
+                Self {
+                    id: format!("{:016x}", fnv1a(key.as_bytes())),
+                    description: format!("{:?} {} in {}", change.change_type, name, file),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Accepted findings, keyed by id
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    findings: BTreeMap<String, String>,
+}
+
+impl Baseline {
+    /// Accept every one of `findings`
+    pub fn from_findings(findings: &[Finding]) -> Self {
+        Self {
+            findings: findings
+                .iter()
+                .map(|finding| (finding.id.clone(), finding.description.clone()))
+                .collect(),
+        }
+    }
+
+    /// Load a baseline file; a missing file accepts nothing
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse baseline: {}", path.display()))
+    }
+
+    /// Write the baseline atomically, sorted by id so it diffs cleanly
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        write_atomic(path, content.as_bytes())
+            .with_context(|| format!("Failed to write baseline: {}", path.display()))
+    }
+
+    /// Number of accepted findings
+    pub fn len(&self) -> usize {
+        self.findings.len()
+    }
+
+    /// Findings the baseline does not accept
+    pub fn new_findings<'a>(&self, findings: &'a [Finding]) -> Vec<&'a Finding> {
+        findings
+            .iter()
+            .filter(|finding| !self.findings.contains_key(&finding.id))
+            .collect()
+    }
+}
+
+/// 64-bit FNV-1a, stable across Rust versions unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smart_diff_parser::{ChangeType, CodeElement, ElementType};
+
+    fn change(change_type: ChangeType, name: &str, line: usize) -> Change {
+        let element = CodeElement {
+            id: format!("{}_{}", name, line),
+            element_type: ElementType::Function,
+            name: name.to_string(),
+            file_path: "/checkout/src/lib.c".to_string(),
+            start_line: line,
+            end_line: line + 3,
+            signature: None,
+            hash: String::new(),
+        };
+        let mut change = Change::new(change_type, format!("{} changed", name));
+        change.source = Some(element.clone());
+        change.target = Some(element);
+        change
+    }
+
+    #[test]
+    fn test_finding_ids_ignore_lines_and_keep_duplicates_apart() {
+        let file = Path::new("src/lib.c");
+        let before = Finding::from_changes(file, &[change(ChangeType::Modify, "parse", 10)]);
+        let shifted = Finding::from_changes(file, &[change(ChangeType::Modify, "parse", 40)]);
+        assert_eq!(before, shifted);
+        assert_eq!(before[0].description, "Modify parse in src/lib.c");
+
+        let overloads = Finding::from_changes(
+            file,
+            &[
+                change(ChangeType::Modify, "parse", 10),
+                change(ChangeType::Modify, "parse", 20),
+            ],
+        );
+        assert_eq!(overloads[0].id, before[0].id);
+        assert_ne!(overloads[0].id, overloads[1].id);
+
+        let other_file = Finding::from_changes(
+            Path::new("src/main.c"),
+            &[change(ChangeType::Modify, "parse", 10)],
+        );
+        assert_ne!(other_file[0].id, before[0].id);
+    }
+
+    #[test]
+    fn test_baseline_round_trip_accepts_saved_findings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEFAULT_BASELINE_PATH);
+        assert_eq!(Baseline::load(&path).unwrap().len(), 0);
+
+        let file = Path::new("src/lib.c");
+        let accepted = Finding::from_changes(file, &[change(ChangeType::Modify, "parse", 10)]);
+        Baseline::from_findings(&accepted).save(&path).unwrap();
+
+        let baseline = Baseline::load(&path).unwrap();
+        let current = Finding::from_changes(
+            file,
+            &[
+                change(ChangeType::Modify, "parse", 12),
+                change(ChangeType::Add, "render", 30),
+            ],
+        );
+        let new: Vec<&str> = baseline
+            .new_findings(&current)
+            .iter()
+            .map(|finding| finding.description.as_str())
+            .collect();
+        assert_eq!(new, vec!["Add render in src/lib.c"]);
+    }
+}
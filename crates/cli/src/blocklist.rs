@@ -0,0 +1,157 @@
+//! Boilerplate functions left out of the change list
+//!
+//! Methods such as Java's `equals` and `hashCode` are regenerated whenever a
+//! class gains a field, so they change along with the real edits and clutter
+//! the results. Changes to functions named in the blocklist are dropped from
+//! the detailed output of `compare`; they still count in its totals.
+//!
+//! Each language has built-in defaults, which the `[blocklist]` table of the
+//! `--config` file can turn off and extend with patterns of its own. A pattern
+//! is a glob matched against the whole function name, or a regular expression
+//! when written between slashes.
+
+use crate::glob;
+use crate::output::ComparisonResult;
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use smart_diff_parser::{Change, Language};
+use std::collections::HashMap;
+
+/// Functions blocklisted by default, per language
+fn default_patterns(language: Language) -> &'static [&'static str] {
+    match language {
+        Language::Java => &["equals", "hashCode", "toString"],
+        Language::Python => &["__repr__", "__str__", "__eq__", "__ne__", "__hash__"],
+        Language::JavaScript | Language::TypeScript => &["toString", "toJSON"],
+        Language::Rust => &["eq", "ne", "hash", "clone"],
+        Language::Go => &["String"],
+        Language::Ruby => &["to_s", "inspect", "hash", "eql?", "=="],
+        Language::PHP => &["__toString"],
+        Language::Swift => &["hash", "=="],
+        Language::Cpp
+        | Language::C
+        | Language::Markdown
+        | Language::Dockerfile
+        | Language::Unknown => &[],
+    }
+}
+
+/// The `[blocklist]` table of the configuration file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BlocklistConfig {
+    /// Whether the built-in patterns of each language apply
+    pub defaults: bool,
+    /// Patterns for every language
+    pub patterns: Vec<String>,
+    /// Patterns for one language, keyed by name or file extension
+    pub languages: HashMap<String, Vec<String>>,
+}
+
+impl Default for BlocklistConfig {
+    fn default() -> Self {
+        Self {
+            defaults: true,
+            patterns: Vec::new(),
+            languages: HashMap::new(),
+        }
+    }
+}
+
+/// Compiled function name patterns
+#[derive(Debug, Clone, Default)]
+pub struct Blocklist {
+    defaults: bool,
+    common: Vec<Regex>,
+    languages: HashMap<Language, Vec<Regex>>,
+}
+
+impl Blocklist {
+    /// Compile the patterns of `config`, failing on the first invalid one
+    pub fn new(config: &BlocklistConfig) -> Result<Self> {
+        let compile = |patterns: &[String]| -> Result<Vec<Regex>> {
+            patterns.iter().map(|pattern| compile(pattern)).collect()
+        };
+
+        let mut languages = HashMap::new();
+        for (name, patterns) in &config.languages {
+            let language = parse_language(name)
+                .with_context(|| format!("Unknown blocklist language '{}'", name))?;
+            languages
+                .entry(language)
+                .or_insert_with(Vec::new)
+                .extend(compile(patterns)?);
+        }
+
+        Ok(Self {
+            defaults: config.defaults,
+            common: compile(&config.patterns)?,
+            languages,
+        })
+    }
+
+    /// Whether changes to a function called `name` are left out
+    ///
+    /// Qualified names like `Point.equals` or `Point::eq` are matched by
+    /// their last segment too.
+    pub fn is_blocked(&self, language: Language, name: &str) -> bool {
+        let short = name.rsplit(|c| c == '.' || c == ':').next().unwrap_or(name);
+        let matches = |regex: &Regex| regex.is_match(name) || regex.is_match(short);
+
+        (self.defaults && default_patterns(language).contains(&short))
+            || self.common.iter().any(matches)
+            || self
+                .languages
+                .get(&language)
+                .is_some_and(|patterns| patterns.iter().any(matches))
+    }
+
+    /// Whether every function a change touches is blocklisted, so a renamed
+    /// `toString` stays listed
+    fn blocks(&self, language: Language, change: &Change) -> bool {
+        let mut names = change
+            .source
+            .iter()
+            .chain(change.target.iter())
+            .map(|element| element.name.as_str())
+            .peekable();
+        names.peek().is_some() && names.all(|name| self.is_blocked(language, name))
+    }
+
+    /// Drop the blocklisted changes of `result`, returning how many were dropped
+    ///
+    /// The statistics of `result` are left as they are, so the dropped
+    /// changes still count in the totals.
+    pub fn filter(&self, result: &mut ComparisonResult) -> usize {
+        let changes = &mut result.diff_result.match_result.changes;
+        let before = changes.len();
+        changes.retain(|change| !self.blocks(result.language, change));
+        before - changes.len()
+    }
+}
+
+/// Compile a glob, or a regular expression written `/…/`, matching whole names
+fn compile(pattern: &str) -> Result<Regex> {
+    let regex = match pattern
+        .strip_prefix('/')
+        .and_then(|pattern| pattern.strip_suffix('/'))
+    {
+        Some(regex) => Regex::new(regex).map_err(anyhow::Error::from),
+        None if pattern.is_empty() => bail!("Empty blocklist pattern"),
+        None => glob::to_regex(pattern),
+    };
+    regex.with_context(|| format!("Invalid blocklist pattern '{}'", pattern))
+}
+
+/// Language named by `name`, its display name or a file extension
+pub(crate) fn parse_language(name: &str) -> Option<Language> {
+    let name = name.trim();
+    match Language::from_extension(name) {
+        Language::Unknown => Language::all()
+            .iter()
+            .copied()
+            .find(|language| language.to_string().eq_ignore_ascii_case(name)),
+        language => Some(language),
+    }
+}
@@ -0,0 +1,172 @@
+//! Call sites of functions whose signature changed
+//!
+//! Calls and signatures of both versions are collected while file pairs are
+//! compared. Once every pair is done, each changed function whose new
+//! signature may break its existing callers is annotated with those callers,
+//! reported as `file:line (caller)`, wherever in the tree they are.
+
+use crate::output::ComparisonResult;
+use smart_diff_engine::{CallSiteTracker, FunctionCallImpact};
+use smart_diff_parser::{ChangeType, ElementType, ParseResult};
+use smart_diff_semantic::{
+    EnhancedFunctionSignature, FunctionSignatureExtractor, SymbolResolver, SymbolResolverConfig,
+    SymbolTable,
+};
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::warn;
+
+/// Calls and function signatures of both versions of the compared tree
+pub struct CallSites {
+    source: SymbolResolver,
+    target: SymbolResolver,
+    source_signatures: HashMap<String, EnhancedFunctionSignature>,
+    target_signatures: HashMap<String, EnhancedFunctionSignature>,
+}
+
+impl Default for CallSites {
+    fn default() -> Self {
+        let resolver = || {
+            SymbolResolver::new(SymbolResolverConfig {
+                track_usages: false,
+                ..Default::default()
+            })
+        };
+        Self {
+            source: resolver(),
+            target: resolver(),
+            source_signatures: HashMap::new(),
+            target_signatures: HashMap::new(),
+        }
+    }
+}
+
+impl CallSites {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the calls and signatures of the two parsed versions of a file pair
+    pub fn add(
+        &mut self,
+        source_path: &Path,
+        source: &ParseResult,
+        target_path: &Path,
+        target: &ParseResult,
+    ) {
+        Self::add_file(
+            &mut self.source,
+            &mut self.source_signatures,
+            source_path,
+            source,
+        );
+        Self::add_file(
+            &mut self.target,
+            &mut self.target_signatures,
+            target_path,
+            target,
+        );
+    }
+
+    fn add_file(
+        resolver: &mut SymbolResolver,
+        signatures: &mut HashMap<String, EnhancedFunctionSignature>,
+        path: &Path,
+        parsed: &ParseResult,
+    ) {
+        let path = path.display().to_string();
+        if let Err(e) = resolver.process_file(&path, parsed) {
+            warn!("Failed to record calls in {}: {}", path, e);
+        }
+        match FunctionSignatureExtractor::with_defaults(parsed.language)
+            .extract_signatures(&path, parsed)
+        {
+            Ok(extracted) => signatures.extend(extracted.signature_map),
+            Err(e) => warn!("Failed to extract signatures from {}: {}", path, e),
+        }
+    }
+
+    /// Symbols and calls of the old version of the tree
+    pub fn source_symbols(&self) -> &SymbolTable {
+        self.source.get_symbol_table()
+    }
+
+    /// Annotate changed functions whose new signature may break existing callers
+    ///
+    /// The reasons go to the change's `breaking_reasons` metadata and the
+    /// callers, from the old version, to `breaking_call_sites`.
+    pub fn annotate_signature_changes(&self, results: &mut [ComparisonResult]) {
+        let analysis = match CallSiteTracker::with_defaults().analyze(
+            self.source.get_symbol_table(),
+            self.target.get_symbol_table(),
+            &self.source_signatures,
+            &self.target_signatures,
+        ) {
+            Ok(analysis) => analysis,
+            Err(e) => {
+                warn!("Failed to analyze call sites: {}", e);
+                return;
+            }
+        };
+        let breaking: HashMap<&str, &FunctionCallImpact> = analysis
+            .impacts
+            .iter()
+            .filter(|impact| !impact.potentially_breaking.is_empty())
+            .map(|impact| (short_name(&impact.function_name), impact))
+            .collect();
+        if breaking.is_empty() {
+            return;
+        }
+
+        for change in results
+            .iter_mut()
+            .flat_map(|result| &mut result.diff_result.match_result.changes)
+        {
+            if matches!(change.change_type, ChangeType::Add | ChangeType::Delete) {
+                continue;
+            }
+            let Some(element) = change.source.as_ref().filter(|element| {
+                matches!(
+                    element.element_type,
+                    ElementType::Function | ElementType::Method
+                )
+            }) else {
+                continue;
+            };
+            let Some(impact) = breaking.get(short_name(&element.name)) else {
+                continue;
+            };
+
+            let call_sites: Vec<String> = impact
+                .potentially_breaking
+                .iter()
+                .map(|site| {
+                    format!(
+                        "{}:{} ({})",
+                        site.file_path,
+                        site.line,
+                        site.caller_function.as_deref().unwrap_or("<top level>")
+                    )
+                })
+                .collect();
+            change.details.description.push_str(&format!(
+                " (may break {} call site{})",
+                call_sites.len(),
+                if call_sites.len() == 1 { "" } else { "s" }
+            ));
+            change.details.metadata.insert(
+                "breaking_reasons".to_string(),
+                impact.breaking_reasons.join("; "),
+            );
+            change
+                .details
+                .metadata
+                .insert("breaking_call_sites".to_string(), call_sites.join(", "));
+        }
+    }
+}
+
+/// Name of a function without its class or module
+fn short_name(name: &str) -> &str {
+    name.rsplit(['.', ':']).next().unwrap_or(name)
+}
@@ -0,0 +1,98 @@
+//! Code churn
+//!
+//! Used by `compare --churn`. Next to the per-function changes, churn says
+//! how much changed in aggregate: lines added, removed and modified, counted
+//! from a line diff of each file pair, and how many functions were touched.
+//! A run of replaced lines counts as modified up to the shorter side of the
+//! replacement; the rest of it counts as added or removed.
+//!
+//! With test classification on (`--separate-tests` or `--check-test-updates`)
+//! the totals are split between production and test code too.
+
+use crate::output::ComparisonResult;
+use crate::test_files::FileCategory;
+use serde::Serialize;
+use similar::{DiffOp, TextDiff};
+
+/// Lines and functions a comparison touched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Churn {
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub lines_modified: usize,
+    pub functions_touched: usize,
+}
+
+impl Churn {
+    /// Churn of one file pair with `functions_touched` changed functions
+    pub fn between(source: &str, target: &str, functions_touched: usize) -> Self {
+        let mut churn = Self {
+            functions_touched,
+            ..Default::default()
+        };
+        for op in TextDiff::from_lines(source, target).ops() {
+            match *op {
+                DiffOp::Equal { .. } => {}
+                DiffOp::Delete { old_len, .. } => churn.lines_removed += old_len,
+                DiffOp::Insert { new_len, .. } => churn.lines_added += new_len,
+                DiffOp::Replace {
+                    old_len, new_len, ..
+                } => {
+                    churn.lines_modified += old_len.min(new_len);
+                    churn.lines_removed += old_len.saturating_sub(new_len);
+                    churn.lines_added += new_len.saturating_sub(old_len);
+                }
+            }
+        }
+        churn
+    }
+
+    pub fn merge(&mut self, other: &Churn) {
+        self.lines_added += other.lines_added;
+        self.lines_removed += other.lines_removed;
+        self.lines_modified += other.lines_modified;
+        self.functions_touched += other.functions_touched;
+    }
+}
+
+/// Churn of one file pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FileChurn {
+    #[serde(flatten)]
+    pub churn: Churn,
+    /// Only present with test classification
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<FileCategory>,
+}
+
+/// Churn of a whole comparison
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ChurnSummary {
+    pub total: Churn,
+    /// Only present with test classification
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub production: Option<Churn>,
+    /// Only present with test classification
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tests: Option<Churn>,
+}
+
+impl ChurnSummary {
+    /// Sum the churn of `results`, or `None` if it was not computed
+    pub fn of(results: &[ComparisonResult]) -> Option<Self> {
+        let mut summary: Option<Self> = None;
+        for churn in results.iter().filter_map(|result| result.churn.as_ref()) {
+            let summary = summary.get_or_insert_with(Self::default);
+            summary.total.merge(&churn.churn);
+            let category = match churn.category {
+                Some(FileCategory::Production) => &mut summary.production,
+                Some(FileCategory::Test) => &mut summary.tests,
+                None => continue,
+            };
+            category
+                .get_or_insert_with(Churn::default)
+                .merge(&churn.churn);
+        }
+        summary
+    }
+}
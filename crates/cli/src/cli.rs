@@ -1,6 +1,7 @@
 //! CLI argument parsing and configuration
 
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use is_terminal::IsTerminal;
 use std::path::PathBuf;
 
 #[derive(Parser, Debug, Clone)]
@@ -15,7 +16,11 @@ of functions and classes across different versions of your codebase."
 )]
 pub struct Cli {
     #[command(subcommand)]
-    pub command: Commands,
+    pub command: Option<Commands>,
+
+    /// List the supported languages and their file extensions, then exit
+    #[arg(long)]
+    pub list_languages: bool,
 
     /// Enable verbose output with detailed logging
     #[arg(short, long, global = true)]
@@ -29,86 +34,162 @@ pub struct Cli {
     #[arg(short, long, global = true)]
     pub config: Option<PathBuf>,
 
-    /// Disable colored output
-    #[arg(long, global = true)]
+    /// When to color output: auto colors only a terminal and follows
+    /// `NO_COLOR` and `CLICOLOR_FORCE`
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "WHEN",
+        default_value = "auto"
+    )]
+    pub color: ColorChoice,
+
+    /// Deprecated alias for `--color=never`
+    #[arg(long, global = true, hide = true)]
     pub no_color: bool,
 
     /// Enable quiet mode (minimal output)
     #[arg(short, long, global = true)]
     pub quiet: bool,
+
+    /// Leave unchanged functions out of function listings; they still count
+    /// toward totals and similarity
+    #[arg(long, global = true)]
+    pub exclude_unchanged: bool,
+}
+
+impl Cli {
+    /// Whether output is colored, by `--color`, the deprecated `--no-color`,
+    /// the environment and whether stdout is a terminal
+    pub fn color_enabled(&self) -> bool {
+        !self.no_color
+            && self.color.resolve(
+                |name| std::env::var(name).ok(),
+                std::io::stdout().is_terminal(),
+            )
+    }
 }
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum Commands {
     /// Compare files or directories with structural analysis
     Compare {
-        /// First file or directory to compare
-        #[arg(value_name = "SOURCE")]
-        source: PathBuf,
-
-        /// Second file or directory to compare
-        #[arg(value_name = "TARGET")]
-        target: PathBuf,
+        #[command(flatten)]
+        options: CompareOptions,
 
         /// Output format for comparison results
         #[arg(short, long, default_value = "text")]
         format: OutputFormat,
 
-        /// Compare directories recursively
-        #[arg(short, long)]
-        recursive: bool,
+        /// Output file path, written atomically (stdout if not specified)
+        #[arg(short, long, alias = "output-file")]
+        output: Option<PathBuf>,
 
-        /// Ignore whitespace changes in comparison
-        #[arg(long)]
-        ignore_whitespace: bool,
+        /// Decimal places of similarity, confidence and other scores in every output format
+        #[arg(long, value_name = "N")]
+        precision: Option<usize>,
 
-        /// Ignore case differences in comparison
+        /// Write scores as percentages, e.g. `85%`, instead of fractions between 0 and 1
         #[arg(long)]
-        ignore_case: bool,
-
-        /// Minimum similarity threshold for function matching (0.0-1.0)
-        #[arg(long, default_value = "0.7")]
-        threshold: f64,
+        as_percent: bool,
 
-        /// Output file path (stdout if not specified)
-        #[arg(short, long)]
-        output: Option<PathBuf>,
+        /// Show per-phase timing statistics (machine-readable with --format json)
+        #[arg(long, visible_alias = "stats")]
+        show_stats: bool,
 
-        /// Force language detection (override auto-detection)
-        #[arg(short, long)]
-        language: Option<Language>,
+        /// Maximum number of changed functions detailed in markdown output
+        #[arg(long, default_value_t = crate::output::DEFAULT_MARKDOWN_MAX_FUNCTIONS)]
+        max_functions: usize,
 
-        /// Enable refactoring pattern detection
+        /// Replace function, type and variable names with stable pseudonyms in all output
         #[arg(long)]
-        detect_refactoring: bool,
+        redact: bool,
 
-        /// Enable cross-file function tracking
-        #[arg(long)]
-        track_moves: bool,
+        /// Show file paths relative to ROOT, or to SOURCE and TARGET if no ROOT is given
+        #[arg(long, value_name = "ROOT", num_args = 0..=1, require_equals = true)]
+        relative_to: Option<Option<PathBuf>>,
 
-        /// Show function-level similarity scores
-        #[arg(long)]
-        show_similarity: bool,
+        /// How much supporting evidence is shown for each detected refactoring
+        #[arg(long, value_enum, default_value = "standard")]
+        refactoring_detail: RefactoringDetail,
 
-        /// Include AST structure in output
+        /// Fail when functions changed, except for changes accepted in the baseline
         #[arg(long)]
-        include_ast: bool,
+        fail_on_changes: bool,
 
-        /// Maximum depth for AST comparison
-        #[arg(long, default_value = "10")]
-        max_depth: usize,
+        /// Baseline file listing accepted findings by id
+        #[arg(long, value_name = "FILE", default_value = crate::baseline::DEFAULT_BASELINE_PATH)]
+        baseline: PathBuf,
 
-        /// Show performance statistics
+        /// Accept all current findings by rewriting the baseline file
         #[arg(long)]
-        show_stats: bool,
+        update_baseline: bool,
+
+        /// Write the time spent in each phase and file as folded stacks, for flamegraph tools
+        #[cfg(feature = "profile")]
+        #[arg(long, value_name = "OUT.folded")]
+        profile: Option<PathBuf>,
+    },
+
+    /// List breaking changes and refactorings that appeared or disappeared between two JSON reports
+    CompareReports {
+        /// Report of the earlier run, written by `compare --format json` or `--format bin`
+        #[arg(value_name = "OLD")]
+        old: PathBuf,
+
+        /// Report of the later run
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
 
-        /// File patterns to include (glob patterns)
-        #[arg(long, value_delimiter = ',')]
-        include: Vec<String>,
+        /// Output format: text, json or json-compact
+        #[arg(short, long, default_value = "text")]
+        format: OutputFormat,
 
-        /// File patterns to exclude (glob patterns)
-        #[arg(long, value_delimiter = ',')]
-        exclude: Vec<String>,
+        /// Output file path, written atomically (stdout if not specified)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Match functions at several similarity thresholds, to help choose `compare --threshold`
+    Calibrate {
+        /// Original file or directory
+        #[arg(value_name = "SOURCE")]
+        source: PathBuf,
+
+        /// Modified file or directory
+        #[arg(value_name = "TARGET")]
+        target: PathBuf,
+
+        /// Thresholds to try (0.0-1.0), comma-separated
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_values_t = [0.5, 0.6, 0.7, 0.8, 0.9, 0.95]
+        )]
+        thresholds: Vec<f64>,
+
+        /// Compare directories recursively
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// Force language detection (override auto-detection)
+        #[arg(short, long)]
+        language: Option<Language>,
+
+        /// Output format: text, json or json-compact
+        #[arg(short, long, default_value = "text")]
+        format: OutputFormat,
+
+        /// Output file path, written atomically (stdout if not specified)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Browse the changed functions of a comparison in an interactive terminal UI
+    Tui {
+        #[command(flatten)]
+        options: CompareOptions,
     },
 
     /// Analyze a single file or directory for code metrics
@@ -141,8 +222,28 @@ pub enum Commands {
         #[arg(long)]
         signatures: bool,
 
-        /// Output file path
-        #[arg(short, long)]
+        /// Output the dependency graph of the analyzed files instead of the analysis results
+        #[arg(long, value_name = "FORMAT")]
+        graph_format: Option<GraphFormat>,
+
+        /// Report functions copied across the analyzed files instead of the analysis results
+        #[arg(long, conflicts_with = "graph_format")]
+        duplicates: bool,
+
+        /// Lowest token similarity (0.0-1.0) for two functions to count as copies
+        #[arg(long, default_value_t = 0.8, requires = "duplicates")]
+        duplicate_threshold: f64,
+
+        /// Limit the graph to the neighborhood of this function, by name or qualified name
+        #[arg(long, value_name = "FUNCTION", requires = "graph_format")]
+        graph_focus: Option<String>,
+
+        /// Edges followed from --graph-focus, towards callers and callees alike
+        #[arg(long, default_value_t = 2, requires = "graph_focus")]
+        graph_hops: usize,
+
+        /// Output file path, written atomically (stdout if not specified)
+        #[arg(short, long, alias = "output-file")]
         output: Option<PathBuf>,
     },
 
@@ -164,6 +265,163 @@ pub enum Commands {
     },
 }
 
+/// Options deciding what is compared and how, shared by `compare` and `tui`
+#[derive(Args, Debug, Clone)]
+#[command(group(
+    ArgGroup::new("test_classification")
+        .args(["separate_tests", "check_test_updates"])
+        .multiple(true)
+))]
+pub struct CompareOptions {
+    /// First file or directory to compare, `-` to read a file from stdin, or a remote URL
+    #[arg(value_name = "SOURCE")]
+    pub source: PathBuf,
+
+    /// Second file or directory to compare, `-` to read a file from stdin, or a remote URL
+    #[arg(value_name = "TARGET")]
+    pub target: PathBuf,
+
+    /// Compare directories recursively
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Ignore whitespace changes in comparison
+    #[arg(long)]
+    pub ignore_whitespace: bool,
+
+    /// Ignore case differences in comparison
+    #[arg(long)]
+    pub ignore_case: bool,
+
+    /// Minimum similarity threshold for function matching (0.0-1.0)
+    #[arg(long, default_value = "0.7")]
+    pub threshold: f64,
+
+    /// Pair function OLD with NEW whatever their similarity, or never with `OLD!=NEW`
+    #[arg(long = "match-hint", value_name = "OLD=NEW")]
+    pub match_hints: Vec<String>,
+
+    /// Force language detection (override auto-detection)
+    #[arg(short, long)]
+    pub language: Option<Language>,
+
+    /// Language of the input read from stdin (detected from the other input if not given)
+    #[arg(long, value_name = "LANG", conflicts_with = "language")]
+    pub stdin_lang: Option<Language>,
+
+    /// Enable refactoring pattern detection
+    #[arg(long)]
+    pub detect_refactoring: bool,
+
+    /// Enable cross-file function tracking
+    #[arg(long)]
+    pub track_moves: bool,
+
+    /// Show function-level similarity scores
+    #[arg(long)]
+    pub show_similarity: bool,
+
+    /// Include AST structure in output
+    #[arg(long)]
+    pub include_ast: bool,
+
+    /// Maximum depth for AST comparison
+    #[arg(long, default_value = "10")]
+    pub max_depth: usize,
+
+    /// File patterns to include (glob patterns)
+    #[arg(long, value_delimiter = ',')]
+    pub include: Vec<String>,
+
+    /// File patterns to exclude (glob patterns)
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<String>,
+
+    /// Report test and production file changes in separate summaries
+    #[arg(long)]
+    pub separate_tests: bool,
+
+    /// Glob patterns identifying test files (overrides built-in heuristics)
+    #[arg(long, value_delimiter = ',', requires = "test_classification")]
+    pub test_patterns: Vec<String>,
+
+    /// Flag changed production functions whose tests did not change too
+    #[arg(long)]
+    pub check_test_updates: bool,
+
+    /// How test function names are matched to the functions they test
+    #[arg(
+        long,
+        value_enum,
+        default_value = "prefix",
+        requires = "check_test_updates"
+    )]
+    pub test_name_match: TestNameMatch,
+
+    /// Shortest function name that a test name may match by prefix
+    #[arg(long, default_value_t = 4, requires = "check_test_updates")]
+    pub test_name_min_length: usize,
+
+    /// Report lines added, removed and modified, and functions touched
+    #[arg(long)]
+    pub churn: bool,
+
+    /// How generated and vendored files appear in results
+    #[arg(long, value_enum, default_value = "summarize")]
+    pub generated: GeneratedCodePolicy,
+
+    /// Extra generated-code header marker, optionally for one language (`[LANG=]TEXT`)
+    #[arg(long = "generated-marker", value_name = "[LANG=]TEXT")]
+    pub generated_markers: Vec<String>,
+
+    /// TOML file of tree-sitter query rules that extract custom elements
+    #[arg(long, value_name = "FILE")]
+    pub extraction_rules: Option<PathBuf>,
+
+    /// Newline-delimited list of changed paths; only these files are compared,
+    /// the others are parsed only to find callers and moved functions
+    #[arg(long, value_name = "FILE")]
+    pub changed_files: Option<PathBuf>,
+
+    /// Memory-map input files of at least this many bytes instead of reading them
+    #[arg(long, value_name = "BYTES")]
+    pub mmap_threshold: Option<u64>,
+
+    /// Seconds a remote SOURCE or TARGET may take to download or clone
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    pub remote_timeout: u64,
+
+    /// Largest remote file, or checkout of a remote repository, in bytes
+    #[arg(long, value_name = "BYTES", default_value_t = 100 * 1024 * 1024)]
+    pub max_remote_size: u64,
+
+    /// Rank changed functions by change size weighted by how often they are called
+    #[arg(long)]
+    pub rank_by_impact: bool,
+
+    /// Order changes by review risk, grouped into critical, high, medium and low tiers
+    #[arg(long)]
+    pub review_focus: bool,
+
+    /// List the N nearest unchanged functions before and after each changed function
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1"
+    )]
+    pub include_unchanged_context: Option<usize>,
+
+    /// How functions without a name, such as callbacks and closures, are compared
+    #[arg(long, value_enum, default_value = "positional")]
+    pub anonymous_functions: AnonymousFunctions,
+
+    /// Compare function signatures only; changes confined to bodies are ignored
+    #[arg(long)]
+    pub signatures_only: bool,
+}
+
 #[derive(ValueEnum, Clone, Debug)]
 pub enum OutputFormat {
     /// Human-readable text output with colors
@@ -176,10 +434,85 @@ pub enum OutputFormat {
     Xml,
     /// Compact JSON format (single line)
     JsonCompact,
+    /// One flat JSON object per function match, for data pipelines
+    Jsonl,
     /// CSV format for tabular data
     Csv,
     /// Markdown format for documentation
     Markdown,
+    /// The JSON report in a compact, versioned binary encoding
+    Bin,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// GraphViz DOT, rendered with e.g. `dot -Tsvg`
+    Dot,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal, unless `NO_COLOR` or `CLICOLOR_FORCE` say otherwise
+    #[default]
+    Auto,
+    /// Always color, even when piped
+    Always,
+    /// Never color
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to color, given a lookup of environment variables
+    ///
+    /// Under `auto`, a non-empty `NO_COLOR` disables color and a
+    /// `CLICOLOR_FORCE` other than `0` forces it, as https://no-color.org and
+    /// https://bixense.com/clicolors describe.
+    pub fn resolve(self, env: impl Fn(&str) -> Option<String>, stdout_is_terminal: bool) -> bool {
+        let no_color = env("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let force = env("CLICOLOR_FORCE").is_some_and(|value| !value.is_empty() && value != "0");
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => !no_color && (force || stdout_is_terminal),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GeneratedCodePolicy {
+    /// Compare generated files like any other file
+    Include,
+    /// Leave generated files out of the comparison entirely
+    Exclude,
+    /// Count generated files in the statistics but collapse their details
+    Summarize,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RefactoringDetail {
+    /// Pattern type and confidence only
+    Minimal,
+    /// Also the description, affected elements and complexity
+    #[default]
+    Standard,
+    /// Everything, including evidence with its strength, characteristics and quality metrics
+    Full,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestNameMatch {
+    /// `testParse`, `test_parse` and `parse_test` test `parse`
+    Exact,
+    /// Also `test_parse_empty_input` tests `parse`
+    Prefix,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnonymousFunctions {
+    /// Name them `<anon@enclosing:index>` and match them by body and enclosing function
+    Positional,
+    /// Leave them out of the comparison
+    Skip,
 }
 
 #[derive(ValueEnum, Clone, Debug)]
@@ -251,10 +584,12 @@ impl OutputFormat {
         match self {
             OutputFormat::Text => "txt",
             OutputFormat::Json | OutputFormat::JsonCompact => "json",
+            OutputFormat::Jsonl => "jsonl",
             OutputFormat::Html => "html",
             OutputFormat::Xml => "xml",
             OutputFormat::Csv => "csv",
             OutputFormat::Markdown => "md",
+            OutputFormat::Bin => "bin",
         }
     }
 
@@ -264,10 +599,24 @@ impl OutputFormat {
         match self {
             OutputFormat::Text => "text/plain",
             OutputFormat::Json | OutputFormat::JsonCompact => "application/json",
+            OutputFormat::Jsonl => "application/jsonl",
             OutputFormat::Html => "text/html",
             OutputFormat::Xml => "application/xml",
             OutputFormat::Csv => "text/csv",
             OutputFormat::Markdown => "text/markdown",
+            OutputFormat::Bin => "application/octet-stream",
+        }
+    }
+}
+
+impl AnonymousFunctions {
+    /// Convert to the parser's naming policy
+    pub fn to_parser_policy(self) -> smart_diff_parser::AnonymousFunctionPolicy {
+        match self {
+            AnonymousFunctions::Positional => {
+                smart_diff_parser::AnonymousFunctionPolicy::Positional
+            }
+            AnonymousFunctions::Skip => smart_diff_parser::AnonymousFunctionPolicy::Skip,
         }
     }
 }
@@ -298,3 +647,31 @@ impl Language {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_color_choice_follows_terminal_and_environment() {
+        assert!(ColorChoice::Auto.resolve(env(&[]), true));
+        assert!(!ColorChoice::Auto.resolve(env(&[]), false));
+        assert!(!ColorChoice::Auto.resolve(env(&[("NO_COLOR", "1")]), true));
+        assert!(ColorChoice::Auto.resolve(env(&[("NO_COLOR", "")]), true));
+        assert!(ColorChoice::Auto.resolve(env(&[("CLICOLOR_FORCE", "1")]), false));
+        assert!(!ColorChoice::Auto.resolve(env(&[("CLICOLOR_FORCE", "0")]), false));
+        assert!(
+            !ColorChoice::Auto.resolve(env(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")]), true)
+        );
+        assert!(ColorChoice::Always.resolve(env(&[("NO_COLOR", "1")]), false));
+        assert!(!ColorChoice::Never.resolve(env(&[("CLICOLOR_FORCE", "1")]), true));
+    }
+}
@@ -1,13 +1,20 @@
 //! Analyze command implementation
 
-use crate::cli::{Cli, Commands, OutputFormat};
+use crate::atomic_file::write_atomic;
+use crate::cli::{Cli, Commands, GraphFormat, OutputFormat};
 use crate::output::{AnalysisResult, OutputFormatter};
 use anyhow::{bail, Context, Result};
 use colored::*;
 use console::Term;
 use indicatif::{ProgressBar, ProgressStyle};
-use smart_diff_parser::{tree_sitter::TreeSitterParser, Language, LanguageDetector, Parser};
-use smart_diff_semantic::{SemanticAnalyzer, SymbolTable};
+use smart_diff_engine::{CloneDetector, CloneDetectorConfig};
+use smart_diff_parser::{
+    tree_sitter::TreeSitterParser, Function, FunctionSignature, Language, LanguageDetector,
+    NodeType, ParseResult, Parser,
+};
+use smart_diff_semantic::{
+    ComprehensiveDependencyGraphBuilder, DotOptions, SemanticAnalyzer, SymbolTable,
+};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::time::Instant;
@@ -15,7 +22,7 @@ use tokio::fs as async_fs;
 use tracing::{debug, info, warn};
 
 pub async fn run(cli: Cli) -> Result<()> {
-    if let Commands::Analyze {
+    if let Some(Commands::Analyze {
         ref path,
         ref format,
         recursive,
@@ -23,8 +30,13 @@ pub async fn run(cli: Cli) -> Result<()> {
         complexity,
         dependencies,
         signatures,
+        graph_format,
+        duplicates,
+        duplicate_threshold,
+        ref graph_focus,
+        graph_hops,
         ref output,
-    } = cli.command
+    }) = cli.command
     {
         let start_time = Instant::now();
         let term = Term::stdout();
@@ -38,6 +50,9 @@ pub async fn run(cli: Cli) -> Result<()> {
         if !path.exists() {
             bail!("Path does not exist: {}", path.display());
         }
+        if !(0.0..=1.0).contains(&duplicate_threshold) {
+            bail!("Duplicate threshold must be between 0.0 and 1.0");
+        }
 
         // Initialize progress tracking
         let progress = if !cli.quiet {
@@ -78,6 +93,7 @@ pub async fn run(cli: Cli) -> Result<()> {
         let language_detector = LanguageDetector;
         let mut parsers: HashMap<Language, TreeSitterParser> = HashMap::new();
         let mut analysis_results = Vec::new();
+        let mut parsed_files = Vec::new();
 
         // Step 3: Process each file
         let total_files = files.len();
@@ -105,8 +121,11 @@ pub async fn run(cli: Cli) -> Result<()> {
             .await;
 
             match file_result {
-                Ok(result) => {
+                Ok((result, parsed)) => {
                     analysis_results.push(result);
+                    if graph_format.is_some() || duplicates {
+                        parsed_files.push((file_path.display().to_string(), parsed));
+                    }
                 }
                 Err(e) => {
                     warn!("Failed to analyze file {:?}: {}", file_path, e);
@@ -128,8 +147,28 @@ pub async fn run(cli: Cli) -> Result<()> {
             pb.set_position(90);
         }
 
-        let output_content =
-            OutputFormatter::format_analysis_results(&analysis_results, &format, cli.no_color)?;
+        let output_content = match graph_format {
+            Some(GraphFormat::Dot) => {
+                render_dependency_graph(parsed_files, graph_focus.clone(), graph_hops)?
+            }
+            None if duplicates => {
+                let config = CloneDetectorConfig {
+                    min_similarity: duplicate_threshold,
+                    ..Default::default()
+                };
+                let functions: Vec<Function> = parsed_files
+                    .iter()
+                    .flat_map(|(file_path, parsed)| functions_in(&parsed.ast, file_path))
+                    .collect();
+                let clusters = CloneDetector::new(config).detect(&functions);
+                OutputFormatter::format_clone_clusters(&clusters, &format, !cli.color_enabled())?
+            }
+            None => OutputFormatter::format_analysis_results(
+                &analysis_results,
+                &format,
+                !cli.color_enabled(),
+            )?,
+        };
 
         // Step 5: Write output
         if let Some(ref pb) = progress {
@@ -233,7 +272,7 @@ async fn analyze_file(
     include_dependencies: bool,
     include_signatures: bool,
     cli: &Cli,
-) -> Result<AnalysisResult> {
+) -> Result<(AnalysisResult, ParseResult)> {
     let file_start = Instant::now();
 
     // Read file content
@@ -318,7 +357,47 @@ async fn analyze_file(
         );
     }
 
-    Ok(result)
+    Ok((result, ast))
+}
+
+/// Render the dependency graph of the parsed files in DOT
+fn render_dependency_graph(
+    files: Vec<(String, ParseResult)>,
+    focus: Option<String>,
+    hops: usize,
+) -> Result<String> {
+    let mut builder = ComprehensiveDependencyGraphBuilder::with_defaults();
+    builder
+        .build_comprehensive_graph(files)
+        .context("Failed to build dependency graph")?;
+    let graph = builder
+        .get_dependency_graph()
+        .to_dot(&DotOptions { focus, hops })?;
+    Ok(graph)
+}
+
+/// Named functions, methods and constructors in a parsed file
+fn functions_in(ast: &smart_diff_parser::ASTNode, file_path: &str) -> Vec<Function> {
+    let mut nodes = ast.find_by_type(&NodeType::Function);
+    nodes.extend(ast.find_by_type(&NodeType::Method));
+    nodes.extend(ast.find_by_type(&NodeType::Constructor));
+
+    nodes
+        .into_iter()
+        // Declarators are just the signature part of a C/C++ definition
+        .filter(|node| {
+            node.metadata.attributes.get("kind").map(String::as_str) != Some("function_declarator")
+        })
+        .filter_map(|node| {
+            let name = node.metadata.attributes.get("name")?;
+            let signature = FunctionSignature::from_node(name.clone(), node);
+            Some(Function::new(
+                signature,
+                node.clone(),
+                file_path.to_string(),
+            ))
+        })
+        .collect()
 }
 
 /// Extract function signatures from symbol table
@@ -344,15 +423,7 @@ async fn write_analysis_output(
 ) -> Result<()> {
     match output_path {
         Some(path) => {
-            if let Some(parent) = path.parent() {
-                async_fs::create_dir_all(parent).await.with_context(|| {
-                    format!("Failed to create output directory: {}", parent.display())
-                })?;
-            }
-
-            async_fs::write(path, content)
-                .await
-                .with_context(|| format!("Failed to write output to: {}", path.display()))?;
+            write_atomic(path, content.as_bytes())?;
 
             info!("Analysis output written to: {}", path.display());
         }
@@ -426,3 +497,73 @@ fn format_duration(duration: std::time::Duration) -> String {
         format!("{}m {:.1}s", minutes, seconds)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser as _;
+
+    #[tokio::test]
+    async fn test_near_duplicate_functions_form_one_clone_cluster() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [
+            (
+                "users.py",
+                "def validate_user_email(user):\n    email = user.email.strip().lower()\n    if \"@\" not in email:\n        raise ValueError(\"invalid user email\")\n    domain = email.split(\"@\")[1]\n    return domain not in BLOCKED_DOMAINS\n",
+            ),
+            (
+                "admins.py",
+                "def validate_admin_email(admin):\n    email = admin.email.strip().lower()\n    if \"@\" not in email:\n        raise ValueError(\"invalid admin email\")\n    domain = email.split(\"@\")[1]\n    audit(\"admin email checked\")\n    return domain not in BLOCKED_DOMAINS\n",
+            ),
+            (
+                "guests.py",
+                "def validate_guest_email(guest):\n    address = guest.email.strip().lower()\n    if \"@\" not in address:\n        raise ValueError(\"invalid guest email\")\n    domain = address.split(\"@\")[1]\n    return domain not in BLOCKED_DOMAINS and domain != LOCAL\n\ndef greet(guest):\n    return \"Hello, \" + guest.name + \"!\"\n",
+            ),
+        ];
+        for (name, code) in files {
+            std::fs::write(dir.path().join(name), code).unwrap();
+        }
+
+        let output = dir.path().join("duplicates.json");
+        let cli = Cli::parse_from([
+            "smart-diff".as_ref(),
+            "--quiet".as_ref(),
+            "analyze".as_ref(),
+            dir.path().as_os_str(),
+            "--duplicates".as_ref(),
+            "--format".as_ref(),
+            "json".as_ref(),
+            "--output".as_ref(),
+            output.as_os_str(),
+        ]);
+        run(cli).await.unwrap();
+
+        let report: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(output).unwrap()).unwrap();
+        let clusters = report["clusters"].as_array().unwrap();
+        assert_eq!(clusters.len(), 1, "{:#}", report);
+        let cluster = &clusters[0];
+        assert_eq!(cluster["clone_type"], "near_miss");
+        let mut names: Vec<_> = cluster["instances"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|instance| instance["name"].as_str().unwrap())
+            .collect();
+        names.sort_unstable();
+        assert_eq!(
+            names,
+            [
+                "validate_admin_email",
+                "validate_guest_email",
+                "validate_user_email"
+            ]
+        );
+        assert_eq!(
+            cluster["suggested_signature"],
+            "validate_email(admin, value)"
+        );
+        // 19 lines, less the shared function and a call in place of each copy
+        assert_eq!(cluster["estimated_lines_saved"], 9);
+    }
+}
@@ -0,0 +1,277 @@
+//! Calibrate command: how matching responds to the similarity threshold
+//!
+//! Scores every function of the source against every function of the target
+//! once, then matches that similarity matrix at each threshold of the sweep,
+//! so the table of matched, renamed, moved, added and deleted functions costs
+//! one scoring pass however many thresholds are tried. Functions are matched
+//! across the whole tree rather than file by file, which is what lets a
+//! match count as a move.
+
+use crate::atomic_file::write_atomic;
+use crate::cli::{Cli, Commands, OutputFormat};
+use crate::commands::compare::{collect_files, extract_functions_from_ast};
+use anyhow::{bail, Context, Result};
+use colored::*;
+use serde::Serialize;
+use smart_diff_engine::FunctionMatcher;
+use smart_diff_parser::{
+    tree_sitter::TreeSitterParser, Function, Language, LanguageDetector, Parser,
+};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+pub async fn run(cli: Cli) -> Result<()> {
+    if let Some(Commands::Calibrate {
+        ref source,
+        ref target,
+        ref thresholds,
+        recursive,
+        ref language,
+        ref format,
+        ref output,
+    }) = cli.command
+    {
+        if let Some(threshold) = thresholds.iter().find(|t| !(0.0..=1.0).contains(*t)) {
+            bail!("Threshold {} is not between 0.0 and 1.0", threshold);
+        }
+        let language = language
+            .as_ref()
+            .map(|language| {
+                language
+                    .to_parser_language()
+                    .context("Invalid language override")
+            })
+            .transpose()?;
+
+        let source_functions = load_functions(source, recursive, language).await?;
+        let target_functions = load_functions(target, recursive, language).await?;
+        let sweep = sweep(&source_functions, &target_functions, thresholds);
+
+        let content = match format {
+            OutputFormat::Text => to_text(&sweep, &source_functions, &target_functions),
+            OutputFormat::Json => serde_json::to_string_pretty(&sweep)? + "\n",
+            OutputFormat::JsonCompact => serde_json::to_string(&sweep)? + "\n",
+            _ => bail!("calibrate supports text, json and json-compact output"),
+        };
+
+        match output {
+            Some(path) => write_atomic(path, content.as_bytes())
+                .with_context(|| format!("Failed to write output: {}", path.display()))?,
+            None => print!("{}", content),
+        }
+    }
+
+    Ok(())
+}
+
+/// Matching outcome at one threshold
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ThresholdCounts {
+    pub threshold: f64,
+    /// Source functions paired with a target function
+    pub matched: usize,
+    /// Matched functions whose name changed
+    pub renamed: usize,
+    /// Matched functions now in another file
+    pub moved: usize,
+    /// Target functions left unmatched
+    pub added: usize,
+    /// Source functions left unmatched
+    pub deleted: usize,
+}
+
+/// Match the functions at each threshold, in ascending order, scoring them only once
+fn sweep(source: &[Function], target: &[Function], thresholds: &[f64]) -> Vec<ThresholdCounts> {
+    let matrix = FunctionMatcher::new(0.0).calculate_similarity_matrix(source, target);
+
+    let mut thresholds = thresholds.to_vec();
+    thresholds.sort_by(f64::total_cmp);
+    thresholds.dedup();
+
+    thresholds
+        .into_iter()
+        .map(|threshold| {
+            let matches = FunctionMatcher::new(threshold).hungarian_matching(&matrix);
+            let (mut renamed, mut moved) = (0, 0);
+            for &(source_index, target_index) in &matches {
+                let (before, after) = (&source[source_index], &target[target_index]);
+                if before.signature.name != after.signature.name {
+                    renamed += 1;
+                }
+                if before.location.file_path != after.location.file_path {
+                    moved += 1;
+                }
+            }
+            ThresholdCounts {
+                threshold,
+                matched: matches.len(),
+                renamed,
+                moved,
+                added: target.len() - matches.len(),
+                deleted: source.len() - matches.len(),
+            }
+        })
+        .collect()
+}
+
+/// Functions of a file, or of the files in a directory, located by their
+/// path relative to it
+async fn load_functions(
+    path: &Path,
+    recursive: bool,
+    language: Option<Language>,
+) -> Result<Vec<Function>> {
+    let files: Vec<(PathBuf, PathBuf)> = if path.is_dir() {
+        let mut files = HashMap::new();
+        collect_files(path, recursive, &[], &[], &mut files).await?;
+        let mut files: Vec<_> = files.into_iter().collect();
+        files.sort();
+        files
+    } else if path.is_file() {
+        // Two single files are one location, whatever their names
+        vec![(PathBuf::new(), path.to_path_buf())]
+    } else {
+        bail!("Path does not exist: {}", path.display());
+    };
+
+    let mut parsers: HashMap<Language, TreeSitterParser> = HashMap::new();
+    let mut functions = Vec::new();
+    for (relative_path, file) in files {
+        let language = match language {
+            Some(language) => language,
+            None => match LanguageDetector::detect_from_path(&file) {
+                Language::Unknown | Language::Markdown | Language::Dockerfile => continue,
+                language => language,
+            },
+        };
+        let content = std::fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read file: {}", file.display()))?;
+        let parser = match parsers.entry(language) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert(TreeSitterParser::new().context("Failed to create parser")?)
+            }
+        };
+        let parsed = match parser.parse(&content, language) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Skipping {}: {}", file.display(), e);
+                continue;
+            }
+        };
+
+        let relative_path = relative_path.display().to_string();
+        functions.extend(extract_functions_from_ast(&parsed.ast).into_iter().map(
+            |mut function| {
+                function.location.file_path = relative_path.clone();
+                function
+            },
+        ));
+    }
+
+    Ok(functions)
+}
+
+fn to_text(sweep: &[ThresholdCounts], source: &[Function], target: &[Function]) -> String {
+    let mut text = format!(
+        "{}\n{} source functions, {} target functions\n\n",
+        "Threshold Calibration".bold().blue(),
+        source.len(),
+        target.len()
+    );
+    text.push_str(&format!(
+        "{:>9}  {:>7}  {:>7}  {:>5}  {:>5}  {:>7}\n",
+        "threshold", "matched", "renamed", "moved", "added", "deleted"
+    ));
+    for counts in sweep {
+        text.push_str(&format!(
+            "{:>9.2}  {:>7}  {:>7}  {:>5}  {:>5}  {:>7}\n",
+            counts.threshold,
+            counts.matched,
+            counts.renamed,
+            counts.moved,
+            counts.added,
+            counts.deleted
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser as _;
+
+    #[tokio::test]
+    async fn test_matches_never_increase_with_the_threshold() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, new) = (dir.path().join("old"), dir.path().join("new"));
+        std::fs::create_dir_all(&old).unwrap();
+        std::fs::create_dir_all(&new).unwrap();
+
+        // Unchanged, renamed, reworked and moved functions, for a spread of similarities
+        std::fs::write(
+            old.join("shapes.py"),
+            "def area(w, h):\n    return w * h\n\n\
+             def perimeter(w, h):\n    return 2 * (w + h)\n\n\
+             def describe(shape):\n    name = shape.name\n    return name.upper()\n\n\
+             def scale(shape, factor):\n    shape.w = shape.w * factor\n    shape.h = shape.h * factor\n    return shape\n",
+        )
+        .unwrap();
+        std::fs::write(
+            new.join("shapes.py"),
+            "def area(w, h):\n    return w * h\n\n\
+             def circumference(w, h):\n    return 2 * (w + h)\n\n\
+             def describe(shape, verbose=False):\n    if verbose:\n        log(shape)\n    parts = [shape.name, str(shape.w), str(shape.h)]\n    return \" \".join(parts)\n",
+        )
+        .unwrap();
+        std::fs::write(
+            new.join("transform.py"),
+            "def scale(shape, factor):\n    shape.w = shape.w * factor\n    shape.h = shape.h * factor\n    return shape\n",
+        )
+        .unwrap();
+
+        let output = dir.path().join("sweep.json");
+        let cli = Cli::parse_from([
+            "smart-diff".as_ref(),
+            "calibrate".as_ref(),
+            old.as_os_str(),
+            new.as_os_str(),
+            "--thresholds".as_ref(),
+            "0.95,0.3,0.5,0.7,0.9,0.99".as_ref(),
+            "--format".as_ref(),
+            "json".as_ref(),
+            "--output".as_ref(),
+            output.as_os_str(),
+        ]);
+        run(cli).await.unwrap();
+
+        let sweep: Vec<serde_json::Value> =
+            serde_json::from_str(&std::fs::read_to_string(output).unwrap()).unwrap();
+        let thresholds: Vec<f64> = sweep
+            .iter()
+            .map(|row| row["threshold"].as_f64().unwrap())
+            .collect();
+        assert_eq!(thresholds, [0.3, 0.5, 0.7, 0.9, 0.95, 0.99]);
+
+        let count = |row: &serde_json::Value, key: &str| row[key].as_u64().unwrap();
+        for pair in sweep.windows(2) {
+            assert!(
+                count(&pair[1], "matched") <= count(&pair[0], "matched"),
+                "{:#?}",
+                sweep
+            );
+        }
+        for row in &sweep {
+            assert_eq!(count(row, "matched") + count(row, "deleted"), 4);
+            assert_eq!(count(row, "matched") + count(row, "added"), 4);
+        }
+
+        // The loosest threshold pairs everything up, moves included
+        assert_eq!(count(&sweep[0], "matched"), 4);
+        assert_eq!(count(&sweep[0], "moved"), 1);
+        assert!(count(&sweep[5], "matched") < 4, "{:#?}", sweep);
+    }
+}
@@ -1,44 +1,84 @@
 //! Compare command implementation
 
-use crate::cli::{Cli, Commands, OutputFormat};
-use crate::output::{ComparisonResult, ComparisonStats, OutputFormatter};
+use crate::archive::Archive;
+use crate::atomic_file::write_atomic;
+use crate::baseline::{Baseline, Finding};
+use crate::blocklist::{Blocklist, BlocklistConfig};
+use crate::call_sites::CallSites;
+use crate::churn::{Churn, ChurnSummary, FileChurn};
+use crate::cli::{
+    AnonymousFunctions, Cli, Commands, CompareOptions, GeneratedCodePolicy, OutputFormat,
+};
+use crate::generated::GeneratedCodeDetector;
+use crate::glob;
+use crate::impact::CallGraphFiles;
+use crate::language_overrides::LanguageOverrides;
+use crate::mapped_file;
+use crate::output::{
+    ComparisonResult, ComparisonStats, ComplexityDelta, OutputFormatter, ScoreFormat,
+    UnchangedContext,
+};
+use crate::redact::Redactor;
+use crate::relative_paths::PathRelativizer;
+use crate::remote::{FetchedInput, RemoteLimits};
+use crate::review_focus::{order_by_risk, RiskWeights};
+use crate::test_files::{CategorizedStats, FileCategory, TestFileClassifier, TestNameMatcher};
 use anyhow::{bail, Context, Result};
 use colored::*;
 use console::Term;
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::Deserialize;
 use smart_diff_engine::{
-    ChangeClassifier, CrossFileTracker, DiffEngine, RefactoringDetectionConfig,
+    extract_sections, extract_stages, BehaviorAssessment, BehaviorClassifier,
+    BehaviorClassifierConfig, BuildStage, CallSiteTracker, ChangeClassifier, CodeVersion,
+    ComparisonPhase, ComparisonProgress, CrossFileTracker, DeletionRisk, DiffEngine,
+    DocumentSection, LiteralChanges, MatchHints, ProgressCallback, RefactoringDetectionConfig,
     RefactoringDetector, SimilarityScorer,
 };
-use smart_diff_parser::{tree_sitter::TreeSitterParser, Language, LanguageDetector, Parser};
-use smart_diff_semantic::{SemanticAnalyzer, SymbolTable};
-use std::collections::HashMap;
+use smart_diff_parser::{
+    notebook::{self, NotebookSource},
+    tree_sitter::TreeSitterParser,
+    ChangeType, CodeElement, ElementType, ExtractionRule, ExtractionRules, Language,
+    LanguageDetector, NormalizationRule, Normalizer, Parser,
+};
+use smart_diff_semantic::{
+    EnhancedFunctionSignature, FunctionSignatureExtractor, ImportDelta, SymbolTable,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::fs as async_fs;
 use tracing::{debug, info, warn};
 
 pub async fn run(cli: Cli) -> Result<()> {
-    if let Commands::Compare {
-        ref source,
-        ref target,
+    if let Some(Commands::Compare {
+        ref options,
         ref format,
-        recursive,
-        ignore_whitespace,
-        ignore_case,
-        threshold,
         ref output,
-        ref language,
-        detect_refactoring,
-        track_moves,
-        show_similarity,
-        include_ast,
-        max_depth,
+        precision,
+        as_percent,
         show_stats,
-        ref include,
-        ref exclude,
-    } = cli.command
+        max_functions,
+        redact,
+        ref relative_to,
+        refactoring_detail,
+        fail_on_changes,
+        ref baseline,
+        update_baseline,
+        #[cfg(feature = "profile")]
+        ref profile,
+        ..
+    }) = cli.command
     {
+        let CompareOptions {
+            ref source,
+            ref target,
+            separate_tests,
+            ref test_patterns,
+            churn,
+            ..
+        } = *options;
         let start_time = Instant::now();
         let term = Term::stdout();
 
@@ -50,123 +90,100 @@ pub async fn run(cli: Cli) -> Result<()> {
             println!("{}", "=".repeat(50).dimmed());
         }
 
-        // Validate inputs
-        validate_inputs(&source, &target, threshold)?;
-
         // Initialize progress tracking
-        let progress = if !cli.quiet {
-            let pb = ProgressBar::new(100);
-            pb.set_style(
-                ProgressStyle::default_bar()
-                    .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos:>3}/{len:3} {msg}")
-                    .unwrap()
-                    .progress_chars("█▉▊▋▌▍▎▏  ")
-            );
-            Some(pb)
-        } else {
-            None
+        let progress = progress_bar(cli.quiet);
+        let report = |update: &ComparisonProgress| {
+            if let Some(ref pb) = progress {
+                show_progress(pb, update);
+            }
         };
 
-        // Step 1: File discovery and filtering
-        if let Some(ref pb) = progress {
-            pb.set_message("Discovering files...");
-            pb.set_position(10);
-        }
-
-        let file_pairs = discover_files(&source, &target, recursive, &include, &exclude)
-            .await
-            .context("Failed to discover files for comparison")?;
+        let (mut comparison_results, total_stats) =
+            compare_inputs(options, cli.config.as_deref(), cli.quiet, Some(&report)).await?;
 
-        if file_pairs.is_empty() {
-            bail!("No files found to compare. Check your input paths and filters.");
+        if cli.exclude_unchanged {
+            for scores in comparison_results
+                .iter_mut()
+                .filter_map(|result| result.similarity_scores.as_mut())
+            {
+                scores.retain(|_, score| *score < 1.0);
+            }
         }
 
-        info!("Found {} file pairs to compare", file_pairs.len());
-
-        // Step 2: Language detection and parser initialization
+        // Step 4: Generate output
         if let Some(ref pb) = progress {
-            pb.set_message("Detecting languages...");
-            pb.set_position(20);
+            pb.set_message("Generating output...");
         }
 
-        let language_detector = LanguageDetector;
-        let mut parsers: HashMap<Language, TreeSitterParser> = HashMap::new();
-        let mut comparison_results = Vec::new();
-        let mut total_stats = ComparisonStats::default();
-
-        // Step 3: Process each file pair
-        let total_pairs = file_pairs.len();
-        for (index, (source_file, target_file)) in file_pairs.iter().enumerate() {
-            if let Some(ref pb) = progress {
-                pb.set_message(format!(
-                    "Processing {}/{}: {}",
-                    index + 1,
-                    total_pairs,
-                    source_file
-                        .file_name()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                ));
-                pb.set_position(20 + (60 * index as u64) / total_pairs as u64);
-            }
-
-            let file_result = process_file_pair(
-                source_file,
-                target_file,
-                &language,
-                &language_detector,
-                &mut parsers,
-                threshold,
-                ignore_whitespace,
-                ignore_case,
-                detect_refactoring,
-                track_moves,
-                show_similarity,
-                include_ast,
-                max_depth,
-                &cli,
-            )
-            .await;
+        // Findings are keyed by paths relative to the compared root, so they
+        // are collected before paths are rewritten
+        let findings: Vec<Finding> = if fail_on_changes || update_baseline {
+            comparison_results
+                .iter()
+                .flat_map(|result| {
+                    Finding::from_changes(
+                        relative_to_root(&result.target_file, &target),
+                        &result.diff_result.match_result.changes,
+                    )
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
 
-            match file_result {
-                Ok(result) => {
-                    total_stats.merge(&result.stats);
-                    comparison_results.push(result);
-                }
-                Err(e) => {
-                    warn!(
-                        "Failed to process file pair {:?} -> {:?}: {}",
-                        source_file, target_file, e
-                    );
-                    if !cli.quiet {
-                        eprintln!(
-                            "{} Failed to process {}: {}",
-                            "Warning:".yellow().bold(),
-                            source_file.display(),
-                            e
-                        );
-                    }
-                }
+        if let Some(root) = relative_to {
+            let mut relativizer = match root {
+                Some(root) => PathRelativizer::with_root(root),
+                None => PathRelativizer::new(&source, &target),
+            };
+            relativizer.relativize_results(&mut comparison_results);
+            let outside = relativizer.outside_root();
+            if !outside.is_empty() && !cli.quiet {
+                eprintln!(
+                    "{} {} paths are outside the --relative-to root and are shown as absolute paths",
+                    "Note:".yellow().bold(),
+                    outside.len()
+                );
             }
         }
 
-        // Step 4: Generate output
-        if let Some(ref pb) = progress {
-            pb.set_message("Generating output...");
-            pb.set_position(90);
+        // Paths are redacted too, so this comes after they are made relative
+        if redact {
+            Redactor::default().redact_results(&mut comparison_results);
         }
 
-        let output_content = OutputFormatter::format_comparison_results(
-            &comparison_results,
-            &format,
-            show_stats.then_some(&total_stats),
-            cli.no_color,
-        )?;
+        let scores = ScoreFormat {
+            precision,
+            as_percent,
+        };
+        let output_content = match format {
+            OutputFormat::Markdown => OutputFormatter::format_markdown(
+                &comparison_results,
+                show_stats.then_some(&total_stats),
+                max_functions,
+                refactoring_detail,
+                scores,
+            )?
+            .into_bytes(),
+            OutputFormat::Bin => OutputFormatter::format_comparison_binary(
+                &comparison_results,
+                show_stats.then_some(&total_stats),
+                refactoring_detail,
+            )?,
+            _ => OutputFormatter::format_comparison_results(
+                &comparison_results,
+                &format,
+                show_stats.then_some(&total_stats),
+                !cli.color_enabled(),
+                refactoring_detail,
+                scores,
+            )?
+            .into_bytes(),
+        };
 
         // Step 5: Write output
         if let Some(ref pb) = progress {
             pb.set_message("Writing output...");
-            pb.set_position(95);
         }
 
         write_output(&output_content, &output, &format)
@@ -183,23 +200,574 @@ pub async fn run(cli: Cli) -> Result<()> {
             display_summary(&comparison_results, &total_stats, elapsed, &term)?;
         }
 
-        if show_stats {
+        #[cfg(feature = "profile")]
+        if let Some(profile) = profile {
+            let mut folded = crate::profile::FoldedProfile::default();
+            for result in &comparison_results {
+                folded.record_file(
+                    relative_to_root(&result.source_file, &source),
+                    &result.stats,
+                );
+            }
+            folded.record_remainder(elapsed);
+            folded.save(profile)?;
+        }
+
+        if separate_tests {
+            let classifier = TestFileClassifier::with_patterns(test_patterns.clone());
+            let categorized =
+                classifier.categorize_stats(comparison_results.iter().map(|result| {
+                    (
+                        relative_to_root(&result.source_file, &source),
+                        &result.stats,
+                    )
+                }));
+            display_categorized_summary(&categorized, &term)?;
+        }
+
+        // JSON formats already carry the stats and churn reports in the output itself
+        let structured_output = matches!(
+            format,
+            OutputFormat::Json
+                | OutputFormat::JsonCompact
+                | OutputFormat::Jsonl
+                | OutputFormat::Bin
+        );
+
+        if churn && !structured_output {
+            if let Some(summary) = ChurnSummary::of(&comparison_results) {
+                display_churn(&summary, &term)?;
+            }
+        }
+
+        if show_stats && !structured_output {
             display_detailed_stats(&total_stats, &term)?;
         }
 
+        if fail_on_changes || update_baseline {
+            if update_baseline {
+                let accepted = Baseline::from_findings(&findings);
+                accepted.save(baseline)?;
+                if !cli.quiet {
+                    println!(
+                        "Baseline {} updated with {} accepted findings",
+                        baseline.display(),
+                        accepted.len()
+                    );
+                }
+            } else {
+                let new_findings = Baseline::load(baseline)?.new_findings(&findings);
+                if !new_findings.is_empty() {
+                    if !cli.quiet {
+                        for finding in &new_findings {
+                            eprintln!("{} {}", finding.id.dimmed(), finding.description);
+                        }
+                    }
+                    bail!(
+                        "{} changes are not accepted in the baseline {}",
+                        new_findings.len(),
+                        baseline.display()
+                    );
+                }
+            }
+        }
+
         Ok(())
     } else {
         unreachable!("Compare command should have been matched")
     }
 }
 
+/// Rules file passed to `--extraction-rules`
+#[derive(Debug, Deserialize)]
+struct ExtractionRulesFile {
+    #[serde(default, rename = "rule")]
+    rules: Vec<ExtractionRule>,
+}
+
+/// Progress bar for a comparison, drawn on stderr so it stays out of the
+/// results; hidden with `--quiet` or when either stream is not a terminal
+fn progress_bar(quiet: bool) -> Option<ProgressBar> {
+    if quiet || !Term::stdout().is_term() || !Term::stderr().is_term() {
+        return None;
+    }
+
+    let pb = ProgressBar::new(0);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos:>3}/{len:3} files {msg}")
+            .unwrap()
+            .progress_chars("█▉▊▋▌▍▎▏  "),
+    );
+    Some(pb)
+}
+
+fn show_progress(pb: &ProgressBar, update: &ComparisonProgress) {
+    if update.total > 0 {
+        pb.set_length(update.total as u64);
+        pb.set_position(update.completed as u64);
+    }
+    match &update.current_file {
+        Some(file) => pb.set_message(format!("{} {}", update.phase.description(), file)),
+        None => pb.set_message(format!("{}...", update.phase.description())),
+    }
+}
+
+/// Compare the inputs of a `compare` command, without formatting the results
+///
+/// Also used by `tui`, which browses the results instead of printing them.
+/// `config` is the `--config` file, and `quiet` keeps failed file pairs off
+/// stderr. `progress` is told of each phase and of each file pair compared.
+pub(crate) async fn compare_inputs(
+    options: &CompareOptions,
+    config: Option<&Path>,
+    quiet: bool,
+    progress: Option<ProgressCallback<'_>>,
+) -> Result<(Vec<ComparisonResult>, ComparisonStats)> {
+    let CompareOptions {
+        ref source,
+        ref target,
+        recursive,
+        ignore_whitespace,
+        threshold,
+        ref match_hints,
+        ref language,
+        ref stdin_lang,
+        detect_refactoring,
+        track_moves,
+        show_similarity,
+        include_ast,
+        ref include,
+        ref exclude,
+        separate_tests,
+        ref test_patterns,
+        check_test_updates,
+        test_name_match,
+        test_name_min_length,
+        generated,
+        ref generated_markers,
+        ref extraction_rules,
+        ref changed_files,
+        mmap_threshold,
+        rank_by_impact,
+        review_focus,
+        include_unchanged_context,
+        anonymous_functions,
+        signatures_only,
+        churn,
+        remote_timeout,
+        max_remote_size,
+        ..
+    } = *options;
+
+    // Remote inputs are compared from a local copy, removed once done
+    let limits = RemoteLimits {
+        timeout: Duration::from_secs(remote_timeout),
+        max_size: max_remote_size,
+    };
+    let remote_source = FetchedInput::fetch(source, &limits).await?;
+    let remote_target = FetchedInput::fetch(target, &limits).await?;
+    let source = remote_source
+        .as_ref()
+        .map_or(source.as_path(), FetchedInput::path);
+    let target = remote_target
+        .as_ref()
+        .map_or(target.as_path(), FetchedInput::path);
+
+    // Validate inputs
+    validate_inputs(source, target, threshold)?;
+    let stdin_content = if is_stdin(source) || is_stdin(target) {
+        Some(std::io::read_to_string(std::io::stdin()).context("Failed to read stdin")?)
+    } else if stdin_lang.is_some() {
+        bail!("--stdin-lang requires SOURCE or TARGET to be `-`");
+    } else {
+        None
+    };
+    let language = language.clone().or_else(|| stdin_lang.clone());
+    let extraction_rules = extraction_rules
+        .as_deref()
+        .map(load_extraction_rules)
+        .transpose()?;
+    let config_file = match config {
+        Some(path) => Some((path, read_compare_config(path)?)),
+        None => None,
+    };
+    let normalizer = match &config_file {
+        Some((path, file)) => load_normalizer(path, file)?,
+        None => Normalizer::default(),
+    };
+    let behavior_config = config_file
+        .as_ref()
+        .map(|(_, file)| file.behavior)
+        .unwrap_or_default();
+    let language_overrides = match &config_file {
+        Some((path, file)) => LanguageOverrides::new(&file.languages)
+            .with_context(|| format!("Invalid language overrides in {}", path.display()))?,
+        None => LanguageOverrides::default(),
+    };
+    let blocklist = match &config_file {
+        Some((path, file)) => Blocklist::new(&file.blocklist)
+            .with_context(|| format!("Invalid blocklist in {}", path.display()))?,
+        None => Blocklist::new(&BlocklistConfig::default())?,
+    };
+    // Hints from the configuration file and the command line must agree
+    let match_hints = MatchHints::parse(
+        &config_file
+            .iter()
+            .flat_map(|(_, file)| file.match_hints.iter())
+            .chain(match_hints)
+            .collect::<Vec<_>>(),
+    )?;
+    let changed_files = changed_files
+        .as_deref()
+        .map(load_changed_files)
+        .transpose()?;
+
+    let report = |update: ComparisonProgress| {
+        if let Some(progress) = progress {
+            progress(&update);
+        }
+    };
+
+    // Step 1: File discovery and filtering
+    report(ComparisonProgress::phase(ComparisonPhase::Discovering));
+
+    let source_archive = Archive::open(&source)?;
+    let target_archive = Archive::open(&target)?;
+
+    let file_pairs = match (&source_archive, &target_archive) {
+        (Some(source_archive), Some(target_archive)) => {
+            info!(
+                "Comparing {:?} archive against {:?} archive",
+                source_archive.format, target_archive.format
+            );
+            discover_archive_files(source_archive, target_archive, &include, &exclude)
+        }
+        (None, None) => discover_files(&source, &target, recursive, &include, &exclude)
+            .await
+            .context("Failed to discover files for comparison")?,
+        _ => bail!("Both inputs must be archives to compare archive contents"),
+    };
+
+    // Files outside the changed-file list are not compared, but stay
+    // candidates for functions moved out of listed files and for their callers
+    let (file_pairs, candidate_pairs) = match changed_files {
+        Some(ref changed) => {
+            let discovered = file_pairs.len();
+            let (file_pairs, candidate_pairs): (Vec<_>, Vec<_>) =
+                file_pairs
+                    .into_iter()
+                    .partition(|(source_file, target_file)| {
+                        is_listed(changed, source_file, &source)
+                            || is_listed(changed, target_file, &target)
+                    });
+            info!(
+                "Restricted comparison to {} of {} file pairs from the changed-file list",
+                file_pairs.len(),
+                discovered
+            );
+            (file_pairs, candidate_pairs)
+        }
+        None => (file_pairs, Vec::new()),
+    };
+
+    if file_pairs.is_empty() {
+        bail!("No files found to compare. Check your input paths and filters.");
+    }
+
+    info!("Found {} file pairs to compare", file_pairs.len());
+
+    // Step 2: Language detection and parser initialization
+    let mut parsers: HashMap<Language, TreeSitterParser> = HashMap::new();
+    let mut comparison_results = Vec::new();
+    let mut total_stats = ComparisonStats::default();
+    let generated_detector = GeneratedCodeDetector::with_markers(generated_markers);
+    let pair_options = FilePairOptions {
+        source_archive: source_archive.as_ref(),
+        target_archive: target_archive.as_ref(),
+        source_root: source,
+        target_root: target,
+        stdin_content: stdin_content.as_deref(),
+        language_override: &language,
+        threshold,
+        ignore_whitespace,
+        detect_refactoring,
+        track_moves,
+        show_similarity,
+        include_ast,
+        anonymous_functions,
+        signatures_only,
+        generated_policy: generated,
+        generated_detector: &generated_detector,
+        extraction_rules: extraction_rules.as_ref(),
+        normalizer: &normalizer,
+        match_hints: &match_hints,
+        mmap_threshold,
+        unchanged_context: include_unchanged_context.unwrap_or(0),
+        behavior_config: &behavior_config,
+        churn,
+    };
+    let mut excluded_generated = 0;
+    // Review focus weighs changes by fan-in too
+    let mut call_graph = (rank_by_impact || review_focus).then(CallGraphFiles::default);
+    // Calls in both trees, to tell dead code from features among deleted
+    // functions and to find the callers a signature change may break
+    let mut call_sites = CallSites::new();
+
+    // Step 3: Process each file pair
+    let total_pairs = file_pairs.len();
+    for (index, (source_file, target_file)) in file_pairs.iter().enumerate() {
+        report(ComparisonProgress {
+            phase: ComparisonPhase::Comparing,
+            completed: index,
+            total: total_pairs,
+            current_file: Some(relative_to_root(source_file, &source).display().to_string()),
+        });
+
+        // Configured languages come before detection, but not before --language
+        let path_override = language
+            .is_none()
+            .then(|| {
+                language_overrides
+                    .language_of(relative_to_root(source_file, &source))
+                    .or_else(|| {
+                        language_overrides.language_of(relative_to_root(target_file, &target))
+                    })
+            })
+            .flatten();
+        let path_language = match path_override {
+            Some((Language::Unknown, pattern)) => {
+                info!(
+                    "Skipping {}: language overridden to Unknown by '{}'",
+                    source_file.display(),
+                    pattern
+                );
+                continue;
+            }
+            Some((overridden, pattern)) => {
+                info!(
+                    "Parsing {} as {}: language overridden by '{}'",
+                    source_file.display(),
+                    overridden,
+                    pattern
+                );
+                Some(overridden)
+            }
+            None => None,
+        };
+
+        let file_result = process_file_pair(
+            source_file,
+            target_file,
+            path_language,
+            &pair_options,
+            &mut parsers,
+            call_graph.as_mut(),
+            &mut call_sites,
+        )
+        .await;
+
+        match file_result {
+            Ok(Some(result)) => {
+                total_stats.merge(&result.stats);
+                comparison_results.push(result);
+            }
+            Ok(None) => excluded_generated += 1,
+            Err(e) => {
+                warn!(
+                    "Failed to process file pair {:?} -> {:?}: {}",
+                    source_file, target_file, e
+                );
+                if !quiet {
+                    eprintln!(
+                        "{} Failed to process {}: {}",
+                        "Warning:".yellow().bold(),
+                        source_file.display(),
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    report(ComparisonProgress {
+        phase: ComparisonPhase::Comparing,
+        completed: total_pairs,
+        total: total_pairs,
+        current_file: None,
+    });
+    report(ComparisonProgress::phase(ComparisonPhase::Analyzing));
+
+    if excluded_generated > 0 {
+        info!(
+            "Excluded {} generated or vendored files",
+            excluded_generated
+        );
+    }
+
+    // Boilerplate is dropped from the change lists, but stays in the totals
+    let blocklisted: usize = comparison_results
+        .iter_mut()
+        .map(|result| blocklist.filter(result))
+        .sum();
+    if blocklisted > 0 {
+        info!("Left out {} changes to blocklisted functions", blocklisted);
+    }
+
+    // Unlisted files are only parsed once a listed function was deleted or
+    // changed, since only then can its new place or its callers be there
+    let needs_candidates = comparison_results.iter().any(|result| {
+        result
+            .diff_result
+            .match_result
+            .changes
+            .iter()
+            .any(|change| change.change_type != ChangeType::Add)
+    });
+    let candidates = if needs_candidates && !candidate_pairs.is_empty() {
+        parse_candidate_pairs(
+            &candidate_pairs,
+            &pair_options,
+            &mut parsers,
+            &mut call_sites,
+        )
+        .await
+    } else {
+        Vec::new()
+    };
+    if track_moves {
+        total_stats.cross_file_moves +=
+            pair_cross_file_moves(&mut comparison_results, candidates, &source, &target);
+    }
+
+    for result in &mut comparison_results {
+        annotate_deletions(result, call_sites.source_symbols());
+    }
+    call_sites.annotate_signature_changes(&mut comparison_results);
+
+    // Churn is split between production and test code whenever files are classified
+    if separate_tests || check_test_updates {
+        let classifier = TestFileClassifier::with_patterns(test_patterns.clone());
+        for result in &mut comparison_results {
+            let path = relative_to_root(&result.source_file, &source);
+            if let Some(churn) = &mut result.churn {
+                churn.category = Some(classifier.classify(path));
+            }
+        }
+    }
+
+    if check_test_updates {
+        annotate_test_updates(
+            &mut comparison_results,
+            &source,
+            &TestFileClassifier::with_patterns(test_patterns.clone()),
+            &TestNameMatcher::new(test_name_match, test_name_min_length),
+        );
+    }
+
+    if let Some(call_graph) = call_graph {
+        let fan_in = call_graph.fan_in();
+        for result in &mut comparison_results {
+            rank_by_fan_in(result, &fan_in);
+        }
+    }
+
+    if review_focus {
+        let weights = config_file
+            .as_ref()
+            .map(|(_, file)| file.review_focus)
+            .unwrap_or_default();
+        for result in &mut comparison_results {
+            order_by_risk(result, &weights);
+        }
+    }
+
+    // Ids are derived from the real names, so they are assigned before redaction
+    for result in &mut comparison_results {
+        result.finding_ids = Finding::from_changes(
+            relative_to_root(&result.target_file, &target),
+            &result.diff_result.match_result.changes,
+        )
+        .into_iter()
+        .map(|finding| finding.id)
+        .collect();
+    }
+
+    for result in &mut comparison_results {
+        if let Some(remote) = &remote_source {
+            remote.restore_path(&mut result.source_file);
+        }
+        if let Some(remote) = &remote_target {
+            remote.restore_path(&mut result.target_file);
+        }
+    }
+
+    Ok((comparison_results, total_stats))
+}
+
+/// Load and validate the `[[rule]]` entries of an extraction rules file
+fn load_extraction_rules(path: &Path) -> Result<Arc<ExtractionRules>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read extraction rules: {}", path.display()))?;
+    let file: ExtractionRulesFile = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse extraction rules: {}", path.display()))?;
+    let rules = ExtractionRules::new(&file.rules)
+        .with_context(|| format!("Invalid extraction rules in {}", path.display()))?;
+
+    info!(
+        "Loaded {} extraction rules from {}",
+        file.rules.len(),
+        path.display()
+    );
+    Ok(Arc::new(rules))
+}
+
+/// Load a newline-delimited changed-file list, as produced by `git diff --name-only`
+///
+/// Blank lines are ignored and a leading `./` is dropped, so listed paths compare
+/// equal to paths relative to the compared roots.
+fn load_changed_files(path: &Path) -> Result<HashSet<PathBuf>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read changed-file list: {}", path.display()))?;
+    let changed: HashSet<PathBuf> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| PathBuf::from(line.strip_prefix("./").unwrap_or(line)))
+        .collect();
+
+    info!(
+        "Loaded {} changed paths from {}",
+        changed.len(),
+        path.display()
+    );
+    Ok(changed)
+}
+
+/// Whether a compared file appears in the changed-file list, by root-relative or full path
+fn is_listed(changed: &HashSet<PathBuf>, path: &Path, root: &Path) -> bool {
+    changed.contains(relative_to_root(path, root)) || changed.contains(path)
+}
+
+/// Path argument naming stdin as an input
+const STDIN_PATH: &str = "-";
+
+/// Whether an input path names stdin
+fn is_stdin(path: &Path) -> bool {
+    path == Path::new(STDIN_PATH)
+}
+
 /// Validate input parameters
 fn validate_inputs(source: &Path, target: &Path, threshold: f64) -> Result<()> {
-    if !source.exists() {
+    if is_stdin(source) && is_stdin(target) {
+        bail!("Only one of SOURCE and TARGET can be read from stdin");
+    }
+
+    if !is_stdin(source) && !source.exists() {
         bail!("Source path does not exist: {}", source.display());
     }
 
-    if !target.exists() {
+    if !is_stdin(target) && !target.exists() {
         bail!("Target path does not exist: {}", target.display());
     }
 
@@ -207,6 +775,18 @@ fn validate_inputs(source: &Path, target: &Path, threshold: f64) -> Result<()> {
         bail!("Threshold must be between 0.0 and 1.0, got: {}", threshold);
     }
 
+    // Stdin holds a single file, compared against a file given by path
+    if is_stdin(source) || is_stdin(target) {
+        let other = if is_stdin(source) { target } else { source };
+        if !other.is_file() {
+            bail!(
+                "The input compared against stdin must be a file: {}",
+                other.display()
+            );
+        }
+        return Ok(());
+    }
+
     // Check if both are files or both are directories
     let source_is_dir = source.is_dir();
     let target_is_dir = target.is_dir();
@@ -232,7 +812,7 @@ async fn discover_files(
 ) -> Result<Vec<(PathBuf, PathBuf)>> {
     let mut file_pairs = Vec::new();
 
-    if source.is_file() && target.is_file() {
+    if is_stdin(source) || is_stdin(target) || (source.is_file() && target.is_file()) {
         // Single file comparison
         file_pairs.push((source.to_path_buf(), target.to_path_buf()));
     } else if source.is_dir() && target.is_dir() {
@@ -280,8 +860,38 @@ async fn discover_directory_files(
     Ok(file_pairs)
 }
 
+/// Pair archive entries by their relative path inside the archive
+fn discover_archive_files(
+    source: &Archive,
+    target: &Archive,
+    include: &[String],
+    exclude: &[String],
+) -> Vec<(PathBuf, PathBuf)> {
+    let mut file_pairs = Vec::new();
+
+    for rel_path in source.entry_paths() {
+        if !should_include_file(rel_path, include, exclude) {
+            continue;
+        }
+
+        if target.contains(rel_path) {
+            file_pairs.push((source.virtual_path(rel_path), target.virtual_path(rel_path)));
+        } else {
+            debug!("File only exists in source archive: {}", rel_path.display());
+        }
+    }
+
+    for rel_path in target.entry_paths() {
+        if !source.contains(rel_path) {
+            debug!("File only exists in target archive: {}", rel_path.display());
+        }
+    }
+
+    file_pairs
+}
+
 /// Collect files from a directory
-async fn collect_files(
+pub(crate) async fn collect_files(
     dir: &Path,
     recursive: bool,
     include: &[String],
@@ -318,14 +928,12 @@ async fn collect_files(
 
 /// Check if file should be included based on filters
 fn should_include_file(path: &Path, include: &[String], exclude: &[String]) -> bool {
-    let path_str = path.to_string_lossy();
-    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
-
     // Check exclude patterns first
-    for pattern in exclude {
-        if glob_match(pattern, &path_str) || glob_match(pattern, &file_name) {
-            return false;
-        }
+    if exclude
+        .iter()
+        .any(|pattern| glob::matches_path(pattern, path))
+    {
+        return false;
     }
 
     // If no include patterns, include by default
@@ -334,85 +942,438 @@ fn should_include_file(path: &Path, include: &[String], exclude: &[String]) -> b
     }
 
     // Check include patterns
-    for pattern in include {
-        if glob_match(pattern, &path_str) || glob_match(pattern, &file_name) {
-            return true;
-        }
+    include
+        .iter()
+        .any(|pattern| glob::matches_path(pattern, path))
+}
+
+/// The parts of the `--config` file read by `compare`
+#[derive(Debug, Deserialize)]
+struct CompareConfigFile {
+    /// Function pairings to force or forbid, as `--match-hint` takes them
+    #[serde(default)]
+    match_hints: Vec<String>,
+    /// Normalization rules keyed by language, e.g. `java = ["sort-imports"]`
+    #[serde(default)]
+    normalization: HashMap<String, Vec<NormalizationRule>>,
+    /// Weights of the risk signals combined by `--review-focus`
+    #[serde(default)]
+    review_focus: RiskWeights,
+    /// Boilerplate functions left out of the change list
+    #[serde(default)]
+    blocklist: BlocklistConfig,
+    /// How modifications are labeled behavior preserving or changing
+    #[serde(default)]
+    behavior: BehaviorClassifierConfig,
+    /// Languages of the files matching path globs, e.g. `"include/*.h" = "cpp"`
+    #[serde(default)]
+    languages: BTreeMap<String, String>,
+}
+
+fn read_compare_config(path: &Path) -> Result<CompareConfigFile> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read configuration: {}", path.display()))?;
+    let file: CompareConfigFile = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse configuration: {}", path.display()))?;
+    file.review_focus
+        .validate()
+        .with_context(|| format!("Invalid review focus weights in {}", path.display()))?;
+    let min_confidence = file.behavior.min_preserving_confidence;
+    if !(0.0..=1.0).contains(&min_confidence) {
+        bail!(
+            "Behavior min_preserving_confidence must be between 0.0 and 1.0, got {} in {}",
+            min_confidence,
+            path.display()
+        );
     }
+    Ok(file)
+}
 
-    false
+/// Validate the `[normalization]` table of the configuration file
+fn load_normalizer(path: &Path, file: &CompareConfigFile) -> Result<Normalizer> {
+    let normalizer = Normalizer::new(&file.normalization)
+        .with_context(|| format!("Invalid normalization rules in {}", path.display()))?;
+
+    if !normalizer.is_empty() {
+        info!(
+            "Normalizing {} before comparison, as configured in {}",
+            file.normalization
+                .iter()
+                .filter(|(_, rules)| !rules.is_empty())
+                .map(|(language, rules)| format!("{} ({:?})", language, rules))
+                .collect::<Vec<_>>()
+                .join(", "),
+            path.display()
+        );
+    }
+    Ok(normalizer)
 }
 
-/// Simple glob pattern matching
-fn glob_match(pattern: &str, text: &str) -> bool {
-    // Simple implementation - could be enhanced with proper glob library
-    if pattern.contains('*') {
-        let parts: Vec<&str> = pattern.split('*').collect();
-        if parts.len() == 2 {
-            text.starts_with(parts[0]) && text.ends_with(parts[1])
-        } else {
-            // More complex patterns - fallback to simple contains
-            text.contains(&pattern.replace('*', ""))
+/// Name the notebook cells holding the functions of each change
+///
+/// Lines of a notebook's functions count from the start of its extracted
+/// code, which no notebook viewer shows, so each change records the cells of
+/// its functions, from 1, as `source_cell` and `target_cell`.
+fn annotate_notebook_cells(
+    result: &mut ComparisonResult,
+    source: &NotebookSource,
+    target: &NotebookSource,
+) {
+    for change in &mut result.diff_result.match_result.changes {
+        let source_cell = change
+            .source
+            .as_ref()
+            .and_then(|element| source.cell_at(element.start_line))
+            .map(|cell| cell.index);
+        let target_cell = change
+            .target
+            .as_ref()
+            .and_then(|element| target.cell_at(element.start_line))
+            .map(|cell| cell.index);
+
+        let metadata = &mut change.details.metadata;
+        if let Some(cell) = source_cell {
+            metadata.insert("source_cell".to_string(), cell.to_string());
         }
-    } else {
-        text == pattern || text.ends_with(pattern)
+        if let Some(cell) = target_cell {
+            metadata.insert("target_cell".to_string(), cell.to_string());
+        }
+        let location = match (source_cell, target_cell) {
+            (Some(source), Some(target)) if source != target => {
+                format!(" (cell {} → cell {})", source, target)
+            }
+            (_, Some(cell)) | (Some(cell), None) => format!(" (cell {})", cell),
+            (None, None) => continue,
+        };
+        change.details.description.push_str(&location);
     }
 }
 
-/// Process a single file pair for comparison
-async fn process_file_pair(
-    source_file: &Path,
-    target_file: &Path,
-    language_override: &Option<crate::cli::Language>,
-    _language_detector: &LanguageDetector,
-    parsers: &mut HashMap<Language, TreeSitterParser>,
+/// How every file pair of a comparison is read and compared
+struct FilePairOptions<'a> {
+    source_archive: Option<&'a Archive>,
+    target_archive: Option<&'a Archive>,
+    /// Compared roots, which paths of generated files are relative to
+    source_root: &'a Path,
+    target_root: &'a Path,
+    stdin_content: Option<&'a str>,
+    /// Language given with `--language` or `--stdin-lang`
+    language_override: &'a Option<crate::cli::Language>,
     threshold: f64,
     ignore_whitespace: bool,
-    _ignore_case: bool,
     detect_refactoring: bool,
     track_moves: bool,
     show_similarity: bool,
     include_ast: bool,
-    _max_depth: usize,
-    cli: &Cli,
-) -> Result<ComparisonResult> {
-    let file_start = Instant::now();
-
-    // Read file contents
-    let source_content = async_fs::read_to_string(source_file)
-        .await
-        .with_context(|| format!("Failed to read source file: {}", source_file.display()))?;
-
-    let target_content = async_fs::read_to_string(target_file)
-        .await
-        .with_context(|| format!("Failed to read target file: {}", target_file.display()))?;
+    anonymous_functions: AnonymousFunctions,
+    signatures_only: bool,
+    generated_policy: GeneratedCodePolicy,
+    generated_detector: &'a GeneratedCodeDetector,
+    extraction_rules: Option<&'a Arc<ExtractionRules>>,
+    normalizer: &'a Normalizer,
+    match_hints: &'a MatchHints,
+    mmap_threshold: Option<u64>,
+    /// Unchanged functions listed around each changed one
+    unchanged_context: usize,
+    behavior_config: &'a BehaviorClassifierConfig,
+    churn: bool,
+}
 
-    // Detect language
-    let detected_language = if let Some(lang_override) = language_override {
-        lang_override
-            .to_parser_language()
-            .context("Invalid language override")?
-    } else {
-        let detected = LanguageDetector::detect_from_path(source_file);
-        if detected != Language::Unknown {
-            detected
-        } else {
-            LanguageDetector::detect_from_content(&source_content)
+/// Get or create the parser for `language`
+fn parser_for<'p>(
+    parsers: &'p mut HashMap<Language, TreeSitterParser>,
+    language: Language,
+    options: &FilePairOptions<'_>,
+) -> &'p mut TreeSitterParser {
+    parsers.entry(language).or_insert_with(|| {
+        let mut parser = TreeSitterParser::builder()
+            .anonymous_functions(options.anonymous_functions.to_parser_policy())
+            .build()
+            .expect("Failed to create parser");
+        if let Some(rules) = options.extraction_rules {
+            parser.set_extraction_rules(Arc::clone(rules));
         }
-    };
+        parser
+    })
+}
 
-    debug!(
-        "Detected language: {:?} for file: {}",
-        detected_language,
-        source_file.display()
-    );
+/// Parse the file pairs left out by the changed-file list, returning the
+/// functions only their target version has
+///
+/// Their calls are recorded in `call_sites`; nothing else is analyzed.
+async fn parse_candidate_pairs(
+    file_pairs: &[(PathBuf, PathBuf)],
+    options: &FilePairOptions<'_>,
+    parsers: &mut HashMap<Language, TreeSitterParser>,
+    call_sites: &mut CallSites,
+) -> Vec<CodeElement> {
+    let mut added = Vec::new();
+    for (source_file, target_file) in file_pairs {
+        let language = match options.language_override {
+            Some(language) => language.to_parser_language(),
+            None => Some(LanguageDetector::detect_from_path(source_file)),
+        };
+        let Some(language) = language.filter(|language| *language != Language::Unknown) else {
+            continue;
+        };
+
+        let parsed = async {
+            let source_content = read_input_file(
+                source_file,
+                options.source_archive,
+                None,
+                options.mmap_threshold,
+            )
+            .await?;
+            let target_content = read_input_file(
+                target_file,
+                options.target_archive,
+                None,
+                options.mmap_threshold,
+            )
+            .await?;
+            let parser = parser_for(parsers, language, options);
+            anyhow::Ok((
+                parser.parse(&source_content, language)?,
+                parser.parse(&target_content, language)?,
+            ))
+        }
+        .await;
+        let (source_ast, target_ast) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                debug!("Skipping move candidate {}: {}", target_file.display(), e);
+                continue;
+            }
+        };
+
+        call_sites.add(source_file, &source_ast, target_file, &target_ast);
+        let existing: HashSet<String> = extract_functions_from_ast(&source_ast.ast)
+            .into_iter()
+            .map(|function| function.signature.name)
+            .collect();
+        added.extend(
+            extract_functions_from_ast(&target_ast.ast)
+                .iter()
+                .filter(|function| !existing.contains(&function.signature.name))
+                .map(|function| CodeElement {
+                    file_path: target_file.display().to_string(),
+                    ..CodeElement::from_function(function)
+                }),
+        );
+    }
+    added
+}
 
-    // Get or create parser for this language
-    let parser = parsers
-        .entry(detected_language)
-        .or_insert_with(|| TreeSitterParser::new().expect("Failed to create parser"));
+/// Turn deleted functions added to another file into cross-file moves,
+/// returning how many were found
+///
+/// A deleted function pairs with the first function of the same name and kind
+/// added to a different file, by another compared pair, whose addition is then
+/// dropped, or among the `candidates` from unlisted files.
+fn pair_cross_file_moves(
+    results: &mut [ComparisonResult],
+    candidates: Vec<CodeElement>,
+    source_root: &Path,
+    target_root: &Path,
+) -> usize {
+    let is_function = |element: &&CodeElement| {
+        matches!(
+            element.element_type,
+            ElementType::Function | ElementType::Method
+        )
+    };
+    // Each added function with its file, and the pair and change adding it
+    let mut added: Vec<(Option<(usize, usize)>, PathBuf, CodeElement)> = Vec::new();
+    for (index, result) in results.iter().enumerate() {
+        for (position, change) in result.diff_result.match_result.changes.iter().enumerate() {
+            if let Some(target) = change
+                .target
+                .as_ref()
+                .filter(is_function)
+                .filter(|_| change.change_type == ChangeType::Add)
+            {
+                added.push((
+                    Some((index, position)),
+                    result.target_file.clone(),
+                    target.clone(),
+                ));
+            }
+        }
+    }
+    added.extend(
+        candidates
+            .into_iter()
+            .map(|element| (None, PathBuf::from(&element.file_path), element)),
+    );
+
+    let mut paired = vec![false; added.len()];
+    let mut dropped = Vec::new();
+    let mut moves = 0;
+    for result in results.iter_mut() {
+        for change in &mut result.diff_result.match_result.changes {
+            let Some(source) = change
+                .source
+                .as_ref()
+                .filter(is_function)
+                .filter(|_| change.change_type == ChangeType::Delete)
+            else {
+                continue;
+            };
+            let Some(index) = (0..added.len()).find(|&index| {
+                let (_, path, element) = &added[index];
+                !paired[index]
+                    && *path != result.target_file
+                    && element.name == source.name
+                    && element.element_type == source.element_type
+            }) else {
+                continue;
+            };
+            paired[index] = true;
+
+            let (addition, path, target) = &added[index];
+            dropped.extend(*addition);
+            change.details.description = format!(
+                "Moved '{}' from {}:{} to {}:{}",
+                source.name,
+                relative_to_root(&result.source_file, source_root).display(),
+                source.start_line,
+                relative_to_root(path, target_root).display(),
+                target.start_line
+            );
+            change.change_type = ChangeType::CrossFileMove;
+            change.target = Some(target.clone());
+            result.stats.cross_file_moves += 1;
+            moves += 1;
+        }
+    }
+
+    dropped.sort_unstable();
+    for (index, position) in dropped.into_iter().rev() {
+        results[index]
+            .diff_result
+            .match_result
+            .changes
+            .remove(position);
+    }
+    moves
+}
+
+/// Process a single file pair for comparison
+///
+/// `path_language` is the language configured for the pair's paths, if any.
+async fn process_file_pair(
+    source_file: &Path,
+    target_file: &Path,
+    path_language: Option<Language>,
+    options: &FilePairOptions<'_>,
+    parsers: &mut HashMap<Language, TreeSitterParser>,
+    call_graph: Option<&mut CallGraphFiles>,
+    call_sites: &mut CallSites,
+) -> Result<Option<ComparisonResult>> {
+    let FilePairOptions {
+        source_archive,
+        target_archive,
+        source_root,
+        target_root,
+        stdin_content,
+        language_override,
+        threshold,
+        ignore_whitespace,
+        detect_refactoring,
+        track_moves,
+        show_similarity,
+        include_ast,
+        anonymous_functions: _,
+        signatures_only,
+        generated_policy,
+        generated_detector,
+        extraction_rules: _,
+        normalizer,
+        match_hints,
+        mmap_threshold,
+        unchanged_context,
+        behavior_config,
+        churn,
+    } = *options;
+    let file_start = Instant::now();
+
+    // Read file contents
+    let source_content =
+        read_input_file(source_file, source_archive, stdin_content, mmap_threshold)
+            .await
+            .with_context(|| format!("Failed to read source file: {}", source_file.display()))?;
+
+    let target_content =
+        read_input_file(target_file, target_archive, stdin_content, mmap_threshold)
+            .await
+            .with_context(|| format!("Failed to read target file: {}", target_file.display()))?;
+
+    // Notebooks are compared by the code of their cells, without outputs or metadata
+    let notebooks = if notebook::is_notebook(source_file) || notebook::is_notebook(target_file) {
+        let extract = |content: &str, path: &Path| {
+            notebook::extract_code(content)
+                .with_context(|| format!("Failed to read notebook: {}", path.display()))
+        };
+        Some((
+            extract(&source_content, source_file)?,
+            extract(&target_content, target_file)?,
+        ))
+    } else {
+        None
+    };
+    let (source_content, target_content) = match &notebooks {
+        Some((source, target)) => (source.source.clone(), target.source.clone()),
+        None => (source_content, target_content),
+    };
+
+    // Generated and vendored files are matched by their path inside the compared tree
+    let generated = generated_policy != GeneratedCodePolicy::Include
+        && (generated_detector
+            .is_generated(relative_to_root(source_file, source_root), &source_content)
+            || generated_detector
+                .is_generated(relative_to_root(target_file, target_root), &target_content));
+    if generated && generated_policy == GeneratedCodePolicy::Exclude {
+        debug!("Skipping generated file: {}", source_file.display());
+        return Ok(None);
+    }
+
+    // Detect language
+    let detected_language = if let Some(lang_override) = language_override {
+        lang_override
+            .to_parser_language()
+            .context("Invalid language override")?
+    } else if let Some(language) = path_language {
+        language
+    } else if let Some((source, _)) = &notebooks {
+        source.language
+    } else {
+        // Stdin has no path, so its language comes from the other input's
+        let detected = match LanguageDetector::detect_from_path(source_file) {
+            Language::Unknown => LanguageDetector::detect_from_path(target_file),
+            detected => detected,
+        };
+        if detected != Language::Unknown {
+            detected
+        } else {
+            LanguageDetector::detect_from_content(&source_content)
+        }
+    };
+
+    debug!(
+        "Detected language: {:?} for file: {}",
+        detected_language,
+        source_file.display()
+    );
+
+    // Configured normalization rewrites both sides before anything compares them
+    let source_content = normalizer.normalize(detected_language, &source_content);
+    let target_content = normalizer.normalize(detected_language, &target_content);
+
+    let parser = parser_for(parsers, detected_language, options);
 
     // Parse source and target files
+    let parsing_start = Instant::now();
     let source_ast = parser
         .parse(&source_content, detected_language)
         .with_context(|| format!("Failed to parse source file: {}", source_file.display()))?;
@@ -421,22 +1382,42 @@ async fn process_file_pair(
         .parse(&target_content, detected_language)
         .with_context(|| format!("Failed to parse target file: {}", target_file.display()))?;
 
-    // Perform semantic analysis
-    let mut semantic_analyzer = SemanticAnalyzer::new();
+    let parsing_time = parsing_start.elapsed();
+
+    // Perform semantic analysis: signatures, bodies and symbols of both files,
+    // for the refactorings and similarities the matched changes do not show
+    let semantic_start = Instant::now();
+    let versions = if detect_refactoring || show_similarity {
+        CodeVersion::from_file(&source_file.display().to_string(), &source_ast)
+            .and_then(|source| {
+                CodeVersion::from_file(&target_file.display().to_string(), &target_ast)
+                    .map(|target| (source, target))
+            })
+            .map_err(|e| warn!("Failed to analyze {}: {}", source_file.display(), e))
+            .ok()
+    } else {
+        None
+    };
 
-    let source_symbols = semantic_analyzer
-        .analyze(&source_ast)
-        .with_context(|| format!("Failed to analyze source file: {}", source_file.display()))?;
+    call_sites.add(source_file, &source_ast, target_file, &target_ast);
 
-    let target_symbols = semantic_analyzer
-        .analyze(&target_ast)
-        .with_context(|| format!("Failed to analyze target file: {}", target_file.display()))?;
+    let semantic_analysis_time = semantic_start.elapsed();
+
+    let import_delta =
+        ImportDelta::from_parse_results(&source_ast, &target_ast).unwrap_or_else(|e| {
+            warn!(
+                "Failed to extract imports from {}: {}",
+                source_file.display(),
+                e
+            );
+            ImportDelta::default()
+        });
 
     // Initialize diff engine components
-    let diff_engine = DiffEngine::new();
+    let diff_engine = DiffEngine::new().with_match_hints(match_hints.clone());
 
     // Configure similarity scorer
-    let similarity_scorer = SimilarityScorer::new(
+    let mut similarity_scorer = SimilarityScorer::new(
         detected_language,
         smart_diff_engine::SimilarityScoringConfig::default(),
     );
@@ -452,7 +1433,11 @@ async fn process_file_pair(
     let refactoring_detector = if detect_refactoring {
         let mut config = RefactoringDetectionConfig::default();
         config.min_confidence_threshold = threshold;
-        Some(RefactoringDetector::with_config(detected_language, config))
+        let detector = RefactoringDetector::with_config(detected_language, config);
+        Some(match &versions {
+            Some((source, target)) => detector.with_versions(source.clone(), target.clone()),
+            None => detector,
+        })
     } else {
         None
     };
@@ -470,13 +1455,164 @@ async fn process_file_pair(
     // Perform comparison
     let comparison_start = Instant::now();
 
-    // Extract functions from AST for comparison
-    let source_functions = extract_functions_from_ast(&source_ast.ast);
-    let target_functions = extract_functions_from_ast(&target_ast.ast);
+    // Element source keyed by code element id, for per-function diffs in reports
+    let (
+        diff_result,
+        source_function_texts,
+        target_function_texts,
+        complexity_deltas,
+        literal_changes,
+        behavior,
+        unchanged_context,
+    ) = if detected_language == Language::Markdown {
+        // Documents are compared section by section
+        let source_sections = extract_sections(&source_ast.ast, &source_content);
+        let target_sections = extract_sections(&target_ast.ast, &target_content);
+        let diff_result = diff_engine
+            .compare_sections(
+                &source_sections,
+                &target_sections,
+                &source_file.display().to_string(),
+                &target_file.display().to_string(),
+            )
+            .context("Failed to perform section comparison")?;
 
-    let diff_result = diff_engine
-        .compare_functions(&source_functions, &target_functions)
-        .context("Failed to perform structural comparison")?;
+        let section_texts = |sections: &[DocumentSection]| -> HashMap<String, String> {
+            sections
+                .iter()
+                .map(|section| (section.id.clone(), section.content.clone()))
+                .collect()
+        };
+        (
+            diff_result,
+            section_texts(&source_sections),
+            section_texts(&target_sections),
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+        )
+    } else if detected_language == Language::Dockerfile {
+        // Dockerfiles are compared stage by stage and instruction by instruction
+        let source_stages = extract_stages(&source_ast.ast);
+        let target_stages = extract_stages(&target_ast.ast);
+        let diff_result = diff_engine
+            .compare_dockerfiles(
+                &source_stages,
+                &target_stages,
+                &source_file.display().to_string(),
+                &target_file.display().to_string(),
+            )
+            .context("Failed to perform Dockerfile comparison")?;
+
+        let instruction_texts = |stages: &[BuildStage]| -> HashMap<String, String> {
+            stages
+                .iter()
+                .flat_map(|stage| {
+                    std::iter::once((stage.name.clone(), stage.content())).chain(
+                        stage
+                            .from
+                            .iter()
+                            .chain(&stage.instructions)
+                            .map(|instruction| (instruction.id.clone(), instruction.text())),
+                    )
+                })
+                .collect()
+        };
+        (
+            diff_result,
+            instruction_texts(&source_stages),
+            instruction_texts(&target_stages),
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+        )
+    } else if signatures_only {
+        // Only declarations are compared, so there is no function text to diff
+        let signatures = |path: &Path, ast| -> Result<Vec<EnhancedFunctionSignature>> {
+            Ok(FunctionSignatureExtractor::with_defaults(detected_language)
+                .extract_signatures(&path.display().to_string(), ast)
+                .with_context(|| format!("Failed to extract signatures: {}", path.display()))?
+                .signatures)
+        };
+        let diff_result = diff_engine
+            .compare_signatures(
+                &signatures(source_file, &source_ast)?,
+                &signatures(target_file, &target_ast)?,
+                &source_file.display().to_string(),
+                &target_file.display().to_string(),
+            )
+            .context("Failed to perform signature comparison")?;
+        (
+            diff_result,
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+        )
+    } else {
+        // Extract functions from AST for comparison, plus elements captured by extraction rules
+        let mut source_functions = extract_functions_from_ast(&source_ast.ast);
+        source_functions.extend(source_ast.custom_elements.iter().cloned());
+        let mut target_functions = extract_functions_from_ast(&target_ast.ast);
+        target_functions.extend(target_ast.custom_elements.iter().cloned());
+
+        let diff_result = diff_engine
+            .compare_functions(&source_functions, &target_functions)
+            .context("Failed to perform structural comparison")?;
+
+        let function_texts =
+            |functions: &[smart_diff_parser::Function]| -> HashMap<String, String> {
+                functions
+                    .iter()
+                    .map(|f| {
+                        (
+                            smart_diff_parser::CodeElement::from_function(f).id,
+                            f.body.metadata.original_text.clone(),
+                        )
+                    })
+                    .collect()
+            };
+        let complexity_deltas = complexity_deltas(
+            detected_language,
+            &source_functions,
+            &target_functions,
+            &diff_result.match_result.changes,
+        );
+        let literal_changes = literal_changes(
+            &source_functions,
+            &target_functions,
+            &diff_result.match_result.changes,
+        );
+        let behavior = behavior_assessments(
+            &BehaviorClassifier::new(detected_language, *behavior_config),
+            &source_functions,
+            &target_functions,
+            &diff_result.match_result.changes,
+        );
+        let unchanged_context = if unchanged_context > 0 {
+            neighboring_unchanged_functions(
+                &source_functions,
+                &target_functions,
+                &diff_result.match_result.changes,
+                unchanged_context,
+            )
+        } else {
+            HashMap::new()
+        };
+        (
+            diff_result,
+            function_texts(&source_functions),
+            function_texts(&target_functions),
+            complexity_deltas,
+            literal_changes,
+            behavior,
+            unchanged_context,
+        )
+    };
 
     let comparison_time = comparison_start.elapsed();
 
@@ -509,19 +1645,25 @@ async fn process_file_pair(
     }
 
     // Detect refactoring patterns if enabled
+    let refactoring_start = Instant::now();
     let refactoring_patterns = if let Some(ref detector) = refactoring_detector {
         detector.detect_patterns(&diff_result.match_result.changes)
     } else {
         Vec::new()
     };
+    let refactoring_detection_time = refactoring_start.elapsed();
 
     // Calculate similarity scores if requested
     let similarity_scores = if show_similarity {
-        Some(calculate_function_similarities(
-            &source_symbols.symbol_table,
-            &target_symbols.symbol_table,
-            &similarity_scorer,
-        )?)
+        Some(match &versions {
+            Some((source, target)) => calculate_function_similarities(
+                source,
+                target,
+                &diff_result.match_result.changes,
+                &mut similarity_scorer,
+            )?,
+            None => HashMap::new(),
+        })
     } else {
         None
     };
@@ -537,19 +1679,30 @@ async fn process_file_pair(
     // Build comparison result
     let stats = ComparisonStats {
         files_compared: 1,
-        functions_compared: 0, // Would need to count functions from symbol table
+        functions_compared: diff_result.statistics.functions_compared,
         changes_detected: diff_result.match_result.changes.len(),
         refactoring_patterns: refactoring_patterns.len(),
         cross_file_moves: cross_file_moves.len(),
-        parsing_time: file_start.elapsed() - comparison_time,
+        parsing_time,
+        semantic_analysis_time,
         comparison_time,
+        refactoring_detection_time,
         total_time: file_start.elapsed(),
         source_lines: source_content.lines().count(),
         target_lines: target_content.lines().count(),
         similarity_score: diff_result.match_result.similarity,
     };
 
-    let result = ComparisonResult {
+    let churn = churn.then(|| FileChurn {
+        churn: Churn::between(
+            &source_content,
+            &target_content,
+            diff_result.match_result.changes.len(),
+        ),
+        category: None,
+    });
+
+    let mut result = ComparisonResult {
         source_file: source_file.to_path_buf(),
         target_file: target_file.to_path_buf(),
         language: detected_language,
@@ -559,91 +1712,253 @@ async fn process_file_pair(
         similarity_scores,
         cross_file_moves,
         stats,
-        source_ast: if include_ast {
-            Some(source_ast.ast)
-        } else {
-            None
-        },
-        target_ast: if include_ast {
-            Some(target_ast.ast)
-        } else {
-            None
-        },
+        source_function_texts,
+        target_function_texts,
+        import_delta,
+        generated: false,
+        fan_in: HashMap::new(),
+        complexity_deltas,
+        literal_changes,
+        behavior,
+        unchanged_context,
+        change_risks: Vec::new(),
+        finding_ids: Vec::new(),
+        churn,
+        source_ast: include_ast.then(|| source_ast.ast.clone()),
+        target_ast: include_ast.then(|| target_ast.ast.clone()),
     };
 
-    if cli.verbose {
-        info!(
-            "Processed {} -> {} in {:?}",
-            source_file.display(),
-            target_file.display(),
-            file_start.elapsed()
-        );
+    if let Some(call_graph) = call_graph {
+        call_graph.add(source_file, source_ast, target_file, target_ast);
     }
 
-    Ok(result)
-}
+    if let Some((source, target)) = &notebooks {
+        annotate_notebook_cells(&mut result, source, target);
+    }
 
-/// Calculate function-level similarity scores
-fn calculate_function_similarities(
-    _source_symbols: &SymbolTable,
-    _target_symbols: &SymbolTable,
-    _similarity_scorer: &SimilarityScorer,
-) -> Result<HashMap<String, f64>> {
-    let _similarities: HashMap<String, f64> = HashMap::new();
+    if generated {
+        result.collapse_generated();
+    }
 
-    // Would need to iterate over functions from symbol table - simplified for now
-    let similarities = HashMap::new();
-    /*
-    for (source_name, source_func) in &source_symbols.functions {
-        let mut best_similarity = 0.0;
-        let mut best_match = String::new();
+    info!(
+        "Processed {} -> {} in {:?}",
+        source_file.display(),
+        target_file.display(),
+        file_start.elapsed()
+    );
+
+    Ok(Some(result))
+}
+
+/// Mark each deleted function as dead-code cleanup or a feature removal
+///
+/// The risk and the functions that called it in the old tree are added to the
+/// change's metadata and description.
+fn annotate_deletions(result: &mut ComparisonResult, source_calls: &SymbolTable) {
+    let tracker = CallSiteTracker::with_defaults();
+    for change in &mut result.diff_result.match_result.changes {
+        let name = match (&change.change_type, &change.source) {
+            (ChangeType::Delete, Some(element))
+                if matches!(
+                    element.element_type,
+                    ElementType::Function | ElementType::Method
+                ) =>
+            {
+                element.name.clone()
+            }
+            _ => continue,
+        };
+        let Some(impact) = tracker
+            .analyze_deletions(source_calls, std::slice::from_ref(&name))
+            .pop()
+        else {
+            continue;
+        };
 
-        for (target_name, target_func) in &target_symbols.functions {
-            // This would need to be implemented in SimilarityScorer
-            // let similarity = similarity_scorer.calculate_function_similarity(source_func, target_func)?;
+        let mut callers: Vec<&str> = impact
+            .live_call_sites
+            .iter()
+            .map(|site| site.caller_function.as_deref().unwrap_or("<top level>"))
+            .collect();
+        callers.sort_unstable();
+        callers.dedup();
+
+        change.details.metadata.insert(
+            "deletion_risk".to_string(),
+            impact.risk.as_str().to_string(),
+        );
+        match impact.risk {
+            DeletionRisk::DeadCode => change.details.description.push_str(" (dead code)"),
+            DeletionRisk::FeatureRemoval => {
+                let callers = callers.join(", ");
+                change
+                    .details
+                    .description
+                    .push_str(&format!(" (feature removal: called from {})", callers));
+                change
+                    .details
+                    .metadata
+                    .insert("callers".to_string(), callers);
+            }
+        }
+    }
+}
 
-            // Placeholder implementation
-            let name_similarity = if source_name == target_name { 1.0 } else { 0.0 };
+/// Record on each changed production function whether a test named after it changed too
+///
+/// Deletions and moves are skipped, as they need no new tests. The outcome is
+/// the change's `tests_updated` metadata, with the changed tests in
+/// `updated_tests`.
+fn annotate_test_updates(
+    results: &mut [ComparisonResult],
+    source_root: &Path,
+    classifier: &TestFileClassifier,
+    matcher: &TestNameMatcher,
+) {
+    let is_test = |result: &ComparisonResult| {
+        classifier.classify(relative_to_root(&result.source_file, source_root))
+            == FileCategory::Test
+    };
+    let changed_tests: Vec<String> = results
+        .iter()
+        .filter(|result| is_test(result))
+        .flat_map(|result| &result.diff_result.match_result.changes)
+        .filter_map(|change| change.target.as_ref().or(change.source.as_ref()))
+        .map(|element| element.name.clone())
+        .collect();
+
+    for result in results.iter_mut() {
+        if is_test(result) {
+            continue;
+        }
+        for change in &mut result.diff_result.match_result.changes {
+            if matches!(
+                change.change_type,
+                ChangeType::Delete | ChangeType::Move | ChangeType::CrossFileMove
+            ) {
+                continue;
+            }
+            let name = match change.target.as_ref().or(change.source.as_ref()) {
+                Some(element)
+                    if matches!(
+                        element.element_type,
+                        ElementType::Function | ElementType::Method
+                    ) =>
+                {
+                    element.name.clone()
+                }
+                _ => continue,
+            };
 
-            if name_similarity > best_similarity {
-                best_similarity = name_similarity;
-                best_match = target_name.clone();
+            let updated_tests: Vec<&str> = changed_tests
+                .iter()
+                .filter(|test| matcher.matches(test, &name))
+                .map(String::as_str)
+                .collect();
+            let tests_updated = !updated_tests.is_empty();
+            change
+                .details
+                .metadata
+                .insert("tests_updated".to_string(), tests_updated.to_string());
+            if tests_updated {
+                change
+                    .details
+                    .metadata
+                    .insert("updated_tests".to_string(), updated_tests.join(", "));
+            } else {
+                change.details.description.push_str(" (tests not updated)");
             }
         }
+    }
+}
 
-        if best_similarity > 0.0 {
-            similarities.insert(format!("{} -> {}", source_name, best_match), best_similarity);
+/// Record the fan-in of a result's changed functions and put the most impactful changes first
+fn rank_by_fan_in(result: &mut ComparisonResult, fan_in: &HashMap<String, usize>) {
+    for change in &result.diff_result.match_result.changes {
+        for element in change.source.iter().chain(change.target.iter()) {
+            if let Some(&calls) = fan_in.get(&element.name) {
+                result.fan_in.insert(element.name.clone(), calls);
+            }
         }
     }
-    */
+
+    let mut changes = std::mem::take(&mut result.diff_result.match_result.changes);
+    changes.sort_by(|a, b| {
+        OutputFormatter::ranked_change_magnitude(result, b)
+            .partial_cmp(&OutputFormatter::ranked_change_magnitude(result, a))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    result.diff_result.match_result.changes = changes;
+}
+
+/// Read a file from disk, mapped when large if asked to, from the archive it
+/// was discovered in, or from stdin
+async fn read_input_file(
+    path: &Path,
+    archive: Option<&Archive>,
+    stdin_content: Option<&str>,
+    mmap_threshold: Option<u64>,
+) -> Result<String> {
+    match (archive, stdin_content) {
+        (Some(archive), _) => archive.read_to_string(path),
+        (None, Some(content)) if is_stdin(path) => Ok(content.to_string()),
+        (None, _) if mmap_threshold.is_some() => mapped_file::read_to_string(path, mmap_threshold),
+        (None, _) => Ok(async_fs::read_to_string(path).await?),
+    }
+}
+
+/// Calculate function-level similarity scores
+///
+/// Functions are paired by name and by the renames and moves among `changes`.
+/// Locals are told apart from calls and types with the symbol tables of both
+/// versions, so consistently renamed locals do not lower a score.
+fn calculate_function_similarities(
+    source: &CodeVersion,
+    target: &CodeVersion,
+    changes: &[smart_diff_parser::Change],
+    similarity_scorer: &mut SimilarityScorer,
+) -> Result<HashMap<String, f64>> {
+    similarity_scorer.set_symbol_tables(source.symbols.clone(), target.symbols.clone());
+
+    let mut similarities = HashMap::new();
+    for (source_name, target_name) in source.function_pairs(target, changes) {
+        let (Some((source_signature, source_ast)), Some((target_signature, target_ast))) = (
+            source.function_with_signature(source_name),
+            target.function_with_signature(target_name),
+        ) else {
+            continue;
+        };
+
+        let score = similarity_scorer.calculate_comprehensive_similarity(
+            source_signature,
+            source_ast,
+            target_signature,
+            target_ast,
+        )?;
+        similarities.insert(
+            format!("{} -> {}", source_name, target_name),
+            score.overall_similarity,
+        );
+    }
 
     Ok(similarities)
 }
 
 /// Write output to file or stdout
 async fn write_output(
-    content: &str,
+    content: &[u8],
     output_path: &Option<PathBuf>,
     _format: &OutputFormat,
 ) -> Result<()> {
     match output_path {
         Some(path) => {
-            // Ensure directory exists
-            if let Some(parent) = path.parent() {
-                async_fs::create_dir_all(parent).await.with_context(|| {
-                    format!("Failed to create output directory: {}", parent.display())
-                })?;
-            }
-
-            async_fs::write(path, content)
-                .await
-                .with_context(|| format!("Failed to write output to: {}", path.display()))?;
+            write_atomic(path, content)?;
 
             info!("Output written to: {}", path.display());
         }
         None => {
             // Write to stdout
-            print!("{}", content);
+            std::io::Write::write_all(&mut std::io::stdout().lock(), content)?;
         }
     }
 
@@ -724,24 +2039,101 @@ fn display_summary(
     Ok(())
 }
 
+/// Path of a compared file relative to the input root, so the root itself never affects classification
+fn relative_to_root<'a>(path: &'a Path, root: &Path) -> &'a Path {
+    match path.strip_prefix(root) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative,
+        _ => path,
+    }
+}
+
+/// Display separate summaries for production and test files
+fn display_categorized_summary(categorized: &CategorizedStats, term: &Term) -> Result<()> {
+    for (title, stats) in [
+        ("Production Code", &categorized.production),
+        ("Test Code", &categorized.tests),
+    ] {
+        term.write_line("")?;
+        term.write_line(&format!("{}", title.bold().green()))?;
+        term.write_line(&format!("{}", "-".repeat(20).dimmed()))?;
+        term.write_line(&format!(
+            "Files compared: {}",
+            stats.files_compared.to_string().bold()
+        ))?;
+        term.write_line(&format!(
+            "Changes detected: {}",
+            stats.changes_detected.to_string().bold()
+        ))?;
+
+        if stats.refactoring_patterns > 0 {
+            term.write_line(&format!(
+                "Refactoring patterns: {}",
+                stats.refactoring_patterns.to_string().bold().yellow()
+            ))?;
+        }
+
+        if stats.files_compared > 0 {
+            term.write_line(&format!(
+                "Average similarity: {:.1}%",
+                stats.similarity_score * 100.0
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Display lines and functions touched, split by file category when classified
+fn display_churn(summary: &ChurnSummary, term: &Term) -> Result<()> {
+    term.write_line("")?;
+    term.write_line(&format!("{}", "Code Churn".bold().cyan()))?;
+    term.write_line(&format!("{}", "-".repeat(20).dimmed()))?;
+
+    let line = |label: &str, churn: &Churn| {
+        format!(
+            "{}{} added, {} removed, {} modified lines; {} functions touched",
+            label,
+            format!("+{}", churn.lines_added).green(),
+            format!("-{}", churn.lines_removed).red(),
+            format!("~{}", churn.lines_modified).yellow(),
+            churn.functions_touched.to_string().bold()
+        )
+    };
+    term.write_line(&line("Total: ", &summary.total))?;
+    if let Some(production) = &summary.production {
+        term.write_line(&line("Production: ", production))?;
+    }
+    if let Some(tests) = &summary.tests {
+        term.write_line(&line("Tests: ", tests))?;
+    }
+
+    Ok(())
+}
+
 /// Display detailed statistics
 fn display_detailed_stats(stats: &ComparisonStats, term: &Term) -> Result<()> {
     term.write_line("")?;
     term.write_line(&format!("{}", "Detailed Statistics".bold().cyan()))?;
     term.write_line(&format!("{}", "=".repeat(30).dimmed()))?;
 
-    term.write_line(&format!(
-        "Parsing time: {}",
-        format_duration(stats.parsing_time)
-    ))?;
-    term.write_line(&format!(
-        "Comparison time: {}",
-        format_duration(stats.comparison_time)
-    ))?;
+    for (phase, duration) in stats.phase_timings() {
+        let label = phase.replace('_', " ");
+        let mut chars = label.chars();
+        let label = match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+            None => label,
+        };
+        term.write_line(&format!(
+            "{:<28} {}",
+            format!("{} time:", label),
+            format_duration(duration)
+        ))?;
+    }
     term.write_line(&format!(
         "Total processing time: {}",
         format_duration(stats.total_time)
     ))?;
+    term.write_line(&format!("Files processed: {}", stats.files_compared))?;
 
     term.write_line("")?;
     term.write_line(&format!("Source lines: {}", stats.source_lines))?;
@@ -755,7 +2147,7 @@ fn display_detailed_stats(stats: &ComparisonStats, term: &Term) -> Result<()> {
         term.write_line("")?;
         term.write_line(&format!(
             "Functions per second: {:.1}",
-            stats.functions_compared as f64 / stats.comparison_time.as_secs_f64()
+            stats.functions_per_second()
         ))?;
         term.write_line(&format!(
             "Lines per second: {:.1}",
@@ -781,8 +2173,167 @@ fn format_duration(duration: Duration) -> String {
     }
 }
 
+/// Complexity change of every function present in both versions, keyed by target element id
+fn complexity_deltas(
+    language: Language,
+    source_functions: &[smart_diff_parser::Function],
+    target_functions: &[smart_diff_parser::Function],
+    changes: &[smart_diff_parser::Change],
+) -> HashMap<String, ComplexityDelta> {
+    let extractor = FunctionSignatureExtractor::with_defaults(language);
+    let metrics = |functions: &[smart_diff_parser::Function]| -> HashMap<String, _> {
+        functions
+            .iter()
+            .filter_map(|f| {
+                let metrics = extractor.calculate_complexity_metrics(&f.body).ok()?;
+                Some((smart_diff_parser::CodeElement::from_function(f).id, metrics))
+            })
+            .collect()
+    };
+    let source_metrics = metrics(source_functions);
+    let target_metrics = metrics(target_functions);
+
+    changes
+        .iter()
+        .filter_map(|change| {
+            let before = source_metrics.get(&change.source.as_ref()?.id)?;
+            let target_id = &change.target.as_ref()?.id;
+            let after = target_metrics.get(target_id)?;
+            Some((target_id.clone(), ComplexityDelta::between(before, after)))
+        })
+        .collect()
+}
+
+/// Literal changes of each matched function, keyed by target element id
+fn literal_changes(
+    source_functions: &[smart_diff_parser::Function],
+    target_functions: &[smart_diff_parser::Function],
+    changes: &[smart_diff_parser::Change],
+) -> HashMap<String, LiteralChanges> {
+    fn bodies(
+        functions: &[smart_diff_parser::Function],
+    ) -> HashMap<String, &smart_diff_parser::ASTNode> {
+        functions
+            .iter()
+            .map(|f| (smart_diff_parser::CodeElement::from_function(f).id, &f.body))
+            .collect()
+    }
+    let source_bodies = bodies(source_functions);
+    let target_bodies = bodies(target_functions);
+
+    changes
+        .iter()
+        .filter_map(|change| {
+            let before = source_bodies.get(&change.source.as_ref()?.id)?;
+            let target_id = &change.target.as_ref()?.id;
+            let after = target_bodies.get(target_id)?;
+            let literals = smart_diff_engine::detect_literal_changes(before, after)?;
+            Some((target_id.clone(), literals))
+        })
+        .collect()
+}
+
+/// Behavior impact of each matched function whose text changed, keyed by target element id
+fn behavior_assessments(
+    classifier: &BehaviorClassifier,
+    source_functions: &[smart_diff_parser::Function],
+    target_functions: &[smart_diff_parser::Function],
+    changes: &[smart_diff_parser::Change],
+) -> HashMap<String, BehaviorAssessment> {
+    fn bodies(
+        functions: &[smart_diff_parser::Function],
+    ) -> HashMap<String, &smart_diff_parser::ASTNode> {
+        functions
+            .iter()
+            .map(|f| (smart_diff_parser::CodeElement::from_function(f).id, &f.body))
+            .collect()
+    }
+    let source_bodies = bodies(source_functions);
+    let target_bodies = bodies(target_functions);
+
+    changes
+        .iter()
+        .filter_map(|change| {
+            let before = source_bodies.get(&change.source.as_ref()?.id)?;
+            let target_id = &change.target.as_ref()?.id;
+            let after = target_bodies.get(target_id)?;
+            (before.metadata.original_text != after.metadata.original_text)
+                .then(|| (target_id.clone(), classifier.classify(before, after)))
+        })
+        .collect()
+}
+
+/// Nearest unchanged functions before and after each changed function, keyed
+/// by the changed function's element id
+///
+/// A function is placed among the other functions of its file by line, in the
+/// target version unless it was deleted. Up to `count` functions on each side
+/// that are not part of any change are listed, by signature, in file order.
+fn neighboring_unchanged_functions(
+    source_functions: &[smart_diff_parser::Function],
+    target_functions: &[smart_diff_parser::Function],
+    changes: &[smart_diff_parser::Change],
+    count: usize,
+) -> HashMap<String, UnchangedContext> {
+    fn by_line(functions: &[smart_diff_parser::Function]) -> Vec<smart_diff_parser::CodeElement> {
+        let mut elements: Vec<_> = functions
+            .iter()
+            .map(smart_diff_parser::CodeElement::from_function)
+            .collect();
+        elements.sort_by(|a, b| {
+            (&a.file_path, a.start_line, &a.name).cmp(&(&b.file_path, b.start_line, &b.name))
+        });
+        elements
+    }
+    let source_elements = by_line(source_functions);
+    let target_elements = by_line(target_functions);
+    let changed: HashSet<&str> = changes
+        .iter()
+        .flat_map(|change| [&change.source, &change.target])
+        .flatten()
+        .map(|element| element.id.as_str())
+        .collect();
+
+    changes
+        .iter()
+        .filter_map(|change| {
+            let (element, elements) = match (&change.source, &change.target) {
+                (_, Some(target)) => (target, &target_elements),
+                (Some(source), None) => (source, &source_elements),
+                (None, None) => return None,
+            };
+            let position = elements.iter().position(|e| e.id == element.id)?;
+            let unchanged = |neighbor: &&smart_diff_parser::CodeElement| {
+                neighbor.file_path == element.file_path && !changed.contains(neighbor.id.as_str())
+            };
+            let label = |neighbor: &smart_diff_parser::CodeElement| {
+                neighbor
+                    .signature
+                    .clone()
+                    .unwrap_or_else(|| neighbor.name.clone())
+            };
+
+            let mut before: Vec<String> = elements[..position]
+                .iter()
+                .rev()
+                .filter(unchanged)
+                .take(count)
+                .map(label)
+                .collect();
+            before.reverse();
+            let after = elements[position + 1..]
+                .iter()
+                .filter(unchanged)
+                .take(count)
+                .map(label)
+                .collect();
+            Some((element.id.clone(), UnchangedContext { before, after }))
+        })
+        .collect()
+}
+
 /// Extract functions from AST for comparison
-fn extract_functions_from_ast(
+pub(crate) fn extract_functions_from_ast(
     ast: &smart_diff_parser::ASTNode,
 ) -> Vec<smart_diff_parser::Function> {
     use smart_diff_parser::{Function, FunctionSignature, NodeType};
@@ -793,6 +2344,16 @@ fn extract_functions_from_ast(
     let function_nodes = ast.find_by_type(&NodeType::Function);
 
     for (i, node) in function_nodes.iter().enumerate() {
+        if node.is_function_declarator() {
+            continue;
+        }
+        // Left unnamed by `--anonymous-functions skip`
+        if node.metadata.attributes.contains_key("anonymous")
+            && !node.metadata.attributes.contains_key("name")
+        {
+            continue;
+        }
+
         let name = node
             .metadata
             .attributes
@@ -801,29 +2362,1282 @@ fn extract_functions_from_ast(
             .unwrap_or_else(|| format!("function_{}", i));
 
         let signature = FunctionSignature {
-            name: name.clone(),
-            parameters: Vec::new(), // Simplified for now
             return_type: Some(smart_diff_parser::Type::new("void".to_string())),
-            modifiers: Vec::new(),
-            generic_parameters: Vec::new(),
-        };
-
-        let function = Function {
-            signature,
-            body: (*node).clone(),
-            location: smart_diff_parser::FunctionLocation {
-                file_path: "".to_string(),
-                start_line: node.metadata.line,
-                end_line: node.metadata.line,
-                start_column: node.metadata.column,
-                end_column: node.metadata.column,
-            },
-            dependencies: Vec::new(),
-            hash: "0".to_string(), // Simplified for now
+            ..FunctionSignature::from_node(name.clone(), node)
         };
 
+        let function = Function::new(signature, (*node).clone(), String::new());
+
         functions.push(function);
     }
 
     functions
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser as _;
+    use std::ffi::OsStr;
+
+    /// Options of a parsed `compare` command line
+    fn compare_options(cli: &Cli) -> &CompareOptions {
+        match &cli.command {
+            Some(Commands::Compare { options, .. }) => options,
+            _ => panic!("not a compare command line"),
+        }
+    }
+
+    #[test]
+    fn test_c_definitions_are_extracted_once_with_their_whole_span() {
+        let c_code = "\
+int clamp(int value, int low, int high)
+{
+    if (value < low) {
+        return low;
+    }
+    return value > high ? high : value;
+}
+";
+        let parsed = TreeSitterParser::new()
+            .unwrap()
+            .parse(c_code, Language::C)
+            .unwrap();
+        let functions = extract_functions_from_ast(&parsed.ast);
+
+        // The definition's declarator is no function of its own
+        assert_eq!(functions.len(), 1, "{:#?}", functions);
+        let clamp = &functions[0];
+        assert_eq!(clamp.signature.name, "clamp");
+        assert_eq!((clamp.location.start_line, clamp.location.end_line), (1, 7));
+        assert_eq!(clamp.body_text(c_code), Some(c_code.trim_end()));
+        assert_ne!(clamp.hash, "0");
+    }
+
+    /// Compare `old` against `new` in `dir` with `extra_args`, returning the JSON report
+    async fn compare_report(dir: &Path, extra_args: &[&OsStr]) -> serde_json::Value {
+        let (old, new) = (dir.join("old"), dir.join("new"));
+        let output = dir.join("report.json");
+        let mut args: Vec<&OsStr> = vec![
+            "smart-diff".as_ref(),
+            "--quiet".as_ref(),
+            "compare".as_ref(),
+            old.as_os_str(),
+            new.as_os_str(),
+            "--format".as_ref(),
+            "json".as_ref(),
+        ];
+        args.extend_from_slice(extra_args);
+        args.extend(["--output".as_ref(), output.as_os_str()]);
+        run(Cli::parse_from(args)).await.unwrap();
+
+        serde_json::from_str(&std::fs::read_to_string(output).unwrap()).unwrap()
+    }
+
+    /// Compare two trees that each hold one hand-written and one generated file
+    async fn compare_with_policy(policy: &str) -> serde_json::Value {
+        let dir = tempfile::tempdir().unwrap();
+        // The new version of each file gains a function
+        for (version, functions) in [("old", 1), ("new", 2)] {
+            let root = dir.path().join(version);
+            std::fs::create_dir_all(&root).unwrap();
+            let bodies = |prefix: &str| {
+                (0..functions)
+                    .map(|i| {
+                        format!(
+                            "int {}_{}(int a) {{\n    return a + {};\n}}\n",
+                            prefix, i, i
+                        )
+                    })
+                    .collect::<String>()
+            };
+            std::fs::write(root.join("math.c"), bodies("add")).unwrap();
+            std::fs::write(
+                root.join("schema.c"),
+                format!("// @generated by schema-gen\n{}", bodies("field")),
+            )
+            .unwrap();
+        }
+
+        compare_report(dir.path(), &["--generated".as_ref(), policy.as_ref()]).await
+    }
+
+    fn result_for<'a>(
+        report: &'a serde_json::Value,
+        file_name: &str,
+    ) -> Option<&'a serde_json::Value> {
+        report["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|result| result["source_file"].as_str().unwrap().ends_with(file_name))
+    }
+
+    #[tokio::test]
+    async fn test_generated_file_excluded() {
+        let report = compare_with_policy("exclude").await;
+
+        assert_eq!(report["results"].as_array().unwrap().len(), 1);
+        assert!(result_for(&report, "math.c").is_some());
+        assert!(result_for(&report, "schema.c").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_generated_file_counted_but_collapsed() {
+        let report = compare_with_policy("summarize").await;
+
+        assert_eq!(report["results"].as_array().unwrap().len(), 2);
+        let math = result_for(&report, "math.c").unwrap();
+        assert!(math.get("generated").is_none());
+        assert!(!math["changes"].as_array().unwrap().is_empty());
+
+        let schema = result_for(&report, "schema.c").unwrap();
+        assert_eq!(schema["generated"], true);
+        assert!(schema["changes"].as_array().unwrap().is_empty());
+        assert!(schema["statistics"]["functions_compared"].as_u64().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_extraction_rules_add_tagged_elements() {
+        let dir = tempfile::tempdir().unwrap();
+        let test_class = |body: &str| {
+            format!(
+                "class CalculatorTest {{\n    @Test\n    void addsNumbers() {{\n        check(add(1, 2));\n    }}\n{}}}\n",
+                body
+            )
+        };
+        for (version, extra) in [
+            ("old", ""),
+            (
+                "new",
+                "    @Test\n    void subtractsNumbers() {\n        check(sub(2, 1));\n    }\n",
+            ),
+        ] {
+            let root = dir.path().join(version);
+            std::fs::create_dir_all(&root).unwrap();
+            std::fs::write(root.join("CalculatorTest.java"), test_class(extra)).unwrap();
+        }
+
+        let rules = dir.path().join("rules.toml");
+        std::fs::write(
+            &rules,
+            r#"
+[[rule]]
+language = "java"
+element_type = "test_case"
+query = """
+(method_declaration
+  (modifiers (marker_annotation name: (identifier) @annotation (#eq? @annotation "Test")))
+  name: (identifier) @name) @element
+"""
+"#,
+        )
+        .unwrap();
+
+        let report = compare_report(
+            dir.path(),
+            &["--extraction-rules".as_ref(), rules.as_os_str()],
+        )
+        .await;
+        let changes = report["results"][0]["changes"].as_array().unwrap();
+        let added = changes
+            .iter()
+            .filter_map(|change| change["target"].as_object())
+            .find(|target| target["name"] == "subtractsNumbers")
+            .expect("New test case is reported");
+        assert_eq!(added["element_type"]["Custom"], "test_case");
+        assert!(added["id"].as_str().unwrap().starts_with("test_case_"));
+    }
+
+    #[tokio::test]
+    async fn test_changed_files_restrict_comparison() {
+        let dir = tempfile::tempdir().unwrap();
+        for (version, offset) in [("old", 1), ("new", 2)] {
+            let root = dir.path().join(version);
+            std::fs::create_dir_all(root.join("lib")).unwrap();
+            for file in ["main.c", "lib/util.c"] {
+                let name = file.trim_end_matches(".c").replace('/', "_");
+                // `helper` moves from the listed file to the unlisted one
+                let helper = match (version, file) {
+                    ("old", "lib/util.c") | ("new", "main.c") => {
+                        "int helper(int a) {\n    return a * 3;\n}\n"
+                    }
+                    _ => "",
+                };
+                std::fs::write(
+                    root.join(file),
+                    format!(
+                        "int {}(int a) {{\n    return a + {};\n}}\n{}",
+                        name, offset, helper
+                    ),
+                )
+                .unwrap();
+            }
+        }
+
+        let changed = dir.path().join("changed.txt");
+        std::fs::write(&changed, "./lib/util.c\n\nREADME.md\n").unwrap();
+
+        let report = compare_report(
+            dir.path(),
+            &[
+                "--recursive".as_ref(),
+                "--track-moves".as_ref(),
+                "--changed-files".as_ref(),
+                changed.as_os_str(),
+            ],
+        )
+        .await;
+        assert_eq!(report["results"].as_array().unwrap().len(), 1);
+        let util = result_for(&report, "util.c").expect("Listed file is compared");
+        assert_eq!(util["statistics"]["functions_compared"], 3);
+        assert!(result_for(&report, "main.c").is_none());
+        let moved = util["changes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|change| change["source"]["name"] == "helper")
+            .expect("Moved function is reported");
+        assert_eq!(moved["change_type"], "CrossFileMove");
+        assert!(moved["target"]["file_path"]
+            .as_str()
+            .unwrap()
+            .ends_with("main.c"));
+    }
+
+    #[tokio::test]
+    async fn test_track_moves_pairs_functions_moved_between_compared_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for (version, home) in [("old", "a.c"), ("new", "b.c")] {
+            let root = dir.path().join(version);
+            std::fs::create_dir_all(&root).unwrap();
+            for file in ["a.c", "b.c"] {
+                let name = file.trim_end_matches(".c");
+                let helper = if file == home {
+                    "int helper(int a) {\n    return a * 3;\n}\n"
+                } else {
+                    ""
+                };
+                std::fs::write(
+                    root.join(file),
+                    format!("int {}(int a) {{\n    return a;\n}}\n{}", name, helper),
+                )
+                .unwrap();
+            }
+        }
+
+        let report = compare_report(dir.path(), &["--track-moves".as_ref()]).await;
+        let changes = |file_name| {
+            result_for(&report, file_name).unwrap()["changes"]
+                .as_array()
+                .unwrap()
+                .clone()
+        };
+        let moved = changes("a.c");
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0]["change_type"], "CrossFileMove");
+        assert_eq!(
+            moved[0]["details"]["description"],
+            "Moved 'helper' from a.c:4 to b.c:4"
+        );
+        assert!(changes("b.c").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_notebook_change_is_attributed_to_its_cell() {
+        let notebook = |clean_body: &str, execution_count: u32| {
+            serde_json::json!({
+                "nbformat": 4,
+                "nbformat_minor": 5,
+                "metadata": {"kernelspec": {"language": "python", "name": "python3"}},
+                "cells": [
+                    {"cell_type": "markdown", "metadata": {}, "source": ["# Sales report\n"]},
+                    {
+                        "cell_type": "code",
+                        "execution_count": execution_count,
+                        "metadata": {"collapsed": execution_count > 1},
+                        "outputs": [{"output_type": "stream", "name": "stdout", "text": [format!("run {}\n", execution_count)]}],
+                        "source": ["import csv\n", "\n", "def load(path):\n", "    with open(path) as f:\n", "        return list(csv.DictReader(f))\n"]
+                    },
+                    {
+                        "cell_type": "code",
+                        "execution_count": execution_count + 1,
+                        "metadata": {},
+                        "outputs": [],
+                        "source": format!("def clean(rows):\n{}", clean_body)
+                    }
+                ]
+            })
+            .to_string()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("old.ipynb");
+        let new = dir.path().join("new.ipynb");
+        std::fs::write(
+            &old,
+            notebook("    return [row for row in rows if row]\n", 1),
+        )
+        .unwrap();
+        std::fs::write(
+            &new,
+            notebook(
+                "    rows = [row for row in rows if row]\n    return [row for row in rows if row['amount']]\n",
+                7,
+            ),
+        )
+        .unwrap();
+
+        let cli = Cli::parse_from([
+            "smart-diff".as_ref(),
+            "--quiet".as_ref(),
+            "compare".as_ref(),
+            old.as_os_str(),
+            new.as_os_str(),
+        ]);
+        let (results, _) = compare_inputs(compare_options(&cli), None, true, None)
+            .await
+            .unwrap();
+        assert_eq!(results[0].language, Language::Python);
+
+        // Outputs, execution counts and metadata differ too, but only `clean` changed
+        let changes = &results[0].diff_result.match_result.changes;
+        assert_eq!(changes.len(), 1, "{:?}", changes);
+        let change = &changes[0];
+        assert_eq!(change.change_type, ChangeType::Modify);
+        assert_eq!(change.target.as_ref().unwrap().name, "clean");
+        assert_eq!(change.details.metadata["source_cell"], "3");
+        assert_eq!(change.details.metadata["target_cell"], "3");
+        assert!(
+            change.details.description.ends_with(" (cell 3)"),
+            "{}",
+            change.details.description
+        );
+    }
+
+    #[tokio::test]
+    async fn test_progress_is_reported_per_file_pair_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        for (version, offset) in [("old", 1), ("new", 2)] {
+            let root = dir.path().join(version);
+            std::fs::create_dir_all(&root).unwrap();
+            for name in ["alpha", "beta", "gamma"] {
+                std::fs::write(
+                    root.join(format!("{}.c", name)),
+                    format!("int {}(int a) {{\n    return a + {};\n}}\n", name, offset),
+                )
+                .unwrap();
+            }
+        }
+
+        let cli = Cli::parse_from([
+            "smart-diff".as_ref(),
+            "--quiet".as_ref(),
+            "compare".as_ref(),
+            dir.path().join("old").as_os_str(),
+            dir.path().join("new").as_os_str(),
+        ]);
+        let updates = std::sync::Mutex::new(Vec::new());
+        let record = |update: &ComparisonProgress| updates.lock().unwrap().push(update.clone());
+        compare_inputs(compare_options(&cli), None, true, Some(&record))
+            .await
+            .unwrap();
+
+        let updates = updates.into_inner().unwrap();
+        assert!(
+            updates.windows(2).all(
+                |pair| (pair[0].phase, pair[0].completed) <= (pair[1].phase, pair[1].completed)
+            ),
+            "{:?}",
+            updates
+        );
+        let compared: Vec<(usize, usize)> = updates
+            .iter()
+            .filter(|update| update.phase == ComparisonPhase::Comparing)
+            .map(|update| (update.completed, update.total))
+            .collect();
+        assert_eq!(compared, [(0, 3), (1, 3), (2, 3), (3, 3)]);
+        assert_eq!(updates.first().unwrap().phase, ComparisonPhase::Discovering);
+        assert_eq!(updates.last().unwrap().phase, ComparisonPhase::Analyzing);
+    }
+
+    #[tokio::test]
+    async fn test_added_branches_report_complexity_increase() {
+        let dir = tempfile::tempdir().unwrap();
+        for (version, body) in [
+            ("old", "    return a + b;\n"),
+            (
+                "new",
+                "    if (a < 0) {\n        return -a;\n    }\n    if (b < 0) {\n        return -b;\n    }\n    while (a > b) {\n        a = a - b;\n    }\n    return a + b;\n",
+            ),
+        ] {
+            let root = dir.path().join(version);
+            std::fs::create_dir_all(&root).unwrap();
+            std::fs::write(
+                root.join("math.c"),
+                format!("int combine(int a, int b) {{\n{}}}\n", body),
+            )
+            .unwrap();
+        }
+
+        let report = compare_report(dir.path(), &[]).await;
+        let change = &report["results"][0]["changes"][0];
+        assert_eq!(change["target"]["name"], "combine");
+        assert_eq!(change["complexity_before"], 1);
+        assert_eq!(change["complexity_after"], 4);
+        assert_eq!(change["delta"], 3);
+        assert!(change["cognitive_delta"].as_i64().unwrap() > 0);
+        assert_eq!(change["large_increase"], false);
+    }
+
+    #[tokio::test]
+    async fn test_changed_function_lists_unchanged_neighbors_in_file_order() {
+        let dir = tempfile::tempdir().unwrap();
+        for (version, body) in [("old", "x + 3"), ("new", "x * 3 - 1")] {
+            let root = dir.path().join(version);
+            std::fs::create_dir_all(&root).unwrap();
+            std::fs::write(
+                root.join("steps.c"),
+                format!(
+                    "int first(int x) {{\n    return x + 1;\n}}\n\n\
+                     int second(int x) {{\n    return x - 2;\n}}\n\n\
+                     int third(int x) {{\n    return {};\n}}\n\n\
+                     int fourth(int x) {{\n    return x * 4;\n}}\n\n\
+                     int fifth(int x) {{\n    return x / 5;\n}}\n",
+                    body
+                ),
+            )
+            .unwrap();
+        }
+
+        let report = compare_report(dir.path(), &["--include-unchanged-context=2".as_ref()]).await;
+        let changes = report["results"][0]["changes"].as_array().unwrap();
+        assert_eq!(changes.len(), 1, "{:#?}", changes);
+        assert_eq!(changes[0]["target"]["name"], "third");
+        let names = |side: &str| -> Vec<String> {
+            changes[0]["context"][side]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|signature| {
+                    let signature = signature.as_str().unwrap();
+                    signature[..signature.find('(').unwrap_or(signature.len())].to_string()
+                })
+                .collect()
+        };
+        assert_eq!(names("before"), ["first", "second"]);
+        assert_eq!(names("after"), ["fourth", "fifth"]);
+    }
+
+    #[tokio::test]
+    async fn test_blocklisted_boilerplate_is_left_out_of_the_change_list() {
+        let dir = tempfile::tempdir().unwrap();
+        for (version, fields, total) in [
+            ("old", ["x", "y"].as_slice(), "total"),
+            ("new", ["x", "y", "z"].as_slice(), "sum"),
+        ] {
+            let root = dir.path().join(version);
+            std::fs::create_dir_all(&root).unwrap();
+            let own = |field: &str| format!("self.{}", field);
+            let other = |field: &str| format!("other.{}", field);
+            let mut source = String::from("class Point:\n");
+            source.push_str(&format!(
+                "    def __eq__(self, other):\n        return ({}) == ({})\n\n",
+                fields.iter().map(|f| own(f)).collect::<Vec<_>>().join(", "),
+                fields
+                    .iter()
+                    .map(|f| other(f))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ));
+            source.push_str(&format!(
+                "    def __hash__(self):\n        return hash(({},))\n\n",
+                fields.iter().map(|f| own(f)).collect::<Vec<_>>().join(", ")
+            ));
+            source.push_str(&format!(
+                "    def describe(self):\n        return \"Point({})\"\n\n",
+                fields.join(", ")
+            ));
+            source.push_str(&format!(
+                "    def {}(self, values):\n        result = 0\n        for value in values:\n            result += value * self.x\n        return result\n",
+                total
+            ));
+            std::fs::write(root.join("point.py"), source).unwrap();
+        }
+        let config = dir.path().join("smart-diff.toml");
+        std::fs::write(&config, "[blocklist]\npatterns = [\"/^desc.*/\"]\n").unwrap();
+
+        let report = compare_report(dir.path(), &["--config".as_ref(), config.as_os_str()]).await;
+        let result = &report["results"][0];
+        // __eq__ and __hash__ by default, describe by the configured pattern
+        let changed: Vec<(&str, &str)> = result["changes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|change| {
+                (
+                    change["source"]["name"].as_str().unwrap(),
+                    change["target"]["name"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(changed, [("total", "sum")]);
+        assert_eq!(result["statistics"]["functions_modified"], 4);
+    }
+
+    #[tokio::test]
+    async fn test_modified_arrow_function_callback_is_matched() {
+        let dir = tempfile::tempdir().unwrap();
+        for (version, body) in [
+            (
+                "old",
+                "  items.forEach((item) => {\n    console.log(item);\n  });\n",
+            ),
+            // A new callback comes first, shifting the position of the edited one
+            (
+                "new",
+                "  const visible = items.filter((item) => item.visible);\n  visible.forEach((item) => {\n    console.log(item);\n    console.log(item.id);\n  });\n",
+            ),
+        ] {
+            let root = dir.path().join(version);
+            std::fs::create_dir_all(&root).unwrap();
+            std::fs::write(
+                root.join("app.js"),
+                format!("function render(items) {{\n{}}}\n", body),
+            )
+            .unwrap();
+        }
+
+        let report = compare_report(dir.path(), &[]).await;
+        let changes: Vec<(&str, &str, &str)> = report["results"][0]["changes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|change| {
+                let name = |side: &str| change[side]["name"].as_str().unwrap_or("");
+                (
+                    change["change_type"].as_str().unwrap(),
+                    name("source"),
+                    name("target"),
+                )
+            })
+            .collect();
+        assert!(
+            changes.contains(&("Modify", "<anon@render:0>", "<anon@render:1>")),
+            "{:?}",
+            changes
+        );
+        assert!(changes.contains(&("Add", "", "<anon@render:0>")));
+    }
+
+    #[tokio::test]
+    async fn test_signatures_only_ignores_body_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        for (version, scale) in [
+            ("old", "int scale(int a, int b) {\n    return a * b;\n}\n"),
+            ("new", "long scale(int a, int b) {\n    return a * b;\n}\n"),
+        ] {
+            let root = dir.path().join(version);
+            std::fs::create_dir_all(&root).unwrap();
+            let body = match version {
+                "old" => "    return a + b;\n",
+                _ => "    if (a < 0) {\n        return b;\n    }\n    return a + b;\n",
+            };
+            std::fs::write(
+                root.join("math.c"),
+                format!("int combine(int a, int b) {{\n{}}}\n{}", body, scale),
+            )
+            .unwrap();
+        }
+
+        let report = compare_report(dir.path(), &["--signatures-only".as_ref()]).await;
+        let changes = report["results"][0]["changes"].as_array().unwrap();
+        assert_eq!(changes.len(), 1, "{:#?}", changes);
+        assert_eq!(changes[0]["target"]["name"], "scale");
+        assert!(changes[0]["details"]["description"]
+            .as_str()
+            .unwrap()
+            .contains("return type int -> long"));
+    }
+
+    #[tokio::test]
+    async fn test_relative_to_renders_every_path_relative_to_root() {
+        let dir = tempfile::tempdir().unwrap();
+        for (version, functions) in [("old", 1), ("new", 2)] {
+            let root = dir.path().join(version).join("src");
+            std::fs::create_dir_all(&root).unwrap();
+            let source: String = (0..functions)
+                .map(|i| format!("int add_{}(int a) {{\n    return a + {};\n}}\n", i, i))
+                .collect();
+            std::fs::write(root.join("math.c"), source).unwrap();
+        }
+
+        let root = dir.path();
+        let compare = move |relative_to: String| async move {
+            compare_report(
+                root,
+                &[
+                    "--recursive".as_ref(),
+                    "--signatures-only".as_ref(),
+                    relative_to.as_ref(),
+                ],
+            )
+            .await
+        };
+
+        let report = compare(format!("--relative-to={}", root.display())).await;
+        let content = report.to_string();
+        assert!(!content.contains(&*root.to_string_lossy()), "{}", content);
+        let result = &report["results"][0];
+        assert_eq!(result["source_file"], "old/src/math.c");
+        assert_eq!(result["target_file"], "new/src/math.c");
+        assert_eq!(
+            result["changes"][0]["target"]["file_path"],
+            "new/src/math.c"
+        );
+
+        // Without a root each side is relative to its own input
+        let report = compare("--relative-to".to_string()).await;
+        assert_eq!(report["results"][0]["source_file"], "src/math.c");
+        assert_eq!(report["results"][0]["target_file"], "src/math.c");
+    }
+
+    #[tokio::test]
+    async fn test_changed_function_without_test_update_is_flagged() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, new) = (dir.path().join("old"), dir.path().join("new"));
+        for root in [&old, &new] {
+            std::fs::create_dir(root).unwrap();
+        }
+        let calc = |scale: &str, shift: &str| {
+            format!(
+                "def scale_total(items):\n    total = sum(items)\n{}    return total * 2\n\n\
+                 def shift_total(items):\n    total = sum(items)\n{}    return total + 1\n",
+                scale, shift
+            )
+        };
+        let tests = |extra: &str| {
+            format!(
+                "def test_scale_total():\n    result = scale_total([1, 2])\n    assert result == 6\n{}\n\
+                 def test_shift_total():\n    result = shift_total([1, 2])\n    assert result == 4\n",
+                extra
+            )
+        };
+        let cap = "    if total > 10:\n        total = 10\n";
+        std::fs::write(old.join("calc.py"), calc("", "")).unwrap();
+        std::fs::write(new.join("calc.py"), calc(cap, cap)).unwrap();
+        std::fs::write(old.join("test_calc.py"), tests("")).unwrap();
+        std::fs::write(
+            new.join("test_calc.py"),
+            tests("    assert scale_total([9, 9]) == 20\n"),
+        )
+        .unwrap();
+
+        let report = compare_report(
+            dir.path(),
+            &["--recursive".as_ref(), "--check-test-updates".as_ref()],
+        )
+        .await;
+        let changes: Vec<&serde_json::Value> = report["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|result| result["changes"].as_array().unwrap())
+            .collect();
+        let metadata = |name: &str| {
+            &changes
+                .iter()
+                .find(|change| change["target"]["name"] == name)
+                .unwrap_or_else(|| panic!("{} not changed: {:#?}", name, changes))["details"]
+                ["metadata"]
+        };
+
+        assert_eq!(metadata("scale_total")["tests_updated"], "true");
+        assert_eq!(metadata("scale_total")["updated_tests"], "test_scale_total");
+        assert_eq!(metadata("shift_total")["tests_updated"], "false");
+        assert!(metadata("test_scale_total")["tests_updated"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_churn_counts_diff_lines_by_file_category() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, new) = (dir.path().join("old"), dir.path().join("new"));
+        for root in [&old, &new] {
+            std::fs::create_dir(root).unwrap();
+        }
+        std::fs::write(
+            old.join("calc.py"),
+            "def total(items):\n    result = sum(items)\n    print(result)\n    return result\n",
+        )
+        .unwrap();
+        // One line modified, one removed and three added
+        std::fs::write(
+            new.join("calc.py"),
+            "def total(items):\n    result = sum(items) * 2\n    return result\n\n\
+             def scale(value):\n    return value * 3\n",
+        )
+        .unwrap();
+        let tests = "def test_total():\n    assert total([1, 2]) == 6\n";
+        std::fs::write(old.join("test_calc.py"), tests).unwrap();
+        std::fs::write(
+            new.join("test_calc.py"),
+            format!("{}    assert total([]) == 0\n", tests),
+        )
+        .unwrap();
+
+        let report = compare_report(
+            dir.path(),
+            &[
+                "--recursive".as_ref(),
+                "--churn".as_ref(),
+                "--separate-tests".as_ref(),
+            ],
+        )
+        .await;
+        let churn = &report["churn"];
+        assert_eq!(churn["total"]["lines_added"], 4);
+        assert_eq!(churn["total"]["lines_removed"], 1);
+        assert_eq!(churn["total"]["lines_modified"], 1);
+        assert_eq!(churn["production"]["lines_added"], 3);
+        assert_eq!(churn["production"]["lines_removed"], 1);
+        assert_eq!(churn["tests"]["lines_added"], 1);
+        assert_eq!(churn["tests"]["lines_removed"], 0);
+        assert!(churn["production"]["functions_touched"].as_u64().unwrap() >= 2);
+
+        let test_file = report["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|result| {
+                result["source_file"]
+                    .as_str()
+                    .unwrap()
+                    .ends_with("test_calc.py")
+            })
+            .unwrap();
+        assert_eq!(test_file["churn"]["category"], "test");
+        assert_eq!(test_file["churn"]["lines_added"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_deleted_functions_are_weighed_by_their_callers() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, new) = (dir.path().join("old"), dir.path().join("new"));
+        for root in [&old, &new] {
+            std::fs::create_dir(root).unwrap();
+        }
+        // `checksum` is called by `main`; `legacy` only calls itself
+        std::fs::write(
+            old.join("calc.c"),
+            "int main(void) {\n    int total = checksum(4);\n    return total;\n}\n\
+             int checksum(int n) {\n    int sum = 0;\n    while (n > 0) {\n        sum = sum + n;\n        n = n - 1;\n    }\n    return sum;\n}\n\
+             int legacy(int depth) {\n    if (depth > 8) {\n        return depth * 3;\n    }\n    return legacy(depth + 2);\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            new.join("calc.c"),
+            "int main(void) {\n    int total = 10;\n    return total;\n}\n",
+        )
+        .unwrap();
+
+        let report = compare_report(dir.path(), &[]).await;
+        let changes = report["results"][0]["changes"].as_array().unwrap();
+        let deletion = |name: &str| {
+            changes
+                .iter()
+                .find(|change| {
+                    change["change_type"] == "Delete" && change["source"]["name"] == name
+                })
+                .unwrap_or_else(|| panic!("{} not deleted: {:#?}", name, changes))
+        };
+
+        let checksum = deletion("checksum");
+        assert_eq!(
+            checksum["details"]["metadata"]["deletion_risk"],
+            "feature_removal"
+        );
+        assert_eq!(checksum["details"]["metadata"]["callers"], "main");
+        let legacy = deletion("legacy");
+        assert_eq!(legacy["details"]["metadata"]["deletion_risk"], "dead_code");
+        assert!(legacy["details"]["description"]
+            .as_str()
+            .unwrap()
+            .ends_with("(dead code)"));
+    }
+
+    #[tokio::test]
+    async fn test_compare_archives_end_to_end() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures");
+        let (source, target) = (
+            fixtures.join("release-1.0.tar.gz"),
+            fixtures.join("release-1.1.zip"),
+        );
+        let cli = Cli::parse_from([
+            "smart-diff".as_ref(),
+            "--quiet".as_ref(),
+            "compare".as_ref(),
+            source.as_os_str(),
+            target.as_os_str(),
+        ]);
+
+        let (results, stats) = compare_inputs(compare_options(&cli), None, true, None)
+            .await
+            .unwrap();
+
+        // Entries pair up across formats once the release directory is stripped
+        assert_eq!(stats.files_compared, 2);
+        let result = |entry: &str| {
+            results
+                .iter()
+                .find(|result| result.source_file.ends_with(entry))
+                .unwrap_or_else(|| panic!("{} not compared", entry))
+        };
+        let math = result("src/math.c");
+        assert_eq!(math.target_file, target.join("src/math.c"));
+        assert_eq!(math.diff_result.match_result.changes.len(), 1);
+        assert!(result("src/util.c")
+            .diff_result
+            .match_result
+            .changes
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_added_required_parameter_reports_breaking_call_sites() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, new) = (dir.path().join("old"), dir.path().join("new"));
+        std::fs::create_dir_all(&old).unwrap();
+        std::fs::create_dir_all(&new).unwrap();
+        let scale = |signature: &str| {
+            format!(
+                "int scale({}) {{\n    int result = n;\n    while (result < 100) {{\n        \
+                 result = result * 2;\n    }}\n    return result;\n}}\n",
+                signature
+            )
+        };
+        std::fs::write(old.join("math.c"), scale("int n")).unwrap();
+        std::fs::write(new.join("math.c"), scale("int n, int factor")).unwrap();
+        // The caller lives in another file, which is not changed
+        let main = "int main(void) {\n    int total = scale(4);\n    return total;\n}\n";
+        std::fs::write(old.join("main.c"), main).unwrap();
+        std::fs::write(new.join("main.c"), main).unwrap();
+
+        let cli = Cli::parse_from([
+            "smart-diff".as_ref(),
+            "--quiet".as_ref(),
+            "compare".as_ref(),
+            old.as_os_str(),
+            new.as_os_str(),
+            "--recursive".as_ref(),
+        ]);
+        let (results, _) = compare_inputs(compare_options(&cli), None, true, None)
+            .await
+            .unwrap();
+        let change = results
+            .iter()
+            .flat_map(|result| &result.diff_result.match_result.changes)
+            .find(|change| change.source.as_ref().is_some_and(|f| f.name == "scale"))
+            .expect("scale changed");
+
+        assert_eq!(
+            change.details.metadata["breaking_reasons"],
+            "added required parameter 'factor'"
+        );
+        let call_sites = &change.details.metadata["breaking_call_sites"];
+        assert!(
+            call_sites.ends_with("main.c:2 (main)"),
+            "call sites: {}",
+            call_sites
+        );
+        assert!(change
+            .details
+            .description
+            .ends_with("(may break 1 call site)"));
+    }
+
+    #[tokio::test]
+    async fn test_baselined_changes_do_not_fail_the_gate() {
+        let dir = tempfile::tempdir().unwrap();
+        let write_version = |version: &str, functions: &[&str]| {
+            let root = dir.path().join(version);
+            std::fs::create_dir_all(&root).unwrap();
+            let source: String = functions
+                .iter()
+                .map(|name| format!("int {}(int a, int b) {{\n    return a + b;\n}}\n", name))
+                .collect();
+            std::fs::write(root.join("math.c"), source).unwrap();
+        };
+        write_version("old", &["add"]);
+        write_version("new", &["add", "mul"]);
+
+        let baseline = dir.path().join(".smartdiff").join("baseline.json");
+        let compare = |flag: &str| {
+            Cli::parse_from([
+                "smart-diff".as_ref(),
+                "--quiet".as_ref(),
+                "compare".as_ref(),
+                dir.path().join("old").as_os_str(),
+                dir.path().join("new").as_os_str(),
+                "--output".as_ref(),
+                dir.path().join("report.txt").as_os_str(),
+                "--baseline".as_ref(),
+                baseline.as_os_str(),
+                flag.as_ref(),
+            ])
+        };
+
+        // Without a baseline the existing change trips the gate
+        assert!(run(compare("--fail-on-changes")).await.is_err());
+
+        run(compare("--update-baseline")).await.unwrap();
+        assert!(baseline.exists());
+        run(compare("--fail-on-changes")).await.unwrap();
+
+        // A change made after the baseline was taken still fails
+        write_version("new", &["add", "mul", "div"]);
+        let error = run(compare("--fail-on-changes")).await.unwrap_err();
+        assert!(
+            error.to_string().starts_with("1 changes are not accepted"),
+            "{}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn test_modifications_are_labeled_by_behavior_impact() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, new) = (dir.path().join("old"), dir.path().join("new"));
+        for root in [&old, &new] {
+            std::fs::create_dir(root).unwrap();
+        }
+        std::fs::write(
+            old.join("bounds.py"),
+            "def clamp(value, low):\n    if value < low:\n        return low\n    return clamp(value, low)\n\n\
+             def limit(value, high):\n    if value > high:\n        return high\n    return value\n",
+        )
+        .unwrap();
+        // `clamp` is only renamed, while the condition of `limit` changes
+        std::fs::write(
+            new.join("bounds.py"),
+            "def bound(value, low):\n    if value < low:\n        return low\n    return bound(value, low)\n\n\
+             def limit(value, high):\n    if value > high or value < 0:\n        return high\n    return value\n",
+        )
+        .unwrap();
+
+        let report = compare_report(dir.path(), &[]).await;
+        let changes = report["results"][0]["changes"].as_array().unwrap();
+        let behavior = |name: &str| {
+            &changes
+                .iter()
+                .find(|change| change["target"]["name"] == name)
+                .unwrap_or_else(|| panic!("{} not changed: {:#?}", name, changes))["behavior"]
+        };
+
+        assert_eq!(behavior("bound")["impact"], "behavior_preserving");
+        assert_eq!(behavior("bound")["evidence"], "rename_only");
+        assert_eq!(behavior("limit")["impact"], "behavior_changing");
+    }
+
+    #[tokio::test]
+    async fn test_language_overrides_apply_before_detection() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, new) = (dir.path().join("old"), dir.path().join("new"));
+        for (root, factor) in [(&old, "2"), (&new, "3")] {
+            std::fs::create_dir_all(root.join("include")).unwrap();
+            // A class, which the C grammar cannot parse
+            std::fs::write(
+                root.join("include/widget.h"),
+                format!(
+                    "class Widget {{\npublic:\n    int area() const {{\n        return width * height * {};\n    }}\n\n\
+                     private:\n    int width;\n    int height;\n}};\n",
+                    factor
+                ),
+            )
+            .unwrap();
+            std::fs::write(
+                root.join("config.ts"),
+                format!("export default {{ retries: {} }};\n", factor),
+            )
+            .unwrap();
+        }
+        let config = dir.path().join("smart-diff.toml");
+        std::fs::write(
+            &config,
+            "[languages]\n\"include/*.h\" = \"cpp\"\n\"*.ts\" = \"unknown\"\n",
+        )
+        .unwrap();
+
+        let report = compare_report(
+            dir.path(),
+            &[
+                "--config".as_ref(),
+                config.as_os_str(),
+                "--recursive".as_ref(),
+            ],
+        )
+        .await;
+        // The config file is skipped, and the header parsed as C++
+        let results = report["results"].as_array().unwrap();
+        assert_eq!(results.len(), 1, "{:#?}", results);
+        assert_eq!(results[0]["language"], "C++");
+        let changes = results[0]["changes"].as_array().unwrap();
+        assert!(
+            changes.iter().any(|change| change["target"]["name"]
+                .as_str()
+                .unwrap()
+                .starts_with("area")),
+            "{:#?}",
+            changes
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_order_and_header_date_churn_is_not_a_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, new) = (
+            dir.path().join("Cart_old.java"),
+            dir.path().join("Cart.java"),
+        );
+        let cart = |date: &str, imports: &str| {
+            format!(
+                "// Generated by shopgen on {}\npackage shop;\n\n{}\nclass Cart {{\n    \
+                 int total(int[] prices) {{\n        int sum = 0;\n        \
+                 for (int price : prices) {{\n            sum = sum + price;\n        }}\n        \
+                 return sum;\n    }}\n}}\n",
+                date, imports
+            )
+        };
+        std::fs::write(
+            &old,
+            cart(
+                "2024-01-31 10:15:00",
+                "import java.util.Map;\nimport java.util.List;\n",
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            &new,
+            cart(
+                "2024-02-07 09:00:12",
+                "import java.util.List;\nimport java.util.Map;\n",
+            ),
+        )
+        .unwrap();
+        let config = dir.path().join("smart-diff.toml");
+        std::fs::write(
+            &config,
+            "[normalization]\njava = [\"sort-imports\", \"strip-generated-header-dates\"]\n",
+        )
+        .unwrap();
+
+        let cli = Cli::parse_from([
+            "smart-diff".as_ref(),
+            "compare".as_ref(),
+            old.as_os_str(),
+            new.as_os_str(),
+            "--churn".as_ref(),
+        ]);
+        let options = compare_options(&cli);
+        let compare = |config| async move {
+            compare_inputs(options, config, true, None)
+                .await
+                .unwrap()
+                .0
+                .remove(0)
+        };
+
+        // Without the rules, the reordered imports and the new date are churn
+        let raw = compare(None).await;
+        assert_ne!(raw.churn.unwrap().churn, Churn::default());
+
+        let normalized = compare(Some(&config)).await;
+        assert!(
+            normalized.diff_result.match_result.changes.is_empty(),
+            "{:#?}",
+            normalized.diff_result.match_result.changes
+        );
+        assert_eq!(normalized.diff_result.match_result.similarity, 1.0);
+        assert!(normalized.import_delta.added.is_empty());
+        assert_eq!(normalized.churn.unwrap().churn, Churn::default());
+    }
+
+    #[tokio::test]
+    async fn test_show_similarity_ignores_renamed_locals() {
+        let dir = tempfile::tempdir().unwrap();
+        let worker = |local: &str, callee: &str| {
+            format!(
+                "class Worker {{\n    int run(int[] items) {{\n        int {l} = 0;\n        \
+                 for (int item : items) {{\n            {l} = {l} + {c}(item);\n        }}\n        \
+                 return {l};\n    }}\n}}\n",
+                l = local,
+                c = callee
+            )
+        };
+        let similarity = |local: &str, callee: &str| {
+            let old = dir.path().join("Old.java");
+            let new = dir.path().join(format!("New_{}_{}.java", local, callee));
+            std::fs::write(&old, worker("total", "weight")).unwrap();
+            std::fs::write(&new, worker(local, callee)).unwrap();
+            let cli = Cli::parse_from([
+                "smart-diff".as_ref(),
+                "compare".as_ref(),
+                old.as_os_str(),
+                new.as_os_str(),
+                "--show-similarity".as_ref(),
+            ]);
+            async move {
+                let result = compare_inputs(compare_options(&cli), None, true, None)
+                    .await
+                    .unwrap()
+                    .0
+                    .remove(0);
+                result.similarity_scores.unwrap()["run -> run"]
+            }
+        };
+
+        let unchanged = similarity("total", "weight").await;
+        let renamed = similarity("sum", "weight").await;
+        let call_changed = similarity("total", "price").await;
+        assert!(
+            (renamed - unchanged).abs() < 1e-9,
+            "{} vs {}",
+            renamed,
+            unchanged
+        );
+        assert!(call_changed < renamed, "{} vs {}", call_changed, renamed);
+    }
+
+    /// Serve `body` to a single HTTP request, returning the URL of `name`
+    fn serve_once(name: &str, body: &'static str) -> String {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/files/{}", listener.local_addr().unwrap(), name);
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request).unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_remote_file_is_fetched_and_compared() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("calc.py");
+        std::fs::write(&local, "def total(items):\n    return sum(items)\n").unwrap();
+        let url = serve_once(
+            "calc.py",
+            "def total(items):\n    return sum(items) * 2\n\n\ndef scale(value):\n    return value * 3\n",
+        );
+
+        let cli = Cli::parse_from([
+            "smart-diff",
+            "--quiet",
+            "compare",
+            local.to_str().unwrap(),
+            &url,
+        ]);
+        let (results, _) = compare_inputs(compare_options(&cli), None, true, None)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].source_file, local);
+        assert_eq!(results[0].target_file, PathBuf::from(&url));
+        let changed: HashSet<&str> = results[0]
+            .diff_result
+            .match_result
+            .changes
+            .iter()
+            .filter_map(|change| change.target.as_ref())
+            .map(|element| element.name.as_str())
+            .collect();
+        assert!(changed.contains("scale"), "{:?}", changed);
+    }
+
+    #[tokio::test]
+    async fn test_remote_file_over_size_limit_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("calc.py");
+        std::fs::write(&local, "def total(items):\n    return sum(items)\n").unwrap();
+        let url = serve_once("calc.py", "def total(items):\n    return 0\n");
+
+        let cli = Cli::parse_from([
+            "smart-diff",
+            "--quiet",
+            "compare",
+            local.to_str().unwrap(),
+            &url,
+            "--max-remote-size",
+            "8",
+        ]);
+        let error = compare_inputs(compare_options(&cli), None, true, None)
+            .await
+            .unwrap_err();
+
+        assert!(format!("{:#}", error).contains("limit of 8"), "{:#}", error);
+    }
+
+    #[tokio::test]
+    async fn test_remote_git_ref_is_cloned_and_compared() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str], cwd: &Path| {
+            let status = std::process::Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(cwd)
+                .output()
+                .unwrap();
+            assert!(status.status.success(), "{:?}", status);
+        };
+        let (repo, local) = (dir.path().join("repo"), dir.path().join("local"));
+        for root in [&repo, &local] {
+            std::fs::create_dir(root).unwrap();
+        }
+        std::fs::write(local.join("calc.c"), "int one(void) {\n    return 1;\n}\n").unwrap();
+        std::fs::write(
+            repo.join("calc.c"),
+            "int one(void) {\n    return 1;\n}\n\nint two(void) {\n    return 2;\n}\n",
+        )
+        .unwrap();
+        git(&["init", "--quiet"], &repo);
+        git(&["add", "calc.c"], &repo);
+        git(&["commit", "--quiet", "-m", "release"], &repo);
+        git(&["tag", "v1"], &repo);
+
+        let remote = format!("git+file://{}#v1", repo.display());
+        let cli = Cli::parse_from([
+            "smart-diff",
+            "--quiet",
+            "compare",
+            local.to_str().unwrap(),
+            &remote,
+            "--recursive",
+        ]);
+        let (results, _) = compare_inputs(compare_options(&cli), None, true, None)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].target_file,
+            PathBuf::from(&remote).join("calc.c")
+        );
+        assert!(results[0]
+            .diff_result
+            .match_result
+            .changes
+            .iter()
+            .any(|change| change.change_type == ChangeType::Add));
+    }
+}
@@ -0,0 +1,416 @@
+//! Compare-reports command: what changed between two comparison runs
+//!
+//! Reads two reports written by `compare --format json` or `--format bin` and
+//! lists the breaking changes and refactorings that appeared or disappeared
+//! between them, for tracking trends across successive CI runs. Changes are
+//! correlated by the stable ids the report carries for each change.
+
+use crate::atomic_file::write_atomic;
+use crate::cli::{Cli, Commands, OutputFormat};
+use anyhow::{bail, Context, Result};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use smart_diff_engine::binary_format;
+use smart_diff_parser::{Change, ChangeType, RefactoringType};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub async fn run(cli: Cli) -> Result<()> {
+    if let Some(Commands::CompareReports {
+        ref old,
+        ref new,
+        ref format,
+        ref output,
+    }) = cli.command
+    {
+        let old_report = Report::load(old)?;
+        let new_report = Report::load(new)?;
+        let diff = ReportDiff::between(&old_report, &new_report);
+
+        let content = match format {
+            OutputFormat::Text => diff.to_text(old, new),
+            OutputFormat::Json => serde_json::to_string_pretty(&diff)? + "\n",
+            OutputFormat::JsonCompact => serde_json::to_string(&diff)? + "\n",
+            _ => bail!("compare-reports supports text, json and json-compact output"),
+        };
+
+        match output {
+            Some(path) => write_atomic(path, content.as_bytes())
+                .with_context(|| format!("Failed to write output: {}", path.display()))?,
+            None => print!("{}", content),
+        }
+    }
+
+    Ok(())
+}
+
+/// The parts of a JSON comparison report needed to correlate findings
+#[derive(Debug, Deserialize)]
+struct Report {
+    results: Vec<ReportFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReportFile {
+    target_file: PathBuf,
+    #[serde(default)]
+    changes: Vec<ReportChange>,
+    #[serde(default)]
+    refactoring_patterns: Vec<ReportRefactoring>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReportChange {
+    id: Option<String>,
+    #[serde(flatten)]
+    change: Change,
+}
+
+/// A refactoring at any `--refactoring-detail` level
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReportRefactoring {
+    pattern_type: RefactoringType,
+    confidence: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    affected_elements: Vec<String>,
+}
+
+impl ReportRefactoring {
+    /// Refactorings carry no id, so they are identified by what they describe
+    fn key(&self) -> String {
+        let mut affected = self.affected_elements.clone();
+        affected.sort();
+        format!(
+            "{:?}\0{}\0{}",
+            self.pattern_type,
+            affected.join("\0"),
+            self.description.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+impl Report {
+    fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read(path)
+            .with_context(|| format!("Failed to read report: {}", path.display()))?;
+        let report: Self = binary_format::load(&content).with_context(|| {
+            format!(
+                "Failed to parse report: {} (expected `compare --format json` or `--format bin` output)",
+                path.display()
+            )
+        })?;
+
+        let has_unidentified_changes = report
+            .results
+            .iter()
+            .flat_map(|file| &file.changes)
+            .any(|change| change.id.is_none());
+        if has_unidentified_changes {
+            bail!(
+                "Report {} has changes without ids; regenerate it with this version of smart-diff",
+                path.display()
+            );
+        }
+        Ok(report)
+    }
+
+    /// Breaking changes keyed by id
+    fn breaking_changes(&self) -> HashMap<&str, BreakingChange> {
+        self.results
+            .iter()
+            .flat_map(|file| file.changes.iter().map(move |change| (file, change)))
+            .filter(|(_, change)| is_breaking(&change.change))
+            .filter_map(|(file, change)| {
+                let id = change.id.as_deref()?;
+                Some((
+                    id,
+                    BreakingChange::new(id, &file.target_file, &change.change),
+                ))
+            })
+            .collect()
+    }
+
+    fn refactorings(&self) -> Vec<&ReportRefactoring> {
+        self.results
+            .iter()
+            .flat_map(|file| &file.refactoring_patterns)
+            .collect()
+    }
+}
+
+/// Whether a change can break callers: the function is gone from its old
+/// name or file, or its parameters changed
+fn is_breaking(change: &Change) -> bool {
+    match change.change_type {
+        ChangeType::Delete | ChangeType::Rename | ChangeType::CrossFileMove => true,
+        ChangeType::Modify | ChangeType::Move => {
+            let signature = |element: &Option<smart_diff_parser::CodeElement>| {
+                element.as_ref().and_then(|e| e.signature.clone())
+            };
+            matches!(
+                (signature(&change.source), signature(&change.target)),
+                (Some(before), Some(after)) if before != after
+            )
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct BreakingChange {
+    id: String,
+    change_type: ChangeType,
+    name: String,
+    file: PathBuf,
+    description: String,
+}
+
+impl BreakingChange {
+    fn new(id: &str, file: &Path, change: &Change) -> Self {
+        let name = change
+            .source
+            .as_ref()
+            .or(change.target.as_ref())
+            .map(|element| element.name.clone())
+            .unwrap_or_default();
+        Self {
+            id: id.to_string(),
+            change_type: change.change_type.clone(),
+            name,
+            file: file.to_path_buf(),
+            description: change.details.description.clone(),
+        }
+    }
+}
+
+/// Findings that appeared or disappeared between two reports
+#[derive(Debug, Serialize)]
+struct ReportDiff {
+    new_breaking_changes: Vec<BreakingChange>,
+    resolved_breaking_changes: Vec<BreakingChange>,
+    new_refactorings: Vec<ReportRefactoring>,
+    resolved_refactorings: Vec<ReportRefactoring>,
+}
+
+impl ReportDiff {
+    fn between(old: &Report, new: &Report) -> Self {
+        let (old_breaking, new_breaking) = (old.breaking_changes(), new.breaking_changes());
+        let (old_refactorings, new_refactorings) = (old.refactorings(), new.refactorings());
+        Self {
+            new_breaking_changes: Self::breaking_changes_only_in(&new_breaking, &old_breaking),
+            resolved_breaking_changes: Self::breaking_changes_only_in(&old_breaking, &new_breaking),
+            new_refactorings: Self::refactorings_only_in(&new_refactorings, &old_refactorings),
+            resolved_refactorings: Self::refactorings_only_in(&old_refactorings, &new_refactorings),
+        }
+    }
+
+    /// Breaking changes of `of` whose id is not in `other`, in file order
+    fn breaking_changes_only_in(
+        of: &HashMap<&str, BreakingChange>,
+        other: &HashMap<&str, BreakingChange>,
+    ) -> Vec<BreakingChange> {
+        let mut changes: Vec<BreakingChange> = of
+            .iter()
+            .filter(|(id, _)| !other.contains_key(*id))
+            .map(|(_, change)| change.clone())
+            .collect();
+        changes.sort_by(|a, b| (&a.file, &a.name, &a.id).cmp(&(&b.file, &b.name, &b.id)));
+        changes
+    }
+
+    /// Refactorings of `of` beyond the number of identical ones in `other`
+    fn refactorings_only_in(
+        of: &[&ReportRefactoring],
+        other: &[&ReportRefactoring],
+    ) -> Vec<ReportRefactoring> {
+        let mut remaining: HashMap<String, usize> = HashMap::new();
+        for refactoring in other {
+            *remaining.entry(refactoring.key()).or_insert(0) += 1;
+        }
+
+        of.iter()
+            .filter(|refactoring| match remaining.get_mut(&refactoring.key()) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            })
+            .map(|refactoring| (*refactoring).clone())
+            .collect()
+    }
+
+    fn to_text(&self, old: &Path, new: &Path) -> String {
+        let mut text = format!(
+            "{}\n{} -> {}\n",
+            "Report Comparison".bold().blue(),
+            old.display(),
+            new.display()
+        );
+
+        for (title, changes, marker) in [
+            (
+                "New breaking changes",
+                &self.new_breaking_changes,
+                "+".red(),
+            ),
+            (
+                "Resolved breaking changes",
+                &self.resolved_breaking_changes,
+                "-".green(),
+            ),
+        ] {
+            text.push_str(&format!("\n{} ({})\n", title.bold(), changes.len()));
+            for change in changes {
+                text.push_str(&format!(
+                    "  {} {:?} {} in {} {}\n",
+                    marker,
+                    change.change_type,
+                    change.name,
+                    change.file.display(),
+                    change.id.dimmed()
+                ));
+            }
+        }
+
+        for (title, refactorings) in [
+            ("New refactorings", &self.new_refactorings),
+            ("Resolved refactorings", &self.resolved_refactorings),
+        ] {
+            text.push_str(&format!("\n{} ({})\n", title.bold(), refactorings.len()));
+            for refactoring in refactorings {
+                text.push_str(&format!(
+                    "  {:?} ({:.0}% confidence){}\n",
+                    refactoring.pattern_type,
+                    refactoring.confidence * 100.0,
+                    refactoring
+                        .description
+                        .as_ref()
+                        .map(|description| format!(": {}", description))
+                        .unwrap_or_default()
+                ));
+            }
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn report(changes: &[(&str, &str, &str)]) -> serde_json::Value {
+        let changes: Vec<serde_json::Value> = changes
+            .iter()
+            .map(|(id, change_type, name)| {
+                let element = serde_json::json!({
+                    "id": format!("func_{}", name),
+                    "element_type": "Function",
+                    "name": name,
+                    "file_path": "src/math.c",
+                    "start_line": 1,
+                    "end_line": 3,
+                    "signature": format!("{}(a: int, b: int)", name),
+                    "hash": "",
+                });
+                let (source, target) = match *change_type {
+                    "Add" => (serde_json::Value::Null, element),
+                    "Delete" => (element, serde_json::Value::Null),
+                    _ => (element.clone(), element),
+                };
+                serde_json::json!({
+                    "id": id,
+                    "change_type": change_type,
+                    "source": source,
+                    "target": target,
+                    "details": {
+                        "description": format!("{} {}", change_type, name),
+                        "affected_lines": [],
+                        "similarity_score": null,
+                        "refactoring_type": null,
+                        "metadata": {},
+                    },
+                    "confidence": 1.0,
+                    "diff": null,
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "results": [{
+                "source_file": "old/src/math.c",
+                "target_file": "new/src/math.c",
+                "changes": changes,
+                "refactoring_patterns": [],
+            }]
+        })
+    }
+
+    #[tokio::test]
+    async fn test_new_breaking_change_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, new, output) = (
+            dir.path().join("old.json"),
+            dir.path().join("new.bin"),
+            dir.path().join("diff.json"),
+        );
+        std::fs::write(
+            &old,
+            report(&[("0001", "Modify", "add"), ("0002", "Delete", "sub")]).to_string(),
+        )
+        .unwrap();
+        // Reports in either format can be compared with each other
+        std::fs::write(
+            &new,
+            binary_format::encode(&report(&[
+                ("0001", "Modify", "add"),
+                ("0002", "Delete", "sub"),
+                ("0003", "Delete", "mul"),
+                ("0004", "Add", "div"),
+            ]))
+            .unwrap(),
+        )
+        .unwrap();
+
+        let cli = Cli::parse_from([
+            "smart-diff".as_ref(),
+            "compare-reports".as_ref(),
+            old.as_os_str(),
+            new.as_os_str(),
+            "--format".as_ref(),
+            "json".as_ref(),
+            "--output".as_ref(),
+            output.as_os_str(),
+        ]);
+        run(cli).await.unwrap();
+
+        let diff: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(output).unwrap()).unwrap();
+        let new_breaking = diff["new_breaking_changes"].as_array().unwrap();
+        assert_eq!(new_breaking.len(), 1, "{:#}", diff);
+        assert_eq!(new_breaking[0]["id"], "0003");
+        assert_eq!(new_breaking[0]["name"], "mul");
+        assert!(diff["resolved_breaking_changes"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_reports_without_ids_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("old.json");
+        let mut old = report(&[("0001", "Delete", "sub")]);
+        old["results"][0]["changes"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("id");
+        std::fs::write(&path, old.to_string()).unwrap();
+
+        let error = Report::load(&path).unwrap_err().to_string();
+        assert!(error.contains("without ids"), "{}", error);
+    }
+}
@@ -125,27 +125,28 @@ impl Default for AppConfig {
 }
 
 pub async fn run(cli: Cli) -> Result<()> {
-    if let Commands::Config { action } = cli.command {
+    let no_color = !cli.color_enabled();
+    if let Some(Commands::Config { action }) = cli.command {
         let term = Term::stdout();
 
         match action {
             ConfigAction::Show { section } => {
-                show_configuration(section.as_deref(), &term, cli.no_color).await?;
+                show_configuration(section.as_deref(), &term, no_color).await?;
             }
             ConfigAction::Set { key, value } => {
-                set_configuration(&key, &value, &term, cli.no_color).await?;
+                set_configuration(&key, &value, &term, no_color).await?;
             }
             ConfigAction::Get { key } => {
-                get_configuration(&key, &term, cli.no_color).await?;
+                get_configuration(&key, &term, no_color).await?;
             }
             ConfigAction::Reset { section } => {
-                reset_configuration(section.as_deref(), &term, cli.no_color).await?;
+                reset_configuration(section.as_deref(), &term, no_color).await?;
             }
             ConfigAction::List => {
-                list_configuration_keys(&term, cli.no_color).await?;
+                list_configuration_keys(&term, no_color).await?;
             }
             ConfigAction::Validate => {
-                validate_configuration(&term, cli.no_color).await?;
+                validate_configuration(&term, no_color).await?;
             }
         }
 
@@ -9,7 +9,7 @@ use smart_diff_parser::{tree_sitter::TreeSitterParser, Language, LanguageDetecto
 use smart_diff_semantic::SemanticAnalyzer;
 
 pub async fn run(cli: Cli) -> Result<()> {
-    if let Commands::Doctor { component, fix } = cli.command {
+    if let Some(Commands::Doctor { component, fix }) = cli.command {
         let term = Term::stdout();
 
         if !cli.quiet {
@@ -0,0 +1,43 @@
+//! `--list-languages`: the languages smart-diff can parse
+
+use crate::cli::Cli;
+use anyhow::Result;
+use colored::*;
+use smart_diff_parser::{LanguageDetector, LanguageSupport};
+
+pub async fn run(_cli: Cli) -> Result<()> {
+    print!("{}", format_languages(&LanguageDetector::supported()));
+    Ok(())
+}
+
+/// One line per language: its name, then its extensions
+///
+/// Languages without a bundled grammar are detected but not parsed, which
+/// the line notes.
+fn format_languages(languages: &[LanguageSupport]) -> String {
+    let width = languages
+        .iter()
+        .map(|support| support.name.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut output = String::new();
+    for support in languages {
+        let extensions: Vec<String> = support
+            .extensions
+            .iter()
+            .map(|extension| format!(".{}", extension))
+            .collect();
+        output.push_str(&format!(
+            "{}  {}{}\n",
+            format!("{:width$}", support.name, width = width).bold(),
+            extensions.join(" "),
+            if support.parsable {
+                String::new()
+            } else {
+                format!("  {}", "(detection only)".dimmed())
+            }
+        ));
+    }
+    output
+}
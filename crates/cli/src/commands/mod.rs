@@ -1,6 +1,10 @@
 //! CLI command implementations
 
 pub mod analyze;
+pub mod calibrate;
 pub mod compare;
+pub mod compare_reports;
 pub mod config;
 pub mod doctor;
+pub mod languages;
+pub mod tui;
@@ -0,0 +1,505 @@
+//! Tui command: browse comparison results in the terminal
+//!
+//! Runs the same comparison as `compare`, with the same options, then shows
+//! the changed functions in a list next to a side-by-side diff of the
+//! selected one. The list can be filtered by change type, searched by name
+//! or file and sorted by file order or by how much each function changed;
+//! the detail view adds the change description and the unified diff
+//! `compare` prints in its reports.
+//!
+//! Keys: `↑`/`↓` or `k`/`j` select, `Enter` toggles the detail view, `t`
+//! cycles the change type filter, `s` toggles the sort order, `/` searches,
+//! `PgUp`/`PgDn` scroll the diff and `q` quits.
+
+use crate::cli::{Cli, Commands};
+use crate::commands::compare::compare_inputs;
+use crate::output::{ComparisonResult, OutputFormatter};
+use anyhow::{bail, Result};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use similar::{DiffOp, TextDiff};
+use smart_diff_parser::{Change, ChangeType};
+
+pub async fn run(cli: Cli) -> Result<()> {
+    if let Some(Commands::Tui { ref options }) = cli.command {
+        // The comparison runs exactly as `compare` with the same options would run it
+        let (results, _) = compare_inputs(options, cli.config.as_deref(), true, None).await?;
+
+        let mut app = App::new(&results);
+        if app.entries.is_empty() {
+            bail!("No changed functions to browse");
+        }
+
+        let mut terminal = ratatui::init();
+        let outcome = app.run(&mut terminal);
+        ratatui::restore();
+        outcome?;
+    }
+
+    Ok(())
+}
+
+/// One changed function
+struct Entry {
+    change: Change,
+    file: String,
+    magnitude: f64,
+    source_text: String,
+    target_text: String,
+    unified_diff: Option<String>,
+}
+
+impl Entry {
+    fn name(&self) -> String {
+        match (&self.change.source, &self.change.target) {
+            (Some(source), Some(target)) if source.name != target.name => {
+                format!("{} → {}", source.name, target.name)
+            }
+            (_, Some(element)) | (Some(element), None) => element.name.clone(),
+            (None, None) => String::from("(unnamed)"),
+        }
+    }
+}
+
+/// Order of the change list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortOrder {
+    /// File by file, in the order `compare` reports them
+    File,
+    /// Most changed first
+    Magnitude,
+}
+
+/// State of the result browser
+pub struct App {
+    entries: Vec<Entry>,
+    /// Indices into `entries` of the listed changes, in list order
+    visible: Vec<usize>,
+    list: ListState,
+    /// Change types present, cycled through by the filter
+    change_types: Vec<ChangeType>,
+    filter: Option<ChangeType>,
+    sort: SortOrder,
+    search: String,
+    /// Whether keys edit the search instead of navigating
+    searching: bool,
+    detail: bool,
+    scroll: u16,
+    quit: bool,
+}
+
+impl App {
+    pub fn new(results: &[ComparisonResult]) -> Self {
+        let mut entries = Vec::new();
+        for result in results {
+            for change in &result.diff_result.match_result.changes {
+                let text =
+                    |element: Option<&smart_diff_parser::CodeElement>,
+                     texts: &std::collections::HashMap<String, String>| {
+                        element
+                            .and_then(|element| texts.get(&element.id))
+                            .cloned()
+                            .unwrap_or_default()
+                    };
+                entries.push(Entry {
+                    change: change.clone(),
+                    file: result.target_file.display().to_string(),
+                    magnitude: OutputFormatter::change_magnitude(change),
+                    source_text: text(change.source.as_ref(), &result.source_function_texts),
+                    target_text: text(change.target.as_ref(), &result.target_function_texts),
+                    unified_diff: OutputFormatter::function_diff(result, change),
+                });
+            }
+        }
+
+        let mut change_types: Vec<ChangeType> = Vec::new();
+        for entry in &entries {
+            if !change_types.contains(&entry.change.change_type) {
+                change_types.push(entry.change.change_type.clone());
+            }
+        }
+
+        let mut app = Self {
+            entries,
+            visible: Vec::new(),
+            list: ListState::default(),
+            change_types,
+            filter: None,
+            sort: SortOrder::File,
+            search: String::new(),
+            searching: false,
+            detail: false,
+            scroll: 0,
+            quit: false,
+        };
+        app.refresh();
+        app
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_key(key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Recompute the listed changes after the filter, search or sort changed
+    fn refresh(&mut self) {
+        let selected = self.selected_index();
+        let query = self.search.to_lowercase();
+        self.visible = (0..self.entries.len())
+            .filter(|&i| {
+                let entry = &self.entries[i];
+                self.filter
+                    .as_ref()
+                    .map_or(true, |filter| entry.change.change_type == *filter)
+                    && (query.is_empty()
+                        || entry.name().to_lowercase().contains(&query)
+                        || entry.file.to_lowercase().contains(&query))
+            })
+            .collect();
+        if self.sort == SortOrder::Magnitude {
+            let entries = &self.entries;
+            self.visible
+                .sort_by(|&a, &b| entries[b].magnitude.total_cmp(&entries[a].magnitude));
+        }
+
+        // Keep the same change selected if it is still listed
+        let position = selected
+            .and_then(|selected| self.visible.iter().position(|&i| i == selected))
+            .or((!self.visible.is_empty()).then_some(0));
+        self.list.select(position);
+        self.scroll = 0;
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        self.list
+            .selected()
+            .and_then(|position| self.visible.get(position).copied())
+    }
+
+    fn select(&mut self, offset: isize) {
+        if self.visible.is_empty() {
+            return;
+        }
+        let current = self.list.selected().unwrap_or(0) as isize;
+        let last = self.visible.len() as isize - 1;
+        self.list
+            .select(Some((current + offset).clamp(0, last) as usize));
+        self.scroll = 0;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if self.searching {
+            match key.code {
+                KeyCode::Enter => self.searching = false,
+                KeyCode::Esc => {
+                    self.searching = false;
+                    self.search.clear();
+                    self.refresh();
+                }
+                KeyCode::Backspace => {
+                    self.search.pop();
+                    self.refresh();
+                }
+                KeyCode::Char(c) => {
+                    self.search.push(c);
+                    self.refresh();
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc if self.detail => self.detail = false,
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.select(1),
+            KeyCode::Up | KeyCode::Char('k') => self.select(-1),
+            KeyCode::Home | KeyCode::Char('g') => self.select(isize::MIN / 2),
+            KeyCode::End | KeyCode::Char('G') => self.select(isize::MAX / 2),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::Enter => {
+                self.detail = !self.detail;
+                self.scroll = 0;
+            }
+            KeyCode::Char('/') => self.searching = true,
+            KeyCode::Char('t') => {
+                self.filter = match &self.filter {
+                    None => self.change_types.first().cloned(),
+                    Some(current) => self
+                        .change_types
+                        .iter()
+                        .position(|change_type| change_type == current)
+                        .and_then(|i| self.change_types.get(i + 1))
+                        .cloned(),
+                };
+                self.refresh();
+            }
+            KeyCode::Char('s') => {
+                self.sort = match self.sort {
+                    SortOrder::File => SortOrder::Magnitude,
+                    SortOrder::Magnitude => SortOrder::File,
+                };
+                self.refresh();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, diff_area] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+
+        self.draw_list(frame, list_area);
+        match self.selected_index().map(|i| &self.entries[i]) {
+            Some(entry) if self.detail => draw_detail(frame, diff_area, entry, self.scroll),
+            Some(entry) => draw_side_by_side(frame, diff_area, entry, self.scroll),
+            None => frame.render_widget(
+                Paragraph::new("No change matches the filter")
+                    .block(Block::default().borders(Borders::ALL)),
+                diff_area,
+            ),
+        }
+
+        let status_line = if self.searching {
+            Line::from(format!("/{}", self.search))
+        } else {
+            Line::from(Span::styled(
+                "↑↓ select  Enter detail  t type  s sort  / search  PgUp/PgDn scroll  q quit",
+                Style::default().add_modifier(Modifier::DIM),
+            ))
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+    }
+
+    fn draw_list(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .visible
+            .iter()
+            .map(|&i| {
+                let entry = &self.entries[i];
+                let change_type = &entry.change.change_type;
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:<8}", format!("{:?}", change_type)),
+                        Style::default().fg(change_color(change_type)),
+                    ),
+                    Span::raw(entry.name()),
+                ]))
+            })
+            .collect();
+
+        let mut title = format!(" Changes {}/{} ", self.visible.len(), self.entries.len());
+        if let Some(filter) = &self.filter {
+            title.push_str(&format!("· {:?} ", filter));
+        }
+        if self.sort == SortOrder::Magnitude {
+            title.push_str("· by magnitude ");
+        }
+        if !self.search.is_empty() {
+            title.push_str(&format!("· \"{}\" ", self.search));
+        }
+
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.list);
+    }
+}
+
+fn change_color(change_type: &ChangeType) -> Color {
+    match change_type {
+        ChangeType::Add => Color::Green,
+        ChangeType::Delete => Color::Red,
+        ChangeType::Modify => Color::Yellow,
+        ChangeType::Rename | ChangeType::Move | ChangeType::CrossFileMove => Color::Cyan,
+        ChangeType::Split | ChangeType::Merge => Color::Magenta,
+    }
+}
+
+/// Old and new text of a function, changed lines aligned side by side
+fn draw_side_by_side(frame: &mut Frame, area: Rect, entry: &Entry, scroll: u16) {
+    let diff = TextDiff::from_lines(&entry.source_text, &entry.target_text);
+    let (old, new) = (diff.old_slices(), diff.new_slices());
+    let line = |text: &str, color: Option<Color>| {
+        let text = text.trim_end_matches(['\n', '\r']).to_string();
+        match color {
+            Some(color) => Line::styled(text, Style::default().fg(color)),
+            None => Line::raw(text),
+        }
+    };
+
+    let (mut left, mut right) = (Vec::new(), Vec::new());
+    for op in diff.ops() {
+        let (old_range, new_range) = (op.old_range(), op.new_range());
+        let removed = !matches!(op, DiffOp::Equal { .. });
+        for row in 0..old_range.len().max(new_range.len()) {
+            left.push(match old.get(old_range.start + row) {
+                Some(text) if row < old_range.len() => line(text, removed.then_some(Color::Red)),
+                _ => Line::raw(""),
+            });
+            right.push(match new.get(new_range.start + row) {
+                Some(text) if row < new_range.len() => line(text, removed.then_some(Color::Green)),
+                _ => Line::raw(""),
+            });
+        }
+    }
+
+    let [left_area, right_area] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(area);
+    let pane = |lines: Vec<Line<'static>>, title: String| {
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .scroll((scroll, 0))
+    };
+    let location = |element: Option<&smart_diff_parser::CodeElement>| {
+        element.map_or_else(
+            || String::from(" (none) "),
+            |element| format!(" {}:{} ", element.name, element.start_line),
+        )
+    };
+    frame.render_widget(
+        pane(left, location(entry.change.source.as_ref())),
+        left_area,
+    );
+    frame.render_widget(
+        pane(right, location(entry.change.target.as_ref())),
+        right_area,
+    );
+}
+
+/// Description, scores and unified diff of a change
+fn draw_detail(frame: &mut Frame, area: Rect, entry: &Entry, scroll: u16) {
+    let change = &entry.change;
+    let mut lines = vec![
+        Line::styled(
+            change.details.description.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Line::raw(format!("File: {}", entry.file)),
+        Line::raw(format!(
+            "Type: {:?}   Magnitude: {:.2}   Confidence: {:.2}",
+            change.change_type, entry.magnitude, change.confidence
+        )),
+    ];
+    for (label, element) in [("Before", &change.source), ("After", &change.target)] {
+        if let Some(element) = element {
+            lines.push(Line::raw(format!(
+                "{}: {} (lines {}-{})",
+                label,
+                element.signature.as_deref().unwrap_or(&element.name),
+                element.start_line,
+                element.end_line
+            )));
+        }
+    }
+    lines.push(Line::raw(""));
+
+    for text in entry.unified_diff.as_deref().unwrap_or_default().lines() {
+        let color = match text.chars().next() {
+            Some('+') => Some(Color::Green),
+            Some('-') => Some(Color::Red),
+            Some('@') => Some(Color::Cyan),
+            _ => None,
+        };
+        lines.push(match color {
+            Some(color) => Line::styled(text.to_string(), Style::default().fg(color)),
+            None => Line::raw(text.to_string()),
+        });
+    }
+
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(" Detail "))
+            .wrap(Wrap { trim: false })
+            .scroll((scroll, 0)),
+        area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser as _;
+    use ratatui::backend::TestBackend;
+    use ratatui::crossterm::event::KeyModifiers;
+    use ratatui::Terminal;
+
+    #[tokio::test]
+    async fn test_tui_renders_the_changed_functions_headless() {
+        let dir = tempfile::tempdir().unwrap();
+        for (version, body) in [
+            ("old", "    return a + b;\n"),
+            ("new", "    int sum = a + b;\n    return sum * 2;\n"),
+        ] {
+            let root = dir.path().join(version);
+            std::fs::create_dir_all(&root).unwrap();
+            let extra = if version == "new" {
+                "\nint negate(int a) {\n    return -a;\n}\n"
+            } else {
+                ""
+            };
+            std::fs::write(
+                root.join("math.c"),
+                format!("int add(int a, int b) {{\n{}}}\n{}", body, extra),
+            )
+            .unwrap();
+        }
+
+        let cli = Cli::parse_from([
+            "smart-diff".as_ref(),
+            "tui".as_ref(),
+            dir.path().join("old").as_os_str(),
+            dir.path().join("new").as_os_str(),
+        ]);
+        let Some(Commands::Tui { options }) = &cli.command else {
+            panic!("not a tui command line");
+        };
+        let (results, _) = compare_inputs(options, None, true, None).await.unwrap();
+        let mut app = App::new(&results);
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        let screen = |terminal: &Terminal<TestBackend>| -> String {
+            let buffer = terminal.backend().buffer();
+            buffer
+                .content()
+                .chunks(buffer.area.width as usize)
+                .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let listed = screen(&terminal);
+        assert!(listed.contains("Changes 2/2"), "{}", listed);
+        assert!(listed.contains("add"), "{}", listed);
+        assert!(listed.contains("negate"), "{}", listed);
+        assert!(listed.contains("return sum * 2;"), "{}", listed);
+
+        // Only added functions, then the detail view of the one left
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        while app.filter != Some(ChangeType::Add) {
+            app.handle_key(key(KeyCode::Char('t')));
+        }
+        app.handle_key(key(KeyCode::Enter));
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let detail = screen(&terminal);
+        assert!(detail.contains("Changes 1/2"), "{}", detail);
+        assert!(detail.contains("+    return -a;"), "{}", detail);
+    }
+}
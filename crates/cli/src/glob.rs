@@ -0,0 +1,66 @@
+//! Glob patterns
+//!
+//! Every glob the command line or the `--config` file takes follows the same
+//! rules: `*` and `?` stop at `/`, while `**` crosses directories, and `**/`
+//! matches no directory at all too. Function name globs follow them as well,
+//! names holding no `/`.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::path::Path;
+
+/// Compile a glob to a regular expression matching whole texts
+pub fn to_regex(glob: &str) -> Result<Regex> {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.next_if_eq(&'/').is_some() {
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).with_context(|| format!("Invalid glob '{}'", glob))
+}
+
+/// Whether `glob` matches the end of `path`, starting at a directory
+///
+/// A glob without `/` thus matches the file name in any directory, and
+/// `src/*.c` matches `src/main.c` whether `path` is relative or absolute.
+pub fn matches_path(glob: &str, path: &Path) -> bool {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let glob = glob.strip_prefix("./").unwrap_or(glob);
+    to_regex(&format!("**/{}", glob)).is_ok_and(|regex| regex.is_match(&path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stars_stop_at_directories_unless_doubled() {
+        let matches = |glob, path| matches_path(glob, Path::new(path));
+
+        assert!(matches("*.c", "src/net/socket.c"));
+        assert!(matches("src/*.c", "/home/me/project/src/main.c"));
+        assert!(!matches("src/*.c", "src/net/socket.c"));
+        assert!(matches("src/**/*.c", "src/net/socket.c"));
+        assert!(matches("src/**/*.c", "src/main.c"));
+        assert!(matches("*.generated.*", "api.generated.ts"));
+        assert!(matches("test_?.py", "tests\\test_1.py"));
+        assert!(!matches("main.c", "src/domain.c"));
+
+        let name = to_regex("get*").unwrap();
+        assert!(name.is_match("getName"));
+        assert!(!name.is_match("forget"));
+    }
+}
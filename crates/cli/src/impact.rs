@@ -0,0 +1,64 @@
+//! Change ranking by the importance of the changed function
+//!
+//! Used by `compare --rank-by-impact`. A function's importance is its fan-in,
+//! the number of call sites found in the dependency graph of the compared
+//! files, so a change to a widely called function outranks an equally large
+//! change to a helper that is called once.
+
+use smart_diff_parser::ParseResult;
+use smart_diff_semantic::ComprehensiveDependencyGraphBuilder;
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::warn;
+
+/// Parsed files of both versions, collected while file pairs are compared
+#[derive(Debug, Default)]
+pub struct CallGraphFiles {
+    source: Vec<(String, ParseResult)>,
+    target: Vec<(String, ParseResult)>,
+}
+
+impl CallGraphFiles {
+    /// Add the two parsed versions of a compared file pair
+    pub fn add(
+        &mut self,
+        source_path: &Path,
+        source: ParseResult,
+        target_path: &Path,
+        target: ParseResult,
+    ) {
+        self.source
+            .push((source_path.display().to_string(), source));
+        self.target
+            .push((target_path.display().to_string(), target));
+    }
+
+    /// Fan-in of every function, keyed by name
+    ///
+    /// Calls are counted in the new version of the tree; the old version only
+    /// fills in functions that no longer exist, so deletions are ranked too.
+    pub fn fan_in(self) -> HashMap<String, usize> {
+        let mut fan_in = Self::count_calls(self.source);
+        fan_in.extend(Self::count_calls(self.target));
+        fan_in
+    }
+
+    fn count_calls(files: Vec<(String, ParseResult)>) -> HashMap<String, usize> {
+        let mut builder = ComprehensiveDependencyGraphBuilder::with_defaults();
+        match builder.build_comprehensive_graph(files) {
+            Ok(()) => builder.function_fan_in(),
+            Err(e) => {
+                warn!("Failed to build call graph for impact ranking: {}", e);
+                HashMap::new()
+            }
+        }
+    }
+}
+
+/// Multiplier applied to the magnitude of a change to a function with `fan_in` call sites
+///
+/// Grows logarithmically, so a heavily used function ranks higher without a
+/// trivial change to it outranking a rewrite of everything else.
+pub fn impact_weight(fan_in: usize) -> f64 {
+    1.0 + (fan_in as f64).ln_1p()
+}
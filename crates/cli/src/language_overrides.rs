@@ -0,0 +1,83 @@
+//! Per-path language overrides
+//!
+//! Detection by extension is sometimes wrong: a `.h` header of a C++ project
+//! is detected as C, and a `.ts` file may be a JSON-like config rather than
+//! code. The `[languages]` table of the `--config` file maps path globs to the
+//! language of the files they match, consulted before detection, which fixes
+//! such files without renaming them. An override to `unknown` skips the files
+//! it matches.
+//!
+//! A glob is matched against the path inside the compared tree. `*` and `?`
+//! stop at `/`, while `**` crosses directories. A glob without `/` matches
+//! the file name in any directory. When several globs match, the longest
+//! wins.
+
+use crate::blocklist::parse_language;
+use crate::glob;
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use smart_diff_parser::Language;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// One configured glob and its language
+#[derive(Debug, Clone)]
+struct LanguageOverride {
+    pattern: String,
+    regex: Regex,
+    language: Language,
+}
+
+/// Compiled language overrides, longest glob first
+#[derive(Debug, Clone, Default)]
+pub struct LanguageOverrides {
+    overrides: Vec<LanguageOverride>,
+}
+
+impl LanguageOverrides {
+    /// Compile the `[languages]` table, failing on the first invalid entry
+    pub fn new(config: &BTreeMap<String, String>) -> Result<Self> {
+        let mut overrides = Vec::new();
+        for (pattern, name) in config {
+            // Unknown is no language to detect, only one to skip
+            let language = if name.trim().eq_ignore_ascii_case("unknown") {
+                Language::Unknown
+            } else {
+                parse_language(name)
+                    .with_context(|| format!("Unknown language '{}' for '{}'", name, pattern))?
+            };
+            overrides.push(LanguageOverride {
+                pattern: pattern.clone(),
+                regex: compile(pattern)?,
+                language,
+            });
+        }
+        overrides.sort_by(|a, b| b.pattern.len().cmp(&a.pattern.len()));
+        Ok(Self { overrides })
+    }
+
+    /// Language configured for `path`, inside the compared tree, and the glob
+    /// that configured it
+    pub fn language_of(&self, path: &Path) -> Option<(Language, &str)> {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let path = path.trim_start_matches("./");
+        self.overrides
+            .iter()
+            .find(|entry| entry.regex.is_match(path))
+            .map(|entry| (entry.language, entry.pattern.as_str()))
+    }
+}
+
+/// Compile a path glob to a regular expression matching whole paths
+fn compile(pattern: &str) -> Result<Regex> {
+    if pattern.is_empty() {
+        bail!("Empty language override pattern");
+    }
+    let glob = match pattern.strip_prefix("./") {
+        Some(glob) => glob.to_string(),
+        None if pattern.contains('/') => pattern.to_string(),
+        None => format!("**/{}", pattern),
+    };
+    glob::to_regex(&glob)
+        .with_context(|| format!("Invalid language override pattern '{}'", pattern))
+}
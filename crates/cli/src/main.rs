@@ -6,13 +6,31 @@
 #![allow(clippy::all)]
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use colored::*;
 use tracing_subscriber::{self, EnvFilter};
 
+mod archive;
+mod atomic_file;
+mod baseline;
+mod blocklist;
+mod call_sites;
+mod churn;
 mod cli;
 mod commands;
+mod generated;
+mod glob;
+mod impact;
+mod language_overrides;
+mod mapped_file;
 mod output;
+#[cfg(feature = "profile")]
+mod profile;
+mod redact;
+mod relative_paths;
+mod remote;
+mod review_focus;
+mod test_files;
 
 use cli::{Cli, Commands};
 
@@ -35,12 +53,17 @@ async fn main() -> Result<()> {
         .with_env_filter(EnvFilter::new(format!("smart_diff={}", log_level)))
         .with_target(false)
         .with_level(false)
+        // Keep stdout for the formatted report only
+        .with_writer(std::io::stderr)
         .init();
 
-    // Disable colors if requested
     if cli.no_color {
-        colored::control::set_override(false);
+        tracing::warn!("--no-color is deprecated; use --color=never");
     }
+    let color = cli.color_enabled();
+    colored::control::set_override(color);
+    console::set_colors_enabled(color);
+    console::set_colors_enabled_stderr(color);
 
     // Handle global configuration
     if let Some(config_path) = &cli.config {
@@ -50,10 +73,20 @@ async fn main() -> Result<()> {
 
     // Route to appropriate command handler
     let result = match cli.command {
-        Commands::Compare { .. } => commands::compare::run(cli.clone()).await,
-        Commands::Analyze { .. } => commands::analyze::run(cli.clone()).await,
-        Commands::Config { .. } => commands::config::run(cli.clone()).await,
-        Commands::Doctor { .. } => commands::doctor::run(cli.clone()).await,
+        _ if cli.list_languages => commands::languages::run(cli.clone()).await,
+        Some(Commands::Compare { .. }) => commands::compare::run(cli.clone()).await,
+        Some(Commands::CompareReports { .. }) => commands::compare_reports::run(cli.clone()).await,
+        Some(Commands::Analyze { .. }) => commands::analyze::run(cli.clone()).await,
+        Some(Commands::Calibrate { .. }) => commands::calibrate::run(cli.clone()).await,
+        Some(Commands::Tui { .. }) => commands::tui::run(cli.clone()).await,
+        Some(Commands::Config { .. }) => commands::config::run(cli.clone()).await,
+        Some(Commands::Doctor { .. }) => commands::doctor::run(cli.clone()).await,
+        None => Cli::command()
+            .error(
+                clap::error::ErrorKind::MissingSubcommand,
+                "a subcommand is required unless --list-languages is given",
+            )
+            .exit(),
     };
 
     // Handle errors with appropriate formatting
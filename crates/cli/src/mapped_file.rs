@@ -0,0 +1,84 @@
+//! Memory-mapped reads of large input files
+//!
+//! With `compare --mmap-threshold`, input files at least that large are
+//! memory-mapped and their UTF-8 validated in place instead of being read
+//! through a buffer, which keeps the cost of scanning trees with very large
+//! files down. Smaller files, and files that cannot be mapped on the current
+//! platform or filesystem, are read normally.
+//!
+//! The content is copied out of the mapping as soon as it is validated, so the
+//! file changing afterwards cannot invalidate it. A file truncated or rewritten
+//! while it is being copied is detected by comparing its length and
+//! modification time before and after, and is then read normally instead.
+
+use anyhow::{Context, Result};
+use memmap2::Mmap;
+use std::fs::{self, File, Metadata};
+use std::path::Path;
+use tracing::debug;
+
+/// Read `path` to a string, memory-mapping it when it is at least `threshold` bytes
+pub fn read_to_string(path: &Path, threshold: Option<u64>) -> Result<String> {
+    if let Some(threshold) = threshold {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
+        let metadata = file
+            .metadata()
+            .with_context(|| format!("Failed to read metadata: {}", path.display()))?;
+        if metadata.len() >= threshold {
+            match read_mapped(&file, &metadata) {
+                Ok(Some(content)) => return Ok(content),
+                Ok(None) => debug!("{} changed while mapped, reading it", path.display()),
+                Err(e) => debug!("Failed to map {}, reading it: {}", path.display(), e),
+            }
+        }
+    }
+
+    fs::read_to_string(path).with_context(|| format!("Failed to read file: {}", path.display()))
+}
+
+/// Content of a mapped file, or `None` if the file changed while it was copied
+fn read_mapped(file: &File, before: &Metadata) -> std::io::Result<Option<String>> {
+    // SAFETY: the mapping is only read while it is copied below, and the copy
+    // is discarded if the file was changed meanwhile
+    let map = unsafe { Mmap::map(file)? };
+    let content = std::str::from_utf8(&map)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?
+        .to_owned();
+    drop(map);
+
+    let after = file.metadata()?;
+    let unchanged = after.len() == before.len()
+        && after.modified().ok() == before.modified().ok()
+        && content.len() as u64 == before.len();
+    Ok(unchanged.then_some(content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mapped_read_matches_buffered_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("large.py");
+        let mut content = String::new();
+        for i in 0..20_000 {
+            content.push_str(&format!(
+                "def step_{i}(x):\n    return x + {i}  # étape {i}\n\n"
+            ));
+        }
+        fs::write(&path, &content).unwrap();
+        assert!(content.len() > 1 << 20);
+
+        let mapped = read_to_string(&path, Some(1024)).unwrap();
+        let buffered = read_to_string(&path, None).unwrap();
+        assert_eq!(mapped, buffered);
+        assert_eq!(mapped, content);
+
+        // Invalid UTF-8 fails the same way however the file is read
+        fs::write(&path, [b'a', 0xff, b'b']).unwrap();
+        assert!(read_to_string(&path, Some(0)).is_err());
+        assert!(read_to_string(&path, None).is_err());
+    }
+}
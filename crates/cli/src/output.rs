@@ -1,18 +1,183 @@
 //! Output formatting utilities
 
-use crate::cli::OutputFormat;
-use anyhow::Result;
+use crate::churn::{ChurnSummary, FileChurn};
+use crate::cli::{OutputFormat, RefactoringDetail};
+use crate::impact::impact_weight;
+use crate::review_focus::ChangeRisk;
+use anyhow::{bail, Result};
 use colored::*;
 use serde::Serialize;
 use smart_diff_engine::{
-    DetailedChangeClassification, DiffResult, FunctionMove, RefactoringPattern,
+    binary_format, engine::DiffStatistics, BehaviorAssessment, BehaviorImpact, CloneCluster,
+    CloneType, DetailedChangeClassification, DiffResult, FunctionMove, LiteralChangeKind,
+    LiteralChanges, PreservationEvidence, RefactoringComplexity, RefactoringPattern,
 };
-use smart_diff_parser::{ASTNode, Language};
-use smart_diff_semantic::{DependencyGraph, FunctionComplexityMetrics, SymbolTable};
+use smart_diff_parser::{ASTNode, Change, ChangeType, Language, RefactoringType};
+use smart_diff_semantic::{DependencyGraph, FunctionComplexityMetrics, ImportDelta, SymbolTable};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::time::Duration;
 
+/// Changed functions detailed in markdown output by default
+pub const DEFAULT_MARKDOWN_MAX_FUNCTIONS: usize = 20;
+
+/// Diff lines shown per function in markdown output before cutting it short
+const MARKDOWN_MAX_DIFF_LINES: usize = 40;
+
+/// Matched functions at or above this similarity are not detailed in markdown output
+const SIGNIFICANT_CHANGE_SIMILARITY: f64 = 0.95;
+
+/// Cyclomatic complexity increase at which a changed function is flagged
+pub const LARGE_COMPLEXITY_INCREASE: i64 = 5;
+
+/// Complexity of a matched function before and after the change
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComplexityDelta {
+    /// Cyclomatic complexity of the source version
+    pub complexity_before: usize,
+    /// Cyclomatic complexity of the target version
+    pub complexity_after: usize,
+    /// Change in cyclomatic complexity
+    pub delta: i64,
+    pub cognitive_before: usize,
+    pub cognitive_after: usize,
+    pub cognitive_delta: i64,
+    /// Cyclomatic complexity grew by at least `LARGE_COMPLEXITY_INCREASE`
+    pub large_increase: bool,
+}
+
+impl ComplexityDelta {
+    pub fn between(before: &FunctionComplexityMetrics, after: &FunctionComplexityMetrics) -> Self {
+        let delta = after.cyclomatic_complexity as i64 - before.cyclomatic_complexity as i64;
+        Self {
+            complexity_before: before.cyclomatic_complexity,
+            complexity_after: after.cyclomatic_complexity,
+            delta,
+            cognitive_before: before.cognitive_complexity,
+            cognitive_after: after.cognitive_complexity,
+            cognitive_delta: after.cognitive_complexity as i64 - before.cognitive_complexity as i64,
+            large_increase: delta >= LARGE_COMPLEXITY_INCREASE,
+        }
+    }
+}
+
+/// How similarities, confidences and other scores are written
+///
+/// With neither option set, each format keeps its own style, e.g. `85.3%` in
+/// text and `0.853300` in XML. Setting either one writes every score of every
+/// format the same way: as a fraction with `precision` decimal places, or as
+/// a percentage, with no decimal places unless a precision is given.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScoreFormat {
+    pub precision: Option<usize>,
+    pub as_percent: bool,
+}
+
+impl ScoreFormat {
+    /// A score written by default as a fraction with `places` decimal places
+    fn fraction(self, score: f64, places: usize) -> String {
+        self.render(score, false, places)
+    }
+
+    /// A score written by default as a percentage with `places` decimal places
+    fn percent(self, score: f64, places: usize) -> String {
+        self.render(score, true, places)
+    }
+
+    fn render(self, score: f64, percent: bool, places: usize) -> String {
+        let (percent, places) = match (self.as_percent, self.precision) {
+            (true, precision) => (true, precision.unwrap_or(0)),
+            (false, Some(precision)) => (false, precision),
+            (false, None) => (percent, places),
+        };
+        if percent {
+            format!("{:.*}%", places, score * 100.0)
+        } else {
+            format!("{:.*}", places, score)
+        }
+    }
+
+    /// A score as a JSON number, scaled to a percentage and rounded as requested
+    fn value(self, score: f64) -> f64 {
+        let (score, places) = if self.as_percent {
+            (score * 100.0, Some(self.precision.unwrap_or(0)))
+        } else {
+            (score, self.precision)
+        };
+        match places {
+            Some(places) => {
+                let scale = 10f64.powi(places.min(15) as i32);
+                (score * scale).round() / scale
+            }
+            None => score,
+        }
+    }
+
+    /// Results with every score written as requested, for the JSON formats
+    fn apply<'a>(self, results: &'a [ComparisonResult]) -> Cow<'a, [ComparisonResult]> {
+        if self == Self::default() {
+            return Cow::Borrowed(results);
+        }
+
+        let mut results = results.to_vec();
+        for result in &mut results {
+            result.stats.similarity_score = self.value(result.stats.similarity_score);
+            let diff = &mut result.diff_result;
+            diff.match_result.similarity = self.value(diff.match_result.similarity);
+            diff.statistics.average_similarity = self.value(diff.statistics.average_similarity);
+            for change in &mut diff.match_result.changes {
+                change.confidence = self.value(change.confidence);
+                change.details.similarity_score = change
+                    .details
+                    .similarity_score
+                    .map(|score| self.value(score));
+            }
+            for pattern in diff
+                .refactoring_patterns
+                .iter_mut()
+                .chain(&mut result.refactoring_patterns)
+            {
+                pattern.confidence = self.value(pattern.confidence);
+                for evidence in &mut pattern.evidence {
+                    evidence.strength = self.value(evidence.strength);
+                }
+                for characteristic in &mut pattern.analysis.characteristics {
+                    characteristic.confidence = self.value(characteristic.confidence);
+                }
+                let quality = &mut pattern.analysis.quality_metrics;
+                for metric in [
+                    &mut quality.quality_improvement,
+                    &mut quality.maintainability_impact,
+                    &mut quality.readability_impact,
+                    &mut quality.testability_impact,
+                    &mut quality.performance_impact,
+                ] {
+                    *metric = self.value(*metric);
+                }
+            }
+            for score in result
+                .similarity_scores
+                .iter_mut()
+                .flat_map(HashMap::values_mut)
+            {
+                *score = self.value(*score);
+            }
+            for function_move in &mut result.cross_file_moves {
+                function_move.confidence = self.value(function_move.confidence);
+            }
+        }
+        Cow::Owned(results)
+    }
+}
+
+/// Unchanged functions next to a changed function, by signature in file order
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UnchangedContext {
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
 /// Complete comparison result for a file pair
 #[derive(Debug, Clone)]
 pub struct ComparisonResult {
@@ -26,12 +191,61 @@ pub struct ComparisonResult {
     pub similarity_scores: Option<HashMap<String, f64>>,
     pub cross_file_moves: Vec<FunctionMove>,
     pub stats: ComparisonStats,
+    /// Source text of each source-side function, keyed by code element id
+    pub source_function_texts: HashMap<String, String>,
+    /// Source text of each target-side function, keyed by code element id
+    pub target_function_texts: HashMap<String, String>,
+    /// Imports added, removed or re-aliased between the two versions
+    pub import_delta: ImportDelta,
+    /// Generated or vendored file whose details were collapsed
+    pub generated: bool,
+    /// Call sites of each changed function, filled in by `--rank-by-impact`
+    pub fan_in: HashMap<String, usize>,
+    /// Complexity change of each matched function, keyed by target element id
+    pub complexity_deltas: HashMap<String, ComplexityDelta>,
+    /// Literals added, removed or changed in each matched function, keyed by target element id
+    pub literal_changes: HashMap<String, LiteralChanges>,
+    /// Whether each matched function's modification changed behavior, keyed by target element id
+    pub behavior: HashMap<String, BehaviorAssessment>,
+    /// Unchanged neighbors of each changed function, filled in by `--include-unchanged-context`
+    pub unchanged_context: HashMap<String, UnchangedContext>,
+    /// Review risk of each change, in change order, filled in by `--review-focus`
+    pub change_risks: Vec<ChangeRisk>,
+    /// Stable id of each change, in change order, as used by the baseline file
+    pub finding_ids: Vec<String>,
+    /// Lines and functions touched, filled in by `--churn`
+    pub churn: Option<FileChurn>,
     #[allow(dead_code)]
     pub source_ast: Option<ASTNode>,
     #[allow(dead_code)]
     pub target_ast: Option<ASTNode>,
 }
 
+impl ComparisonResult {
+    /// Keep only the statistics of a generated file, dropping per-change details
+    pub fn collapse_generated(&mut self) {
+        self.generated = true;
+        self.diff_result.match_result.changes.clear();
+        self.diff_result.refactoring_patterns.clear();
+        self.classified_changes.clear();
+        self.refactoring_patterns.clear();
+        self.similarity_scores = None;
+        self.cross_file_moves.clear();
+        self.source_function_texts.clear();
+        self.target_function_texts.clear();
+        self.import_delta = ImportDelta::default();
+        self.fan_in.clear();
+        self.complexity_deltas.clear();
+        self.literal_changes.clear();
+        self.behavior.clear();
+        self.unchanged_context.clear();
+        self.change_risks.clear();
+        self.finding_ids.clear();
+        self.source_ast = None;
+        self.target_ast = None;
+    }
+}
+
 /// Analysis result for a single file
 #[derive(Debug, Clone)]
 pub struct AnalysisResult {
@@ -55,7 +269,9 @@ pub struct ComparisonStats {
     pub refactoring_patterns: usize,
     pub cross_file_moves: usize,
     pub parsing_time: Duration,
+    pub semantic_analysis_time: Duration,
     pub comparison_time: Duration,
+    pub refactoring_detection_time: Duration,
     pub total_time: Duration,
     pub source_lines: usize,
     pub target_lines: usize,
@@ -71,7 +287,9 @@ impl ComparisonStats {
         self.refactoring_patterns += other.refactoring_patterns;
         self.cross_file_moves += other.cross_file_moves;
         self.parsing_time += other.parsing_time;
+        self.semantic_analysis_time += other.semantic_analysis_time;
         self.comparison_time += other.comparison_time;
+        self.refactoring_detection_time += other.refactoring_detection_time;
         self.total_time += other.total_time;
         self.source_lines += other.source_lines;
         self.target_lines += other.target_lines;
@@ -84,6 +302,176 @@ impl ComparisonStats {
                 / self.files_compared as f64;
         }
     }
+
+    /// Wall time spent in each comparison phase, in pipeline order
+    pub fn phase_timings(&self) -> Vec<(&'static str, Duration)> {
+        vec![
+            ("parsing", self.parsing_time),
+            ("semantic_analysis", self.semantic_analysis_time),
+            ("matching", self.comparison_time),
+            ("refactoring_detection", self.refactoring_detection_time),
+        ]
+    }
+
+    /// Functions processed per second of total processing time
+    pub fn functions_per_second(&self) -> f64 {
+        let seconds = self.total_time.as_secs_f64();
+        if seconds > 0.0 {
+            self.functions_compared as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// Build the machine-readable timing report printed by `--stats`
+    pub fn report(&self) -> StatsReport {
+        StatsReport {
+            files_processed: self.files_compared,
+            functions_processed: self.functions_compared,
+            functions_per_second: self.functions_per_second(),
+            total_time_ms: self.total_time.as_secs_f64() * 1000.0,
+            phases: self
+                .phase_timings()
+                .into_iter()
+                .map(|(phase, duration)| PhaseTiming {
+                    phase: phase.to_string(),
+                    duration_ms: duration.as_secs_f64() * 1000.0,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Timing breakdown of a comparison run
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsReport {
+    pub files_processed: usize,
+    pub functions_processed: usize,
+    pub functions_per_second: f64,
+    pub total_time_ms: f64,
+    pub phases: Vec<PhaseTiming>,
+}
+
+/// Wall time spent in a single comparison phase
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseTiming {
+    pub phase: String,
+    pub duration_ms: f64,
+}
+
+/// Comparison results as written by the JSON formats
+#[derive(Debug, Serialize)]
+struct JsonReport<'a> {
+    results: Vec<JsonFileComparison<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<StatsReport>,
+    /// Only present with `--churn`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    churn: Option<ChurnSummary>,
+}
+
+/// A single file comparison in JSON output
+#[derive(Debug, Serialize)]
+struct JsonFileComparison<'a> {
+    source_file: &'a PathBuf,
+    target_file: &'a PathBuf,
+    language: String,
+    similarity: f64,
+    statistics: &'a DiffStatistics,
+    changes: Vec<JsonChange<'a>>,
+    imports: &'a ImportDelta,
+    refactoring_patterns: Vec<JsonRefactoringPattern<'a>>,
+    /// Only present for collapsed generated files
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    generated: bool,
+    /// Only present with `--churn`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    churn: Option<&'a FileChurn>,
+}
+
+/// A change in JSON output, with the unified diff of the function's source
+#[derive(Debug, Serialize)]
+struct JsonChange<'a> {
+    /// Stable id that correlates the change across runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<&'a str>,
+    #[serde(flatten)]
+    change: &'a Change,
+    diff: Option<String>,
+    /// Only present for functions that exist in both versions
+    #[serde(flatten)]
+    complexity: Option<&'a ComplexityDelta>,
+    /// Only present for functions whose literals changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    literals: Option<&'a LiteralChanges>,
+    /// Only present for functions that exist in both versions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    behavior: Option<&'a BehaviorAssessment>,
+    /// Only present with `--include-unchanged-context`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context: Option<&'a UnchangedContext>,
+    /// Only present with `--review-focus`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    risk: Option<&'a ChangeRisk>,
+}
+
+/// A refactoring pattern in JSON output, trimmed to the requested detail level
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum JsonRefactoringPattern<'a> {
+    Minimal {
+        pattern_type: &'a RefactoringType,
+        confidence: f64,
+    },
+    Standard {
+        pattern_type: &'a RefactoringType,
+        confidence: f64,
+        description: &'a str,
+        affected_elements: &'a [String],
+        complexity: &'a RefactoringComplexity,
+    },
+    Full(&'a RefactoringPattern),
+}
+
+impl<'a> JsonRefactoringPattern<'a> {
+    fn new(pattern: &'a RefactoringPattern, detail: RefactoringDetail) -> Self {
+        match detail {
+            RefactoringDetail::Minimal => Self::Minimal {
+                pattern_type: &pattern.pattern_type,
+                confidence: pattern.confidence,
+            },
+            RefactoringDetail::Standard => Self::Standard {
+                pattern_type: &pattern.pattern_type,
+                confidence: pattern.confidence,
+                description: &pattern.description,
+                affected_elements: &pattern.affected_elements,
+                complexity: &pattern.complexity,
+            },
+            RefactoringDetail::Full => Self::Full(pattern),
+        }
+    }
+}
+
+/// A function match in JSON Lines output, flat so it loads straight into a table
+#[derive(Debug, Serialize)]
+struct JsonlMatch<'a> {
+    source_file: &'a PathBuf,
+    target_file: &'a PathBuf,
+    language: String,
+    change_type: &'a ChangeType,
+    source_name: Option<&'a str>,
+    target_name: Option<&'a str>,
+    source_start_line: Option<usize>,
+    source_end_line: Option<usize>,
+    target_start_line: Option<usize>,
+    target_end_line: Option<usize>,
+    similarity: Option<f64>,
+    confidence: f64,
+    file_similarity: f64,
+    refactoring_type: Option<&'a RefactoringType>,
+    complexity_before: Option<usize>,
+    complexity_after: Option<usize>,
+    complexity_delta: Option<i64>,
 }
 
 /// Output formatter for comparison results
@@ -96,18 +484,39 @@ impl OutputFormatter {
         format: &OutputFormat,
         stats: Option<&ComparisonStats>,
         no_color: bool,
+        detail: RefactoringDetail,
+        scores: ScoreFormat,
     ) -> Result<String> {
         match format {
-            OutputFormat::Text => Self::format_text(results, stats, no_color),
-            OutputFormat::Json => Self::format_json(results, stats),
-            OutputFormat::JsonCompact => Self::format_json_compact(results, stats),
-            OutputFormat::Html => Self::format_html(results, stats),
-            OutputFormat::Xml => Self::format_xml(results, stats),
-            OutputFormat::Csv => Self::format_csv(results, stats),
-            OutputFormat::Markdown => Self::format_markdown(results, stats),
+            OutputFormat::Text => Self::format_text(results, stats, no_color, detail, scores),
+            OutputFormat::Json => Self::format_json(&scores.apply(results), stats, detail),
+            OutputFormat::JsonCompact => {
+                Self::format_json_compact(&scores.apply(results), stats, detail)
+            }
+            OutputFormat::Jsonl => Self::format_jsonl(&scores.apply(results)),
+            OutputFormat::Html => Self::format_html(results, stats, detail, scores),
+            OutputFormat::Xml => Self::format_xml(results, stats, detail, scores),
+            OutputFormat::Csv => Self::format_csv(results, stats, scores),
+            OutputFormat::Markdown => Self::format_markdown(
+                results,
+                stats,
+                DEFAULT_MARKDOWN_MAX_FUNCTIONS,
+                detail,
+                scores,
+            ),
+            OutputFormat::Bin => bail!("Binary output is written by format_comparison_binary"),
         }
     }
 
+    /// Encode the JSON report in the binary format of [`binary_format`]
+    pub fn format_comparison_binary(
+        results: &[ComparisonResult],
+        stats: Option<&ComparisonStats>,
+        detail: RefactoringDetail,
+    ) -> Result<Vec<u8>> {
+        binary_format::encode(&Self::json_report(results, stats, detail))
+    }
+
     /// Format analysis results in the specified format
     pub fn format_analysis_results(
         results: &[AnalysisResult],
@@ -117,12 +526,93 @@ impl OutputFormatter {
         match format {
             OutputFormat::Text => Self::format_analysis_text(results, no_color),
             OutputFormat::Json => Self::format_analysis_json(results),
-            OutputFormat::JsonCompact => Self::format_analysis_json_compact(results),
+            OutputFormat::JsonCompact | OutputFormat::Jsonl => {
+                Self::format_analysis_json_compact(results)
+            }
             OutputFormat::Html => Self::format_analysis_html(results),
             OutputFormat::Xml => Self::format_analysis_xml(results),
             OutputFormat::Csv => Self::format_analysis_csv(results),
             OutputFormat::Markdown => Self::format_analysis_markdown(results),
+            OutputFormat::Bin => bail!("Binary output is only supported by compare"),
+        }
+    }
+
+    /// Format clone clusters found by `analyze --duplicates`
+    pub fn format_clone_clusters(
+        clusters: &[CloneCluster],
+        format: &OutputFormat,
+        no_color: bool,
+    ) -> Result<String> {
+        let lines_saved: usize = clusters.iter().map(|c| c.estimated_lines_saved).sum();
+        match format {
+            OutputFormat::Text => Ok(Self::format_clone_clusters_text(
+                clusters,
+                lines_saved,
+                no_color,
+            )),
+            OutputFormat::Json => Ok(serde_json::to_string_pretty(&serde_json::json!({
+                "clusters": clusters,
+                "estimated_lines_saved": lines_saved,
+            }))?),
+            OutputFormat::JsonCompact | OutputFormat::Jsonl => {
+                Ok(serde_json::to_string(&serde_json::json!({
+                    "clusters": clusters,
+                    "estimated_lines_saved": lines_saved,
+                }))?)
+            }
+            _ => bail!("Duplicate reports support text and JSON output"),
+        }
+    }
+
+    fn format_clone_clusters_text(
+        clusters: &[CloneCluster],
+        lines_saved: usize,
+        no_color: bool,
+    ) -> String {
+        let mut output = String::new();
+
+        let header = "Duplicate Code";
+        if no_color {
+            output.push_str(&format!("{}\n{}\n\n", header, "=".repeat(header.len())));
+        } else {
+            output.push_str(&format!(
+                "{}\n{}\n\n",
+                header.bold().blue(),
+                "=".repeat(header.len()).dimmed()
+            ));
+        }
+
+        for (index, cluster) in clusters.iter().enumerate() {
+            let clone_type = match cluster.clone_type {
+                CloneType::Exact => "exact copies",
+                CloneType::Renamed => "renamed copies",
+                CloneType::NearMiss => "near copies",
+            };
+            output.push_str(&format!(
+                "Cluster {}: {} {}, {:.1}% similar\n",
+                index + 1,
+                cluster.instances.len(),
+                clone_type,
+                cluster.similarity * 100.0
+            ));
+            for instance in &cluster.instances {
+                output.push_str(&format!(
+                    "  {}:{}-{} {}\n",
+                    instance.file_path, instance.start_line, instance.end_line, instance.name
+                ));
+            }
+            output.push_str(&format!(
+                "  Extract: {}\n  Estimated lines saved: {}\n\n",
+                cluster.suggested_signature, cluster.estimated_lines_saved
+            ));
         }
+
+        output.push_str(&format!(
+            "Clusters: {}, estimated lines saved: {}\n",
+            clusters.len(),
+            lines_saved
+        ));
+        output
     }
 
     /// Format analysis results as text
@@ -328,6 +818,8 @@ impl OutputFormatter {
         results: &[ComparisonResult],
         stats: Option<&ComparisonStats>,
         no_color: bool,
+        detail: RefactoringDetail,
+        scores: ScoreFormat,
     ) -> Result<String> {
         let mut output = String::new();
 
@@ -371,13 +863,20 @@ impl OutputFormatter {
             // Basic information
             output.push_str(&format!("Language: {:?}\n", result.language));
             output.push_str(&format!(
-                "Similarity: {:.1}%\n",
-                result.stats.similarity_score * 100.0
-            ));
-            output.push_str(&format!(
-                "Changes: {}\n",
-                result.diff_result.match_result.changes.len()
+                "Similarity: {}\n",
+                scores.percent(result.stats.similarity_score, 1)
             ));
+            if result.generated {
+                output.push_str(&format!(
+                    "Changes: {} (generated file, details collapsed)\n",
+                    result.stats.changes_detected
+                ));
+            } else {
+                output.push_str(&format!(
+                    "Changes: {}\n",
+                    result.diff_result.match_result.changes.len()
+                ));
+            }
 
             if !result.refactoring_patterns.is_empty() {
                 output.push_str(&format!(
@@ -406,6 +905,19 @@ impl OutputFormatter {
                 }
 
                 for (i, change) in result.diff_result.match_result.changes.iter().enumerate() {
+                    // Changes in review order are grouped by risk tier
+                    if let Some(risk) = result.change_risks.get(i) {
+                        let previous = i.checked_sub(1).and_then(|p| result.change_risks.get(p));
+                        if previous.map(|previous| previous.tier) != Some(risk.tier) {
+                            let heading = format!("[{} risk]", risk.tier.as_str());
+                            if no_color {
+                                output.push_str(&format!("{}\n", heading));
+                            } else {
+                                output.push_str(&format!("{}\n", heading.bold()));
+                            }
+                        }
+                    }
+
                     let change_desc = format!(
                         "{}. {:?}: {}",
                         i + 1,
@@ -428,6 +940,109 @@ impl OutputFormatter {
                         };
                         output.push_str(&format!("{}\n", colored_desc));
                     }
+
+                    if let Some(complexity) = Self::complexity_delta(result, change) {
+                        let line = format!(
+                            "   Complexity: {} -> {} ({:+}), cognitive {} -> {} ({:+})",
+                            complexity.complexity_before,
+                            complexity.complexity_after,
+                            complexity.delta,
+                            complexity.cognitive_before,
+                            complexity.cognitive_after,
+                            complexity.cognitive_delta
+                        );
+                        match (complexity.large_increase, no_color) {
+                            (false, _) => output.push_str(&format!("{}\n", line)),
+                            (true, true) => {
+                                output.push_str(&format!("{} [large increase]\n", line))
+                            }
+                            (true, false) => output.push_str(&format!(
+                                "{} {}\n",
+                                line,
+                                "[large increase]".red().bold()
+                            )),
+                        }
+                    }
+
+                    if let Some(behavior) = Self::behavior(result, change) {
+                        output.push_str(&format!("   {}\n", Self::behavior_label(behavior)));
+                    }
+
+                    if let Some(literals) = Self::literal_changes(result, change) {
+                        if literals.literal_only {
+                            output.push_str("   Only literals changed\n");
+                        }
+                        for literal in &literals.changes {
+                            let (verb, values) = match literal.kind {
+                                LiteralChangeKind::Added => {
+                                    ("added", literal.new_value.clone().unwrap_or_default())
+                                }
+                                LiteralChangeKind::Removed => {
+                                    ("removed", literal.old_value.clone().unwrap_or_default())
+                                }
+                                LiteralChangeKind::Changed => (
+                                    "changed",
+                                    format!(
+                                        "{} -> {}",
+                                        literal.old_value.as_deref().unwrap_or_default(),
+                                        literal.new_value.as_deref().unwrap_or_default()
+                                    ),
+                                ),
+                            };
+                            output.push_str(&format!(
+                                "   Literal {}: {} (line {})\n",
+                                verb, values, literal.line
+                            ));
+                        }
+                    }
+
+                    if let Some(context) = Self::unchanged_context(result, change) {
+                        if !context.before.is_empty() {
+                            output.push_str(&format!(
+                                "   Preceded by: {}\n",
+                                context.before.join(", ")
+                            ));
+                        }
+                        if !context.after.is_empty() {
+                            output.push_str(&format!(
+                                "   Followed by: {}\n",
+                                context.after.join(", ")
+                            ));
+                        }
+                    }
+                }
+                output.push_str("\n");
+            }
+
+            // Import changes section
+            if !result.import_delta.is_empty() {
+                let imports_header = "Import Changes";
+                if no_color {
+                    output.push_str(&format!(
+                        "{}\n{}\n",
+                        imports_header,
+                        "-".repeat(imports_header.len())
+                    ));
+                } else {
+                    output.push_str(&format!(
+                        "{}\n{}\n",
+                        imports_header.bold(),
+                        "-".repeat(imports_header.len()).dimmed()
+                    ));
+                }
+
+                for (marker, import) in Self::import_delta_lines(&result.import_delta) {
+                    let line = format!("{} {}", marker, import);
+                    if no_color {
+                        output.push_str(&format!("{}\n", line));
+                    } else {
+                        let colored_line = match marker {
+                            '+' => line.green(),
+                            '-' => line.red(),
+                            _ => line.yellow(),
+                        };
+                        output.push_str(&format!("{}\n", colored_line));
+                    }
                 }
                 output.push_str("\n");
             }
@@ -451,24 +1066,30 @@ impl OutputFormatter {
 
                 for (i, pattern) in result.refactoring_patterns.iter().enumerate() {
                     output.push_str(&format!(
-                        "{}. {:?} (confidence: {:.3})\n",
+                        "{}. {:?} (confidence: {})\n",
                         i + 1,
                         pattern.pattern_type,
-                        pattern.confidence
+                        scores.fraction(pattern.confidence, 3)
                     ));
+                    if detail == RefactoringDetail::Minimal {
+                        continue;
+                    }
                     output.push_str(&format!("   Description: {}\n", pattern.description));
                     output.push_str(&format!("   Affected: {:?}\n", pattern.affected_elements));
                     output.push_str(&format!(
                         "   Complexity: {:?}\n",
                         pattern.complexity.complexity_level
                     ));
+                    if detail == RefactoringDetail::Full {
+                        Self::push_text_pattern_evidence(&mut output, pattern, scores);
+                    }
                 }
                 output.push_str("\n");
             }
 
             // Similarity scores section
-            if let Some(ref scores) = result.similarity_scores {
-                if !scores.is_empty() {
+            if let Some(ref similarities) = result.similarity_scores {
+                if !similarities.is_empty() {
                     let similarity_header = "Function Similarities";
                     if no_color {
                         output.push_str(&format!(
@@ -484,8 +1105,12 @@ impl OutputFormatter {
                         ));
                     }
 
-                    for (func_pair, score) in scores {
-                        output.push_str(&format!("{}: {:.3}\n", func_pair, score));
+                    for (func_pair, score) in similarities {
+                        output.push_str(&format!(
+                            "{}: {}\n",
+                            func_pair,
+                            scores.fraction(*score, 3)
+                        ));
                     }
                     output.push_str("\n");
                 }
@@ -510,10 +1135,10 @@ impl OutputFormatter {
 
                 for (i, move_info) in result.cross_file_moves.iter().enumerate() {
                     output.push_str(&format!(
-                        "{}. {} (confidence: {:.3})\n",
+                        "{}. {} (confidence: {})\n",
                         i + 1,
                         format!("{:?}", move_info.move_type), // Use move_type instead of description
-                        move_info.confidence
+                        scores.fraction(move_info.confidence, 3)
                     ));
                 }
                 output.push_str("\n");
@@ -553,8 +1178,8 @@ impl OutputFormatter {
             ));
             output.push_str(&format!("Cross-file moves: {}\n", stats.cross_file_moves));
             output.push_str(&format!(
-                "Average similarity: {:.1}%\n",
-                stats.similarity_score * 100.0
+                "Average similarity: {}\n",
+                scores.percent(stats.similarity_score, 1)
             ));
             output.push_str(&format!(
                 "Total processing time: {}\n",
@@ -565,30 +1190,184 @@ impl OutputFormatter {
         Ok(output)
     }
 
+    /// Evidence, characteristics and quality metrics of a pattern in text output
+    fn push_text_pattern_evidence(
+        output: &mut String,
+        pattern: &RefactoringPattern,
+        scores: ScoreFormat,
+    ) {
+        if !pattern.evidence.is_empty() {
+            output.push_str("   Evidence:\n");
+            for evidence in &pattern.evidence {
+                output.push_str(&format!(
+                    "     - {:?} (strength: {}): {}\n",
+                    evidence.evidence_type,
+                    scores.fraction(evidence.strength, 3),
+                    evidence.description
+                ));
+            }
+        }
+        if !pattern.analysis.characteristics.is_empty() {
+            output.push_str("   Characteristics:\n");
+            for characteristic in &pattern.analysis.characteristics {
+                output.push_str(&format!(
+                    "     - {:?}: {} (confidence: {})\n",
+                    characteristic.characteristic_type,
+                    characteristic.value,
+                    scores.fraction(characteristic.confidence, 3)
+                ));
+            }
+        }
+        let quality = &pattern.analysis.quality_metrics;
+        output.push_str(&format!(
+            "   Quality: improvement {}, maintainability {}, readability {}, testability {}, performance {}\n",
+            scores.fraction(quality.quality_improvement, 2),
+            scores.fraction(quality.maintainability_impact, 2),
+            scores.fraction(quality.readability_impact, 2),
+            scores.fraction(quality.testability_impact, 2),
+            scores.fraction(quality.performance_impact, 2)
+        ));
+    }
+
     /// Format as JSON
     fn format_json(
-        _results: &[ComparisonResult],
-        _stats: Option<&ComparisonStats>,
+        results: &[ComparisonResult],
+        stats: Option<&ComparisonStats>,
+        detail: RefactoringDetail,
     ) -> Result<String> {
-        // JSON serialization disabled due to non-serializable types
-        let _output = "JSON output not yet supported for comparison results";
-
-        Ok("JSON output not yet supported for comparison results".to_string())
+        Ok(serde_json::to_string_pretty(&Self::json_report(
+            results, stats, detail,
+        ))?)
     }
 
     /// Format as compact JSON
     fn format_json_compact(
-        _results: &[ComparisonResult],
-        _stats: Option<&ComparisonStats>,
+        results: &[ComparisonResult],
+        stats: Option<&ComparisonStats>,
+        detail: RefactoringDetail,
     ) -> Result<String> {
-        // JSON serialization disabled due to non-serializable types
-        Ok("JSON output not yet supported for comparison results".to_string())
+        Ok(serde_json::to_string(&Self::json_report(
+            results, stats, detail,
+        ))?)
+    }
+
+    /// Format as JSON Lines, one function match per line
+    ///
+    /// Floats are written in their shortest round-trip form, so no precision is
+    /// lost unless the scores were rounded beforehand.
+    fn format_jsonl(results: &[ComparisonResult]) -> Result<String> {
+        let mut jsonl = String::new();
+
+        for result in results {
+            for change in &result.diff_result.match_result.changes {
+                let (source, target) = (change.source.as_ref(), change.target.as_ref());
+                let complexity = Self::complexity_delta(result, change);
+                let record = JsonlMatch {
+                    source_file: &result.source_file,
+                    target_file: &result.target_file,
+                    language: result.language.to_string(),
+                    change_type: &change.change_type,
+                    source_name: source.map(|e| e.name.as_str()),
+                    target_name: target.map(|e| e.name.as_str()),
+                    source_start_line: source.map(|e| e.start_line),
+                    source_end_line: source.map(|e| e.end_line),
+                    target_start_line: target.map(|e| e.start_line),
+                    target_end_line: target.map(|e| e.end_line),
+                    similarity: change.details.similarity_score,
+                    confidence: change.confidence,
+                    file_similarity: result.diff_result.match_result.similarity,
+                    refactoring_type: change
+                        .details
+                        .refactoring_type
+                        .as_ref()
+                        .or_else(|| Self::refactoring_type_of(result, change)),
+                    complexity_before: complexity.map(|c| c.complexity_before),
+                    complexity_after: complexity.map(|c| c.complexity_after),
+                    complexity_delta: complexity.map(|c| c.delta),
+                };
+                jsonl.push_str(&serde_json::to_string(&record)?);
+                jsonl.push('\n');
+            }
+        }
+
+        Ok(jsonl)
+    }
+
+    /// Type of the first detected refactoring pattern involving a change's elements
+    fn refactoring_type_of<'a>(
+        result: &'a ComparisonResult,
+        change: &Change,
+    ) -> Option<&'a RefactoringType> {
+        let names: Vec<&str> = [&change.source, &change.target]
+            .into_iter()
+            .flatten()
+            .flat_map(|element| [element.id.as_str(), element.name.as_str()])
+            .collect();
+        result
+            .refactoring_patterns
+            .iter()
+            .find(|pattern| {
+                pattern
+                    .affected_elements
+                    .iter()
+                    .any(|element| names.contains(&element.as_str()))
+            })
+            .map(|pattern| &pattern.pattern_type)
+    }
+
+    /// Build the serializable report shared by both JSON formats
+    fn json_report<'a>(
+        results: &'a [ComparisonResult],
+        stats: Option<&ComparisonStats>,
+        detail: RefactoringDetail,
+    ) -> JsonReport<'a> {
+        JsonReport {
+            results: results
+                .iter()
+                .map(|result| JsonFileComparison {
+                    source_file: &result.source_file,
+                    target_file: &result.target_file,
+                    language: result.language.to_string(),
+                    similarity: result.diff_result.match_result.similarity,
+                    statistics: &result.diff_result.statistics,
+                    changes: result
+                        .diff_result
+                        .match_result
+                        .changes
+                        .iter()
+                        .enumerate()
+                        .map(|(index, change)| JsonChange {
+                            id: result.finding_ids.get(index).map(String::as_str),
+                            change,
+                            diff: Self::function_diff(result, change),
+                            complexity: Self::complexity_delta(result, change),
+                            literals: Self::literal_changes(result, change),
+                            behavior: Self::behavior(result, change),
+                            context: Self::unchanged_context(result, change),
+                            risk: result.change_risks.get(index),
+                        })
+                        .collect(),
+                    imports: &result.import_delta,
+                    refactoring_patterns: result
+                        .refactoring_patterns
+                        .iter()
+                        .map(|pattern| JsonRefactoringPattern::new(pattern, detail))
+                        .collect(),
+                    generated: result.generated,
+                    churn: result.churn.as_ref(),
+                })
+                .collect(),
+            stats: stats.map(ComparisonStats::report),
+            churn: ChurnSummary::of(results),
+        }
     }
 
     /// Format as HTML
     fn format_html(
         results: &[ComparisonResult],
         stats: Option<&ComparisonStats>,
+        detail: RefactoringDetail,
+        scores: ScoreFormat,
     ) -> Result<String> {
         let mut html = String::new();
 
@@ -630,7 +1409,10 @@ impl OutputFormatter {
                 "                <div class=\"info-item\"><strong>Language:</strong> {:?}</div>\n",
                 result.language
             ));
-            html.push_str(&format!("                <div class=\"info-item\"><strong>Similarity:</strong> {:.1}%</div>\n", result.stats.similarity_score * 100.0));
+            html.push_str(&format!(
+                "                <div class=\"info-item\"><strong>Similarity:</strong> {}</div>\n",
+                scores.percent(result.stats.similarity_score, 1)
+            ));
             html.push_str(&format!(
                 "                <div class=\"info-item\"><strong>Changes:</strong> {}</div>\n",
                 result.diff_result.match_result.changes.len()
@@ -686,11 +1468,25 @@ impl OutputFormatter {
                         "                        <div class=\"pattern-type\">{:?}</div>\n",
                         pattern.pattern_type
                     ));
-                    html.push_str(&format!("                        <div class=\"pattern-confidence\">Confidence: {:.1}%</div>\n", pattern.confidence * 100.0));
-                    html.push_str(&format!(
-                        "                        <div class=\"pattern-desc\">{}</div>\n",
-                        html_escape(&pattern.description)
-                    ));
+                    html.push_str(&format!("                        <div class=\"pattern-confidence\">Confidence: {}</div>\n", scores.percent(pattern.confidence, 1)));
+                    if detail != RefactoringDetail::Minimal {
+                        html.push_str(&format!(
+                            "                        <div class=\"pattern-desc\">{}</div>\n",
+                            html_escape(&pattern.description)
+                        ));
+                    }
+                    if detail == RefactoringDetail::Full && !pattern.evidence.is_empty() {
+                        html.push_str("                        <ul class=\"pattern-evidence\">\n");
+                        for evidence in &pattern.evidence {
+                            html.push_str(&format!(
+                                "                            <li>{:?} ({}): {}</li>\n",
+                                evidence.evidence_type,
+                                scores.percent(evidence.strength, 1),
+                                html_escape(&evidence.description)
+                            ));
+                        }
+                        html.push_str("                        </ul>\n");
+                    }
                     html.push_str("                    </div>\n");
                 }
 
@@ -722,7 +1518,10 @@ impl OutputFormatter {
                 "                <div class=\"stat-item\"><strong>Patterns:</strong> {}</div>\n",
                 stats.refactoring_patterns
             ));
-            html.push_str(&format!("                <div class=\"stat-item\"><strong>Similarity:</strong> {:.1}%</div>\n", stats.similarity_score * 100.0));
+            html.push_str(&format!(
+                "                <div class=\"stat-item\"><strong>Similarity:</strong> {}</div>\n",
+                scores.percent(stats.similarity_score, 1)
+            ));
             html.push_str(&format!(
                 "                <div class=\"stat-item\"><strong>Time:</strong> {}</div>\n",
                 Self::format_duration(stats.total_time)
@@ -738,7 +1537,12 @@ impl OutputFormatter {
     }
 
     /// Format as XML
-    fn format_xml(results: &[ComparisonResult], stats: Option<&ComparisonStats>) -> Result<String> {
+    fn format_xml(
+        results: &[ComparisonResult],
+        stats: Option<&ComparisonStats>,
+        detail: RefactoringDetail,
+        scores: ScoreFormat,
+    ) -> Result<String> {
         let mut xml = String::new();
 
         xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
@@ -770,8 +1574,8 @@ impl OutputFormatter {
                 result.language
             ));
             xml.push_str(&format!(
-                "      <similarity>{:.6}</similarity>\n",
-                result.stats.similarity_score
+                "      <similarity>{}</similarity>\n",
+                scores.fraction(result.stats.similarity_score, 6)
             ));
 
             // Changes
@@ -787,8 +1591,8 @@ impl OutputFormatter {
                     xml_escape(&change.details.description)
                 ));
                 xml.push_str(&format!(
-                    "          <confidence>{:.6}</confidence>\n",
-                    change.confidence
+                    "          <confidence>{}</confidence>\n",
+                    scores.fraction(change.confidence, 6)
                 ));
                 xml.push_str("        </change>\n");
             }
@@ -804,13 +1608,25 @@ impl OutputFormatter {
                         pattern.pattern_type
                     ));
                     xml.push_str(&format!(
-                        "          <confidence>{:.6}</confidence>\n",
-                        pattern.confidence
-                    ));
-                    xml.push_str(&format!(
-                        "          <description>{}</description>\n",
-                        xml_escape(&pattern.description)
+                        "          <confidence>{}</confidence>\n",
+                        scores.fraction(pattern.confidence, 6)
                     ));
+                    if detail != RefactoringDetail::Minimal {
+                        xml.push_str(&format!(
+                            "          <description>{}</description>\n",
+                            xml_escape(&pattern.description)
+                        ));
+                    }
+                    if detail == RefactoringDetail::Full {
+                        for evidence in &pattern.evidence {
+                            xml.push_str(&format!(
+                                "          <evidence type=\"{:?}\" strength=\"{}\">{}</evidence>\n",
+                                evidence.evidence_type,
+                                scores.fraction(evidence.strength, 6),
+                                xml_escape(&evidence.description)
+                            ));
+                        }
+                    }
                     xml.push_str("        </pattern>\n");
                 }
                 xml.push_str("      </refactoring-patterns>\n");
@@ -840,8 +1656,8 @@ impl OutputFormatter {
                 stats.refactoring_patterns
             ));
             xml.push_str(&format!(
-                "    <similarity-score>{:.6}</similarity-score>\n",
-                stats.similarity_score
+                "    <similarity-score>{}</similarity-score>\n",
+                scores.fraction(stats.similarity_score, 6)
             ));
             xml.push_str(&format!(
                 "    <total-time-ms>{}</total-time-ms>\n",
@@ -859,6 +1675,7 @@ impl OutputFormatter {
     fn format_csv(
         results: &[ComparisonResult],
         _stats: Option<&ComparisonStats>,
+        scores: ScoreFormat,
     ) -> Result<String> {
         let mut csv = String::new();
 
@@ -868,11 +1685,11 @@ impl OutputFormatter {
         // Data rows
         for result in results {
             csv.push_str(&format!(
-                "{},{},{:?},{:.6},{},{},{}\n",
+                "{},{},{:?},{},{},{},{}\n",
                 csv_escape(&result.source_file.to_string_lossy()),
                 csv_escape(&result.target_file.to_string_lossy()),
                 result.language,
-                result.stats.similarity_score,
+                scores.fraction(result.stats.similarity_score, 6),
                 result.diff_result.match_result.changes.len(),
                 result.refactoring_patterns.len(),
                 result.stats.total_time.as_millis()
@@ -882,118 +1699,161 @@ impl OutputFormatter {
         Ok(csv)
     }
 
-    /// Format as Markdown
-    fn format_markdown(
+    /// Format comparison results as GitHub-flavored markdown for PR comments
+    ///
+    /// Renders a table of change counts, a collapsible diff for each of the
+    /// `max_functions` most changed functions and a list of detected refactorings.
+    pub fn format_markdown(
         results: &[ComparisonResult],
         stats: Option<&ComparisonStats>,
+        max_functions: usize,
+        detail: RefactoringDetail,
+        scores: ScoreFormat,
     ) -> Result<String> {
-        let mut md = String::new();
+        let changes: Vec<(&ComparisonResult, &Change)> = results
+            .iter()
+            .flat_map(|result| {
+                result
+                    .diff_result
+                    .match_result
+                    .changes
+                    .iter()
+                    .map(move |change| (result, change))
+            })
+            .collect();
+        let count = |types: &[ChangeType]| {
+            changes
+                .iter()
+                .filter(|(_, change)| types.contains(&change.change_type))
+                .count()
+        };
 
-        // Title
-        md.push_str("# Smart Code Diff Results\n\n");
-
-        // Process each comparison
-        for (index, result) in results.iter().enumerate() {
-            if results.len() > 1 {
+        let mut md = String::new();
+        md.push_str("## Smart Diff Summary\n\n");
+
+        md.push_str("| Change | Count |\n");
+        md.push_str("|--------|------:|\n");
+        md.push_str(&format!("| Added | {} |\n", count(&[ChangeType::Add])));
+        md.push_str(&format!("| Deleted | {} |\n", count(&[ChangeType::Delete])));
+        md.push_str(&format!(
+            "| Modified | {} |\n",
+            count(&[ChangeType::Modify])
+        ));
+        md.push_str(&format!("| Renamed | {} |\n", count(&[ChangeType::Rename])));
+        md.push_str(&format!(
+            "| Moved | {} |\n",
+            count(&[ChangeType::Move, ChangeType::CrossFileMove])
+        ));
+        md.push_str(&format!(
+            "| Split / Merged | {} |\n",
+            count(&[ChangeType::Split, ChangeType::Merge])
+        ));
+        md.push_str(&format!("| **Total** | **{}** |\n\n", changes.len()));
+
+        // Most changed functions first
+        let mut significant: Vec<&(&ComparisonResult, &Change)> = changes
+            .iter()
+            .filter(|(_, change)| Self::is_significant_change(change))
+            .collect();
+        significant.sort_by(|a, b| {
+            Self::ranked_change_magnitude(b.0, b.1)
+                .partial_cmp(&Self::ranked_change_magnitude(a.0, a.1))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        if !significant.is_empty() {
+            md.push_str("### Changed Functions\n\n");
+            for (result, change) in significant.iter().take(max_functions) {
+                Self::push_markdown_function(&mut md, result, change, scores);
+            }
+            if significant.len() > max_functions {
                 md.push_str(&format!(
-                    "## Comparison {}: {} → {}\n\n",
-                    index + 1,
-                    result.source_file.display(),
-                    result.target_file.display()
+                    "_…and {} more changed functions_\n\n",
+                    significant.len() - max_functions
                 ));
             }
+        }
 
-            // Basic information
-            md.push_str("### Overview\n\n");
-            md.push_str(&format!("- **Language**: {:?}\n", result.language));
-            md.push_str(&format!(
-                "- **Similarity**: {:.1}%\n",
-                result.stats.similarity_score * 100.0
-            ));
-            md.push_str(&format!(
-                "- **Changes**: {}\n",
-                result.diff_result.match_result.changes.len()
-            ));
-            md.push_str(&format!(
-                "- **Processing Time**: {}\n\n",
-                Self::format_duration(result.stats.total_time)
-            ));
-
-            // Changes
-            if !result.diff_result.match_result.changes.is_empty() {
-                md.push_str("### Changes Detected\n\n");
-                for (i, change) in result.diff_result.match_result.changes.iter().enumerate() {
-                    let emoji = match change.change_type {
-                        smart_diff_parser::ChangeType::Add => "➕",
-                        smart_diff_parser::ChangeType::Delete => "➖",
-                        smart_diff_parser::ChangeType::Modify => "✏️",
-                        smart_diff_parser::ChangeType::Rename => "🏷️",
-                        smart_diff_parser::ChangeType::Move => "📦",
-                        smart_diff_parser::ChangeType::CrossFileMove => "🔄",
-                        smart_diff_parser::ChangeType::Split => "🔀",
-                        smart_diff_parser::ChangeType::Merge => "🔗",
+        let import_changes: Vec<&ComparisonResult> = results
+            .iter()
+            .filter(|result| !result.import_delta.is_empty())
+            .collect();
+        if !import_changes.is_empty() {
+            md.push_str("### Import Changes\n\n");
+            for result in import_changes {
+                md.push_str(&format!("**{}**\n\n", result.target_file.display()));
+                for (marker, import) in Self::import_delta_lines(&result.import_delta) {
+                    let verb = match marker {
+                        '+' => "Added",
+                        '-' => "Removed",
+                        _ => "Changed",
                     };
-
-                    md.push_str(&format!(
-                        "{}. {} **{:?}**: {}\n",
-                        i + 1,
-                        emoji,
-                        change.change_type,
-                        change.details.description
-                    ));
+                    md.push_str(&format!("- {} `{}`\n", verb, import));
                 }
-                md.push_str("\n");
+                md.push('\n');
             }
+        }
 
-            // Refactoring patterns
-            if !result.refactoring_patterns.is_empty() {
-                md.push_str("### Refactoring Patterns\n\n");
-                for (i, pattern) in result.refactoring_patterns.iter().enumerate() {
+        let generated: Vec<&ComparisonResult> =
+            results.iter().filter(|result| result.generated).collect();
+        if !generated.is_empty() {
+            md.push_str("### Generated Files\n\n");
+            for result in generated {
+                md.push_str(&format!(
+                    "- `{}`: {} changes (collapsed)\n",
+                    result.target_file.display(),
+                    result.stats.changes_detected
+                ));
+            }
+            md.push('\n');
+        }
+
+        let patterns: Vec<&RefactoringPattern> = results
+            .iter()
+            .flat_map(|result| result.refactoring_patterns.iter())
+            .collect();
+        if !patterns.is_empty() {
+            md.push_str("### Detected Refactorings\n\n");
+            for pattern in patterns.iter().take(max_functions) {
+                if detail == RefactoringDetail::Minimal {
                     md.push_str(&format!(
-                        "{}. **{:?}** (confidence: {:.1}%)\n",
-                        i + 1,
+                        "- **{:?}** ({})\n",
                         pattern.pattern_type,
-                        pattern.confidence * 100.0
-                    ));
-                    md.push_str(&format!("   - {}\n", pattern.description));
-                    md.push_str(&format!(
-                        "   - Affected: {}\n",
-                        pattern.affected_elements.join(", ")
-                    ));
-                    md.push_str(&format!(
-                        "   - Complexity: {:?}\n",
-                        pattern.complexity.complexity_level
+                        scores.percent(pattern.confidence, 0)
                     ));
+                    continue;
+                }
+                md.push_str(&format!(
+                    "- **{:?}** ({}): {}\n",
+                    pattern.pattern_type,
+                    scores.percent(pattern.confidence, 0),
+                    pattern.description
+                ));
+                if detail == RefactoringDetail::Full {
+                    for evidence in &pattern.evidence {
+                        md.push_str(&format!(
+                            "  - {:?} ({}): {}\n",
+                            evidence.evidence_type,
+                            scores.percent(evidence.strength, 0),
+                            evidence.description
+                        ));
+                    }
                 }
-                md.push_str("\n");
             }
-
-            if results.len() > 1 && index < results.len() - 1 {
-                md.push_str("---\n\n");
+            if patterns.len() > max_functions {
+                md.push_str(&format!(
+                    "- _…and {} more_\n",
+                    patterns.len() - max_functions
+                ));
             }
+            md.push('\n');
         }
 
-        // Statistics
         if let Some(stats) = stats {
-            md.push_str("## Overall Statistics\n\n");
-            md.push_str("| Metric | Value |\n");
-            md.push_str("|--------|-------|\n");
-            md.push_str(&format!("| Files Compared | {} |\n", stats.files_compared));
-            md.push_str(&format!(
-                "| Functions Analyzed | {} |\n",
-                stats.functions_compared
-            ));
-            md.push_str(&format!("| Total Changes | {} |\n", stats.changes_detected));
-            md.push_str(&format!(
-                "| Refactoring Patterns | {} |\n",
-                stats.refactoring_patterns
-            ));
-            md.push_str(&format!(
-                "| Average Similarity | {:.1}% |\n",
-                stats.similarity_score * 100.0
-            ));
             md.push_str(&format!(
-                "| Total Processing Time | {} |\n",
+                "<sub>{} files, {} functions compared in {}</sub>\n",
+                stats.files_compared,
+                stats.functions_compared,
                 Self::format_duration(stats.total_time)
             ));
         }
@@ -1001,6 +1861,214 @@ impl OutputFormatter {
         Ok(md)
     }
 
+    /// One line per import change: `+` added, `-` removed, `~` alias changed
+    fn import_delta_lines(delta: &ImportDelta) -> Vec<(char, String)> {
+        let mut lines = Vec::new();
+        lines.extend(delta.added.iter().map(|import| ('+', import.to_string())));
+        lines.extend(delta.removed.iter().map(|import| ('-', import.to_string())));
+        lines.extend(
+            delta
+                .changed
+                .iter()
+                .map(|change| ('~', format!("{} -> {}", change.source, change.target))),
+        );
+        lines
+    }
+
+    /// Whether a change deserves its own diff section in markdown output
+    fn is_significant_change(change: &Change) -> bool {
+        match change.change_type {
+            ChangeType::Add | ChangeType::Delete | ChangeType::Rename => true,
+            _ => {
+                change.details.similarity_score.unwrap_or(change.confidence)
+                    < SIGNIFICANT_CHANGE_SIMILARITY
+            }
+        }
+    }
+
+    /// How much a change altered its function (0.0 = unchanged, 1.0 = entirely new)
+    pub fn change_magnitude(change: &Change) -> f64 {
+        match change.change_type {
+            ChangeType::Add | ChangeType::Delete => 1.0,
+            _ => 1.0 - change.details.similarity_score.unwrap_or(change.confidence),
+        }
+    }
+
+    /// Change magnitude weighted by the fan-in of the changed function, if known
+    pub fn ranked_change_magnitude(result: &ComparisonResult, change: &Change) -> f64 {
+        let fan_in = change
+            .target
+            .as_ref()
+            .or(change.source.as_ref())
+            .and_then(|element| result.fan_in.get(&element.name))
+            .copied()
+            .unwrap_or(0);
+        Self::change_magnitude(change) * impact_weight(fan_in)
+    }
+
+    /// Append a collapsible section with the diff of a single changed function
+    fn push_markdown_function(
+        md: &mut String,
+        result: &ComparisonResult,
+        change: &Change,
+        scores: ScoreFormat,
+    ) {
+        let name = match (&change.source, &change.target) {
+            (Some(source), Some(target)) if source.name != target.name => {
+                format!("{} → {}", source.name, target.name)
+            }
+            (_, Some(element)) | (Some(element), None) => element.name.clone(),
+            (None, None) => return,
+        };
+        let file_path = match change.target.as_ref().or(change.source.as_ref()) {
+            Some(element) if !element.file_path.is_empty() => element.file_path.clone(),
+            _ if change.target.is_some() => result.target_file.display().to_string(),
+            _ => result.source_file.display().to_string(),
+        };
+        let similarity = match change.change_type {
+            ChangeType::Add | ChangeType::Delete => String::new(),
+            _ => format!(
+                " · {} similar",
+                scores.percent(
+                    change.details.similarity_score.unwrap_or(change.confidence),
+                    0
+                )
+            ),
+        };
+        let complexity = match Self::complexity_delta(result, change) {
+            Some(complexity) if complexity.delta != 0 => format!(
+                " · complexity {} → {} ({:+}){}",
+                complexity.complexity_before,
+                complexity.complexity_after,
+                complexity.delta,
+                if complexity.large_increase {
+                    " ⚠️"
+                } else {
+                    ""
+                }
+            ),
+            _ => String::new(),
+        };
+        let literals = match Self::literal_changes(result, change) {
+            Some(literals) if literals.literal_only => " · literals only",
+            _ => "",
+        };
+
+        md.push_str("<details>\n");
+        md.push_str(&format!(
+            "<summary><code>{}</code> · {:?}{}{}{} · <code>{}</code></summary>\n\n",
+            html_escape(&name),
+            change.change_type,
+            similarity,
+            complexity,
+            literals,
+            html_escape(&file_path)
+        ));
+
+        let diff = Self::function_diff(result, change).unwrap_or_default();
+        let diff_lines: Vec<&str> = diff.lines().collect();
+
+        let shown = &diff_lines[..diff_lines.len().min(MARKDOWN_MAX_DIFF_LINES)];
+        let fence = code_fence(shown);
+
+        md.push_str(&format!("{}diff\n", fence));
+        for line in shown {
+            md.push_str(line);
+            md.push('\n');
+        }
+        if diff_lines.len() > MARKDOWN_MAX_DIFF_LINES {
+            md.push_str(&format!(
+                "... {} more lines\n",
+                diff_lines.len() - MARKDOWN_MAX_DIFF_LINES
+            ));
+        }
+        md.push_str(&format!("{}\n\n</details>\n\n", fence));
+    }
+
+    /// Complexity change of a function that exists in both versions
+    fn complexity_delta<'a>(
+        result: &'a ComparisonResult,
+        change: &Change,
+    ) -> Option<&'a ComplexityDelta> {
+        change.source.as_ref()?;
+        result.complexity_deltas.get(&change.target.as_ref()?.id)
+    }
+
+    /// Literal changes of a function that exists in both versions
+    fn literal_changes<'a>(
+        result: &'a ComparisonResult,
+        change: &Change,
+    ) -> Option<&'a LiteralChanges> {
+        change.source.as_ref()?;
+        result.literal_changes.get(&change.target.as_ref()?.id)
+    }
+
+    /// Behavior impact of a function that exists in both versions
+    fn behavior<'a>(
+        result: &'a ComparisonResult,
+        change: &Change,
+    ) -> Option<&'a BehaviorAssessment> {
+        change.source.as_ref()?;
+        result.behavior.get(&change.target.as_ref()?.id)
+    }
+
+    /// e.g. `Behavior preserving (formatting only, 99% confidence)`
+    fn behavior_label(behavior: &BehaviorAssessment) -> String {
+        let impact = match behavior.impact {
+            BehaviorImpact::BehaviorPreserving => "Behavior preserving",
+            BehaviorImpact::BehaviorChanging => "Behavior changing",
+        };
+        let evidence = match behavior.evidence {
+            Some(PreservationEvidence::FormattingOnly) => "formatting only, ",
+            Some(PreservationEvidence::DocumentationOnly) => "documentation only, ",
+            Some(PreservationEvidence::RenameOnly) => "rename only, ",
+            None => "",
+        };
+        format!(
+            "{} ({}{:.0}% confidence)",
+            impact,
+            evidence,
+            behavior.confidence * 100.0
+        )
+    }
+
+    /// Unchanged neighbors of a changed function
+    fn unchanged_context<'a>(
+        result: &'a ComparisonResult,
+        change: &Change,
+    ) -> Option<&'a UnchangedContext> {
+        let element = change.target.as_ref().or(change.source.as_ref())?;
+        result.unchanged_context.get(&element.id)
+    }
+
+    /// Unified diff of a changed function's source, if its text is known
+    pub fn function_diff(result: &ComparisonResult, change: &Change) -> Option<String> {
+        let text_of = |element: Option<&smart_diff_parser::CodeElement>,
+                       texts: &HashMap<String, String>| {
+            let mut text = element
+                .and_then(|e| texts.get(&e.id))
+                .cloned()
+                .unwrap_or_default();
+            // Function text stops at the closing brace; avoid "no newline" markers
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text
+        };
+        let source_text = text_of(change.source.as_ref(), &result.source_function_texts);
+        let target_text = text_of(change.target.as_ref(), &result.target_function_texts);
+        if source_text.is_empty() && target_text.is_empty() {
+            return None;
+        }
+
+        Some(
+            similar::TextDiff::from_lines(&source_text, &target_text)
+                .unified_diff()
+                .context_radius(2)
+                .to_string(),
+        )
+    }
+
     /// Format duration for display
     fn format_duration(duration: Duration) -> String {
         let total_ms = duration.as_millis();
@@ -1019,6 +2087,18 @@ impl OutputFormatter {
 
 // Utility functions for escaping
 
+/// Backtick fence longer than any run of backticks in `lines`, so code that
+/// holds a fence of its own cannot close the block early
+fn code_fence(lines: &[&str]) -> String {
+    let longest_run = lines
+        .iter()
+        .flat_map(|line| line.split(|c| c != '`'))
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest_run.max(2) + 1)
+}
+
 fn html_escape(text: &str) -> String {
     text.replace('&', "&amp;")
         .replace('<', "&lt;")
@@ -1042,3 +2122,559 @@ fn csv_escape(text: &str) -> String {
         text.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smart_diff_engine::engine::DiffStatistics;
+    use smart_diff_engine::{RefactoringEvidence, RefactoringEvidenceType};
+    use smart_diff_parser::{CodeElement, ElementType, MatchResult};
+
+    fn code_element(name: &str, version: &str) -> CodeElement {
+        CodeElement {
+            id: format!("func_{}_{}", name, version),
+            element_type: ElementType::Function,
+            name: name.to_string(),
+            file_path: "src/lib.c".to_string(),
+            start_line: 1,
+            end_line: 3,
+            signature: None,
+            hash: format!("{}_{}", name, version),
+        }
+    }
+
+    /// A comparison result where every listed function was modified
+    fn modified_functions_result(names: &[String]) -> ComparisonResult {
+        let mut match_result = MatchResult::new();
+        let mut source_function_texts = HashMap::new();
+        let mut target_function_texts = HashMap::new();
+
+        for (i, name) in names.iter().enumerate() {
+            let source = code_element(name, "old");
+            let target = code_element(name, "new");
+            source_function_texts.insert(
+                source.id.clone(),
+                format!("int {}(int x) {{\n    return x;\n}}\n", name),
+            );
+            target_function_texts.insert(
+                target.id.clone(),
+                format!("int {}(int x) {{\n    return x + {};\n}}\n", name, i),
+            );
+
+            let mut change = Change::new(ChangeType::Modify, format!("{} modified", name));
+            change.source = Some(source);
+            change.target = Some(target);
+            change.details.similarity_score = Some(0.6);
+            match_result.changes.push(change);
+        }
+
+        ComparisonResult {
+            source_file: PathBuf::from("old/src/lib.c"),
+            target_file: PathBuf::from("new/src/lib.c"),
+            language: Language::C,
+            diff_result: DiffResult {
+                match_result,
+                refactoring_patterns: Vec::new(),
+                execution_time_ms: 0,
+                statistics: DiffStatistics {
+                    functions_compared: names.len(),
+                    functions_matched: names.len(),
+                    functions_added: 0,
+                    functions_removed: 0,
+                    functions_modified: names.len(),
+                    average_similarity: 0.6,
+                },
+            },
+            classified_changes: Vec::new(),
+            refactoring_patterns: Vec::new(),
+            similarity_scores: None,
+            cross_file_moves: Vec::new(),
+            stats: ComparisonStats::default(),
+            source_function_texts,
+            target_function_texts,
+            import_delta: ImportDelta::default(),
+            generated: false,
+            fan_in: HashMap::new(),
+            complexity_deltas: HashMap::new(),
+            literal_changes: HashMap::new(),
+            behavior: HashMap::new(),
+            unchanged_context: HashMap::new(),
+            change_risks: Vec::new(),
+            finding_ids: Vec::new(),
+            churn: None,
+            source_ast: None,
+            target_ast: None,
+        }
+    }
+
+    fn sample_stats() -> ComparisonStats {
+        ComparisonStats {
+            files_compared: 2,
+            functions_compared: 40,
+            parsing_time: Duration::from_millis(120),
+            semantic_analysis_time: Duration::from_millis(30),
+            comparison_time: Duration::from_millis(45),
+            refactoring_detection_time: Duration::from_millis(5),
+            total_time: Duration::from_millis(200),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_stats_report_includes_all_phases() {
+        let report = sample_stats().report();
+
+        let phases: Vec<&str> = report.phases.iter().map(|p| p.phase.as_str()).collect();
+        assert_eq!(
+            phases,
+            vec![
+                "parsing",
+                "semantic_analysis",
+                "matching",
+                "refactoring_detection"
+            ]
+        );
+        assert!(report.phases.iter().all(|p| p.duration_ms >= 0.0));
+        assert_eq!(report.files_processed, 2);
+        assert!((report.functions_per_second - 200.0).abs() < 1e-9);
+
+        let empty = ComparisonStats::default().report();
+        assert_eq!(empty.phases.len(), 4);
+        assert_eq!(empty.functions_per_second, 0.0);
+    }
+
+    #[test]
+    fn test_json_output_contains_stats() {
+        let stats = sample_stats();
+        let output = OutputFormatter::format_comparison_results(
+            &[],
+            &OutputFormat::Json,
+            Some(&stats),
+            true,
+            RefactoringDetail::Standard,
+            ScoreFormat::default(),
+        )
+        .unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        let phases = value["stats"]["phases"].as_array().unwrap();
+        assert_eq!(phases.len(), 4);
+        for phase in phases {
+            assert!(phase["duration_ms"].as_f64().unwrap() >= 0.0);
+        }
+    }
+
+    #[test]
+    fn test_markdown_summary_table_and_function_diffs() {
+        let names = vec!["parse_header".to_string(), "write_body".to_string()];
+        let output = OutputFormatter::format_markdown(
+            &[modified_functions_result(&names)],
+            None,
+            10,
+            RefactoringDetail::Standard,
+            ScoreFormat::default(),
+        )
+        .unwrap();
+
+        assert!(output.contains("| Change | Count |\n|--------|------:|\n"));
+        assert!(output.contains("| Modified | 2 |"));
+        for name in &names {
+            assert!(output.contains(&format!("<code>{}</code>", name)));
+        }
+        assert!(output.contains("<details>"));
+        assert!(output.contains("```diff\n"));
+        assert!(output.contains("+    return x + 1;"));
+        assert!(!output.contains("more changed functions"));
+    }
+
+    #[test]
+    fn test_markdown_fence_outlasts_backticks_in_code() {
+        let names = vec!["render_help".to_string()];
+        let mut result = modified_functions_result(&names);
+        for text in result.target_function_texts.values_mut() {
+            *text = text.replace(
+                "    return",
+                "    puts(\"```sh\\nmake check\\n````\");\n    return",
+            );
+        }
+
+        let output = OutputFormatter::format_markdown(
+            &[result],
+            None,
+            10,
+            RefactoringDetail::Standard,
+            ScoreFormat::default(),
+        )
+        .unwrap();
+
+        assert!(output.contains("\n`````diff\n"), "{}", output);
+        assert!(output.contains("+    puts(\"```sh\\nmake check\\n````\");\n"));
+        assert!(output.contains("\n`````\n\n</details>"), "{}", output);
+    }
+
+    #[test]
+    fn test_markdown_ranks_by_fan_in() {
+        let names = vec!["format_label".to_string(), "parse_config".to_string()];
+        let mut result = modified_functions_result(&names);
+        let first_detailed = |result: &ComparisonResult| {
+            let output = OutputFormatter::format_markdown(
+                &[result.clone()],
+                None,
+                10,
+                RefactoringDetail::Standard,
+                ScoreFormat::default(),
+            )
+            .unwrap();
+            let position = |name: &str| output.find(&format!("<code>{}</code>", name)).unwrap();
+            if position("format_label") < position("parse_config") {
+                "format_label"
+            } else {
+                "parse_config"
+            }
+        };
+
+        // Equal magnitude without fan-in keeps the original order
+        assert_eq!(first_detailed(&result), "format_label");
+
+        result.fan_in = [
+            ("format_label".to_string(), 1),
+            ("parse_config".to_string(), 12),
+        ]
+        .into();
+        let changes = &result.diff_result.match_result.changes;
+        assert!(
+            OutputFormatter::ranked_change_magnitude(&result, &changes[1])
+                > OutputFormatter::ranked_change_magnitude(&result, &changes[0])
+        );
+        assert_eq!(first_detailed(&result), "parse_config");
+    }
+
+    #[test]
+    fn test_review_focus_puts_called_breaking_change_before_formatting_change() {
+        use crate::review_focus::{order_by_risk, RiskTier, RiskWeights};
+
+        let names = vec!["format_banner".to_string(), "parse_config".to_string()];
+        let mut result = modified_functions_result(&names);
+        {
+            let changes = &mut result.diff_result.match_result.changes;
+            // Whitespace only
+            changes[0].details.similarity_score = Some(0.99);
+            // A parameter was added
+            changes[1].source.as_mut().unwrap().signature =
+                Some("parse_config(path: char*)".to_string());
+            changes[1].target.as_mut().unwrap().signature =
+                Some("parse_config(path: char*, strict: int)".to_string());
+        }
+        result.fan_in = [("parse_config".to_string(), 12)].into();
+
+        order_by_risk(&mut result, &RiskWeights::default());
+        let order: Vec<&str> = result
+            .diff_result
+            .match_result
+            .changes
+            .iter()
+            .map(|change| change.target.as_ref().unwrap().name.as_str())
+            .collect();
+        assert_eq!(order, ["parse_config", "format_banner"]);
+        assert!(result.change_risks[0].tier >= RiskTier::High);
+        assert_eq!(result.change_risks[1].tier, RiskTier::Low);
+
+        let output = OutputFormatter::format_comparison_results(
+            std::slice::from_ref(&result),
+            &OutputFormat::Text,
+            None,
+            true,
+            RefactoringDetail::Standard,
+            ScoreFormat::default(),
+        )
+        .unwrap();
+        let low = output.find("[low risk]").unwrap();
+        assert!(
+            output.find("parse_config modified").unwrap() < low,
+            "{}",
+            output
+        );
+        assert!(
+            low < output.find("format_banner modified").unwrap(),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_score_precision_and_percentages_apply_to_every_format() {
+        let mut result = modified_functions_result(&["parse_header".to_string()]);
+        result.stats.similarity_score = 0.85333;
+        result.diff_result.match_result.similarity = 0.85333;
+        let render = |format: &OutputFormat, scores| {
+            OutputFormatter::format_comparison_results(
+                std::slice::from_ref(&result),
+                format,
+                None,
+                true,
+                RefactoringDetail::Standard,
+                scores,
+            )
+            .unwrap()
+        };
+        let precise = ScoreFormat {
+            precision: Some(4),
+            as_percent: false,
+        };
+        let percent = ScoreFormat {
+            precision: None,
+            as_percent: true,
+        };
+
+        // Each format keeps its own style by default
+        assert!(render(&OutputFormat::Text, ScoreFormat::default()).contains("Similarity: 85.3%\n"));
+        assert!(render(&OutputFormat::Xml, ScoreFormat::default())
+            .contains("<similarity>0.853330</similarity>"));
+
+        assert!(render(&OutputFormat::Text, precise).contains("Similarity: 0.8533\n"));
+        assert!(render(&OutputFormat::Text, percent).contains("Similarity: 85%\n"));
+        assert!(render(&OutputFormat::Xml, precise).contains("<similarity>0.8533</similarity>"));
+        assert!(render(&OutputFormat::Csv, percent).contains(",C,85%,"));
+        assert!(render(&OutputFormat::Markdown, precise).contains(" · 0.6000 similar"));
+
+        let json = |scores| -> serde_json::Value {
+            serde_json::from_str(&render(&OutputFormat::Json, scores)).unwrap()
+        };
+        assert_eq!(json(precise)["results"][0]["similarity"], 0.8533);
+        assert_eq!(json(percent)["results"][0]["similarity"], 85.0);
+        assert_eq!(
+            json(percent)["results"][0]["changes"][0]["details"]["similarity_score"],
+            60.0
+        );
+    }
+
+    #[test]
+    fn test_jsonl_emits_one_flat_record_per_match() {
+        let names = vec!["format_label".to_string(), "parse_config".to_string()];
+        let mut result = modified_functions_result(&names);
+        let precise = 0.1 + 0.2;
+        result.diff_result.match_result.changes[1]
+            .details
+            .similarity_score = Some(precise);
+
+        let output = OutputFormatter::format_jsonl(&[result]).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        for (line, name) in lines.iter().zip(&names) {
+            let record: serde_json::Value = serde_json::from_str(line).unwrap();
+            let object = record.as_object().unwrap();
+            for key in [
+                "source_file",
+                "target_file",
+                "language",
+                "change_type",
+                "source_name",
+                "target_name",
+                "source_start_line",
+                "source_end_line",
+                "target_start_line",
+                "target_end_line",
+                "similarity",
+                "confidence",
+                "file_similarity",
+                "refactoring_type",
+                "complexity_delta",
+            ] {
+                assert!(object.contains_key(key), "missing {} in {}", key, line);
+            }
+            assert!(object.values().all(|value| !value.is_object()));
+            assert_eq!(record["source_name"], name.as_str());
+            assert_eq!(record["target_file"], "new/src/lib.c");
+        }
+
+        let record: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(record["similarity"].as_f64(), Some(precise));
+    }
+
+    #[test]
+    fn test_markdown_truncates_to_max_functions() {
+        let names: Vec<String> = (0..200).map(|i| format!("function_{}", i)).collect();
+        let result = modified_functions_result(&names);
+
+        let output = OutputFormatter::format_markdown(
+            &[result],
+            None,
+            5,
+            RefactoringDetail::Standard,
+            ScoreFormat::default(),
+        )
+        .unwrap();
+
+        assert_eq!(output.matches("<details>").count(), 5);
+        assert!(output.contains("_…and 195 more changed functions_"));
+        assert!(output.contains("| Modified | 200 |"));
+        assert!(output.len() < 4_000, "markdown is {} bytes", output.len());
+    }
+
+    #[test]
+    fn test_refactoring_detail_levels_control_evidence() {
+        let mut pattern = RefactoringPattern::custom(
+            "inline-helper",
+            0.8,
+            "Inlined helper into caller",
+            vec!["helper".to_string()],
+        );
+        pattern.evidence.push(RefactoringEvidence {
+            evidence_type: RefactoringEvidenceType::CodeSimilarity,
+            description: "Helper body appears in caller".to_string(),
+            strength: 0.75,
+            data: HashMap::new(),
+        });
+        let mut result = modified_functions_result(&["caller".to_string()]);
+        result.refactoring_patterns.push(pattern);
+
+        let render = |format: &OutputFormat, detail| {
+            OutputFormatter::format_comparison_results(
+                std::slice::from_ref(&result),
+                format,
+                None,
+                true,
+                detail,
+                ScoreFormat::default(),
+            )
+            .unwrap()
+        };
+        let levels = [
+            RefactoringDetail::Minimal,
+            RefactoringDetail::Standard,
+            RefactoringDetail::Full,
+        ];
+
+        let texts: Vec<String> = levels
+            .iter()
+            .map(|&detail| render(&OutputFormat::Text, detail))
+            .collect();
+        assert!(texts[0].len() < texts[1].len() && texts[1].len() < texts[2].len());
+        assert!(texts
+            .iter()
+            .all(|text| text.contains("(confidence: 0.800)")));
+        assert!(!texts[0].contains("Description: Inlined helper into caller"));
+        assert!(texts[1].contains("Description: Inlined helper into caller"));
+        assert!(!texts[1].contains("Evidence:"));
+        assert!(
+            texts[2].contains("CodeSimilarity (strength: 0.750): Helper body appears in caller")
+        );
+        assert!(texts[2].contains("Quality: improvement 0.00"));
+
+        let fields: Vec<Vec<String>> = levels
+            .iter()
+            .map(|&detail| {
+                let report: serde_json::Value =
+                    serde_json::from_str(&render(&OutputFormat::Json, detail)).unwrap();
+                let mut keys: Vec<String> = report["results"][0]["refactoring_patterns"][0]
+                    .as_object()
+                    .unwrap()
+                    .keys()
+                    .cloned()
+                    .collect();
+                keys.sort();
+                keys
+            })
+            .collect();
+        assert_eq!(fields[0], vec!["confidence", "pattern_type"]);
+        assert_eq!(
+            fields[1],
+            vec![
+                "affected_elements",
+                "complexity",
+                "confidence",
+                "description",
+                "pattern_type"
+            ]
+        );
+        assert!(fields[2].contains(&"evidence".to_string()));
+        assert!(fields[2].contains(&"analysis".to_string()));
+    }
+
+    /// The parts of a binary report checked against the results it was written from
+    #[derive(serde::Deserialize)]
+    struct DecodedReport {
+        results: Vec<DecodedFile>,
+        stats: Option<serde::de::IgnoredAny>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct DecodedFile {
+        similarity: f64,
+        changes: Vec<DecodedChange>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct DecodedChange {
+        #[serde(flatten)]
+        change: Change,
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_binary_report_round_trips_losslessly(
+            changes in proptest::collection::vec(
+                (
+                    "[a-z_][a-z0-9_]{0,11}",
+                    0usize..6,
+                    proptest::option::of(proptest::num::f64::NORMAL | proptest::num::f64::ZERO),
+                    proptest::num::f64::NORMAL
+                        | proptest::num::f64::SUBNORMAL
+                        | proptest::num::f64::ZERO,
+                    "\\PC{0,30}",
+                    proptest::collection::hash_map("[a-z]{1,8}", "\\PC{0,12}", 0..3),
+                ),
+                0..8,
+            ),
+            similarity in 0.0f64..=1.0,
+            with_stats in proptest::bool::ANY,
+        ) {
+            use proptest::prelude::*;
+
+            let names: Vec<String> = changes.iter().map(|change| change.0.clone()).collect();
+            let mut result = modified_functions_result(&names);
+            result.diff_result.match_result.similarity = similarity;
+            let change_types = [
+                ChangeType::Add,
+                ChangeType::Delete,
+                ChangeType::Modify,
+                ChangeType::Rename,
+                ChangeType::Move,
+                ChangeType::CrossFileMove,
+            ];
+            for (change, (_, change_type, score, confidence, description, metadata)) in result
+                .diff_result
+                .match_result
+                .changes
+                .iter_mut()
+                .zip(changes)
+            {
+                change.change_type = change_types[change_type].clone();
+                change.details.similarity_score = score;
+                change.confidence = confidence;
+                change.details.description = description;
+                change.details.metadata = metadata;
+            }
+            let stats = sample_stats();
+            let stats = with_stats.then_some(&stats);
+
+            let binary = OutputFormatter::format_comparison_binary(
+                std::slice::from_ref(&result),
+                stats,
+                RefactoringDetail::Full,
+            )
+            .unwrap();
+            let decoded: DecodedReport = binary_format::decode(&binary).unwrap();
+            prop_assert_eq!(decoded.stats.is_some(), with_stats);
+            let file = &decoded.results[0];
+            prop_assert_eq!(
+                file.similarity.to_bits(),
+                result.diff_result.match_result.similarity.to_bits()
+            );
+            let changes: Vec<&Change> = file.changes.iter().map(|change| &change.change).collect();
+            let expected: Vec<&Change> = result.diff_result.match_result.changes.iter().collect();
+            prop_assert_eq!(changes, expected);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Folded-stack profile of a compare run
+//!
+//! `compare --profile <out.folded>` writes the time spent in each phase of
+//! each compared file pair as folded stacks, one `frame;frame;frame count`
+//! line per stack with the count in microseconds, the input format of
+//! `inferno-flamegraph` and `flamegraph.pl`. Phases are measured by the
+//! comparison itself, so no sampling profiler is involved.
+
+use crate::atomic_file::write_atomic;
+use crate::output::ComparisonStats;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Root frame of every stack
+const ROOT_FRAME: &str = "smart-diff;compare";
+
+/// Time per stack, accumulated over a run
+#[derive(Debug, Default)]
+pub struct FoldedProfile {
+    stacks: BTreeMap<String, u64>,
+}
+
+impl FoldedProfile {
+    /// Add the phase timings of one compared file pair
+    pub fn record_file(&mut self, file: &Path, stats: &ComparisonStats) {
+        let file = frame(&file.to_string_lossy().replace('\\', "/"));
+        for (phase, duration) in stats.phase_timings() {
+            self.record(&format!("{};{};{}", ROOT_FRAME, phase, file), duration);
+        }
+    }
+
+    /// Attribute the part of the run's wall time not spent in any file's
+    /// phases, such as discovery and output, to its own frame
+    pub fn record_remainder(&mut self, total: Duration) {
+        let measured: u64 = self.stacks.values().sum();
+        let remainder = (total.as_micros() as u64).saturating_sub(measured);
+        self.record(
+            &format!("{};other", ROOT_FRAME),
+            Duration::from_micros(remainder),
+        );
+    }
+
+    fn record(&mut self, stack: &str, duration: Duration) {
+        let micros = duration.as_micros() as u64;
+        if micros > 0 {
+            *self.stacks.entry(stack.to_string()).or_insert(0) += micros;
+        }
+    }
+
+    /// Folded stacks, sorted by stack
+    pub fn folded(&self) -> String {
+        self.stacks
+            .iter()
+            .map(|(stack, micros)| format!("{} {}\n", stack, micros))
+            .collect()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write_atomic(path, self.folded().as_bytes())
+            .with_context(|| format!("Failed to write profile: {}", path.display()))
+    }
+}
+
+/// A file path as a single frame: `;` separates frames and a line ends the stack
+fn frame(name: &str) -> String {
+    name.replace(';', ":").replace(['\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folded_stacks_per_phase_and_file() {
+        let stats = ComparisonStats {
+            parsing_time: Duration::from_micros(300),
+            comparison_time: Duration::from_micros(1200),
+            ..Default::default()
+        };
+        let mut profile = FoldedProfile::default();
+        profile.record_file(Path::new("src/a;b.c"), &stats);
+        profile.record_file(Path::new("src/a;b.c"), &stats);
+        profile.record_remainder(Duration::from_micros(4000));
+
+        assert_eq!(
+            profile.folded(),
+            "smart-diff;compare;matching;src/a:b.c 2400\n\
+             smart-diff;compare;other 1000\n\
+             smart-diff;compare;parsing;src/a:b.c 600\n"
+        );
+    }
+}
@@ -0,0 +1,660 @@
+//! Identifier redaction for shareable output
+//!
+//! Used by `compare --redact` to share comparison structure without disclosing
+//! source. Function, type and variable names are replaced with stable
+//! pseudonyms (`fn_1`, `type_3`, `var_2`) before any output is formatted, so
+//! every format reports the same pseudonyms, change structure and similarity
+//! numbers. Function bodies kept for diffs are tokenized: every identifier is
+//! pseudonymized while keywords, literals' digits, punctuation and layout stay.
+//! File paths are redacted component by component with the same pseudonyms,
+//! keeping separators and file extensions, so `src/billing/tax.c` becomes
+//! `var_1/var_2/var_3.c`.
+
+use crate::output::ComparisonResult;
+use smart_diff_parser::{CodeElement, ElementType};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Keywords and builtin type names of the supported languages, kept verbatim
+const KEYWORDS: &[&str] = &[
+    "abstract",
+    "and",
+    "as",
+    "async",
+    "await",
+    "bool",
+    "boolean",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "def",
+    "default",
+    "defer",
+    "del",
+    "do",
+    "double",
+    "elif",
+    "else",
+    "enum",
+    "except",
+    "export",
+    "extends",
+    "extern",
+    "false",
+    "final",
+    "finally",
+    "float",
+    "fn",
+    "for",
+    "from",
+    "func",
+    "function",
+    "go",
+    "if",
+    "impl",
+    "implements",
+    "import",
+    "in",
+    "inline",
+    "instanceof",
+    "int",
+    "interface",
+    "is",
+    "lambda",
+    "let",
+    "long",
+    "loop",
+    "match",
+    "mod",
+    "mut",
+    "new",
+    "nil",
+    "None",
+    "not",
+    "null",
+    "or",
+    "package",
+    "pass",
+    "private",
+    "protected",
+    "pub",
+    "public",
+    "raise",
+    "ref",
+    "return",
+    "self",
+    "Self",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "string",
+    "struct",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "throws",
+    "trait",
+    "true",
+    "True",
+    "False",
+    "try",
+    "type",
+    "typedef",
+    "typeof",
+    "union",
+    "unsigned",
+    "use",
+    "var",
+    "void",
+    "volatile",
+    "where",
+    "while",
+    "with",
+    "yield",
+];
+
+/// Kind of identifier, which determines its pseudonym prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IdentifierKind {
+    Function,
+    Type,
+    Variable,
+}
+
+impl IdentifierKind {
+    fn prefix(self) -> &'static str {
+        match self {
+            IdentifierKind::Function => "fn",
+            IdentifierKind::Type => "type",
+            IdentifierKind::Variable => "var",
+        }
+    }
+
+    fn of_element(element_type: &ElementType) -> Self {
+        match element_type {
+            ElementType::Function | ElementType::Method | ElementType::Custom(_) => {
+                IdentifierKind::Function
+            }
+            ElementType::Class
+            | ElementType::Interface
+            | ElementType::Module
+            | ElementType::Section
+            | ElementType::BuildStage => IdentifierKind::Type,
+            ElementType::Variable | ElementType::Constant | ElementType::Instruction => {
+                IdentifierKind::Variable
+            }
+        }
+    }
+
+    /// Guess the kind of an identifier found in source text
+    fn guess(name: &str, rest: &str) -> Self {
+        let starts_upper = name.chars().next().is_some_and(char::is_uppercase);
+        let has_lower = name.chars().any(char::is_lowercase);
+
+        if rest.trim_start().starts_with('(') {
+            IdentifierKind::Function
+        } else if starts_upper && has_lower {
+            IdentifierKind::Type
+        } else {
+            IdentifierKind::Variable
+        }
+    }
+}
+
+/// Replaces identifiers with pseudonyms, consistently for a whole run
+#[derive(Debug, Default)]
+pub struct Redactor {
+    pseudonyms: HashMap<String, String>,
+    counters: HashMap<&'static str, usize>,
+}
+
+impl Redactor {
+    /// Redact every identifier in the comparison results, in place
+    ///
+    /// Pseudonyms are assigned in result and change order, so the same input
+    /// always produces the same mapping.
+    pub fn redact_results(&mut self, results: &mut [ComparisonResult]) {
+        for result in results.iter() {
+            self.register_result(result);
+        }
+
+        for result in results.iter_mut() {
+            self.redact_result(result);
+        }
+    }
+
+    /// Assign pseudonyms to every name in a result, before anything is rewritten
+    fn register_result(&mut self, result: &ComparisonResult) {
+        for change in &result.diff_result.match_result.changes {
+            for (element, texts) in [
+                (&change.source, &result.source_function_texts),
+                (&change.target, &result.target_function_texts),
+            ] {
+                if let Some(element) = element {
+                    self.register(
+                        &element.name,
+                        IdentifierKind::of_element(&element.element_type),
+                    );
+                    if let Some(text) = texts.get(&element.id) {
+                        self.tokenize(text);
+                    }
+                }
+            }
+        }
+
+        // Functions not involved in any change, in a stable order
+        for texts in [&result.source_function_texts, &result.target_function_texts] {
+            let mut ids: Vec<&String> = texts.keys().collect();
+            ids.sort();
+            for id in ids {
+                self.tokenize(&texts[id]);
+            }
+        }
+
+        // Paths last, so a file named after a function gets its pseudonym.
+        // Registered before any rewriting, so descriptions mentioning a path
+        // are redacted too
+        let element_paths = result
+            .diff_result
+            .match_result
+            .changes
+            .iter()
+            .flat_map(|change| [&change.source, &change.target])
+            .flatten()
+            .map(|element| element.file_path.as_str());
+        let move_paths = result
+            .cross_file_moves
+            .iter()
+            .flat_map(|function_move| [&function_move.source_file, &function_move.target_file])
+            .map(String::as_str);
+        let paths: Vec<String> = [&result.source_file, &result.target_file]
+            .into_iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .chain(element_paths.chain(move_paths).map(str::to_string))
+            .collect();
+        for path in paths {
+            self.redact_path(&path);
+        }
+    }
+
+    fn redact_result(&mut self, result: &mut ComparisonResult) {
+        for path in [&mut result.source_file, &mut result.target_file] {
+            *path = PathBuf::from(self.redact_path(&path.to_string_lossy()));
+        }
+        for function_move in &mut result.cross_file_moves {
+            function_move.source_file = self.redact_path(&function_move.source_file);
+            function_move.target_file = self.redact_path(&function_move.target_file);
+            let signature = &mut function_move.function_signature;
+            signature.name = self.redact_text(&signature.name);
+            signature.qualified_name = self.redact_text(&signature.qualified_name);
+            signature.file_path = self.redact_path(&signature.file_path);
+        }
+
+        let match_result = &mut result.diff_result.match_result;
+        for change in &mut match_result.changes {
+            for element in [&mut change.source, &mut change.target]
+                .into_iter()
+                .flatten()
+            {
+                self.redact_element(element);
+            }
+            change.details.description = self.redact_text(&change.details.description);
+            for value in change.details.metadata.values_mut() {
+                *value = self.redact_text(value);
+            }
+        }
+        match_result.mapping = match_result
+            .mapping
+            .iter()
+            .map(|(source, target)| (self.redact_text(source), self.redact_text(target)))
+            .collect();
+        result.fan_in = std::mem::take(&mut result.fan_in)
+            .into_iter()
+            .map(|(name, calls)| (self.redact_text(&name), calls))
+            .collect();
+        for id in match_result
+            .unmatched_source
+            .iter_mut()
+            .chain(match_result.unmatched_target.iter_mut())
+        {
+            *id = self.redact_text(id);
+        }
+
+        for pattern in result
+            .refactoring_patterns
+            .iter_mut()
+            .chain(result.diff_result.refactoring_patterns.iter_mut())
+        {
+            pattern.description = self.redact_text(&pattern.description);
+            let analysis = &mut pattern.analysis;
+            let mut names: Vec<&mut String> = pattern
+                .affected_elements
+                .iter_mut()
+                .chain(pattern.related_changes.iter_mut())
+                .chain(analysis.impact.affected_functions.iter_mut())
+                .chain(analysis.characteristics.iter_mut().map(|c| &mut c.value))
+                .collect();
+            if let Some(before_after) = analysis.before_after.as_mut() {
+                names.extend(before_after.before_elements.iter_mut());
+                names.extend(before_after.after_elements.iter_mut());
+            }
+            for evidence in pattern.evidence.iter_mut() {
+                names.push(&mut evidence.description);
+                names.extend(evidence.data.values_mut());
+            }
+            for name in names {
+                *name = self.redact_text(name);
+            }
+        }
+
+        for classification in &mut result.classified_changes {
+            let analysis = &mut classification.analysis;
+            analysis.description = self.redact_text(&analysis.description);
+            for characteristic in &mut analysis.characteristics {
+                characteristic.value = self.redact_text(&characteristic.value);
+            }
+            for evidence in &mut analysis.evidence {
+                evidence.description = self.redact_text(&evidence.description);
+                for value in evidence.data.values_mut() {
+                    *value = self.redact_text(value);
+                }
+            }
+            for alternative in &mut analysis.alternatives {
+                alternative.reason = self.redact_text(&alternative.reason);
+            }
+            for component in &mut classification.impact.affected_components {
+                *component = self.redact_text(component);
+            }
+        }
+
+        if let Some(scores) = result.similarity_scores.take() {
+            result.similarity_scores = Some(
+                scores
+                    .into_iter()
+                    .map(|(pair, score)| (self.redact_text(&pair), score))
+                    .collect(),
+            );
+        }
+
+        for texts in [
+            &mut result.source_function_texts,
+            &mut result.target_function_texts,
+        ] {
+            *texts = texts
+                .iter()
+                .map(|(id, text)| (self.redact_text(id), self.tokenize(text)))
+                .collect();
+        }
+
+        let delta = &mut result.import_delta;
+        let imports = delta
+            .added
+            .iter_mut()
+            .chain(delta.removed.iter_mut())
+            .chain(
+                delta
+                    .changed
+                    .iter_mut()
+                    .flat_map(|change| [&mut change.source, &mut change.target]),
+            );
+        for import in imports {
+            import.imported_name = self.tokenize(&import.imported_name);
+            import.alias = import.alias.as_deref().map(|alias| self.tokenize(alias));
+            import.source_path = import
+                .source_path
+                .as_deref()
+                .map(|path| self.tokenize(path));
+        }
+
+        result.literal_changes = std::mem::take(&mut result.literal_changes)
+            .into_iter()
+            .map(|(id, mut literals)| {
+                for literal in &mut literals.changes {
+                    for value in [&mut literal.old_value, &mut literal.new_value]
+                        .into_iter()
+                        .flatten()
+                    {
+                        *value = self.tokenize(value);
+                    }
+                }
+                (self.redact_text(&id), literals)
+            })
+            .collect();
+
+        result.behavior = std::mem::take(&mut result.behavior)
+            .into_iter()
+            .map(|(id, behavior)| (self.redact_text(&id), behavior))
+            .collect();
+
+        result.unchanged_context = std::mem::take(&mut result.unchanged_context)
+            .into_iter()
+            .map(|(id, mut context)| {
+                for signature in context.before.iter_mut().chain(context.after.iter_mut()) {
+                    *signature = self.tokenize(signature);
+                }
+                (self.redact_text(&id), context)
+            })
+            .collect();
+
+        // ASTs carry original source text and names in every node
+        result.source_ast = None;
+        result.target_ast = None;
+    }
+
+    fn redact_element(&mut self, element: &mut CodeElement) {
+        element.id = self.redact_text(&element.id);
+        element.file_path = self.redact_path(&element.file_path);
+        element.name = self.register(
+            &element.name,
+            IdentifierKind::of_element(&element.element_type),
+        );
+        if let Some(signature) = element.signature.as_mut() {
+            *signature = self.tokenize(signature);
+        }
+    }
+
+    /// Pseudonym for `name`, assigning a new one of `kind` on first sight
+    fn register(&mut self, name: &str, kind: IdentifierKind) -> String {
+        if let Some(pseudonym) = self.pseudonyms.get(name) {
+            return pseudonym.clone();
+        }
+
+        let counter = self.counters.entry(kind.prefix()).or_insert(0);
+        *counter += 1;
+        let pseudonym = format!("{}_{}", kind.prefix(), counter);
+        self.pseudonyms.insert(name.to_string(), pseudonym.clone());
+        pseudonym
+    }
+
+    /// Replace every non-keyword identifier in source code, registering new ones
+    fn tokenize(&mut self, code: &str) -> String {
+        rewrite_identifiers(code, |name, rest| {
+            if KEYWORDS.contains(&name) {
+                name.to_string()
+            } else {
+                self.register(name, IdentifierKind::guess(name, rest))
+            }
+        })
+    }
+
+    /// Replace every component of a path, keeping separators and the file
+    /// extension, registering new names
+    fn redact_path(&mut self, path: &str) -> String {
+        let file_start = path.rfind(['/', '\\']).map_or(0, |i| i + 1);
+        // A leading dot marks a hidden file rather than an extension
+        let extension_start = path[file_start..]
+            .rfind('.')
+            .filter(|&dot| dot > 0)
+            .map(|dot| file_start + dot);
+        match extension_start {
+            Some(dot) => format!("{}{}", self.tokenize(&path[..dot]), &path[dot..]),
+            None => self.tokenize(path),
+        }
+    }
+
+    /// Replace already-known identifiers in free-form text
+    fn redact_text(&self, text: &str) -> String {
+        rewrite_identifiers(text, |name, _| {
+            self.pseudonyms
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| name.to_string())
+        })
+    }
+}
+
+/// Rebuild `text`, passing each identifier and the text following it to `rewrite`
+fn rewrite_identifiers(text: &str, mut rewrite: impl FnMut(&str, &str) -> String) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find(is_word) {
+        output.push_str(&rest[..start]);
+        let word_len = rest[start..]
+            .find(|c: char| !is_word(c))
+            .unwrap_or(rest.len() - start);
+        let word = &rest[start..start + word_len];
+        let after = &rest[start + word_len..];
+
+        // Numeric literals such as `42` or `0x1f` are not identifiers
+        if word.starts_with(|c: char| c.is_ascii_digit()) {
+            output.push_str(word);
+        } else {
+            output.push_str(&rewrite(word, after));
+        }
+        rest = after;
+    }
+    output.push_str(rest);
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{OutputFormat, RefactoringDetail};
+    use crate::output::{ComparisonStats, OutputFormatter, ScoreFormat};
+    use smart_diff_engine::engine::{DiffResult, DiffStatistics};
+    use smart_diff_parser::{Change, ChangeDetail, ChangeType, MatchResult};
+    use smart_diff_semantic::{ImportDelta, ImportInfo};
+    use std::path::PathBuf;
+
+    fn element(id: &str, name: &str) -> CodeElement {
+        CodeElement {
+            id: id.to_string(),
+            name: name.to_string(),
+            file_path: "proprietary/billing/tax_engine.c".to_string(),
+            start_line: 1,
+            end_line: 4,
+            element_type: ElementType::Function,
+            signature: Some(format!("{}(order: Invoice)", name)),
+            hash: id.to_string(),
+        }
+    }
+
+    fn proprietary_result() -> ComparisonResult {
+        let source_text = "int computeTax(Invoice order) {\n    int rate = lookupRate(order);\n    return rate * 2;\n}";
+        let target_text = "int computeTax(Invoice order) {\n    int rate = lookupRate(order);\n    return rate * 3;\n}";
+        let change = Change {
+            change_type: ChangeType::Modify,
+            source: Some(element("func_a", "computeTax")),
+            target: Some(element("func_b", "computeTax")),
+            details: ChangeDetail {
+                description: "Function computeTax modified".to_string(),
+                affected_lines: Vec::new(),
+                similarity_score: Some(0.8),
+                refactoring_type: None,
+                metadata: HashMap::new(),
+            },
+            confidence: 0.8,
+        };
+
+        ComparisonResult {
+            source_file: PathBuf::from("proprietary/billing/tax_engine.c"),
+            target_file: PathBuf::from("proprietary/billing/tax_engine.c"),
+            language: smart_diff_parser::Language::C,
+            diff_result: DiffResult {
+                match_result: MatchResult {
+                    similarity: 0.8,
+                    mapping: [("func_a".to_string(), "func_b".to_string())].into(),
+                    changes: vec![change],
+                    unmatched_source: Vec::new(),
+                    unmatched_target: Vec::new(),
+                },
+                refactoring_patterns: Vec::new(),
+                execution_time_ms: 0,
+                statistics: DiffStatistics {
+                    functions_compared: 2,
+                    functions_matched: 1,
+                    functions_added: 0,
+                    functions_removed: 0,
+                    functions_modified: 1,
+                    average_similarity: 0.8,
+                },
+            },
+            classified_changes: Vec::new(),
+            refactoring_patterns: Vec::new(),
+            similarity_scores: None,
+            cross_file_moves: Vec::new(),
+            stats: ComparisonStats::default(),
+            source_function_texts: [("func_a".to_string(), source_text.to_string())].into(),
+            target_function_texts: [("func_b".to_string(), target_text.to_string())].into(),
+            import_delta: ImportDelta {
+                added: vec![ImportInfo {
+                    imported_name: "tax_tables.h".to_string(),
+                    source_path: Some("tax_tables.h".to_string()),
+                    alias: None,
+                    is_wildcard: false,
+                    line: 1,
+                    column: 1,
+                }],
+                ..Default::default()
+            },
+            generated: false,
+            fan_in: HashMap::new(),
+            complexity_deltas: HashMap::new(),
+            literal_changes: HashMap::new(),
+            behavior: HashMap::new(),
+            unchanged_context: HashMap::new(),
+            change_risks: Vec::new(),
+            finding_ids: Vec::new(),
+            churn: None,
+            source_ast: None,
+            target_ast: None,
+        }
+    }
+
+    #[test]
+    fn test_redacted_json_leaks_no_identifiers() {
+        let mut results = vec![proprietary_result()];
+        Redactor::default().redact_results(&mut results);
+
+        let output = OutputFormatter::format_comparison_results(
+            &results,
+            &OutputFormat::Json,
+            None,
+            true,
+            RefactoringDetail::Full,
+            ScoreFormat::default(),
+        )
+        .unwrap();
+
+        for identifier in [
+            "computeTax",
+            "Invoice",
+            "order",
+            "rate",
+            "lookupRate",
+            "tax_tables",
+            "proprietary",
+            "billing",
+            "tax_engine",
+        ] {
+            assert!(
+                !output.contains(identifier),
+                "{} leaked into redacted output:\n{}",
+                identifier,
+                output
+            );
+        }
+        assert!(output.contains("fn_1"));
+        assert!(output.contains("type_1"));
+
+        // Structure and numbers survive redaction
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        let change = &value["results"][0]["changes"][0];
+        assert_eq!(change["change_type"], "Modify");
+        assert_eq!(change["details"]["similarity_score"], 0.8);
+        assert!(value["results"][0]["source_file"]
+            .as_str()
+            .unwrap()
+            .ends_with(".c"));
+        assert!(change["diff"]
+            .as_str()
+            .unwrap()
+            .contains("return var_2 * 3;"));
+    }
+
+    #[test]
+    fn test_pseudonyms_are_stable_across_runs() {
+        let redact = || {
+            let mut results = vec![proprietary_result()];
+            Redactor::default().redact_results(&mut results);
+            results[0].source_function_texts["func_a"].clone()
+        };
+
+        assert_eq!(redact(), redact());
+        assert_eq!(
+            redact(),
+            "int fn_1(type_1 var_1) {\n    int var_2 = fn_2(var_1);\n    return var_2 * 2;\n}"
+        );
+    }
+}
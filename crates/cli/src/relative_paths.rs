@@ -0,0 +1,141 @@
+//! Portable file paths in comparison output
+//!
+//! Used by `compare --relative-to` so reports do not depend on where the
+//! inputs were checked out. Every file path in the results is rewritten
+//! relative to a root before any output is formatted. Paths outside the root
+//! are shown as absolute paths and reported, rather than climbing out of the
+//! root with `..`. Relative paths always use `/` separators.
+
+use crate::output::ComparisonResult;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Rewrites the file paths of comparison results relative to a root
+#[derive(Debug)]
+pub struct PathRelativizer {
+    source_root: PathBuf,
+    target_root: PathBuf,
+    outside_root: BTreeSet<PathBuf>,
+}
+
+impl PathRelativizer {
+    /// Source paths relative to `source_root` and target paths relative to `target_root`
+    ///
+    /// A root that is a file, as in a single-file comparison, stands for its
+    /// directory.
+    pub fn new(source_root: &Path, target_root: &Path) -> Self {
+        Self {
+            source_root: Self::root_directory(source_root),
+            target_root: Self::root_directory(target_root),
+            outside_root: BTreeSet::new(),
+        }
+    }
+
+    /// All paths relative to `root`
+    pub fn with_root(root: &Path) -> Self {
+        Self::new(root, root)
+    }
+
+    /// Paths left absolute because they are outside their root
+    pub fn outside_root(&self) -> &BTreeSet<PathBuf> {
+        &self.outside_root
+    }
+
+    pub fn relativize_results(&mut self, results: &mut [ComparisonResult]) {
+        for result in results {
+            result.source_file = self.source_path(&result.source_file);
+            result.target_file = self.target_path(&result.target_file);
+
+            for change in &mut result.diff_result.match_result.changes {
+                if let Some(element) = change.source.as_mut() {
+                    element.file_path = self.source_string(&element.file_path);
+                }
+                if let Some(element) = change.target.as_mut() {
+                    element.file_path = self.target_string(&element.file_path);
+                }
+            }
+
+            for function_move in &mut result.cross_file_moves {
+                function_move.source_file = self.source_string(&function_move.source_file);
+                function_move.target_file = self.target_string(&function_move.target_file);
+                function_move.function_signature.file_path =
+                    self.target_string(&function_move.function_signature.file_path);
+            }
+        }
+    }
+
+    fn source_path(&mut self, path: &Path) -> PathBuf {
+        let root = self.source_root.clone();
+        self.relativize(path, &root)
+    }
+
+    fn target_path(&mut self, path: &Path) -> PathBuf {
+        let root = self.target_root.clone();
+        self.relativize(path, &root)
+    }
+
+    /// Element paths are empty when the parser was not given one
+    fn source_string(&mut self, path: &str) -> String {
+        if path.is_empty() {
+            return String::new();
+        }
+        self.source_path(Path::new(path)).display().to_string()
+    }
+
+    fn target_string(&mut self, path: &str) -> String {
+        if path.is_empty() {
+            return String::new();
+        }
+        self.target_path(Path::new(path)).display().to_string()
+    }
+
+    fn relativize(&mut self, path: &Path, root: &Path) -> PathBuf {
+        // Stdin has no location to rewrite
+        if path == Path::new("-") {
+            return path.to_path_buf();
+        }
+        let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        match absolute.strip_prefix(root) {
+            // Forward slashes, so reports from Windows and Unix runners match
+            Ok(relative) if !relative.as_os_str().is_empty() => {
+                PathBuf::from(relative.to_string_lossy().replace('\\', "/"))
+            }
+            Ok(_) => absolute.file_name().map(PathBuf::from).unwrap_or(absolute),
+            Err(_) => {
+                self.outside_root.insert(absolute.clone());
+                absolute
+            }
+        }
+    }
+
+    fn root_directory(root: &Path) -> PathBuf {
+        let absolute = std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf());
+        match absolute.parent() {
+            Some(parent) if absolute.is_file() => parent.to_path_buf(),
+            _ => absolute,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_outside_root_stay_absolute() {
+        let root = std::path::absolute("checkout").unwrap();
+        let mut relativizer = PathRelativizer::with_root(&root);
+
+        assert_eq!(
+            relativizer.source_path(&root.join("src/lib.c")),
+            PathBuf::from("src/lib.c")
+        );
+        let elsewhere = std::path::absolute("elsewhere/lib.c").unwrap();
+        assert_eq!(relativizer.target_path(&elsewhere), elsewhere);
+        assert_eq!(relativizer.source_string(""), "");
+        assert_eq!(relativizer.source_path(Path::new("-")), PathBuf::from("-"));
+
+        let outside: Vec<&PathBuf> = relativizer.outside_root().iter().collect();
+        assert_eq!(outside, vec![&elsewhere]);
+    }
+}
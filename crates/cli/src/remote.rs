@@ -0,0 +1,229 @@
+//! Remote inputs
+//!
+//! `compare` takes a URL in place of SOURCE or TARGET, to compare local work
+//! against a canonical copy without downloading it by hand. The input is
+//! fetched to a temporary directory first and compared from there:
+//!
+//! - `http://` and `https://` URLs are downloaded as a single file, which keeps
+//!   the last segment of the URL as its name so its language is detected
+//! - `git://` and `git+<scheme>://` URLs are shallow-cloned, at the branch or
+//!   tag given after `#` or at the default branch, and compared as a directory
+//!
+//! Only public remotes are supported: git never prompts for credentials. Both
+//! kinds of fetch are bounded by a timeout and a size limit, and the temporary
+//! copy is removed once the comparison is done. Results name remote files by
+//! their URL rather than by the temporary copy.
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::process::Command;
+use tracing::info;
+
+/// Bounds on fetching one remote input
+#[derive(Debug, Clone, Copy)]
+pub struct RemoteLimits {
+    /// Longest a download or clone may take
+    pub timeout: Duration,
+    /// Largest download, or checkout of a clone, in bytes
+    pub max_size: u64,
+}
+
+/// Where a remote input comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RemoteSource {
+    /// A single file served over HTTP
+    Http { url: String },
+    /// A git repository at a branch or tag, or at its default branch
+    Git {
+        url: String,
+        reference: Option<String>,
+    },
+}
+
+impl RemoteSource {
+    /// Recognize a remote input by its scheme, `None` for local paths
+    fn parse(input: &Path) -> Option<Self> {
+        let input = input.to_str()?;
+        if input.starts_with("http://") || input.starts_with("https://") {
+            return Some(Self::Http {
+                url: input.to_string(),
+            });
+        }
+
+        let url = if input.starts_with("git://") {
+            input
+        } else {
+            input.strip_prefix("git+")?
+        };
+        let (url, reference) = match url.split_once('#') {
+            Some((url, reference)) if !reference.is_empty() => (url, Some(reference.to_string())),
+            Some((url, _)) => (url, None),
+            None => (url, None),
+        };
+        Some(Self::Git {
+            url: url.to_string(),
+            reference,
+        })
+    }
+}
+
+/// Local copy of a remote input, removed when dropped
+#[derive(Debug)]
+pub struct FetchedInput {
+    /// The input as given on the command line
+    input: PathBuf,
+    /// The fetched file or checkout
+    path: PathBuf,
+    _dir: TempDir,
+}
+
+impl FetchedInput {
+    /// Fetch `input` if it names a remote, returning `None` for local paths
+    pub async fn fetch(input: &Path, limits: &RemoteLimits) -> Result<Option<Self>> {
+        let Some(source) = RemoteSource::parse(input) else {
+            return Ok(None);
+        };
+
+        let dir = tempfile::tempdir().context("Failed to create a directory for remote input")?;
+        let path = match &source {
+            RemoteSource::Http { url } => download(url, dir.path(), limits).await,
+            RemoteSource::Git { url, reference } => {
+                clone(url, reference.as_deref(), dir.path(), limits).await
+            }
+        }
+        .with_context(|| format!("Failed to fetch {}", input.display()))?;
+
+        info!("Fetched {} to {}", input.display(), path.display());
+        Ok(Some(Self {
+            input: input.to_path_buf(),
+            path,
+            _dir: dir,
+        }))
+    }
+
+    /// The local copy to compare
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Rename a path inside the local copy after the remote input it came from
+    pub fn restore_path(&self, path: &mut PathBuf) {
+        if let Ok(relative) = path.strip_prefix(&self.path) {
+            *path = if relative.as_os_str().is_empty() {
+                self.input.clone()
+            } else {
+                self.input.join(relative)
+            };
+        }
+    }
+}
+
+/// Download the file at `url` into `dir`, named after the URL's last segment
+async fn download(url: &str, dir: &Path, limits: &RemoteLimits) -> Result<PathBuf> {
+    let client = reqwest::Client::builder()
+        .timeout(limits.timeout)
+        .build()
+        .context("Failed to create HTTP client")?;
+    let mut response = client.get(url).send().await?.error_for_status()?;
+    if let Some(length) = response.content_length() {
+        if length > limits.max_size {
+            bail!(
+                "Remote file is {} bytes, more than the limit of {}",
+                length,
+                limits.max_size
+            );
+        }
+    }
+
+    // The declared length may be missing or wrong, so the body is counted too
+    let mut content = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if (content.len() + chunk.len()) as u64 > limits.max_size {
+            bail!(
+                "Remote file is more than the limit of {} bytes",
+                limits.max_size
+            );
+        }
+        content.extend_from_slice(&chunk);
+    }
+
+    let path = dir.join(file_name(url));
+    tokio::fs::write(&path, content).await?;
+    Ok(path)
+}
+
+/// Last segment of the path of `url`, without query or fragment
+fn file_name(url: &str) -> &str {
+    let url = url.split(['?', '#']).next().unwrap_or(url);
+    let after_host = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .split_once('/')
+        .map_or("", |(_, path)| path);
+    match after_host.rsplit('/').next() {
+        Some(name) if !name.is_empty() && name != "." && name != ".." => name,
+        _ => "download",
+    }
+}
+
+/// Shallow-clone `url` at `reference` into `dir`, without its history
+async fn clone(
+    url: &str,
+    reference: Option<&str>,
+    dir: &Path,
+    limits: &RemoteLimits,
+) -> Result<PathBuf> {
+    let checkout = dir.join("checkout");
+    let mut command = Command::new("git");
+    command.args(["clone", "--quiet", "--depth", "1"]);
+    if let Some(reference) = reference {
+        command.args(["--branch", reference]);
+    }
+    command
+        .arg("--")
+        .arg(url)
+        .arg(&checkout)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+
+    let output = tokio::time::timeout(limits.timeout, command.output())
+        .await
+        .with_context(|| format!("git clone timed out after {:?}", limits.timeout))?
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git clone failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    // Only the checked-out files are compared
+    std::fs::remove_dir_all(checkout.join(".git"))?;
+    let size = directory_size(&checkout)?;
+    if size > limits.max_size {
+        bail!(
+            "Checkout is {} bytes, more than the limit of {}",
+            size,
+            limits.max_size
+        );
+    }
+    Ok(checkout)
+}
+
+fn directory_size(dir: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            directory_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
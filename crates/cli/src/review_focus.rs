@@ -0,0 +1,204 @@
+//! Review order by risk
+//!
+//! Used by `compare --review-focus`. Each change gets a risk score between 0
+//! and 1 combining signals other analyses already produce: how much the
+//! function changed, whether its callers are affected, how often it is
+//! called, how much more complex it became and whether its tests were left
+//! untouched. Changes are put in descending order of risk and grouped into
+//! tiers, so a reviewer short on time knows what to read first.
+//!
+//! The weight of each signal is set in the `[review_focus]` table of the
+//! `--config` file; weights are relative, so they need not sum to 1.
+
+use crate::impact::impact_weight;
+use crate::output::{ComparisonResult, OutputFormatter, LARGE_COMPLEXITY_INCREASE};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use smart_diff_engine::ApiCompatibilityImpact;
+use smart_diff_parser::{Change, ChangeType};
+
+/// Relative weight of each risk signal
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskWeights {
+    /// How much of the function changed
+    pub magnitude: f64,
+    /// Whether callers must change: deletions, renames, signature changes and
+    /// breaking refactorings
+    pub api_impact: f64,
+    /// How many call sites the function has, found with the call graph
+    pub fan_in: f64,
+    /// How much its cyclomatic complexity grew
+    pub complexity: f64,
+    /// Whether its tests were left untouched, as found by `--check-test-updates`
+    pub test_coverage: f64,
+}
+
+impl Default for RiskWeights {
+    fn default() -> Self {
+        Self {
+            magnitude: 0.25,
+            api_impact: 0.3,
+            fan_in: 0.2,
+            complexity: 0.15,
+            test_coverage: 0.1,
+        }
+    }
+}
+
+impl RiskWeights {
+    /// Reject negative weights and weights that are all zero
+    pub fn validate(&self) -> Result<()> {
+        let weights = [
+            self.magnitude,
+            self.api_impact,
+            self.fan_in,
+            self.complexity,
+            self.test_coverage,
+        ];
+        if weights
+            .iter()
+            .any(|weight| !weight.is_finite() || *weight < 0.0)
+        {
+            bail!("Review focus weights must be non-negative numbers");
+        }
+        if weights.iter().sum::<f64>() == 0.0 {
+            bail!("At least one review focus weight must be positive");
+        }
+        Ok(())
+    }
+
+    fn total(&self) -> f64 {
+        self.magnitude + self.api_impact + self.fan_in + self.complexity + self.test_coverage
+    }
+}
+
+/// Risk tier of a change, from its risk score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskTier {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl RiskTier {
+    fn of(score: f64) -> Self {
+        match score {
+            s if s >= 0.75 => RiskTier::Critical,
+            s if s >= 0.5 => RiskTier::High,
+            s if s >= 0.25 => RiskTier::Medium,
+            _ => RiskTier::Low,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RiskTier::Low => "low",
+            RiskTier::Medium => "medium",
+            RiskTier::High => "high",
+            RiskTier::Critical => "critical",
+        }
+    }
+}
+
+/// Risk of a single change
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ChangeRisk {
+    pub score: f64,
+    pub tier: RiskTier,
+}
+
+/// Risk of `change`, a change of `result`
+pub fn change_risk(
+    result: &ComparisonResult,
+    change: &Change,
+    weights: &RiskWeights,
+) -> ChangeRisk {
+    let element = change.target.as_ref().or(change.source.as_ref());
+
+    let magnitude = OutputFormatter::change_magnitude(change);
+    let fan_in = element
+        .and_then(|element| result.fan_in.get(&element.name))
+        .map_or(0.0, |&calls| 1.0 - 1.0 / impact_weight(calls));
+    let complexity = element
+        .filter(|_| change.source.is_some())
+        .and_then(|element| result.complexity_deltas.get(&element.id))
+        .map_or(0.0, |delta| {
+            (delta.delta.max(0) as f64 / LARGE_COMPLEXITY_INCREASE as f64).min(1.0)
+        });
+    let test_coverage = match change.details.metadata.get("tests_updated") {
+        Some(updated) if updated == "false" => 1.0,
+        _ => 0.0,
+    };
+
+    let score = (weights.magnitude * magnitude
+        + weights.api_impact * api_impact(result, change)
+        + weights.fan_in * fan_in
+        + weights.complexity * complexity
+        + weights.test_coverage * test_coverage)
+        / weights.total();
+    ChangeRisk {
+        score,
+        tier: RiskTier::of(score),
+    }
+}
+
+/// How much a change affects the callers of its function, from 0 to 1
+fn api_impact(result: &ComparisonResult, change: &Change) -> f64 {
+    let by_change_type: f64 = match change.change_type {
+        ChangeType::Delete => match change.details.metadata.get("deletion_risk") {
+            Some(risk) if risk == "dead_code" => 0.2,
+            _ => 1.0,
+        },
+        ChangeType::Rename => 1.0,
+        ChangeType::CrossFileMove | ChangeType::Split | ChangeType::Merge => 0.5,
+        ChangeType::Add | ChangeType::Move | ChangeType::Modify => 0.0,
+    };
+    let by_signature = match (&change.source, &change.target) {
+        (Some(source), Some(target)) if source.signature != target.signature => 1.0,
+        _ => 0.0,
+    };
+
+    let names: Vec<&str> = change
+        .source
+        .iter()
+        .chain(change.target.iter())
+        .map(|element| element.name.as_str())
+        .collect();
+    let by_refactoring = result
+        .refactoring_patterns
+        .iter()
+        .filter(|pattern| {
+            pattern
+                .affected_elements
+                .iter()
+                .any(|affected| names.contains(&affected.as_str()))
+        })
+        .map(|pattern| match pattern.analysis.impact.api_compatibility {
+            ApiCompatibilityImpact::Breaking => 1.0,
+            ApiCompatibilityImpact::PotentiallyBreaking => 0.5,
+            ApiCompatibilityImpact::BackwardCompatible | ApiCompatibilityImpact::None => 0.0,
+        })
+        .fold(0.0, f64::max);
+
+    by_change_type.max(by_signature).max(by_refactoring)
+}
+
+/// Score the changes of `result` and put the riskiest first
+pub fn order_by_risk(result: &mut ComparisonResult, weights: &RiskWeights) {
+    let changes = std::mem::take(&mut result.diff_result.match_result.changes);
+    let mut scored: Vec<(Change, ChangeRisk)> = changes
+        .into_iter()
+        .map(|change| {
+            let risk = change_risk(result, &change, weights);
+            (change, risk)
+        })
+        .collect();
+    scored.sort_by(|(_, a), (_, b)| b.score.total_cmp(&a.score));
+
+    let (changes, risks) = scored.into_iter().unzip();
+    result.diff_result.match_result.changes = changes;
+    result.change_risks = risks;
+}
@@ -0,0 +1,308 @@
+//! Test vs. production file classification
+//!
+//! Used by `compare --separate-tests` to report test-file changes apart from
+//! production changes, and by `compare --check-test-updates` to tell whether a
+//! changed function's tests changed too. Classification is path based, with
+//! per-language naming conventions on top of common test directory names.
+//! Custom glob patterns replace the built-in heuristics entirely.
+//!
+//! Whole files are classified, never parts of them. Tests written inside a
+//! production file, such as an inline Rust `#[cfg(test)] mod tests { .. }`,
+//! count as production code along with the rest of the file; only test
+//! modules kept in a file of their own, like `mod tests;` in `tests.rs`, are
+//! told apart.
+
+use crate::cli::TestNameMatch;
+use crate::glob;
+use crate::output::ComparisonStats;
+use serde::Serialize;
+use smart_diff_parser::{Language, LanguageDetector};
+use std::path::Path;
+
+/// Directory names that hold test code in most ecosystems
+const TEST_DIRECTORIES: &[&str] = &["test", "tests", "__tests__", "spec", "testing"];
+
+/// Whether a file holds production or test code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileCategory {
+    Production,
+    Test,
+}
+
+/// Classifies files as test or production code
+#[derive(Debug, Clone, Default)]
+pub struct TestFileClassifier {
+    /// Custom glob patterns overriding the built-in heuristics
+    patterns: Vec<String>,
+}
+
+/// Comparison statistics split by file category
+#[derive(Debug, Clone, Default)]
+pub struct CategorizedStats {
+    pub production: ComparisonStats,
+    pub tests: ComparisonStats,
+}
+
+impl TestFileClassifier {
+    /// Create a classifier using custom glob patterns; empty falls back to heuristics
+    pub fn with_patterns(patterns: Vec<String>) -> Self {
+        Self { patterns }
+    }
+
+    /// Classify a file by its path
+    pub fn classify(&self, path: &Path) -> FileCategory {
+        let is_test = if self.patterns.is_empty() {
+            Self::in_test_directory(path) || Self::has_test_file_name(path)
+        } else {
+            self.patterns
+                .iter()
+                .any(|pattern| glob::matches_path(pattern, path))
+        };
+
+        if is_test {
+            FileCategory::Test
+        } else {
+            FileCategory::Production
+        }
+    }
+
+    /// Split per-file statistics into production and test buckets
+    pub fn categorize_stats<'a>(
+        &self,
+        files: impl IntoIterator<Item = (&'a Path, &'a ComparisonStats)>,
+    ) -> CategorizedStats {
+        let mut categorized = CategorizedStats::default();
+
+        for (path, stats) in files {
+            match self.classify(path) {
+                FileCategory::Production => categorized.production.merge(stats),
+                FileCategory::Test => categorized.tests.merge(stats),
+            }
+        }
+
+        categorized
+    }
+
+    fn in_test_directory(path: &Path) -> bool {
+        path.parent()
+            .map(|parent| {
+                parent.components().any(|c| {
+                    let name = c.as_os_str().to_string_lossy().to_lowercase();
+                    TEST_DIRECTORIES.contains(&name.as_str())
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    /// Language-specific test file naming conventions
+    fn has_test_file_name(path: &Path) -> bool {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+        match LanguageDetector::detect_from_path(path) {
+            Language::Go => stem.ends_with("_test"),
+            Language::Java => {
+                stem.ends_with("Test") || stem.ends_with("Tests") || stem.ends_with("IT")
+            }
+            Language::JavaScript | Language::TypeScript => {
+                stem.ends_with(".spec") || stem.ends_with(".test")
+            }
+            Language::Python => stem.starts_with("test_") || stem.ends_with("_test"),
+            // `#[cfg(test)] mod tests;` modules live in their own `tests.rs`
+            Language::Rust => {
+                file_name == "tests.rs" || file_name == "test.rs" || stem.ends_with("_test")
+            }
+            Language::C | Language::Cpp => {
+                stem.starts_with("test_") || stem.ends_with("_test") || stem.ends_with("_unittest")
+            }
+            Language::Ruby => stem.ends_with("_spec") || stem.ends_with("_test"),
+            Language::PHP | Language::Swift => stem.ends_with("Test") || stem.ends_with("Tests"),
+            Language::Markdown | Language::Dockerfile | Language::Unknown => false,
+        }
+    }
+}
+
+/// Matches test functions to the functions they test by naming convention
+#[derive(Debug, Clone, Copy)]
+pub struct TestNameMatcher {
+    mode: TestNameMatch,
+    /// Shortest function name matched by prefix, so that a test such as
+    /// `test_get_config` is not taken for a test of `get`
+    min_prefix_length: usize,
+}
+
+impl TestNameMatcher {
+    pub fn new(mode: TestNameMatch, min_prefix_length: usize) -> Self {
+        Self {
+            mode,
+            min_prefix_length,
+        }
+    }
+
+    /// Whether the test function `test` is named after `function`
+    ///
+    /// Names are compared without case or underscores, so `testParseLine`,
+    /// `test_parse_line` and `parse_line_test` all test `parseLine`.
+    pub fn matches(&self, test: &str, function: &str) -> bool {
+        let Some(tested) = Self::tested_name(test) else {
+            return false;
+        };
+        let function = Self::normalize(Self::last_segment(function));
+        if function.is_empty() {
+            return false;
+        }
+        tested == function
+            || (self.mode == TestNameMatch::Prefix
+                && function.len() >= self.min_prefix_length
+                && tested.starts_with(&function))
+    }
+
+    /// Name of the function a test is about, or `None` if `test` is not named like a test
+    fn tested_name(test: &str) -> Option<String> {
+        let name = Self::last_segment(test);
+        let after_prefix = ["test", "Test"]
+            .iter()
+            .find_map(|prefix| name.strip_prefix(prefix))
+            .filter(|rest| rest.starts_with('_') || rest.starts_with(char::is_uppercase));
+        let tested = after_prefix.or_else(|| {
+            ["_tests", "_test", "Tests", "Test"]
+                .iter()
+                .find_map(|suffix| name.strip_suffix(suffix))
+        })?;
+        let tested = Self::normalize(tested);
+        (!tested.is_empty()).then_some(tested)
+    }
+
+    /// `parse` of `Parser.parse` or `Parser::parse`
+    fn last_segment(name: &str) -> &str {
+        name.rsplit(['.', ':']).next().unwrap_or(name)
+    }
+
+    fn normalize(name: &str) -> String {
+        name.chars()
+            .filter(|c| *c != '_')
+            .flat_map(char::to_lowercase)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_classify_by_language_conventions() {
+        let classifier = TestFileClassifier::default();
+
+        let test_files = [
+            "pkg/server/handler_test.go",
+            "src/main/java/com/acme/ParserTest.java",
+            "web/src/app.spec.ts",
+            "web/src/app.test.js",
+            "lib/test_utils.py",
+            "crates/parser/src/tests.rs",
+            "crates/cli/tests/compare.rs",
+            "src/__tests__/widget.js",
+        ];
+        for path in test_files {
+            assert_eq!(
+                classifier.classify(Path::new(path)),
+                FileCategory::Test,
+                "{} should be a test file",
+                path
+            );
+        }
+
+        let production_files = [
+            "pkg/server/handler.go",
+            "src/main/java/com/acme/Parser.java",
+            "web/src/app.ts",
+            "lib/utils.py",
+            "crates/parser/src/lib.rs",
+            "src/testing_helpers_impl.c",
+        ];
+        for path in production_files {
+            assert_eq!(
+                classifier.classify(Path::new(path)),
+                FileCategory::Production,
+                "{} should be a production file",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_inline_test_modules_are_not_told_apart() {
+        let classifier = TestFileClassifier::default();
+
+        // `src/lib.rs` holding `#[cfg(test)] mod tests { .. }` is production code
+        assert_eq!(
+            classifier.classify(Path::new("src/lib.rs")),
+            FileCategory::Production
+        );
+        // While its `mod tests;` counterpart is a test file
+        assert_eq!(
+            classifier.classify(Path::new("src/lib/tests.rs")),
+            FileCategory::Test
+        );
+    }
+
+    #[test]
+    fn test_custom_patterns_override_heuristics() {
+        let classifier = TestFileClassifier::with_patterns(vec!["*_check.c".to_string()]);
+
+        assert_eq!(
+            classifier.classify(Path::new("src/math_check.c")),
+            FileCategory::Test
+        );
+        assert_eq!(
+            classifier.classify(Path::new("tests/math.c")),
+            FileCategory::Production
+        );
+    }
+
+    #[test]
+    fn test_categorize_stats_buckets_mixed_files() {
+        let classifier = TestFileClassifier::default();
+
+        let file_stats = |changes| ComparisonStats {
+            files_compared: 1,
+            changes_detected: changes,
+            ..Default::default()
+        };
+        let files = vec![
+            (PathBuf::from("src/parser.rs"), file_stats(3)),
+            (PathBuf::from("src/lexer.rs"), file_stats(1)),
+            (PathBuf::from("tests/parser_test.rs"), file_stats(7)),
+            (PathBuf::from("pkg/lexer_test.go"), file_stats(2)),
+            (PathBuf::from("pkg/lexer.go"), file_stats(4)),
+        ];
+
+        let categorized =
+            classifier.categorize_stats(files.iter().map(|(path, stats)| (path.as_path(), stats)));
+
+        assert_eq!(categorized.production.files_compared, 3);
+        assert_eq!(categorized.production.changes_detected, 8);
+        assert_eq!(categorized.tests.files_compared, 2);
+        assert_eq!(categorized.tests.changes_detected, 9);
+    }
+
+    #[test]
+    fn test_name_matcher_follows_conventions_and_is_tunable() {
+        let prefix = TestNameMatcher::new(TestNameMatch::Prefix, 4);
+        assert!(prefix.matches("testParseLine", "parseLine"));
+        assert!(prefix.matches("test_parse_line", "parseLine"));
+        assert!(prefix.matches("parse_line_test", "parse_line"));
+        assert!(prefix.matches("TestParseLine", "Parser.parseLine"));
+        assert!(prefix.matches("test_parse_line_rejects_tabs", "parse_line"));
+        assert!(!prefix.matches("test_get_config", "get"));
+        assert!(!prefix.matches("testament", "ament"));
+        assert!(!prefix.matches("parse_line", "parse_line"));
+
+        let exact = TestNameMatcher::new(TestNameMatch::Exact, 4);
+        assert!(exact.matches("test_parse_line", "parse_line"));
+        assert!(!exact.matches("test_parse_line_rejects_tabs", "parse_line"));
+    }
+}
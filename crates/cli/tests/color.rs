@@ -0,0 +1,48 @@
+//! Coloring of output by `--color` and the environment
+
+use assert_cmd::Command;
+
+fn compare(dir: &std::path::Path, color: Option<&str>) -> String {
+    let old = dir.join("old.c");
+    let new = dir.join("new.c");
+    std::fs::write(&old, "int add(int a, int b) {\n    return a + b;\n}\n").unwrap();
+    std::fs::write(
+        &new,
+        "int add(int a, int b) {\n    return a + b;\n}\n\
+         int mul(int a, int b) {\n    return a * b;\n}\n",
+    )
+    .unwrap();
+
+    let mut command = Command::cargo_bin("smart-diff").unwrap();
+    command.env_remove("NO_COLOR").env_remove("CLICOLOR_FORCE");
+    if let Some(color) = color {
+        command.arg(format!("--color={}", color));
+    }
+    let output = command
+        .args(["--quiet", "compare"])
+        .arg(&old)
+        .arg(&new)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_auto_color_is_off_when_stdout_is_not_a_terminal() {
+    let dir = tempfile::tempdir().unwrap();
+    let stdout = compare(dir.path(), None);
+    assert!(stdout.contains("mul"), "{}", stdout);
+    assert!(!stdout.contains("\x1b["), "{:?}", stdout);
+}
+
+#[test]
+fn test_always_colors_piped_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let stdout = compare(dir.path(), Some("always"));
+    assert!(stdout.contains("\x1b["), "{:?}", stdout);
+}
@@ -0,0 +1,48 @@
+//! Folded-stack profiles written by `compare --profile`
+#![cfg(feature = "profile")]
+
+use assert_cmd::Command;
+
+#[test]
+fn test_compare_writes_well_formed_folded_profile() {
+    let dir = tempfile::tempdir().unwrap();
+    let (old, new) = (dir.path().join("old"), dir.path().join("new"));
+    for (root, body) in [(&old, "a + b"), (&new, "b + a")] {
+        std::fs::create_dir_all(root).unwrap();
+        std::fs::write(
+            root.join("math.c"),
+            format!("int add(int a, int b) {{\n    return {};\n}}\n", body),
+        )
+        .unwrap();
+    }
+    let profile = dir.path().join("out.folded");
+
+    let output = Command::cargo_bin("smart-diff")
+        .unwrap()
+        .args(["--quiet", "compare"])
+        .args([&old, &new])
+        .args(["--format", "json", "--profile"])
+        .arg(&profile)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let folded = std::fs::read_to_string(&profile).unwrap();
+    assert!(!folded.is_empty());
+    for line in folded.lines() {
+        let (stack, count) = line.rsplit_once(' ').unwrap();
+        assert!(count.parse::<u64>().unwrap() > 0, "{}", line);
+        let frames: Vec<&str> = stack.split(';').collect();
+        assert_eq!(&frames[..2], ["smart-diff", "compare"], "{}", line);
+        assert!(frames.iter().all(|frame| !frame.is_empty()), "{}", line);
+    }
+    assert!(
+        folded.lines().any(|line| line.contains(";parsing;math.c ")),
+        "{}",
+        folded
+    );
+}
@@ -0,0 +1,55 @@
+//! Comparing an input piped to stdin against a file
+
+use assert_cmd::Command;
+
+#[test]
+fn test_compare_stdin_against_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let old = dir.path().join("math.c");
+    std::fs::write(&old, "int add(int a, int b) {\n    return a + b;\n}\n").unwrap();
+
+    let output = Command::cargo_bin("smart-diff")
+        .unwrap()
+        .args(["--quiet", "compare"])
+        .arg(&old)
+        .args(["-", "--stdin-lang", "c", "--format", "json"])
+        .write_stdin(
+            "int add(int a, int b) {\n    return a + b;\n}\n\
+             int mul(int a, int b) {\n    return a * b;\n}\n",
+        )
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let result = &report["results"][0];
+    assert_eq!(result["target_file"], "-");
+    let changes: Vec<(&str, &str)> = result["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|change| {
+            (
+                change["change_type"].as_str().unwrap(),
+                change["target"]["name"].as_str().unwrap_or_default(),
+            )
+        })
+        .collect();
+    assert_eq!(changes, vec![("Add", "mul")]);
+}
+
+#[test]
+fn test_compare_rejects_stdin_on_both_sides() {
+    let output = Command::cargo_bin("smart-diff")
+        .unwrap()
+        .args(["--quiet", "compare", "-", "-"])
+        .write_stdin("int add(int a, int b) { return a + b; }\n")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Only one of SOURCE and TARGET"));
+}
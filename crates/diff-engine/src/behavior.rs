@@ -0,0 +1,326 @@
+//! Behavior-preserving and behavior-changing modifications
+//!
+//! Similarity says how much of a function changed, not whether what it does
+//! changed. For a quick triage signal, each modification is labeled behavior
+//! preserving only when one of these checks shows its code is the same:
+//!
+//! - formatting only: nothing but whitespace changed
+//! - documentation only: nothing but comments and docstrings changed
+//! - rename only: identifiers were renamed consistently, and none of the
+//!   renamed ones names something outside the function, such as a called
+//!   function or a field
+//!
+//! The two versions are also checked for structural equivalence, the same
+//! tree of node types once comments are left out, which makes a rename more
+//! trustworthy. Everything else changes behavior. The labels are conservative:
+//! a preserving finding less certain than the configured minimum is reported
+//! as behavior changing.
+
+use crate::token_similarity::tokenize;
+use serde::{Deserialize, Serialize};
+use smart_diff_parser::{ASTNode, CommentSyntax, Language, NodeType, SyntaxSpanKind};
+use std::collections::{HashMap, HashSet};
+
+/// Confidence of each preserving check
+const FORMATTING_ONLY_CONFIDENCE: f64 = 0.99;
+const DOCUMENTATION_ONLY_CONFIDENCE: f64 = 0.95;
+const RENAME_ONLY_CONFIDENCE: f64 = 0.9;
+/// A rename whose syntax tree changed shape is trusted less
+const RESHAPED_RENAME_CONFIDENCE: f64 = 0.7;
+
+/// Whether a modification likely changed what the code does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BehaviorImpact {
+    BehaviorPreserving,
+    BehaviorChanging,
+}
+
+/// Check that found a modification to preserve behavior
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreservationEvidence {
+    FormattingOnly,
+    DocumentationOnly,
+    RenameOnly,
+}
+
+/// Behavior impact of one modification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BehaviorAssessment {
+    pub impact: BehaviorImpact,
+    /// Confidence in `impact`, from 0.0 to 1.0
+    pub confidence: f64,
+    /// Preserving check that passed, even when not confidently enough to count
+    pub evidence: Option<PreservationEvidence>,
+    /// Same tree of node types in both versions, comments aside
+    pub structurally_equivalent: bool,
+}
+
+/// Settings for behavior classification
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BehaviorClassifierConfig {
+    /// Lowest confidence at which a modification is labeled behavior preserving
+    pub min_preserving_confidence: f64,
+    /// Whether consistent renames of local identifiers preserve behavior
+    pub renames_preserve_behavior: bool,
+}
+
+impl Default for BehaviorClassifierConfig {
+    fn default() -> Self {
+        Self {
+            min_preserving_confidence: 0.85,
+            renames_preserve_behavior: true,
+        }
+    }
+}
+
+/// Labels modifications of one language as behavior preserving or changing
+pub struct BehaviorClassifier {
+    syntax: &'static CommentSyntax,
+    config: BehaviorClassifierConfig,
+}
+
+impl BehaviorClassifier {
+    pub fn new(language: Language, config: BehaviorClassifierConfig) -> Self {
+        Self {
+            syntax: CommentSyntax::for_language(&language),
+            config,
+        }
+    }
+
+    /// Classify the modification of a function from `source` to `target`
+    pub fn classify(&self, source: &ASTNode, target: &ASTNode) -> BehaviorAssessment {
+        let (old, new) = (
+            &source.metadata.original_text,
+            &target.metadata.original_text,
+        );
+        let structurally_equivalent = same_structure(source, target);
+
+        let old_tokens = self.code_tokens(old);
+        let new_tokens = self.code_tokens(new);
+        let finding = if old_tokens == new_tokens {
+            if self.documentation(old) == self.documentation(new) {
+                Some((
+                    PreservationEvidence::FormattingOnly,
+                    FORMATTING_ONLY_CONFIDENCE,
+                ))
+            } else {
+                Some((
+                    PreservationEvidence::DocumentationOnly,
+                    DOCUMENTATION_ONLY_CONFIDENCE,
+                ))
+            }
+        } else if self.config.renames_preserve_behavior
+            && renamed_only(source, target, &old_tokens, &new_tokens)
+        {
+            let confidence = if structurally_equivalent {
+                RENAME_ONLY_CONFIDENCE
+            } else {
+                RESHAPED_RENAME_CONFIDENCE
+            };
+            Some((PreservationEvidence::RenameOnly, confidence))
+        } else {
+            None
+        };
+
+        let (impact, confidence) = match finding {
+            Some((_, confidence)) if confidence >= self.config.min_preserving_confidence => {
+                (BehaviorImpact::BehaviorPreserving, confidence)
+            }
+            Some((_, confidence)) => (BehaviorImpact::BehaviorChanging, 1.0 - confidence),
+            // Code edited within the same structure, such as a changed
+            // operator or constant, is less clearly a change of behavior
+            None if structurally_equivalent => (BehaviorImpact::BehaviorChanging, 0.8),
+            None => (BehaviorImpact::BehaviorChanging, 0.95),
+        };
+        BehaviorAssessment {
+            impact,
+            confidence,
+            evidence: finding.map(|(evidence, _)| evidence),
+            structurally_equivalent,
+        }
+    }
+
+    /// Tokens of the code of `text`, without comments and docstrings, with
+    /// each string literal kept whole so whitespace inside it counts
+    fn code_tokens<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let docs = self.syntax.doc_spans(text);
+        let mut tokens = Vec::new();
+        for span in self.syntax.spans(text) {
+            let slice = &text[span.start..span.end];
+            match span.kind {
+                SyntaxSpanKind::Code => tokens.extend(tokenize(slice)),
+                SyntaxSpanKind::String if !docs.contains(&span) => tokens.push(slice),
+                SyntaxSpanKind::String | SyntaxSpanKind::Comment => {}
+            }
+        }
+        tokens
+    }
+
+    /// Words of the comments and docstrings of `text`
+    fn documentation<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let docs = self.syntax.doc_spans(text);
+        self.syntax
+            .spans(text)
+            .into_iter()
+            .filter(|span| span.kind == SyntaxSpanKind::Comment || docs.contains(span))
+            .flat_map(|span| text[span.start..span.end].split_whitespace())
+            .collect()
+    }
+}
+
+/// Whether two token sequences differ only by a consistent renaming of
+/// identifiers that do not name anything outside the function
+///
+/// The function's own name may be renamed wherever it appears.
+fn renamed_only(source: &ASTNode, target: &ASTNode, old: &[&str], new: &[&str]) -> bool {
+    if old.len() != new.len() {
+        return false;
+    }
+    let (source_identifiers, target_identifiers) = (identifiers(source), identifiers(target));
+    let own_name = |node: &ASTNode| node.metadata.attributes.get("name").cloned();
+    let (source_name, target_name) = (own_name(source), own_name(target));
+
+    let mut forward = HashMap::new();
+    let mut backward = HashMap::new();
+    let mut renamed = false;
+    for (i, (&a, &b)) in old.iter().zip(new).enumerate() {
+        if !(source_identifiers.contains(a) && target_identifiers.contains(b)) {
+            if a != b {
+                return false;
+            }
+            continue;
+        }
+        if *forward.entry(a).or_insert(b) != b || *backward.entry(b).or_insert(a) != a {
+            return false;
+        }
+        if a != b {
+            let renames_function =
+                source_name.as_deref() == Some(a) && target_name.as_deref() == Some(b);
+            if !renames_function && refers_outside(old, i) {
+                return false;
+            }
+            renamed = true;
+        }
+    }
+    renamed
+}
+
+/// Whether the identifier at `index` is called or reached through another
+/// name, and so likely names something defined outside the function
+fn refers_outside(tokens: &[&str], index: usize) -> bool {
+    let before = |offset: usize| index.checked_sub(offset).map(|i| tokens[i]);
+    tokens.get(index + 1) == Some(&"(")
+        || before(1) == Some(".")
+        || (before(1) == Some(":") && before(2) == Some(":"))
+        || (before(1) == Some(">") && before(2) == Some("-"))
+}
+
+/// Whether two trees have the same node types in the same shape, comments aside
+fn same_structure(source: &ASTNode, target: &ASTNode) -> bool {
+    fn code(node: &ASTNode) -> Vec<&ASTNode> {
+        node.children
+            .iter()
+            .filter(|child| child.node_type != NodeType::Comment)
+            .collect()
+    }
+    let (source_children, target_children) = (code(source), code(target));
+    source.node_type == target.node_type
+        && source_children.len() == target_children.len()
+        && source_children
+            .iter()
+            .zip(&target_children)
+            .all(|(source, target)| same_structure(source, target))
+}
+
+/// Texts of the identifier nodes under `node`
+fn identifiers(node: &ASTNode) -> HashSet<&str> {
+    let mut names = HashSet::new();
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        let is_identifier = node.node_type == NodeType::Identifier
+            || node
+                .metadata
+                .attributes
+                .get("kind")
+                .is_some_and(|kind| kind.ends_with("identifier"));
+        if is_identifier {
+            names.insert(node.metadata.original_text.trim());
+        }
+        stack.extend(&node.children);
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smart_diff_parser::{tree_sitter::TreeSitterParser, Parser};
+
+    fn parse_function(code: &str) -> ASTNode {
+        let parser = TreeSitterParser::new().unwrap();
+        let result = parser.parse(code, Language::Python).unwrap();
+        result.ast.find_by_type(&NodeType::Function)[0].clone()
+    }
+
+    fn classify(before: &str, after: &str) -> BehaviorAssessment {
+        BehaviorClassifier::new(Language::Python, BehaviorClassifierConfig::default())
+            .classify(&parse_function(before), &parse_function(after))
+    }
+
+    #[test]
+    fn test_reformatted_function_preserves_behavior() {
+        let assessment = classify(
+            "def clamp(value, low, high):\n    if value < low:\n        return low\n    return min(value, high)\n",
+            "def clamp(value,low,high):\n\n    if value<low:\n        return low\n    return min( value, high )\n",
+        );
+
+        assert_eq!(assessment.impact, BehaviorImpact::BehaviorPreserving);
+        assert_eq!(
+            assessment.evidence,
+            Some(PreservationEvidence::FormattingOnly)
+        );
+        assert!(assessment.structurally_equivalent);
+    }
+
+    #[test]
+    fn test_changed_condition_changes_behavior() {
+        let assessment = classify(
+            "def clamp(value, low, high):\n    if value < low:\n        return low\n    return min(value, high)\n",
+            "def clamp(value, low, high):\n    if value <= low:\n        return low\n    return min(value, high)\n",
+        );
+
+        assert_eq!(assessment.impact, BehaviorImpact::BehaviorChanging);
+        assert_eq!(assessment.evidence, None);
+    }
+
+    #[test]
+    fn test_local_rename_and_docstring_preserve_behavior_but_call_rename_does_not() {
+        let original = "def total(items):\n    result = sum(items)\n    return result\n";
+
+        let documented = classify(
+            original,
+            "def total(items):\n    \"\"\"Sum of the items.\"\"\"\n    # Plain sum\n    result = sum(items)\n    return result\n",
+        );
+        assert_eq!(documented.impact, BehaviorImpact::BehaviorPreserving);
+        assert_eq!(
+            documented.evidence,
+            Some(PreservationEvidence::DocumentationOnly)
+        );
+
+        let renamed = classify(
+            original,
+            "def total(values):\n    subtotal = sum(values)\n    return subtotal\n",
+        );
+        assert_eq!(renamed.impact, BehaviorImpact::BehaviorPreserving);
+        assert_eq!(renamed.evidence, Some(PreservationEvidence::RenameOnly));
+
+        let other_call = classify(
+            original,
+            "def total(items):\n    result = max(items)\n    return result\n",
+        );
+        assert_eq!(other_call.impact, BehaviorImpact::BehaviorChanging);
+    }
+}
@@ -0,0 +1,119 @@
+//! Compact binary encoding of comparison results
+//!
+//! Results are encoded as MessagePack behind a magic header and a format
+//! version: the CLI's `compare --format bin` reports, and the comparisons the
+//! MCP server caches on disk. MessagePack is self-describing, so flattened
+//! and untagged parts round-trip just as they do through JSON, and floats are
+//! stored bit for bit. Data written by an incompatible version is rejected
+//! rather than misread.
+
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// First bytes of everything in the binary format
+pub const MAGIC: &[u8; 8] = b"SDIFFBIN";
+
+/// Version of the binary layout, bumped whenever old data cannot be read
+pub const FORMAT_VERSION: u16 = 1;
+
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+/// Encode a value with the binary header
+pub fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(4096);
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    rmp_serde::encode::write_named(&mut bytes, value).context("Failed to encode binary data")?;
+    Ok(bytes)
+}
+
+/// Whether `bytes` start like binary data
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Decode binary data, rejecting other versions of the format
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    if !is_binary(bytes) {
+        bail!("Not in the binary format: missing the SDIFFBIN header");
+    }
+    let Some(version) = bytes.get(MAGIC.len()..HEADER_LEN) else {
+        bail!("Truncated binary format header");
+    };
+    let version = u16::from_le_bytes([version[0], version[1]]);
+    if version != FORMAT_VERSION {
+        bail!(
+            "Binary format version {} is not supported (expected {}); regenerate the data",
+            version,
+            FORMAT_VERSION
+        );
+    }
+    rmp_serde::from_slice(&bytes[HEADER_LEN..]).context("Failed to decode binary data")
+}
+
+/// Load a value written in either the binary format or JSON, told apart by the header
+pub fn load<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    if is_binary(bytes) {
+        decode(bytes)
+    } else {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use smart_diff_parser::{Change, ChangeDetail, ChangeType, CodeElement, ElementType};
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Report {
+        similarity: f64,
+        changes: Vec<Change>,
+    }
+
+    #[test]
+    fn test_load_detects_format_and_rejects_other_versions() {
+        let element = CodeElement {
+            id: "add".to_string(),
+            name: "add".to_string(),
+            file_path: "math.c".to_string(),
+            start_line: 1,
+            end_line: 3,
+            element_type: ElementType::Function,
+            signature: Some("int add(int a, int b)".to_string()),
+            hash: "1f".to_string(),
+        };
+        let report = Report {
+            similarity: 0.1 + 0.2,
+            changes: vec![Change {
+                change_type: ChangeType::Modify,
+                source: Some(element.clone()),
+                target: Some(element),
+                details: ChangeDetail {
+                    description: "Function add modified".to_string(),
+                    affected_lines: vec![2],
+                    similarity_score: Some(f64::MIN_POSITIVE / 2.0),
+                    refactoring_type: None,
+                    metadata: HashMap::from([("reason".to_string(), "body".to_string())]),
+                },
+                confidence: 0.9,
+            }],
+        };
+        let binary = encode(&report).unwrap();
+        assert!(binary.starts_with(b"SDIFFBIN\x01\x00"));
+
+        let from_binary: Report = load(&binary).unwrap();
+        let from_json: Report = load(&serde_json::to_vec(&report).unwrap()).unwrap();
+        assert_eq!(from_binary, report);
+        assert_eq!(from_json, report);
+
+        let mut newer = binary.clone();
+        newer[MAGIC.len()] = 2;
+        let error = load::<Report>(&newer).unwrap_err().to_string();
+        assert!(error.contains("version 2 is not supported"), "{}", error);
+        assert!(load::<Report>(&binary[..9]).is_err());
+    }
+}
@@ -0,0 +1,502 @@
+//! Call-site impact analysis for changed functions
+//!
+//! For every function whose signature changed between two versions, this module lists
+//! the call sites recorded by symbol resolution in both versions and flags the existing
+//! call sites that are likely to break under the new signature. Deleted functions are
+//! weighed the same way: one nothing called was dead code, one with callers removed a
+//! feature those callers relied on.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use smart_diff_semantic::{
+    EnhancedFunctionSignature, FunctionParameter, ReferenceType, ScopeType, SymbolKind, SymbolTable,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Configuration for call-site tracking
+#[derive(Debug, Clone)]
+pub struct CallSiteTrackerConfig {
+    /// Treat parameter type changes as breaking for callers
+    pub flag_parameter_type_changes: bool,
+    /// Treat return type changes as breaking for callers
+    pub flag_return_type_changes: bool,
+}
+
+impl Default for CallSiteTrackerConfig {
+    fn default() -> Self {
+        Self {
+            flag_parameter_type_changes: true,
+            flag_return_type_changes: false,
+        }
+    }
+}
+
+/// Call-site tracker
+pub struct CallSiteTracker {
+    config: CallSiteTrackerConfig,
+}
+
+/// Result of call-site impact analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallSiteAnalysisResult {
+    /// Impact per changed function, sorted by function name
+    pub impacts: Vec<FunctionCallImpact>,
+    /// Total call sites flagged as potentially breaking
+    pub potentially_breaking_count: usize,
+}
+
+/// Call sites affected by a single function's signature change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCallImpact {
+    /// Name of the changed function
+    pub function_name: String,
+    /// Why the signature change may break callers (empty if compatible)
+    pub breaking_reasons: Vec<String>,
+    /// Call sites in the source version
+    pub source_call_sites: Vec<CallSite>,
+    /// Call sites in the target version
+    pub target_call_sites: Vec<CallSite>,
+    /// Existing call sites written against the old signature that may no longer compile
+    pub potentially_breaking: Vec<CallSite>,
+}
+
+/// A single call site, reported as `(file, line, caller_function)`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CallSite {
+    /// File containing the call
+    pub file_path: String,
+    /// Line of the call
+    pub line: usize,
+    /// Function containing the call, if it could be determined
+    pub caller_function: Option<String>,
+}
+
+/// Risk of deleting a function, judged by its callers in the source version
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionRisk {
+    /// Nothing else called it, so removing it is cleanup
+    DeadCode,
+    /// Other code called it, and those callers had to change too
+    FeatureRemoval,
+}
+
+impl DeletionRisk {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeletionRisk::DeadCode => "dead_code",
+            DeletionRisk::FeatureRemoval => "feature_removal",
+        }
+    }
+}
+
+/// Callers of a deleted function in the source version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletionImpact {
+    /// Name of the deleted function
+    pub function_name: String,
+    pub risk: DeletionRisk,
+    /// Call sites outside the function itself
+    pub live_call_sites: Vec<CallSite>,
+}
+
+impl CallSiteTracker {
+    pub fn new(config: CallSiteTrackerConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn with_defaults() -> Self {
+        Self::new(CallSiteTrackerConfig::default())
+    }
+
+    /// Analyze call sites of every function whose signature changed
+    pub fn analyze(
+        &self,
+        source_symbols: &SymbolTable,
+        target_symbols: &SymbolTable,
+        source_signatures: &HashMap<String, EnhancedFunctionSignature>,
+        target_signatures: &HashMap<String, EnhancedFunctionSignature>,
+    ) -> Result<CallSiteAnalysisResult> {
+        let mut impacts = Vec::new();
+
+        let mut function_names: Vec<&String> = source_signatures
+            .keys()
+            .filter(|name| target_signatures.contains_key(*name))
+            .collect();
+        function_names.sort();
+
+        for function_name in function_names {
+            let source_sig = &source_signatures[function_name];
+            let target_sig = &target_signatures[function_name];
+
+            if !Self::signature_changed(source_sig, target_sig) {
+                continue;
+            }
+
+            // References are recorded under the called name, not the qualified one
+            let breaking_reasons = self.breaking_reasons(source_sig, target_sig);
+            let source_call_sites = Self::collect_call_sites(source_symbols, &source_sig.name);
+            let target_call_sites = Self::collect_call_sites(target_symbols, &target_sig.name);
+
+            let potentially_breaking = if breaking_reasons.is_empty() {
+                Vec::new()
+            } else {
+                source_call_sites.clone()
+            };
+
+            impacts.push(FunctionCallImpact {
+                function_name: function_name.clone(),
+                breaking_reasons,
+                source_call_sites,
+                target_call_sites,
+                potentially_breaking,
+            });
+        }
+
+        let potentially_breaking_count = impacts.iter().map(|i| i.potentially_breaking.len()).sum();
+
+        Ok(CallSiteAnalysisResult {
+            impacts,
+            potentially_breaking_count,
+        })
+    }
+
+    /// Weigh deleted functions by their callers in the source version
+    ///
+    /// Recursive calls do not keep a function alive, so only call sites in
+    /// other functions count.
+    pub fn analyze_deletions(
+        &self,
+        source_symbols: &SymbolTable,
+        deleted_functions: &[String],
+    ) -> Vec<DeletionImpact> {
+        deleted_functions
+            .iter()
+            .map(|function_name| {
+                let live_call_sites: Vec<CallSite> =
+                    Self::collect_call_sites(source_symbols, function_name)
+                        .into_iter()
+                        .filter(|site| site.caller_function.as_ref() != Some(function_name))
+                        .collect();
+                let risk = if live_call_sites.is_empty() {
+                    DeletionRisk::DeadCode
+                } else {
+                    DeletionRisk::FeatureRemoval
+                };
+                DeletionImpact {
+                    function_name: function_name.clone(),
+                    risk,
+                    live_call_sites,
+                }
+            })
+            .collect()
+    }
+
+    /// Check whether the callable shape of a function changed
+    fn signature_changed(
+        source_sig: &EnhancedFunctionSignature,
+        target_sig: &EnhancedFunctionSignature,
+    ) -> bool {
+        source_sig.parameters != target_sig.parameters
+            || source_sig.return_type != target_sig.return_type
+    }
+
+    /// Explain why existing callers may break under the new signature
+    fn breaking_reasons(
+        &self,
+        source_sig: &EnhancedFunctionSignature,
+        target_sig: &EnhancedFunctionSignature,
+    ) -> Vec<String> {
+        let mut reasons = Vec::new();
+
+        let source_names: HashSet<&str> = source_sig
+            .parameters
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        let target_names: HashSet<&str> = target_sig
+            .parameters
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+
+        for param in &target_sig.parameters {
+            if !source_names.contains(param.name.as_str()) && Self::is_required(param) {
+                reasons.push(format!("added required parameter '{}'", param.name));
+            }
+        }
+
+        for param in &source_sig.parameters {
+            if !target_names.contains(param.name.as_str()) {
+                reasons.push(format!("removed parameter '{}'", param.name));
+            }
+        }
+
+        if self.config.flag_parameter_type_changes {
+            for source_param in &source_sig.parameters {
+                if let Some(target_param) = target_sig
+                    .parameters
+                    .iter()
+                    .find(|p| p.name == source_param.name)
+                {
+                    if source_param.param_type != target_param.param_type {
+                        reasons.push(format!(
+                            "parameter '{}' changed type from {} to {}",
+                            source_param.name,
+                            source_param.param_type.base_type,
+                            target_param.param_type.base_type
+                        ));
+                    }
+                }
+            }
+        }
+
+        if self.config.flag_return_type_changes && source_sig.return_type != target_sig.return_type
+        {
+            reasons.push(format!(
+                "return type changed from {} to {}",
+                source_sig.return_type.base_type, target_sig.return_type.base_type
+            ));
+        }
+
+        reasons
+    }
+
+    /// Whether callers must supply an argument for this parameter
+    fn is_required(param: &FunctionParameter) -> bool {
+        !param.is_optional && !param.is_varargs && param.default_value.is_none()
+    }
+
+    /// Collect deduplicated call sites of a function from a symbol table
+    fn collect_call_sites(symbols: &SymbolTable, function_name: &str) -> Vec<CallSite> {
+        let mut call_sites: Vec<CallSite> = symbols
+            .get_references(function_name)
+            .into_iter()
+            .filter(|r| r.reference_type == ReferenceType::Call)
+            .map(|r| CallSite {
+                file_path: r.file_path.clone(),
+                line: r.line,
+                caller_function: Self::find_enclosing_function(symbols, &r.file_path, r.line),
+            })
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+
+        call_sites.sort();
+        call_sites
+    }
+
+    /// Find the innermost function whose scope contains `line`
+    fn find_enclosing_function(
+        symbols: &SymbolTable,
+        file_path: &str,
+        line: usize,
+    ) -> Option<String> {
+        let scope = symbols
+            .scoped_symbols
+            .values()
+            .filter(|s| {
+                s.scope_type == ScopeType::Function
+                    && s.file_path == file_path
+                    && s.start_line <= line
+                    && line <= s.end_line
+            })
+            .max_by_key(|s| s.start_line)?;
+
+        let parent = symbols.scoped_symbols.get(&scope.parent_id?)?;
+        parent
+            .symbols
+            .values()
+            .find(|s| {
+                matches!(s.symbol_kind, SymbolKind::Function | SymbolKind::Method)
+                    && s.line == scope.start_line
+            })
+            .map(|s| s.name.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smart_diff_semantic::{FunctionType, Symbol, SymbolReference, TypeSignature, Visibility};
+
+    fn create_parameter(name: &str, type_name: &str, position: usize) -> FunctionParameter {
+        FunctionParameter {
+            name: name.to_string(),
+            param_type: TypeSignature::new(type_name.to_string()),
+            default_value: None,
+            is_optional: false,
+            is_varargs: false,
+            annotations: Vec::new(),
+            position,
+        }
+    }
+
+    fn create_signature(
+        name: &str,
+        parameters: Vec<FunctionParameter>,
+    ) -> EnhancedFunctionSignature {
+        EnhancedFunctionSignature {
+            name: name.to_string(),
+            qualified_name: name.to_string(),
+            parameters,
+            return_type: TypeSignature::new("int".to_string()),
+            generic_parameters: Vec::new(),
+            visibility: Visibility::Public,
+            modifiers: Vec::new(),
+            annotations: Vec::new(),
+            file_path: "math.c".to_string(),
+            line: 1,
+            column: 1,
+            end_line: 5,
+            function_type: FunctionType::Function,
+            complexity_metrics: None,
+            dependencies: Vec::new(),
+            signature_hash: format!("hash_{}", name),
+            normalized_hash: format!("normalized_{}", name),
+        }
+    }
+
+    /// Build a symbol table with `callee` defined in math.c and called from `caller` in main.c
+    fn create_symbol_table(callee: &str, caller: &str, call_lines: &[usize]) -> SymbolTable {
+        let mut table = SymbolTable::new();
+
+        let math_scope = table.create_scope(None, ScopeType::File, "math.c".to_string(), 1, 100);
+        table.add_symbol(Symbol::new(
+            callee.to_string(),
+            SymbolKind::Function,
+            "math.c".to_string(),
+            1,
+            1,
+            math_scope,
+        ));
+
+        let main_scope = table.create_scope(None, ScopeType::File, "main.c".to_string(), 1, 100);
+        table.add_symbol(Symbol::new(
+            caller.to_string(),
+            SymbolKind::Function,
+            "main.c".to_string(),
+            10,
+            1,
+            main_scope,
+        ));
+        table.create_scope(
+            Some(main_scope),
+            ScopeType::Function,
+            "main.c".to_string(),
+            10,
+            60,
+        );
+
+        for &line in call_lines {
+            table.add_reference(
+                callee,
+                SymbolReference {
+                    file_path: "main.c".to_string(),
+                    line,
+                    column: 5,
+                    reference_type: ReferenceType::Call,
+                },
+            );
+        }
+
+        table
+    }
+
+    #[test]
+    fn test_added_required_parameter_flags_existing_call_sites() {
+        let tracker = CallSiteTracker::with_defaults();
+
+        let source_symbols = create_symbol_table("add", "main", &[12, 15]);
+        let target_symbols = create_symbol_table("add", "main", &[12]);
+
+        let mut source_signatures = HashMap::new();
+        source_signatures.insert(
+            "add".to_string(),
+            create_signature(
+                "add",
+                vec![
+                    create_parameter("a", "int", 0),
+                    create_parameter("b", "int", 1),
+                ],
+            ),
+        );
+
+        let mut target_signatures = HashMap::new();
+        target_signatures.insert(
+            "add".to_string(),
+            create_signature(
+                "add",
+                vec![
+                    create_parameter("a", "int", 0),
+                    create_parameter("b", "int", 1),
+                    create_parameter("carry", "int", 2),
+                ],
+            ),
+        );
+
+        let result = tracker
+            .analyze(
+                &source_symbols,
+                &target_symbols,
+                &source_signatures,
+                &target_signatures,
+            )
+            .unwrap();
+
+        assert_eq!(result.impacts.len(), 1);
+        let impact = &result.impacts[0];
+        assert_eq!(impact.function_name, "add");
+        assert!(impact
+            .breaking_reasons
+            .iter()
+            .any(|r| r.contains("added required parameter 'carry'")));
+
+        assert_eq!(
+            impact.potentially_breaking,
+            vec![
+                CallSite {
+                    file_path: "main.c".to_string(),
+                    line: 12,
+                    caller_function: Some("main".to_string()),
+                },
+                CallSite {
+                    file_path: "main.c".to_string(),
+                    line: 15,
+                    caller_function: Some("main".to_string()),
+                },
+            ]
+        );
+        assert_eq!(impact.target_call_sites.len(), 1);
+        assert_eq!(result.potentially_breaking_count, 2);
+    }
+
+    #[test]
+    fn test_optional_parameter_is_not_breaking() {
+        let tracker = CallSiteTracker::with_defaults();
+        let symbols = create_symbol_table("add", "main", &[12]);
+
+        let mut source_signatures = HashMap::new();
+        source_signatures.insert(
+            "add".to_string(),
+            create_signature("add", vec![create_parameter("a", "int", 0)]),
+        );
+
+        let mut optional = create_parameter("b", "int", 1);
+        optional.default_value = Some("0".to_string());
+        let mut target_signatures = HashMap::new();
+        target_signatures.insert(
+            "add".to_string(),
+            create_signature("add", vec![create_parameter("a", "int", 0), optional]),
+        );
+
+        let result = tracker
+            .analyze(&symbols, &symbols, &source_signatures, &target_signatures)
+            .unwrap();
+
+        assert_eq!(result.impacts.len(), 1);
+        assert!(result.impacts[0].breaking_reasons.is_empty());
+        assert_eq!(result.impacts[0].source_call_sites.len(), 1);
+        assert_eq!(result.potentially_breaking_count, 0);
+    }
+}
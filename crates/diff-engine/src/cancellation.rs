@@ -0,0 +1,31 @@
+//! Cancellation of long comparisons
+//!
+//! A front end that stops waiting for a comparison, such as the web UI once a
+//! request deadline passes, cancels the [`CancellationToken`] it handed to the
+//! matchers and detectors doing the work. They check it between units of work
+//! and give up early, so an abandoned comparison frees its thread soon after
+//! instead of running to the end for nobody.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Shared flag telling a comparison to stop; clones cancel together
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every holder of the token to stop
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
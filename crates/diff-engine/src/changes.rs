@@ -4,6 +4,7 @@
 //! code changes with detailed analysis, confidence scoring, and integration with
 //! tree edit distance and similarity scoring algorithms.
 
+use crate::identifier::normalize_identifier;
 use crate::similarity_scorer::{
     ComprehensiveSimilarityScore, SimilarityScorer, SimilarityScoringConfig,
 };
@@ -17,12 +18,14 @@ use std::collections::HashMap;
 /// Configuration for change classification
 #[derive(Debug, Clone)]
 pub struct ChangeClassificationConfig {
-    /// Minimum similarity threshold for considering elements as modified (vs replaced)
-    pub modification_threshold: f64,
-    /// Minimum similarity threshold for rename detection
-    pub rename_threshold: f64,
-    /// Minimum similarity threshold for move detection
-    pub move_threshold: f64,
+    /// Minimum similarity for a matched pair to count as modified rather than replaced
+    pub modify_min_similarity: f64,
+    /// Minimum similarity for a renamed pair to be classified as a rename
+    pub rename_min_similarity: f64,
+    /// Minimum similarity for a repositioned pair to be classified as a move
+    pub move_min_similarity: f64,
+    /// Minimum similarity for a matched pair to be reported as identical
+    pub identical_min_similarity: f64,
     /// Enable detailed AST-based analysis
     pub enable_ast_analysis: bool,
     /// Enable semantic analysis integration
@@ -33,19 +36,23 @@ pub struct ChangeClassificationConfig {
     pub max_ast_depth: usize,
     /// Enable change impact analysis
     pub enable_impact_analysis: bool,
+    /// Compare names by their words, so `getUserName` and `get_user_name` are equal
+    pub normalize_identifiers: bool,
 }
 
 impl Default for ChangeClassificationConfig {
     fn default() -> Self {
         Self {
-            modification_threshold: 0.7,
-            rename_threshold: 0.8,
-            move_threshold: 0.9,
+            modify_min_similarity: 0.7,
+            rename_min_similarity: 0.8,
+            move_min_similarity: 0.9,
+            identical_min_similarity: 0.99,
             enable_ast_analysis: true,
             enable_semantic_analysis: true,
             enable_confidence_scoring: true,
             max_ast_depth: 20,
             enable_impact_analysis: true,
+            normalize_identifiers: false,
         }
     }
 }
@@ -120,6 +127,10 @@ pub enum CharacteristicType {
     DependencyChange,
     /// Signature change
     SignatureChange,
+    /// Repeated expression replaced by a new local variable
+    ExtractVariable,
+    /// Local variable replaced by its expression at each use
+    InlineVariable,
 }
 
 /// Evidence supporting a classification
@@ -513,7 +524,8 @@ impl ChangeClassifier {
                 .collect(),
             });
 
-            if name_similarity > self.config.rename_threshold && primary_type != ChangeType::Rename
+            if name_similarity > self.config.rename_min_similarity
+                && primary_type != ChangeType::Rename
             {
                 alternatives.push(AlternativeClassification {
                     change_type: ChangeType::Rename,
@@ -523,6 +535,19 @@ impl ChangeClassifier {
             }
         }
 
+        // Low overall similarity suggests the element was replaced, not edited
+        if let Some(metrics) = &similarity_metrics {
+            if primary_type == ChangeType::Modify
+                && metrics.overall_similarity < self.config.modify_min_similarity
+            {
+                alternatives.push(AlternativeClassification {
+                    change_type: ChangeType::Delete,
+                    confidence: 1.0 - metrics.overall_similarity,
+                    reason: "Low similarity suggests the element was replaced".to_string(),
+                });
+            }
+        }
+
         // Analyze location changes
         if source.file_path != target.file_path {
             characteristics.push(ChangeCharacteristic {
@@ -646,6 +671,11 @@ impl ChangeClassifier {
             }
         }
 
+        // Analyze refactorings within the function body
+        if let (Some(src_ast), Some(tgt_ast)) = (source_ast, target_ast) {
+            characteristics.extend(variable_refactoring_characteristics(src_ast, tgt_ast));
+        }
+
         // Calculate overall complexity score
         let complexity_score = self.calculate_change_complexity(
             source,
@@ -725,13 +755,13 @@ impl ChangeClassifier {
                 .map(|m| m.overall_similarity)
                 .unwrap_or(name_similarity);
 
-            if overall_similarity > self.config.rename_threshold {
+            if overall_similarity > self.config.rename_min_similarity {
                 return (ChangeType::Rename, overall_similarity);
             }
         }
 
         // Move within same file (only if high similarity)
-        if is_moved_in_file && similarity > self.config.move_threshold {
+        if is_moved_in_file && similarity > self.config.move_min_similarity {
             return (ChangeType::Move, similarity);
         }
 
@@ -745,6 +775,18 @@ impl ChangeClassifier {
             return 1.0;
         }
 
+        if self.config.normalize_identifiers {
+            return self.edit_distance_similarity(
+                &normalize_identifier(name1),
+                &normalize_identifier(name2),
+            );
+        }
+
+        self.edit_distance_similarity(name1, name2)
+    }
+
+    /// Similarity from the Levenshtein distance relative to the longer string
+    fn edit_distance_similarity(&self, name1: &str, name2: &str) -> f64 {
         let max_len = name1.len().max(name2.len());
         if max_len == 0 {
             return 1.0;
@@ -1237,6 +1279,164 @@ impl ChangeClassifier {
     }
 }
 
+/// Node kinds binding one name to an initializer, across the supported grammars
+const DECLARATOR_KINDS: &[&str] = &[
+    "variable_declarator",
+    "init_declarator",
+    "let_declaration",
+    "assignment",
+];
+
+/// An expression written out in one version of a function and bound to a
+/// local variable in the other
+struct VariableRefactoring {
+    variable: String,
+    expression: String,
+    /// Occurrences of the expression replaced by the variable
+    occurrences: usize,
+}
+
+/// Extract variable and inline variable characteristics between two versions of a function
+fn variable_refactoring_characteristics(
+    source: &ASTNode,
+    target: &ASTNode,
+) -> Vec<ChangeCharacteristic> {
+    let mut characteristics = Vec::new();
+    if let Some(extracted) = find_variable_refactoring(source, target) {
+        characteristics.push(ChangeCharacteristic {
+            characteristic_type: CharacteristicType::ExtractVariable,
+            value: format!(
+                "Extracted '{}' into variable '{}' ({} occurrence{})",
+                extracted.expression,
+                extracted.variable,
+                extracted.occurrences,
+                if extracted.occurrences == 1 { "" } else { "s" }
+            ),
+            confidence: variable_refactoring_confidence(extracted.occurrences),
+        });
+    }
+    if let Some(inlined) = find_variable_refactoring(target, source) {
+        characteristics.push(ChangeCharacteristic {
+            characteristic_type: CharacteristicType::InlineVariable,
+            value: format!(
+                "Inlined variable '{}' as '{}' ({} occurrence{})",
+                inlined.variable,
+                inlined.expression,
+                inlined.occurrences,
+                if inlined.occurrences == 1 { "" } else { "s" }
+            ),
+            confidence: variable_refactoring_confidence(inlined.occurrences),
+        });
+    }
+    characteristics
+}
+
+/// A single replaced occurrence may be coincidence; each further one makes it likelier
+fn variable_refactoring_confidence(occurrences: usize) -> f64 {
+    (0.4 + 0.2 * occurrences as f64).min(0.95)
+}
+
+/// Find a variable declared in `with_variable` whose initializer appears, as
+/// identical subtrees, in `with_expression` where the variable is now referenced
+///
+/// Returns the variable replacing the most occurrences.
+fn find_variable_refactoring(
+    with_expression: &ASTNode,
+    with_variable: &ASTNode,
+) -> Option<VariableRefactoring> {
+    let mut existing = Vec::new();
+    collect_declarations(with_expression, &mut existing);
+
+    let mut declarations = Vec::new();
+    collect_declarations(with_variable, &mut declarations);
+
+    declarations
+        .into_iter()
+        .filter(|(name, _)| !existing.iter().any(|(existing, _)| existing == name))
+        .filter_map(|(variable, initializer)| {
+            let expression = compact(&initializer.metadata.original_text);
+            let before = count_subtrees(with_expression, &expression);
+            // The initializer itself is one of the remaining occurrences
+            let remaining = count_subtrees(with_variable, &expression).saturating_sub(1);
+            let occurrences = before.saturating_sub(remaining);
+            let references = count_identifiers(with_variable, &variable).saturating_sub(1);
+            (occurrences > 0 && references >= occurrences).then(|| VariableRefactoring {
+                variable,
+                expression: initializer.metadata.original_text.trim().to_string(),
+                occurrences,
+            })
+        })
+        .max_by_key(|refactoring| refactoring.occurrences)
+}
+
+/// Collect `(name, initializer)` of declarations whose initializer is more
+/// than a lone identifier or literal
+fn collect_declarations<'a>(node: &'a ASTNode, declarations: &mut Vec<(String, &'a ASTNode)>) {
+    let kind = node.metadata.attributes.get("kind").map(String::as_str);
+    if kind.is_some_and(|kind| DECLARATOR_KINDS.contains(&kind)) {
+        let name = node
+            .children
+            .iter()
+            .find(|child| child.node_type == smart_diff_parser::NodeType::Identifier);
+        let initializer = node
+            .children
+            .iter()
+            .skip_while(|child| {
+                child.metadata.attributes.get("kind").map(String::as_str) != Some("=")
+            })
+            .nth(1)
+            .map(unwrap_parentheses);
+        if let (Some(name), Some(initializer)) = (name, initializer) {
+            if !initializer.children.is_empty() {
+                declarations.push((name.metadata.original_text.clone(), initializer));
+            }
+        }
+    }
+    for child in &node.children {
+        collect_declarations(child, declarations);
+    }
+}
+
+fn unwrap_parentheses(node: &ASTNode) -> &ASTNode {
+    match (
+        node.metadata.attributes.get("kind").map(String::as_str),
+        node.children.as_slice(),
+    ) {
+        (Some("parenthesized_expression"), [inner]) => unwrap_parentheses(inner),
+        _ => node,
+    }
+}
+
+/// Count the outermost subtrees whose text is `expression`, ignoring whitespace
+fn count_subtrees(node: &ASTNode, expression: &str) -> usize {
+    if !node.children.is_empty()
+        && node.metadata.original_text.len() >= expression.len()
+        && compact(&node.metadata.original_text) == expression
+    {
+        return 1;
+    }
+    node.children
+        .iter()
+        .map(|child| count_subtrees(child, expression))
+        .sum()
+}
+
+fn count_identifiers(node: &ASTNode, name: &str) -> usize {
+    let own = usize::from(
+        node.node_type == smart_diff_parser::NodeType::Identifier
+            && node.metadata.original_text == name,
+    );
+    own + node
+        .children
+        .iter()
+        .map(|child| count_identifiers(child, name))
+        .sum::<usize>()
+}
+
+fn compact(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1316,9 +1516,9 @@ mod tests {
     fn test_change_classification_config_default() {
         let config = ChangeClassificationConfig::default();
 
-        assert_eq!(config.modification_threshold, 0.7);
-        assert_eq!(config.rename_threshold, 0.8);
-        assert_eq!(config.move_threshold, 0.9);
+        assert_eq!(config.modify_min_similarity, 0.7);
+        assert_eq!(config.rename_min_similarity, 0.8);
+        assert_eq!(config.move_min_similarity, 0.9);
         assert!(config.enable_ast_analysis);
         assert!(config.enable_semantic_analysis);
         assert!(config.enable_confidence_scoring);
@@ -1448,6 +1648,28 @@ mod tests {
         assert!(similarity > 0.8);
     }
 
+    #[test]
+    fn test_normalized_name_similarity_ignores_naming_convention() {
+        let plain = ChangeClassifier::new(Language::Java);
+        assert!(plain.calculate_name_similarity("getUserName", "get_user_name") < 1.0);
+
+        let config = ChangeClassificationConfig {
+            normalize_identifiers: true,
+            ..Default::default()
+        };
+        let classifier = ChangeClassifier::with_config(Language::Java, config);
+
+        assert_eq!(
+            classifier.calculate_name_similarity("getUserName", "get_user_name"),
+            1.0
+        );
+        assert_eq!(
+            classifier.calculate_name_similarity("GetUserName", "get_user_name"),
+            1.0
+        );
+        assert!(classifier.calculate_name_similarity("getUser", "getUsers") < 1.0);
+    }
+
     #[test]
     fn test_levenshtein_distance() {
         let classifier = ChangeClassifier::new(Language::Java);
@@ -1550,29 +1772,52 @@ mod tests {
         assert!(description.contains("new.java"));
     }
 
+    #[test]
+    fn test_rename_min_similarity_decides_borderline_rename() {
+        // Name similarity is 15/16 ≈ 0.94 with no AST metrics available
+        let source = create_test_code_element("calculate_total", "test.java", 10);
+        let target = create_test_code_element("calculate_totals", "test.java", 10);
+
+        let classify = |rename_min_similarity| {
+            let config = ChangeClassificationConfig {
+                rename_min_similarity,
+                ..Default::default()
+            };
+            ChangeClassifier::with_config(Language::Java, config)
+                .classify_change_detailed(Some(&source), Some(&target), None, None, None, None)
+                .unwrap()
+                .change_type
+        };
+
+        assert_eq!(classify(0.9), ChangeType::Rename);
+        assert_eq!(classify(0.95), ChangeType::Modify);
+    }
+
     #[test]
     fn test_configuration_updates() {
         let mut classifier = ChangeClassifier::new(Language::Java);
 
-        let original_threshold = classifier.get_config().modification_threshold;
+        let original_threshold = classifier.get_config().modify_min_similarity;
         assert_eq!(original_threshold, 0.7);
 
         let new_config = ChangeClassificationConfig {
-            modification_threshold: 0.8,
-            rename_threshold: 0.9,
-            move_threshold: 0.95,
+            modify_min_similarity: 0.8,
+            rename_min_similarity: 0.9,
+            move_min_similarity: 0.95,
+            identical_min_similarity: 1.0,
             enable_ast_analysis: false,
             enable_semantic_analysis: false,
             enable_confidence_scoring: false,
             max_ast_depth: 10,
             enable_impact_analysis: false,
+            normalize_identifiers: false,
         };
 
         classifier.set_config(new_config);
 
-        assert_eq!(classifier.get_config().modification_threshold, 0.8);
-        assert_eq!(classifier.get_config().rename_threshold, 0.9);
-        assert_eq!(classifier.get_config().move_threshold, 0.95);
+        assert_eq!(classifier.get_config().modify_min_similarity, 0.8);
+        assert_eq!(classifier.get_config().rename_min_similarity, 0.9);
+        assert_eq!(classifier.get_config().move_min_similarity, 0.95);
         assert!(!classifier.get_config().enable_ast_analysis);
         assert!(!classifier.get_config().enable_semantic_analysis);
     }
@@ -1592,4 +1837,84 @@ mod tests {
         classifier.set_semantic_analysis(true);
         assert!(classifier.similarity_scorer.is_some());
     }
+
+    #[test]
+    fn test_extract_variable_detected_within_modified_function() -> Result<()> {
+        use smart_diff_parser::{tree_sitter::TreeSitterParser, NodeType, Parser};
+
+        fn parse_method(code: &str) -> ASTNode {
+            let parser = TreeSitterParser::new().unwrap();
+            let result = parser.parse(code, Language::Java).unwrap();
+            result.ast.find_by_type(&NodeType::Method)[0].clone()
+        }
+
+        let duplicated = parse_method(
+            r#"
+class Order {
+    double total(double price, int quantity) {
+        if (price * quantity > 100) {
+            return price * quantity * 0.9;
+        }
+        return price * quantity;
+    }
+}
+"#,
+        );
+        let extracted = parse_method(
+            r#"
+class Order {
+    double total(double price, int quantity) {
+        double subtotal = price * quantity;
+        if (subtotal > 100) {
+            return subtotal * 0.9;
+        }
+        return subtotal;
+    }
+}
+"#,
+        );
+
+        let mut classifier = ChangeClassifier::new(Language::Java);
+        let source = create_test_code_element("total", "Order.java", 3);
+        let target = create_test_code_element("total", "Order.java", 3);
+
+        let result = classifier.classify_change_detailed(
+            Some(&source),
+            Some(&target),
+            Some(&duplicated),
+            Some(&extracted),
+            None,
+            None,
+        )?;
+        assert_eq!(result.change_type, ChangeType::Modify);
+        let characteristic = result
+            .analysis
+            .characteristics
+            .iter()
+            .find(|c| c.characteristic_type == CharacteristicType::ExtractVariable)
+            .expect("extract variable characteristic");
+        assert!(characteristic.value.contains("'subtotal'"));
+        assert!(characteristic.value.contains("3 occurrences"));
+        assert!(characteristic.confidence > 0.9);
+
+        // The same pair read backwards inlines the variable
+        let reverse = classifier.classify_change_detailed(
+            Some(&target),
+            Some(&source),
+            Some(&extracted),
+            Some(&duplicated),
+            None,
+            None,
+        )?;
+        let characteristic_types: Vec<_> = reverse
+            .analysis
+            .characteristics
+            .iter()
+            .map(|c| &c.characteristic_type)
+            .collect();
+        assert!(characteristic_types.contains(&&CharacteristicType::InlineVariable));
+        assert!(!characteristic_types.contains(&&CharacteristicType::ExtractVariable));
+
+        Ok(())
+    }
 }
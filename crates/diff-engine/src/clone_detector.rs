@@ -0,0 +1,481 @@
+//! Clone detection across files
+//!
+//! Copy-pasted functions drift apart: one copy gets a renamed variable,
+//! another a different constant or an extra check. Each function is reduced
+//! to its sequence of leaf tokens, and again with identifiers and literals
+//! abstracted away; functions whose abstracted sequences mostly agree are
+//! grouped into clusters of exact copies (Type-1), copies with renamed
+//! identifiers or changed literals (Type-2) and copies with tokens added or
+//! removed (Type-3). Each cluster comes with the signature of a shared
+//! function that could replace its members and the lines that would save.
+
+use crate::identifier::identifier_words;
+use crate::literal_changes::is_literal;
+use serde::{Deserialize, Serialize};
+use smart_diff_parser::{ASTNode, Function, NodeType};
+use std::collections::HashMap;
+
+/// Node kinds holding a function's parameter list in the supported grammars
+const PARAMETER_LIST_KINDS: &[&str] = &["parameters", "formal_parameters", "parameter_list"];
+
+/// Stands in for every identifier when comparing structure
+const IDENTIFIER_TOKEN: &str = "\0identifier";
+
+/// Stands in for every literal when comparing structure
+const LITERAL_TOKEN: &str = "\0literal";
+
+/// Settings for clone detection
+#[derive(Debug, Clone)]
+pub struct CloneDetectorConfig {
+    /// Lowest token similarity (0.0 to 1.0) for two functions to be clones
+    pub min_similarity: f64,
+    /// Functions with fewer tokens are too small to be worth extracting
+    pub min_tokens: usize,
+}
+
+impl Default for CloneDetectorConfig {
+    fn default() -> Self {
+        Self {
+            min_similarity: 0.8,
+            min_tokens: 20,
+        }
+    }
+}
+
+/// How closely the members of a clone cluster agree, closest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloneType {
+    /// Identical apart from layout, comments and the function's name (Type-1)
+    Exact,
+    /// Same structure, with identifiers or literals changed (Type-2)
+    Renamed,
+    /// Similar structure, with tokens added or removed (Type-3)
+    NearMiss,
+}
+
+/// One copy in a clone cluster
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloneInstance {
+    pub file_path: String,
+    pub name: String,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+impl CloneInstance {
+    fn lines(&self) -> usize {
+        self.end_line.saturating_sub(self.start_line) + 1
+    }
+}
+
+/// Functions that are copies of each other
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloneCluster {
+    /// Loosest kind of copy among the pairs that formed the cluster
+    pub clone_type: CloneType,
+    /// Lowest similarity among the pairs that formed the cluster
+    pub similarity: f64,
+    /// Copies ordered by file and line
+    pub instances: Vec<CloneInstance>,
+    /// Signature of a shared function that could replace the copies, with a
+    /// parameter for each literal that differs between them
+    pub suggested_signature: String,
+    /// Lines saved by replacing each copy with a one-line call to the shared function
+    pub estimated_lines_saved: usize,
+}
+
+/// Groups functions that are copies of each other into clusters
+#[derive(Debug, Clone, Default)]
+pub struct CloneDetector {
+    config: CloneDetectorConfig,
+}
+
+/// A function reduced to its tokens
+struct Candidate<'a> {
+    function: &'a Function,
+    /// Leaf texts, without the function's name
+    tokens: Vec<&'a str>,
+    /// `tokens` with identifiers and literals replaced by placeholders
+    abstracted: Vec<&'a str>,
+    counts: HashMap<&'a str, usize>,
+}
+
+impl<'a> Candidate<'a> {
+    fn new(function: &'a Function) -> Self {
+        let mut candidate = Self {
+            function,
+            tokens: Vec::new(),
+            abstracted: Vec::new(),
+            counts: HashMap::new(),
+        };
+        let mut name = Some(function.signature.name.as_str());
+        candidate.collect(&function.body, &mut name);
+        for token in &candidate.abstracted {
+            *candidate.counts.entry(*token).or_default() += 1;
+        }
+        candidate
+    }
+
+    /// Collect leaf tokens in source order, skipping the first occurrence of
+    /// the function's name so copies under other names still match exactly
+    fn collect(&mut self, node: &'a ASTNode, name: &mut Option<&str>) {
+        let literal = is_literal(node);
+        if !literal && !node.children.is_empty() {
+            for child in &node.children {
+                self.collect(child, name);
+            }
+            return;
+        }
+
+        let text = node.metadata.original_text.trim();
+        if text.is_empty() {
+            return;
+        }
+        if *name == Some(text) {
+            *name = None;
+            return;
+        }
+        self.tokens.push(text);
+        self.abstracted.push(if literal {
+            LITERAL_TOKEN
+        } else if is_identifier(node) {
+            IDENTIFIER_TOKEN
+        } else {
+            text
+        });
+    }
+
+    fn instance(&self) -> CloneInstance {
+        CloneInstance {
+            file_path: self.function.location.file_path.clone(),
+            name: self.function.signature.name.clone(),
+            start_line: self.function.location.start_line,
+            end_line: self.function.location.end_line,
+        }
+    }
+
+    /// Whether one function contains the other, such as a nested closure
+    fn overlaps(&self, other: &Candidate) -> bool {
+        let (a, b) = (&self.function.location, &other.function.location);
+        a.file_path == b.file_path && a.start_line <= b.end_line && b.start_line <= a.end_line
+    }
+}
+
+fn is_identifier(node: &ASTNode) -> bool {
+    node.node_type == NodeType::Identifier
+        || node
+            .metadata
+            .attributes
+            .get("kind")
+            .is_some_and(|kind| kind.ends_with("identifier"))
+}
+
+impl CloneDetector {
+    pub fn new(config: CloneDetectorConfig) -> Self {
+        Self { config }
+    }
+
+    /// Clone clusters among `functions`, those saving the most lines first
+    pub fn detect(&self, functions: &[Function]) -> Vec<CloneCluster> {
+        let candidates: Vec<Candidate> = functions
+            .iter()
+            .map(Candidate::new)
+            .filter(|candidate| candidate.tokens.len() >= self.config.min_tokens)
+            .collect();
+
+        let mut roots: Vec<usize> = (0..candidates.len()).collect();
+        let mut links = Vec::new();
+        for i in 0..candidates.len() {
+            for j in i + 1..candidates.len() {
+                let (a, b) = (&candidates[i], &candidates[j]);
+                if a.overlaps(b) {
+                    continue;
+                }
+                if let Some(link) = self.compare(a, b) {
+                    let (root_a, root_b) = (find(&mut roots, i), find(&mut roots, j));
+                    roots[root_b] = root_a;
+                    links.push((i, link));
+                }
+            }
+        }
+
+        let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+        for index in 0..candidates.len() {
+            let root = find(&mut roots, index);
+            members.entry(root).or_default().push(index);
+        }
+
+        let mut clusters: Vec<CloneCluster> = members
+            .into_iter()
+            .filter(|(_, members)| members.len() > 1)
+            .map(|(root, mut members)| {
+                let (clone_type, similarity) = links
+                    .iter()
+                    .filter(|(index, _)| find(&mut roots, *index) == root)
+                    .fold(
+                        (CloneType::Exact, 1.0f64),
+                        |(clone_type, similarity), (_, (link_type, link_similarity))| {
+                            (clone_type.max(*link_type), similarity.min(*link_similarity))
+                        },
+                    );
+                members.sort_by_key(|&index| {
+                    let location = &candidates[index].function.location;
+                    (location.file_path.clone(), location.start_line)
+                });
+                let members: Vec<&Candidate> =
+                    members.iter().map(|&index| &candidates[index]).collect();
+                let instances: Vec<CloneInstance> =
+                    members.iter().map(|member| member.instance()).collect();
+                let total_lines: usize = instances.iter().map(CloneInstance::lines).sum();
+
+                CloneCluster {
+                    clone_type,
+                    similarity,
+                    suggested_signature: suggest_signature(&members),
+                    estimated_lines_saved: total_lines
+                        .saturating_sub(instances[0].lines() + instances.len()),
+                    instances,
+                }
+            })
+            .collect();
+
+        clusters.sort_by(|a, b| {
+            b.estimated_lines_saved
+                .cmp(&a.estimated_lines_saved)
+                .then_with(|| a.instances[0].file_path.cmp(&b.instances[0].file_path))
+                .then_with(|| a.instances[0].start_line.cmp(&b.instances[0].start_line))
+        });
+        clusters
+    }
+
+    /// Kind of copy and similarity of two functions, if they are clones
+    fn compare(&self, a: &Candidate, b: &Candidate) -> Option<(CloneType, f64)> {
+        if a.tokens == b.tokens {
+            return Some((CloneType::Exact, 1.0));
+        }
+        if a.abstracted == b.abstracted {
+            return Some((CloneType::Renamed, 1.0));
+        }
+
+        // Cheap upper bounds on the similarity first: length, then shared tokens
+        let total = (a.abstracted.len() + b.abstracted.len()) as f64;
+        let shortest = a.abstracted.len().min(b.abstracted.len());
+        if 2.0 * shortest as f64 / total < self.config.min_similarity {
+            return None;
+        }
+        let shared: usize = a
+            .counts
+            .iter()
+            .map(|(token, count)| (*count).min(b.counts.get(token).copied().unwrap_or(0)))
+            .sum();
+        if 2.0 * shared as f64 / total < self.config.min_similarity {
+            return None;
+        }
+
+        let similarity = 2.0 * lcs_length(&a.abstracted, &b.abstracted) as f64 / total;
+        (similarity >= self.config.min_similarity).then_some((CloneType::NearMiss, similarity))
+    }
+}
+
+fn find(roots: &mut [usize], mut index: usize) -> usize {
+    while roots[index] != index {
+        roots[index] = roots[roots[index]];
+        index = roots[index];
+    }
+    index
+}
+
+fn lcs_length(a: &[&str], b: &[&str]) -> usize {
+    let mut previous = vec![0; b.len() + 1];
+    let mut current = vec![0; b.len() + 1];
+    for token in a {
+        for (j, other) in b.iter().enumerate() {
+            current[j + 1] = if token == other {
+                previous[j] + 1
+            } else {
+                previous[j + 1].max(current[j])
+            };
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Index pairs of a longest common subsequence of `a` and `b`
+fn lcs_pairs(a: &[&str], b: &[&str]) -> Vec<(usize, usize)> {
+    let (n, m) = (a.len(), b.len());
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if a[i] == b[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// Signature for a function replacing the clones: the words their names
+/// share, the first copy's parameters, and a parameter per differing literal
+fn suggest_signature(members: &[&Candidate]) -> String {
+    let first = members[0];
+    let name = shared_name(
+        &members
+            .iter()
+            .map(|member| member.function.signature.name.as_str())
+            .collect::<Vec<_>>(),
+    );
+
+    // Literals of the first copy that some other copy writes differently
+    let mut varying = vec![false; first.tokens.len()];
+    for other in &members[1..] {
+        for (i, j) in lcs_pairs(&first.abstracted, &other.abstracted) {
+            if first.abstracted[i] == LITERAL_TOKEN && first.tokens[i] != other.tokens[j] {
+                varying[i] = true;
+            }
+        }
+    }
+    let mut literals: Vec<&str> = Vec::new();
+    for (index, _) in varying.iter().enumerate().filter(|(_, varies)| **varies) {
+        if !literals.contains(&first.tokens[index]) {
+            literals.push(first.tokens[index]);
+        }
+    }
+
+    let mut parameters = parameter_names(&first.function.body);
+    parameters.extend((1..=literals.len()).map(|n| match literals.len() {
+        1 => "value".to_string(),
+        _ => format!("value{}", n),
+    }));
+    format!("{}({})", name, parameters.join(", "))
+}
+
+/// Words shared by every name, in the order and naming style of the first
+fn shared_name(names: &[&str]) -> String {
+    let first = names[0];
+    let words = identifier_words(first);
+    let others: Vec<Vec<String>> = names[1..]
+        .iter()
+        .map(|name| identifier_words(name))
+        .collect();
+    let shared: Vec<&String> = words
+        .iter()
+        .filter(|word| others.iter().all(|other| other.contains(word)))
+        .collect();
+    if shared.is_empty() || shared.len() == words.len() {
+        return first.to_string();
+    }
+
+    if first.contains('_') || !first.chars().any(char::is_uppercase) {
+        return shared
+            .iter()
+            .map(|word| word.as_str())
+            .collect::<Vec<_>>()
+            .join("_");
+    }
+    let pascal = first.starts_with(|c: char| c.is_uppercase());
+    shared
+        .iter()
+        .enumerate()
+        .map(|(index, word)| {
+            if index == 0 && !pascal {
+                return word.to_string();
+            }
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|c| c.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Names of a function's parameters, from the first parameter list in its subtree
+fn parameter_names(node: &ASTNode) -> Vec<String> {
+    fn first_identifier(node: &ASTNode) -> Option<&str> {
+        if node.metadata.attributes.get("kind").map(String::as_str) == Some("identifier") {
+            return Some(node.metadata.original_text.trim());
+        }
+        node.children.iter().find_map(first_identifier)
+    }
+    fn parameter_list(node: &ASTNode) -> Option<&ASTNode> {
+        let kind = node.metadata.attributes.get("kind").map(String::as_str);
+        if kind.is_some_and(|kind| PARAMETER_LIST_KINDS.contains(&kind)) {
+            return Some(node);
+        }
+        node.children.iter().find_map(parameter_list)
+    }
+
+    parameter_list(node)
+        .map(|list| {
+            list.children
+                .iter()
+                .filter_map(first_identifier)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smart_diff_parser::{tree_sitter::TreeSitterParser, FunctionSignature, Language, Parser};
+
+    fn parse_functions(code: &str, file_path: &str) -> Vec<Function> {
+        let parser = TreeSitterParser::new().unwrap();
+        let result = parser.parse(code, Language::Python).unwrap();
+        result
+            .ast
+            .find_by_type(&NodeType::Function)
+            .into_iter()
+            .map(|node| {
+                let name = node.metadata.attributes.get("name").cloned().unwrap();
+                let signature = FunctionSignature::from_node(name, node);
+                Function::new(signature, node.clone(), file_path.to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_renamed_copy_is_a_type_2_clone_with_a_literal_parameter() {
+        let mut functions = parse_functions(
+            "def load_users(path):\n    rows = read_csv(path)\n    valid = [row for row in rows if row[\"active\"]]\n    log(\"loaded users\", len(valid))\n    return valid\n",
+            "users.py",
+        );
+        functions.extend(parse_functions(
+            "def load_orders(source):\n    lines = read_csv(source)\n    valid = [line for line in lines if line[\"active\"]]\n    log(\"loaded orders\", len(valid))\n    return valid\n\ndef unrelated(a, b):\n    return a * b + a - b\n",
+            "orders.py",
+        ));
+
+        let clusters = CloneDetector::new(CloneDetectorConfig {
+            min_tokens: 10,
+            ..Default::default()
+        })
+        .detect(&functions);
+        assert_eq!(clusters.len(), 1, "{:?}", clusters);
+        let cluster = &clusters[0];
+        assert_eq!(cluster.clone_type, CloneType::Renamed);
+        let names: Vec<_> = cluster.instances.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["load_orders", "load_users"]);
+        assert_eq!(cluster.suggested_signature, "load(source, value)");
+        assert_eq!(cluster.estimated_lines_saved, 3);
+    }
+}
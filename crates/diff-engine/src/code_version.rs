@@ -0,0 +1,181 @@
+//! Parsed code of one side of a comparison
+//!
+//! Most refactorings show in the matched changes themselves. Others only
+//! show in what the changes leave out: parameters folded into a new type, a
+//! method moving up its class hierarchy, constants turned into an enum. A
+//! [`CodeVersion`] keeps the signatures, function bodies and types of one
+//! side, so a [`RefactoringDetector`](crate::RefactoringDetector) given both
+//! sides can look for those refactorings too. Its symbol table, with a scope
+//! per function, also lets a [`SimilarityScorer`](crate::SimilarityScorer)
+//! tell locals apart from calls and types.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use smart_diff_parser::{ASTNode, Change, NodeType, ParseResult};
+use smart_diff_semantic::{
+    EnhancedFunctionSignature, ExtractedTypeInfo, FunctionSignatureExtractor, SymbolResolver,
+    SymbolTable, TypeDependencyGraphBuilder, TypeExtractionResult, TypeExtractor, TypeInfo,
+};
+use std::collections::{BTreeSet, HashMap};
+use tracing::warn;
+
+/// Signatures, functions and types of one side of a comparison
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CodeVersion {
+    /// Signatures keyed by qualified name
+    pub signatures: HashMap<String, EnhancedFunctionSignature>,
+    /// Function and method nodes keyed by name
+    pub functions: HashMap<String, ASTNode>,
+    /// Symbols of every file, with their scopes
+    pub symbols: SymbolTable,
+    types: Vec<ExtractedTypeInfo>,
+}
+
+impl CodeVersion {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Version made of a single parsed file
+    pub fn from_file(file_path: &str, parsed: &ParseResult) -> Result<Self> {
+        let mut version = Self::new();
+        version.add_file(file_path, parsed)?;
+        Ok(version)
+    }
+
+    /// Add the signatures, functions, symbols and types of a parsed file
+    pub fn add_file(&mut self, file_path: &str, parsed: &ParseResult) -> Result<()> {
+        let signatures = FunctionSignatureExtractor::with_defaults(parsed.language)
+            .extract_signatures(file_path, parsed)?;
+        let types =
+            TypeExtractor::with_defaults(parsed.language).extract_types(file_path, parsed)?;
+        let mut resolver = SymbolResolver::with_defaults();
+        resolver.process_file(file_path, parsed)?;
+
+        self.signatures.extend(signatures.signature_map);
+        self.types.extend(types.types);
+        self.symbols.merge(resolver.get_symbol_table().clone());
+        collect_functions(&parsed.ast, &mut self.functions);
+        Ok(())
+    }
+
+    /// Add everything of `other`, such as another file of the same side
+    pub fn extend(&mut self, other: &CodeVersion) {
+        self.signatures.extend(
+            other
+                .signatures
+                .iter()
+                .map(|(name, signature)| (name.clone(), signature.clone())),
+        );
+        self.functions.extend(
+            other
+                .functions
+                .iter()
+                .map(|(name, function)| (name.clone(), function.clone())),
+        );
+        self.types.extend(other.types.iter().cloned());
+        self.symbols.merge(other.symbols.clone());
+    }
+
+    /// Function or method node named `name`, which may be qualified with its
+    /// class or module
+    pub fn function(&self, name: &str) -> Option<&ASTNode> {
+        self.functions.get(name).or_else(|| {
+            let short = name.rsplit(['.', ':']).next()?;
+            self.functions.get(short)
+        })
+    }
+
+    /// Signature and node of the function named `name`, for scoring it with
+    /// [`SimilarityScorer::calculate_comprehensive_similarity`](crate::SimilarityScorer::calculate_comprehensive_similarity)
+    pub fn function_with_signature(
+        &self,
+        name: &str,
+    ) -> Option<(&EnhancedFunctionSignature, &ASTNode)> {
+        let function = self.function(name)?;
+        let short = name.rsplit(['.', ':']).next().unwrap_or(name);
+        let signature = self.signatures.get(name).or_else(|| {
+            let mut candidates = self
+                .signatures
+                .values()
+                .filter(|signature| signature.name == short)
+                .peekable();
+            let first = *candidates.peek()?;
+            // Same-named methods of different classes are told apart by line
+            Some(
+                candidates
+                    .find(|signature| signature.line == function.metadata.line)
+                    .unwrap_or(first),
+            )
+        })?;
+        Some((signature, function))
+    }
+
+    /// Names of the functions of this version and their counterparts in
+    /// `target`: functions of the same name, and the pairs of `changes`
+    pub fn function_pairs<'a>(
+        &'a self,
+        target: &'a CodeVersion,
+        changes: &'a [Change],
+    ) -> BTreeSet<(&'a str, &'a str)> {
+        let mut pairs: BTreeSet<(&str, &str)> = self
+            .functions
+            .keys()
+            .filter(|name| target.functions.contains_key(*name))
+            .map(|name| (name.as_str(), name.as_str()))
+            .collect();
+        pairs.extend(changes.iter().filter_map(|change| {
+            Some((
+                change.source.as_ref()?.name.as_str(),
+                change.target.as_ref()?.name.as_str(),
+            ))
+        }));
+        pairs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty() && self.functions.is_empty() && self.types.is_empty()
+    }
+
+    /// Types declared in the version
+    pub fn type_infos(&self) -> Vec<TypeInfo> {
+        self.types
+            .iter()
+            .map(|extracted| extracted.type_info.clone())
+            .collect()
+    }
+
+    /// Dependency graph of the types declared in the version
+    ///
+    /// Built from every file at once, so relationships between types of
+    /// different files are kept.
+    pub fn type_graph(&self) -> TypeDependencyGraphBuilder {
+        let mut builder = TypeDependencyGraphBuilder::new();
+        let extraction = TypeExtractionResult {
+            types: self.types.clone(),
+            type_aliases: HashMap::new(),
+            primitive_usage: HashMap::new(),
+            generic_usage: HashMap::new(),
+        };
+        if let Err(e) = builder.build_from_extraction_result(&extraction) {
+            warn!("Failed to build type graph: {}", e);
+            return TypeDependencyGraphBuilder::new();
+        }
+        builder
+    }
+}
+
+/// Record the function and method nodes under `node` by their name
+fn collect_functions(node: &ASTNode, functions: &mut HashMap<String, ASTNode>) {
+    // C/C++ declarators are only the signature part of a definition
+    let is_declarator =
+        node.metadata.attributes.get("kind").map(String::as_str) == Some("function_declarator");
+    if matches!(node.node_type, NodeType::Function | NodeType::Method) && !is_declarator {
+        if let Some(name) = node.metadata.attributes.get("name") {
+            functions.insert(name.clone(), node.clone());
+        }
+    }
+    for child in &node.children {
+        collect_functions(child, functions);
+    }
+}
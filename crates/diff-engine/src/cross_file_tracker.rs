@@ -1,5 +1,6 @@
 //! Cross-file function tracking for detecting function moves between files
 
+use crate::file_refactoring_detector::FileRefactoringDetector;
 use crate::hungarian_matcher::{HungarianMatcher, HungarianMatcherConfig};
 use crate::similarity_scorer::{ComprehensiveSimilarityScore, SimilarityScorer};
 use anyhow::Result;
@@ -9,6 +10,7 @@ use smart_diff_semantic::{
     ComprehensiveDependencyGraphBuilder, EnhancedFunctionSignature, SymbolResolver,
 };
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
 /// Configuration for cross-file function tracking
 #[derive(Debug, Clone)]
@@ -27,6 +29,10 @@ pub struct CrossFileTrackerConfig {
     pub use_global_symbol_table: bool,
     /// Enable dependency graph analysis for move detection
     pub use_dependency_analysis: bool,
+    /// Fraction (0.0 to 1.0) of a file's functions that must move to the same
+    /// file in another directory for those moves to be reported as one
+    /// relocation of the file; `None` reports every move on its own
+    pub relocation_threshold: Option<f64>,
 }
 
 impl Default for CrossFileTrackerConfig {
@@ -39,6 +45,7 @@ impl Default for CrossFileTrackerConfig {
             max_files_to_consider: 50,
             use_global_symbol_table: true,
             use_dependency_analysis: true,
+            relocation_threshold: None,
         }
     }
 }
@@ -65,6 +72,11 @@ pub struct CrossFileTrackingResult {
     pub cross_file_splits: Vec<CrossFileSplit>,
     /// Functions that were merged from multiple files
     pub cross_file_merges: Vec<CrossFileMerge>,
+    /// Files renamed or moved without changes to their functions, and files
+    /// whose functions moved wholesale to another directory; their unchanged
+    /// functions are not listed in `moved_functions`
+    #[serde(default)]
+    pub relocations: Vec<FileRelocation>,
     /// File-level statistics
     pub file_statistics: HashMap<String, FileTrackingStats>,
     /// Overall tracking statistics
@@ -135,6 +147,26 @@ pub struct CrossFileMerge {
     pub confidence: f64,
 }
 
+/// A file's functions moved together to one other file
+///
+/// Either the file was renamed or moved with its functions unchanged, or
+/// most of its functions moved to one file in another directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileRelocation {
+    /// Source file path
+    pub source_file: String,
+    /// Target file path
+    pub target_file: String,
+    /// Whether the file name changed along with its directory
+    pub was_renamed: bool,
+    /// Functions that moved with the file, by their original name
+    pub functions: Vec<String>,
+    /// Moves of functions that also changed on the way, still reported one by one
+    pub modified_functions: Vec<FunctionMove>,
+    /// Average confidence of the moves
+    pub confidence: f64,
+}
+
 /// Type of function move
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum MoveType {
@@ -178,6 +210,9 @@ pub struct CrossFileTrackingStats {
     pub total_splits: usize,
     /// Total cross-file merges
     pub total_merges: usize,
+    /// Total files relocated, unchanged or to another directory
+    #[serde(default)]
+    pub total_relocations: usize,
     /// Percentage of functions that moved
     pub move_percentage: f64,
     /// Average confidence of detections
@@ -235,6 +270,7 @@ impl CrossFileTracker {
             renamed_and_moved: Vec::new(),
             cross_file_splits: Vec::new(),
             cross_file_merges: Vec::new(),
+            relocations: Vec::new(),
             file_statistics: HashMap::new(),
             overall_statistics: CrossFileTrackingStats {
                 total_files: 0,
@@ -243,27 +279,37 @@ impl CrossFileTracker {
                 total_rename_moves: 0,
                 total_splits: 0,
                 total_merges: 0,
+                total_relocations: 0,
                 move_percentage: 0.0,
                 average_confidence: 0.0,
                 execution_time_ms: 0,
             },
         };
 
-        // Step 1: Perform intra-file matching to identify unmatched functions
+        // Step 1: Relocate files renamed or moved unchanged, so none of their
+        // functions is matched or scored
+        result.relocations = self.relocate_unchanged_files(source_files, target_files);
+        let relocated: HashSet<&str> = result
+            .relocations
+            .iter()
+            .flat_map(|r| [r.source_file.as_str(), r.target_file.as_str()])
+            .collect();
+
+        // Step 2: Perform intra-file matching to identify unmatched functions
         let (unmatched_source, unmatched_target) =
-            self.identify_unmatched_functions(source_files, target_files)?;
+            self.identify_unmatched_functions(source_files, target_files, &relocated)?;
 
-        // Step 2: Detect simple cross-file moves
+        // Step 3: Detect simple cross-file moves
         let moves = self.detect_cross_file_moves(&unmatched_source, &unmatched_target)?;
         result.moved_functions = moves;
 
-        // Step 3: Detect renames with moves if enabled
+        // Step 4: Detect renames with moves if enabled
         if self.config.track_renames {
             let rename_moves = self.detect_rename_moves(&unmatched_source, &unmatched_target)?;
             result.renamed_and_moved = rename_moves;
         }
 
-        // Step 4: Detect cross-file splits and merges if enabled
+        // Step 5: Detect cross-file splits and merges if enabled
         if self.config.track_splits_merges {
             let splits = self.detect_cross_file_splits(&unmatched_source, &unmatched_target)?;
             let merges = self.detect_cross_file_merges(&unmatched_source, &unmatched_target)?;
@@ -271,7 +317,7 @@ impl CrossFileTracker {
             result.cross_file_merges = merges;
         }
 
-        // Step 5: Calculate statistics
+        // Step 6: Calculate statistics
         result.file_statistics =
             self.calculate_file_statistics(source_files, target_files, &result);
 
@@ -279,14 +325,163 @@ impl CrossFileTracker {
         result.overall_statistics =
             self.calculate_overall_statistics(source_files, target_files, &result, execution_time);
 
+        // Step 7: Collapse wholesale moves into relocations, after the
+        // statistics so those still count every function that moved
+        if let Some(threshold) = self.config.relocation_threshold {
+            self.collapse_relocations(threshold, source_files, &mut result);
+        }
+
         Ok(result)
     }
+
+    /// Replace the moves of a file's functions to one file in another
+    /// directory with a single relocation, once they reach `threshold` of
+    /// the file's functions
+    ///
+    /// Functions renamed on the way count towards the threshold but stay in
+    /// `renamed_and_moved`; those otherwise changed move into the
+    /// relocation's `modified_functions`.
+    fn collapse_relocations(
+        &self,
+        threshold: f64,
+        source_files: &HashMap<String, Vec<(EnhancedFunctionSignature, ASTNode)>>,
+        result: &mut CrossFileTrackingResult,
+    ) {
+        // A function can turn up both as a move and as a rename, so count names
+        let mut moved_together: HashMap<(&str, &str), HashSet<&str>> = HashMap::new();
+        for (source_file, target_file, name) in result
+            .moved_functions
+            .iter()
+            .map(|m| {
+                (
+                    m.source_file.as_str(),
+                    m.target_file.as_str(),
+                    m.function_signature.name.as_str(),
+                )
+            })
+            .chain(result.renamed_and_moved.iter().map(|m| {
+                (
+                    m.source_file.as_str(),
+                    m.target_file.as_str(),
+                    m.original_name.as_str(),
+                )
+            }))
+        {
+            moved_together
+                .entry((source_file, target_file))
+                .or_default()
+                .insert(name);
+        }
+
+        let mut relocated: Vec<(String, String)> = moved_together
+            .into_iter()
+            .filter(|((source_file, target_file), moved)| {
+                let total = source_files.get(*source_file).map_or(0, Vec::len);
+                Path::new(source_file).parent() != Path::new(target_file).parent()
+                    && total > 0
+                    && moved.len() as f64 / total as f64 >= threshold
+            })
+            .map(|((source_file, target_file), _)| {
+                (source_file.to_string(), target_file.to_string())
+            })
+            .collect();
+        relocated.sort();
+
+        for (source_file, target_file) in relocated {
+            let (moves, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut result.moved_functions)
+                .into_iter()
+                .partition(|m| m.source_file == source_file && m.target_file == target_file);
+            result.moved_functions = rest;
+
+            let renamed: Vec<&FunctionRenameMove> = result
+                .renamed_and_moved
+                .iter()
+                .filter(|m| {
+                    m.source_file == source_file
+                        && m.target_file == target_file
+                        && !moves
+                            .iter()
+                            .any(|moved| moved.function_signature.name == m.original_name)
+                })
+                .collect();
+            let confidences: Vec<f64> = moves
+                .iter()
+                .map(|m| m.confidence)
+                .chain(renamed.iter().map(|m| m.confidence))
+                .collect();
+            let functions = moves
+                .iter()
+                .map(|m| m.function_signature.name.clone())
+                .chain(renamed.iter().map(|m| m.original_name.clone()))
+                .collect();
+
+            result.relocations.push(FileRelocation {
+                was_renamed: Path::new(&source_file).file_name()
+                    != Path::new(&target_file).file_name(),
+                source_file,
+                target_file,
+                functions,
+                modified_functions: moves
+                    .into_iter()
+                    .filter(|m| m.move_type != MoveType::SimpleMove)
+                    .collect(),
+                confidence: confidences.iter().sum::<f64>() / confidences.len() as f64,
+            });
+        }
+
+        result.overall_statistics.total_relocations = result.relocations.len();
+    }
+    /// Relocations of the files renamed or moved with their functions
+    /// unchanged
+    ///
+    /// Files are paired by hashing the text of their functions, the way
+    /// [`FileRefactoringDetector::pair_unchanged_files`] pairs whole files.
+    fn relocate_unchanged_files(
+        &self,
+        source_files: &HashMap<String, Vec<(EnhancedFunctionSignature, ASTNode)>>,
+        target_files: &HashMap<String, Vec<(EnhancedFunctionSignature, ASTNode)>>,
+    ) -> Vec<FileRelocation> {
+        let function_texts =
+            |files: &HashMap<String, Vec<(EnhancedFunctionSignature, ASTNode)>>| {
+                files
+                    .iter()
+                    .map(|(path, functions)| {
+                        let text = functions
+                            .iter()
+                            .map(|(_, ast)| ast.metadata.original_text.as_str())
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        (path.clone(), text)
+                    })
+                    .collect::<HashMap<_, _>>()
+            };
+
+        FileRefactoringDetector::with_defaults()
+            .pair_unchanged_files(&function_texts(source_files), &function_texts(target_files))
+            .into_iter()
+            .map(|(source_file, target_file, _)| FileRelocation {
+                was_renamed: Path::new(&source_file).file_name()
+                    != Path::new(&target_file).file_name(),
+                functions: source_files[&source_file]
+                    .iter()
+                    .map(|(signature, _)| signature.name.clone())
+                    .collect(),
+                modified_functions: Vec::new(),
+                confidence: 1.0,
+                source_file,
+                target_file,
+            })
+            .collect()
+    }
+
     #[allow(clippy::type_complexity)]
-    /// Identify functions that are unmatched within their original files
+    /// Identify functions that are unmatched within their original files,
+    /// leaving out the files in `relocated`
     fn identify_unmatched_functions(
         &mut self,
         source_files: &HashMap<String, Vec<(EnhancedFunctionSignature, ASTNode)>>,
         target_files: &HashMap<String, Vec<(EnhancedFunctionSignature, ASTNode)>>,
+        relocated: &HashSet<&str>,
     ) -> Result<(
         HashMap<String, Vec<(usize, EnhancedFunctionSignature, ASTNode)>>, // source file -> unmatched functions
         HashMap<String, Vec<(usize, EnhancedFunctionSignature, ASTNode)>>, // target file -> unmatched functions
@@ -296,6 +491,9 @@ impl CrossFileTracker {
 
         // For each file, perform intra-file matching to find unmatched functions
         for (file_path, source_functions) in source_files {
+            if relocated.contains(file_path.as_str()) {
+                continue;
+            }
             if let Some(target_functions) = target_files.get(file_path) {
                 // Match functions within the same file
                 let match_result = self
@@ -348,7 +546,7 @@ impl CrossFileTracker {
 
         // Handle new files - all functions are unmatched
         for (file_path, target_functions) in target_files {
-            if !source_files.contains_key(file_path) {
+            if !source_files.contains_key(file_path) && !relocated.contains(file_path.as_str()) {
                 let unmatched: Vec<_> = target_functions
                     .iter()
                     .enumerate()
@@ -787,13 +985,12 @@ impl CrossFileTracker {
                 return true;
             }
 
-            // Check for common prefixes/suffixes
-            if n1.len() > 3 && n2.len() > 3 {
-                let prefix1 = &n1[..3];
-                let prefix2 = &n2[..3];
-                if prefix1 == prefix2 {
-                    return true;
-                }
+            // Check for common prefixes/suffixes, by character so non-ASCII names can't split a char
+            if n1.chars().count() > 3
+                && n2.chars().count() > 3
+                && n1.chars().take(3).eq(n2.chars().take(3))
+            {
+                return true;
             }
         }
 
@@ -815,10 +1012,10 @@ impl CrossFileTracker {
             "part", "step", "phase", "stage", "helper", "util", "validate", "process", "handle",
         ];
         for pattern in &split_patterns {
-            if split_lower.contains(pattern) && original_lower.len() > 5 {
+            if split_lower.contains(pattern) && original_lower.chars().count() > 5 {
                 // Check if they share a common root
-                let original_root = &original_lower[..original_lower.len().min(5)];
-                if split_lower.contains(original_root) {
+                let original_root: String = original_lower.chars().take(5).collect();
+                if split_lower.contains(&original_root) {
                     return true;
                 }
             }
@@ -920,6 +1117,16 @@ impl CrossFileTracker {
                 }
             }
 
+            // Count functions of files relocated unchanged
+            for relocation in &result.relocations {
+                if relocation.source_file == file_path {
+                    moved_out += relocation.functions.len();
+                }
+                if relocation.target_file == file_path {
+                    moved_in += relocation.functions.len();
+                }
+            }
+
             // Count renames with moves
             for rename_move in &result.renamed_and_moved {
                 if rename_move.source_file == file_path {
@@ -960,7 +1167,8 @@ impl CrossFileTracker {
         let total_target_functions: usize = target_files.values().map(|f| f.len()).sum();
         let total_functions = total_source_functions.max(total_target_functions);
 
-        let total_moves = result.moved_functions.len();
+        let relocated_functions: usize = result.relocations.iter().map(|r| r.functions.len()).sum();
+        let total_moves = result.moved_functions.len() + relocated_functions;
         let total_rename_moves = result.renamed_and_moved.len();
         let total_splits = result.cross_file_splits.len();
         let total_merges = result.cross_file_merges.len();
@@ -985,6 +1193,11 @@ impl CrossFileTracker {
             confidence_count += 1;
         }
 
+        for relocation in &result.relocations {
+            total_confidence += relocation.confidence * relocation.functions.len() as f64;
+            confidence_count += relocation.functions.len();
+        }
+
         for split in &result.cross_file_splits {
             total_confidence += split.confidence;
             confidence_count += 1;
@@ -1008,6 +1221,7 @@ impl CrossFileTracker {
             total_rename_moves,
             total_splits,
             total_merges,
+            total_relocations: result.relocations.len(),
             move_percentage,
             average_confidence,
             execution_time_ms,
@@ -1190,6 +1404,9 @@ mod tests {
         assert!(
             !tracker.are_files_related("src/main/Calculator.java", "test/unit/DatabaseTest.java")
         );
+
+        // Prefixes are compared by character, not byte
+        assert!(!tracker.are_files_related("src/a/Größe.java", "lib/b/Grüße.java"));
     }
 
     #[test]
@@ -1204,6 +1421,9 @@ mod tests {
         // Not split patterns
         assert!(!tracker.is_potential_split_function("processData", "formatOutput"));
         assert!(!tracker.is_potential_split_function("calculateTotal", "deleteRecord"));
+
+        // Non-ASCII names share a root by character
+        assert!(tracker.is_potential_split_function("größeBerechnen", "größeHelper"));
     }
 
     #[test]
@@ -1235,6 +1455,7 @@ mod tests {
             max_files_to_consider: 25,
             use_global_symbol_table: false,
             use_dependency_analysis: false,
+            relocation_threshold: Some(0.75),
         };
 
         tracker.set_config(new_config);
@@ -1246,5 +1467,128 @@ mod tests {
         assert_eq!(tracker.get_config().max_files_to_consider, 25);
         assert!(!tracker.get_config().use_global_symbol_table);
         assert!(!tracker.get_config().use_dependency_analysis);
+        assert_eq!(tracker.get_config().relocation_threshold, Some(0.75));
+    }
+
+    /// Signatures of the methods in Java `code`, each with its AST
+    fn java_functions(code: &str, file_path: &str) -> Vec<(EnhancedFunctionSignature, ASTNode)> {
+        use smart_diff_parser::{tree_sitter::TreeSitterParser, NodeType, Parser};
+        use smart_diff_semantic::FunctionSignatureExtractor;
+
+        let parsed = TreeSitterParser::new()
+            .unwrap()
+            .parse(code, Language::Java)
+            .unwrap();
+        let methods = parsed.ast.find_by_type(&NodeType::Method);
+        FunctionSignatureExtractor::with_defaults(Language::Java)
+            .extract_signatures(file_path, &parsed)
+            .unwrap()
+            .signatures
+            .into_iter()
+            .map(|signature| {
+                let node = methods
+                    .iter()
+                    .find(|node| node.metadata.line == signature.line)
+                    .copied()
+                    .unwrap()
+                    .clone();
+                (signature, node)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_functions_moved_en_masse_are_one_relocation() {
+        let methods = [
+            "    static String trim(String s) {\n        return s == null ? \"\" : s.strip();\n    }\n",
+            "    static boolean isBlank(String s) {\n        return s == null || s.strip().isEmpty();\n    }\n",
+            "    static String capitalize(String s) {\n        if (isBlank(s)) {\n            return s;\n        }\n        return Character.toUpperCase(s.charAt(0)) + s.substring(1);\n    }\n",
+            "    static String repeat(String s, int times) {\n        StringBuilder out = new StringBuilder();\n        for (int i = 0; i < times; i++) {\n            out.append(s);\n        }\n        return out.toString();\n    }\n",
+        ];
+        let old_code = format!("class Strings {{\n{}}}\n", methods.concat());
+        // `repeat` gains a guard clause on its way to the new package
+        let new_code = format!(
+            "class Strings {{\n{}{}{}}}\n",
+            methods[..3].concat(),
+            "    static String repeat(String s, int times) {\n        if (times <= 0) {\n            return \"\";\n        }\n",
+            &methods[3][methods[3].find("        StringBuilder").unwrap()..]
+        );
+
+        let old_path = "src/util/Strings.java";
+        let new_path = "src/text/Strings.java";
+        let source_files =
+            HashMap::from([(old_path.to_string(), java_functions(&old_code, old_path))]);
+        let target_files =
+            HashMap::from([(new_path.to_string(), java_functions(&new_code, new_path))]);
+
+        let mut tracker = CrossFileTracker::with_defaults(Language::Java);
+        let individually = tracker
+            .track_cross_file_changes(&source_files, &target_files)
+            .unwrap();
+        assert_eq!(individually.moved_functions.len(), 4);
+        assert!(individually.relocations.is_empty());
+
+        tracker.set_config(CrossFileTrackerConfig {
+            relocation_threshold: Some(0.8),
+            ..Default::default()
+        });
+        let result = tracker
+            .track_cross_file_changes(&source_files, &target_files)
+            .unwrap();
+        assert!(
+            result.moved_functions.is_empty(),
+            "{:?}",
+            result.moved_functions
+        );
+        assert_eq!(result.relocations.len(), 1);
+        assert_eq!(result.overall_statistics.total_relocations, 1);
+        assert_eq!(result.overall_statistics.total_moves, 4);
+
+        let relocation = &result.relocations[0];
+        assert_eq!(relocation.source_file, old_path);
+        assert_eq!(relocation.target_file, new_path);
+        assert!(!relocation.was_renamed);
+        assert_eq!(relocation.functions.len(), 4);
+        let modified: Vec<_> = relocation
+            .modified_functions
+            .iter()
+            .map(|m| m.function_signature.name.as_str())
+            .collect();
+        assert_eq!(modified, ["repeat"]);
+    }
+
+    #[test]
+    fn test_file_renamed_unchanged_is_relocated_without_matching() {
+        let code = "class Strings {\n    static String trim(String s) {\n        return s == null ? \"\" : s.strip();\n    }\n\n    static boolean isBlank(String s) {\n        return s == null || s.strip().isEmpty();\n    }\n}\n";
+        let old_path = "src/util/Strings.java";
+        let new_path = "src/util/Text.java";
+        let source_files = HashMap::from([(old_path.to_string(), java_functions(code, old_path))]);
+        let target_files = HashMap::from([(new_path.to_string(), java_functions(code, new_path))]);
+
+        // No function is scored, so even an impossible similarity threshold
+        // leaves the rename found
+        let mut tracker = CrossFileTracker::new(
+            Language::Java,
+            CrossFileTrackerConfig {
+                min_cross_file_similarity: 2.0,
+                ..Default::default()
+            },
+        );
+        let result = tracker
+            .track_cross_file_changes(&source_files, &target_files)
+            .unwrap();
+
+        assert!(result.moved_functions.is_empty());
+        assert!(result.renamed_and_moved.is_empty());
+        assert_eq!(result.relocations.len(), 1);
+        let relocation = &result.relocations[0];
+        assert_eq!(relocation.source_file, old_path);
+        assert_eq!(relocation.target_file, new_path);
+        assert!(relocation.was_renamed);
+        assert_eq!(relocation.functions, ["trim", "isBlank"]);
+        assert!(relocation.modified_functions.is_empty());
+        assert_eq!(result.overall_statistics.total_moves, 2);
+        assert_eq!(result.overall_statistics.total_relocations, 1);
+        assert_eq!(result.file_statistics[old_path].functions_moved_out, 2);
     }
 }
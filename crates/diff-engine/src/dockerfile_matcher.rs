@@ -0,0 +1,709 @@
+//! Stage- and instruction-level comparison of Dockerfiles
+//!
+//! Build stages are matched across versions by their `AS` alias, then by base
+//! image, then by content, and the instructions of matched stages are paired
+//! like sections of a document: identical instructions first, then
+//! instructions with the same keyword and similar arguments. Instructions
+//! that kept their text but changed position are reported as moves, since
+//! reordering changes which layers a rebuild can reuse.
+//!
+//! Every change carries a `significance` in its metadata: a new base image
+//! can change everything the image contains, a `RUN` step changes what is
+//! built, `COPY` and `ADD` change what goes into the image, and the other
+//! instructions change how it is configured.
+
+use crate::section_matcher::SectionMatcher;
+use crate::token_similarity::{TokenSimilarity, TokenSimilarityConfig};
+use smart_diff_parser::{
+    ASTNode, Change, ChangeType, CodeElement, ElementType, MatchResult, NodeType,
+};
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+/// What an instruction change affects in the built image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstructionSignificance {
+    /// `FROM`: the image everything else is built on
+    BaseImage,
+    /// `RUN`: commands executed at build time
+    BuildStep,
+    /// `COPY` and `ADD`: files put into the image
+    Content,
+    /// Every other instruction: environment, metadata and runtime settings
+    Configuration,
+}
+
+impl InstructionSignificance {
+    pub fn of(keyword: &str) -> Self {
+        match keyword {
+            "FROM" => InstructionSignificance::BaseImage,
+            "RUN" => InstructionSignificance::BuildStep,
+            "COPY" | "ADD" => InstructionSignificance::Content,
+            _ => InstructionSignificance::Configuration,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InstructionSignificance::BaseImage => "base_image",
+            InstructionSignificance::BuildStep => "build_step",
+            InstructionSignificance::Content => "content",
+            InstructionSignificance::Configuration => "configuration",
+        }
+    }
+}
+
+/// One instruction of a Dockerfile
+#[derive(Debug, Clone, PartialEq)]
+pub struct DockerInstruction {
+    /// Unique key: the stage name and the instruction's line
+    pub id: String,
+    /// Uppercase keyword, e.g. `RUN`
+    pub keyword: String,
+    /// Arguments with continuation lines joined and whitespace collapsed
+    pub arguments: String,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+impl DockerInstruction {
+    /// Keyword and arguments
+    pub fn text(&self) -> String {
+        format!("{} {}", self.keyword, self.arguments)
+            .trim_end()
+            .to_string()
+    }
+
+    /// Code element describing this instruction in a change
+    pub fn to_element(&self, file_path: &str) -> CodeElement {
+        let text = self.text();
+        let name = match text.char_indices().nth(60) {
+            Some((end, _)) => format!("{}...", &text[..end]),
+            None => text.clone(),
+        };
+        CodeElement {
+            id: self.id.clone(),
+            element_type: ElementType::Instruction,
+            name,
+            file_path: file_path.to_string(),
+            start_line: self.start_line,
+            end_line: self.end_line,
+            hash: hash(&text),
+            signature: Some(text),
+        }
+    }
+}
+
+/// One build stage: a `FROM` instruction and the instructions up to the next
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildStage {
+    /// `AS` alias, or `stage N` for an unnamed stage; global `ARG`s before
+    /// the first `FROM` form a stage named `global`
+    pub name: String,
+    pub alias: Option<String>,
+    /// Image or earlier stage the stage is built on, `None` for `global`
+    pub base_image: Option<String>,
+    /// The `FROM` instruction
+    pub from: Option<DockerInstruction>,
+    /// Instructions after `FROM`, in file order
+    pub instructions: Vec<DockerInstruction>,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+impl BuildStage {
+    /// Text of every instruction, `FROM` included
+    pub fn content(&self) -> String {
+        self.from
+            .iter()
+            .chain(&self.instructions)
+            .map(DockerInstruction::text)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Code element describing this stage in a change
+    pub fn to_element(&self, file_path: &str) -> CodeElement {
+        CodeElement {
+            id: self.name.clone(),
+            element_type: ElementType::BuildStage,
+            name: self.name.clone(),
+            file_path: file_path.to_string(),
+            start_line: self.start_line,
+            end_line: self.end_line,
+            signature: self.from.as_ref().map(DockerInstruction::text),
+            hash: hash(&self.content()),
+        }
+    }
+}
+
+fn hash(text: &str) -> String {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    text.hash(&mut hasher);
+    format!("{:x}", hasher.finish())
+}
+
+/// Build stages of a parsed Dockerfile, in file order
+pub fn extract_stages(ast: &ASTNode) -> Vec<BuildStage> {
+    let mut stages = Vec::new();
+
+    let global: Vec<&ASTNode> = ast
+        .children
+        .iter()
+        .filter(|child| child.node_type != NodeType::Section)
+        .collect();
+    if !global.is_empty() {
+        stages.push(stage("global", None, None, &global));
+    }
+
+    for node in ast
+        .children
+        .iter()
+        .filter(|child| child.node_type == NodeType::Section)
+    {
+        let attributes = &node.metadata.attributes;
+        let name = attributes.get("name").map_or("", String::as_str);
+        let instructions: Vec<&ASTNode> = node.children.iter().collect();
+        stages.push(stage(
+            name,
+            attributes.get("alias").cloned(),
+            attributes.get("base_image").cloned(),
+            &instructions,
+        ));
+    }
+
+    stages
+}
+
+fn stage(
+    name: &str,
+    alias: Option<String>,
+    base_image: Option<String>,
+    nodes: &[&ASTNode],
+) -> BuildStage {
+    let mut instructions: Vec<DockerInstruction> = nodes
+        .iter()
+        .map(|node| {
+            let attributes = &node.metadata.attributes;
+            let start_line = node.metadata.line;
+            DockerInstruction {
+                id: format!("{}:{}", name, start_line),
+                keyword: attributes.get("instruction").cloned().unwrap_or_default(),
+                arguments: attributes.get("arguments").cloned().unwrap_or_default(),
+                start_line,
+                end_line: start_line + node.metadata.original_text.lines().count().max(1) - 1,
+            }
+        })
+        .collect();
+    let from = match base_image {
+        Some(_) if !instructions.is_empty() => Some(instructions.remove(0)),
+        _ => None,
+    };
+
+    let lines = from
+        .iter()
+        .chain(&instructions)
+        .map(|instruction| (instruction.start_line, instruction.end_line));
+    let start_line = lines.clone().map(|(start, _)| start).min().unwrap_or(1);
+    let end_line = lines.map(|(_, end)| end).max().unwrap_or(start_line);
+
+    BuildStage {
+        name: name.to_string(),
+        alias,
+        base_image,
+        from,
+        instructions,
+        start_line,
+        end_line,
+    }
+}
+
+/// Settings for Dockerfile matching
+#[derive(Debug, Clone)]
+pub struct DockerfileMatcherConfig {
+    /// Similarity at which instructions with the same keyword, or stages
+    /// with different names and base images, are paired
+    pub modify_threshold: f64,
+    pub similarity: TokenSimilarityConfig,
+}
+
+impl Default for DockerfileMatcherConfig {
+    fn default() -> Self {
+        Self {
+            modify_threshold: 0.5,
+            similarity: TokenSimilarityConfig {
+                ngram_size: 1,
+                ignore_case: false,
+            },
+        }
+    }
+}
+
+/// Matches the build stages and instructions of two versions of a Dockerfile
+pub struct DockerfileMatcher {
+    config: DockerfileMatcherConfig,
+    similarity: TokenSimilarity,
+}
+
+impl Default for DockerfileMatcher {
+    fn default() -> Self {
+        Self::new(DockerfileMatcherConfig::default())
+    }
+}
+
+impl DockerfileMatcher {
+    pub fn new(config: DockerfileMatcherConfig) -> Self {
+        let similarity = TokenSimilarity::new(config.similarity.clone());
+        Self { config, similarity }
+    }
+
+    /// Match stages, then the instructions of each pair of matched stages
+    ///
+    /// Added and deleted stages are reported as one change each rather than
+    /// one per instruction.
+    pub fn match_stages(
+        &self,
+        source: &[BuildStage],
+        target: &[BuildStage],
+        source_file: &str,
+        target_file: &str,
+    ) -> MatchResult {
+        let mut result = MatchResult::new();
+        if source.is_empty() && target.is_empty() {
+            result.similarity = 1.0;
+            return result;
+        }
+
+        let pairs = self.pair_stages(source, target);
+        let in_order = SectionMatcher::longest_ordered_run(&pairs);
+
+        for (pair_idx, &(source_idx, target_idx)) in pairs.iter().enumerate() {
+            let (old, new) = (&source[source_idx], &target[target_idx]);
+            result.mapping.insert(old.name.clone(), new.name.clone());
+
+            let stage_change = |change_type, description: String| {
+                significant(
+                    Change::new(change_type, description).with_elements(
+                        Some(old.to_element(source_file)),
+                        Some(new.to_element(target_file)),
+                    ),
+                    "FROM",
+                )
+            };
+            if !in_order.contains(&pair_idx) {
+                result.changes.push(stage_change(
+                    ChangeType::Move,
+                    format!(
+                        "Stage '{}' moved from line {} to line {}",
+                        new.name, old.start_line, new.start_line
+                    ),
+                ));
+            } else if old.alias.is_some() && new.alias.is_some() && old.alias != new.alias {
+                result.changes.push(stage_change(
+                    ChangeType::Rename,
+                    format!("Stage '{}' renamed to '{}'", old.name, new.name),
+                ));
+            }
+            if old.base_image != new.base_image {
+                result.changes.push(stage_change(
+                    ChangeType::Modify,
+                    format!(
+                        "Base image of stage '{}' changed from '{}' to '{}'",
+                        new.name,
+                        old.base_image.as_deref().unwrap_or_default(),
+                        new.base_image.as_deref().unwrap_or_default()
+                    ),
+                ));
+            } else if let (Some(old_from), Some(new_from)) = (&old.from, &new.from) {
+                // Same image, other flags such as `--platform`
+                if old_from.arguments != new_from.arguments {
+                    result.changes.push(self.instruction_change(
+                        ChangeType::Modify,
+                        format!("FROM of stage '{}' modified", new.name),
+                        old_from,
+                        new_from,
+                        source_file,
+                        target_file,
+                    ));
+                }
+            }
+
+            self.match_instructions(old, new, source_file, target_file, &mut result);
+        }
+
+        let matched_sources: HashSet<usize> = pairs.iter().map(|&(s, _)| s).collect();
+        let matched_targets: HashSet<usize> = pairs.iter().map(|&(_, t)| t).collect();
+        for (source_idx, stage) in source.iter().enumerate() {
+            if !matched_sources.contains(&source_idx) {
+                result.unmatched_source.push(stage.name.clone());
+                result.changes.push(significant(
+                    Change::new(
+                        ChangeType::Delete,
+                        format!("Stage '{}' deleted", stage.name),
+                    )
+                    .with_elements(Some(stage.to_element(source_file)), None),
+                    "FROM",
+                ));
+            }
+        }
+        for (target_idx, stage) in target.iter().enumerate() {
+            if !matched_targets.contains(&target_idx) {
+                result.unmatched_target.push(stage.name.clone());
+                result.changes.push(significant(
+                    Change::new(ChangeType::Add, format!("Stage '{}' added", stage.name))
+                        .with_elements(None, Some(stage.to_element(target_file))),
+                    "FROM",
+                ));
+            }
+        }
+
+        result.calculate_similarity();
+        result
+    }
+
+    /// Pair the instructions of two matched stages and record their changes
+    fn match_instructions(
+        &self,
+        old: &BuildStage,
+        new: &BuildStage,
+        source_file: &str,
+        target_file: &str,
+        result: &mut MatchResult,
+    ) {
+        let (source, target) = (&old.instructions, &new.instructions);
+        let pairs = self.pair_instructions(source, target);
+        let in_order = SectionMatcher::longest_ordered_run(&pairs);
+
+        for (pair_idx, &(source_idx, target_idx)) in pairs.iter().enumerate() {
+            let (before, after) = (&source[source_idx], &target[target_idx]);
+            result.mapping.insert(before.id.clone(), after.id.clone());
+
+            let (change_type, description) = if !in_order.contains(&pair_idx) {
+                (
+                    ChangeType::Move,
+                    format!(
+                        "{} instruction of stage '{}' moved from line {} to line {}",
+                        after.keyword, new.name, before.start_line, after.start_line
+                    ),
+                )
+            } else if before.arguments != after.arguments {
+                (
+                    ChangeType::Modify,
+                    format!(
+                        "{} instruction of stage '{}' modified",
+                        after.keyword, new.name
+                    ),
+                )
+            } else {
+                continue;
+            };
+            result.changes.push(self.instruction_change(
+                change_type,
+                description,
+                before,
+                after,
+                source_file,
+                target_file,
+            ));
+        }
+
+        let matched_sources: HashSet<usize> = pairs.iter().map(|&(s, _)| s).collect();
+        let matched_targets: HashSet<usize> = pairs.iter().map(|&(_, t)| t).collect();
+        for (source_idx, instruction) in source.iter().enumerate() {
+            if !matched_sources.contains(&source_idx) {
+                result.unmatched_source.push(instruction.id.clone());
+                result.changes.push(significant(
+                    Change::new(
+                        ChangeType::Delete,
+                        format!(
+                            "{} instruction deleted from stage '{}'",
+                            instruction.keyword, old.name
+                        ),
+                    )
+                    .with_elements(Some(instruction.to_element(source_file)), None),
+                    &instruction.keyword,
+                ));
+            }
+        }
+        for (target_idx, instruction) in target.iter().enumerate() {
+            if !matched_targets.contains(&target_idx) {
+                result.unmatched_target.push(instruction.id.clone());
+                result.changes.push(significant(
+                    Change::new(
+                        ChangeType::Add,
+                        format!(
+                            "{} instruction added to stage '{}'",
+                            instruction.keyword, new.name
+                        ),
+                    )
+                    .with_elements(None, Some(instruction.to_element(target_file))),
+                    &instruction.keyword,
+                ));
+            }
+        }
+    }
+
+    fn instruction_change(
+        &self,
+        change_type: ChangeType,
+        description: String,
+        before: &DockerInstruction,
+        after: &DockerInstruction,
+        source_file: &str,
+        target_file: &str,
+    ) -> Change {
+        let similarity = self.similarity.similarity(&before.text(), &after.text());
+        let mut change = Change::new(change_type, description).with_elements(
+            Some(before.to_element(source_file)),
+            Some(after.to_element(target_file)),
+        );
+        change.confidence = similarity;
+        change.details.similarity_score = Some(similarity);
+        significant(change, &after.keyword)
+    }
+
+    /// Pair source and target stages, sorted by source position
+    fn pair_stages(&self, source: &[BuildStage], target: &[BuildStage]) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        let mut open_targets: Vec<usize> = (0..target.len()).collect();
+        let mut open_sources: Vec<usize> = (0..source.len()).collect();
+
+        let mut pair_by = |same: &dyn Fn(&BuildStage, &BuildStage) -> bool,
+                           open_sources: &mut Vec<usize>,
+                           open_targets: &mut Vec<usize>| {
+            open_sources.retain(|&source_idx| {
+                match open_targets
+                    .iter()
+                    .position(|&target_idx| same(&source[source_idx], &target[target_idx]))
+                {
+                    Some(pos) => {
+                        pairs.push((source_idx, open_targets.remove(pos)));
+                        false
+                    }
+                    None => true,
+                }
+            });
+        };
+
+        // Aliases and the global arguments first, then unnamed stages by base image
+        pair_by(
+            &|a, b| a.alias.is_some() && a.alias == b.alias,
+            &mut open_sources,
+            &mut open_targets,
+        );
+        pair_by(
+            &|a, b| a.base_image.is_none() && b.base_image.is_none(),
+            &mut open_sources,
+            &mut open_targets,
+        );
+        pair_by(
+            &|a, b| a.alias.is_none() && b.alias.is_none() && a.base_image == b.base_image,
+            &mut open_sources,
+            &mut open_targets,
+        );
+
+        // Renamed stages and unnamed stages on a new base image are paired by content
+        for source_idx in open_sources {
+            let content = source[source_idx].content();
+            let best = open_targets
+                .iter()
+                .enumerate()
+                .filter(|(_, &target_idx)| target[target_idx].base_image.is_some())
+                .map(|(pos, &target_idx)| {
+                    (
+                        pos,
+                        self.similarity
+                            .similarity(&content, &target[target_idx].content()),
+                    )
+                })
+                .filter(|&(_, similarity)| similarity >= self.config.modify_threshold)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((pos, _)) = best {
+                pairs.push((source_idx, open_targets.remove(pos)));
+            }
+        }
+
+        pairs.sort_unstable();
+        pairs
+    }
+
+    /// Pair source and target instructions, sorted by source position
+    fn pair_instructions(
+        &self,
+        source: &[DockerInstruction],
+        target: &[DockerInstruction],
+    ) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        let mut open_targets: Vec<usize> = (0..target.len()).collect();
+
+        // Unchanged instructions first, so an edit elsewhere cannot take their place
+        let mut open_sources: Vec<usize> = Vec::new();
+        for (source_idx, instruction) in source.iter().enumerate() {
+            match open_targets
+                .iter()
+                .position(|&target_idx| target[target_idx].text() == instruction.text())
+            {
+                Some(pos) => pairs.push((source_idx, open_targets.remove(pos))),
+                None => open_sources.push(source_idx),
+            }
+        }
+
+        for source_idx in open_sources {
+            let instruction = &source[source_idx];
+            let best = open_targets
+                .iter()
+                .enumerate()
+                .filter(|(_, &target_idx)| target[target_idx].keyword == instruction.keyword)
+                .map(|(pos, &target_idx)| {
+                    (
+                        pos,
+                        self.similarity
+                            .similarity(&instruction.arguments, &target[target_idx].arguments),
+                    )
+                })
+                .filter(|&(_, similarity)| similarity >= self.config.modify_threshold)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((pos, _)) = best {
+                pairs.push((source_idx, open_targets.remove(pos)));
+            }
+        }
+
+        pairs.sort_unstable();
+        pairs
+    }
+}
+
+/// `change` with the significance of an instruction with `keyword`; changes
+/// to whole stages count as `FROM` changes
+fn significant(mut change: Change, keyword: &str) -> Change {
+    change.details.metadata.insert(
+        "significance".to_string(),
+        InstructionSignificance::of(keyword).as_str().to_string(),
+    );
+    change
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smart_diff_parser::{tree_sitter::TreeSitterParser, Language, Parser};
+
+    fn stages(dockerfile: &str) -> Vec<BuildStage> {
+        let parser = TreeSitterParser::new().unwrap();
+        let result = parser.parse(dockerfile, Language::Dockerfile).unwrap();
+        extract_stages(&result.ast)
+    }
+
+    #[test]
+    fn test_changed_run_step_and_added_stage() {
+        let source = stages(
+            "ARG RUST_VERSION=1.75\n\
+             FROM rust:${RUST_VERSION} AS build\n\
+             WORKDIR /src\n\
+             COPY . .\n\
+             RUN cargo build --release \\\n    --bin app\n\
+             \n\
+             FROM debian:bookworm-slim\n\
+             COPY --from=build /src/target/release/app /usr/local/bin/app\n\
+             CMD [\"app\"]\n",
+        );
+        let target = stages(
+            "ARG RUST_VERSION=1.75\n\
+             FROM rust:${RUST_VERSION} AS build\n\
+             WORKDIR /src\n\
+             COPY . .\n\
+             RUN cargo build --release --locked \\\n    --bin app\n\
+             \n\
+             FROM build AS test\n\
+             RUN cargo test --locked\n\
+             \n\
+             FROM debian:bookworm-slim\n\
+             COPY --from=build /src/target/release/app /usr/local/bin/app\n\
+             CMD [\"app\"]\n",
+        );
+
+        let names: Vec<&str> = target.iter().map(|stage| stage.name.as_str()).collect();
+        assert_eq!(names, vec!["global", "build", "test", "stage 2"]);
+        assert_eq!(
+            source[1].instructions[2].arguments,
+            "cargo build --release --bin app"
+        );
+        assert_eq!(
+            (
+                source[1].instructions[2].start_line,
+                source[1].instructions[2].end_line
+            ),
+            (5, 6)
+        );
+
+        let result =
+            DockerfileMatcher::default().match_stages(&source, &target, "Dockerfile", "Dockerfile");
+
+        let changes: Vec<(&ChangeType, &ElementType, &str, &str)> = result
+            .changes
+            .iter()
+            .map(|change| {
+                let element = change.target.as_ref().or(change.source.as_ref()).unwrap();
+                (
+                    &change.change_type,
+                    &element.element_type,
+                    element.name.as_str(),
+                    change.details.metadata["significance"].as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (
+                    &ChangeType::Modify,
+                    &ElementType::Instruction,
+                    "RUN cargo build --release --locked --bin app",
+                    "build_step"
+                ),
+                (
+                    &ChangeType::Add,
+                    &ElementType::BuildStage,
+                    "test",
+                    "base_image"
+                ),
+            ]
+        );
+        assert_eq!(result.mapping.get("stage 1"), Some(&"stage 2".to_string()));
+    }
+
+    #[test]
+    fn test_new_base_image_and_reordered_instructions() {
+        let source = stages(
+            "FROM python:3.11-slim\nENV PIP_NO_CACHE_DIR=1\nWORKDIR /app\nCOPY app/ app/\nCOPY requirements.txt .\nRUN pip install -r requirements.txt\n",
+        );
+        // Requirements copied and installed before the code, so that layer is cached
+        let target = stages(
+            "FROM python:3.12-slim\nENV PIP_NO_CACHE_DIR=1\nWORKDIR /app\nCOPY requirements.txt .\nRUN pip install -r requirements.txt\nCOPY app/ app/\n",
+        );
+
+        let result =
+            DockerfileMatcher::default().match_stages(&source, &target, "Dockerfile", "Dockerfile");
+        let changes: Vec<(&ChangeType, &str)> = result
+            .changes
+            .iter()
+            .map(|change| {
+                (
+                    &change.change_type,
+                    change.details.metadata["significance"].as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (&ChangeType::Modify, "base_image"),
+                (&ChangeType::Move, "content")
+            ]
+        );
+        assert_eq!(
+            result.changes[1].target.as_ref().unwrap().name,
+            "COPY app/ app/"
+        );
+    }
+}
@@ -1,11 +1,16 @@
 //! Main diff engine
 
 use crate::changes::ChangeClassifier;
+use crate::dockerfile_matcher::{BuildStage, DockerfileMatcher};
+use crate::match_hints::MatchHints;
 use crate::matching::FunctionMatcher;
 use crate::refactoring::RefactoringDetector;
+use crate::section_matcher::{DocumentSection, SectionMatcher};
+use crate::signature_matcher::SignatureMatcher;
 use crate::tree_edit::{TreeEditDistance, ZhangShashaConfig};
 use serde::{Deserialize, Serialize};
 use smart_diff_parser::{Function, Language, MatchResult};
+use smart_diff_semantic::EnhancedFunctionSignature;
 use thiserror::Error;
 
 /// Main diff engine that orchestrates the comparison process
@@ -16,6 +21,9 @@ pub struct DiffEngine {
     #[allow(dead_code)]
     change_classifier: ChangeClassifier,
     refactoring_detector: RefactoringDetector,
+    section_matcher: SectionMatcher,
+    dockerfile_matcher: DockerfileMatcher,
+    signature_matcher: SignatureMatcher,
 }
 
 /// Result of diff computation
@@ -49,6 +57,9 @@ pub enum DiffError {
 
     #[error("Processing error: {0}")]
     ProcessingError(String),
+
+    #[error("Invalid match hints: {0}")]
+    InvalidMatchHints(String),
 }
 
 impl Default for DiffEngine {
@@ -65,9 +76,18 @@ impl DiffEngine {
             tree_edit_distance: TreeEditDistance::new(ZhangShashaConfig::default()),
             change_classifier: ChangeClassifier::new(language),
             refactoring_detector: RefactoringDetector::new(language),
+            section_matcher: SectionMatcher::default(),
+            dockerfile_matcher: DockerfileMatcher::default(),
+            signature_matcher: SignatureMatcher::default(),
         }
     }
 
+    /// Engine whose function matching follows `hints`
+    pub fn with_match_hints(mut self, hints: MatchHints) -> Self {
+        self.function_matcher.set_hints(hints);
+        self
+    }
+
     /// Compare two sets of functions
     pub fn compare_functions(
         &self,
@@ -87,8 +107,10 @@ impl DiffEngine {
             .detect_patterns(&match_result.changes);
 
         // Calculate statistics
-        let statistics =
-            self.calculate_statistics(source_functions, target_functions, &match_result);
+        let statistics = self.calculate_statistics(
+            source_functions.len() + target_functions.len(),
+            &match_result,
+        );
 
         let execution_time_ms = start_time.elapsed().as_millis() as u64;
 
@@ -100,13 +122,111 @@ impl DiffEngine {
         })
     }
 
+    /// Compare two versions of a document section by section
+    ///
+    /// Sections take the place of functions in the result and its statistics;
+    /// no refactoring patterns are detected in prose.
+    pub fn compare_sections(
+        &self,
+        source_sections: &[DocumentSection],
+        target_sections: &[DocumentSection],
+        source_file: &str,
+        target_file: &str,
+    ) -> Result<DiffResult, DiffError> {
+        let start_time = std::time::Instant::now();
+
+        let match_result = self.section_matcher.match_sections(
+            source_sections,
+            target_sections,
+            source_file,
+            target_file,
+        );
+        let statistics =
+            self.calculate_statistics(source_sections.len() + target_sections.len(), &match_result);
+
+        Ok(DiffResult {
+            match_result,
+            refactoring_patterns: Vec::new(),
+            execution_time_ms: start_time.elapsed().as_millis() as u64,
+            statistics,
+        })
+    }
+
+    /// Compare two versions of a Dockerfile stage by stage
+    ///
+    /// Build stages and their instructions take the place of functions in the
+    /// result and its statistics; no refactoring patterns are detected.
+    pub fn compare_dockerfiles(
+        &self,
+        source_stages: &[BuildStage],
+        target_stages: &[BuildStage],
+        source_file: &str,
+        target_file: &str,
+    ) -> Result<DiffResult, DiffError> {
+        let start_time = std::time::Instant::now();
+
+        let match_result = self.dockerfile_matcher.match_stages(
+            source_stages,
+            target_stages,
+            source_file,
+            target_file,
+        );
+        let elements = |stages: &[BuildStage]| -> usize {
+            stages
+                .iter()
+                .map(|stage| 1 + stage.instructions.len())
+                .sum()
+        };
+        let statistics = self.calculate_statistics(
+            elements(source_stages) + elements(target_stages),
+            &match_result,
+        );
+
+        Ok(DiffResult {
+            match_result,
+            refactoring_patterns: Vec::new(),
+            execution_time_ms: start_time.elapsed().as_millis() as u64,
+            statistics,
+        })
+    }
+
+    /// Compare two versions of a file by function signatures alone
+    ///
+    /// Body changes are not reported and no refactoring patterns are detected;
+    /// only added, removed and redeclared functions appear in the result.
+    pub fn compare_signatures(
+        &self,
+        source_signatures: &[EnhancedFunctionSignature],
+        target_signatures: &[EnhancedFunctionSignature],
+        source_file: &str,
+        target_file: &str,
+    ) -> Result<DiffResult, DiffError> {
+        let start_time = std::time::Instant::now();
+
+        let match_result = self.signature_matcher.match_signatures(
+            source_signatures,
+            target_signatures,
+            source_file,
+            target_file,
+        );
+        let statistics = self.calculate_statistics(
+            source_signatures.len() + target_signatures.len(),
+            &match_result,
+        );
+
+        Ok(DiffResult {
+            match_result,
+            refactoring_patterns: Vec::new(),
+            execution_time_ms: start_time.elapsed().as_millis() as u64,
+            statistics,
+        })
+    }
+
     fn calculate_statistics(
         &self,
-        source: &[Function],
-        target: &[Function],
+        functions_compared: usize,
         match_result: &MatchResult,
     ) -> DiffStatistics {
-        let functions_compared = source.len() + target.len();
         let functions_matched = match_result.mapping.len();
         let functions_added = match_result.unmatched_target.len();
         let functions_removed = match_result.unmatched_source.len();
@@ -5,6 +5,10 @@
 //! - File splits: Detecting when a file is split into multiple files
 //! - File merges: Detecting when multiple files are merged into one
 //! - File moves: Detecting when files are moved to different directories
+//!
+//! Files renamed or moved without changes are paired by their content hash
+//! alone, before any of the scoring above; see
+//! [`FileRefactoringDetector::pair_unchanged_files`].
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -79,6 +83,10 @@ pub struct FileRename {
     pub symbol_migration_score: f64,
     /// Overall confidence in the rename detection
     pub confidence: f64,
+    /// Whether the content is unchanged, ignoring whitespace, so the file
+    /// was paired by its hash alone
+    #[serde(default)]
+    pub content_unchanged: bool,
 }
 
 /// File split detection result
@@ -118,6 +126,10 @@ pub struct FileMove {
     pub was_renamed: bool,
     /// Confidence in the move detection
     pub confidence: f64,
+    /// Whether the content is unchanged, ignoring whitespace, so the file
+    /// was paired by its hash alone
+    #[serde(default)]
+    pub content_unchanged: bool,
 }
 
 /// Statistics about file refactoring detection
@@ -187,21 +199,52 @@ impl FileRefactoringDetector {
             },
         };
 
-        // Step 1: Create content fingerprints for all files
-        let source_fingerprints = self.create_fingerprints(source_files)?;
-        let target_fingerprints = self.create_fingerprints(target_files)?;
+        // Step 1: Pair files renamed or moved unchanged by their hashes alone
+        let unchanged = self.pair_unchanged_files(source_files, target_files);
+        for (source_path, target_path, content_sim) in &unchanged {
+            let path_sim = if self.config.use_path_similarity {
+                self.calculate_path_similarity(source_path, target_path)
+            } else {
+                0.0
+            };
+            self.classify_relocation(
+                source_path,
+                target_path,
+                (*content_sim, path_sim, 1.0),
+                1.0,
+                true,
+                &mut result.file_renames,
+                &mut result.file_moves,
+            );
+        }
+        let paired_sources: HashSet<&String> = unchanged.iter().map(|(s, _, _)| s).collect();
+        let paired_targets: HashSet<&String> = unchanged.iter().map(|(_, t, _)| t).collect();
+        let remaining_sources: HashMap<String, String> = source_files
+            .iter()
+            .filter(|(path, _)| !paired_sources.contains(path))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let remaining_targets: HashMap<String, String> = target_files
+            .iter()
+            .filter(|(path, _)| !paired_targets.contains(path))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
 
-        // Step 2: Detect file renames and moves
+        // Step 2: Create content fingerprints for the remaining files
+        let source_fingerprints = self.create_fingerprints(&remaining_sources)?;
+        let target_fingerprints = self.create_fingerprints(&remaining_targets)?;
+
+        // Step 3: Detect the remaining file renames and moves
         let (renames, moves) = self.detect_renames_and_moves(
-            source_files,
-            target_files,
+            &remaining_sources,
+            &remaining_targets,
             &source_fingerprints,
             &target_fingerprints,
         )?;
-        result.file_renames = renames;
-        result.file_moves = moves;
+        result.file_renames.extend(renames);
+        result.file_moves.extend(moves);
 
-        // Step 3: Identify unmatched files for split/merge detection
+        // Step 4: Identify unmatched files for split/merge detection
         let matched_sources: HashSet<String> = result
             .file_renames
             .iter()
@@ -228,11 +271,11 @@ impl FileRefactoringDetector {
             .map(|(k, v)| (k.clone(), v.clone()))
             .collect();
 
-        // Step 4: Detect file splits
+        // Step 5: Detect file splits
         let splits = self.detect_file_splits(&unmatched_sources, &unmatched_targets)?;
         result.file_splits = splits;
 
-        // Step 5: Detect file merges
+        // Step 6: Detect file merges
         let merges = self.detect_file_merges(&unmatched_sources, &unmatched_targets)?;
         result.file_merges = merges;
 
@@ -246,6 +289,76 @@ impl FileRefactoringDetector {
         Ok(result)
     }
 
+    /// Pair files that disappeared with new files of the same content
+    ///
+    /// Each file whose path is gone from `target_files` is paired with a
+    /// file new to `target_files` with the same content hash or, failing
+    /// that, the same hash ignoring whitespace; the closest path wins when
+    /// several match. Returns `(source_path, target_path, content_similarity)`
+    /// triples, sorted by source path. Only hashes are computed, so this is
+    /// cheap enough to run before any identifier extraction or scoring.
+    pub fn pair_unchanged_files(
+        &self,
+        source_files: &HashMap<String, String>,
+        target_files: &HashMap<String, String>,
+    ) -> Vec<(String, String, f64)> {
+        if !self.config.use_content_fingerprinting {
+            return Vec::new();
+        }
+
+        // Blank files all hash the same, so they pair with nothing
+        let mut added: Vec<(&String, (String, String))> = target_files
+            .iter()
+            .filter(|(path, content)| {
+                !source_files.contains_key(*path) && !content.trim().is_empty()
+            })
+            .map(|(path, content)| (path, Self::content_hashes(content)))
+            .collect();
+        added.sort_by(|a, b| a.0.cmp(b.0));
+        let mut removed: Vec<(&String, &String)> = source_files
+            .iter()
+            .filter(|(path, content)| {
+                !target_files.contains_key(*path) && !content.trim().is_empty()
+            })
+            .collect();
+        removed.sort();
+
+        let mut pairs = Vec::new();
+        let mut claimed = HashSet::new();
+        for (source_path, content) in removed {
+            let (content_hash, normalized_hash) = Self::content_hashes(content);
+            let closest = |same: &dyn Fn(&(String, String)) -> bool| {
+                added
+                    .iter()
+                    .filter(|(path, hashes)| !claimed.contains(*path) && same(hashes))
+                    .map(|(path, _)| {
+                        let path_sim = self.calculate_path_similarity(source_path, path);
+                        (*path, path_sim)
+                    })
+                    .fold(
+                        None,
+                        |best: Option<(&String, f64)>, (path, path_sim)| match best {
+                            Some((_, best_sim)) if best_sim >= path_sim => best,
+                            _ => Some((path, path_sim)),
+                        },
+                    )
+                    .map(|(path, _)| path.clone())
+            };
+
+            let matched = closest(&|(hash, _)| *hash == content_hash)
+                .map(|path| (path, 1.0))
+                .or_else(|| {
+                    closest(&|(_, hash)| *hash == normalized_hash).map(|path| (path, 0.95))
+                });
+            if let Some((target_path, content_sim)) = matched {
+                claimed.insert(target_path.clone());
+                pairs.push((source_path.clone(), target_path, content_sim));
+            }
+        }
+
+        pairs
+    }
+
     /// Create content fingerprints for all files
     fn create_fingerprints(
         &self,
@@ -263,6 +376,26 @@ impl FileRefactoringDetector {
 
     /// Create a content fingerprint for a single file
     fn create_fingerprint(&self, content: &str) -> Result<ContentFingerprint> {
+        let (content_hash, normalized_hash) = Self::content_hashes(content);
+
+        // Extract identifiers (simple regex-based extraction)
+        let identifier_set = self.extract_identifiers(content);
+
+        // Count lines
+        let line_count = content.lines().count();
+        let non_empty_line_count = content.lines().filter(|line| !line.trim().is_empty()).count();
+
+        Ok(ContentFingerprint {
+            content_hash,
+            normalized_hash,
+            identifier_set,
+            line_count,
+            non_empty_line_count,
+        })
+    }
+
+    /// Hash of the content, and of the content without whitespace
+    fn content_hashes(content: &str) -> (String, String) {
         use std::collections::hash_map::DefaultHasher;
         use std::hash::{Hash, Hasher};
 
@@ -280,20 +413,7 @@ impl FileRefactoringDetector {
         normalized.hash(&mut hasher);
         let normalized_hash = format!("{:x}", hasher.finish());
 
-        // Extract identifiers (simple regex-based extraction)
-        let identifier_set = self.extract_identifiers(content);
-
-        // Count lines
-        let line_count = content.lines().count();
-        let non_empty_line_count = content.lines().filter(|line| !line.trim().is_empty()).count();
-
-        Ok(ContentFingerprint {
-            content_hash,
-            normalized_hash,
-            identifier_set,
-            line_count,
-            non_empty_line_count,
-        })
+        (content_hash, normalized_hash)
     }
 
     /// Extract identifiers from content
@@ -406,45 +526,76 @@ impl FileRefactoringDetector {
                 let target_fp = target_fingerprints.get(&target_path).unwrap();
                 let symbol_migration = self.calculate_symbol_migration(source_fp, target_fp);
 
-                // Check if it's a move (directory change) or rename (filename change)
-                let source_dir = Path::new(source_path).parent();
-                let target_dir = Path::new(&target_path).parent();
-                let source_name = Path::new(source_path).file_name();
-                let target_name = Path::new(&target_path).file_name();
-
-                if source_dir != target_dir && source_name == target_name {
-                    // Pure move (same filename, different directory)
-                    moves.push(FileMove {
-                        source_path: source_path.clone(),
-                        target_path: target_path.clone(),
-                        was_renamed: false,
-                        confidence: combined_score,
-                    });
-                } else if source_dir == target_dir && source_name != target_name {
-                    // Pure rename (same directory, different filename)
-                    renames.push(FileRename {
-                        source_path: source_path.clone(),
-                        target_path: target_path.clone(),
-                        content_similarity: content_sim,
-                        path_similarity: path_sim,
-                        symbol_migration_score: symbol_migration,
-                        confidence: combined_score,
-                    });
-                } else {
-                    // Move + rename
-                    moves.push(FileMove {
-                        source_path: source_path.clone(),
-                        target_path: target_path.clone(),
-                        was_renamed: true,
-                        confidence: combined_score,
-                    });
-                }
+                self.classify_relocation(
+                    source_path,
+                    &target_path,
+                    (content_sim, path_sim, symbol_migration),
+                    combined_score,
+                    false,
+                    &mut renames,
+                    &mut moves,
+                );
             }
         }
 
         Ok((renames, moves))
     }
 
+    /// Record a matched file pair as a rename or a move
+    ///
+    /// `scores` are the content similarity, path similarity and symbol
+    /// migration score of the pair.
+    #[allow(clippy::too_many_arguments)]
+    fn classify_relocation(
+        &self,
+        source_path: &str,
+        target_path: &str,
+        scores: (f64, f64, f64),
+        confidence: f64,
+        content_unchanged: bool,
+        renames: &mut Vec<FileRename>,
+        moves: &mut Vec<FileMove>,
+    ) {
+        let (content_similarity, path_similarity, symbol_migration_score) = scores;
+
+        // Check if it's a move (directory change) or rename (filename change)
+        let source_dir = Path::new(source_path).parent();
+        let target_dir = Path::new(target_path).parent();
+        let source_name = Path::new(source_path).file_name();
+        let target_name = Path::new(target_path).file_name();
+
+        if source_dir != target_dir && source_name == target_name {
+            // Pure move (same filename, different directory)
+            moves.push(FileMove {
+                source_path: source_path.to_string(),
+                target_path: target_path.to_string(),
+                was_renamed: false,
+                confidence,
+                content_unchanged,
+            });
+        } else if source_dir == target_dir && source_name != target_name {
+            // Pure rename (same directory, different filename)
+            renames.push(FileRename {
+                source_path: source_path.to_string(),
+                target_path: target_path.to_string(),
+                content_similarity,
+                path_similarity,
+                symbol_migration_score,
+                confidence,
+                content_unchanged,
+            });
+        } else {
+            // Move + rename
+            moves.push(FileMove {
+                source_path: source_path.to_string(),
+                target_path: target_path.to_string(),
+                was_renamed: true,
+                confidence,
+                content_unchanged,
+            });
+        }
+    }
+
     /// Calculate content similarity between two fingerprints
     fn calculate_content_similarity(
         &self,
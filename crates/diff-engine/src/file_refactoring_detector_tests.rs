@@ -296,5 +296,42 @@ class Calculator {
             );
         }
     }
+
+    #[test]
+    fn test_unchanged_file_rename_is_paired_by_hash() {
+        let content = "def parse(line):\n    return line.split(',')\n\ndef render(rows):\n    return '\\n'.join(rows)\n";
+        let source_files = HashMap::from([
+            ("lib/csv_utils.py".to_string(), content.to_string()),
+            ("lib/empty.py".to_string(), String::new()),
+        ]);
+        let target_files = HashMap::from([
+            ("lib/tabular.py".to_string(), content.to_string()),
+            ("lib/blank.py".to_string(), String::new()),
+        ]);
+
+        let detector = FileRefactoringDetector::with_defaults();
+        assert_eq!(
+            detector.pair_unchanged_files(&source_files, &target_files),
+            [(
+                "lib/csv_utils.py".to_string(),
+                "lib/tabular.py".to_string(),
+                1.0
+            )]
+        );
+
+        let result = detector
+            .detect_file_refactorings(&source_files, &target_files)
+            .unwrap();
+        let rename = result
+            .file_renames
+            .iter()
+            .find(|rename| rename.source_path == "lib/csv_utils.py")
+            .expect("unchanged file should be a rename");
+        assert_eq!(rename.target_path, "lib/tabular.py");
+        assert!(rename.content_unchanged);
+        assert_eq!(rename.content_similarity, 1.0);
+        assert_eq!(rename.confidence, 1.0);
+        assert!(result.file_moves.is_empty());
+    }
 }
 
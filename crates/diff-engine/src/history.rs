@@ -0,0 +1,265 @@
+//! Function history across several versions
+//!
+//! A pairwise diff shows the net change between two versions; a reviewer of
+//! a long-lived branch often wants to know how each function got there.
+//! Consecutive versions are matched pairwise and the matches chained, so a
+//! function keeps its identity through renames, and each function gets a
+//! trajectory: where it appeared, what happened to it at every later version
+//! and, if it went away, where it disappeared.
+
+use crate::literal_changes::detect_literal_changes;
+use crate::matching::FunctionMatcher;
+use serde::{Deserialize, Serialize};
+use smart_diff_parser::Function;
+
+/// What happened to a function at one version, compared with the version before
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum FunctionEvent {
+    /// Present in the first version
+    Initial,
+    /// First present in this version
+    Added,
+    /// Same as in the previous version
+    Unchanged,
+    /// Body changed, name kept
+    Modified,
+    /// Name changed, and the body too if `modified`
+    Renamed {
+        previous_name: String,
+        modified: bool,
+    },
+    /// Gone from this version on
+    Deleted,
+}
+
+/// A function at one version
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrajectoryStep {
+    /// Index of the version in the compared list
+    pub version: usize,
+    /// Name of the function at this version, or its last name once deleted
+    pub name: String,
+    #[serde(flatten)]
+    pub event: FunctionEvent,
+}
+
+/// Everything that happened to one function, one step per version from the
+/// one it appeared in until the end or its deletion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionTrajectory {
+    pub steps: Vec<TrajectoryStep>,
+}
+
+impl FunctionTrajectory {
+    /// Latest name of the function
+    pub fn name(&self) -> &str {
+        &self
+            .steps
+            .last()
+            .expect("a trajectory has a first step")
+            .name
+    }
+
+    /// Events of the trajectory, in version order
+    pub fn events(&self) -> Vec<&FunctionEvent> {
+        self.steps.iter().map(|step| &step.event).collect()
+    }
+
+    /// Whether the function is in the last version
+    pub fn survives(&self) -> bool {
+        self.steps
+            .last()
+            .is_some_and(|step| step.event != FunctionEvent::Deleted)
+    }
+}
+
+/// Trajectories of every function over a list of versions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionHistory {
+    pub versions: usize,
+    /// In order of first appearance, then of position in that version
+    pub trajectories: Vec<FunctionTrajectory>,
+}
+
+impl FunctionHistory {
+    /// Trajectory of the function with `name` at any version
+    pub fn trajectory(&self, name: &str) -> Option<&FunctionTrajectory> {
+        self.trajectories
+            .iter()
+            .find(|trajectory| trajectory.steps.iter().any(|step| step.name == name))
+    }
+}
+
+/// Compares an ordered list of versions, chaining pairwise function matches
+pub struct HistoryComparer {
+    matcher: FunctionMatcher,
+}
+
+impl HistoryComparer {
+    /// Comparer matching functions between consecutive versions at `threshold`
+    pub fn new(threshold: f64) -> Self {
+        Self {
+            matcher: FunctionMatcher::new(threshold),
+        }
+    }
+
+    /// Trajectories of the functions of `versions`, oldest version first
+    pub fn compare(&self, versions: &[Vec<Function>]) -> FunctionHistory {
+        let mut trajectories: Vec<FunctionTrajectory> = Vec::new();
+        // Trajectory of each function of the previous version
+        let mut previous: Vec<usize> = Vec::new();
+
+        for (version, functions) in versions.iter().enumerate() {
+            let mut current = vec![usize::MAX; functions.len()];
+
+            if version > 0 {
+                let before = &versions[version - 1];
+                let matrix = self.matcher.calculate_similarity_matrix(before, functions);
+                let mut matched = vec![false; before.len()];
+
+                for (source, target) in self.matcher.hungarian_matching(&matrix) {
+                    matched[source] = true;
+                    current[target] = previous[source];
+                    let event = self.event(&before[source], &functions[target]);
+                    trajectories[previous[source]].steps.push(TrajectoryStep {
+                        version,
+                        name: functions[target].signature.name.clone(),
+                        event,
+                    });
+                }
+
+                for (source, function) in before.iter().enumerate() {
+                    if !matched[source] {
+                        trajectories[previous[source]].steps.push(TrajectoryStep {
+                            version,
+                            name: function.signature.name.clone(),
+                            event: FunctionEvent::Deleted,
+                        });
+                    }
+                }
+            }
+
+            for (target, function) in functions.iter().enumerate() {
+                if current[target] == usize::MAX {
+                    current[target] = trajectories.len();
+                    trajectories.push(FunctionTrajectory {
+                        steps: vec![TrajectoryStep {
+                            version,
+                            name: function.signature.name.clone(),
+                            event: if version == 0 {
+                                FunctionEvent::Initial
+                            } else {
+                                FunctionEvent::Added
+                            },
+                        }],
+                    });
+                }
+            }
+
+            previous = current;
+        }
+
+        FunctionHistory {
+            versions: versions.len(),
+            trajectories,
+        }
+    }
+
+    /// Event for a function matched between consecutive versions
+    fn event(&self, before: &Function, after: &Function) -> FunctionEvent {
+        // The body comparison is structural, blind to the name and to literals
+        let modified = self
+            .matcher
+            .calculate_function_similarity(before, after)
+            .body_similarity
+            < 1.0
+            || detect_literal_changes(&before.body, &after.body).is_some();
+
+        if before.signature.name != after.signature.name {
+            FunctionEvent::Renamed {
+                previous_name: before.signature.name.clone(),
+                modified,
+            }
+        } else if modified {
+            FunctionEvent::Modified
+        } else {
+            FunctionEvent::Unchanged
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smart_diff_parser::{
+        tree_sitter::TreeSitterParser, FunctionSignature, Language, NodeType, Parser,
+    };
+
+    fn parse_functions(code: &str) -> Vec<Function> {
+        let parser = TreeSitterParser::new().unwrap();
+        let result = parser.parse(code, Language::Python).unwrap();
+        result
+            .ast
+            .find_by_type(&NodeType::Function)
+            .into_iter()
+            .map(|node| {
+                let name = node.metadata.attributes.get("name").cloned().unwrap();
+                let signature = FunctionSignature::from_node(name, node);
+                Function::new(signature, node.clone(), "orders.py".to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_trajectory_of_a_function_renamed_then_modified() {
+        let versions = [
+            "def calc_total(items):\n    total = 0\n    for item in items:\n        total += item.price\n    return total\n\n\
+             def tax(total):\n    return total * 0.2\n",
+            "def order_total(items):\n    total = 0\n    for item in items:\n        total += item.price\n    return total\n\n\
+             def tax(total):\n    return total * 0.2\n\n\
+             def discount(total):\n    return total * 0.1\n",
+            "def order_total(items):\n    total = 0\n    for item in items:\n        if item.in_stock:\n            total += item.price\n    return total\n\n\
+             def discount(total):\n    return total * 0.1\n",
+        ]
+        .map(parse_functions);
+
+        let history = HistoryComparer::new(0.7).compare(&versions);
+        assert_eq!(history.versions, 3);
+        assert_eq!(history.trajectories.len(), 3, "{:#?}", history);
+
+        let total = history.trajectory("calc_total").unwrap();
+        assert_eq!(total.name(), "order_total");
+        assert_eq!(
+            total.events(),
+            [
+                &FunctionEvent::Initial,
+                &FunctionEvent::Renamed {
+                    previous_name: "calc_total".to_string(),
+                    modified: false,
+                },
+                &FunctionEvent::Modified,
+            ]
+        );
+
+        // Gone midway
+        let tax = history.trajectory("tax").unwrap();
+        assert_eq!(
+            tax.events(),
+            [
+                &FunctionEvent::Initial,
+                &FunctionEvent::Unchanged,
+                &FunctionEvent::Deleted
+            ]
+        );
+        assert!(!tax.survives());
+
+        // Appeared midway
+        let discount = history.trajectory("discount").unwrap();
+        assert_eq!(discount.steps[0].version, 1);
+        assert_eq!(
+            discount.events(),
+            [&FunctionEvent::Added, &FunctionEvent::Unchanged]
+        );
+    }
+}
@@ -5,7 +5,8 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use smart_diff_parser::{ASTNode, Language};
 use smart_diff_semantic::EnhancedFunctionSignature;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use tracing::warn;
 
 /// Configuration for Hungarian algorithm matching
 #[derive(Debug, Clone)]
@@ -22,6 +23,13 @@ pub struct HungarianMatcherConfig {
     pub enable_cross_file_matching: bool,
     /// Penalty for cross-file matches (0.0 = no penalty, 1.0 = maximum penalty)
     pub cross_file_penalty: f64,
+    /// Score each function pair at most once per matching run
+    pub enable_similarity_cache: bool,
+    /// Solve independent blocks of the cost matrix separately and in parallel
+    pub enable_block_decomposition: bool,
+    /// Largest similarity matrix, in cells, built for the optimal assignment;
+    /// larger inputs are matched greedily instead
+    pub max_matrix_cells: usize,
 }
 
 impl Default for HungarianMatcherConfig {
@@ -33,6 +41,9 @@ impl Default for HungarianMatcherConfig {
             max_candidates_per_function: 10,
             enable_cross_file_matching: true,
             cross_file_penalty: 0.1,
+            enable_similarity_cache: true,
+            enable_block_decomposition: true,
+            max_matrix_cells: 1_000_000,
         }
     }
 }
@@ -41,6 +52,12 @@ impl Default for HungarianMatcherConfig {
 pub struct HungarianMatcher {
     config: HungarianMatcherConfig,
     similarity_scorer: SimilarityScorer,
+    /// Scores of the current run, keyed by (source index, target index)
+    similarity_cache: HashMap<(usize, usize), ComprehensiveSimilarityScore>,
+    similarity_computations: usize,
+    similarity_cache_hits: usize,
+    assignment_blocks: usize,
+    greedy_fallback: bool,
 }
 
 /// Result of Hungarian algorithm matching
@@ -124,6 +141,15 @@ pub struct MatchingStatistics {
     pub average_cost: f64,
     /// Execution time in milliseconds
     pub execution_time_ms: u64,
+    /// Function pairs whose comprehensive similarity was computed
+    pub similarity_computations: usize,
+    /// Pair scores reused from earlier in the run instead of recomputed
+    pub similarity_cache_hits: usize,
+    /// Independent sub-matrices the assignment was solved in; 1 without
+    /// block decomposition
+    pub assignment_blocks: usize,
+    /// Whether the input exceeded `max_matrix_cells` and was matched greedily
+    pub greedy_fallback: bool,
 }
 
 impl HungarianMatcher {
@@ -133,6 +159,11 @@ impl HungarianMatcher {
         Self {
             config,
             similarity_scorer,
+            similarity_cache: HashMap::new(),
+            similarity_computations: 0,
+            similarity_cache_hits: 0,
+            assignment_blocks: 0,
+            greedy_fallback: false,
         }
     }
 
@@ -161,26 +192,66 @@ impl HungarianMatcher {
             return Ok(self.create_all_deletions_result(source_functions.len()));
         }
 
-        // Calculate similarity matrix
-        let similarity_matrix =
-            self.calculate_similarity_matrix(source_functions, target_functions)?;
-
-        // Convert similarity to cost matrix (Hungarian algorithm minimizes cost)
-        let cost_matrix = self.similarity_to_cost_matrix(&similarity_matrix);
+        self.similarity_computations = 0;
+        self.similarity_cache_hits = 0;
+        self.assignment_blocks = 0;
+        self.greedy_fallback = false;
+        let result = self.match_non_empty(source_functions, target_functions, start_time);
+        // Cache keys are indices into this run's functions, so the cache must not outlive it
+        self.similarity_cache = HashMap::new();
+        result
+    }
 
-        // Apply Hungarian algorithm
-        let assignments = self.solve_hungarian_assignment(&cost_matrix)?;
+    fn match_non_empty(
+        &mut self,
+        source_functions: &[(EnhancedFunctionSignature, ASTNode)],
+        target_functions: &[(EnhancedFunctionSignature, ASTNode)],
+        start_time: std::time::Instant,
+    ) -> Result<HungarianMatchResult> {
+        let cells = source_functions
+            .len()
+            .saturating_mul(target_functions.len());
+        let assignments = if cells > self.config.max_matrix_cells {
+            warn!(
+                "{} x {} functions exceed the {} cell similarity matrix limit, matching greedily",
+                source_functions.len(),
+                target_functions.len(),
+                self.config.max_matrix_cells
+            );
+            self.greedy_fallback = true;
+            self.solve_greedy_assignment(source_functions, target_functions)?
+        } else {
+            // Calculate similarity matrix
+            let similarity_matrix =
+                self.calculate_similarity_matrix(source_functions, target_functions)?;
+
+            // Convert similarity to cost matrix (Hungarian algorithm minimizes cost)
+            let cost_matrix = self.similarity_to_cost_matrix(&similarity_matrix);
+
+            // Apply Hungarian algorithm
+            let assignments = if self.config.enable_block_decomposition {
+                self.solve_blocked_assignment(&cost_matrix)?
+            } else {
+                self.assignment_blocks = 1;
+                Self::solve_hungarian_assignment(&cost_matrix)?
+            };
+
+            assignments
+                .into_iter()
+                .map(|(source_idx, target_idx)| {
+                    let similarity = similarity_matrix[source_idx][target_idx].clone();
+                    (source_idx, target_idx, similarity)
+                })
+                .collect()
+        };
 
         // Process assignments and create result
-        let mut result = self.process_assignments(
-            source_functions,
-            target_functions,
-            &assignments,
-            &similarity_matrix,
-        )?;
+        let mut result =
+            self.process_assignments(source_functions, target_functions, assignments)?;
 
-        // Detect many-to-many mappings if enabled
-        if self.config.enable_many_to_many {
+        // Detect many-to-many mappings if enabled; they score every pair of
+        // unmatched functions, which the greedy fallback is there to avoid
+        if self.config.enable_many_to_many && !self.greedy_fallback {
             let many_to_many = self.detect_many_to_many_mappings(
                 source_functions,
                 target_functions,
@@ -211,20 +282,17 @@ impl HungarianMatcher {
     ) -> Result<Vec<Vec<ComprehensiveSimilarityScore>>> {
         let mut matrix = Vec::with_capacity(source_functions.len());
 
-        for (source_sig, source_ast) in source_functions {
+        for (source_idx, (source_sig, _)) in source_functions.iter().enumerate() {
             let mut row = Vec::with_capacity(target_functions.len());
 
-            for (target_sig, target_ast) in target_functions {
-                let mut similarity = self.similarity_scorer.calculate_comprehensive_similarity(
-                    source_sig, source_ast, target_sig, target_ast,
+            for (target_idx, (target_sig, _)) in target_functions.iter().enumerate() {
+                let mut similarity = self.pair_similarity(
+                    source_functions,
+                    target_functions,
+                    source_idx,
+                    target_idx,
                 )?;
-
-                // Apply cross-file penalty if enabled
-                if self.config.enable_cross_file_matching
-                    && source_sig.file_path != target_sig.file_path
-                {
-                    similarity.overall_similarity *= 1.0 - self.config.cross_file_penalty;
-                }
+                self.apply_cross_file_penalty(source_sig, target_sig, &mut similarity);
 
                 row.push(similarity);
             }
@@ -235,6 +303,61 @@ impl HungarianMatcher {
         Ok(matrix)
     }
 
+    /// Lower the similarity of a pair in different files, if enabled
+    fn apply_cross_file_penalty(
+        &self,
+        source_sig: &EnhancedFunctionSignature,
+        target_sig: &EnhancedFunctionSignature,
+        similarity: &mut ComprehensiveSimilarityScore,
+    ) {
+        if self.config.enable_cross_file_matching && source_sig.file_path != target_sig.file_path {
+            similarity.overall_similarity *= 1.0 - self.config.cross_file_penalty;
+        }
+    }
+
+    /// Comprehensive similarity of one function pair, before any cross-file penalty
+    ///
+    /// The matrix, split, merge and group passes revisit the same pairs, so
+    /// scores are cached for the rest of the run when enabled.
+    fn pair_similarity(
+        &mut self,
+        source_functions: &[(EnhancedFunctionSignature, ASTNode)],
+        target_functions: &[(EnhancedFunctionSignature, ASTNode)],
+        source_idx: usize,
+        target_idx: usize,
+    ) -> Result<ComprehensiveSimilarityScore> {
+        let key = (source_idx, target_idx);
+        if let Some(similarity) = self.similarity_cache.get(&key) {
+            self.similarity_cache_hits += 1;
+            return Ok(similarity.clone());
+        }
+
+        let similarity =
+            self.score_pair(source_functions, target_functions, source_idx, target_idx)?;
+
+        if self.config.enable_similarity_cache {
+            self.similarity_cache.insert(key, similarity.clone());
+        }
+        Ok(similarity)
+    }
+
+    /// Comprehensive similarity of one function pair, computed without the cache
+    fn score_pair(
+        &mut self,
+        source_functions: &[(EnhancedFunctionSignature, ASTNode)],
+        target_functions: &[(EnhancedFunctionSignature, ASTNode)],
+        source_idx: usize,
+        target_idx: usize,
+    ) -> Result<ComprehensiveSimilarityScore> {
+        let (source_sig, source_ast) = &source_functions[source_idx];
+        let (target_sig, target_ast) = &target_functions[target_idx];
+        let similarity = self
+            .similarity_scorer
+            .calculate_comprehensive_similarity(source_sig, source_ast, target_sig, target_ast)?;
+        self.similarity_computations += 1;
+        Ok(similarity)
+    }
+
     /// Convert similarity matrix to cost matrix for Hungarian algorithm
     fn similarity_to_cost_matrix(
         &self,
@@ -258,8 +381,165 @@ impl HungarianMatcher {
             .collect()
     }
 
+    /// Assign functions greedily, most similar pairs first, without a similarity matrix
+    ///
+    /// Every pair is scored once but only the `max_candidates_per_function`
+    /// most similar assignable targets of each source are kept, so memory
+    /// grows with the number of functions rather than of pairs. Scores are
+    /// not cached for the same reason. The assignment is valid, each function
+    /// being paired at most once, but may be less similar overall than the
+    /// optimal one.
+    fn solve_greedy_assignment(
+        &mut self,
+        source_functions: &[(EnhancedFunctionSignature, ASTNode)],
+        target_functions: &[(EnhancedFunctionSignature, ASTNode)],
+    ) -> Result<Vec<(usize, usize, ComprehensiveSimilarityScore)>> {
+        let by_similarity =
+            |a: &(usize, ComprehensiveSimilarityScore),
+             b: &(usize, ComprehensiveSimilarityScore)| {
+                b.1.overall_similarity
+                    .total_cmp(&a.1.overall_similarity)
+                    .then(a.0.cmp(&b.0))
+            };
+        let bucket_size = self.config.max_candidates_per_function.max(1);
+
+        let mut candidates = Vec::new();
+        for (source_idx, (source_sig, _)) in source_functions.iter().enumerate() {
+            let mut bucket: Vec<(usize, ComprehensiveSimilarityScore)> = Vec::new();
+            for (target_idx, (target_sig, _)) in target_functions.iter().enumerate() {
+                let mut similarity =
+                    self.score_pair(source_functions, target_functions, source_idx, target_idx)?;
+                self.apply_cross_file_penalty(source_sig, target_sig, &mut similarity);
+                if 1.0 - similarity.overall_similarity > self.config.max_assignment_cost {
+                    continue;
+                }
+
+                bucket.push((target_idx, similarity));
+                if bucket.len() >= 2 * bucket_size {
+                    bucket.sort_by(by_similarity);
+                    bucket.truncate(bucket_size);
+                }
+            }
+
+            bucket.sort_by(by_similarity);
+            bucket.truncate(bucket_size);
+            candidates.extend(
+                bucket
+                    .into_iter()
+                    .map(|(target_idx, similarity)| (source_idx, target_idx, similarity)),
+            );
+        }
+
+        candidates.sort_by(|a, b| {
+            b.2.overall_similarity
+                .total_cmp(&a.2.overall_similarity)
+                .then((a.0, a.1).cmp(&(b.0, b.1)))
+        });
+
+        let mut matched_source = vec![false; source_functions.len()];
+        let mut matched_target = vec![false; target_functions.len()];
+        let mut assignments = Vec::new();
+        for (source_idx, target_idx, similarity) in candidates {
+            if !matched_source[source_idx] && !matched_target[target_idx] {
+                matched_source[source_idx] = true;
+                matched_target[target_idx] = true;
+                assignments.push((source_idx, target_idx, similarity));
+            }
+        }
+
+        assignments.sort_by_key(|&(source_idx, target_idx, _)| (source_idx, target_idx));
+        Ok(assignments)
+    }
+
+    /// Solve the assignment problem block by block
+    ///
+    /// A block is a connected component of the bipartite graph of pairs whose
+    /// cost is finite, so every pair that could be assigned lies within one
+    /// block and pairs across blocks never are. The optimal assignment is then
+    /// the union of the blocks' optimal assignments, found in parallel on
+    /// sub-matrices far smaller than the whole when a file has many functions.
+    fn solve_blocked_assignment(
+        &mut self,
+        cost_matrix: &[Vec<f64>],
+    ) -> Result<Vec<(usize, usize)>> {
+        use rayon::prelude::*;
+
+        let blocks = Self::assignment_blocks(cost_matrix);
+        self.assignment_blocks = blocks.len();
+
+        let solved: Vec<Vec<(usize, usize)>> = blocks
+            .par_iter()
+            .map(|(rows, columns)| {
+                let sub_matrix: Vec<Vec<f64>> = rows
+                    .iter()
+                    .map(|&row| {
+                        columns
+                            .iter()
+                            .map(|&column| cost_matrix[row][column])
+                            .collect()
+                    })
+                    .collect();
+                Ok(Self::solve_hungarian_assignment(&sub_matrix)?
+                    .into_iter()
+                    .map(|(row, column)| (rows[row], columns[column]))
+                    .collect())
+            })
+            .collect::<Result<_>>()?;
+
+        let mut assignments: Vec<(usize, usize)> = solved.into_iter().flatten().collect();
+        assignments.sort_unstable();
+        Ok(assignments)
+    }
+
+    /// Source and target indices of each block of assignable pairs
+    ///
+    /// Functions without any finite-cost pair are left out, as they can only
+    /// end up unmatched.
+    fn assignment_blocks(cost_matrix: &[Vec<f64>]) -> Vec<(Vec<usize>, Vec<usize>)> {
+        fn find(parent: &mut [usize], mut node: usize) -> usize {
+            while parent[node] != node {
+                parent[node] = parent[parent[node]];
+                node = parent[node];
+            }
+            node
+        }
+
+        let height = cost_matrix.len();
+        let width = cost_matrix.first().map_or(0, Vec::len);
+
+        // Union-find over sources 0..height and targets height..height + width
+        let mut parent: Vec<usize> = (0..height + width).collect();
+        for (row, costs) in cost_matrix.iter().enumerate() {
+            for (column, cost) in costs.iter().enumerate() {
+                if cost.is_finite() {
+                    let (a, b) = (find(&mut parent, row), find(&mut parent, height + column));
+                    parent[a.max(b)] = a.min(b);
+                }
+            }
+        }
+
+        // Roots are the smallest index of their component, so blocks come out
+        // ordered by their first source
+        let mut blocks: Vec<(Vec<usize>, Vec<usize>)> = Vec::new();
+        let mut block_of_root: HashMap<usize, usize> = HashMap::new();
+        for node in 0..height + width {
+            let root = find(&mut parent, node);
+            let block = *block_of_root.entry(root).or_insert_with(|| {
+                blocks.push((Vec::new(), Vec::new()));
+                blocks.len() - 1
+            });
+            if node < height {
+                blocks[block].0.push(node);
+            } else {
+                blocks[block].1.push(node - height);
+            }
+        }
+        blocks.retain(|(rows, columns)| !rows.is_empty() && !columns.is_empty());
+        blocks
+    }
+
     /// Solve the assignment problem using Hungarian algorithm
-    fn solve_hungarian_assignment(&self, cost_matrix: &[Vec<f64>]) -> Result<Vec<(usize, usize)>> {
+    fn solve_hungarian_assignment(cost_matrix: &[Vec<f64>]) -> Result<Vec<(usize, usize)>> {
         use hungarian::minimize;
 
         // Convert to the format expected by the hungarian crate
@@ -307,8 +587,7 @@ impl HungarianMatcher {
         &self,
         source_functions: &[(EnhancedFunctionSignature, ASTNode)],
         target_functions: &[(EnhancedFunctionSignature, ASTNode)],
-        assignments: &[(usize, usize)],
-        similarity_matrix: &[Vec<ComprehensiveSimilarityScore>],
+        assignments: Vec<(usize, usize, ComprehensiveSimilarityScore)>,
     ) -> Result<HungarianMatchResult> {
         let mut function_assignments = Vec::new();
         let mut matched_source = HashSet::new();
@@ -317,22 +596,21 @@ impl HungarianMatcher {
         let mut total_similarity = 0.0;
 
         // Process valid assignments
-        for &(source_idx, target_idx) in assignments {
-            let similarity = &similarity_matrix[source_idx][target_idx];
+        for (source_idx, target_idx, similarity) in assignments {
             let cost = 1.0 - similarity.overall_similarity;
 
+            matched_source.insert(source_idx);
+            matched_target.insert(target_idx);
+            total_cost += cost;
+            total_similarity += similarity.overall_similarity;
+
             function_assignments.push(FunctionAssignment {
                 source_index: source_idx,
                 target_index: target_idx,
-                similarity: similarity.clone(),
-                cost,
                 confidence: similarity.confidence,
+                similarity,
+                cost,
             });
-
-            matched_source.insert(source_idx);
-            matched_target.insert(target_idx);
-            total_cost += cost;
-            total_similarity += similarity.overall_similarity;
         }
 
         // Find unmatched functions
@@ -368,6 +646,10 @@ impl HungarianMatcher {
                 match_percentage: 0.0,
                 average_cost: 0.0,
                 execution_time_ms: 0,
+                similarity_computations: 0,
+                similarity_cache_hits: 0,
+                assignment_blocks: 0,
+                greedy_fallback: false,
             }, // Will be calculated later
         })
     }
@@ -426,7 +708,7 @@ impl HungarianMatcher {
 
         // For each unmatched source function, look for multiple similar target functions
         for &source_idx in unmatched_source {
-            let (source_sig, source_ast) = &source_functions[source_idx];
+            let source_sig = &source_functions[source_idx].0;
             let mut candidates = Vec::new();
 
             // Check unmatched target functions for similarity
@@ -435,9 +717,11 @@ impl HungarianMatcher {
                     continue;
                 }
 
-                let (target_sig, target_ast) = &target_functions[target_idx];
-                let similarity = self.similarity_scorer.calculate_comprehensive_similarity(
-                    source_sig, source_ast, target_sig, target_ast,
+                let similarity = self.pair_similarity(
+                    source_functions,
+                    target_functions,
+                    source_idx,
+                    target_idx,
                 )?;
 
                 if similarity.overall_similarity >= self.config.min_similarity_threshold {
@@ -501,7 +785,7 @@ impl HungarianMatcher {
 
         // For each unmatched target function, look for multiple similar source functions
         for &target_idx in unmatched_target {
-            let (target_sig, target_ast) = &target_functions[target_idx];
+            let target_sig = &target_functions[target_idx].0;
             let mut candidates = Vec::new();
 
             // Check unmatched source functions for similarity
@@ -510,9 +794,11 @@ impl HungarianMatcher {
                     continue;
                 }
 
-                let (source_sig, source_ast) = &source_functions[source_idx];
-                let similarity = self.similarity_scorer.calculate_comprehensive_similarity(
-                    source_sig, source_ast, target_sig, target_ast,
+                let similarity = self.pair_similarity(
+                    source_functions,
+                    target_functions,
+                    source_idx,
+                    target_idx,
                 )?;
 
                 if similarity.overall_similarity >= self.config.min_similarity_threshold {
@@ -706,11 +992,11 @@ impl HungarianMatcher {
         // Calculate pairwise similarities within the groups
         for &source_idx in source_group {
             for &target_idx in target_group {
-                let (source_sig, source_ast) = &source_functions[source_idx];
-                let (target_sig, target_ast) = &target_functions[target_idx];
-
-                let similarity = self.similarity_scorer.calculate_comprehensive_similarity(
-                    source_sig, source_ast, target_sig, target_ast,
+                let similarity = self.pair_similarity(
+                    source_functions,
+                    target_functions,
+                    source_idx,
+                    target_idx,
                 )?;
 
                 total_similarity += similarity.overall_similarity;
@@ -840,6 +1126,10 @@ impl HungarianMatcher {
             match_percentage,
             average_cost,
             execution_time_ms,
+            similarity_computations: self.similarity_computations,
+            similarity_cache_hits: self.similarity_cache_hits,
+            assignment_blocks: self.assignment_blocks,
+            greedy_fallback: self.greedy_fallback,
         }
     }
 
@@ -862,6 +1152,10 @@ impl HungarianMatcher {
                 match_percentage: 100.0,
                 average_cost: 0.0,
                 execution_time_ms,
+                similarity_computations: 0,
+                similarity_cache_hits: 0,
+                assignment_blocks: 0,
+                greedy_fallback: false,
             },
         }
     }
@@ -885,6 +1179,10 @@ impl HungarianMatcher {
                 match_percentage: 0.0,
                 average_cost: 1.0,
                 execution_time_ms: 0,
+                similarity_computations: 0,
+                similarity_cache_hits: 0,
+                assignment_blocks: 0,
+                greedy_fallback: false,
             },
         }
     }
@@ -908,6 +1206,10 @@ impl HungarianMatcher {
                 match_percentage: 0.0,
                 average_cost: 1.0,
                 execution_time_ms: 0,
+                similarity_computations: 0,
+                similarity_cache_hits: 0,
+                assignment_blocks: 0,
+                greedy_fallback: false,
             },
         }
     }
@@ -981,6 +1283,8 @@ mod tests {
         assert_eq!(config.max_candidates_per_function, 10);
         assert!(config.enable_cross_file_matching);
         assert_eq!(config.cross_file_penalty, 0.1);
+        assert!(config.enable_similarity_cache);
+        assert_eq!(config.max_matrix_cells, 1_000_000);
     }
 
     #[test]
@@ -1208,6 +1512,196 @@ mod tests {
         assert!(complex_confidence <= 1.0);
     }
 
+    #[test]
+    fn test_similarity_computed_once_per_pair() {
+        let functions = |names: &[&str]| -> Vec<(EnhancedFunctionSignature, ASTNode)> {
+            names
+                .iter()
+                .map(|name| {
+                    (
+                        create_test_function_signature(name, "test.java"),
+                        create_test_ast_node(NodeType::Function),
+                    )
+                })
+                .collect()
+        };
+        let source_functions = functions(&["load", "parse", "render"]);
+        let target_functions = functions(&["fetch", "decode", "draw"]);
+
+        // Nothing is assigned, so the split, merge and group passes revisit every pair
+        let config = |enable_similarity_cache| HungarianMatcherConfig {
+            min_similarity_threshold: 1.01,
+            max_assignment_cost: -1.0,
+            enable_similarity_cache,
+            ..Default::default()
+        };
+
+        let mut matcher = HungarianMatcher::new(Language::Java, config(true));
+        let cached = matcher
+            .match_functions(&source_functions, &target_functions)
+            .unwrap();
+        assert_eq!(cached.unmatched_source.len(), 3);
+        assert_eq!(cached.statistics.similarity_computations, 9);
+        assert!(cached.statistics.similarity_cache_hits > 0);
+        assert!(matcher.similarity_cache.is_empty());
+
+        let mut matcher = HungarianMatcher::new(Language::Java, config(false));
+        let uncached = matcher
+            .match_functions(&source_functions, &target_functions)
+            .unwrap();
+        assert_eq!(uncached.statistics.similarity_cache_hits, 0);
+        assert_eq!(
+            uncached.statistics.similarity_computations,
+            9 + cached.statistics.similarity_cache_hits
+        );
+        assert_eq!(
+            uncached.many_to_many_mappings.len(),
+            cached.many_to_many_mappings.len()
+        );
+    }
+
+    #[test]
+    fn test_blocked_matching_matches_full_matrix() {
+        // Functions of different files and shapes, each renamed in a small way,
+        // plus some that were deleted or added
+        let families = [
+            ("parser.java", NodeType::Function),
+            ("render.java", NodeType::Method),
+            ("store.java", NodeType::Constructor),
+        ];
+        let mut source_functions = Vec::new();
+        let mut target_functions = Vec::new();
+        for (family, (file, node_type)) in families.iter().enumerate() {
+            for i in 0..12 {
+                let name = format!("{}_step{}", ["parse", "draw", "save"][family], i);
+                let node = create_test_ast_node(*node_type);
+                if i % 5 != 4 {
+                    source_functions
+                        .push((create_test_function_signature(&name, file), node.clone()));
+                }
+                if i % 6 != 5 {
+                    let renamed = if i % 3 == 0 {
+                        format!("{}V2", name)
+                    } else {
+                        name
+                    };
+                    target_functions.push((create_test_function_signature(&renamed, file), node));
+                }
+            }
+        }
+        target_functions.reverse();
+
+        let config = |enable_block_decomposition| HungarianMatcherConfig {
+            min_similarity_threshold: 0.8,
+            max_assignment_cost: 0.2,
+            enable_cross_file_matching: false,
+            enable_many_to_many: false,
+            enable_block_decomposition,
+            ..Default::default()
+        };
+        let pairs = |result: &HungarianMatchResult| -> Vec<(usize, usize)> {
+            result
+                .assignments
+                .iter()
+                .map(|a| (a.source_index, a.target_index))
+                .collect()
+        };
+
+        let full = HungarianMatcher::new(Language::Java, config(false))
+            .match_functions(&source_functions, &target_functions)
+            .unwrap();
+        let blocked = HungarianMatcher::new(Language::Java, config(true))
+            .match_functions(&source_functions, &target_functions)
+            .unwrap();
+
+        assert_eq!(full.statistics.assignment_blocks, 1);
+        assert!(
+            blocked.statistics.assignment_blocks > 1,
+            "{}",
+            blocked.statistics.assignment_blocks
+        );
+        assert!(!full.assignments.is_empty());
+        assert_eq!(pairs(&blocked), pairs(&full));
+        assert_eq!(blocked.unmatched_source, full.unmatched_source);
+        assert_eq!(blocked.unmatched_target, full.unmatched_target);
+    }
+
+    #[test]
+    fn test_oversized_matrix_falls_back_to_greedy_matching() {
+        let mut source_functions = Vec::new();
+        let mut target_functions = Vec::new();
+        for i in 0..8 {
+            let name = format!("parse_step{}", i);
+            let node = create_test_ast_node(NodeType::Function);
+            if i != 7 {
+                source_functions.push((
+                    create_test_function_signature(&name, "parser.java"),
+                    node.clone(),
+                ));
+            }
+            if i != 0 {
+                target_functions.push((create_test_function_signature(&name, "parser.java"), node));
+            }
+        }
+        target_functions.reverse();
+
+        // 7 x 7 functions make a 49 cell matrix
+        let config = |max_matrix_cells| HungarianMatcherConfig {
+            min_similarity_threshold: 0.8,
+            max_assignment_cost: 0.2,
+            max_matrix_cells,
+            ..Default::default()
+        };
+
+        let mut matcher = HungarianMatcher::new(Language::Java, config(48));
+        let result = matcher
+            .match_functions(&source_functions, &target_functions)
+            .unwrap();
+
+        assert!(result.statistics.greedy_fallback);
+        assert_eq!(result.statistics.assignment_blocks, 0);
+        assert_eq!(result.statistics.similarity_computations, 49);
+        assert_eq!(result.statistics.similarity_cache_hits, 0);
+        assert!(result.many_to_many_mappings.is_empty());
+
+        // Every function is either assigned exactly once or left unmatched
+        let sources: HashSet<usize> = result.assignments.iter().map(|a| a.source_index).collect();
+        let targets: HashSet<usize> = result.assignments.iter().map(|a| a.target_index).collect();
+        assert_eq!(sources.len(), result.assignments.len());
+        assert_eq!(targets.len(), result.assignments.len());
+        assert_eq!(
+            result.assignments.len() + result.unmatched_source.len(),
+            source_functions.len()
+        );
+        assert_eq!(
+            result.assignments.len() + result.unmatched_target.len(),
+            target_functions.len()
+        );
+        assert!(result
+            .unmatched_source
+            .iter()
+            .all(|index| !sources.contains(index)));
+        assert!(result
+            .unmatched_target
+            .iter()
+            .all(|index| !targets.contains(index)));
+
+        // Functions present on both sides find themselves
+        let unchanged = result
+            .assignments
+            .iter()
+            .filter(|a| {
+                source_functions[a.source_index].0.name == target_functions[a.target_index].0.name
+            })
+            .count();
+        assert_eq!(unchanged, 6);
+
+        let optimal = HungarianMatcher::new(Language::Java, config(49))
+            .match_functions(&source_functions, &target_functions)
+            .unwrap();
+        assert!(!optimal.statistics.greedy_fallback);
+    }
+
     #[test]
     fn test_config_updates() {
         let mut matcher = HungarianMatcher::with_defaults(Language::Java);
@@ -1222,6 +1716,9 @@ mod tests {
             max_candidates_per_function: 5,
             enable_cross_file_matching: false,
             cross_file_penalty: 0.2,
+            enable_similarity_cache: false,
+            enable_block_decomposition: false,
+            max_matrix_cells: 100,
         };
 
         matcher.set_config(new_config);
@@ -1232,5 +1729,6 @@ mod tests {
         assert_eq!(matcher.get_config().max_candidates_per_function, 5);
         assert!(!matcher.get_config().enable_cross_file_matching);
         assert_eq!(matcher.get_config().cross_file_penalty, 0.2);
+        assert_eq!(matcher.get_config().max_matrix_cells, 100);
     }
 }
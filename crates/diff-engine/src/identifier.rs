@@ -0,0 +1,98 @@
+//! Identifier normalization across naming conventions
+//!
+//! Code ported between languages or restyled often renames `getUserName` to
+//! `get_user_name` or `GetUserName` without changing what it refers to. These
+//! helpers split an identifier into its words so such names compare equal,
+//! while names made of different words (`getUser` vs `getUsers`) stay distinct.
+
+/// Split an identifier into lowercase words on camelCase, PascalCase,
+/// snake_case and kebab-case boundaries
+///
+/// Acronyms stay whole (`HTTPServer` is `http`, `server`) and digits stick to
+/// the word they follow (`utf8Decode` is `utf8`, `decode`).
+pub fn identifier_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+
+    for part in name.split(|c: char| !c.is_alphanumeric()) {
+        let chars: Vec<char> = part.chars().collect();
+        let mut word = String::new();
+
+        for (i, &c) in chars.iter().enumerate() {
+            let prev = i.checked_sub(1).map(|p| chars[p]);
+            let next = chars.get(i + 1);
+
+            let boundary = c.is_uppercase()
+                && prev.is_some_and(|p| {
+                    // fooBar, utf8Decode
+                    !p.is_uppercase()
+                        // HTTPServer: the `S` starts a new word
+                        || next.is_some_and(|n| n.is_lowercase())
+                });
+            if boundary && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            word.extend(c.to_lowercase());
+        }
+
+        if !word.is_empty() {
+            words.push(word);
+        }
+    }
+
+    words
+}
+
+/// Canonical form of an identifier: its words joined by `_`
+///
+/// Leading underscores are kept, since they mark private or special names
+/// (`_cache`, `__init__`) rather than separate words.
+pub fn normalize_identifier(name: &str) -> String {
+    let prefix_len = name.len() - name.trim_start_matches('_').len();
+    format!(
+        "{}{}",
+        &name[..prefix_len],
+        identifier_words(name).join("_")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_naming_conventions_normalize_equal() {
+        let canonical = normalize_identifier("getUserName");
+        assert_eq!(canonical, "get_user_name");
+        assert_eq!(normalize_identifier("get_user_name"), canonical);
+        assert_eq!(normalize_identifier("GetUserName"), canonical);
+        assert_eq!(normalize_identifier("GET_USER_NAME"), canonical);
+        assert_eq!(normalize_identifier("get-user-name"), canonical);
+
+        assert_eq!(normalize_identifier("HTTPServer"), "http_server");
+        assert_eq!(
+            normalize_identifier("parseHTTPResponse"),
+            "parse_http_response"
+        );
+        assert_eq!(normalize_identifier("utf8Decode"), "utf8_decode");
+    }
+
+    #[test]
+    fn test_different_names_stay_distinct() {
+        assert_ne!(
+            normalize_identifier("getUser"),
+            normalize_identifier("getUsers")
+        );
+        assert_ne!(
+            normalize_identifier("getUser"),
+            normalize_identifier("get_user_name")
+        );
+        assert_ne!(
+            normalize_identifier("_cache"),
+            normalize_identifier("cache")
+        );
+        assert_ne!(
+            normalize_identifier("__init__"),
+            normalize_identifier("init")
+        );
+    }
+}
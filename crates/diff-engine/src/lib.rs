@@ -3,25 +3,49 @@
 //! Core diff computation engine that implements tree edit distance algorithms,
 //! function matching, and change classification.
 
+pub mod behavior;
+pub mod binary_format;
 pub mod binary_matcher;
+pub mod call_site_tracker;
+pub mod cancellation;
 pub mod changes;
 pub mod class_hierarchy_tracker;
+pub mod clone_detector;
+pub mod code_version;
 pub mod cross_file_tracker;
+pub mod dockerfile_matcher;
 pub mod engine;
 pub mod file_refactoring_detector;
 pub mod graph_matcher;
+pub mod history;
 pub mod hungarian_matcher;
+pub mod identifier;
+pub mod literal_changes;
+pub mod match_hints;
 pub mod matching;
+pub mod progress;
 pub mod refactoring;
+pub mod section_matcher;
+pub mod signature_matcher;
 pub mod similarity_scorer;
 pub mod smart_matcher;
 pub mod symbol_migration_tracker;
+pub mod token_similarity;
 pub mod tree_edit;
 
+pub use behavior::{
+    BehaviorAssessment, BehaviorClassifier, BehaviorClassifierConfig, BehaviorImpact,
+    PreservationEvidence,
+};
 pub use binary_matcher::{
     BinaryFunctionInfo, BinaryFunctionMatch, BinaryFunctionMatcher, BinaryMatchType,
     BinaryMatcherConfig,
 };
+pub use call_site_tracker::{
+    CallSite, CallSiteAnalysisResult, CallSiteTracker, CallSiteTrackerConfig, DeletionImpact,
+    DeletionRisk, FunctionCallImpact,
+};
+pub use cancellation::CancellationToken;
 pub use changes::{
     AlternativeClassification, ChangeAnalysis, ChangeCharacteristic, ChangeClassificationConfig,
     ChangeClassifier, ChangeImpact, CharacteristicType, ClassificationEvidence,
@@ -33,10 +57,18 @@ pub use class_hierarchy_tracker::{
     HierarchyChangeType, HierarchyStatistics, InterfaceChange, InterfaceChangeType, MethodInfo,
     MethodMigration, MethodMigrationType, Visibility,
 };
+pub use clone_detector::{
+    CloneCluster, CloneDetector, CloneDetectorConfig, CloneInstance, CloneType,
+};
+pub use code_version::CodeVersion;
 pub use cross_file_tracker::{
     CrossFileMerge, CrossFileSplit, CrossFileTracker, CrossFileTrackerConfig,
-    CrossFileTrackingResult, CrossFileTrackingStats, FileTrackingStats, FunctionMove,
-    FunctionRenameMove, MoveType,
+    CrossFileTrackingResult, CrossFileTrackingStats, FileRelocation, FileTrackingStats,
+    FunctionMove, FunctionRenameMove, MoveType,
+};
+pub use dockerfile_matcher::{
+    extract_stages, BuildStage, DockerInstruction, DockerfileMatcher, DockerfileMatcherConfig,
+    InstructionSignificance,
 };
 pub use engine::{DiffEngine, DiffError, DiffResult};
 pub use file_refactoring_detector::{
@@ -49,18 +81,31 @@ pub use graph_matcher::{
     FunctionRename, GraphMatchResult, GraphMatcher, GraphMatcherConfig,
     MatchType as GraphMatchType,
 };
+pub use history::{
+    FunctionEvent, FunctionHistory, FunctionTrajectory, HistoryComparer, TrajectoryStep,
+};
 pub use hungarian_matcher::{
     FunctionAssignment, HungarianMatchResult, HungarianMatcher, HungarianMatcherConfig,
     ManyToManyMapping, MappingType, MatchingStatistics,
 };
+pub use identifier::{identifier_words, normalize_identifier};
+pub use literal_changes::{
+    detect_literal_changes, LiteralChange, LiteralChangeKind, LiteralChanges,
+};
+pub use match_hints::{MatchHint, MatchHints};
 pub use matching::{FunctionMatcher, SimilarityScore};
+pub use progress::{ComparisonPhase, ComparisonProgress, ProgressCallback};
 pub use refactoring::{
     ApiCompatibilityImpact, BeforeAfterComparison, RefactoringAnalysis, RefactoringCharacteristic,
     RefactoringCharacteristicType, RefactoringComplexity, RefactoringComplexityLevel,
     RefactoringDetectionConfig, RefactoringDetector, RefactoringEffort, RefactoringEvidence,
     RefactoringEvidenceType, RefactoringImpact, RefactoringImpactLevel, RefactoringPattern,
-    RefactoringQualityMetrics, SizeComparison,
+    RefactoringQualityMetrics, RefactoringRule, SizeComparison, MIN_CONFIDENCE_FLOOR,
 };
+pub use section_matcher::{
+    extract_sections, DocumentSection, SectionMatcher, SectionMatcherConfig,
+};
+pub use signature_matcher::{signature_declaration, signature_element, SignatureMatcher};
 pub use similarity_scorer::{
     ASTSimilarityScore, ComprehensiveSimilarityScore, ContextSimilarityScore,
     DetailedSimilarityBreakdown, MatchType, SemanticSimilarityMetrics, SimilarityFactor,
@@ -68,11 +113,14 @@ pub use similarity_scorer::{
 };
 pub use smart_diff_parser::MatchResult;
 pub use smart_diff_parser::{Change, ChangeType};
-pub use smart_matcher::{SmartMatcher, SmartMatcherConfig};
+pub use smart_matcher::{
+    CrossFilePenaltyCurve, ExactMatchPrepass, SmartMatcher, SmartMatcherConfig,
+};
 pub use symbol_migration_tracker::{
     FileMigration, MigrationStatistics, ReferenceChange, ReferenceChangeType, SymbolMigration,
     SymbolMigrationResult, SymbolMigrationTracker, SymbolMigrationTrackerConfig,
 };
+pub use token_similarity::{tokenize, TokenSimilarity, TokenSimilarityConfig};
 pub use tree_edit::{EditCost, EditOperation, TreeEditDistance, ZhangShashaConfig};
 
 /// Re-export commonly used types
@@ -0,0 +1,263 @@
+//! Changes to the literals of a matched function
+//!
+//! A changed threshold, error message or query is easy to miss inside a
+//! generic "modified" change. The string and numeric literals of both
+//! versions of a function are aligned in source order, so each can be
+//! reported as added, removed or changed, and a change that touches nothing
+//! but literals is told apart from a change to the logic around them.
+
+use serde::{Deserialize, Serialize};
+use smart_diff_parser::{ASTNode, NodeType};
+
+/// Node kinds holding a literal in the supported grammars, beyond those
+/// already mapped to `NodeType::Literal`
+const LITERAL_KINDS: &[&str] = &[
+    // Strings
+    "string",
+    "string_literal",
+    "raw_string_literal",
+    "interpreted_string_literal",
+    "concatenated_string",
+    "template_string",
+    "text_block",
+    "encapsed_string",
+    "line_string_literal",
+    "multi_line_string_literal",
+    "char_literal",
+    "character_literal",
+    "rune_literal",
+    // Numbers
+    "number",
+    "integer",
+    "float",
+    "number_literal",
+    "integer_literal",
+    "float_literal",
+    "int_literal",
+    "real_literal",
+    "imaginary_literal",
+    "decimal_integer_literal",
+    "hex_integer_literal",
+    "octal_integer_literal",
+    "binary_integer_literal",
+    "decimal_floating_point_literal",
+    "hex_floating_point_literal",
+];
+
+/// Stands in for every literal when comparing the code around them
+const LITERAL_PLACEHOLDER: &str = "\0literal";
+
+/// How a literal differs between the two versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiteralChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A literal added, removed or changed in a matched function
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LiteralChange {
+    pub kind: LiteralChangeKind,
+    /// Literal as written in the source version
+    pub old_value: Option<String>,
+    /// Literal as written in the target version
+    pub new_value: Option<String>,
+    /// Line of the literal, in the target version unless it was removed
+    pub line: usize,
+}
+
+/// Literal changes between two versions of a function
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LiteralChanges {
+    pub changes: Vec<LiteralChange>,
+    /// Nothing but literals changed: the code around them is identical
+    pub literal_only: bool,
+}
+
+/// Literal changes between two versions of a function, `None` if its literals are unchanged
+pub fn detect_literal_changes(source: &ASTNode, target: &ASTNode) -> Option<LiteralChanges> {
+    let (mut source_literals, mut source_skeleton) = (Vec::new(), Vec::new());
+    collect(source, &mut source_literals, &mut source_skeleton);
+    let (mut target_literals, mut target_skeleton) = (Vec::new(), Vec::new());
+    collect(target, &mut target_literals, &mut target_skeleton);
+
+    let literal_only = source_skeleton == target_skeleton;
+    let changes = if literal_only {
+        // Same shape, so the literals pair up by position
+        source_literals
+            .iter()
+            .zip(&target_literals)
+            .filter(|(old, new)| old.value != new.value)
+            .map(|(old, new)| LiteralChange::changed(old, new))
+            .collect()
+    } else {
+        align(&source_literals, &target_literals)
+    };
+
+    (!changes.is_empty()).then_some(LiteralChanges {
+        changes,
+        literal_only,
+    })
+}
+
+struct Literal {
+    value: String,
+    line: usize,
+}
+
+impl LiteralChange {
+    fn changed(old: &Literal, new: &Literal) -> Self {
+        Self {
+            kind: LiteralChangeKind::Changed,
+            old_value: Some(old.value.clone()),
+            new_value: Some(new.value.clone()),
+            line: new.line,
+        }
+    }
+}
+
+pub(crate) fn is_literal(node: &ASTNode) -> bool {
+    node.node_type == NodeType::Literal
+        || node
+            .metadata
+            .attributes
+            .get("kind")
+            .is_some_and(|kind| LITERAL_KINDS.contains(&kind.as_str()))
+}
+
+/// Collect the literals of `node` in source order, and the text of every
+/// other leaf with literals replaced by a placeholder
+fn collect(node: &ASTNode, literals: &mut Vec<Literal>, skeleton: &mut Vec<String>) {
+    if is_literal(node) {
+        literals.push(Literal {
+            value: node.metadata.original_text.trim().to_string(),
+            line: node.metadata.line,
+        });
+        skeleton.push(LITERAL_PLACEHOLDER.to_string());
+    } else if node.children.is_empty() {
+        skeleton.push(node.metadata.original_text.trim().to_string());
+    } else {
+        for child in &node.children {
+            collect(child, literals, skeleton);
+        }
+    }
+}
+
+/// Align two literal sequences by their longest common subsequence
+///
+/// Literals removed and added at the same point are paired up as changed;
+/// any left over are reported as removed or added.
+fn align(source: &[Literal], target: &[Literal]) -> Vec<LiteralChange> {
+    let (n, m) = (source.len(), target.len());
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if source[i].value == target[j].value {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && source[i].value == target[j].value {
+            flush(&mut removed, &mut added, &mut changes);
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lengths[i][j + 1] >= lengths[i + 1][j]) {
+            added.push(&target[j]);
+            j += 1;
+        } else {
+            removed.push(&source[i]);
+            i += 1;
+        }
+    }
+    flush(&mut removed, &mut added, &mut changes);
+    changes
+}
+
+fn flush(removed: &mut Vec<&Literal>, added: &mut Vec<&Literal>, changes: &mut Vec<LiteralChange>) {
+    let paired = removed.len().min(added.len());
+    for (old, new) in removed.iter().zip(added.iter()) {
+        changes.push(LiteralChange::changed(old, new));
+    }
+    for old in &removed[paired..] {
+        changes.push(LiteralChange {
+            kind: LiteralChangeKind::Removed,
+            old_value: Some(old.value.clone()),
+            new_value: None,
+            line: old.line,
+        });
+    }
+    for new in &added[paired..] {
+        changes.push(LiteralChange {
+            kind: LiteralChangeKind::Added,
+            old_value: None,
+            new_value: Some(new.value.clone()),
+            line: new.line,
+        });
+    }
+    removed.clear();
+    added.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smart_diff_parser::{tree_sitter::TreeSitterParser, Language, Parser};
+
+    fn parse_function(code: &str) -> ASTNode {
+        let parser = TreeSitterParser::new().unwrap();
+        let result = parser.parse(code, Language::Python).unwrap();
+        result.ast.find_by_type(&NodeType::Function)[0].clone()
+    }
+
+    #[test]
+    fn test_changed_constant_is_a_literal_only_change() {
+        let before = parse_function(
+            "def should_alert(load):\n    if load > 0.75:\n        log(\"high load\")\n        return True\n    return False\n",
+        );
+        let after = parse_function(
+            "def should_alert(load):\n    if load > 0.9:\n        log(\"high load\")\n        return True\n    return False\n",
+        );
+
+        let literals = detect_literal_changes(&before, &after).expect("literal change");
+        assert!(literals.literal_only);
+        assert_eq!(
+            literals.changes,
+            vec![LiteralChange {
+                kind: LiteralChangeKind::Changed,
+                old_value: Some("0.75".to_string()),
+                new_value: Some("0.9".to_string()),
+                line: 2,
+            }]
+        );
+        assert!(detect_literal_changes(&before, &before).is_none());
+
+        // A new branch changes the logic, and brings its own literals
+        let reworked = parse_function(
+            "def should_alert(load):\n    if load > 0.9:\n        log(\"high load\")\n        return True\n    if load < 0:\n        raise ValueError(\"negative load\")\n    return False\n",
+        );
+        let literals = detect_literal_changes(&before, &reworked).expect("literal changes");
+        assert!(!literals.literal_only);
+        let kinds: Vec<_> = literals.changes.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                LiteralChangeKind::Changed,
+                LiteralChangeKind::Added,
+                LiteralChangeKind::Added
+            ]
+        );
+        assert_eq!(
+            literals.changes[2].new_value.as_deref(),
+            Some("\"negative load\"")
+        );
+    }
+}
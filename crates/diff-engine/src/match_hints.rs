@@ -0,0 +1,175 @@
+//! User-provided hints that anchor function matching
+//!
+//! The matcher pairs functions by similarity, and sometimes gets a pairing
+//! wrong: a function rewritten past recognition and renamed, or two similar
+//! helpers swapped. A hint names a source function and a target function and
+//! either forces them to be paired, whatever their similarity, or forbids
+//! pairing them. Forced pairs are fixed before the assignment is solved, and
+//! every other function is matched around them.
+
+use crate::engine::DiffError;
+use smart_diff_parser::Function;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// A single hint, written `OLD=NEW` to force a pairing or `OLD!=NEW` to forbid it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchHint {
+    Force { source: String, target: String },
+    Forbid { source: String, target: String },
+}
+
+impl FromStr for MatchHint {
+    type Err = DiffError;
+
+    fn from_str(hint: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || DiffError::InvalidMatchHints(format!("'{}' is neither OLD=NEW nor OLD!=NEW", hint));
+        let (source, target, forbid) = match hint.split_once("!=") {
+            Some((source, target)) => (source, target, true),
+            None => {
+                let (source, target) = hint.split_once('=').ok_or_else(invalid)?;
+                (source, target, false)
+            }
+        };
+        let (source, target) = (source.trim().to_string(), target.trim().to_string());
+        if source.is_empty() || target.is_empty() || target.contains('=') {
+            return Err(invalid());
+        }
+
+        Ok(if forbid {
+            MatchHint::Forbid { source, target }
+        } else {
+            MatchHint::Force { source, target }
+        })
+    }
+}
+
+/// Validated hints: no function is forced onto two others, and no pairing is
+/// both forced and forbidden
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatchHints {
+    forced: Vec<(String, String)>,
+    forbidden: Vec<(String, String)>,
+}
+
+impl MatchHints {
+    /// Validate `hints`, failing on the first pair of hints that contradict each other
+    pub fn new(hints: impl IntoIterator<Item = MatchHint>) -> Result<Self, DiffError> {
+        let mut result = Self::default();
+        let mut forced_targets: HashMap<String, String> = HashMap::new();
+        let mut forced_sources: HashMap<String, String> = HashMap::new();
+
+        for hint in hints {
+            match hint {
+                MatchHint::Force { source, target } => {
+                    if let Some(previous) = forced_targets.get(&source) {
+                        if *previous != target {
+                            return Err(DiffError::InvalidMatchHints(format!(
+                                "'{}' is forced onto both '{}' and '{}'",
+                                source, previous, target
+                            )));
+                        }
+                        continue;
+                    }
+                    if let Some(previous) = forced_sources.get(&target) {
+                        return Err(DiffError::InvalidMatchHints(format!(
+                            "both '{}' and '{}' are forced onto '{}'",
+                            previous, source, target
+                        )));
+                    }
+                    forced_targets.insert(source.clone(), target.clone());
+                    forced_sources.insert(target.clone(), source.clone());
+                    result.forced.push((source, target));
+                }
+                MatchHint::Forbid { source, target } => {
+                    if !result.forbidden.contains(&(source.clone(), target.clone())) {
+                        result.forbidden.push((source, target));
+                    }
+                }
+            }
+        }
+
+        if let Some((source, target)) = result
+            .forbidden
+            .iter()
+            .find(|pair| result.forced.contains(pair))
+        {
+            return Err(DiffError::InvalidMatchHints(format!(
+                "'{}' onto '{}' is both forced and forbidden",
+                source, target
+            )));
+        }
+
+        Ok(result)
+    }
+
+    /// Parse and validate hints written `OLD=NEW` or `OLD!=NEW`
+    pub fn parse<S: AsRef<str>>(hints: &[S]) -> Result<Self, DiffError> {
+        let hints = hints
+            .iter()
+            .map(|hint| hint.as_ref().parse())
+            .collect::<Result<Vec<MatchHint>, _>>()?;
+        Self::new(hints)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.forced.is_empty() && self.forbidden.is_empty()
+    }
+
+    /// Source and target names of the forced pairings
+    pub fn forced(&self) -> &[(String, String)] {
+        &self.forced
+    }
+
+    /// Source and target names of the forbidden pairings
+    pub fn forbidden(&self) -> &[(String, String)] {
+        &self.forbidden
+    }
+
+    /// Fix the forced pairs of `source` and `target` functions and take them,
+    /// and the forbidden pairs, out of the similarity matrix
+    ///
+    /// Hints naming a function absent from either side are ignored. When a
+    /// name is shared, as by overloads, its functions are paired in order.
+    pub(crate) fn apply(
+        &self,
+        source: &[Function],
+        target: &[Function],
+        similarity_matrix: &mut [Vec<f64>],
+    ) -> Vec<(usize, usize)> {
+        let indices = |functions: &[Function], name: &str| -> Vec<usize> {
+            functions
+                .iter()
+                .enumerate()
+                .filter(|(_, function)| function.signature.name == name)
+                .map(|(index, _)| index)
+                .collect()
+        };
+
+        let mut pairs = Vec::new();
+        for (source_name, target_name) in &self.forced {
+            for (s, t) in indices(source, source_name)
+                .into_iter()
+                .zip(indices(target, target_name))
+            {
+                // Neither function is left for the assignment to pair otherwise
+                similarity_matrix[s].fill(f64::NEG_INFINITY);
+                for row in similarity_matrix.iter_mut() {
+                    row[t] = f64::NEG_INFINITY;
+                }
+                pairs.push((s, t));
+            }
+        }
+
+        for (source_name, target_name) in &self.forbidden {
+            for s in indices(source, source_name) {
+                for t in indices(target, target_name) {
+                    similarity_matrix[s][t] = f64::NEG_INFINITY;
+                }
+            }
+        }
+
+        pairs
+    }
+}
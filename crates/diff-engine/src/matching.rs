@@ -1,11 +1,20 @@
 //! Function matching algorithms
 
+use crate::cancellation::CancellationToken;
+use crate::literal_changes::detect_literal_changes;
+use crate::match_hints::MatchHints;
 use serde::{Deserialize, Serialize};
-use smart_diff_parser::{Function, MatchResult};
+use smart_diff_parser::{anonymous_function_scope, Function, MatchResult};
+
+/// Similarity subtracted when matching functions compiled under different
+/// preprocessor conditions, enough to break ties between identical bodies
+const CONDITION_CHANGE_PENALTY: f64 = 0.01;
 
 /// Function matcher that finds optimal mappings between function sets
 pub struct FunctionMatcher {
     threshold: f64,
+    hints: MatchHints,
+    cancellation: CancellationToken,
 }
 
 /// Similarity score between two functions
@@ -19,7 +28,22 @@ pub struct SimilarityScore {
 
 impl FunctionMatcher {
     pub fn new(threshold: f64) -> Self {
-        Self { threshold }
+        Self {
+            threshold,
+            hints: MatchHints::default(),
+            cancellation: CancellationToken::default(),
+        }
+    }
+
+    /// Give up on `match_functions` once `cancellation` is cancelled, with an
+    /// empty result
+    pub fn set_cancellation(&mut self, cancellation: CancellationToken) {
+        self.cancellation = cancellation;
+    }
+
+    /// Force or forbid pairings in `match_functions`, whatever the similarity
+    pub fn set_hints(&mut self, hints: MatchHints) {
+        self.hints = hints;
     }
 
     /// Match functions between two sets using Hungarian algorithm
@@ -36,11 +60,20 @@ impl FunctionMatcher {
         }
 
         // Calculate similarity matrix
-        let similarity_matrix =
+        let mut similarity_matrix =
             self.calculate_similarity_matrix(source_functions, target_functions);
+        if self.cancellation.is_cancelled() {
+            return MatchResult::new();
+        }
+
+        // Hinted pairs are fixed first, the rest are matched around them
+        let forced = self
+            .hints
+            .apply(source_functions, target_functions, &mut similarity_matrix);
 
         // Apply Hungarian algorithm for optimal matching
-        let matches = self.hungarian_matching(&similarity_matrix);
+        let mut matches = self.hungarian_matching(&similarity_matrix);
+        matches.extend(forced);
 
         // Process matches and create result
         self.process_matches(source_functions, target_functions, &matches, &mut result);
@@ -49,7 +82,9 @@ impl FunctionMatcher {
         result
     }
 
-    fn calculate_similarity_matrix(
+    /// Similarity of every source function to every target function,
+    /// independent of the threshold
+    pub fn calculate_similarity_matrix(
         &self,
         source: &[Function],
         target: &[Function],
@@ -57,10 +92,21 @@ impl FunctionMatcher {
         let mut matrix = Vec::new();
 
         for source_func in source {
+            // Scoring is most of the work, so a cancelled match stops here
+            if self.cancellation.is_cancelled() {
+                break;
+            }
             let mut row = Vec::new();
             for target_func in target {
                 let similarity = self.calculate_function_similarity(source_func, target_func);
-                row.push(similarity.overall_similarity);
+                // Per-platform definitions of the same function pair up with
+                // the definition under the same condition
+                let condition_penalty = if source_func.condition == target_func.condition {
+                    0.0
+                } else {
+                    CONDITION_CHANGE_PENALTY
+                };
+                row.push(similarity.overall_similarity - condition_penalty);
             }
             matrix.push(row);
         }
@@ -74,15 +120,33 @@ impl FunctionMatcher {
         func1: &Function,
         func2: &Function,
     ) -> SimilarityScore {
-        // Signature similarity (40% weight)
-        let signature_similarity = func1.signature.similarity(&func2.signature);
-
         // Body similarity using AST structure (40% weight)
         let body_similarity = self.calculate_ast_similarity(&func1.body, &func2.body);
 
         // Context similarity (20% weight) - based on surrounding functions, calls, etc.
         let context_similarity = self.calculate_context_similarity(func1, func2);
 
+        // The positional name of an anonymous function shifts when a callback is
+        // added before it, so only its enclosing scope counts, and the body weighs more
+        let anonymous_scopes = (
+            anonymous_function_scope(&func1.signature.name),
+            anonymous_function_scope(&func2.signature.name),
+        );
+        if let (Some(scope1), Some(scope2)) = anonymous_scopes {
+            let scope_similarity = if scope1 == scope2 { 1.0 } else { 0.0 };
+            return SimilarityScore {
+                signature_similarity: scope_similarity,
+                body_similarity,
+                context_similarity,
+                overall_similarity: scope_similarity * 0.2
+                    + body_similarity * 0.6
+                    + context_similarity * 0.2,
+            };
+        }
+
+        // Signature similarity (40% weight)
+        let signature_similarity = func1.signature.similarity(&func2.signature);
+
         // Weighted overall similarity
         let overall_similarity =
             signature_similarity * 0.4 + body_similarity * 0.4 + context_similarity * 0.2;
@@ -141,7 +205,11 @@ impl FunctionMatcher {
         }
     }
 
-    fn hungarian_matching(&self, similarity_matrix: &[Vec<f64>]) -> Vec<(usize, usize)> {
+    /// Optimal source-target index pairs at or above the threshold
+    ///
+    /// Pairs below the threshold are never assigned, so a precomputed matrix
+    /// can be matched at several thresholds without scoring functions again.
+    pub fn hungarian_matching(&self, similarity_matrix: &[Vec<f64>]) -> Vec<(usize, usize)> {
         // Use the Hungarian algorithm from the hungarian crate
         use hungarian::minimize;
 
@@ -212,16 +280,33 @@ impl FunctionMatcher {
 
             // Create change record if functions are different
             let similarity = self.calculate_function_similarity(source_func, target_func);
-            if similarity.overall_similarity < 1.0 {
+            if source_func.condition != target_func.condition {
+                result.changes.push(Self::condition_change(
+                    source_func,
+                    target_func,
+                    similarity.overall_similarity,
+                ));
+            } else if similarity.overall_similarity < 1.0
+                // Structural similarity is blind to a changed constant or message
+                || detect_literal_changes(&source_func.body, &target_func.body).is_some()
+            {
                 let source_element = smart_diff_parser::CodeElement::from_function(source_func);
                 let target_element = smart_diff_parser::CodeElement::from_function(target_func);
 
-                let change = smart_diff_parser::Change::new(
-                    smart_diff_parser::ChangeType::Modify,
+                let description = if similarity.overall_similarity < 1.0 {
                     format!(
                         "Function '{}' modified (similarity: {:.2})",
                         source_func.signature.name, similarity.overall_similarity
-                    ),
+                    )
+                } else {
+                    format!(
+                        "Function '{}' modified (only literals changed)",
+                        source_func.signature.name
+                    )
+                };
+                let change = smart_diff_parser::Change::new(
+                    smart_diff_parser::ChangeType::Modify,
+                    description,
                 )
                 .with_elements(Some(source_element), Some(target_element))
                 .with_confidence(similarity.overall_similarity);
@@ -238,10 +323,16 @@ impl FunctionMatcher {
                 let source_element = smart_diff_parser::CodeElement::from_function(func);
                 let change = smart_diff_parser::Change::new(
                     smart_diff_parser::ChangeType::Delete,
-                    format!("Function '{}' deleted", func.signature.name),
+                    format!(
+                        "Function '{}' deleted{}",
+                        func.signature.name,
+                        Self::condition_suffix(func)
+                    ),
                 )
                 .with_elements(Some(source_element), None);
-                result.changes.push(change);
+                result
+                    .changes
+                    .push(Self::with_condition_metadata(change, func, None));
             }
         }
 
@@ -252,11 +343,230 @@ impl FunctionMatcher {
                 let target_element = smart_diff_parser::CodeElement::from_function(func);
                 let change = smart_diff_parser::Change::new(
                     smart_diff_parser::ChangeType::Add,
-                    format!("Function '{}' added", func.signature.name),
+                    format!(
+                        "Function '{}' added{}",
+                        func.signature.name,
+                        Self::condition_suffix(func)
+                    ),
                 )
                 .with_elements(None, Some(target_element));
-                result.changes.push(change);
+                result
+                    .changes
+                    .push(Self::with_condition_metadata(change, func, None));
             }
         }
     }
+
+    /// Change for a function that moved to another preprocessor branch
+    ///
+    /// A function moved from `#ifdef A` to `#else` unchanged is a move between
+    /// conditions rather than a deletion and an addition; one that also changed
+    /// is a modification naming both conditions.
+    fn condition_change(
+        source_func: &Function,
+        target_func: &Function,
+        similarity: f64,
+    ) -> smart_diff_parser::Change {
+        let describe = |condition: &Option<String>| match condition {
+            Some(condition) => format!("`{}`", condition),
+            None => "unconditional code".to_string(),
+        };
+        let (change_type, verb) = if similarity < 1.0 {
+            (smart_diff_parser::ChangeType::Modify, "modified and moved")
+        } else {
+            (smart_diff_parser::ChangeType::Move, "moved")
+        };
+        let change = smart_diff_parser::Change::new(
+            change_type,
+            format!(
+                "Function '{}' {} from {} to {}",
+                source_func.signature.name,
+                verb,
+                describe(&source_func.condition),
+                describe(&target_func.condition)
+            ),
+        )
+        .with_elements(
+            Some(smart_diff_parser::CodeElement::from_function(source_func)),
+            Some(smart_diff_parser::CodeElement::from_function(target_func)),
+        )
+        .with_confidence(similarity);
+        Self::with_condition_metadata(change, target_func, Some(source_func))
+    }
+
+    /// ` (under `defined(X)`)` for a function compiled only under a condition
+    fn condition_suffix(func: &Function) -> String {
+        func.condition
+            .as_ref()
+            .map(|condition| format!(" (under `{}`)", condition))
+            .unwrap_or_default()
+    }
+
+    /// Record the conditions of a change's functions in its metadata
+    fn with_condition_metadata(
+        mut change: smart_diff_parser::Change,
+        func: &Function,
+        previous: Option<&Function>,
+    ) -> smart_diff_parser::Change {
+        let metadata = &mut change.details.metadata;
+        if let Some(condition) = &func.condition {
+            metadata.insert("condition".to_string(), condition.clone());
+        }
+        if let Some(condition) = previous.and_then(|previous| previous.condition.as_ref()) {
+            metadata.insert("previous_condition".to_string(), condition.clone());
+        }
+        change
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smart_diff_parser::{
+        tree_sitter::TreeSitterParser, ChangeType, FunctionSignature, Language, NodeType, Parser,
+    };
+
+    fn parse_c_functions(code: &str) -> Vec<Function> {
+        let parser = TreeSitterParser::new().unwrap();
+        let result = parser.parse(code, Language::C).unwrap();
+        result
+            .ast
+            .find_by_type(&NodeType::Function)
+            .into_iter()
+            .filter(|node| node.metadata.attributes["kind"] == "function_definition")
+            .map(|node| {
+                let name = node.metadata.attributes.get("name").cloned().unwrap();
+                let signature = FunctionSignature::from_node(name, node);
+                Function::new(signature, node.clone(), "port.c".to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_function_moved_between_preprocessor_branches() {
+        let source = parse_c_functions(
+            "#ifdef _WIN32\nint open_port(int id) {\n    return connect_port(id);\n}\n#endif\n\
+             int close_port(int id) {\n    return release(id);\n}\n",
+        );
+        let target = parse_c_functions(
+            "#ifdef _WIN32\n#else\nint open_port(int id) {\n    return connect_port(id);\n}\n#endif\n\
+             int close_port(int id) {\n    return release(id);\n}\n",
+        );
+
+        let result = FunctionMatcher::new(0.7).match_functions(&source, &target);
+        assert_eq!(result.changes.len(), 1, "{:?}", result.changes);
+        let change = &result.changes[0];
+        assert_eq!(change.change_type, ChangeType::Move);
+        assert_eq!(
+            change.details.description,
+            "Function 'open_port' moved from `defined(_WIN32)` to `!defined(_WIN32)`"
+        );
+        assert_eq!(
+            change.details.metadata["previous_condition"],
+            "defined(_WIN32)"
+        );
+        assert_eq!(change.details.metadata["condition"], "!defined(_WIN32)");
+    }
+
+    #[test]
+    fn test_constant_only_change_is_reported() {
+        let source = parse_c_functions(
+            "int retry_limit(int load) {\n    return load > 80 ? 3 : 5;\n}\n\
+             int close_port(int id) {\n    return release(id);\n}\n",
+        );
+        let target = parse_c_functions(
+            "int retry_limit(int load) {\n    return load > 90 ? 3 : 5;\n}\n\
+             int close_port(int id) {\n    return release(id);\n}\n",
+        );
+
+        let result = FunctionMatcher::new(0.7).match_functions(&source, &target);
+        assert_eq!(result.changes.len(), 1, "{:?}", result.changes);
+        let change = &result.changes[0];
+        assert_eq!(change.change_type, ChangeType::Modify);
+        assert_eq!(
+            change.details.description,
+            "Function 'retry_limit' modified (only literals changed)"
+        );
+    }
+
+    #[test]
+    fn test_cancelled_match_gives_up_with_an_empty_result() {
+        let code = "int scale(int x) {\n    return x * 2;\n}\n";
+        let (source, target) = (parse_c_functions(code), parse_c_functions(code));
+        let cancellation = CancellationToken::new();
+        let mut matcher = FunctionMatcher::new(0.7);
+        matcher.set_cancellation(cancellation.clone());
+
+        assert_eq!(matcher.match_functions(&source, &target).mapping.len(), 1);
+
+        cancellation.cancel();
+        let result = matcher.match_functions(&source, &target);
+        assert!(result.mapping.is_empty());
+        assert!(result.changes.is_empty());
+    }
+
+    #[test]
+    fn test_match_hint_overrides_the_default_pairing() {
+        let source = parse_c_functions(
+            "int scale(int x) {\n    return x * 2;\n}\n\
+             int close_port(int id) {\n    return release(id);\n}\n",
+        );
+        let target = parse_c_functions(
+            "int scale(int x) {\n    return x * 2;\n}\n\
+             int twice(int x) {\n    return x + x;\n}\n\
+             int close_port(int id) {\n    return release(id);\n}\n",
+        );
+        let pairs = |result: &MatchResult| -> Vec<(String, String)> {
+            let mut pairs: Vec<_> = result
+                .mapping
+                .iter()
+                .map(|(source_hash, target_hash)| {
+                    let name = |functions: &[Function], hash: &str| {
+                        functions
+                            .iter()
+                            .find(|function| function.hash == hash)
+                            .unwrap()
+                            .signature
+                            .name
+                            .clone()
+                    };
+                    (name(&source, source_hash), name(&target, target_hash))
+                })
+                .collect();
+            pairs.sort();
+            pairs
+        };
+
+        let mut matcher = FunctionMatcher::new(0.7);
+        let result = matcher.match_functions(&source, &target);
+        assert!(pairs(&result).contains(&("scale".to_string(), "scale".to_string())));
+
+        matcher.set_hints(MatchHints::parse(&["scale=twice"]).unwrap());
+        let result = matcher.match_functions(&source, &target);
+        assert_eq!(
+            pairs(&result),
+            [
+                ("close_port".to_string(), "close_port".to_string()),
+                ("scale".to_string(), "twice".to_string())
+            ]
+        );
+        let added: Vec<_> = result
+            .changes
+            .iter()
+            .filter(|change| change.change_type == ChangeType::Add)
+            .map(|change| change.target.as_ref().unwrap().name.as_str())
+            .collect();
+        assert_eq!(added, ["scale"]);
+
+        // Forbidding the obvious pairing leaves the next best one
+        matcher.set_hints(MatchHints::parse(&["scale!=scale"]).unwrap());
+        let result = matcher.match_functions(&source, &target);
+        assert!(pairs(&result).contains(&("scale".to_string(), "twice".to_string())));
+
+        let conflict = MatchHints::parse(&["scale=twice", "scale=close_port"]).unwrap_err();
+        assert!(conflict
+            .to_string()
+            .contains("'scale' is forced onto both 'twice' and 'close_port'"));
+        assert!(MatchHints::parse(&["scale=twice", "scale!=twice"]).is_err());
+    }
 }
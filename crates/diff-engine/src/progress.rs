@@ -0,0 +1,60 @@
+//! Progress of long comparisons
+//!
+//! Comparing two trees of thousands of files takes a while. Code driving such
+//! a comparison reports how far it got as [`ComparisonProgress`] updates to a
+//! [`ProgressCallback`], so each front end can show them its own way: the CLI
+//! draws a progress bar, an editor integration could fill a status line.
+//!
+//! Updates arrive in phase order, and within a phase `completed` never
+//! decreases.
+
+use serde::{Deserialize, Serialize};
+
+/// Stage of a multi-file comparison, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparisonPhase {
+    /// Finding the files to compare
+    Discovering,
+    /// Parsing and comparing file pairs
+    Comparing,
+    /// Analyses spanning every file, once all pairs are compared
+    Analyzing,
+}
+
+impl ComparisonPhase {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ComparisonPhase::Discovering => "Discovering files",
+            ComparisonPhase::Comparing => "Comparing",
+            ComparisonPhase::Analyzing => "Analyzing changes",
+        }
+    }
+}
+
+/// One progress update
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComparisonProgress {
+    pub phase: ComparisonPhase,
+    /// Files done so far in this phase
+    pub completed: usize,
+    /// Files in this phase, 0 while unknown
+    pub total: usize,
+    /// File being worked on, if any
+    pub current_file: Option<String>,
+}
+
+impl ComparisonProgress {
+    /// Update for a phase whose files are not counted
+    pub fn phase(phase: ComparisonPhase) -> Self {
+        Self {
+            phase,
+            completed: 0,
+            total: 0,
+            current_file: None,
+        }
+    }
+}
+
+/// Receiver of progress updates
+pub type ProgressCallback<'a> = &'a (dyn Fn(&ComparisonProgress) + Send + Sync);
@@ -4,16 +4,30 @@
 //! common code refactoring patterns with confidence scoring, detailed analysis, and
 //! integration with change classification and similarity analysis.
 
+use crate::cancellation::CancellationToken;
 use crate::changes::ChangeClassifier;
+use crate::code_version::CodeVersion;
+use crate::identifier::normalize_identifier;
 use crate::similarity_scorer::{
     ComprehensiveSimilarityScore, SimilarityScorer, SimilarityScoringConfig,
 };
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use smart_diff_parser::{ASTNode, Change, ChangeType, Language, RefactoringType};
-use smart_diff_semantic::EnhancedFunctionSignature;
+use smart_diff_parser::{
+    ASTNode, Change, ChangeType, CodeElement, Language, NodeType, RefactoringType,
+};
+use smart_diff_semantic::{
+    EnhancedFunctionSignature, ExtractedTypeInfo, FunctionParameter, TypeChangeKind,
+    TypeDependencyGraphBuilder, TypeEquivalence, TypeInfo, TypeKind, TypeSignature,
+};
 use std::collections::{HashMap, HashSet};
 
+/// Lowest confidence threshold a caller may request
+///
+/// Below this, detectors mostly report coincidental similarities rather than
+/// refactorings, so requested thresholds are raised to it.
+pub const MIN_CONFIDENCE_FLOOR: f64 = 0.3;
+
 /// Configuration for refactoring pattern detection
 #[derive(Debug, Clone)]
 pub struct RefactoringDetectionConfig {
@@ -33,6 +47,16 @@ pub struct RefactoringDetectionConfig {
     pub enable_inline_class: bool,
     /// Enable change signature detection
     pub enable_change_signature: bool,
+    /// Enable introduce parameter object detection
+    pub enable_introduce_parameter_object: bool,
+    /// Enable guard clause (early return) detection
+    pub enable_guard_clause_detection: bool,
+    /// Enable pull up / push down method detection
+    pub enable_pull_up_push_down: bool,
+    /// Enable sync/async conversion detection
+    pub enable_sync_async_detection: bool,
+    /// Enable detection of constants replaced by an enum or a dedicated type
+    pub enable_replace_constants_with_type: bool,
     /// Maximum distance for related changes
     pub max_related_distance: usize,
     /// Enable complex pattern detection
@@ -50,18 +74,57 @@ impl Default for RefactoringDetectionConfig {
             enable_extract_class: true,
             enable_inline_class: true,
             enable_change_signature: true,
+            enable_introduce_parameter_object: true,
+            enable_guard_clause_detection: true,
+            enable_pull_up_push_down: true,
+            enable_sync_async_detection: true,
+            enable_replace_constants_with_type: true,
             max_related_distance: 50,
             enable_complex_patterns: true,
         }
     }
 }
 
+impl RefactoringDetectionConfig {
+    /// Default configuration with a caller-chosen confidence threshold
+    ///
+    /// The threshold is clamped to [`MIN_CONFIDENCE_FLOOR`]..=1.0; an invalid
+    /// value keeps the default threshold.
+    pub fn with_min_confidence(threshold: f64) -> Self {
+        let default = Self::default();
+        let min_confidence_threshold = if threshold.is_nan() {
+            default.min_confidence_threshold
+        } else {
+            threshold.clamp(MIN_CONFIDENCE_FLOOR, 1.0)
+        };
+
+        Self {
+            min_confidence_threshold,
+            ..default
+        }
+    }
+}
+
+/// A project-specific refactoring detector run alongside the built-in ones
+///
+/// Rules see the same groups of related changes as the built-in detectors and
+/// usually report [`RefactoringType::Custom`] patterns. Their patterns go through
+/// the same confidence threshold and ordering as built-in ones.
+pub trait RefactoringRule: Send + Sync {
+    /// Detect patterns in groups of related changes
+    fn detect(&self, groups: &[Vec<&Change>]) -> Vec<RefactoringPattern>;
+}
+
 /// Comprehensive refactoring pattern detector
 pub struct RefactoringDetector {
     config: RefactoringDetectionConfig,
     change_classifier: Option<ChangeClassifier>,
     similarity_scorer: Option<SimilarityScorer>,
     language: Language,
+    custom_rules: Vec<Box<dyn RefactoringRule>>,
+    /// Source and target code, for refactorings the changes alone do not show
+    versions: Option<(CodeVersion, CodeVersion)>,
+    cancellation: CancellationToken,
 }
 
 /// Detected refactoring pattern with detailed analysis
@@ -85,6 +148,49 @@ pub struct RefactoringPattern {
     pub complexity: RefactoringComplexity,
 }
 
+impl RefactoringPattern {
+    /// Create a pattern for a custom rule with neutral analysis and impact
+    pub fn custom(
+        name: impl Into<String>,
+        confidence: f64,
+        description: impl Into<String>,
+        affected_elements: Vec<String>,
+    ) -> Self {
+        Self {
+            pattern_type: RefactoringType::Custom(name.into()),
+            confidence,
+            description: description.into(),
+            analysis: RefactoringAnalysis {
+                characteristics: Vec::new(),
+                before_after: None,
+                impact: RefactoringImpact {
+                    impact_level: RefactoringImpactLevel::Low,
+                    affected_files: Vec::new(),
+                    affected_functions: affected_elements.clone(),
+                    is_breaking_change: false,
+                    api_compatibility: ApiCompatibilityImpact::None,
+                },
+                quality_metrics: RefactoringQualityMetrics {
+                    quality_improvement: 0.0,
+                    maintainability_impact: 0.0,
+                    readability_impact: 0.0,
+                    testability_impact: 0.0,
+                    performance_impact: 0.0,
+                },
+            },
+            evidence: Vec::new(),
+            related_changes: Vec::new(),
+            complexity: RefactoringComplexity {
+                complexity_level: RefactoringComplexityLevel::Simple,
+                elements_involved: affected_elements.len(),
+                files_affected: 0,
+                estimated_effort: RefactoringEffort::Low,
+            },
+            affected_elements,
+        }
+    }
+}
+
 /// Detailed analysis of a refactoring pattern
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RefactoringAnalysis {
@@ -128,6 +234,8 @@ pub enum RefactoringCharacteristicType {
     DependencyChange,
     /// Structure change pattern
     StructureChange,
+    /// Nested conditionals replaced by early returns
+    GuardClauseExtraction,
 }
 
 /// Evidence supporting a refactoring pattern
@@ -300,6 +408,9 @@ impl RefactoringDetector {
                 SimilarityScoringConfig::default(),
             )),
             language,
+            custom_rules: Vec::new(),
+            versions: None,
+            cancellation: CancellationToken::default(),
         }
     }
 
@@ -313,6 +424,9 @@ impl RefactoringDetector {
                 SimilarityScoringConfig::default(),
             )),
             language,
+            custom_rules: Vec::new(),
+            versions: None,
+            cancellation: CancellationToken::default(),
         }
     }
 
@@ -323,53 +437,94 @@ impl RefactoringDetector {
             change_classifier: None,
             similarity_scorer: None,
             language,
+            custom_rules: Vec::new(),
+            versions: None,
+            cancellation: CancellationToken::default(),
         }
     }
 
+    /// Register extra rules to run alongside the built-in detectors
+    pub fn with_rules(mut self, rules: Vec<Box<dyn RefactoringRule>>) -> Self {
+        self.custom_rules.extend(rules);
+        self
+    }
+
+    /// Look at the code of both versions too, not only at the changes
+    ///
+    /// Refactorings that only show in signatures, function bodies or types,
+    /// such as an introduced parameter object, are detected only then.
+    pub fn with_versions(mut self, source: CodeVersion, target: CodeVersion) -> Self {
+        self.versions = Some((source, target));
+        self
+    }
+
+    /// Skip the detectors not yet run once `cancellation` is cancelled,
+    /// keeping the patterns found so far
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Register an extra rule to run alongside the built-in detectors
+    pub fn add_rule(&mut self, rule: Box<dyn RefactoringRule>) {
+        self.custom_rules.push(rule);
+    }
+
     /// Detect refactoring patterns from a set of changes
     pub fn detect_patterns(&self, changes: &[Change]) -> Vec<RefactoringPattern> {
         let mut patterns = Vec::new();
 
-        if changes.is_empty() {
+        // Constants replaced by a type change no function, so the code of the
+        // versions is looked at even without changes
+        if changes.is_empty() && self.versions.is_none() {
             return patterns;
         }
 
         // Group related changes for pattern analysis
         let change_groups = self.group_related_changes(changes);
 
-        // Detect different types of refactoring patterns
-        if self.config.enable_extract_method {
+        // Detect different types of refactoring patterns, until cancelled
+        let running = || !self.cancellation.is_cancelled();
+        if self.config.enable_extract_method && running() {
             patterns.extend(self.detect_extract_method_patterns(&change_groups));
         }
 
-        if self.config.enable_inline_method {
+        if self.config.enable_inline_method && running() {
             patterns.extend(self.detect_inline_method_patterns(&change_groups));
         }
 
-        if self.config.enable_rename_detection {
+        if self.config.enable_rename_detection && running() {
             patterns.extend(self.detect_rename_patterns(&change_groups));
         }
 
-        if self.config.enable_move_detection {
+        if self.config.enable_move_detection && running() {
             patterns.extend(self.detect_move_patterns(&change_groups));
         }
 
-        if self.config.enable_extract_class {
+        if self.config.enable_extract_class && running() {
             patterns.extend(self.detect_extract_class_patterns(&change_groups));
         }
 
-        if self.config.enable_inline_class {
+        if self.config.enable_inline_class && running() {
             patterns.extend(self.detect_inline_class_patterns(&change_groups));
         }
 
-        if self.config.enable_change_signature {
+        if self.config.enable_change_signature && running() {
             patterns.extend(self.detect_change_signature_patterns(&change_groups));
         }
 
-        if self.config.enable_complex_patterns {
+        if self.config.enable_complex_patterns && running() {
             patterns.extend(self.detect_complex_patterns(&change_groups));
         }
 
+        if let Some((source, target)) = self.versions.as_ref().filter(|_| running()) {
+            patterns.extend(self.detect_version_patterns(changes, source, target));
+        }
+
+        for rule in self.custom_rules.iter().take_while(|_| running()) {
+            patterns.extend(rule.detect(&change_groups));
+        }
+
         // Filter patterns by confidence threshold
         patterns.retain(|p| p.confidence >= self.config.min_confidence_threshold);
 
@@ -394,7 +549,7 @@ impl RefactoringDetector {
     ) -> Result<Vec<RefactoringPattern>> {
         let mut patterns = Vec::new();
 
-        if changes.is_empty() {
+        if changes.is_empty() && self.versions.is_none() {
             return Ok(patterns);
         }
 
@@ -464,6 +619,20 @@ impl RefactoringDetector {
             )?);
         }
 
+        if let Some((source, target)) = &self.versions {
+            patterns.extend(self.detect_version_patterns(changes, source, target));
+        }
+
+        if !self.custom_rules.is_empty() {
+            let group_refs: Vec<Vec<&Change>> = change_groups
+                .iter()
+                .map(|group| group.iter().collect())
+                .collect();
+            for rule in &self.custom_rules {
+                patterns.extend(rule.detect(&group_refs));
+            }
+        }
+
         // Filter and sort patterns
         patterns.retain(|p| p.confidence >= self.config.min_confidence_threshold);
         patterns.sort_by(|a, b| {
@@ -475,6 +644,57 @@ impl RefactoringDetector {
         Ok(patterns)
     }
 
+    /// Detect the refactorings that show in the code of both versions
+    fn detect_version_patterns(
+        &self,
+        changes: &[Change],
+        source: &CodeVersion,
+        target: &CodeVersion,
+    ) -> Vec<RefactoringPattern> {
+        let mut patterns = Vec::new();
+
+        let mut seen = HashSet::new();
+        for change in changes {
+            let (Some(source_element), Some(target_element)) = (&change.source, &change.target)
+            else {
+                continue;
+            };
+            if !seen.insert((&source_element.name, &target_element.name)) {
+                continue;
+            }
+            if let (Some(source_function), Some(target_function)) = (
+                source.function(&source_element.name),
+                target.function(&target_element.name),
+            ) {
+                patterns.extend(self.detect_guard_clause_extraction(
+                    &target_element.name,
+                    source_function,
+                    target_function,
+                ));
+            }
+        }
+
+        patterns.extend(self.detect_introduce_parameter_object(
+            &source.signatures,
+            &target.signatures,
+            &source.type_infos(),
+            &target.type_infos(),
+        ));
+        patterns.extend(self.detect_sync_async_conversion(&source.signatures, &target.signatures));
+
+        let (source_types, target_types) = (source.type_graph(), target.type_graph());
+        patterns.extend(self.detect_return_type_changes(
+            &source.signatures,
+            &target.signatures,
+            &source_types,
+            &target_types,
+        ));
+        patterns.extend(self.detect_pull_up_push_down(changes, &source_types, &target_types));
+        patterns.extend(self.detect_constant_replacement(&source_types, &target_types));
+
+        patterns
+    }
+
     /// Group related changes for pattern analysis
     fn group_related_changes<'a>(&self, changes: &'a [Change]) -> Vec<Vec<&'a Change>> {
         let mut groups = Vec::new();
@@ -1699,167 +1919,1412 @@ impl RefactoringDetector {
         confidence.min(1.0)
     }
 
-    // Analysis creation methods
-
-    /// Create extract method analysis
-    fn create_extract_method_analysis(
+    /// Detect Introduce Parameter Object refactorings
+    ///
+    /// A function whose parameter list shrank and that now takes a newly introduced type
+    /// whose fields cover the removed parameters is reported as `IntroduceParameterObject`.
+    /// Confidence rises when the new type's name relates to the function (e.g. `render`
+    /// taking `RenderOptions`).
+    pub fn detect_introduce_parameter_object(
         &self,
-        modified: &Change,
-        added: &Change,
-    ) -> RefactoringAnalysis {
-        let mut characteristics = Vec::new();
+        source_signatures: &HashMap<String, EnhancedFunctionSignature>,
+        target_signatures: &HashMap<String, EnhancedFunctionSignature>,
+        source_types: &[TypeInfo],
+        target_types: &[TypeInfo],
+    ) -> Vec<RefactoringPattern> {
+        let mut patterns = Vec::new();
 
-        characteristics.push(RefactoringCharacteristic {
-            characteristic_type: RefactoringCharacteristicType::CodeExtraction,
-            value: "Method extracted from existing function".to_string(),
-            confidence: 0.8,
-        });
+        if !self.config.enable_introduce_parameter_object {
+            return patterns;
+        }
 
-        if let Some(target) = &added.target {
-            characteristics.push(RefactoringCharacteristic {
-                characteristic_type: RefactoringCharacteristicType::NameChange,
-                value: format!("New method name: {}", target.name),
-                confidence: 1.0,
-            });
+        let existing_types: HashSet<&str> = source_types.iter().map(|t| t.name.as_str()).collect();
+        let introduced_types: HashMap<&str, &TypeInfo> = target_types
+            .iter()
+            .filter(|t| !existing_types.contains(t.name.as_str()))
+            .map(|t| (t.name.as_str(), t))
+            .collect();
+
+        if introduced_types.is_empty() {
+            return patterns;
         }
 
-        RefactoringAnalysis {
-            characteristics,
-            before_after: None,
-            impact: RefactoringImpact {
-                impact_level: RefactoringImpactLevel::Low,
-                affected_files: vec![modified
-                    .source
-                    .as_ref()
-                    .map(|s| s.file_path.clone())
-                    .unwrap_or_default()],
-                affected_functions: vec![
-                    modified
-                        .source
-                        .as_ref()
-                        .map(|s| s.name.clone())
-                        .unwrap_or_default(),
-                    added
-                        .target
-                        .as_ref()
-                        .map(|t| t.name.clone())
-                        .unwrap_or_default(),
-                ],
-                is_breaking_change: false,
-                api_compatibility: ApiCompatibilityImpact::BackwardCompatible,
-            },
-            quality_metrics: RefactoringQualityMetrics {
-                quality_improvement: 0.7,
-                maintainability_impact: 0.8,
-                readability_impact: 0.6,
-                testability_impact: 0.7,
-                performance_impact: 0.0,
-            },
+        let mut function_names: Vec<&String> = source_signatures.keys().collect();
+        function_names.sort();
+
+        for function_name in function_names {
+            let source_sig = &source_signatures[function_name];
+            let Some(target_sig) = target_signatures.get(function_name) else {
+                continue;
+            };
+
+            if target_sig.parameters.len() >= source_sig.parameters.len() {
+                continue;
+            }
+
+            let removed_params: Vec<&FunctionParameter> = source_sig
+                .parameters
+                .iter()
+                .filter(|p| !target_sig.parameters.iter().any(|t| t.name == p.name))
+                .collect();
+
+            if removed_params.len() < 2 {
+                continue;
+            }
+
+            for new_param in &target_sig.parameters {
+                if source_sig
+                    .parameters
+                    .iter()
+                    .any(|p| p.name == new_param.name)
+                {
+                    continue;
+                }
+
+                let type_name = Self::parameter_type_name(&new_param.param_type);
+                let Some(type_info) = introduced_types.get(type_name) else {
+                    continue;
+                };
+
+                let confidence = self.calculate_parameter_object_confidence(
+                    function_name,
+                    &removed_params,
+                    type_info,
+                );
+
+                if confidence >= self.config.min_confidence_threshold {
+                    patterns.push(self.create_parameter_object_pattern(
+                        source_sig,
+                        target_sig,
+                        &removed_params,
+                        type_info,
+                        confidence,
+                    ));
+                }
+            }
         }
+
+        patterns
     }
 
-    /// Create inline method analysis
-    fn create_inline_method_analysis(
+    /// Detect nested conditionals flattened into guard clauses
+    ///
+    /// `source` and `target` are the two versions of the same function. The change is
+    /// reported as `IntroduceGuardClauses` when the target has more early returns and a
+    /// lower nesting depth while its identifiers and literals stay largely the same, i.e.
+    /// the logic was restructured rather than rewritten. Confidence rises with the
+    /// nesting depth removed.
+    pub fn detect_guard_clause_extraction(
         &self,
-        deleted: &Change,
-        modified: &Change,
-    ) -> RefactoringAnalysis {
-        let characteristics = vec![RefactoringCharacteristic {
-            characteristic_type: RefactoringCharacteristicType::CodeInlining,
-            value: "Method inlined into calling function".to_string(),
-            confidence: 0.8,
-        }];
+        function_name: &str,
+        source: &ASTNode,
+        target: &ASTNode,
+    ) -> Option<RefactoringPattern> {
+        if !self.config.enable_guard_clause_detection {
+            return None;
+        }
 
-        RefactoringAnalysis {
-            characteristics,
-            before_after: None,
-            impact: RefactoringImpact {
-                impact_level: RefactoringImpactLevel::Low,
-                affected_files: vec![deleted
-                    .source
-                    .as_ref()
-                    .map(|s| s.file_path.clone())
-                    .unwrap_or_default()],
-                affected_functions: vec![
-                    deleted
-                        .source
-                        .as_ref()
-                        .map(|s| s.name.clone())
-                        .unwrap_or_default(),
-                    modified
-                        .target
-                        .as_ref()
-                        .map(|t| t.name.clone())
-                        .unwrap_or_default(),
-                ],
-                is_breaking_change: false,
-                api_compatibility: ApiCompatibilityImpact::BackwardCompatible,
-            },
-            quality_metrics: RefactoringQualityMetrics {
-                quality_improvement: 0.5,
-                maintainability_impact: 0.4,
-                readability_impact: 0.3,
-                testability_impact: 0.2,
-                performance_impact: 0.1,
-            },
+        let source_depth = Self::max_nesting_depth(source, 0);
+        let target_depth = Self::max_nesting_depth(target, 0);
+        let source_returns = Self::count_early_returns(source, false);
+        let target_returns = Self::count_early_returns(target, false);
+
+        if target_depth >= source_depth || target_returns <= source_returns {
+            return None;
         }
-    }
 
-    /// Create rename analysis
-    fn create_rename_analysis(&self, change: &Change) -> RefactoringAnalysis {
-        let mut characteristics = Vec::new();
+        // Guard clauses invert conditions and duplicate fallbacks, so compare the
+        // distinct identifiers and literals rather than the statements themselves
+        let source_tokens = Self::body_tokens(source);
+        let target_tokens = Self::body_tokens(target);
+        let token_similarity = if source_tokens.is_empty() && target_tokens.is_empty() {
+            1.0
+        } else {
+            source_tokens.intersection(&target_tokens).count() as f64
+                / source_tokens.union(&target_tokens).count() as f64
+        };
 
-        if let (Some(source), Some(target)) = (&change.source, &change.target) {
-            characteristics.push(RefactoringCharacteristic {
-                characteristic_type: RefactoringCharacteristicType::NameChange,
-                value: format!("Renamed from '{}' to '{}'", source.name, target.name),
-                confidence: 0.9,
-            });
+        if token_similarity < 0.8 {
+            return None;
+        }
 
-            let name_similarity = self.calculate_name_similarity(&source.name, &target.name);
-            characteristics.push(RefactoringCharacteristic {
-                characteristic_type: RefactoringCharacteristicType::NameChange,
-                value: format!("Name similarity: {:.3}", name_similarity),
-                confidence: 0.8,
-            });
+        let depth_reduction = (source_depth - target_depth) as f64 / source_depth as f64;
+        let confidence = (token_similarity * 0.6 + 0.25 + depth_reduction * 0.15).min(1.0);
+        if confidence < self.config.min_confidence_threshold {
+            return None;
         }
 
-        RefactoringAnalysis {
-            characteristics,
-            before_after: None,
-            impact: RefactoringImpact {
-                impact_level: RefactoringImpactLevel::Medium,
-                affected_files: vec![change
-                    .source
-                    .as_ref()
-                    .map(|s| s.file_path.clone())
-                    .unwrap_or_default()],
-                affected_functions: vec![
-                    change
-                        .source
-                        .as_ref()
-                        .map(|s| s.name.clone())
-                        .unwrap_or_default(),
-                    change
-                        .target
-                        .as_ref()
-                        .map(|t| t.name.clone())
-                        .unwrap_or_default(),
+        let mut data = HashMap::new();
+        data.insert("nesting_depth_before".to_string(), source_depth.to_string());
+        data.insert("nesting_depth_after".to_string(), target_depth.to_string());
+        data.insert(
+            "early_returns_before".to_string(),
+            source_returns.to_string(),
+        );
+        data.insert(
+            "early_returns_after".to_string(),
+            target_returns.to_string(),
+        );
+        data.insert(
+            "token_similarity".to_string(),
+            format!("{:.2}", token_similarity),
+        );
+
+        Some(RefactoringPattern {
+            pattern_type: RefactoringType::IntroduceGuardClauses,
+            confidence,
+            description: format!(
+                "Replaced nested conditionals in '{}' with guard clauses (nesting depth {} -> {})",
+                function_name, source_depth, target_depth
+            ),
+            affected_elements: vec![function_name.to_string()],
+            analysis: RefactoringAnalysis {
+                characteristics: vec![
+                    RefactoringCharacteristic {
+                        characteristic_type: RefactoringCharacteristicType::GuardClauseExtraction,
+                        value: format!(
+                            "Early returns increased from {} to {}",
+                            source_returns, target_returns
+                        ),
+                        confidence,
+                    },
+                    RefactoringCharacteristic {
+                        characteristic_type: RefactoringCharacteristicType::ComplexityChange,
+                        value: format!(
+                            "Nesting depth reduced from {} to {}",
+                            source_depth, target_depth
+                        ),
+                        confidence,
+                    },
                 ],
-                is_breaking_change: true, // Renames are potentially breaking
-                api_compatibility: ApiCompatibilityImpact::PotentiallyBreaking,
+                before_after: None,
+                impact: RefactoringImpact {
+                    impact_level: RefactoringImpactLevel::Low,
+                    affected_files: Vec::new(),
+                    affected_functions: vec![function_name.to_string()],
+                    is_breaking_change: false,
+                    api_compatibility: ApiCompatibilityImpact::None,
+                },
+                quality_metrics: RefactoringQualityMetrics {
+                    quality_improvement: 0.5 + depth_reduction * 0.3,
+                    maintainability_impact: 0.4 + depth_reduction * 0.4,
+                    readability_impact: 0.5 + depth_reduction * 0.4,
+                    testability_impact: 0.2,
+                    performance_impact: 0.0,
+                },
             },
-            quality_metrics: RefactoringQualityMetrics {
-                quality_improvement: 0.6,
-                maintainability_impact: 0.7,
-                readability_impact: 0.8,
-                testability_impact: 0.5,
-                performance_impact: 0.0,
+            evidence: vec![RefactoringEvidence {
+                evidence_type: RefactoringEvidenceType::StructurePattern,
+                description: format!(
+                    "Same identifiers and literals with flatter control flow ({:.0}% token overlap)",
+                    token_similarity * 100.0
+                ),
+                strength: confidence,
+                data,
+            }],
+            related_changes: vec![format!("{:?}", ChangeType::Modify)],
+            complexity: RefactoringComplexity {
+                complexity_level: RefactoringComplexityLevel::Simple,
+                elements_involved: 1,
+                files_affected: 1,
+                estimated_effort: RefactoringEffort::Low,
             },
-        }
+        })
     }
 
-    /// Create move analysis
+    /// Detect functions converted between synchronous and asynchronous
+    ///
+    /// A function present in both versions is reported as `SyncAsyncConversion` when it
+    /// gained or lost the `async` modifier, or when its return type became or stopped
+    /// being a future (`Future`, `Promise`, `Task`, ...). Callers must start or stop
+    /// awaiting it, so the conversion is always a breaking API change. Confidence is
+    /// higher when the modifier changed than when only the return type did.
+    pub fn detect_sync_async_conversion(
+        &self,
+        source_signatures: &HashMap<String, EnhancedFunctionSignature>,
+        target_signatures: &HashMap<String, EnhancedFunctionSignature>,
+    ) -> Vec<RefactoringPattern> {
+        let mut patterns = Vec::new();
+
+        if !self.config.enable_sync_async_detection {
+            return patterns;
+        }
+
+        let mut function_names: Vec<&String> = source_signatures.keys().collect();
+        function_names.sort();
+
+        for function_name in function_names {
+            let source_sig = &source_signatures[function_name];
+            let Some(target_sig) = target_signatures.get(function_name) else {
+                continue;
+            };
+
+            let modifier_before = Self::has_async_modifier(source_sig);
+            let modifier_after = Self::has_async_modifier(target_sig);
+            let future_before = Self::returns_future(&source_sig.return_type);
+            let future_after = Self::returns_future(&target_sig.return_type);
+
+            let was_async = modifier_before || future_before;
+            let is_async = modifier_after || future_after;
+            if was_async == is_async {
+                continue;
+            }
+
+            let confidence = if modifier_before != modifier_after {
+                0.95
+            } else {
+                0.8
+            };
+            if confidence < self.config.min_confidence_threshold {
+                continue;
+            }
+
+            patterns.push(self.create_sync_async_pattern(
+                source_sig,
+                target_sig,
+                is_async,
+                modifier_before != modifier_after,
+                confidence,
+            ));
+        }
+
+        patterns
+    }
+
+    fn create_sync_async_pattern(
+        &self,
+        source_sig: &EnhancedFunctionSignature,
+        target_sig: &EnhancedFunctionSignature,
+        now_async: bool,
+        modifier_changed: bool,
+        confidence: f64,
+    ) -> RefactoringPattern {
+        let (before, after) = if now_async {
+            ("synchronous", "asynchronous")
+        } else {
+            ("asynchronous", "synchronous")
+        };
+
+        let mut data = HashMap::new();
+        data.insert("async_before".to_string(), (!now_async).to_string());
+        data.insert("async_after".to_string(), now_async.to_string());
+        data.insert(
+            "return_type_before".to_string(),
+            source_sig.return_type.to_string(),
+        );
+        data.insert(
+            "return_type_after".to_string(),
+            target_sig.return_type.to_string(),
+        );
+
+        let evidence_description = if modifier_changed {
+            format!(
+                "'async' modifier {}",
+                if now_async { "added" } else { "removed" }
+            )
+        } else {
+            format!(
+                "Return type changed from '{}' to '{}'",
+                source_sig.return_type, target_sig.return_type
+            )
+        };
+
+        RefactoringPattern {
+            pattern_type: RefactoringType::SyncAsyncConversion,
+            confidence,
+            description: format!(
+                "Converted '{}' from {} to {}",
+                target_sig.name, before, after
+            ),
+            affected_elements: vec![target_sig.name.clone()],
+            analysis: RefactoringAnalysis {
+                characteristics: vec![RefactoringCharacteristic {
+                    characteristic_type: RefactoringCharacteristicType::SignatureChange,
+                    value: format!("Function became {}", after),
+                    confidence,
+                }],
+                before_after: None,
+                impact: RefactoringImpact {
+                    impact_level: RefactoringImpactLevel::Medium,
+                    affected_files: vec![target_sig.file_path.clone()],
+                    affected_functions: vec![target_sig.name.clone()],
+                    is_breaking_change: true,
+                    api_compatibility: ApiCompatibilityImpact::Breaking,
+                },
+                quality_metrics: RefactoringQualityMetrics {
+                    quality_improvement: 0.0,
+                    maintainability_impact: 0.0,
+                    readability_impact: 0.0,
+                    testability_impact: -0.1,
+                    performance_impact: if now_async { 0.3 } else { 0.0 },
+                },
+            },
+            evidence: vec![RefactoringEvidence {
+                evidence_type: RefactoringEvidenceType::StructurePattern,
+                description: evidence_description,
+                strength: confidence,
+                data,
+            }],
+            related_changes: vec![format!("{:?}", ChangeType::Modify)],
+            complexity: RefactoringComplexity {
+                complexity_level: RefactoringComplexityLevel::Simple,
+                elements_involved: 1,
+                files_affected: 1,
+                estimated_effort: RefactoringEffort::Low,
+            },
+        }
+    }
+
+    fn has_async_modifier(signature: &EnhancedFunctionSignature) -> bool {
+        signature
+            .modifiers
+            .iter()
+            .any(|modifier| modifier.eq_ignore_ascii_case("async"))
+    }
+
+    /// Whether a return type is a future, promise or other awaitable
+    fn returns_future(return_type: &TypeSignature) -> bool {
+        const FUTURE_TYPES: &[&str] = &[
+            "Future",
+            "CompletableFuture",
+            "CompletionStage",
+            "ListenableFuture",
+            "Promise",
+            "PromiseLike",
+            "Task",
+            "ValueTask",
+            "Awaitable",
+            "Coroutine",
+        ];
+
+        // `impl Future<Output = T>`, `java.util.concurrent.Future` and `std::future::Future`
+        let base = return_type.base_type.trim();
+        let base = base.strip_prefix("impl ").unwrap_or(base);
+        let base = base.split('<').next().unwrap_or(base).trim();
+        let base = base.rsplit(['.', ':']).next().unwrap_or(base);
+        FUTURE_TYPES.contains(&base)
+    }
+
+    /// Classify return type changes as widening, narrowing or unrelated
+    ///
+    /// A function present in both versions whose return type changed is reported as a
+    /// `ChangeSignature` pattern whose compatibility impact follows the subtyping
+    /// relationship between the two types, taken from either version's type graph:
+    /// widening to a supertype keeps callers that only use the supertype's members
+    /// working and is potentially breaking; narrowing to a subtype breaks overrides and
+    /// implementations still returning the old type; an unrelated type breaks callers
+    /// outright.
+    pub fn detect_return_type_changes(
+        &self,
+        source_signatures: &HashMap<String, EnhancedFunctionSignature>,
+        target_signatures: &HashMap<String, EnhancedFunctionSignature>,
+        source_types: &TypeDependencyGraphBuilder,
+        target_types: &TypeDependencyGraphBuilder,
+    ) -> Vec<RefactoringPattern> {
+        let mut patterns = Vec::new();
+
+        if !self.config.enable_change_signature {
+            return patterns;
+        }
+
+        let mut supertypes = source_types.supertypes();
+        for (type_name, parents) in target_types.supertypes() {
+            supertypes.entry(type_name).or_default().extend(parents);
+        }
+
+        let mut function_names: Vec<&String> = source_signatures.keys().collect();
+        function_names.sort();
+
+        for function_name in function_names {
+            let source_sig = &source_signatures[function_name];
+            let Some(target_sig) = target_signatures.get(function_name) else {
+                continue;
+            };
+
+            let change = TypeEquivalence::classify_change(
+                &source_sig.return_type,
+                &target_sig.return_type,
+                &supertypes,
+            );
+            let (verb, api_compatibility, impact_level) = match change {
+                TypeChangeKind::Equivalent => continue,
+                TypeChangeKind::Widening => (
+                    "Widened",
+                    ApiCompatibilityImpact::PotentiallyBreaking,
+                    RefactoringImpactLevel::Low,
+                ),
+                TypeChangeKind::Narrowing => (
+                    "Narrowed",
+                    ApiCompatibilityImpact::Breaking,
+                    RefactoringImpactLevel::Medium,
+                ),
+                TypeChangeKind::Unrelated => (
+                    "Changed",
+                    ApiCompatibilityImpact::Breaking,
+                    RefactoringImpactLevel::High,
+                ),
+            };
+
+            // Only an unrelated type is certain to break callers
+            let confidence = if change == TypeChangeKind::Unrelated {
+                0.95
+            } else {
+                0.85
+            };
+            if confidence < self.config.min_confidence_threshold {
+                continue;
+            }
+
+            let kind = format!("{:?}", change).to_lowercase();
+            let mut data = HashMap::new();
+            data.insert("return_type_change".to_string(), kind.clone());
+            data.insert(
+                "return_type_before".to_string(),
+                source_sig.return_type.to_string(),
+            );
+            data.insert(
+                "return_type_after".to_string(),
+                target_sig.return_type.to_string(),
+            );
+
+            let is_breaking_change = api_compatibility == ApiCompatibilityImpact::Breaking;
+            patterns.push(RefactoringPattern {
+                pattern_type: RefactoringType::ChangeSignature,
+                confidence,
+                description: format!(
+                    "{} return type of '{}' from '{}' to '{}'",
+                    verb, target_sig.name, source_sig.return_type, target_sig.return_type
+                ),
+                affected_elements: vec![target_sig.name.clone()],
+                analysis: RefactoringAnalysis {
+                    characteristics: vec![RefactoringCharacteristic {
+                        characteristic_type: RefactoringCharacteristicType::SignatureChange,
+                        value: format!("Return type change is {}", kind),
+                        confidence,
+                    }],
+                    before_after: None,
+                    impact: RefactoringImpact {
+                        impact_level,
+                        affected_files: vec![target_sig.file_path.clone()],
+                        affected_functions: vec![target_sig.name.clone()],
+                        is_breaking_change,
+                        api_compatibility,
+                    },
+                    quality_metrics: RefactoringQualityMetrics {
+                        quality_improvement: 0.0,
+                        maintainability_impact: 0.0,
+                        readability_impact: 0.0,
+                        testability_impact: 0.0,
+                        performance_impact: 0.0,
+                    },
+                },
+                evidence: vec![RefactoringEvidence {
+                    evidence_type: RefactoringEvidenceType::DependencyEvidence,
+                    description: format!(
+                        "'{}' and '{}' compared through the type hierarchy",
+                        source_sig.return_type, target_sig.return_type
+                    ),
+                    strength: confidence,
+                    data,
+                }],
+                related_changes: vec![format!("{:?}", ChangeType::Modify)],
+                complexity: RefactoringComplexity {
+                    complexity_level: RefactoringComplexityLevel::Simple,
+                    elements_involved: 1,
+                    files_affected: 1,
+                    estimated_effort: RefactoringEffort::Low,
+                },
+            });
+        }
+
+        patterns
+    }
+
+    /// Detect constants replaced by an enum or a dedicated type
+    ///
+    /// Constants are static final, or `const`, fields of a primitive or string type.
+    /// When constants of the source are gone from the target and a type new to the
+    /// target declares members, as enum values or as constants of its own type, the
+    /// constants are reported as replaced by the type with `ReplaceConstantsWithType`.
+    /// Members are paired with removed constants by name, ignoring case, underscores
+    /// and a prefix naming the type (`STATUS_ACTIVE` for `Status.ACTIVE`); without any
+    /// such pair, the removed constants must be exactly as many as the members.
+    /// Confidence rises with the share of members named after a removed constant and
+    /// when fields or method signatures of the target refer to the new type.
+    pub fn detect_constant_replacement(
+        &self,
+        source_types: &TypeDependencyGraphBuilder,
+        target_types: &TypeDependencyGraphBuilder,
+    ) -> Vec<RefactoringPattern> {
+        let mut patterns = Vec::new();
+
+        if !self.config.enable_replace_constants_with_type {
+            return patterns;
+        }
+
+        let source_map = source_types.get_type_info_map();
+        let target_map = target_types.get_type_info_map();
+        let constants = |types: &HashMap<String, ExtractedTypeInfo>| -> Vec<(String, String)> {
+            let mut constants: Vec<(String, String)> = types
+                .values()
+                .filter(|extracted| extracted.type_info.kind != TypeKind::Enum)
+                .flat_map(|extracted| {
+                    extracted
+                        .type_info
+                        .fields
+                        .iter()
+                        .filter(|field| {
+                            field.is_static
+                                && field.is_final
+                                && Self::is_primitive_constant_type(&field.type_name)
+                        })
+                        .map(|field| (extracted.type_info.name.clone(), field.name.clone()))
+                })
+                .collect();
+            constants.sort();
+            constants
+        };
+        let target_constants = constants(target_map);
+        let mut removed: Vec<(String, String)> = constants(source_map)
+            .into_iter()
+            .filter(|constant| !target_constants.contains(constant))
+            .collect();
+        if removed.is_empty() {
+            return patterns;
+        }
+
+        let mut new_types: Vec<&ExtractedTypeInfo> = target_map
+            .values()
+            .filter(|extracted| !source_map.contains_key(&extracted.type_info.name))
+            .collect();
+        new_types.sort_by(|a, b| a.type_info.name.cmp(&b.type_info.name));
+
+        for new_type in new_types {
+            let type_info = &new_type.type_info;
+            let is_enum = type_info.kind == TypeKind::Enum;
+            let members: Vec<&str> = type_info
+                .fields
+                .iter()
+                .filter(|field| {
+                    is_enum
+                        || (field.is_static && field.is_final && field.type_name == type_info.name)
+                })
+                .map(|field| field.name.as_str())
+                .collect();
+            if members.len() < 2 {
+                continue;
+            }
+
+            let type_prefix = Self::normalize_constant_name(&type_info.name);
+            let mut replaced: Vec<(String, String)> = Vec::new();
+            for member in &members {
+                let member = Self::normalize_constant_name(member);
+                let position = removed.iter().position(|(_, constant)| {
+                    let constant = Self::normalize_constant_name(constant);
+                    constant == member
+                        || constant.strip_prefix(type_prefix.as_str()) == Some(member.as_str())
+                });
+                if let Some(position) = position {
+                    replaced.push(removed.remove(position));
+                }
+            }
+            let named_matches = replaced.len();
+            if named_matches == 0 {
+                if removed.len() != members.len() {
+                    continue;
+                }
+                replaced = std::mem::take(&mut removed);
+            } else if named_matches < 2 {
+                removed.append(&mut replaced);
+                removed.sort();
+                continue;
+            }
+
+            let mentions = |type_name: &str| {
+                type_name
+                    .split(|c: char| !c.is_alphanumeric() && c != '_')
+                    .any(|word| word == type_info.name)
+            };
+            let referenced = target_map.values().any(|extracted| {
+                extracted.type_info.name != type_info.name
+                    && (extracted
+                        .type_info
+                        .fields
+                        .iter()
+                        .any(|field| mentions(&field.type_name))
+                        || extracted.type_info.methods.iter().any(|method| {
+                            mentions(&method.return_type)
+                                || method
+                                    .parameters
+                                    .iter()
+                                    .any(|parameter| mentions(&parameter.type_name))
+                        }))
+            });
+
+            let name_ratio = named_matches as f64 / members.len() as f64;
+            let confidence =
+                (0.55 + 0.35 * name_ratio + if referenced { 0.1 } else { 0.0 }).min(1.0);
+            if confidence < self.config.min_confidence_threshold {
+                continue;
+            }
+
+            let kind = if is_enum { "enum" } else { "type" };
+            let constant_names: Vec<String> = replaced
+                .iter()
+                .map(|(owner, constant)| format!("{}.{}", owner, constant))
+                .collect();
+            let mut data = HashMap::new();
+            data.insert("replacement_type".to_string(), type_info.name.clone());
+            data.insert("replaced_constants".to_string(), constant_names.join(", "));
+            data.insert("members".to_string(), members.join(", "));
+            data.insert("name_matches".to_string(), named_matches.to_string());
+
+            let mut affected_files: Vec<String> = replaced
+                .iter()
+                .filter_map(|(owner, _)| source_map.get(owner))
+                .map(|owner| owner.type_info.file_path.clone())
+                .chain(std::iter::once(type_info.file_path.clone()))
+                .collect();
+            affected_files.sort();
+            affected_files.dedup();
+
+            patterns.push(RefactoringPattern {
+                pattern_type: RefactoringType::ReplaceConstantsWithType,
+                confidence,
+                description: format!(
+                    "Replaced constants {} with {} '{}'",
+                    constant_names.join(", "),
+                    kind,
+                    type_info.name
+                ),
+                affected_elements: std::iter::once(type_info.name.clone())
+                    .chain(constant_names.iter().cloned())
+                    .collect(),
+                analysis: RefactoringAnalysis {
+                    characteristics: vec![RefactoringCharacteristic {
+                        characteristic_type: RefactoringCharacteristicType::DependencyChange,
+                        value: format!(
+                            "{} of {} members named after a removed constant",
+                            named_matches,
+                            members.len()
+                        ),
+                        confidence,
+                    }],
+                    before_after: None,
+                    impact: RefactoringImpact {
+                        impact_level: RefactoringImpactLevel::Medium,
+                        affected_files: affected_files.clone(),
+                        affected_functions: Vec::new(),
+                        // Code still using the removed constants must switch to the type
+                        is_breaking_change: false,
+                        api_compatibility: ApiCompatibilityImpact::PotentiallyBreaking,
+                    },
+                    quality_metrics: RefactoringQualityMetrics {
+                        quality_improvement: 0.3,
+                        maintainability_impact: 0.3,
+                        readability_impact: 0.2,
+                        testability_impact: 0.0,
+                        performance_impact: 0.0,
+                    },
+                },
+                evidence: vec![RefactoringEvidence {
+                    evidence_type: RefactoringEvidenceType::NamePattern,
+                    description: format!(
+                        "{} '{}' introduced as {} constants were removed",
+                        kind,
+                        type_info.name,
+                        replaced.len()
+                    ),
+                    strength: confidence,
+                    data,
+                }],
+                related_changes: vec![
+                    format!("{:?}", ChangeType::Add),
+                    format!("{:?}", ChangeType::Delete),
+                ],
+                complexity: RefactoringComplexity {
+                    complexity_level: RefactoringComplexityLevel::Moderate,
+                    elements_involved: replaced.len() + 1,
+                    files_affected: affected_files.len(),
+                    estimated_effort: RefactoringEffort::Medium,
+                },
+            });
+        }
+
+        patterns
+    }
+
+    /// Whether a field of this type can hold a constant an enum would replace
+    fn is_primitive_constant_type(type_name: &str) -> bool {
+        const CONSTANT_TYPES: &[&str] = &[
+            "String",
+            "string",
+            "str",
+            "char",
+            "Character",
+            "int",
+            "Integer",
+            "long",
+            "Long",
+            "short",
+            "Short",
+            "byte",
+            "Byte",
+            "uint",
+            "i8",
+            "i16",
+            "i32",
+            "i64",
+            "u8",
+            "u16",
+            "u32",
+            "u64",
+            "usize",
+            "isize",
+        ];
+
+        // `&'static str` in Rust, `java.lang.String` in Java
+        let base = type_name.trim().trim_start_matches('&');
+        let base = base.strip_prefix("'static").unwrap_or(base).trim();
+        let base = base.rsplit('.').next().unwrap_or(base);
+        CONSTANT_TYPES.contains(&base)
+    }
+
+    /// Constant or member name compared case- and underscore-insensitively
+    fn normalize_constant_name(name: &str) -> String {
+        name.chars()
+            .filter(|c| *c != '_')
+            .flat_map(char::to_lowercase)
+            .collect()
+    }
+
+    /// Detect methods pulled up into a superclass or pushed down into a subclass
+    ///
+    /// A change pairing a method of one class with the same method in another class
+    /// is reported as `PullUpMethod` when the new class is an ancestor of the old one
+    /// and as `PushDownMethod` when it is a descendant, following the inheritance
+    /// relationships of either version's type graph. The old class must have lost the
+    /// method and the new class gained it. Confidence rises with the similarity of the
+    /// two bodies and when the classes are parent and child rather than further apart.
+    pub fn detect_pull_up_push_down(
+        &self,
+        changes: &[Change],
+        source_types: &TypeDependencyGraphBuilder,
+        target_types: &TypeDependencyGraphBuilder,
+    ) -> Vec<RefactoringPattern> {
+        let mut patterns = Vec::new();
+
+        if !self.config.enable_pull_up_push_down {
+            return patterns;
+        }
+
+        let mut parents: HashMap<String, HashSet<String>> = HashMap::new();
+        for relationship in source_types
+            .inheritance_relationships()
+            .into_iter()
+            .chain(target_types.inheritance_relationships())
+        {
+            parents
+                .entry(relationship.from_type)
+                .or_default()
+                .insert(relationship.to_type);
+        }
+        if parents.is_empty() {
+            return patterns;
+        }
+
+        for change in changes {
+            let (Some(source), Some(target)) = (&change.source, &change.target) else {
+                continue;
+            };
+            let method = Self::unqualified_name(&source.name);
+            if method != Self::unqualified_name(&target.name) {
+                continue;
+            }
+            let (Some(old_class), Some(new_class)) = (
+                Self::owning_type(source_types, source, method),
+                Self::owning_type(target_types, target, method),
+            ) else {
+                continue;
+            };
+            if old_class == new_class
+                || Self::type_has_method(target_types, old_class, method)
+                || Self::type_has_method(source_types, new_class, method)
+            {
+                continue;
+            }
+
+            let pulled_up = Self::ancestor_distance(&parents, old_class, new_class)
+                .map(|distance| (RefactoringType::PullUpMethod, distance));
+            let Some((pattern_type, distance)) = pulled_up.or_else(|| {
+                Self::ancestor_distance(&parents, new_class, old_class)
+                    .map(|distance| (RefactoringType::PushDownMethod, distance))
+            }) else {
+                continue;
+            };
+
+            let body_similarity = change
+                .details
+                .similarity_score
+                .unwrap_or(change.confidence)
+                .clamp(0.0, 1.0);
+            let mut confidence = 0.5 + body_similarity * 0.4;
+            if distance == 1 {
+                confidence += 0.1;
+            }
+            let confidence = confidence.min(1.0);
+
+            if confidence >= self.config.min_confidence_threshold {
+                patterns.push(Self::create_hierarchy_move_pattern(
+                    pattern_type,
+                    method,
+                    (old_class, source),
+                    (new_class, target),
+                    body_similarity,
+                    confidence,
+                ));
+            }
+        }
+
+        patterns
+    }
+
+    /// Method name without its class or module qualification
+    fn unqualified_name(name: &str) -> &str {
+        name.rsplit(['.', ':']).next().unwrap_or(name)
+    }
+
+    /// Class declaring `method` at the location of `element`
+    ///
+    /// Among the classes in the element's file that declare the method, the one
+    /// starting closest before the element wins, so nested and neighbouring
+    /// classes in one file are told apart.
+    fn owning_type<'a>(
+        types: &'a TypeDependencyGraphBuilder,
+        element: &CodeElement,
+        method: &str,
+    ) -> Option<&'a str> {
+        types
+            .get_type_info_map()
+            .values()
+            .map(|extracted_type| &extracted_type.type_info)
+            .filter(|type_info| {
+                type_info.file_path == element.file_path
+                    && type_info.methods.iter().any(|m| m.name == method)
+            })
+            .max_by_key(|type_info| {
+                (
+                    type_info.line <= element.start_line,
+                    type_info.line,
+                    std::cmp::Reverse(type_info.name.as_str()),
+                )
+            })
+            .map(|type_info| type_info.name.as_str())
+    }
+
+    fn type_has_method(types: &TypeDependencyGraphBuilder, type_name: &str, method: &str) -> bool {
+        types
+            .get_type_info_map()
+            .get(type_name)
+            .is_some_and(|extracted_type| {
+                extracted_type
+                    .type_info
+                    .methods
+                    .iter()
+                    .any(|m| m.name == method)
+            })
+    }
+
+    /// Number of inheritance steps from `descendant` up to `ancestor`, if it is one
+    fn ancestor_distance(
+        parents: &HashMap<String, HashSet<String>>,
+        descendant: &str,
+        ancestor: &str,
+    ) -> Option<usize> {
+        let mut visited = HashSet::new();
+        let mut frontier = vec![descendant];
+        for distance in 1..=parents.len() {
+            let mut next = Vec::new();
+            for type_name in frontier {
+                for parent in parents.get(type_name).into_iter().flatten() {
+                    if parent == ancestor {
+                        return Some(distance);
+                    }
+                    if visited.insert(parent.as_str()) {
+                        next.push(parent.as_str());
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+        None
+    }
+
+    /// Build the pattern for a method moved along the class hierarchy
+    fn create_hierarchy_move_pattern(
+        pattern_type: RefactoringType,
+        method: &str,
+        (old_class, source): (&str, &CodeElement),
+        (new_class, target): (&str, &CodeElement),
+        body_similarity: f64,
+        confidence: f64,
+    ) -> RefactoringPattern {
+        let pulled_up = pattern_type == RefactoringType::PullUpMethod;
+        let description = if pulled_up {
+            format!(
+                "Pulled up method '{}' from '{}' to superclass '{}'",
+                method, old_class, new_class
+            )
+        } else {
+            format!(
+                "Pushed down method '{}' from '{}' to subclass '{}'",
+                method, old_class, new_class
+            )
+        };
+
+        let mut affected_files = vec![source.file_path.clone()];
+        if target.file_path != source.file_path {
+            affected_files.push(target.file_path.clone());
+        }
+
+        let mut data = HashMap::new();
+        data.insert("method".to_string(), method.to_string());
+        data.insert("source_class".to_string(), old_class.to_string());
+        data.insert("target_class".to_string(), new_class.to_string());
+        data.insert(
+            "body_similarity".to_string(),
+            format!("{:.2}", body_similarity),
+        );
+
+        RefactoringPattern {
+            pattern_type,
+            confidence,
+            description,
+            affected_elements: vec![
+                format!("{}.{}", old_class, method),
+                format!("{}.{}", new_class, method),
+            ],
+            analysis: RefactoringAnalysis {
+                characteristics: vec![RefactoringCharacteristic {
+                    characteristic_type: RefactoringCharacteristicType::LocationChange,
+                    value: format!("Moved from '{}' to '{}'", old_class, new_class),
+                    confidence,
+                }],
+                before_after: None,
+                impact: RefactoringImpact {
+                    impact_level: RefactoringImpactLevel::Low,
+                    affected_files: affected_files.clone(),
+                    affected_functions: vec![method.to_string()],
+                    // Subclasses inherit a pulled-up method; other subclasses of
+                    // the old class lose a pushed-down one
+                    is_breaking_change: !pulled_up,
+                    api_compatibility: if pulled_up {
+                        ApiCompatibilityImpact::BackwardCompatible
+                    } else {
+                        ApiCompatibilityImpact::PotentiallyBreaking
+                    },
+                },
+                quality_metrics: RefactoringQualityMetrics {
+                    quality_improvement: 0.5,
+                    maintainability_impact: 0.6,
+                    readability_impact: 0.3,
+                    testability_impact: 0.3,
+                    performance_impact: 0.0,
+                },
+            },
+            evidence: vec![RefactoringEvidence {
+                evidence_type: RefactoringEvidenceType::DependencyEvidence,
+                description: format!(
+                    "'{}' inherits from '{}' ({:.0}% body similarity)",
+                    if pulled_up { old_class } else { new_class },
+                    if pulled_up { new_class } else { old_class },
+                    body_similarity * 100.0
+                ),
+                strength: confidence,
+                data,
+            }],
+            related_changes: vec![format!("{:?}", ChangeType::CrossFileMove)],
+            complexity: RefactoringComplexity {
+                complexity_level: RefactoringComplexityLevel::Simple,
+                elements_involved: 1,
+                files_affected: affected_files.len(),
+                estimated_effort: RefactoringEffort::Low,
+            },
+        }
+    }
+
+    /// Deepest nesting of conditionals, loops, switches and try blocks below `node`
+    fn max_nesting_depth(node: &ASTNode, depth: usize) -> usize {
+        let depth = match node.node_type {
+            NodeType::IfStatement
+            | NodeType::WhileLoop
+            | NodeType::ForLoop
+            | NodeType::DoWhileLoop
+            | NodeType::SwitchStatement
+            | NodeType::TryStatement => depth + 1,
+            _ => depth,
+        };
+
+        node.children
+            .iter()
+            .map(|child| Self::max_nesting_depth(child, depth))
+            .max()
+            .unwrap_or(depth)
+            .max(depth)
+    }
+
+    /// Returns inside a conditional, i.e. ones that leave the function early
+    fn count_early_returns(node: &ASTNode, in_conditional: bool) -> usize {
+        if node.node_type == NodeType::ReturnStatement {
+            return usize::from(in_conditional);
+        }
+
+        let in_conditional = in_conditional || node.node_type == NodeType::IfStatement;
+        node.children
+            .iter()
+            .map(|child| Self::count_early_returns(child, in_conditional))
+            .sum()
+    }
+
+    /// Distinct identifiers and literals, ignoring keywords, operators and structure
+    fn body_tokens(node: &ASTNode) -> HashSet<String> {
+        let mut tokens = HashSet::new();
+        let mut stack = vec![node];
+
+        while let Some(current) = stack.pop() {
+            if matches!(current.node_type, NodeType::Identifier | NodeType::Literal) {
+                if let Some(text) = current.metadata.attributes.get("text") {
+                    tokens.insert(text.clone());
+                }
+            }
+            stack.extend(current.children.iter());
+        }
+
+        tokens
+    }
+
+    /// Bare type name of a parameter, without references, pointers or module path
+    fn parameter_type_name(param_type: &TypeSignature) -> &str {
+        let base = param_type
+            .base_type
+            .trim_start_matches(['&', '*'])
+            .trim_start_matches("mut ")
+            .trim_start_matches("const ")
+            .trim();
+
+        base.rsplit("::").next().unwrap_or(base)
+    }
+
+    /// Calculate confidence that `type_info` replaced the removed parameters
+    fn calculate_parameter_object_confidence(
+        &self,
+        function_name: &str,
+        removed_params: &[&FunctionParameter],
+        type_info: &TypeInfo,
+    ) -> f64 {
+        let mut name_matches = 0;
+        let mut type_matches = 0;
+
+        for param in removed_params {
+            let param_name = normalize_identifier(&param.name);
+            if let Some(field) = type_info
+                .fields
+                .iter()
+                .find(|f| normalize_identifier(&f.name) == param_name)
+            {
+                name_matches += 1;
+                if normalize_identifier(&field.type_name)
+                    == normalize_identifier(&param.param_type.base_type)
+                {
+                    type_matches += 1;
+                }
+            }
+        }
+
+        let coverage = name_matches as f64 / removed_params.len() as f64;
+        if coverage < 0.5 {
+            return 0.0;
+        }
+
+        let mut confidence = 0.35 + coverage * 0.4;
+        confidence += (type_matches as f64 / removed_params.len() as f64) * 0.1;
+
+        // Parameter objects are usually named after the function they configure
+        let type_name = normalize_identifier(&type_info.name);
+        let function_name = normalize_identifier(function_name);
+        if !function_name.is_empty() && type_name.contains(&function_name) {
+            confidence += 0.15;
+        } else if [
+            "options",
+            "params",
+            "parameters",
+            "args",
+            "config",
+            "request",
+        ]
+        .iter()
+        .any(|suffix| type_name.ends_with(suffix))
+        {
+            confidence += 0.05;
+        }
+
+        confidence.min(1.0)
+    }
+
+    /// Build the pattern for a detected parameter object
+    fn create_parameter_object_pattern(
+        &self,
+        source_sig: &EnhancedFunctionSignature,
+        target_sig: &EnhancedFunctionSignature,
+        removed_params: &[&FunctionParameter],
+        type_info: &TypeInfo,
+        confidence: f64,
+    ) -> RefactoringPattern {
+        let removed_names: Vec<String> = removed_params.iter().map(|p| p.name.clone()).collect();
+
+        let mut affected_files = vec![target_sig.file_path.clone()];
+        if !type_info.file_path.is_empty() && type_info.file_path != target_sig.file_path {
+            affected_files.push(type_info.file_path.clone());
+        }
+
+        let mut data = HashMap::new();
+        data.insert("removed_parameters".to_string(), removed_names.join(", "));
+        data.insert("parameter_object".to_string(), type_info.name.clone());
+        data.insert(
+            "parameter_count_before".to_string(),
+            source_sig.parameters.len().to_string(),
+        );
+        data.insert(
+            "parameter_count_after".to_string(),
+            target_sig.parameters.len().to_string(),
+        );
+
+        RefactoringPattern {
+            pattern_type: RefactoringType::IntroduceParameterObject,
+            confidence,
+            description: format!(
+                "Introduced parameter object '{}' in '{}' replacing parameters {}",
+                type_info.name,
+                target_sig.name,
+                removed_names.join(", ")
+            ),
+            affected_elements: vec![target_sig.name.clone(), type_info.name.clone()],
+            analysis: RefactoringAnalysis {
+                characteristics: vec![
+                    RefactoringCharacteristic {
+                        characteristic_type: RefactoringCharacteristicType::SignatureChange,
+                        value: format!(
+                            "Parameter count reduced from {} to {}",
+                            source_sig.parameters.len(),
+                            target_sig.parameters.len()
+                        ),
+                        confidence,
+                    },
+                    RefactoringCharacteristic {
+                        characteristic_type: RefactoringCharacteristicType::StructureChange,
+                        value: format!("New type '{}' groups removed parameters", type_info.name),
+                        confidence,
+                    },
+                ],
+                before_after: None,
+                impact: RefactoringImpact {
+                    impact_level: RefactoringImpactLevel::Medium,
+                    affected_files: affected_files.clone(),
+                    affected_functions: vec![target_sig.name.clone()],
+                    is_breaking_change: true,
+                    api_compatibility: ApiCompatibilityImpact::Breaking,
+                },
+                quality_metrics: RefactoringQualityMetrics {
+                    quality_improvement: 0.7,
+                    maintainability_impact: 0.8,
+                    readability_impact: 0.7,
+                    testability_impact: 0.6,
+                    performance_impact: 0.0,
+                },
+            },
+            evidence: vec![RefactoringEvidence {
+                evidence_type: RefactoringEvidenceType::StructurePattern,
+                description: format!(
+                    "Fields of '{}' correspond to removed parameters",
+                    type_info.name
+                ),
+                strength: confidence,
+                data,
+            }],
+            related_changes: vec![format!("{:?}", ChangeType::Modify)],
+            complexity: RefactoringComplexity {
+                complexity_level: RefactoringComplexityLevel::Moderate,
+                elements_involved: removed_params.len() + 1,
+                files_affected: affected_files.len(),
+                estimated_effort: RefactoringEffort::Low,
+            },
+        }
+    }
+
+    // Analysis creation methods
+
+    /// Create extract method analysis
+    fn create_extract_method_analysis(
+        &self,
+        modified: &Change,
+        added: &Change,
+    ) -> RefactoringAnalysis {
+        let mut characteristics = Vec::new();
+
+        characteristics.push(RefactoringCharacteristic {
+            characteristic_type: RefactoringCharacteristicType::CodeExtraction,
+            value: "Method extracted from existing function".to_string(),
+            confidence: 0.8,
+        });
+
+        if let Some(target) = &added.target {
+            characteristics.push(RefactoringCharacteristic {
+                characteristic_type: RefactoringCharacteristicType::NameChange,
+                value: format!("New method name: {}", target.name),
+                confidence: 1.0,
+            });
+        }
+
+        RefactoringAnalysis {
+            characteristics,
+            before_after: None,
+            impact: RefactoringImpact {
+                impact_level: RefactoringImpactLevel::Low,
+                affected_files: vec![modified
+                    .source
+                    .as_ref()
+                    .map(|s| s.file_path.clone())
+                    .unwrap_or_default()],
+                affected_functions: vec![
+                    modified
+                        .source
+                        .as_ref()
+                        .map(|s| s.name.clone())
+                        .unwrap_or_default(),
+                    added
+                        .target
+                        .as_ref()
+                        .map(|t| t.name.clone())
+                        .unwrap_or_default(),
+                ],
+                is_breaking_change: false,
+                api_compatibility: ApiCompatibilityImpact::BackwardCompatible,
+            },
+            quality_metrics: RefactoringQualityMetrics {
+                quality_improvement: 0.7,
+                maintainability_impact: 0.8,
+                readability_impact: 0.6,
+                testability_impact: 0.7,
+                performance_impact: 0.0,
+            },
+        }
+    }
+
+    /// Create inline method analysis
+    fn create_inline_method_analysis(
+        &self,
+        deleted: &Change,
+        modified: &Change,
+    ) -> RefactoringAnalysis {
+        let characteristics = vec![RefactoringCharacteristic {
+            characteristic_type: RefactoringCharacteristicType::CodeInlining,
+            value: "Method inlined into calling function".to_string(),
+            confidence: 0.8,
+        }];
+
+        RefactoringAnalysis {
+            characteristics,
+            before_after: None,
+            impact: RefactoringImpact {
+                impact_level: RefactoringImpactLevel::Low,
+                affected_files: vec![deleted
+                    .source
+                    .as_ref()
+                    .map(|s| s.file_path.clone())
+                    .unwrap_or_default()],
+                affected_functions: vec![
+                    deleted
+                        .source
+                        .as_ref()
+                        .map(|s| s.name.clone())
+                        .unwrap_or_default(),
+                    modified
+                        .target
+                        .as_ref()
+                        .map(|t| t.name.clone())
+                        .unwrap_or_default(),
+                ],
+                is_breaking_change: false,
+                api_compatibility: ApiCompatibilityImpact::BackwardCompatible,
+            },
+            quality_metrics: RefactoringQualityMetrics {
+                quality_improvement: 0.5,
+                maintainability_impact: 0.4,
+                readability_impact: 0.3,
+                testability_impact: 0.2,
+                performance_impact: 0.1,
+            },
+        }
+    }
+
+    /// Create rename analysis
+    fn create_rename_analysis(&self, change: &Change) -> RefactoringAnalysis {
+        let mut characteristics = Vec::new();
+
+        if let (Some(source), Some(target)) = (&change.source, &change.target) {
+            characteristics.push(RefactoringCharacteristic {
+                characteristic_type: RefactoringCharacteristicType::NameChange,
+                value: format!("Renamed from '{}' to '{}'", source.name, target.name),
+                confidence: 0.9,
+            });
+
+            let name_similarity = self.calculate_name_similarity(&source.name, &target.name);
+            characteristics.push(RefactoringCharacteristic {
+                characteristic_type: RefactoringCharacteristicType::NameChange,
+                value: format!("Name similarity: {:.3}", name_similarity),
+                confidence: 0.8,
+            });
+        }
+
+        RefactoringAnalysis {
+            characteristics,
+            before_after: None,
+            impact: RefactoringImpact {
+                impact_level: RefactoringImpactLevel::Medium,
+                affected_files: vec![change
+                    .source
+                    .as_ref()
+                    .map(|s| s.file_path.clone())
+                    .unwrap_or_default()],
+                affected_functions: vec![
+                    change
+                        .source
+                        .as_ref()
+                        .map(|s| s.name.clone())
+                        .unwrap_or_default(),
+                    change
+                        .target
+                        .as_ref()
+                        .map(|t| t.name.clone())
+                        .unwrap_or_default(),
+                ],
+                is_breaking_change: true, // Renames are potentially breaking
+                api_compatibility: ApiCompatibilityImpact::PotentiallyBreaking,
+            },
+            quality_metrics: RefactoringQualityMetrics {
+                quality_improvement: 0.6,
+                maintainability_impact: 0.7,
+                readability_impact: 0.8,
+                testability_impact: 0.5,
+                performance_impact: 0.0,
+            },
+        }
+    }
+
+    /// Create move analysis
     fn create_move_analysis(&self, change: &Change) -> RefactoringAnalysis {
         let mut characteristics = Vec::new();
 
@@ -2318,6 +3783,22 @@ impl RefactoringDetector {
         if self.config.enable_change_signature {
             types.push(RefactoringType::ChangeSignature);
         }
+        if self.config.enable_introduce_parameter_object {
+            types.push(RefactoringType::IntroduceParameterObject);
+        }
+        if self.config.enable_guard_clause_detection {
+            types.push(RefactoringType::IntroduceGuardClauses);
+        }
+        if self.config.enable_pull_up_push_down {
+            types.push(RefactoringType::PullUpMethod);
+            types.push(RefactoringType::PushDownMethod);
+        }
+        if self.config.enable_sync_async_detection {
+            types.push(RefactoringType::SyncAsyncConversion);
+        }
+        if self.config.enable_replace_constants_with_type {
+            types.push(RefactoringType::ReplaceConstantsWithType);
+        }
 
         types
     }
@@ -2492,10 +3973,33 @@ mod tests {
         assert!(config.enable_extract_class);
         assert!(config.enable_inline_class);
         assert!(config.enable_change_signature);
+        assert!(config.enable_introduce_parameter_object);
+        assert!(config.enable_guard_clause_detection);
+        assert!(config.enable_pull_up_push_down);
+        assert!(config.enable_sync_async_detection);
+        assert!(config.enable_replace_constants_with_type);
         assert_eq!(config.max_related_distance, 50);
         assert!(config.enable_complex_patterns);
     }
 
+    #[test]
+    fn test_requested_confidence_is_clamped() {
+        let lenient = RefactoringDetectionConfig::with_min_confidence(0.5);
+        assert_eq!(lenient.min_confidence_threshold, 0.5);
+        assert!(lenient.enable_extract_method);
+
+        let too_low = RefactoringDetectionConfig::with_min_confidence(0.0);
+        assert_eq!(too_low.min_confidence_threshold, MIN_CONFIDENCE_FLOOR);
+        assert_eq!(
+            RefactoringDetectionConfig::with_min_confidence(1.5).min_confidence_threshold,
+            1.0
+        );
+        assert_eq!(
+            RefactoringDetectionConfig::with_min_confidence(f64::NAN).min_confidence_threshold,
+            0.7
+        );
+    }
+
     #[test]
     fn test_refactoring_detector_creation() {
         let detector = RefactoringDetector::new(Language::Java);
@@ -2880,6 +4384,11 @@ mod tests {
             enable_extract_class: false,
             enable_inline_class: false,
             enable_change_signature: false,
+            enable_introduce_parameter_object: false,
+            enable_guard_clause_detection: false,
+            enable_pull_up_push_down: false,
+            enable_sync_async_detection: false,
+            enable_replace_constants_with_type: false,
             max_related_distance: 25,
             enable_complex_patterns: false,
         };
@@ -2972,4 +4481,737 @@ mod tests {
             })
             .collect();
     }
+
+    fn create_test_parameter(name: &str, type_name: &str, position: usize) -> FunctionParameter {
+        FunctionParameter {
+            name: name.to_string(),
+            param_type: TypeSignature::new(type_name.to_string()),
+            default_value: None,
+            is_optional: false,
+            is_varargs: false,
+            annotations: Vec::new(),
+            position,
+        }
+    }
+
+    fn create_test_signature_with_params(
+        name: &str,
+        parameters: Vec<FunctionParameter>,
+    ) -> EnhancedFunctionSignature {
+        use smart_diff_semantic::{FunctionType, Visibility};
+
+        EnhancedFunctionSignature {
+            name: name.to_string(),
+            qualified_name: name.to_string(),
+            parameters,
+            return_type: TypeSignature::new("void".to_string()),
+            generic_parameters: Vec::new(),
+            visibility: Visibility::Public,
+            modifiers: Vec::new(),
+            annotations: Vec::new(),
+            file_path: "render.rs".to_string(),
+            line: 1,
+            column: 1,
+            end_line: 10,
+            function_type: FunctionType::Function,
+            complexity_metrics: None,
+            dependencies: Vec::new(),
+            signature_hash: format!("hash_{}", name),
+            normalized_hash: format!("normalized_{}", name),
+        }
+    }
+
+    fn create_test_struct(name: &str, fields: &[(&str, &str)]) -> TypeInfo {
+        use smart_diff_semantic::{FieldInfo, TypeKind, Visibility};
+
+        TypeInfo {
+            name: name.to_string(),
+            kind: TypeKind::Struct,
+            generic_parameters: Vec::new(),
+            fields: fields
+                .iter()
+                .map(|(field_name, type_name)| FieldInfo {
+                    name: field_name.to_string(),
+                    type_name: type_name.to_string(),
+                    visibility: Visibility::Public,
+                    is_static: false,
+                    is_final: false,
+                })
+                .collect(),
+            methods: Vec::new(),
+            file_path: "render.rs".to_string(),
+            line: 20,
+        }
+    }
+
+    #[test]
+    fn test_introduce_parameter_object_detection() {
+        let detector = RefactoringDetector::new(Language::Rust);
+
+        let mut source_signatures = HashMap::new();
+        source_signatures.insert(
+            "render".to_string(),
+            create_test_signature_with_params(
+                "render",
+                vec![
+                    create_test_parameter("title", "String", 0),
+                    create_test_parameter("width", "u32", 1),
+                    create_test_parameter("height", "u32", 2),
+                ],
+            ),
+        );
+
+        let mut target_signatures = HashMap::new();
+        target_signatures.insert(
+            "render".to_string(),
+            create_test_signature_with_params(
+                "render",
+                vec![create_test_parameter("options", "&RenderOptions", 0)],
+            ),
+        );
+
+        let fields = [("title", "String"), ("width", "u32"), ("height", "u32")];
+        let target_types = vec![create_test_struct("RenderOptions", &fields)];
+
+        let patterns = detector.detect_introduce_parameter_object(
+            &source_signatures,
+            &target_signatures,
+            &[],
+            &target_types,
+        );
+
+        assert_eq!(patterns.len(), 1);
+        let pattern = &patterns[0];
+        assert_eq!(
+            pattern.pattern_type,
+            RefactoringType::IntroduceParameterObject
+        );
+        assert!(pattern
+            .affected_elements
+            .contains(&"RenderOptions".to_string()));
+        assert_eq!(
+            pattern.evidence[0].data.get("removed_parameters"),
+            Some(&"title, width, height".to_string())
+        );
+
+        // A type name unrelated to the function still matches, with lower confidence
+        let unrelated_types = vec![create_test_struct("Layout", &fields)];
+        let mut unrelated_target = target_signatures.clone();
+        unrelated_target.get_mut("render").unwrap().parameters =
+            vec![create_test_parameter("layout", "Layout", 0)];
+
+        let unrelated = detector.detect_introduce_parameter_object(
+            &source_signatures,
+            &unrelated_target,
+            &[],
+            &unrelated_types,
+        );
+        assert_eq!(unrelated.len(), 1);
+        assert!(pattern.confidence > unrelated[0].confidence);
+
+        // Types that already existed in the source are not introduced parameter objects
+        let existing = detector.detect_introduce_parameter_object(
+            &source_signatures,
+            &target_signatures,
+            &target_types,
+            &target_types,
+        );
+        assert!(existing.is_empty());
+    }
+
+    #[test]
+    fn test_guard_clause_extraction_detection() {
+        use smart_diff_parser::{tree_sitter::TreeSitterParser, Parser};
+
+        fn parse_method(code: &str) -> ASTNode {
+            let parser = TreeSitterParser::new().unwrap();
+            let result = parser.parse(code, Language::Java).unwrap();
+            result.ast.find_by_type(&NodeType::Method)[0].clone()
+        }
+
+        let nested = parse_method(
+            r#"
+class Greeter {
+    String describe(User user) {
+        if (user != null) {
+            if (user.isActive()) {
+                if (user.hasName()) {
+                    return user.getName();
+                }
+            }
+        }
+        return "unknown";
+    }
+}
+"#,
+        );
+        let guarded = parse_method(
+            r#"
+class Greeter {
+    String describe(User user) {
+        if (user == null) {
+            return "unknown";
+        }
+        if (!user.isActive()) {
+            return "unknown";
+        }
+        if (!user.hasName()) {
+            return "unknown";
+        }
+        return user.getName();
+    }
+}
+"#,
+        );
+
+        let detector = RefactoringDetector::new(Language::Java);
+        let pattern = detector
+            .detect_guard_clause_extraction("describe", &nested, &guarded)
+            .expect("guard clauses should be detected");
+
+        assert_eq!(pattern.pattern_type, RefactoringType::IntroduceGuardClauses);
+        assert!(pattern.analysis.characteristics.iter().any(|c| {
+            c.characteristic_type == RefactoringCharacteristicType::GuardClauseExtraction
+        }));
+        assert!(pattern.analysis.quality_metrics.maintainability_impact > 0.0);
+        assert!(pattern.analysis.quality_metrics.readability_impact > 0.0);
+        assert_eq!(
+            pattern.evidence[0].data.get("nesting_depth_before"),
+            Some(&"3".to_string())
+        );
+        assert_eq!(
+            pattern.evidence[0].data.get("nesting_depth_after"),
+            Some(&"1".to_string())
+        );
+
+        // Going the other way nests the code again, which is not a guard clause refactoring
+        assert!(detector
+            .detect_guard_clause_extraction("describe", &guarded, &nested)
+            .is_none());
+    }
+
+    #[test]
+    fn test_sync_async_conversion_detection() {
+        let detector = RefactoringDetector::new(Language::TypeScript);
+
+        let load = create_test_signature_with_params(
+            "load",
+            vec![create_test_parameter("id", "string", 0)],
+        );
+        let mut async_load = load.clone();
+        async_load.modifiers = vec!["async".to_string()];
+        async_load.return_type = TypeSignature::new("Promise".to_string())
+            .with_generics(vec![TypeSignature::new("User".to_string())]);
+
+        let source_signatures = HashMap::from([("load".to_string(), load.clone())]);
+        let target_signatures = HashMap::from([("load".to_string(), async_load.clone())]);
+
+        let patterns =
+            detector.detect_sync_async_conversion(&source_signatures, &target_signatures);
+        assert_eq!(patterns.len(), 1);
+        let pattern = &patterns[0];
+        assert_eq!(pattern.pattern_type, RefactoringType::SyncAsyncConversion);
+        assert!(pattern.analysis.impact.is_breaking_change);
+        assert_eq!(
+            pattern.analysis.impact.api_compatibility,
+            ApiCompatibilityImpact::Breaking
+        );
+        assert_eq!(
+            pattern.evidence[0].data.get("async_after"),
+            Some(&"true".to_string())
+        );
+
+        // A future return type alone marks the function asynchronous, in either direction
+        let mut future_load = load.clone();
+        future_load.return_type = TypeSignature::new("CompletableFuture".to_string());
+        let reverted = detector.detect_sync_async_conversion(
+            &HashMap::from([("load".to_string(), future_load)]),
+            &source_signatures,
+        );
+        assert_eq!(reverted.len(), 1);
+        assert_eq!(
+            reverted[0].evidence[0].data.get("async_after"),
+            Some(&"false".to_string())
+        );
+        assert!(pattern.confidence > reverted[0].confidence);
+
+        // Staying asynchronous is not a conversion
+        assert!(detector
+            .detect_sync_async_conversion(&target_signatures, &target_signatures)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_custom_rule_patterns_included() {
+        /// Flags functions replaced by an `...Async` counterpart
+        struct CallbackToAsyncRule;
+
+        impl RefactoringRule for CallbackToAsyncRule {
+            fn detect(&self, groups: &[Vec<&Change>]) -> Vec<RefactoringPattern> {
+                groups
+                    .iter()
+                    .flatten()
+                    .filter_map(|change| change.target.as_ref())
+                    .filter(|target| target.name.ends_with("Async"))
+                    .map(|target| {
+                        RefactoringPattern::custom(
+                            "callback-to-async",
+                            0.9,
+                            format!("Migrated to async in '{}'", target.name),
+                            vec![target.name.clone()],
+                        )
+                    })
+                    .collect()
+            }
+        }
+
+        let detector = RefactoringDetector::minimal(Language::JavaScript)
+            .with_rules(vec![Box::new(CallbackToAsyncRule)]);
+
+        let changes = vec![create_test_change(
+            ChangeType::Rename,
+            Some(create_test_code_element("loadUser", "user.js", 10)),
+            Some(create_test_code_element("loadUserAsync", "user.js", 10)),
+            Some(0.85),
+        )];
+
+        let patterns = detector.detect_patterns(&changes);
+        let custom = patterns
+            .iter()
+            .find(|p| p.pattern_type == RefactoringType::Custom("callback-to-async".to_string()))
+            .expect("custom rule pattern should be reported");
+        assert_eq!(custom.affected_elements, vec!["loadUserAsync".to_string()]);
+    }
+
+    fn parsed_version(language: Language, file_path: &str, code: &str) -> CodeVersion {
+        use smart_diff_parser::{tree_sitter::TreeSitterParser, Parser};
+
+        let parsed = TreeSitterParser::new()
+            .unwrap()
+            .parse(code, language)
+            .unwrap();
+        CodeVersion::from_file(file_path, &parsed).unwrap()
+    }
+
+    #[test]
+    fn test_detect_patterns_finds_introduced_parameter_object() {
+        let source = parsed_version(
+            Language::Java,
+            "Canvas.java",
+            "class Canvas {\n    void render(String title, int width, int height) {\n        draw(title, width, height);\n    }\n}\n",
+        );
+        let target = parsed_version(
+            Language::Java,
+            "Canvas.java",
+            "class RenderOptions {\n    String title;\n    int width;\n    int height;\n}\n\n\
+             class Canvas {\n    void render(RenderOptions options) {\n        draw(options.title, options.width, options.height);\n    }\n}\n",
+        );
+        let changes = vec![create_test_change(
+            ChangeType::Modify,
+            Some(create_test_code_element("render", "Canvas.java", 2)),
+            Some(create_test_code_element("render", "Canvas.java", 8)),
+            Some(0.7),
+        )];
+
+        let detector = RefactoringDetector::new(Language::Java);
+        assert!(!detector
+            .detect_patterns(&changes)
+            .iter()
+            .any(|p| p.pattern_type == RefactoringType::IntroduceParameterObject));
+
+        let patterns = detector
+            .with_versions(source, target)
+            .detect_patterns(&changes);
+        let pattern = patterns
+            .iter()
+            .find(|p| p.pattern_type == RefactoringType::IntroduceParameterObject)
+            .expect("parameter object detected");
+        assert!(pattern
+            .affected_elements
+            .contains(&"RenderOptions".to_string()));
+    }
+
+    #[test]
+    fn test_detect_patterns_finds_guard_clauses() {
+        let nested = r#"
+class Greeter {
+    String describe(User user) {
+        if (user != null) {
+            if (user.isActive()) {
+                if (user.hasName()) {
+                    return user.getName();
+                }
+            }
+        }
+        return "unknown";
+    }
+}
+"#;
+        let guarded = r#"
+class Greeter {
+    String describe(User user) {
+        if (user == null) {
+            return "unknown";
+        }
+        if (!user.isActive()) {
+            return "unknown";
+        }
+        if (!user.hasName()) {
+            return "unknown";
+        }
+        return user.getName();
+    }
+}
+"#;
+        let changes = vec![create_test_change(
+            ChangeType::Modify,
+            Some(create_test_code_element("describe", "Greeter.java", 2)),
+            Some(create_test_code_element("describe", "Greeter.java", 2)),
+            Some(0.6),
+        )];
+
+        let patterns = RefactoringDetector::new(Language::Java)
+            .with_versions(
+                parsed_version(Language::Java, "Greeter.java", nested),
+                parsed_version(Language::Java, "Greeter.java", guarded),
+            )
+            .detect_patterns(&changes);
+
+        let pattern = patterns
+            .iter()
+            .find(|p| p.pattern_type == RefactoringType::IntroduceGuardClauses)
+            .expect("guard clauses detected");
+        assert_eq!(pattern.affected_elements, vec!["describe".to_string()]);
+    }
+
+    #[test]
+    fn test_detect_patterns_finds_pulled_up_method() {
+        use smart_diff_parser::{tree_sitter::TreeSitterParser, Parser};
+
+        let version = |files: &[(&str, &str)]| {
+            let parser = TreeSitterParser::new().unwrap();
+            let mut version = CodeVersion::new();
+            for (path, code) in files {
+                let parsed = parser.parse(code, Language::Java).unwrap();
+                version.add_file(path, &parsed).unwrap();
+            }
+            version
+        };
+        let speak = "    String speak() {\n        return name + \" makes a sound\";\n    }\n";
+        let source = version(&[
+            ("Animal.java", "class Animal {\n    String name;\n}\n"),
+            (
+                "Dog.java",
+                &format!("class Dog extends Animal {{\n{}}}\n", speak),
+            ),
+        ]);
+        let target = version(&[
+            (
+                "Animal.java",
+                &format!("class Animal {{\n    String name;\n{}}}\n", speak),
+            ),
+            ("Dog.java", "class Dog extends Animal {\n}\n"),
+        ]);
+        let moved = create_test_change(
+            ChangeType::CrossFileMove,
+            Some(create_test_code_element("speak", "Dog.java", 2)),
+            Some(create_test_code_element("speak", "Animal.java", 3)),
+            Some(1.0),
+        );
+
+        let patterns = RefactoringDetector::new(Language::Java)
+            .with_versions(source, target)
+            .detect_patterns(&[moved]);
+
+        let pattern = patterns
+            .iter()
+            .find(|p| p.pattern_type == RefactoringType::PullUpMethod)
+            .expect("pulled up method detected");
+        assert_eq!(
+            pattern.affected_elements,
+            vec!["Dog.speak".to_string(), "Animal.speak".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_detect_patterns_finds_sync_async_conversion() {
+        let source = parsed_version(
+            Language::Java,
+            "UserStore.java",
+            "class UserStore {\n    User load(String id) {\n        return users.get(id);\n    }\n}\n",
+        );
+        let target = parsed_version(
+            Language::Java,
+            "UserStore.java",
+            "class UserStore {\n    CompletableFuture<User> load(String id) {\n        \
+             return CompletableFuture.supplyAsync(() -> users.get(id));\n    }\n}\n",
+        );
+        let changes = vec![create_test_change(
+            ChangeType::Modify,
+            Some(create_test_code_element("load", "UserStore.java", 2)),
+            Some(create_test_code_element("load", "UserStore.java", 2)),
+            Some(0.7),
+        )];
+
+        let patterns = RefactoringDetector::new(Language::Java)
+            .with_versions(source, target)
+            .detect_patterns(&changes);
+
+        let pattern = patterns
+            .iter()
+            .find(|p| p.pattern_type == RefactoringType::SyncAsyncConversion)
+            .expect("sync to async conversion detected");
+        assert_eq!(
+            pattern.evidence[0].data.get("async_after"),
+            Some(&"true".to_string())
+        );
+    }
+
+    #[test]
+    fn test_detect_patterns_classifies_return_type_change() {
+        let shapes = "interface Shape {\n    double area();\n}\n\n\
+                      class Circle implements Shape {\n    public double area() {\n        return 3.14;\n    }\n}\n\n";
+        let factory = |return_type: &str| {
+            format!(
+                "{}class Factory {{\n    {} createShape() {{\n        return new Circle();\n    }}\n}}\n",
+                shapes, return_type
+            )
+        };
+        let changes = vec![create_test_change(
+            ChangeType::Modify,
+            Some(create_test_code_element("createShape", "Factory.java", 12)),
+            Some(create_test_code_element("createShape", "Factory.java", 12)),
+            Some(0.9),
+        )];
+
+        let patterns = RefactoringDetector::new(Language::Java)
+            .with_versions(
+                parsed_version(Language::Java, "Factory.java", &factory("Shape")),
+                parsed_version(Language::Java, "Factory.java", &factory("Circle")),
+            )
+            .detect_patterns(&changes);
+
+        let pattern = patterns
+            .iter()
+            .find(|p| {
+                p.evidence
+                    .iter()
+                    .any(|e| e.data.contains_key("return_type_change"))
+            })
+            .expect("return type change classified");
+        assert_eq!(pattern.pattern_type, RefactoringType::ChangeSignature);
+        assert_eq!(
+            pattern.evidence[0].data.get("return_type_change"),
+            Some(&"narrowing".to_string())
+        );
+        assert!(pattern.analysis.impact.is_breaking_change);
+    }
+
+    #[test]
+    fn test_detect_patterns_finds_constants_replaced_by_enum() {
+        let source = parsed_version(
+            Language::Java,
+            "Order.java",
+            "class Order {\n    static final String STATUS_ACTIVE = \"active\";\n    \
+             static final String STATUS_SHIPPED = \"shipped\";\n    \
+             String status;\n}\n",
+        );
+        let target = parsed_version(
+            Language::Java,
+            "Order.java",
+            "enum Status {\n    ACTIVE,\n    SHIPPED\n}\n\nclass Order {\n    Status status;\n}\n",
+        );
+
+        let patterns = RefactoringDetector::new(Language::Java)
+            .with_versions(source, target)
+            .detect_patterns(&[]);
+
+        let pattern = patterns
+            .iter()
+            .find(|p| p.pattern_type == RefactoringType::ReplaceConstantsWithType)
+            .expect("constants replaced by enum detected");
+        assert_eq!(
+            pattern.evidence[0].data.get("replaced_constants"),
+            Some(&"Order.STATUS_ACTIVE, Order.STATUS_SHIPPED".to_string())
+        );
+    }
+
+    fn java_type_graph(files: &[(&str, &str)]) -> TypeDependencyGraphBuilder {
+        use smart_diff_parser::{tree_sitter::TreeSitterParser, Parser};
+        use smart_diff_semantic::TypeExtractor;
+
+        let parser = TreeSitterParser::new().unwrap();
+        let parsed = files
+            .iter()
+            .map(|(path, code)| {
+                (
+                    path.to_string(),
+                    parser.parse(code, Language::Java).unwrap(),
+                )
+            })
+            .collect();
+        let extracted = TypeExtractor::with_defaults(Language::Java)
+            .extract_types_from_files(parsed)
+            .unwrap();
+        let mut builder = TypeDependencyGraphBuilder::new();
+        builder.build_from_extraction_result(&extracted).unwrap();
+        builder
+    }
+
+    #[test]
+    fn test_pull_up_method_detection() {
+        let speak = "    String speak() {\n        return name + \" makes a sound\";\n    }\n";
+        let source_types = java_type_graph(&[
+            ("Animal.java", "class Animal {\n    String name;\n}\n"),
+            (
+                "Dog.java",
+                &format!("class Dog extends Animal {{\n{}}}\n", speak),
+            ),
+        ]);
+        let target_types = java_type_graph(&[
+            (
+                "Animal.java",
+                &format!("class Animal {{\n    String name;\n{}}}\n", speak),
+            ),
+            ("Dog.java", "class Dog extends Animal {\n}\n"),
+        ]);
+
+        let detector = RefactoringDetector::new(Language::Java);
+        let moved = create_test_change(
+            ChangeType::CrossFileMove,
+            Some(create_test_code_element("speak", "Dog.java", 2)),
+            Some(create_test_code_element("speak", "Animal.java", 3)),
+            Some(1.0),
+        );
+        let patterns = detector.detect_pull_up_push_down(
+            std::slice::from_ref(&moved),
+            &source_types,
+            &target_types,
+        );
+        assert_eq!(patterns.len(), 1);
+        assert_eq!(patterns[0].pattern_type, RefactoringType::PullUpMethod);
+        assert_eq!(
+            patterns[0].affected_elements,
+            vec!["Dog.speak".to_string(), "Animal.speak".to_string()]
+        );
+        assert!(!patterns[0].analysis.impact.is_breaking_change);
+
+        // The same move in reverse pushes the method down
+        let reversed = create_test_change(
+            ChangeType::CrossFileMove,
+            moved.target.clone(),
+            moved.source.clone(),
+            Some(1.0),
+        );
+        let pushed = detector.detect_pull_up_push_down(&[reversed], &target_types, &source_types);
+        assert_eq!(pushed.len(), 1);
+        assert_eq!(pushed[0].pattern_type, RefactoringType::PushDownMethod);
+
+        // A rewritten body lowers the confidence
+        let rewritten = create_test_change(
+            ChangeType::CrossFileMove,
+            moved.source.clone(),
+            moved.target.clone(),
+            Some(0.6),
+        );
+        let less_confident =
+            detector.detect_pull_up_push_down(&[rewritten], &source_types, &target_types);
+        assert!(less_confident[0].confidence < patterns[0].confidence);
+    }
+
+    #[test]
+    fn test_return_type_narrowing_is_breaking() {
+        let types = java_type_graph(&[
+            ("Shape.java", "interface Shape {\n    double area();\n}\n"),
+            (
+                "Circle.java",
+                "class Circle implements Shape {\n    public double area() {\n        return 3.14;\n    }\n}\n",
+            ),
+            ("Label.java", "class Label {\n}\n"),
+        ]);
+
+        let with_return_type = |return_type: &str| {
+            let mut signature = create_test_signature_with_params("createShape", Vec::new());
+            signature.return_type = TypeSignature::new(return_type.to_string());
+            HashMap::from([("createShape".to_string(), signature)])
+        };
+        let (shape, circle, label) = (
+            with_return_type("Shape"),
+            with_return_type("Circle"),
+            with_return_type("Label"),
+        );
+        let detector = RefactoringDetector::new(Language::Java);
+
+        let narrowed = detector.detect_return_type_changes(&shape, &circle, &types, &types);
+        assert_eq!(narrowed.len(), 1);
+        assert_eq!(narrowed[0].pattern_type, RefactoringType::ChangeSignature);
+        assert_eq!(
+            narrowed[0].evidence[0].data.get("return_type_change"),
+            Some(&"narrowing".to_string())
+        );
+        assert!(narrowed[0].analysis.impact.is_breaking_change);
+        assert_eq!(
+            narrowed[0].analysis.impact.api_compatibility,
+            ApiCompatibilityImpact::Breaking
+        );
+
+        let widened = detector.detect_return_type_changes(&circle, &shape, &types, &types);
+        assert_eq!(
+            widened[0].evidence[0].data.get("return_type_change"),
+            Some(&"widening".to_string())
+        );
+        assert!(!widened[0].analysis.impact.is_breaking_change);
+
+        let unrelated = detector.detect_return_type_changes(&shape, &label, &types, &types);
+        assert_eq!(
+            unrelated[0].evidence[0].data.get("return_type_change"),
+            Some(&"unrelated".to_string())
+        );
+        assert!(unrelated[0].analysis.impact.is_breaking_change);
+
+        assert!(detector
+            .detect_return_type_changes(&shape, &shape, &types, &types)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_string_constants_replaced_by_enum() {
+        let source_types = java_type_graph(&[(
+            "Order.java",
+            "class Order {\n    static final String STATUS_ACTIVE = \"active\";\n    \
+             static final String STATUS_SHIPPED = \"shipped\";\n    \
+             static final String STATUS_CANCELLED = \"cancelled\";\n    \
+             String status;\n}\n",
+        )]);
+        let target_types = java_type_graph(&[
+            (
+                "Status.java",
+                "enum Status {\n    ACTIVE,\n    SHIPPED,\n    CANCELLED\n}\n",
+            ),
+            ("Order.java", "class Order {\n    Status status;\n}\n"),
+        ]);
+        let detector = RefactoringDetector::new(Language::Java);
+
+        let patterns = detector.detect_constant_replacement(&source_types, &target_types);
+        assert_eq!(patterns.len(), 1, "{:#?}", patterns);
+        let pattern = &patterns[0];
+        assert_eq!(
+            pattern.pattern_type,
+            RefactoringType::ReplaceConstantsWithType
+        );
+        assert_eq!(
+            pattern.evidence[0].data.get("replaced_constants"),
+            Some(&"Order.STATUS_ACTIVE, Order.STATUS_SHIPPED, Order.STATUS_CANCELLED".to_string())
+        );
+        assert_eq!(
+            pattern.evidence[0].data.get("name_matches"),
+            Some(&"3".to_string())
+        );
+        assert!(pattern.confidence > 0.95, "{}", pattern.confidence);
+
+        // Nothing was replaced when the constants stay
+        assert!(detector
+            .detect_constant_replacement(&source_types, &source_types)
+            .is_empty());
+    }
 }
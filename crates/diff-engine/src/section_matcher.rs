@@ -0,0 +1,456 @@
+//! Section-level comparison of documentation files
+//!
+//! A Markdown document is split into sections, each a heading plus everything
+//! up to the next heading of the same or a higher level, and sections are
+//! matched across versions like functions are in code. Sections that keep
+//! their heading but change position are reported as moves, so a reordered
+//! document does not read as a wholesale rewrite.
+
+use crate::token_similarity::{TokenSimilarity, TokenSimilarityConfig};
+use smart_diff_parser::{
+    ASTNode, Change, ChangeType, CodeElement, ElementType, MatchResult, NodeType,
+};
+use std::collections::{HashMap, HashSet};
+
+/// One section of a document
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentSection {
+    /// Unique key: the heading path, with a `#n` suffix for repeated paths
+    pub id: String,
+    pub title: String,
+    pub level: usize,
+    /// Titles of the enclosing sections, outermost first
+    pub parents: Vec<String>,
+    pub start_line: usize,
+    /// Last line before the next heading of the same or a higher level
+    pub end_line: usize,
+    /// Text between the heading and the next heading of any level
+    pub content: String,
+}
+
+impl DocumentSection {
+    /// Code element describing this section in a change
+    pub fn to_element(&self, file_path: &str) -> CodeElement {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        self.content.hash(&mut hasher);
+
+        CodeElement {
+            id: self.id.clone(),
+            element_type: ElementType::Section,
+            name: self.title.clone(),
+            file_path: file_path.to_string(),
+            start_line: self.start_line,
+            end_line: self.end_line,
+            signature: Some(self.path()),
+            hash: format!("{:x}", hasher.finish()),
+        }
+    }
+
+    /// Heading path, e.g. `Guide > Install > Linux`
+    pub fn path(&self) -> String {
+        self.parents
+            .iter()
+            .chain(std::iter::once(&self.title))
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" > ")
+    }
+}
+
+/// Split a parsed Markdown document into its sections, in document order
+///
+/// Headings come from the AST, so `#` lines inside code blocks are not
+/// mistaken for headings; section text is taken from `source` by line.
+pub fn extract_sections(ast: &ASTNode, source: &str) -> Vec<DocumentSection> {
+    let mut headings = Vec::new();
+    collect_headings(ast, &mut headings);
+
+    let lines: Vec<&str> = source.lines().collect();
+    let mut sections: Vec<DocumentSection> = Vec::with_capacity(headings.len());
+    let mut open: Vec<(usize, String)> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for (i, heading) in headings.iter().enumerate() {
+        let (line, level, title) = (heading.line, heading.level, &heading.title);
+        while open
+            .last()
+            .is_some_and(|(open_level, _)| *open_level >= level)
+        {
+            open.pop();
+        }
+        let parents: Vec<String> = open.iter().map(|(_, title)| title.clone()).collect();
+
+        let next_heading = headings.get(i + 1).map_or(lines.len() + 1, |h| h.line);
+        let end_line = headings[i + 1..]
+            .iter()
+            .find(|h| h.level <= level)
+            .map_or(lines.len(), |h| h.line - 1)
+            .max(line);
+        let body_start = heading.last_line.min(lines.len());
+        let content = lines
+            .get(body_start..(next_heading - 1).max(body_start))
+            .unwrap_or_default()
+            .join("\n")
+            .trim()
+            .to_string();
+
+        let mut section = DocumentSection {
+            id: String::new(),
+            title: title.clone(),
+            level,
+            parents,
+            start_line: line,
+            end_line,
+            content,
+        };
+        let occurrence = seen.entry(section.path()).or_insert(0);
+        *occurrence += 1;
+        section.id = match *occurrence {
+            1 => section.path(),
+            n => format!("{}#{}", section.path(), n),
+        };
+
+        open.push((level, title.clone()));
+        sections.push(section);
+    }
+
+    sections
+}
+
+/// A heading found in the AST
+struct Heading {
+    line: usize,
+    /// Last line of the heading itself, which is the underline for setext headings
+    last_line: usize,
+    level: usize,
+    title: String,
+}
+
+/// Headings in document order
+fn collect_headings(node: &ASTNode, headings: &mut Vec<Heading>) {
+    if node.node_type == NodeType::Heading {
+        let attributes = &node.metadata.attributes;
+        let line = node.metadata.line;
+        let heading_lines = node.metadata.original_text.trim_end().lines().count();
+        headings.push(Heading {
+            line,
+            last_line: line + heading_lines.max(1) - 1,
+            level: attributes
+                .get("heading_level")
+                .and_then(|level| level.parse().ok())
+                .unwrap_or(1),
+            title: attributes.get("name").cloned().unwrap_or_default(),
+        });
+        return;
+    }
+    for child in &node.children {
+        collect_headings(child, headings);
+    }
+}
+
+/// Settings for section matching
+#[derive(Debug, Clone)]
+pub struct SectionMatcherConfig {
+    /// Content similarity at which sections with different headings are paired
+    pub rename_threshold: f64,
+    pub content_similarity: TokenSimilarityConfig,
+}
+
+impl Default for SectionMatcherConfig {
+    fn default() -> Self {
+        Self {
+            rename_threshold: 0.7,
+            content_similarity: TokenSimilarityConfig {
+                ngram_size: 2,
+                ignore_case: true,
+            },
+        }
+    }
+}
+
+/// Matches the sections of two versions of a document
+pub struct SectionMatcher {
+    config: SectionMatcherConfig,
+    content_similarity: TokenSimilarity,
+}
+
+impl Default for SectionMatcher {
+    fn default() -> Self {
+        Self::new(SectionMatcherConfig::default())
+    }
+}
+
+impl SectionMatcher {
+    pub fn new(config: SectionMatcherConfig) -> Self {
+        let content_similarity = TokenSimilarity::new(config.content_similarity.clone());
+        Self {
+            config,
+            content_similarity,
+        }
+    }
+
+    /// Match sections by heading path, then by heading, then by content
+    ///
+    /// Matched sections out of their original relative order, or now under a
+    /// different parent, are reported as moves; the longest run of sections
+    /// that kept their order stays in place.
+    pub fn match_sections(
+        &self,
+        source: &[DocumentSection],
+        target: &[DocumentSection],
+        source_file: &str,
+        target_file: &str,
+    ) -> MatchResult {
+        let mut result = MatchResult::new();
+        if source.is_empty() && target.is_empty() {
+            result.similarity = 1.0;
+            return result;
+        }
+
+        let pairs = self.pair_sections(source, target);
+        let in_order = Self::longest_ordered_run(&pairs);
+
+        let mut matched_targets = HashSet::new();
+        for (pair_idx, &(source_idx, target_idx)) in pairs.iter().enumerate() {
+            let (old, new) = (&source[source_idx], &target[target_idx]);
+            matched_targets.insert(target_idx);
+            result.mapping.insert(old.id.clone(), new.id.clone());
+
+            let similarity = self
+                .content_similarity
+                .similarity(&old.content, &new.content);
+            let moved = !in_order.contains(&pair_idx);
+            let (change_type, description) = if old.parents != new.parents {
+                (
+                    ChangeType::Move,
+                    format!("Section '{}' moved to '{}'", old.path(), new.path()),
+                )
+            } else if moved {
+                (
+                    ChangeType::Move,
+                    format!(
+                        "Section '{}' moved from line {} to line {}",
+                        new.path(),
+                        old.start_line,
+                        new.start_line
+                    ),
+                )
+            } else if old.title != new.title {
+                (
+                    ChangeType::Rename,
+                    format!("Section '{}' renamed to '{}'", old.title, new.title),
+                )
+            } else if old.content != new.content {
+                (
+                    ChangeType::Modify,
+                    format!(
+                        "Section '{}' modified (similarity: {:.2})",
+                        new.path(),
+                        similarity
+                    ),
+                )
+            } else {
+                continue;
+            };
+
+            let mut change = Change::new(change_type, description).with_elements(
+                Some(old.to_element(source_file)),
+                Some(new.to_element(target_file)),
+            );
+            change.confidence = similarity;
+            change.details.similarity_score = Some(similarity);
+            result.changes.push(change);
+        }
+
+        let matched_sources: HashSet<usize> = pairs.iter().map(|&(s, _)| s).collect();
+        for (source_idx, section) in source.iter().enumerate() {
+            if !matched_sources.contains(&source_idx) {
+                result.unmatched_source.push(section.id.clone());
+                result.changes.push(
+                    Change::new(
+                        ChangeType::Delete,
+                        format!("Section '{}' deleted", section.path()),
+                    )
+                    .with_elements(Some(section.to_element(source_file)), None),
+                );
+            }
+        }
+        for (target_idx, section) in target.iter().enumerate() {
+            if !matched_targets.contains(&target_idx) {
+                result.unmatched_target.push(section.id.clone());
+                result.changes.push(
+                    Change::new(
+                        ChangeType::Add,
+                        format!("Section '{}' added", section.path()),
+                    )
+                    .with_elements(None, Some(section.to_element(target_file))),
+                );
+            }
+        }
+
+        result.calculate_similarity();
+        result
+    }
+
+    /// Pair source and target sections, sorted by source position
+    fn pair_sections(
+        &self,
+        source: &[DocumentSection],
+        target: &[DocumentSection],
+    ) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        let mut open_sources: Vec<usize> = (0..source.len()).collect();
+        let mut open_targets: Vec<usize> = (0..target.len()).collect();
+
+        let mut pair_by = |same: &dyn Fn(&DocumentSection, &DocumentSection) -> bool,
+                           open_sources: &mut Vec<usize>,
+                           open_targets: &mut Vec<usize>| {
+            open_sources.retain(|&source_idx| {
+                match open_targets
+                    .iter()
+                    .position(|&target_idx| same(&source[source_idx], &target[target_idx]))
+                {
+                    Some(pos) => {
+                        pairs.push((source_idx, open_targets.remove(pos)));
+                        false
+                    }
+                    None => true,
+                }
+            });
+        };
+
+        // Unchanged heading paths first, then headings that moved under another parent
+        pair_by(&|a, b| a.id == b.id, &mut open_sources, &mut open_targets);
+        pair_by(
+            &|a, b| a.title == b.title,
+            &mut open_sources,
+            &mut open_targets,
+        );
+
+        // Renamed headings are paired by content
+        for source_idx in open_sources {
+            let best = open_targets
+                .iter()
+                .enumerate()
+                .map(|(pos, &target_idx)| {
+                    let similarity = self
+                        .content_similarity
+                        .similarity(&source[source_idx].content, &target[target_idx].content);
+                    (pos, similarity)
+                })
+                .filter(|&(_, similarity)| similarity >= self.config.rename_threshold)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((pos, _)) = best {
+                pairs.push((source_idx, open_targets.remove(pos)));
+            }
+        }
+
+        pairs.sort_unstable();
+        pairs
+    }
+
+    /// Indices of the pairs forming the longest run whose target order
+    /// follows the source order
+    pub(crate) fn longest_ordered_run(pairs: &[(usize, usize)]) -> HashSet<usize> {
+        // Longest increasing subsequence of target positions, O(n^2)
+        let mut length = vec![1; pairs.len()];
+        let mut previous = vec![None; pairs.len()];
+        for i in 0..pairs.len() {
+            for j in 0..i {
+                if pairs[j].1 < pairs[i].1 && length[j] + 1 > length[i] {
+                    length[i] = length[j] + 1;
+                    previous[i] = Some(j);
+                }
+            }
+        }
+
+        let mut run = HashSet::new();
+        let mut current = (0..pairs.len()).max_by_key(|&i| (length[i], std::cmp::Reverse(i)));
+        while let Some(i) = current {
+            run.insert(i);
+            current = previous[i];
+        }
+        run
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smart_diff_parser::{tree_sitter::TreeSitterParser, Language, Parser};
+
+    fn sections(markdown: &str) -> Vec<DocumentSection> {
+        let parser = TreeSitterParser::new().unwrap();
+        let result = parser.parse(markdown, Language::Markdown).unwrap();
+        extract_sections(&result.ast, markdown)
+    }
+
+    #[test]
+    fn test_extract_sections_builds_heading_hierarchy() {
+        let doc = sections(
+            "# Guide\n\nIntro.\n\n## Install\n\nRun make.\n\n```sh\n# not a heading\n```\n\n### Linux\n\nUse apt.\n\nUsage\n-----\n\nCall it.\n",
+        );
+
+        let paths: Vec<String> = doc.iter().map(DocumentSection::path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "Guide",
+                "Guide > Install",
+                "Guide > Install > Linux",
+                "Guide > Usage"
+            ]
+        );
+        assert_eq!(doc[0].content, "Intro.");
+        assert!(doc[1].content.contains("# not a heading"));
+        assert_eq!((doc[1].start_line, doc[1].end_line), (5, 16));
+        assert_eq!(doc[3].level, 2);
+        assert_eq!(doc[3].content, "Call it.");
+    }
+
+    #[test]
+    fn test_moved_and_edited_sections() {
+        let source = sections(
+            "# Guide\n\n## Install\n\nRun make install.\n\n## Usage\n\nCall the binary.\n\n## FAQ\n\nAsk on the forum.\n",
+        );
+        // FAQ moved to the front, Usage edited
+        let target = sections(
+            "# Guide\n\n## FAQ\n\nAsk on the forum.\n\n## Install\n\nRun make install.\n\n## Usage\n\nCall the binary with --help.\n",
+        );
+
+        let result = SectionMatcher::default().match_sections(&source, &target, "old.md", "new.md");
+
+        let changes: Vec<(&ChangeType, &str)> = result
+            .changes
+            .iter()
+            .map(|c| (&c.change_type, c.source.as_ref().unwrap().name.as_str()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![(&ChangeType::Modify, "Usage"), (&ChangeType::Move, "FAQ")]
+        );
+        assert_eq!(result.mapping.len(), 4);
+        assert_eq!(
+            result.changes[1].target.as_ref().unwrap().element_type,
+            ElementType::Section
+        );
+    }
+
+    #[test]
+    fn test_renamed_and_removed_sections() {
+        let source = sections(
+            "## Setup\n\nInstall the toolchain and run the build.\n\n## Legacy\n\nOld notes.\n",
+        );
+        let target = sections("## Getting started\n\nInstall the toolchain and run the build.\n\n## Changelog\n\nNew.\n");
+
+        let result = SectionMatcher::default().match_sections(&source, &target, "old.md", "new.md");
+        let kinds: Vec<&ChangeType> = result.changes.iter().map(|c| &c.change_type).collect();
+        assert_eq!(
+            kinds,
+            vec![&ChangeType::Rename, &ChangeType::Delete, &ChangeType::Add]
+        );
+    }
+}
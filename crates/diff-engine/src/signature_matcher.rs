@@ -0,0 +1,296 @@
+//! Signature-only comparison of functions
+//!
+//! Functions are compared by their declarations alone: name, parameters,
+//! return type, visibility and modifiers. A function whose body changed but
+//! whose declaration did not is unchanged, which narrows a diff down to API
+//! changes.
+
+use smart_diff_parser::{Change, ChangeType, CodeElement, ElementType, Language, MatchResult};
+use smart_diff_semantic::{EnhancedFunctionSignature, FunctionSignatureExtractor};
+use std::collections::HashSet;
+
+/// Declaration of a function as written in a change, e.g. `public static int add(int, int)`
+pub fn signature_declaration(signature: &EnhancedFunctionSignature) -> String {
+    let parameters: Vec<String> = signature
+        .parameters
+        .iter()
+        .map(|parameter| parameter.param_type.to_string())
+        .collect();
+    let mut declaration = format!("{:?}", signature.visibility).to_lowercase();
+    for modifier in &signature.modifiers {
+        if !modifier.is_empty() && *modifier != declaration {
+            declaration.push(' ');
+            declaration.push_str(modifier);
+        }
+    }
+    let return_type = signature.return_type.to_string();
+    if !return_type.is_empty() {
+        declaration.push(' ');
+        declaration.push_str(&return_type);
+    }
+    format!(
+        "{} {}({})",
+        declaration,
+        signature.qualified_name,
+        parameters.join(", ")
+    )
+}
+
+/// Code element describing a function signature in a change
+pub fn signature_element(signature: &EnhancedFunctionSignature, file_path: &str) -> CodeElement {
+    CodeElement {
+        id: format!("{}:{}", signature.qualified_name, signature.line),
+        element_type: ElementType::Function,
+        name: signature.name.clone(),
+        file_path: file_path.to_string(),
+        start_line: signature.line,
+        end_line: signature.end_line,
+        signature: Some(signature_declaration(signature)),
+        hash: signature.signature_hash.clone(),
+    }
+}
+
+/// Matches the function signatures of two versions of a file
+pub struct SignatureMatcher {
+    extractor: FunctionSignatureExtractor,
+}
+
+impl Default for SignatureMatcher {
+    fn default() -> Self {
+        Self::new(Language::Unknown)
+    }
+}
+
+impl SignatureMatcher {
+    pub fn new(language: Language) -> Self {
+        Self {
+            extractor: FunctionSignatureExtractor::with_defaults(language),
+        }
+    }
+
+    /// Match signatures by qualified name and parameter types, then by qualified name
+    ///
+    /// Paired signatures that differ are reported as modifications naming the
+    /// parts of the declaration that changed; unpaired ones as additions and
+    /// deletions.
+    pub fn match_signatures(
+        &self,
+        source: &[EnhancedFunctionSignature],
+        target: &[EnhancedFunctionSignature],
+        source_file: &str,
+        target_file: &str,
+    ) -> MatchResult {
+        let mut result = MatchResult::new();
+        if source.is_empty() && target.is_empty() {
+            result.similarity = 1.0;
+            return result;
+        }
+
+        let mut matched_sources = HashSet::new();
+        let mut matched_targets = HashSet::new();
+        let mut pairs = Vec::new();
+        // Overloads are paired by parameter types first, so that changing one
+        // overload does not shift the pairing of the others
+        for same_parameters in [true, false] {
+            for (source_idx, old) in source.iter().enumerate() {
+                if matched_sources.contains(&source_idx) {
+                    continue;
+                }
+                let found = target.iter().enumerate().find(|(target_idx, new)| {
+                    !matched_targets.contains(target_idx)
+                        && old.qualified_name == new.qualified_name
+                        && (!same_parameters
+                            || Self::parameter_types(old) == Self::parameter_types(new))
+                });
+                if let Some((target_idx, _)) = found {
+                    matched_sources.insert(source_idx);
+                    matched_targets.insert(target_idx);
+                    pairs.push((source_idx, target_idx));
+                }
+            }
+        }
+        pairs.sort_unstable();
+
+        for (source_idx, target_idx) in pairs {
+            let (old, new) = (&source[source_idx], &target[target_idx]);
+            let (old_element, new_element) = (
+                signature_element(old, source_file),
+                signature_element(new, target_file),
+            );
+            result
+                .mapping
+                .insert(old_element.id.clone(), new_element.id.clone());
+
+            let differences = Self::differences(old, new);
+            if differences.is_empty() {
+                continue;
+            }
+            let similarity = self
+                .extractor
+                .calculate_similarity(old, new)
+                .overall_similarity;
+            let mut change = Change::new(
+                ChangeType::Modify,
+                format!(
+                    "Signature of '{}' changed: {}",
+                    new.qualified_name,
+                    differences.join(", ")
+                ),
+            )
+            .with_elements(Some(old_element), Some(new_element));
+            change.confidence = similarity;
+            change.details.similarity_score = Some(similarity);
+            result.changes.push(change);
+        }
+
+        for (source_idx, signature) in source.iter().enumerate() {
+            if !matched_sources.contains(&source_idx) {
+                let element = signature_element(signature, source_file);
+                result.unmatched_source.push(element.id.clone());
+                result.changes.push(
+                    Change::new(
+                        ChangeType::Delete,
+                        format!("Function '{}' deleted", signature.qualified_name),
+                    )
+                    .with_elements(Some(element), None),
+                );
+            }
+        }
+        for (target_idx, signature) in target.iter().enumerate() {
+            if !matched_targets.contains(&target_idx) {
+                let element = signature_element(signature, target_file);
+                result.unmatched_target.push(element.id.clone());
+                result.changes.push(
+                    Change::new(
+                        ChangeType::Add,
+                        format!("Function '{}' added", signature.qualified_name),
+                    )
+                    .with_elements(None, Some(element)),
+                );
+            }
+        }
+
+        result.calculate_similarity();
+        result
+    }
+
+    fn parameter_types(signature: &EnhancedFunctionSignature) -> Vec<String> {
+        signature
+            .parameters
+            .iter()
+            .map(|parameter| parameter.param_type.to_string())
+            .collect()
+    }
+
+    /// Parts of the declaration that differ, e.g. `return type int -> long`
+    fn differences(
+        old: &EnhancedFunctionSignature,
+        new: &EnhancedFunctionSignature,
+    ) -> Vec<String> {
+        let mut differences = Vec::new();
+        let (old_params, new_params) = (Self::parameter_types(old), Self::parameter_types(new));
+        if old_params != new_params {
+            differences.push(format!(
+                "parameters ({}) -> ({})",
+                old_params.join(", "),
+                new_params.join(", ")
+            ));
+        }
+        if old.return_type != new.return_type {
+            differences.push(format!(
+                "return type {} -> {}",
+                old.return_type, new.return_type
+            ));
+        }
+        if old.visibility != new.visibility {
+            differences.push(format!(
+                "visibility {:?} -> {:?}",
+                old.visibility, new.visibility
+            ));
+        }
+        let (mut old_modifiers, mut new_modifiers) = (old.modifiers.clone(), new.modifiers.clone());
+        old_modifiers.sort();
+        new_modifiers.sort();
+        if old_modifiers != new_modifiers {
+            differences.push(format!(
+                "modifiers [{}] -> [{}]",
+                old_modifiers.join(" "),
+                new_modifiers.join(" ")
+            ));
+        }
+        differences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smart_diff_parser::{tree_sitter::TreeSitterParser, Parser};
+
+    fn signatures(code: &str) -> Vec<EnhancedFunctionSignature> {
+        let parser = TreeSitterParser::new().unwrap();
+        let result = parser.parse(code, Language::Java).unwrap();
+        FunctionSignatureExtractor::with_defaults(Language::Java)
+            .extract_signatures("Calc.java", &result)
+            .unwrap()
+            .signatures
+    }
+
+    #[test]
+    fn test_body_changes_are_ignored() {
+        let source = signatures(
+            "class Calc {\n    public int add(int a, int b) {\n        return a + b;\n    }\n}\n",
+        );
+        let target = signatures(
+            "class Calc {\n    public int add(int a, int b) {\n        int sum = a + b;\n        log(sum);\n        return sum;\n    }\n}\n",
+        );
+
+        let result = SignatureMatcher::new(Language::Java).match_signatures(
+            &source,
+            &target,
+            "old/Calc.java",
+            "new/Calc.java",
+        );
+        assert!(result.changes.is_empty(), "{:?}", result.changes);
+        assert_eq!(result.mapping.len(), 1);
+    }
+
+    #[test]
+    fn test_return_type_and_modifier_changes() {
+        let source = signatures(
+            "class Calc {\n    public int add(int a, int b) {\n        return a + b;\n    }\n    private void reset() {}\n}\n",
+        );
+        let target = signatures(
+            "class Calc {\n    public long add(int a, int b) {\n        return a + b;\n    }\n    public static void reset() {}\n    void clear() {}\n}\n",
+        );
+
+        let result = SignatureMatcher::new(Language::Java).match_signatures(
+            &source,
+            &target,
+            "old/Calc.java",
+            "new/Calc.java",
+        );
+        let changes: Vec<(&ChangeType, &str)> = result
+            .changes
+            .iter()
+            .map(|c| (&c.change_type, c.details.description.as_str()))
+            .collect();
+        assert_eq!(changes.len(), 3, "{:?}", changes);
+        assert_eq!(changes[0].0, &ChangeType::Modify);
+        assert!(changes[0].1.contains("return type int -> long"));
+        assert!(changes[1].1.contains("visibility Private -> Public"));
+        assert!(changes[1]
+            .1
+            .contains("modifiers [private] -> [public static]"));
+        assert_eq!(changes[2].0, &ChangeType::Add);
+        assert_eq!(
+            result.changes[0]
+                .target
+                .as_ref()
+                .unwrap()
+                .signature
+                .as_deref(),
+            Some("public long Calc.add(int, int)")
+        );
+    }
+}
@@ -5,7 +5,8 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use smart_diff_parser::{ASTNode, Language, NodeType};
 use smart_diff_semantic::{
-    EnhancedFunctionSignature, FunctionSignatureExtractor, FunctionSignatureSimilarity,
+    EnhancedFunctionSignature, FunctionSignatureExtractor, FunctionSignatureSimilarity, ScopeType,
+    SimilarityBreakdown, SymbolKind, SymbolTable,
 };
 use std::collections::{HashMap, HashSet};
 
@@ -30,6 +31,8 @@ pub struct SimilarityScoringConfig {
     pub max_ast_depth: usize,
     /// Edit distance costs for AST comparison
     pub edit_costs: EditCost,
+    /// Canonicalize local variable names before comparing bodies (default: true)
+    pub enable_alpha_renaming: bool,
 }
 
 impl Default for SimilarityScoringConfig {
@@ -44,6 +47,7 @@ impl Default for SimilarityScoringConfig {
             enable_cross_language: false,
             max_ast_depth: 10,
             edit_costs: EditCost::default(),
+            enable_alpha_renaming: true,
         }
     }
 }
@@ -56,6 +60,8 @@ pub struct SimilarityScorer {
     signature_extractor: FunctionSignatureExtractor,
     tree_edit_calculator: TreeEditDistance,
     context_cache: HashMap<String, ContextInfo>,
+    source_symbols: Option<SymbolTable>,
+    target_symbols: Option<SymbolTable>,
 }
 
 /// Comprehensive similarity score with detailed breakdown
@@ -212,6 +218,8 @@ impl SimilarityScorer {
             signature_extractor,
             tree_edit_calculator,
             context_cache: HashMap::new(),
+            source_symbols: None,
+            target_symbols: None,
         }
     }
 
@@ -219,6 +227,52 @@ impl SimilarityScorer {
         Self::new(language, SimilarityScoringConfig::default())
     }
 
+    /// Provide symbol tables used to tell local variables apart from calls and types
+    ///
+    /// `source` describes the first function passed to
+    /// [`calculate_comprehensive_similarity`](Self::calculate_comprehensive_similarity),
+    /// `target` the second.
+    pub fn set_symbol_tables(&mut self, source: SymbolTable, target: SymbolTable) {
+        self.source_symbols = Some(source);
+        self.target_symbols = Some(target);
+        self.context_cache.clear();
+    }
+
+    /// Whether two functions are the same once their locals are read as one
+    ///
+    /// Locals are told apart with the symbol tables set with
+    /// [`set_symbol_tables`](Self::set_symbol_tables). Unlike the similarity
+    /// scores, the text of every token counts, literals included.
+    pub fn same_up_to_local_renaming(
+        &self,
+        func1_signature: &EnhancedFunctionSignature,
+        func1_ast: &ASTNode,
+        func2_signature: &EnhancedFunctionSignature,
+        func2_ast: &ASTNode,
+    ) -> bool {
+        let locals1 = Self::local_names(func1_signature, self.source_symbols.as_ref());
+        let locals2 = Self::local_names(func2_signature, self.target_symbols.as_ref());
+        Self::same_tokens(
+            &Self::alpha_normalize(func1_ast, &locals1),
+            &Self::alpha_normalize(func2_ast, &locals2),
+        )
+    }
+
+    /// Whether two trees have the same shape and the same leaf text
+    fn same_tokens(node1: &ASTNode, node2: &ASTNode) -> bool {
+        if node1.node_type != node2.node_type || node1.children.len() != node2.children.len() {
+            return false;
+        }
+        if node1.children.is_empty() {
+            return node1.metadata.attributes.get("text") == node2.metadata.attributes.get("text");
+        }
+        node1
+            .children
+            .iter()
+            .zip(&node2.children)
+            .all(|(child1, child2)| Self::same_tokens(child1, child2))
+    }
+
     /// Calculate comprehensive similarity between two functions
     pub fn calculate_comprehensive_similarity(
         &mut self,
@@ -227,6 +281,20 @@ impl SimilarityScorer {
         func2_signature: &EnhancedFunctionSignature,
         func2_ast: &ASTNode,
     ) -> Result<ComprehensiveSimilarityScore> {
+        // Consistently renamed locals should not count as a body change
+        let normalized;
+        let (func1_ast, func2_ast) = if self.config.enable_alpha_renaming {
+            let locals1 = Self::local_names(func1_signature, self.source_symbols.as_ref());
+            let locals2 = Self::local_names(func2_signature, self.target_symbols.as_ref());
+            normalized = (
+                Self::alpha_normalize(func1_ast, &locals1),
+                Self::alpha_normalize(func2_ast, &locals2),
+            );
+            (&normalized.0, &normalized.1)
+        } else {
+            (func1_ast, func2_ast)
+        };
+
         // Calculate signature similarity
         let signature_similarity = self
             .signature_extractor
@@ -265,9 +333,7 @@ impl SimilarityScorer {
 
         // Build detailed breakdown
         let similarity_breakdown = self.build_detailed_breakdown(
-            func1_signature,
             func1_ast,
-            func2_signature,
             func2_ast,
             &signature_similarity,
             &body_similarity,
@@ -286,6 +352,181 @@ impl SimilarityScorer {
         })
     }
 
+    /// Calculate similarity between two arbitrary AST subtrees
+    ///
+    /// Scores fragments smaller than a function, such as two statements or two
+    /// expressions. Fragments have no signature or enclosing scope, so the
+    /// signature components are zero, the context score covers only calls,
+    /// variables and dependencies, and the overall similarity is the body
+    /// similarity. Subtrees of different node types score low instead of failing.
+    pub fn score_subtrees(
+        &self,
+        subtree1: &ASTNode,
+        subtree2: &ASTNode,
+    ) -> Result<ComprehensiveSimilarityScore> {
+        let signature_similarity = FunctionSignatureSimilarity {
+            overall_similarity: 0.0,
+            name_similarity: 0.0,
+            parameter_similarity: 0.0,
+            return_type_similarity: 0.0,
+            modifier_similarity: 0.0,
+            complexity_similarity: 0.0,
+            is_potential_match: false,
+            similarity_breakdown: SimilarityBreakdown {
+                exact_name_match: false,
+                parameter_count_match: false,
+                parameter_types_match: Vec::new(),
+                return_type_match: false,
+                visibility_match: false,
+                static_match: false,
+                generic_parameters_match: false,
+            },
+        };
+
+        let body_similarity = self.calculate_ast_similarity(subtree1, subtree2)?;
+
+        let context1 = self.extract_context_info(subtree1);
+        let context2 = self.extract_context_info(subtree2);
+        let function_call_similarity =
+            self.calculate_set_similarity(&context1.function_calls, &context2.function_calls);
+        let variable_usage_similarity =
+            self.calculate_set_similarity(&context1.variable_names, &context2.variable_names);
+        let dependency_similarity =
+            self.calculate_set_similarity(&context1.dependencies, &context2.dependencies);
+        let context_similarity = ContextSimilarityScore {
+            overall_similarity: (function_call_similarity * 0.3
+                + variable_usage_similarity * 0.2
+                + dependency_similarity * 0.2)
+                / 0.7,
+            function_call_similarity,
+            variable_usage_similarity,
+            dependency_similarity,
+            surrounding_code_similarity: 0.0,
+            namespace_context_similarity: 0.0,
+        };
+
+        let semantic_metrics = self.calculate_semantic_metrics(subtree1, subtree2)?;
+
+        let overall_similarity = body_similarity.overall_similarity;
+        let match_type =
+            self.classify_match_type(&signature_similarity, &body_similarity, overall_similarity);
+
+        // Without a signature, confidence rests on the body and its context
+        let structural_penalty = if body_similarity.structural_similarity < 0.3 {
+            -0.1
+        } else {
+            0.0
+        };
+        let confidence = (body_similarity.overall_similarity * 0.8
+            + context_similarity.overall_similarity * 0.2
+            + structural_penalty)
+            .clamp(0.0, 1.0);
+
+        let similarity_breakdown = self.build_detailed_breakdown(
+            subtree1,
+            subtree2,
+            &signature_similarity,
+            &body_similarity,
+            &context_similarity,
+        )?;
+
+        Ok(ComprehensiveSimilarityScore {
+            overall_similarity,
+            signature_similarity,
+            body_similarity,
+            context_similarity,
+            semantic_metrics,
+            confidence,
+            match_type,
+            similarity_breakdown,
+        })
+    }
+
+    /// Names of parameters and local variables declared inside a function
+    ///
+    /// Parameters come from the signature; locals come from function and block
+    /// scopes of the symbol table that lie within the function's line range.
+    /// Functions, types and fields are never included, so calls and type
+    /// references keep their names.
+    fn local_names(
+        signature: &EnhancedFunctionSignature,
+        symbols: Option<&SymbolTable>,
+    ) -> HashSet<String> {
+        let mut locals: HashSet<String> = signature
+            .parameters
+            .iter()
+            .map(|p| p.name.clone())
+            .collect();
+
+        if let Some(symbols) = symbols {
+            let end_line = signature.end_line.max(signature.line);
+            let scopes = symbols.scoped_symbols.values().filter(|scope| {
+                matches!(scope.scope_type, ScopeType::Function | ScopeType::Block)
+                    && scope.file_path == signature.file_path
+                    && scope.start_line >= signature.line
+                    && scope.end_line <= end_line
+            });
+
+            for scope in scopes {
+                locals.extend(
+                    scope
+                        .symbols
+                        .values()
+                        .filter(|symbol| {
+                            matches!(
+                                symbol.symbol_kind,
+                                SymbolKind::Variable | SymbolKind::Parameter
+                            )
+                        })
+                        .map(|symbol| symbol.name.clone()),
+                );
+            }
+        }
+
+        locals
+    }
+
+    /// Rename local identifiers to canonical names in order of first appearance
+    ///
+    /// Two bodies that differ only by a consistent renaming of locals normalize
+    /// to the same tree, while an inconsistent renaming still differs.
+    fn alpha_normalize(ast: &ASTNode, locals: &HashSet<String>) -> ASTNode {
+        let mut normalized = ast.clone();
+        if !locals.is_empty() {
+            let mut canonical_names = HashMap::new();
+            Self::alpha_normalize_recursive(&mut normalized, locals, &mut canonical_names);
+        }
+        normalized
+    }
+
+    fn alpha_normalize_recursive(
+        node: &mut ASTNode,
+        locals: &HashSet<String>,
+        canonical_names: &mut HashMap<String, String>,
+    ) {
+        if node.node_type == NodeType::Identifier {
+            for key in ["identifier", "name", "text"] {
+                let Some(name) = node.metadata.attributes.get(key) else {
+                    continue;
+                };
+                if !locals.contains(name) {
+                    continue;
+                }
+
+                let next_index = canonical_names.len();
+                let canonical = canonical_names
+                    .entry(name.clone())
+                    .or_insert_with(|| format!("$local{}", next_index))
+                    .clone();
+                node.metadata.attributes.insert(key.to_string(), canonical);
+            }
+        }
+
+        for child in &mut node.children {
+            Self::alpha_normalize_recursive(child, locals, canonical_names);
+        }
+    }
+
     /// Calculate advanced AST similarity with structural analysis
     fn calculate_ast_similarity(
         &self,
@@ -440,8 +681,13 @@ impl SimilarityScorer {
     /// Recursively extract content features
     #[allow(clippy::only_used_in_recursion)]
     fn extract_content_features_recursive(&self, ast: &ASTNode, features: &mut HashSet<String>) {
-        // Extract identifiers
-        if let Some(identifier) = ast.metadata.attributes.get("identifier") {
+        // Extract identifiers, which parsed code only records as node text
+        let identifier = ast.metadata.attributes.get("identifier").or_else(|| {
+            (ast.node_type == NodeType::Identifier)
+                .then(|| ast.metadata.attributes.get("text"))
+                .flatten()
+        });
+        if let Some(identifier) = identifier {
             features.insert(format!("id:{}", identifier));
         }
 
@@ -1164,12 +1410,9 @@ impl SimilarityScorer {
     }
 
     /// Build detailed similarity breakdown
-    #[allow(clippy::too_many_arguments)]
     fn build_detailed_breakdown(
         &self,
-        _func1_signature: &EnhancedFunctionSignature,
         func1_ast: &ASTNode,
-        _func2_signature: &EnhancedFunctionSignature,
         func2_ast: &ASTNode,
         signature_similarity: &FunctionSignatureSimilarity,
         body_similarity: &ASTSimilarityScore,
@@ -1418,6 +1661,7 @@ mod tests {
         assert_eq!(config.context_weight, 0.2);
         assert_eq!(config.match_threshold, 0.7);
         assert!(config.enable_advanced_ast_comparison);
+        assert!(config.enable_alpha_renaming);
         assert!(config.enable_semantic_context);
         assert!(!config.enable_cross_language);
         assert_eq!(config.max_ast_depth, 10);
@@ -1666,4 +1910,220 @@ mod tests {
         let (size, _) = scorer.get_cache_stats();
         assert_eq!(size, 0);
     }
+
+    /// `for (var = 0; var < limit; ...) { callee(var, items); }` with `var` local
+    fn loop_function_ast(var: &str, callee: &str) -> ASTNode {
+        let identifier = |name: &str| {
+            create_test_ast_node(
+                NodeType::Identifier,
+                HashMap::from([
+                    ("identifier".to_string(), name.to_string()),
+                    ("name".to_string(), name.to_string()),
+                ]),
+            )
+        };
+        let with_children = |mut node: ASTNode, children: Vec<ASTNode>| {
+            node.children = children;
+            node
+        };
+
+        let condition = with_children(
+            create_test_ast_node(
+                NodeType::BinaryExpression,
+                HashMap::from([("operator".to_string(), "<".to_string())]),
+            ),
+            vec![identifier(var), identifier("limit")],
+        );
+        let call = with_children(
+            create_test_ast_node(
+                NodeType::CallExpression,
+                HashMap::from([("function_name".to_string(), callee.to_string())]),
+            ),
+            vec![identifier(var), identifier("items")],
+        );
+        let body = with_children(
+            create_test_ast_node(NodeType::Block, HashMap::new()),
+            vec![call],
+        );
+        let for_loop = with_children(
+            create_test_ast_node(NodeType::ForLoop, HashMap::new()),
+            vec![identifier(var), condition, body],
+        );
+
+        with_children(
+            create_test_ast_node(NodeType::Function, HashMap::new()),
+            vec![for_loop],
+        )
+    }
+
+    fn symbols_with_local(name: &str) -> SymbolTable {
+        use smart_diff_semantic::Symbol;
+
+        let mut symbols = SymbolTable::new();
+        let scope = symbols.create_scope(None, ScopeType::Function, "test.java".to_string(), 1, 10);
+        symbols.add_symbol(Symbol::new(
+            name.to_string(),
+            SymbolKind::Variable,
+            "test.java".to_string(),
+            2,
+            14,
+            scope,
+        ));
+        symbols
+    }
+
+    fn loop_similarity(target_var: &str, target_callee: &str) -> ComprehensiveSimilarityScore {
+        let mut scorer = SimilarityScorer::with_defaults(Language::Java);
+        scorer.set_symbol_tables(symbols_with_local("i"), symbols_with_local(target_var));
+
+        scorer
+            .calculate_comprehensive_similarity(
+                &create_test_function_signature("run", "v1::Worker.run"),
+                &loop_function_ast("i", "process"),
+                &create_test_function_signature("run", "v2::Worker.run"),
+                &loop_function_ast(target_var, target_callee),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_score_subtrees() {
+        let scorer = SimilarityScorer::with_defaults(Language::Java);
+        let condition = |left: &str, right: &str| {
+            let mut node = create_test_ast_node(
+                NodeType::BinaryExpression,
+                HashMap::from([("operator".to_string(), "<".to_string())]),
+            );
+            node.children = [left, right]
+                .into_iter()
+                .map(|name| {
+                    create_test_ast_node(
+                        NodeType::Identifier,
+                        HashMap::from([("name".to_string(), name.to_string())]),
+                    )
+                })
+                .collect();
+            node
+        };
+
+        let same = scorer
+            .score_subtrees(&condition("i", "limit"), &condition("i", "limit"))
+            .unwrap();
+        assert!(same.overall_similarity > 0.9, "{}", same.overall_similarity);
+        assert_eq!(same.body_similarity.structural_similarity, 1.0);
+
+        // An expression against an unrelated loop of a different node type
+        let unrelated = scorer
+            .score_subtrees(&condition("i", "limit"), &loop_function_ast("j", "process"))
+            .unwrap();
+        assert!(
+            unrelated.overall_similarity < 0.5,
+            "{}",
+            unrelated.overall_similarity
+        );
+        assert_eq!(unrelated.body_similarity.structural_similarity, 0.0);
+        assert_eq!(unrelated.match_type, MatchType::NoMatch);
+    }
+
+    #[test]
+    fn test_consistent_local_rename_scores_as_identical() {
+        let unchanged = loop_similarity("i", "process");
+        let renamed = loop_similarity("index", "process");
+
+        assert!(renamed.overall_similarity > 0.9);
+        assert!((renamed.overall_similarity - unchanged.overall_similarity).abs() < 1e-9);
+        assert_eq!(renamed.body_similarity.content_similarity, 1.0);
+        assert_eq!(renamed.context_similarity.variable_usage_similarity, 1.0);
+
+        // Without normalization the rename shows up as a content change
+        let mut scorer = SimilarityScorer::new(
+            Language::Java,
+            SimilarityScoringConfig {
+                enable_alpha_renaming: false,
+                ..Default::default()
+            },
+        );
+        let raw = scorer
+            .calculate_comprehensive_similarity(
+                &create_test_function_signature("run", "v1::Worker.run"),
+                &loop_function_ast("i", "process"),
+                &create_test_function_signature("run", "v2::Worker.run"),
+                &loop_function_ast("index", "process"),
+            )
+            .unwrap();
+        assert!(raw.body_similarity.content_similarity < 1.0);
+    }
+
+    #[test]
+    fn test_changed_call_target_is_not_renamed_away() {
+        let renamed = loop_similarity("index", "process");
+        let call_changed = loop_similarity("i", "handle");
+
+        assert!(call_changed.context_similarity.function_call_similarity < 1.0);
+        assert!(call_changed.overall_similarity < renamed.overall_similarity);
+    }
+
+    #[test]
+    fn test_parsed_local_rename_scores_as_identical() {
+        use crate::code_version::CodeVersion;
+        use smart_diff_parser::{tree_sitter::TreeSitterParser, Parser};
+
+        let version = |local: &str, callee: &str| {
+            let code = format!(
+                "class Worker {{\n    int run(int[] items) {{\n        int {l} = 0;\n        \
+                 for (int item : items) {{\n            {l} = {l} + {c}(item);\n        }}\n        \
+                 return {l};\n    }}\n}}\n",
+                l = local,
+                c = callee
+            );
+            let parsed = TreeSitterParser::new()
+                .unwrap()
+                .parse(&code, Language::Java)
+                .unwrap();
+            CodeVersion::from_file("Worker.java", &parsed).unwrap()
+        };
+        let score_with = |source: &CodeVersion, target: &CodeVersion, symbols: bool| {
+            let mut scorer = SimilarityScorer::with_defaults(Language::Java);
+            if symbols {
+                scorer.set_symbol_tables(source.symbols.clone(), target.symbols.clone());
+            }
+            let (source_signature, source_ast) = source.function_with_signature("run").unwrap();
+            let (target_signature, target_ast) = target.function_with_signature("run").unwrap();
+            scorer
+                .calculate_comprehensive_similarity(
+                    source_signature,
+                    source_ast,
+                    target_signature,
+                    target_ast,
+                )
+                .unwrap()
+        };
+
+        let original = version("total", "weight");
+        let renamed = score_with(&original, &version("sum", "weight"), true);
+        assert_eq!(renamed.body_similarity.content_similarity, 1.0);
+        assert_eq!(renamed.context_similarity.variable_usage_similarity, 1.0);
+
+        // Without the symbol tables only parameters are known to be local
+        let unresolved = score_with(&original, &version("sum", "weight"), false);
+        assert!(unresolved.body_similarity.content_similarity < 1.0);
+
+        let (source_signature, source_ast) = original.function_with_signature("run").unwrap();
+        let same = |target: &CodeVersion| {
+            let (target_signature, target_ast) = target.function_with_signature("run").unwrap();
+            let mut scorer = SimilarityScorer::with_defaults(Language::Java);
+            scorer.set_symbol_tables(original.symbols.clone(), target.symbols.clone());
+            scorer.same_up_to_local_renaming(
+                source_signature,
+                source_ast,
+                target_signature,
+                target_ast,
+            )
+        };
+        assert!(same(&version("sum", "weight")));
+        assert!(!same(&version("total", "price")));
+
+        let call_changed = score_with(&original, &version("total", "price"), true);
+        assert!(call_changed.overall_similarity < renamed.overall_similarity);
+    }
 }
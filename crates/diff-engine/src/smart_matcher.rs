@@ -4,8 +4,13 @@
 //! common real-world scenarios over theoretical optimality. It uses smart rules to
 //! handle same-named functions, simple functions, and cross-file moves.
 
-use smart_diff_parser::{Change, ChangeType, CodeElement, Function, MatchResult};
-use std::collections::HashSet;
+use crate::identifier::{identifier_words, normalize_identifier};
+use crate::token_similarity::{TokenSimilarity, TokenSimilarityConfig};
+use smart_diff_parser::{
+    Change, ChangeType, CodeElement, Function, FunctionSignature, MatchResult,
+};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
 /// Configuration for smart matching
 #[derive(Debug, Clone)]
@@ -14,8 +19,14 @@ pub struct SmartMatcherConfig {
     pub similarity_threshold: f64,
     /// Whether to enable cross-file matching
     pub enable_cross_file_matching: bool,
-    /// Penalty factor for cross-file matches (0.0 to 1.0)
-    pub cross_file_penalty: f64,
+    /// Penalty curve for cross-file matches, scaled by directory distance
+    pub cross_file_penalty_fn: CrossFilePenaltyCurve,
+    /// Whether to pair byte-identical function bodies before similarity scoring
+    pub enable_exact_match_prepass: bool,
+    /// Whether to compare names by their words, so `getUserName` matches `get_user_name`
+    pub normalize_identifiers: bool,
+    /// How function bodies are tokenized and compared
+    pub content_similarity: TokenSimilarityConfig,
 }
 
 impl Default for SmartMatcherConfig {
@@ -23,19 +34,100 @@ impl Default for SmartMatcherConfig {
         Self {
             similarity_threshold: 0.7,
             enable_cross_file_matching: true,
-            cross_file_penalty: 0.5,
+            cross_file_penalty_fn: CrossFilePenaltyCurve::default(),
+            enable_exact_match_prepass: true,
+            normalize_identifiers: false,
+            content_similarity: TokenSimilarityConfig::default(),
         }
     }
 }
 
+/// Cross-file penalty that grows with the directory distance between two files
+///
+/// The penalty (0.0 to 1.0) raises the matching threshold for a cross-file pair
+/// towards 1.0, so a function moved to a sibling file in the same directory is
+/// accepted more readily than one moved across the whole tree.
+#[derive(Debug, Clone)]
+pub struct CrossFilePenaltyCurve {
+    /// Penalty for a move between two files in the same directory
+    pub base_penalty: f64,
+    /// Additional penalty per directory level separating the two files
+    pub per_level_penalty: f64,
+    /// Upper bound on the penalty
+    pub max_penalty: f64,
+}
+
+impl Default for CrossFilePenaltyCurve {
+    fn default() -> Self {
+        Self {
+            base_penalty: 0.5,
+            per_level_penalty: 0.05,
+            max_penalty: 0.9,
+        }
+    }
+}
+
+impl CrossFilePenaltyCurve {
+    /// Penalty for matching a function in `source_path` to one in `target_path`
+    pub fn penalty(&self, source_path: &str, target_path: &str) -> f64 {
+        if source_path == target_path {
+            return 0.0;
+        }
+
+        let distance = Self::directory_distance(source_path, target_path) as f64;
+        (self.base_penalty + self.per_level_penalty * distance)
+            .min(self.max_penalty)
+            .clamp(0.0, 1.0)
+    }
+
+    /// Number of directory levels separating two files, from their shared prefix
+    ///
+    /// Files in the same directory have distance 0; `a/x.rs` and `b/y.rs` have
+    /// distance 2 (up one level from `a`, down one level into `b`).
+    pub fn directory_distance(source_path: &str, target_path: &str) -> usize {
+        let source_dirs: Vec<_> = Path::new(source_path)
+            .parent()
+            .map(|p| p.components().collect())
+            .unwrap_or_default();
+        let target_dirs: Vec<_> = Path::new(target_path)
+            .parent()
+            .map(|p| p.components().collect())
+            .unwrap_or_default();
+
+        let shared = source_dirs
+            .iter()
+            .zip(target_dirs.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        (source_dirs.len() - shared) + (target_dirs.len() - shared)
+    }
+}
+
+/// Result of the content-addressable exact-match pre-pass
+#[derive(Debug, Clone, Default)]
+pub struct ExactMatchPrepass {
+    /// Source index -> target index for functions with byte-identical bodies
+    pub pairs: HashMap<usize, usize>,
+    /// Source indices left for the similarity-scoring phase
+    pub remaining_source: Vec<usize>,
+    /// Target indices left for the similarity-scoring phase
+    pub remaining_target: Vec<usize>,
+}
+
 /// Smart function matcher using practical heuristics
 pub struct SmartMatcher {
     config: SmartMatcherConfig,
+    content_similarity: TokenSimilarity,
 }
 
 impl SmartMatcher {
     pub fn new(config: SmartMatcherConfig) -> Self {
-        Self { config }
+        let content_similarity = TokenSimilarity::new(config.content_similarity.clone());
+        Self {
+            config,
+            content_similarity,
+        }
     }
 
     /// Match functions between two sets using smart heuristics
@@ -51,39 +143,27 @@ impl SmartMatcher {
             return result;
         }
 
+        // Pair byte-identical functions up front so they skip similarity scoring
+        let prepass = if self.config.enable_exact_match_prepass {
+            self.exact_match_prepass(source_functions, target_functions)
+        } else {
+            ExactMatchPrepass {
+                pairs: HashMap::new(),
+                remaining_source: (0..source_functions.len()).collect(),
+                remaining_target: (0..target_functions.len()).collect(),
+            }
+        };
+
         // Track which target functions have been matched
-        let mut matched_targets = HashSet::new();
+        let mut matched_targets: HashSet<usize> = prepass.pairs.values().copied().collect();
 
         // First pass: Match functions greedily by best similarity
-        for source_func in source_functions {
-            let mut best_match: Option<(usize, f64)> = None;
-
-            for (target_idx, target_func) in target_functions.iter().enumerate() {
-                if matched_targets.contains(&target_idx) {
-                    continue;
-                }
-
-                let similarity = self.calculate_function_similarity(source_func, target_func);
-
-                // Apply cross-file penalty to matching threshold, not to similarity score
-                let same_file = source_func.location.file_path == target_func.location.file_path;
-                let matching_threshold = if !same_file && self.config.enable_cross_file_matching {
-                    // Require higher similarity for cross-file matches
-                    self.config.similarity_threshold.max(0.85)
-                } else {
-                    self.config.similarity_threshold
-                };
-
-                if similarity >= matching_threshold {
-                    if let Some((_, best_sim)) = best_match {
-                        if similarity > best_sim {
-                            best_match = Some((target_idx, similarity));
-                        }
-                    } else {
-                        best_match = Some((target_idx, similarity));
-                    }
-                }
-            }
+        for (source_idx, source_func) in source_functions.iter().enumerate() {
+            let best_match = if let Some(&target_idx) = prepass.pairs.get(&source_idx) {
+                Some((target_idx, 1.0))
+            } else {
+                self.find_best_match(source_func, target_functions, &matched_targets)
+            };
 
             if let Some((target_idx, similarity)) = best_match {
                 let target_func = &target_functions[target_idx];
@@ -94,8 +174,9 @@ impl SmartMatcher {
                     .mapping
                     .insert(source_func.hash.clone(), target_func.hash.clone());
 
-                // Create change record if not identical
-                if similarity < 1.0 {
+                // Create change record if not identical or if it crossed files
+                let same_file = source_func.location.file_path == target_func.location.file_path;
+                if similarity < 1.0 || !same_file {
                     let source_element = CodeElement::from_function(source_func);
                     let target_element = CodeElement::from_function(target_func);
 
@@ -156,10 +237,190 @@ impl SmartMatcher {
         result
     }
 
+    /// Pair functions whose bodies are byte-identical without scoring them
+    ///
+    /// Identical functions are paired within the same file first, so a helper that
+    /// exists in several files on both sides stays with its own file, and within a
+    /// file with the function of the same name if there is one. Whatever is left
+    /// is paired across files only when the content is unambiguous (exactly one
+    /// remaining source and one remaining target). Functions are grouped by their
+    /// whole text, so equal hashes alone never pair two functions.
+    pub fn exact_match_prepass(
+        &self,
+        source_functions: &[Function],
+        target_functions: &[Function],
+    ) -> ExactMatchPrepass {
+        let mut source_by_text: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (idx, func) in source_functions.iter().enumerate() {
+            if let Some(text) = Self::full_text(func) {
+                source_by_text.entry(text).or_default().push(idx);
+            }
+        }
+
+        let mut target_by_text: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (idx, func) in target_functions.iter().enumerate() {
+            if let Some(text) = Self::full_text(func) {
+                target_by_text.entry(text).or_default().push(idx);
+            }
+        }
+
+        let mut pairs = HashMap::new();
+
+        for (text, sources) in &source_by_text {
+            let Some(targets) = target_by_text.get(text) else {
+                continue;
+            };
+
+            let mut open_targets: Vec<usize> = targets.clone();
+            let mut open_sources = Vec::new();
+
+            // Same-file pairs take precedence over cross-file ones
+            for &source_idx in sources {
+                let source = &source_functions[source_idx];
+                let same_file = |&t: &usize| {
+                    target_functions[t].location.file_path == source.location.file_path
+                };
+                let same_name = open_targets.iter().position(|t| {
+                    same_file(t) && target_functions[*t].signature.name == source.signature.name
+                });
+                if let Some(pos) = same_name.or_else(|| open_targets.iter().position(same_file)) {
+                    pairs.insert(source_idx, open_targets.remove(pos));
+                } else {
+                    open_sources.push(source_idx);
+                }
+            }
+
+            // Only pair across files when there is a single candidate on each side
+            if self.config.enable_cross_file_matching
+                && open_sources.len() == 1
+                && open_targets.len() == 1
+            {
+                pairs.insert(open_sources[0], open_targets[0]);
+            }
+        }
+
+        let paired_targets: HashSet<usize> = pairs.values().copied().collect();
+
+        ExactMatchPrepass {
+            remaining_source: (0..source_functions.len())
+                .filter(|idx| !pairs.contains_key(idx))
+                .collect(),
+            remaining_target: (0..target_functions.len())
+                .filter(|idx| !paired_targets.contains(idx))
+                .collect(),
+            pairs,
+        }
+    }
+
+    /// Whole raw text of a function, if it has any
+    ///
+    /// Text shorter than the function's source span was cut short when the
+    /// AST was built, and cannot tell identical functions apart.
+    fn full_text(func: &Function) -> Option<&str> {
+        let text = func.body.metadata.original_text.as_str();
+        let complete = func.byte_span().is_none_or(|span| span.len() == text.len());
+        (!text.is_empty() && complete).then_some(text)
+    }
+
+    /// Find the best unmatched target for a source function by similarity
+    fn find_best_match(
+        &self,
+        source_func: &Function,
+        target_functions: &[Function],
+        matched_targets: &HashSet<usize>,
+    ) -> Option<(usize, f64)> {
+        // Overloads share a name, so a same-named target is only a candidate when
+        // no other open target of that name has a closer parameter list
+        let source_name = self.comparable_name(&source_func.signature);
+        let is_open_overload = |target_idx: usize, target_func: &Function| {
+            !matched_targets.contains(&target_idx)
+                && self.comparable_name(&target_func.signature) == source_name
+        };
+        let best_overload_rank = target_functions
+            .iter()
+            .enumerate()
+            .filter(|&(target_idx, target_func)| is_open_overload(target_idx, target_func))
+            .map(|(_, target_func)| Self::overload_rank(source_func, target_func))
+            .max();
+
+        // Candidates are ranked by how far they clear their own threshold, so an
+        // equally similar function closer in the directory tree wins
+        let mut best_match: Option<(usize, f64, f64)> = None;
+
+        for (target_idx, target_func) in target_functions.iter().enumerate() {
+            if matched_targets.contains(&target_idx) {
+                continue;
+            }
+            if is_open_overload(target_idx, target_func)
+                && Some(Self::overload_rank(source_func, target_func)) < best_overload_rank
+            {
+                continue;
+            }
+
+            let similarity = self.calculate_function_similarity(source_func, target_func);
+            let matching_threshold = self.matching_threshold(source_func, target_func);
+
+            if similarity >= matching_threshold {
+                let margin = similarity - matching_threshold;
+                match best_match {
+                    Some((_, _, best_margin)) if margin <= best_margin => {}
+                    _ => best_match = Some((target_idx, similarity, margin)),
+                }
+            }
+        }
+
+        best_match.map(|(target_idx, similarity, _)| (target_idx, similarity))
+    }
+
+    /// How closely two parameter lists agree: 2 for the same types, 1 for the
+    /// same arity, 0 otherwise
+    fn overload_rank(func1: &Function, func2: &Function) -> u8 {
+        let (params1, params2) = (&func1.signature.parameters, &func2.signature.parameters);
+        if params1.len() != params2.len() {
+            0
+        } else if params1
+            .iter()
+            .zip(params2)
+            .all(|(p1, p2)| p1.param_type.name == p2.param_type.name)
+        {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Similarity required to match two functions
+    ///
+    /// Cross-file pairs apply the penalty curve to the threshold, not to the
+    /// similarity score, which should keep reflecting actual code similarity.
+    pub fn matching_threshold(&self, source_func: &Function, target_func: &Function) -> f64 {
+        let threshold = self.config.similarity_threshold;
+        let same_file = source_func.location.file_path == target_func.location.file_path;
+
+        if same_file || !self.config.enable_cross_file_matching {
+            return threshold;
+        }
+
+        let penalty = self.config.cross_file_penalty_fn.penalty(
+            &source_func.location.file_path,
+            &target_func.location.file_path,
+        );
+        threshold + (1.0 - threshold) * penalty
+    }
+
     /// Calculate similarity between two functions using smart rules
     fn calculate_function_similarity(&self, func1: &Function, func2: &Function) -> f64 {
         let same_file = func1.location.file_path == func2.location.file_path;
-        let same_name = func1.signature.name == func2.signature.name;
+        let name1 = self.comparable_name(&func1.signature);
+        let name2 = self.comparable_name(&func2.signature);
+        let same_name = name1 == name2;
+
+        // Rule 0: Methods on different receiver types (Go) are never the same function
+        if let (Some(r1), Some(r2)) = (&func1.signature.receiver, &func2.signature.receiver) {
+            if r1 != r2 {
+                return 0.0;
+            }
+        }
 
         // Rule 1: Same-named functions in same file should always match
         if same_name && same_file {
@@ -185,7 +446,7 @@ impl SmartMatcher {
         let name_sim = if same_name {
             1.0
         } else {
-            self.string_similarity(&func1.signature.name, &func2.signature.name)
+            Self::name_similarity(&name1, &name2)
         };
 
         // Name similarity (30% weight)
@@ -246,6 +507,16 @@ impl SmartMatcher {
         }
     }
 
+    /// Qualified name used for name comparisons, normalized when configured
+    fn comparable_name(&self, signature: &FunctionSignature) -> String {
+        let name = signature.qualified_name();
+        if self.config.normalize_identifiers {
+            normalize_identifier(&name)
+        } else {
+            name
+        }
+    }
+
     /// Check if a function is "simple" (small body, likely a getter/setter/wrapper)
     fn is_simple_function(&self, func: &Function) -> bool {
         // Count non-empty nodes in the body
@@ -292,8 +563,9 @@ impl SmartMatcher {
         };
 
         // Content similarity - compare actual text content
-        let content_sim =
-            self.string_similarity(&body1.metadata.original_text, &body2.metadata.original_text);
+        let content_sim = self
+            .content_similarity
+            .similarity(&body1.metadata.original_text, &body2.metadata.original_text);
 
         // Weighted combination: structure (30%) + content (70%)
         // Content is more important to avoid matching structurally similar but semantically different code
@@ -314,26 +586,16 @@ impl SmartMatcher {
         }
     }
 
-    /// Simple string similarity using character overlap
-    fn string_similarity(&self, s1: &str, s2: &str) -> f64 {
-        if s1 == s2 {
-            return 1.0;
-        }
-        if s1.is_empty() || s2.is_empty() {
-            return 0.0;
-        }
-
-        let chars1: HashSet<char> = s1.chars().collect();
-        let chars2: HashSet<char> = s2.chars().collect();
-
-        let intersection = chars1.intersection(&chars2).count();
-        let union = chars1.union(&chars2).count();
-
-        if union == 0 {
-            0.0
-        } else {
-            intersection as f64 / union as f64
-        }
+    /// Name similarity by the words the two names share, in any order
+    fn name_similarity(name1: &str, name2: &str) -> f64 {
+        let words = TokenSimilarity::new(TokenSimilarityConfig {
+            ngram_size: 1,
+            ignore_case: true,
+        });
+        words.similarity(
+            &identifier_words(name1).join(" "),
+            &identifier_words(name2).join(" "),
+        )
     }
 
     /// Classify the type of change between two matched functions
@@ -344,11 +606,14 @@ impl SmartMatcher {
         similarity: f64,
     ) -> ChangeType {
         let same_file = func1.location.file_path == func2.location.file_path;
-        let same_name = func1.signature.name == func2.signature.name;
+        let same_name = func1.signature.qualified_name() == func2.signature.qualified_name();
+        // Only differs from `same_name` when identifiers are normalized
+        let same_words =
+            self.comparable_name(&func1.signature) == self.comparable_name(&func2.signature);
 
         if !same_file && same_name {
             ChangeType::CrossFileMove
-        } else if !same_name && similarity > 0.9 {
+        } else if !same_name && (same_words || similarity > 0.9) {
             ChangeType::Rename
         } else if !same_file {
             ChangeType::Move
@@ -368,3 +633,382 @@ impl SmartMatcher {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smart_diff_parser::{ASTNode, FunctionSignature, NodeMetadata, NodeType};
+    use std::collections::HashMap;
+
+    fn create_function(name: &str, file_path: &str, text: &str) -> Function {
+        let body = ASTNode::new(
+            NodeType::Function,
+            NodeMetadata {
+                line: 1,
+                column: 1,
+                original_text: text.to_string(),
+                attributes: HashMap::new(),
+            },
+        );
+        Function::new(
+            FunctionSignature::new(name.to_string()),
+            body,
+            file_path.to_string(),
+        )
+    }
+
+    fn create_method(receiver: &str, name: &str, file_path: &str, text: &str) -> Function {
+        let mut function = create_function(name, file_path, text);
+        function.signature.receiver = Some(receiver.to_string());
+        Function::new(function.signature, function.body, file_path.to_string())
+    }
+
+    #[test]
+    fn test_methods_only_match_on_same_receiver() {
+        let matcher = SmartMatcher::new(SmartMatcherConfig::default());
+
+        let source = vec![
+            create_method(
+                "Server",
+                "Close",
+                "server.go",
+                "func (s *Server) Close() error { return s.listener.Close() }",
+            ),
+            create_method(
+                "Client",
+                "Close",
+                "server.go",
+                "func (c *Client) Close() error { return c.conn.Close() }",
+            ),
+        ];
+
+        // Server.Close was removed: Client.Close must not be claimed by it
+        let target = vec![create_method(
+            "Client",
+            "Close",
+            "server.go",
+            "func (c *Client) Close() error { c.closed = true; return c.conn.Close() }",
+        )];
+
+        let result = matcher.match_functions(&source, &target);
+        assert_eq!(result.mapping.get(&source[1].hash), Some(&target[0].hash));
+        assert_eq!(result.unmatched_source, vec![source[0].hash.clone()]);
+
+        // Both modified and reordered: each method stays with its own receiver
+        let target = vec![
+            create_method(
+                "Client",
+                "Close",
+                "server.go",
+                "func (c *Client) Close() error { c.closed = true; return c.conn.Close() }",
+            ),
+            create_method(
+                "Server",
+                "Close",
+                "server.go",
+                "func (s *Server) Close() error { s.wg.Wait(); return s.listener.Close() }",
+            ),
+        ];
+
+        let result = matcher.match_functions(&source, &target);
+        assert_eq!(result.mapping.get(&source[0].hash), Some(&target[1].hash));
+        assert_eq!(result.mapping.get(&source[1].hash), Some(&target[0].hash));
+    }
+
+    #[test]
+    fn test_normalized_names_match_across_naming_conventions() {
+        let source = vec![create_function(
+            "getUserName",
+            "users.py",
+            "def getUserName(user):\n    return user.first + ' ' + user.last",
+        )];
+        let target = vec![create_function(
+            "get_user_name",
+            "users.py",
+            "def get_user_name(user):\n    return f'{user.first} {user.last}'",
+        )];
+
+        let strict = SmartMatcher::new(SmartMatcherConfig::default());
+        assert!(strict.match_functions(&source, &target).mapping.is_empty());
+
+        let matcher = SmartMatcher::new(SmartMatcherConfig {
+            normalize_identifiers: true,
+            ..SmartMatcherConfig::default()
+        });
+        let result = matcher.match_functions(&source, &target);
+        assert_eq!(result.mapping.get(&source[0].hash), Some(&target[0].hash));
+        assert_eq!(result.changes[0].change_type, ChangeType::Rename);
+    }
+
+    #[test]
+    fn test_exact_match_prepass_pairs_identical_functions() {
+        let matcher = SmartMatcher::new(SmartMatcherConfig::default());
+
+        let source = vec![
+            create_function("helper", "a.c", "int helper() { return 1; }"),
+            create_function("compute", "a.c", "int compute() { return 2; }"),
+        ];
+        let target = vec![
+            create_function("compute", "a.c", "int compute() { return 3; }"),
+            create_function("helper", "a.c", "int helper() { return 1; }"),
+        ];
+
+        let prepass = matcher.exact_match_prepass(&source, &target);
+
+        assert_eq!(prepass.pairs.get(&0), Some(&1));
+        assert_eq!(prepass.remaining_source, vec![1]);
+        assert_eq!(prepass.remaining_target, vec![0]);
+    }
+
+    #[test]
+    fn test_exact_match_prepass_compares_whole_bodies() {
+        use smart_diff_parser::{tree_sitter::TreeSitterParser, Language, Parser};
+
+        let parse = |code: &str| -> Vec<Function> {
+            let parsed = TreeSitterParser::new()
+                .unwrap()
+                .parse(code, Language::C)
+                .unwrap();
+            parsed
+                .ast
+                .find_by_type(&NodeType::Function)
+                .into_iter()
+                .filter(|node| {
+                    node.metadata.attributes.get("kind").map(String::as_str)
+                        == Some("function_definition")
+                })
+                .map(|node| {
+                    let name = node.metadata.attributes["name"].clone();
+                    Function::new(
+                        FunctionSignature::from_node(name, node),
+                        node.clone(),
+                        "totals.c".to_string(),
+                    )
+                })
+                .collect()
+        };
+        // Identical for well over the AST builder's 200 characters of node text
+        let totals = |result: &str| {
+            format!(
+                "int totals(int *values, int count)\n{{\n{}    return {};\n}}\n",
+                "    int sum = 0;\n    for (int i = 0; i < count; i++) {\n        \
+                 sum += values[i] * values[i] - values[i] / 2 + values[i] % 3;\n    }\n"
+                    .repeat(2),
+                result
+            )
+        };
+        let source = parse(&totals("sum"));
+        let target = parse(&totals("sum + 1"));
+        assert!(source[0].body.metadata.original_text.len() > 200);
+
+        let matcher = SmartMatcher::new(SmartMatcherConfig::default());
+        assert!(matcher
+            .exact_match_prepass(&source, &target)
+            .pairs
+            .is_empty());
+        let result = matcher.match_functions(&source, &target);
+        assert_eq!(result.changes.len(), 1, "{:#?}", result.changes);
+        assert_eq!(result.changes[0].change_type, ChangeType::Modify);
+
+        // An unchanged copy is still paired up front
+        let prepass = matcher.exact_match_prepass(&source, &parse(&totals("sum")));
+        assert_eq!(prepass.pairs.get(&0), Some(&0));
+    }
+
+    #[test]
+    fn test_exact_match_prepass_prefers_same_name_within_a_file() {
+        let matcher = SmartMatcher::new(SmartMatcherConfig::default());
+        let text = "{ return 0; }";
+
+        let source = vec![
+            create_function("reset", "a.c", text),
+            create_function("clear", "a.c", text),
+        ];
+        let target = vec![
+            create_function("clear", "a.c", text),
+            create_function("reset", "a.c", text),
+        ];
+
+        let prepass = matcher.exact_match_prepass(&source, &target);
+
+        assert_eq!(prepass.pairs.get(&0), Some(&1));
+        assert_eq!(prepass.pairs.get(&1), Some(&0));
+    }
+
+    #[test]
+    fn test_exact_match_prepass_respects_file_locality() {
+        let matcher = SmartMatcher::new(SmartMatcherConfig::default());
+        let text = "static int helper() { return 1; }";
+
+        let source = vec![
+            create_function("helper", "a.c", text),
+            create_function("helper", "b.c", text),
+        ];
+        let target = vec![
+            create_function("helper", "b.c", text),
+            create_function("helper", "a.c", text),
+        ];
+
+        let prepass = matcher.exact_match_prepass(&source, &target);
+
+        assert_eq!(prepass.pairs.get(&0), Some(&1));
+        assert_eq!(prepass.pairs.get(&1), Some(&0));
+        assert!(prepass.remaining_source.is_empty());
+        assert!(prepass.remaining_target.is_empty());
+    }
+
+    #[test]
+    fn test_exact_match_prepass_skips_ambiguous_cross_file_pairs() {
+        let matcher = SmartMatcher::new(SmartMatcherConfig::default());
+        let text = "static int helper() { return 1; }";
+
+        let source = vec![
+            create_function("helper", "a.c", text),
+            create_function("helper", "b.c", text),
+        ];
+        let target = vec![
+            create_function("helper", "c.c", text),
+            create_function("helper", "d.c", text),
+        ];
+
+        let prepass = matcher.exact_match_prepass(&source, &target);
+
+        assert!(prepass.pairs.is_empty());
+        assert_eq!(prepass.remaining_source, vec![0, 1]);
+        assert_eq!(prepass.remaining_target, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_identical_cross_file_function_reported_as_move() {
+        let matcher = SmartMatcher::new(SmartMatcherConfig::default());
+        let text = "int helper() { return 1; }";
+
+        let source = vec![create_function("helper", "old.c", text)];
+        let target = vec![create_function("helper", "new.c", text)];
+
+        let result = matcher.match_functions(&source, &target);
+
+        assert_eq!(result.mapping.len(), 1);
+        assert_eq!(result.changes.len(), 1);
+        assert_eq!(result.changes[0].change_type, ChangeType::CrossFileMove);
+        assert_eq!(result.changes[0].details.similarity_score, Some(1.0));
+    }
+
+    #[test]
+    fn test_directory_distance() {
+        assert_eq!(
+            CrossFilePenaltyCurve::directory_distance("src/a.rs", "src/b.rs"),
+            0
+        );
+        assert_eq!(
+            CrossFilePenaltyCurve::directory_distance("src/net/a.rs", "src/io/b.rs"),
+            2
+        );
+        assert_eq!(
+            CrossFilePenaltyCurve::directory_distance("a.rs", "lib/core/util/b.rs"),
+            3
+        );
+    }
+
+    #[test]
+    fn test_intra_directory_move_scores_higher_than_cross_package_move() {
+        let curve = CrossFilePenaltyCurve::default();
+
+        let intra = curve.penalty("src/net/client.rs", "src/net/transport.rs");
+        let cross = curve.penalty("src/net/client.rs", "tools/legacy/compat.rs");
+        assert!(intra < cross);
+        assert_eq!(curve.penalty("src/a.rs", "src/a.rs"), 0.0);
+
+        let body = "fn process(items: &[u32]) -> u32 { let mut total = 0; for item in items { total += item * 2; } total }";
+        let source = create_function("process", "src/net/client.rs", body);
+        let intra_target = create_function("process", "src/net/transport.rs", body);
+        let cross_target = create_function("process", "tools/legacy/compat.rs", body);
+
+        // With two equally similar candidates, the one in the same directory wins
+        let config = SmartMatcherConfig {
+            enable_exact_match_prepass: false,
+            ..SmartMatcherConfig::default()
+        };
+        let matcher = SmartMatcher::new(config);
+
+        assert!(
+            matcher.matching_threshold(&source, &intra_target)
+                < matcher.matching_threshold(&source, &cross_target)
+        );
+
+        let result = matcher.match_functions(
+            std::slice::from_ref(&source),
+            &[cross_target.clone(), intra_target.clone()],
+        );
+
+        let moved = result
+            .changes
+            .iter()
+            .find(|c| c.source.is_some() && c.target.is_some())
+            .expect("function should be matched");
+        assert_eq!(
+            moved.target.as_ref().unwrap().file_path,
+            "src/net/transport.rs"
+        );
+    }
+
+    fn parse_java_methods(code: &str) -> Vec<Function> {
+        use smart_diff_parser::{tree_sitter::TreeSitterParser, Language, Parser};
+
+        let parser = TreeSitterParser::new().unwrap();
+        let result = parser.parse(code, Language::Java).unwrap();
+        result
+            .ast
+            .find_by_type(&NodeType::Method)
+            .into_iter()
+            .map(|node| {
+                let name = node.metadata.attributes.get("name").cloned().unwrap();
+                let signature = FunctionSignature::from_node(name, node);
+                Function::new(signature, node.clone(), "Worker.java".to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_overloads_match_same_arity_counterparts() {
+        let source = parse_java_methods(
+            r#"class Worker {
+    int process(int count) {
+        int total = count * 2;
+        return total + 1;
+    }
+
+    int process(String label, int count) {
+        int total = count * 2;
+        System.out.println(label + total);
+        return total + 1;
+    }
+}"#,
+        );
+        // The one-argument overload now logs like the two-argument one used to,
+        // and the two-argument overload stopped logging
+        let target = parse_java_methods(
+            r#"class Worker {
+    int process(int count) {
+        int total = count * 2;
+        System.out.println("count" + total);
+        return total + 1;
+    }
+
+    int process(String label, int count) {
+        int total = count * 2;
+        return total + 2;
+    }
+}"#,
+        );
+        assert_eq!(source.len(), 2);
+        assert_eq!(target.len(), 2);
+        assert_eq!(source[1].signature.parameters.len(), 2);
+        assert_eq!(target[1].signature.parameters[0].param_type.name, "String");
+
+        let result =
+            SmartMatcher::new(SmartMatcherConfig::default()).match_functions(&source, &target);
+        assert_eq!(result.mapping.get(&source[0].hash), Some(&target[0].hash));
+        assert_eq!(result.mapping.get(&source[1].hash), Some(&target[1].hash));
+    }
+}
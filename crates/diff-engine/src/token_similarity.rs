@@ -0,0 +1,179 @@
+//! Token-based content similarity
+//!
+//! Source text is split into identifiers, keywords, numbers and operator
+//! characters, and two texts are compared by the overlap of their token
+//! n-grams. Unlike a character-set comparison, this tells `a - b` from `b - a`
+//! and does not treat anagrams as identical, while staying linear in the input
+//! size.
+
+use std::collections::HashMap;
+
+/// Settings for token-based similarity
+#[derive(Debug, Clone)]
+pub struct TokenSimilarityConfig {
+    /// Number of consecutive tokens compared as a unit; 1 ignores token order
+    pub ngram_size: usize,
+    /// Whether identifiers and keywords are compared case-insensitively
+    pub ignore_case: bool,
+}
+
+impl Default for TokenSimilarityConfig {
+    fn default() -> Self {
+        Self {
+            ngram_size: 2,
+            ignore_case: false,
+        }
+    }
+}
+
+/// Split source text into identifier, number and operator tokens
+///
+/// Runs of alphanumeric characters and `_` form one token; every other
+/// non-whitespace character is a token of its own.
+pub fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut word_start = None;
+
+    for (i, c) in text.char_indices() {
+        if c.is_alphanumeric() || c == '_' {
+            word_start.get_or_insert(i);
+            continue;
+        }
+        if let Some(start) = word_start.take() {
+            tokens.push(&text[start..i]);
+        }
+        if !c.is_whitespace() {
+            tokens.push(&text[i..i + c.len_utf8()]);
+        }
+    }
+    if let Some(start) = word_start {
+        tokens.push(&text[start..]);
+    }
+
+    tokens
+}
+
+/// Similarity of two texts by their shared token n-grams
+#[derive(Debug, Clone, Default)]
+pub struct TokenSimilarity {
+    config: TokenSimilarityConfig,
+}
+
+impl TokenSimilarity {
+    pub fn new(config: TokenSimilarityConfig) -> Self {
+        Self { config }
+    }
+
+    /// Weighted Jaccard similarity (0.0 to 1.0) of the token n-grams of two texts
+    ///
+    /// Texts shorter than the n-gram size are compared by their tokens alone.
+    pub fn similarity(&self, text1: &str, text2: &str) -> f64 {
+        if text1 == text2 {
+            return 1.0;
+        }
+
+        let (tokens1, tokens2) = (self.tokens(text1), self.tokens(text2));
+        if tokens1.is_empty() || tokens2.is_empty() {
+            return if tokens1.is_empty() && tokens2.is_empty() {
+                1.0
+            } else {
+                0.0
+            };
+        }
+
+        let n = self
+            .config
+            .ngram_size
+            .clamp(1, tokens1.len().min(tokens2.len()));
+        let grams1 = Self::ngram_counts(&tokens1, n);
+        let grams2 = Self::ngram_counts(&tokens2, n);
+
+        let shared: usize = grams1
+            .iter()
+            .filter_map(|(gram, &count)| grams2.get(gram).map(|&other| count.min(other)))
+            .sum();
+        let total = (tokens1.len() - n + 1) + (tokens2.len() - n + 1) - shared;
+
+        shared as f64 / total as f64
+    }
+
+    fn tokens(&self, text: &str) -> Vec<String> {
+        tokenize(text)
+            .into_iter()
+            .map(|token| {
+                if self.config.ignore_case {
+                    token.to_lowercase()
+                } else {
+                    token.to_string()
+                }
+            })
+            .collect()
+    }
+
+    fn ngram_counts(tokens: &[String], n: usize) -> HashMap<&[String], usize> {
+        let mut counts = HashMap::new();
+        for gram in tokens.windows(n) {
+            *counts.entry(gram).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_splits_identifiers_and_operators() {
+        assert_eq!(
+            tokenize("let total=a+ b_2;"),
+            vec!["let", "total", "=", "a", "+", "b_2", ";"]
+        );
+        assert!(tokenize("  \n\t").is_empty());
+    }
+
+    #[test]
+    fn test_reordered_tokens_score_below_identical() {
+        let similarity = TokenSimilarity::default();
+        let original = "fn area(w: f64, h: f64) -> f64 {\n    let area = w * h;\n    area\n}";
+        let reordered = "fn area(w: f64, h: f64) -> f64 {\n    area\n    let area = h * w;\n}";
+        let unrelated = "def greet(name):\n    print(f\"hello {name}\")";
+
+        let identical = similarity.similarity(original, &original.replace("    ", "  "));
+        let shuffled = similarity.similarity(original, reordered);
+        assert_eq!(identical, 1.0);
+        assert!(shuffled < 0.9, "reordered tokens scored {}", shuffled);
+        assert!(shuffled > similarity.similarity(original, unrelated));
+
+        // Order is ignored with unigrams, so only the token multiset counts
+        let unigrams = TokenSimilarity::new(TokenSimilarityConfig {
+            ngram_size: 1,
+            ..Default::default()
+        });
+        assert_eq!(unigrams.similarity(original, reordered), 1.0);
+    }
+
+    #[test]
+    fn test_anagrams_are_not_identical() {
+        let similarity = TokenSimilarity::default();
+
+        // Same character set, entirely different tokens
+        assert_eq!(similarity.similarity("listen()", "silent()"), 1.0 / 3.0);
+        assert_eq!(similarity.similarity("stop", "pots"), 0.0);
+        assert_eq!(similarity.similarity("", ""), 1.0);
+        assert_eq!(similarity.similarity("x", ""), 0.0);
+    }
+
+    #[test]
+    fn test_ignore_case() {
+        let text1 = "SELECT name FROM users";
+        let text2 = "select name from users";
+        assert!(TokenSimilarity::default().similarity(text1, text2) < 0.5);
+
+        let similarity = TokenSimilarity::new(TokenSimilarityConfig {
+            ignore_case: true,
+            ..Default::default()
+        });
+        assert_eq!(similarity.similarity(text1, text2), 1.0);
+    }
+}
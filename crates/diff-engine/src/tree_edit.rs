@@ -5,7 +5,8 @@
 
 use serde::{Deserialize, Serialize};
 use smart_diff_parser::{ASTNode, NodeType};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 
 /// Configuration for Zhang-Shasha tree edit distance algorithm
@@ -29,6 +30,9 @@ pub struct ZhangShashaConfig {
     pub similarity_threshold: f64,
     /// Enable parallel processing for large trees
     pub enable_parallel: bool,
+    /// Node types left out of the comparison, such as comments or imports;
+    /// the children of a left-out node take its place under its parent
+    pub ignored_node_types: HashSet<NodeType>,
 }
 
 impl Default for ZhangShashaConfig {
@@ -43,6 +47,7 @@ impl Default for ZhangShashaConfig {
             max_nodes: 10000,
             similarity_threshold: 0.1,
             enable_parallel: true,
+            ignored_node_types: HashSet::new(),
         }
     }
 }
@@ -124,6 +129,13 @@ impl TreeEditDistance {
 
     /// Calculate edit distance between two ASTs using optimized Zhang-Shasha algorithm
     pub fn calculate_distance(&self, tree1: &ASTNode, tree2: &ASTNode) -> f64 {
+        let tree1 = self.strip_ignored_nodes(tree1);
+        let tree2 = self.strip_ignored_nodes(tree2);
+        self.stripped_distance(&tree1, &tree2)
+    }
+
+    /// Edit distance between two trees already stripped of ignored nodes
+    fn stripped_distance(&self, tree1: &ASTNode, tree2: &ASTNode) -> f64 {
         // Early termination for identical trees
         if self.are_trees_identical(tree1, tree2) {
             return 0.0;
@@ -166,22 +178,29 @@ impl TreeEditDistance {
 
     /// Calculate edit operations to transform tree1 into tree2
     pub fn calculate_operations(&self, tree1: &ASTNode, tree2: &ASTNode) -> Vec<EditOperation> {
+        let tree1 = self.strip_ignored_nodes(tree1);
+        let tree2 = self.strip_ignored_nodes(tree2);
+
         // Early termination for identical trees
-        if self.are_trees_identical(tree1, tree2) {
+        if self.are_trees_identical(&tree1, &tree2) {
             return Vec::new();
         }
 
         // Preprocess trees
-        let tree1_info = self.preprocess_tree(tree1);
-        let tree2_info = self.preprocess_tree(tree2);
+        let tree1_info = self.preprocess_tree(&tree1);
+        let tree2_info = self.preprocess_tree(&tree2);
 
         // Calculate operations using Zhang-Shasha with backtracking
-        self.zhang_shasha_operations(&tree1_info, &tree2_info, tree1, tree2)
+        self.zhang_shasha_operations(&tree1_info, &tree2_info, &tree1, &tree2)
     }
 
     /// Calculate similarity score (1.0 - normalized distance)
     pub fn calculate_similarity(&self, tree1: &ASTNode, tree2: &ASTNode) -> f64 {
-        let distance = self.calculate_distance(tree1, tree2);
+        let tree1 = self.strip_ignored_nodes(tree1);
+        let tree2 = self.strip_ignored_nodes(tree2);
+        let (tree1, tree2) = (tree1.as_ref(), tree2.as_ref());
+
+        let distance = self.stripped_distance(tree1, tree2);
         let max_nodes = self.count_nodes(tree1).max(self.count_nodes(tree2)) as f64;
 
         if max_nodes == 0.0 {
@@ -192,6 +211,40 @@ impl TreeEditDistance {
         (1.0 - normalized_distance).max(0.0)
     }
 
+    /// `tree` without the nodes of the ignored types, each replaced by its
+    /// children so the rest of the tree keeps its shape
+    ///
+    /// The root is always kept, even if its type is ignored.
+    fn strip_ignored_nodes<'a>(&self, tree: &'a ASTNode) -> Cow<'a, ASTNode> {
+        if self.config.ignored_node_types.is_empty() {
+            return Cow::Borrowed(tree);
+        }
+        Cow::Owned(ASTNode {
+            id: tree.id.clone(),
+            node_type: tree.node_type,
+            children: self.strip_ignored_children(&tree.children),
+            metadata: tree.metadata.clone(),
+        })
+    }
+
+    fn strip_ignored_children(&self, children: &[ASTNode]) -> Vec<ASTNode> {
+        let mut kept = Vec::with_capacity(children.len());
+        for child in children {
+            let grandchildren = self.strip_ignored_children(&child.children);
+            if self.config.ignored_node_types.contains(&child.node_type) {
+                kept.extend(grandchildren);
+            } else {
+                kept.push(ASTNode {
+                    id: child.id.clone(),
+                    node_type: child.node_type,
+                    children: grandchildren,
+                    metadata: child.metadata.clone(),
+                });
+            }
+        }
+        kept
+    }
+
     /// Check if two trees are structurally identical
     #[allow(clippy::only_used_in_recursion)]
     fn are_trees_identical(&self, tree1: &ASTNode, tree2: &ASTNode) -> bool {
@@ -708,6 +761,60 @@ mod tests {
         assert_eq!(config.max_nodes, 10000);
         assert_eq!(config.similarity_threshold, 0.1);
         assert!(config.enable_parallel);
+        assert!(config.ignored_node_types.is_empty());
+    }
+
+    #[test]
+    fn test_ignored_comments_leave_no_edit_distance() {
+        // A comment of its own and one holding a statement, which must move
+        // up to the comment's parent rather than be dropped with it
+        let commented = create_test_node(
+            NodeType::Function,
+            vec![
+                create_leaf_node(NodeType::Comment),
+                create_test_node(
+                    NodeType::Block,
+                    vec![
+                        create_test_node(
+                            NodeType::Comment,
+                            vec![create_leaf_node(NodeType::ReturnStatement)],
+                        ),
+                        create_leaf_node(NodeType::Identifier),
+                    ],
+                ),
+            ],
+        );
+        let plain = create_test_node(
+            NodeType::Function,
+            vec![create_test_node(
+                NodeType::Block,
+                vec![
+                    create_leaf_node(NodeType::ReturnStatement),
+                    create_leaf_node(NodeType::Identifier),
+                ],
+            )],
+        );
+
+        let ted = TreeEditDistance::with_defaults();
+        assert!(ted.calculate_distance(&commented, &plain) > 0.0);
+
+        let ted = TreeEditDistance::new(ZhangShashaConfig {
+            ignored_node_types: HashSet::from([NodeType::Comment]),
+            ..Default::default()
+        });
+        assert_eq!(ted.calculate_distance(&commented, &plain), 0.0);
+        assert_eq!(ted.calculate_similarity(&commented, &plain), 1.0);
+        assert!(ted.calculate_operations(&commented, &plain).is_empty());
+
+        // Without the statement the comment held, the trees differ again
+        let emptied = create_test_node(
+            NodeType::Function,
+            vec![create_test_node(
+                NodeType::Block,
+                vec![create_leaf_node(NodeType::Identifier)],
+            )],
+        );
+        assert!(ted.calculate_distance(&commented, &emptied) > 0.0);
     }
 
     #[test]
@@ -1001,6 +1108,7 @@ mod tests {
             max_nodes: 5000,
             similarity_threshold: 0.2,
             enable_parallel: false,
+            ignored_node_types: HashSet::from([NodeType::Comment]),
         };
 
         ted.set_config(new_config);
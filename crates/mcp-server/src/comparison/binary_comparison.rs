@@ -261,4 +261,3 @@ mod tests {
         assert_eq!(manager.list_comparisons().len(), 0);
     }
 }
-
@@ -0,0 +1,87 @@
+//! On-disk cache of evicted comparisons
+//!
+//! When a cache directory is configured, comparisons evicted from memory are
+//! written to it in the [binary format](smart_diff_engine::binary_format),
+//! one file per comparison, and read back the next time they are asked for.
+//! A file written by an incompatible version of the format is removed and
+//! the comparison treated as gone, so a server upgrade never misreads an old
+//! cache.
+
+use super::context::{ComparisonContext, ComparisonId};
+use anyhow::{Context, Result};
+use smart_diff_engine::binary_format;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Extension of cached comparison files
+const EXTENSION: &str = "sdiffbin";
+
+/// Directory of comparisons in the binary format
+#[derive(Debug, Clone)]
+pub struct ComparisonCache {
+    dir: PathBuf,
+}
+
+impl ComparisonCache {
+    /// Cache in `dir`, created if missing
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create cache directory {}", dir.display()))?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, id: ComparisonId) -> PathBuf {
+        self.dir.join(format!("{}.{}", id, EXTENSION))
+    }
+
+    /// Write a comparison, replacing any earlier copy
+    pub fn save(&self, context: &ComparisonContext) -> Result<()> {
+        let path = self.path(context.id);
+        let bytes = binary_format::encode(context)?;
+        // Written aside and renamed, so a reader never sees half a file
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, bytes)
+            .and_then(|()| std::fs::rename(&partial, &path))
+            .with_context(|| format!("Failed to write cached comparison {}", path.display()))
+    }
+
+    /// Read a cached comparison, if there is a readable one
+    pub fn load(&self, id: ComparisonId) -> Option<ComparisonContext> {
+        let path = self.path(id);
+        let bytes = std::fs::read(&path).ok()?;
+        match binary_format::decode(&bytes) {
+            Ok(context) => Some(context),
+            Err(e) => {
+                warn!("Discarding cached comparison {}: {:#}", path.display(), e);
+                self.remove(id);
+                None
+            }
+        }
+    }
+
+    /// Remove a cached comparison, returning whether there was one
+    pub fn remove(&self, id: ComparisonId) -> bool {
+        std::fs::remove_file(self.path(id)).is_ok()
+    }
+
+    /// Ids of the cached comparisons
+    pub fn ids(&self) -> Vec<ComparisonId> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != EXTENSION {
+                    return None;
+                }
+                id_of(&path)
+            })
+            .collect()
+    }
+}
+
+fn id_of(path: &Path) -> Option<ComparisonId> {
+    path.file_stem()?.to_str()?.parse().ok()
+}
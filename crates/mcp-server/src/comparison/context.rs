@@ -1,8 +1,14 @@
 //! Comparison context data structures
 
 use serde::{Deserialize, Serialize};
-use smart_diff_engine::DiffResult;
-use smart_diff_parser::Function;
+use smart_diff_engine::{CodeVersion, DiffResult, RefactoringPattern};
+use smart_diff_parser::{
+    ASTNode, Function, LanguageDetector, MatchResult, NodeMetadata, NodeType, ParseResult,
+};
+use smart_diff_semantic::{
+    CallNeighborhood, ComprehensiveDependencyGraphBuilder, ImportDelta, ImportInfo,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use uuid::Uuid;
 
 /// Unique identifier for a comparison
@@ -32,8 +38,16 @@ impl std::fmt::Display for ComparisonId {
     }
 }
 
+impl std::str::FromStr for ComparisonId {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(s).map(Self)
+    }
+}
+
 /// Parameters for a comparison operation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ComparisonParams {
     pub source_path: String,
     pub target_path: String,
@@ -43,10 +57,13 @@ pub struct ComparisonParams {
     pub file_patterns: Vec<String>,
     #[serde(default)]
     pub ignore_patterns: Vec<String>,
+    /// Minimum confidence of reported refactoring patterns; detector default when unset
+    #[serde(default)]
+    pub min_refactoring_confidence: Option<f64>,
 }
 
 /// A single function change with metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FunctionChange {
     pub function_name: String,
     pub source_file: Option<String>,
@@ -66,6 +83,10 @@ pub struct FunctionChange {
     /// True if this is a high-similarity move (>= 0.95) with no meaningful changes
     #[serde(default)]
     pub is_unchanged_move: bool,
+    /// True if the function's body is unchanged apart from its name and
+    /// consistently renamed locals
+    #[serde(default)]
+    pub body_preserved: bool,
 }
 
 impl FunctionChange {
@@ -74,7 +95,11 @@ impl FunctionChange {
         match self.change_type.as_str() {
             "added" => 1.0,
             "deleted" => 1.0,
+            "modified" if self.body_preserved => 0.0,
             "modified" => 1.0 - self.similarity_score,
+            // A pure rename changes no behavior, so it must not outrank edits
+            // to a body, however small
+            "renamed" if self.body_preserved => 0.0,
             "renamed" => 0.3, // Renamed but similar content
             "moved" => 0.2,   // Moved but same content
             _ => 0.0,
@@ -83,7 +108,10 @@ impl FunctionChange {
 }
 
 /// Complete comparison context
-#[derive(Debug, Clone)]
+///
+/// Serializable, so comparisons can be cached on disk in the
+/// [binary format](smart_diff_engine::binary_format).
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct ComparisonContext {
     pub id: ComparisonId,
@@ -93,9 +121,42 @@ pub struct ComparisonContext {
     pub diff_result: Option<DiffResult>,
     pub function_changes: Vec<FunctionChange>,
     pub unchanged_moves: usize,
+    /// Refactoring patterns found among the changes, most confident first
+    pub refactoring_patterns: Vec<RefactoringPattern>,
+    /// Raw matcher output, kept so a later re-comparison can reuse it
+    pub match_result: Option<MatchResult>,
+    /// Content hash of every parsed source file, keyed by relative path
+    pub source_file_hashes: HashMap<String, u64>,
+    /// Content hash of every parsed target file, keyed by relative path
+    pub target_file_hashes: HashMap<String, u64>,
+    /// Imports of every parsed source file, keyed by relative path
+    pub source_imports: HashMap<String, Vec<ImportInfo>>,
+    /// Imports of every parsed target file, keyed by relative path
+    pub target_imports: HashMap<String, Vec<ImportInfo>>,
+    /// Parsed code of every source file, keyed by relative path
+    pub source_versions: HashMap<String, CodeVersion>,
+    /// Parsed code of every target file, keyed by relative path
+    pub target_versions: HashMap<String, CodeVersion>,
+    /// Reuse statistics when this comparison was built from a previous one
+    pub incremental_stats: Option<IncrementalStats>,
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// What an incremental re-comparison reused and what it had to match again
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IncrementalStats {
+    /// Files whose content is unchanged on both sides
+    pub reused_files: usize,
+    /// Files that changed, plus files pulled in by cross-file matches
+    pub rematched_files: usize,
+    /// Function pairs carried over from the previous comparison
+    pub reused_matches: usize,
+    /// Source functions handed to the matcher again
+    pub rematched_source_functions: usize,
+    /// Target functions handed to the matcher again
+    pub rematched_target_functions: usize,
+}
+
 impl ComparisonContext {
     pub fn new(params: ComparisonParams) -> Self {
         Self {
@@ -106,6 +167,15 @@ impl ComparisonContext {
             diff_result: None,
             function_changes: Vec::new(),
             unchanged_moves: 0,
+            refactoring_patterns: Vec::new(),
+            match_result: None,
+            source_file_hashes: HashMap::new(),
+            target_file_hashes: HashMap::new(),
+            source_imports: HashMap::new(),
+            target_imports: HashMap::new(),
+            source_versions: HashMap::new(),
+            target_versions: HashMap::new(),
+            incremental_stats: None,
             created_at: chrono::Utc::now(),
         }
     }
@@ -118,7 +188,13 @@ impl ComparisonContext {
     /// 3. Deleted functions (alphabetically)
     /// 4. Renamed/moved functions (sorted by magnitude)
     pub fn get_sorted_changes(&self) -> Vec<FunctionChange> {
-        let mut changes = self.function_changes.clone();
+        self.ranked_changes().into_iter().cloned().collect()
+    }
+
+    /// Changes in the order of [`get_sorted_changes`](Self::get_sorted_changes),
+    /// without copying them
+    pub fn ranked_changes(&self) -> Vec<&FunctionChange> {
+        let mut changes: Vec<&FunctionChange> = self.function_changes.iter().collect();
         changes.sort_by(|a, b| {
             // First, prioritize by change type
             let type_priority_a = Self::change_type_priority(&a.change_type);
@@ -159,6 +235,98 @@ impl ComparisonContext {
             .find(|c| c.function_name == name)
     }
 
+    /// Callers and callees of a changed function, up to `depth` calls away
+    ///
+    /// Calls are resolved across every parsed file of the version the function
+    /// exists in: the new one, or the old one for deleted functions.
+    pub fn get_call_neighborhood(&self, change: &FunctionChange, depth: usize) -> CallNeighborhood {
+        let (functions, file) = match &change.target_file {
+            Some(file) => (&self.target_functions, file),
+            None => match &change.source_file {
+                Some(file) => (&self.source_functions, file),
+                None => return CallNeighborhood::default(),
+            },
+        };
+
+        // Only function bodies are kept after parsing, so each file is
+        // rebuilt as a program made of its functions
+        let mut bodies: BTreeMap<&str, Vec<ASTNode>> = BTreeMap::new();
+        for function in functions {
+            bodies
+                .entry(function.location.file_path.as_str())
+                .or_default()
+                .push(function.body.clone());
+        }
+        let files = bodies
+            .into_iter()
+            .map(|(path, children)| {
+                let mut ast = ASTNode::new(
+                    NodeType::Program,
+                    NodeMetadata {
+                        line: 1,
+                        column: 1,
+                        original_text: String::new(),
+                        attributes: HashMap::new(),
+                    },
+                );
+                ast.children = children;
+                let parse_result = ParseResult {
+                    ast,
+                    language: LanguageDetector::detect_from_path(path),
+                    errors: Vec::new(),
+                    warnings: Vec::new(),
+                    custom_elements: Vec::new(),
+                };
+                (path.to_string(), parse_result)
+            })
+            .collect();
+
+        let mut builder = ComprehensiveDependencyGraphBuilder::with_defaults();
+        if let Err(e) = builder.build_comprehensive_graph(files) {
+            tracing::warn!("Failed to build call graph: {}", e);
+            return CallNeighborhood::default();
+        }
+        builder.call_neighborhood(&change.function_name, Some(file), depth)
+    }
+
+    /// Refactoring patterns grouped by type, most frequent type first
+    ///
+    /// Patterns keep their confidence order within a group.
+    pub fn get_refactoring_groups(&self) -> Vec<(String, Vec<&RefactoringPattern>)> {
+        let mut groups: Vec<(String, Vec<&RefactoringPattern>)> = Vec::new();
+
+        for pattern in &self.refactoring_patterns {
+            let pattern_type = format!("{:?}", pattern.pattern_type);
+            match groups.iter_mut().find(|(name, _)| *name == pattern_type) {
+                Some((_, patterns)) => patterns.push(pattern),
+                None => groups.push((pattern_type, vec![pattern])),
+            }
+        }
+
+        groups.sort_by_key(|(_, patterns)| std::cmp::Reverse(patterns.len()));
+        groups
+    }
+
+    /// Import changes per file, for files whose imports differ, sorted by path
+    pub fn get_import_deltas(&self) -> Vec<(String, ImportDelta)> {
+        let files: BTreeSet<&String> = self
+            .source_imports
+            .keys()
+            .chain(self.target_imports.keys())
+            .collect();
+
+        files
+            .into_iter()
+            .filter_map(|file| {
+                let source = self.source_imports.get(file).map(Vec::as_slice);
+                let target = self.target_imports.get(file).map(Vec::as_slice);
+                let delta =
+                    ImportDelta::between(source.unwrap_or_default(), target.unwrap_or_default());
+                (!delta.is_empty()).then(|| (file.clone(), delta))
+            })
+            .collect()
+    }
+
     /// Get summary statistics
     pub fn get_summary(&self) -> ComparisonSummary {
         let added = self
@@ -195,6 +363,14 @@ impl ComparisonContext {
         let unchanged =
             total_functions.saturating_sub(added + deleted + modified + renamed + moved);
 
+        let import_deltas = self.get_import_deltas();
+        let count_imports = |count: fn(&ImportDelta) -> usize| {
+            import_deltas
+                .iter()
+                .map(|(_, delta)| count(delta))
+                .sum::<usize>()
+        };
+
         ComparisonSummary {
             total_functions,
             added,
@@ -204,6 +380,17 @@ impl ComparisonContext {
             moved,
             unchanged,
             unchanged_moves: self.unchanged_moves,
+            imports_added: count_imports(|d| d.added.len()),
+            imports_removed: count_imports(|d| d.removed.len()),
+            imports_changed: count_imports(|d| d.changed.len()),
+            refactorings: self
+                .get_refactoring_groups()
+                .into_iter()
+                .map(|(pattern_type, patterns)| RefactoringCount {
+                    pattern_type,
+                    count: patterns.len(),
+                })
+                .collect(),
         }
     }
 }
@@ -222,4 +409,21 @@ pub struct ComparisonSummary {
     /// These are filtered from the changes list to reduce noise
     #[serde(default)]
     pub unchanged_moves: usize,
+    /// Imports added, removed or re-aliased across all files
+    #[serde(default)]
+    pub imports_added: usize,
+    #[serde(default)]
+    pub imports_removed: usize,
+    #[serde(default)]
+    pub imports_changed: usize,
+    /// Detected refactoring patterns per type, most frequent first
+    #[serde(default)]
+    pub refactorings: Vec<RefactoringCount>,
+}
+
+/// Number of detected refactoring patterns of one type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefactoringCount {
+    pub pattern_type: String,
+    pub count: usize,
 }
@@ -0,0 +1,92 @@
+//! Eviction of idle comparisons
+//!
+//! A long-running server would otherwise keep every comparison an agent ever
+//! created. Comparisons not accessed within the idle TTL are dropped by a
+//! periodic sweep, and the least recently accessed ones are dropped whenever
+//! the count exceeds the cap. With a cache directory, evicted comparisons
+//! move to disk instead of being dropped.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Environment variable overriding [`EvictionConfig::idle_ttl`], in seconds
+pub const IDLE_TTL_ENV: &str = "SMART_DIFF_COMPARISON_TTL_SECS";
+
+/// Environment variable overriding [`EvictionConfig::max_comparisons`]
+pub const MAX_COMPARISONS_ENV: &str = "SMART_DIFF_MAX_COMPARISONS";
+
+/// Environment variable setting [`EvictionConfig::cache_dir`]
+pub const CACHE_DIR_ENV: &str = "SMART_DIFF_COMPARISON_CACHE_DIR";
+
+/// When stored comparisons are evicted
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvictionConfig {
+    /// Comparisons not accessed for this long are evicted
+    pub idle_ttl: Duration,
+    /// Most comparisons kept, at least one; the least recently accessed are
+    /// evicted first
+    pub max_comparisons: usize,
+    /// How often the background task sweeps idle comparisons
+    pub sweep_interval: Duration,
+    /// Directory evicted comparisons are written to and read back from;
+    /// without one they are dropped
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl Default for EvictionConfig {
+    fn default() -> Self {
+        Self {
+            idle_ttl: Duration::from_secs(60 * 60),
+            max_comparisons: 100,
+            sweep_interval: Duration::from_secs(60),
+            cache_dir: None,
+        }
+    }
+}
+
+impl EvictionConfig {
+    /// Defaults overridden by [`IDLE_TTL_ENV`], [`MAX_COMPARISONS_ENV`] and
+    /// [`CACHE_DIR_ENV`]
+    ///
+    /// Unparsable values are ignored with a warning.
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(seconds) = Self::env_number(IDLE_TTL_ENV) {
+            config.idle_ttl = Duration::from_secs(seconds);
+            config.sweep_interval = config.sweep_interval.min(config.idle_ttl);
+        }
+        if let Some(max) = Self::env_number(MAX_COMPARISONS_ENV) {
+            config.max_comparisons = max as usize;
+        }
+        config.cache_dir = std::env::var_os(CACHE_DIR_ENV)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from);
+        config
+    }
+
+    fn env_number(name: &str) -> Option<u64> {
+        let value = std::env::var(name).ok()?;
+        match value.trim().parse() {
+            Ok(number) => Some(number),
+            Err(_) => {
+                tracing::warn!("Ignoring {}={:?}: not a whole number", name, value);
+                None
+            }
+        }
+    }
+}
+
+/// Source of the current time, replaceable in tests
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// The system's monotonic clock
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
@@ -0,0 +1,153 @@
+//! Reading commits and blobs from a git repository
+//!
+//! Commit comparisons shell out to the `git` executable rather than linking a
+//! git library: the server only needs a handful of read-only plumbing commands,
+//! and using the user's own `git` honours their repository configuration.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Parameters for comparing two commits of a repository
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitComparisonParams {
+    pub repo_path: String,
+    /// Revision compared against; defaults to a parent of `head_revision`
+    #[serde(default)]
+    pub base_revision: Option<String>,
+    pub head_revision: String,
+    /// Parent used when `base_revision` is omitted; 1 is the first parent of a merge
+    #[serde(default = "default_parent")]
+    pub parent: usize,
+    /// Minimum confidence of reported refactoring patterns; detector default when unset
+    #[serde(default)]
+    pub min_refactoring_confidence: Option<f64>,
+}
+
+fn default_parent() -> usize {
+    1
+}
+
+/// A file touched between two commits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedFile {
+    /// Path in the base commit, `None` for added files
+    pub old_path: Option<String>,
+    /// Path in the head commit, `None` for deleted files
+    pub new_path: Option<String>,
+}
+
+/// A git working tree or bare repository
+#[derive(Debug, Clone)]
+pub struct GitRepository {
+    path: PathBuf,
+}
+
+impl GitRepository {
+    /// Open the repository containing `path`
+    pub async fn open(path: &Path) -> Result<Self> {
+        let repository = Self {
+            path: path.to_path_buf(),
+        };
+        repository
+            .git(&["rev-parse", "--git-dir"])
+            .await
+            .with_context(|| format!("Not a git repository: {}", path.display()))?;
+        Ok(repository)
+    }
+
+    /// Full commit id of a revision
+    pub async fn resolve_commit(&self, revision: &str) -> Result<String> {
+        let output = self
+            .git(&[
+                "rev-parse",
+                "--verify",
+                "--end-of-options",
+                &format!("{}^{{commit}}", revision),
+            ])
+            .await
+            .with_context(|| format!("Unknown revision: {}", revision))?;
+        Ok(output.trim().to_string())
+    }
+
+    /// The `parent`-th parent of a commit (1-based, as in `commit^N`)
+    pub async fn parent_of(&self, commit: &str, parent: usize) -> Result<String> {
+        if parent == 0 {
+            bail!("Parent numbers start at 1");
+        }
+        self.resolve_commit(&format!("{}^{}", commit, parent))
+            .await
+            .with_context(|| format!("Commit {} has no parent {}", commit, parent))
+    }
+
+    /// Files that differ between two commits, with renames detected
+    pub async fn changed_files(&self, base: &str, head: &str) -> Result<Vec<ChangedFile>> {
+        let output = self
+            .git(&["diff", "--name-status", "-z", "-M", base, head, "--"])
+            .await?;
+
+        let mut fields = output.split('\0').filter(|field| !field.is_empty());
+        let mut files = Vec::new();
+
+        while let Some(status) = fields.next() {
+            let mut path = || {
+                fields
+                    .next()
+                    .map(str::to_string)
+                    .context("Truncated git diff output")
+            };
+            let file = match status.chars().next() {
+                Some('A') => ChangedFile {
+                    old_path: None,
+                    new_path: Some(path()?),
+                },
+                Some('D') => ChangedFile {
+                    old_path: Some(path()?),
+                    new_path: None,
+                },
+                Some('R') | Some('C') => ChangedFile {
+                    old_path: Some(path()?),
+                    new_path: Some(path()?),
+                },
+                _ => {
+                    let path = path()?;
+                    ChangedFile {
+                        old_path: Some(path.clone()),
+                        new_path: Some(path),
+                    }
+                }
+            };
+            files.push(file);
+        }
+
+        Ok(files)
+    }
+
+    /// Content of a file as of a commit
+    pub async fn read_blob(&self, commit: &str, path: &str) -> Result<String> {
+        self.git(&["cat-file", "blob", &format!("{}:{}", commit, path)])
+            .await
+            .with_context(|| format!("Failed to read {} at {}", path, commit))
+    }
+
+    async fn git(&self, args: &[&str]) -> Result<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.path)
+            .args(args)
+            .output()
+            .await
+            .context("Failed to run git")?;
+
+        if !output.status.success() {
+            bail!(
+                "git {} failed: {}",
+                args.first().unwrap_or(&""),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        String::from_utf8(output.stdout).context("git output is not valid UTF-8")
+    }
+}
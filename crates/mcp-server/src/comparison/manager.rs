@@ -1,30 +1,73 @@
 //! Comparison manager for handling multiple comparison contexts
 
-use super::context::{ComparisonContext, ComparisonId, ComparisonParams, FunctionChange};
+use super::cache::ComparisonCache;
+use super::context::{
+    ComparisonContext, ComparisonId, ComparisonParams, FunctionChange, IncrementalStats,
+};
+use super::eviction::{Clock, EvictionConfig, SystemClock};
+use super::git::{GitComparisonParams, GitRepository};
 use anyhow::{Context as AnyhowContext, Result};
-use smart_diff_engine::{SmartMatcher, SmartMatcherConfig};
+use smart_diff_engine::{
+    tokenize, CodeVersion, CrossFilePenaltyCurve, RefactoringDetectionConfig, RefactoringDetector,
+    SimilarityScorer, SmartMatcher, SmartMatcherConfig,
+};
 use smart_diff_parser::{
-    tree_sitter::TreeSitterParser, Function, Language, LanguageDetector, Parser,
+    tree_sitter::TreeSitterParser, Function, Language, LanguageDetector, MatchResult, Parser,
 };
-use std::collections::HashMap;
+use smart_diff_semantic::{ImportInfo, SymbolResolver};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::path::Path;
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::Instant;
 use tracing::{debug, info, warn};
 use walkdir::WalkDir;
 
 /// Manages multiple comparison contexts
 pub struct ComparisonManager {
-    contexts: Arc<RwLock<HashMap<ComparisonId, ComparisonContext>>>,
+    contexts: Arc<RwLock<HashMap<ComparisonId, StoredComparison>>>,
     parser: Arc<Mutex<TreeSitterParser>>,
     smart_matcher: Arc<Mutex<SmartMatcher>>,
+    eviction: EvictionConfig,
+    /// Where evicted comparisons go, if anywhere
+    cache: Option<ComparisonCache>,
+    clock: Arc<dyn Clock>,
+    evicted: AtomicU64,
+}
+
+/// A comparison with the time it was last read or created
+struct StoredComparison {
+    context: ComparisonContext,
+    last_accessed: Instant,
+}
+
+/// Counts describing the comparisons held in memory
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ComparisonManagerStats {
+    /// Comparisons currently stored
+    pub comparisons: usize,
+    /// Comparisons evicted since the manager was created
+    pub evicted: u64,
+    pub idle_ttl_secs: u64,
+    pub max_comparisons: usize,
 }
 
 impl ComparisonManager {
     pub fn new() -> Self {
+        Self::with_eviction(EvictionConfig::default())
+    }
+
+    /// Manager evicting idle comparisons as configured
+    pub fn with_eviction(eviction: EvictionConfig) -> Self {
         let config = SmartMatcherConfig {
             similarity_threshold: 0.7,
             enable_cross_file_matching: true,
-            cross_file_penalty: 0.5,
+            cross_file_penalty_fn: CrossFilePenaltyCurve::default(),
+            enable_exact_match_prepass: true,
+            normalize_identifiers: false,
+            content_similarity: Default::default(),
         };
 
         // Configure parser with large max_text_length to avoid truncating function bodies
@@ -39,15 +82,158 @@ impl ComparisonManager {
             .build()
             .expect("Failed to create parser");
 
+        let cache = eviction
+            .cache_dir
+            .as_ref()
+            .and_then(|dir| match ComparisonCache::new(dir) {
+                Ok(cache) => Some(cache),
+                Err(e) => {
+                    warn!("Evicted comparisons will be dropped: {:#}", e);
+                    None
+                }
+            });
+
         Self {
             contexts: Arc::new(RwLock::new(HashMap::new())),
             parser: Arc::new(Mutex::new(parser)),
             smart_matcher: Arc::new(Mutex::new(SmartMatcher::new(config))),
+            eviction,
+            cache,
+            clock: Arc::new(SystemClock),
+            evicted: AtomicU64::new(0),
+        }
+    }
+
+    /// Use `clock` for access times instead of the system clock
+    #[cfg(test)]
+    fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sweep idle comparisons every `sweep_interval` in a background task
+    ///
+    /// The task holds only a weak reference and ends once the manager is dropped.
+    pub fn spawn_eviction_task(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let manager: Weak<Self> = Arc::downgrade(self);
+        let sweep_interval = self.eviction.sweep_interval;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(sweep_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                if let Err(e) = manager.evict_idle() {
+                    warn!("Failed to evict idle comparisons: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Evict comparisons not accessed within the idle TTL
+    ///
+    /// Readers clone a comparison while holding the lock this takes for
+    /// writing, so a comparison is never evicted in the middle of a read.
+    /// Returns the number of comparisons evicted.
+    pub fn evict_idle(&self) -> Result<usize> {
+        let now = self.clock.now();
+        let mut contexts = self
+            .contexts
+            .write()
+            .map_err(|e| anyhow::anyhow!("Lock poisoned: {}", e))?;
+        let idle: Vec<ComparisonId> = contexts
+            .iter()
+            .filter(|(_, stored)| {
+                now.saturating_duration_since(stored.last_accessed) >= self.eviction.idle_ttl
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &idle {
+            self.evict(&mut contexts, *id);
+        }
+        self.record_evictions(idle.len());
+        Ok(idle.len())
+    }
+
+    /// Evict the least recently accessed comparisons above the count cap
+    fn evict_over_capacity(&self, contexts: &mut HashMap<ComparisonId, StoredComparison>) {
+        let max_comparisons = self.eviction.max_comparisons.max(1);
+        if contexts.len() <= max_comparisons {
+            return;
+        }
+        let mut by_access: Vec<(Instant, ComparisonId)> = contexts
+            .iter()
+            .map(|(id, stored)| (stored.last_accessed, *id))
+            .collect();
+        by_access.sort_by_key(|(last_accessed, _)| *last_accessed);
+        let excess = contexts.len() - max_comparisons;
+        for (_, id) in by_access.into_iter().take(excess) {
+            self.evict(contexts, id);
         }
+        self.record_evictions(excess);
+    }
+
+    /// Remove a comparison from memory, writing it to the cache if there is one
+    ///
+    /// Written while the lock is held, so a reader finds the comparison in
+    /// memory or on disk but never in neither.
+    fn evict(&self, contexts: &mut HashMap<ComparisonId, StoredComparison>, id: ComparisonId) {
+        let Some(stored) = contexts.remove(&id) else {
+            return;
+        };
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.save(&stored.context) {
+                warn!("Dropping comparison {}: {:#}", id, e);
+            }
+        }
+    }
+
+    fn record_evictions(&self, count: usize) {
+        if count > 0 {
+            info!("Evicted {} comparison(s)", count);
+            self.evicted.fetch_add(count as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Number of stored comparisons and evictions so far
+    pub fn stats(&self) -> Result<ComparisonManagerStats> {
+        Ok(ComparisonManagerStats {
+            comparisons: self
+                .contexts
+                .read()
+                .map_err(|e| anyhow::anyhow!("Lock poisoned: {}", e))?
+                .len(),
+            evicted: self.evicted.load(Ordering::Relaxed),
+            idle_ttl_secs: self.eviction.idle_ttl.as_secs(),
+            max_comparisons: self.eviction.max_comparisons,
+        })
     }
 
     /// Create a new comparison
     pub async fn create_comparison(&self, params: ComparisonParams) -> Result<ComparisonId> {
+        self.run_comparison(params, None).await
+    }
+
+    /// Create a comparison, reusing results for files unchanged since `previous`
+    ///
+    /// Files whose content hash matches on both sides keep their parsed functions
+    /// and function pairings. Functions in changed files, functions left unmatched
+    /// last time, and cross-file pairs touching a changed file are matched again.
+    pub async fn recompare(
+        &self,
+        params: ComparisonParams,
+        previous: &ComparisonContext,
+    ) -> Result<ComparisonId> {
+        self.run_comparison(params, Some(previous)).await
+    }
+
+    async fn run_comparison(
+        &self,
+        params: ComparisonParams,
+        previous: Option<&ComparisonContext>,
+    ) -> Result<ComparisonId> {
         info!(
             "Creating comparison: {} vs {}",
             params.source_path, params.target_path
@@ -59,13 +245,120 @@ impl ComparisonManager {
         let source_base = Path::new(&params.source_path);
         let target_base = Path::new(&params.target_path);
 
-        context.source_functions = self
-            .parse_location(&params.source_path, &params, source_base)
+        let previous_source = previous.map(|p| {
+            PreviousSide::new(
+                &p.source_functions,
+                &p.source_file_hashes,
+                &p.source_imports,
+                &p.source_versions,
+            )
+        });
+        let previous_target = previous.map(|p| {
+            PreviousSide::new(
+                &p.target_functions,
+                &p.target_file_hashes,
+                &p.target_imports,
+                &p.target_versions,
+            )
+        });
+
+        let source = self
+            .parse_location(
+                &params.source_path,
+                &params,
+                source_base,
+                previous_source.as_ref(),
+            )
             .await?;
-        context.target_functions = self
-            .parse_location(&params.target_path, &params, target_base)
+        context.source_functions = source.functions;
+        context.source_file_hashes = source.file_hashes;
+        context.source_imports = source.imports;
+        context.source_versions = source.versions;
+
+        let target = self
+            .parse_location(
+                &params.target_path,
+                &params,
+                target_base,
+                previous_target.as_ref(),
+            )
             .await?;
+        context.target_functions = target.functions;
+        context.target_file_hashes = target.file_hashes;
+        context.target_imports = target.imports;
+        context.target_versions = target.versions;
+
+        self.match_and_store(context, previous)
+    }
+
+    /// Compare two commits of a git repository
+    ///
+    /// Only files git reports as changed between the commits are read and parsed.
+    /// Without a base revision the head commit is compared against its parent,
+    /// the first one for merge commits unless `params.parent` says otherwise.
+    pub async fn create_git_comparison(&self, params: GitComparisonParams) -> Result<ComparisonId> {
+        let repository = GitRepository::open(Path::new(&params.repo_path)).await?;
+        let head = repository.resolve_commit(&params.head_revision).await?;
+        let base = match &params.base_revision {
+            Some(revision) => repository.resolve_commit(revision).await?,
+            None => repository.parent_of(&head, params.parent).await?,
+        };
 
+        info!(
+            "Creating git comparison in {}: {} vs {}",
+            params.repo_path, base, head
+        );
+
+        let changed_files = repository.changed_files(&base, &head).await?;
+        debug!("{} files changed between commits", changed_files.len());
+
+        let mut source = ParsedLocation::default();
+        let mut target = ParsedLocation::default();
+        for file in &changed_files {
+            for (commit, path, location) in [
+                (&base, &file.old_path, &mut source),
+                (&head, &file.new_path, &mut target),
+            ] {
+                let Some(path) = path else { continue };
+                if !self.is_supported_path(Path::new(path)) {
+                    continue;
+                }
+
+                let content = repository.read_blob(commit, path).await?;
+                match self.parse_content(Path::new(path), path.clone(), &content, None) {
+                    Ok(parsed) => location.add(parsed),
+                    Err(e) => warn!("Failed to parse {} at {}: {}", path, commit, e),
+                }
+            }
+        }
+
+        let short = |commit: &str| commit.chars().take(12).collect::<String>();
+        let mut context = ComparisonContext::new(ComparisonParams {
+            source_path: format!("{}@{}", params.repo_path, short(&base)),
+            target_path: format!("{}@{}", params.repo_path, short(&head)),
+            recursive: true,
+            file_patterns: Vec::new(),
+            ignore_patterns: Vec::new(),
+            min_refactoring_confidence: params.min_refactoring_confidence,
+        });
+        context.source_functions = source.functions;
+        context.source_file_hashes = source.file_hashes;
+        context.source_imports = source.imports;
+        context.source_versions = source.versions;
+        context.target_functions = target.functions;
+        context.target_file_hashes = target.file_hashes;
+        context.target_imports = target.imports;
+        context.target_versions = target.versions;
+
+        self.match_and_store(context, None)
+    }
+
+    /// Match the parsed functions of `context` and store it under a new id
+    fn match_and_store(
+        &self,
+        mut context: ComparisonContext,
+        previous: Option<&ComparisonContext>,
+    ) -> Result<ComparisonId> {
         info!(
             "Parsed {} source functions and {} target functions",
             context.source_functions.len(),
@@ -73,22 +366,55 @@ impl ComparisonManager {
         );
 
         // Perform comparison using smart matcher
-        let match_result = self
-            .smart_matcher
-            .lock()
-            .map_err(|e| anyhow::anyhow!("Lock poisoned: {}", e))?
-            .match_functions(&context.source_functions, &context.target_functions);
+        let previous_match = previous.and_then(|p| p.match_result.as_ref().map(|m| (p, m)));
+        let match_result = if let Some((previous, previous_match)) = previous_match {
+            let (match_result, stats) =
+                self.match_incrementally(previous, previous_match, &context)?;
+            info!(
+                "Reused {} matches from {} unchanged files, re-matched {} files",
+                stats.reused_matches, stats.reused_files, stats.rematched_files
+            );
+            context.incremental_stats = Some(stats);
+            match_result
+        } else {
+            self.smart_matcher
+                .lock()
+                .map_err(|e| anyhow::anyhow!("Lock poisoned: {}", e))?
+                .match_functions(&context.source_functions, &context.target_functions)
+        };
 
         // Extract function changes from match result
         let (function_changes, unchanged_moves) = self.extract_function_changes_from_match_result(
             &match_result,
             &context.source_functions,
             &context.target_functions,
-            source_base,
-            target_base,
         )?;
         context.function_changes = function_changes;
         context.unchanged_moves = unchanged_moves;
+        mark_renamed_locals(&mut context);
+
+        // Pattern detection looks at the matched changes and at the code of
+        // whole sides, whose files may be of any language
+        let refactoring_config = context
+            .params
+            .min_refactoring_confidence
+            .map(RefactoringDetectionConfig::with_min_confidence)
+            .unwrap_or_default();
+        let whole_side = |versions: &HashMap<String, CodeVersion>| {
+            let mut side = CodeVersion::new();
+            for version in versions.values() {
+                side.extend(version);
+            }
+            side
+        };
+        context.refactoring_patterns =
+            RefactoringDetector::with_config(Language::Unknown, refactoring_config)
+                .with_versions(
+                    whole_side(&context.source_versions),
+                    whole_side(&context.target_versions),
+                )
+                .detect_patterns(&match_result.changes);
+        context.match_result = Some(match_result);
 
         // Calculate change magnitudes
         for change in &mut context.function_changes {
@@ -97,68 +423,250 @@ impl ComparisonManager {
 
         let id = context.id;
 
-        // Store context
-        self.contexts
+        // Store context, making room for it if the cap is reached
+        let mut contexts = self
+            .contexts
             .write()
-            .map_err(|e| anyhow::anyhow!("Lock poisoned: {}", e))?
-            .insert(id, context);
+            .map_err(|e| anyhow::anyhow!("Lock poisoned: {}", e))?;
+        contexts.insert(
+            id,
+            StoredComparison {
+                context,
+                last_accessed: self.clock.now(),
+            },
+        );
+        self.evict_over_capacity(&mut contexts);
+        drop(contexts);
 
         info!("Comparison {} created successfully", id);
 
         Ok(id)
     }
 
-    /// Get a comparison context
+    /// Match only what changed since `previous`, carrying over the rest of its result
+    fn match_incrementally(
+        &self,
+        previous: &ComparisonContext,
+        previous_match: &MatchResult,
+        context: &ComparisonContext,
+    ) -> Result<(MatchResult, IncrementalStats)> {
+        let all_files: HashSet<&str> = context
+            .source_file_hashes
+            .keys()
+            .chain(context.target_file_hashes.keys())
+            .chain(previous.source_file_hashes.keys())
+            .chain(previous.target_file_hashes.keys())
+            .map(String::as_str)
+            .collect();
+
+        let mut dirty_files: HashSet<&str> = all_files
+            .iter()
+            .copied()
+            .filter(|file| {
+                context.source_file_hashes.get(*file) != previous.source_file_hashes.get(*file)
+                    || context.target_file_hashes.get(*file)
+                        != previous.target_file_hashes.get(*file)
+            })
+            .collect();
+
+        // A function that crossed files last time may have moved again, so both
+        // ends of a cross-file pair touching a changed file are re-matched
+        loop {
+            let before = dirty_files.len();
+            for change in &previous_match.changes {
+                if let (Some(source), Some(target)) = (&change.source, &change.target) {
+                    if source.file_path != target.file_path
+                        && (dirty_files.contains(source.file_path.as_str())
+                            || dirty_files.contains(target.file_path.as_str()))
+                    {
+                        dirty_files.insert(&source.file_path);
+                        dirty_files.insert(&target.file_path);
+                    }
+                }
+            }
+            if dirty_files.len() == before {
+                break;
+            }
+        }
+
+        let is_clean =
+            |function: &Function| !dirty_files.contains(function.location.file_path.as_str());
+        let clean_source: HashSet<&str> = context
+            .source_functions
+            .iter()
+            .filter(|f| is_clean(f))
+            .map(|f| f.hash.as_str())
+            .collect();
+        let clean_target: HashSet<&str> = context
+            .target_functions
+            .iter()
+            .filter(|f| is_clean(f))
+            .map(|f| f.hash.as_str())
+            .collect();
+
+        // Pairs with both ends in unchanged files keep their previous result
+        let mut result = MatchResult::new();
+        for (source_hash, target_hash) in &previous_match.mapping {
+            if clean_source.contains(source_hash.as_str())
+                && clean_target.contains(target_hash.as_str())
+            {
+                result
+                    .mapping
+                    .insert(source_hash.clone(), target_hash.clone());
+            }
+        }
+        result.changes.extend(
+            previous_match
+                .changes
+                .iter()
+                .filter(|change| match (&change.source, &change.target) {
+                    (Some(source), Some(target)) => {
+                        result.mapping.get(&source.hash) == Some(&target.hash)
+                    }
+                    _ => false,
+                })
+                .cloned(),
+        );
+
+        // Everything else goes back through the matcher
+        let reused_targets: HashSet<&String> = result.mapping.values().collect();
+        let remaining_source: Vec<Function> = context
+            .source_functions
+            .iter()
+            .filter(|f| !is_clean(f) || !result.mapping.contains_key(&f.hash))
+            .cloned()
+            .collect();
+        let remaining_target: Vec<Function> = context
+            .target_functions
+            .iter()
+            .filter(|f| !is_clean(f) || !reused_targets.contains(&f.hash))
+            .cloned()
+            .collect();
+
+        let stats = IncrementalStats {
+            reused_files: all_files.len().saturating_sub(dirty_files.len()),
+            rematched_files: dirty_files.len(),
+            reused_matches: result.mapping.len(),
+            rematched_source_functions: remaining_source.len(),
+            rematched_target_functions: remaining_target.len(),
+        };
+
+        let rematched = self
+            .smart_matcher
+            .lock()
+            .map_err(|e| anyhow::anyhow!("Lock poisoned: {}", e))?
+            .match_functions(&remaining_source, &remaining_target);
+
+        result.mapping.extend(rematched.mapping);
+        result.changes.extend(rematched.changes);
+        result.unmatched_source = rematched.unmatched_source;
+        result.unmatched_target = rematched.unmatched_target;
+        result.calculate_similarity();
+
+        Ok((result, stats))
+    }
+
+    /// Get a comparison context, marking it as accessed
+    ///
+    /// A comparison evicted to the cache is brought back into memory.
     pub fn get_comparison(&self, id: ComparisonId) -> Result<ComparisonContext> {
-        self.contexts
+        let mut contexts = self
+            .contexts
+            .write()
+            .map_err(|e| anyhow::anyhow!("Lock poisoned: {}", e))?;
+        if let Some(stored) = contexts.get_mut(&id) {
+            stored.last_accessed = self.clock.now();
+            return Ok(stored.context.clone());
+        }
+
+        let context = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.load(id))
+            .ok_or_else(|| anyhow::anyhow!("Comparison {} not found", id))?;
+        debug!("Comparison {} read back from the cache", id);
+        if let Some(cache) = &self.cache {
+            cache.remove(id);
+        }
+        contexts.insert(
+            id,
+            StoredComparison {
+                context: context.clone(),
+                last_accessed: self.clock.now(),
+            },
+        );
+        self.evict_over_capacity(&mut contexts);
+        Ok(context)
+    }
+
+    /// Parameters of a comparison, without marking it as accessed
+    pub fn get_comparison_params(&self, id: ComparisonId) -> Result<ComparisonParams> {
+        let in_memory = self
+            .contexts
             .read()
             .map_err(|e| anyhow::anyhow!("Lock poisoned: {}", e))?
             .get(&id)
-            .cloned()
+            .map(|stored| stored.context.params.clone());
+        in_memory
+            .or_else(|| Some(self.cache.as_ref()?.load(id)?.params))
             .ok_or_else(|| anyhow::anyhow!("Comparison {} not found", id))
     }
 
-    /// List all comparisons
+    /// List all comparisons, in memory or in the cache
     pub fn list_comparisons(&self) -> Result<Vec<ComparisonId>> {
-        Ok(self
+        let mut ids: Vec<ComparisonId> = self
             .contexts
             .read()
             .map_err(|e| anyhow::anyhow!("Lock poisoned: {}", e))?
             .keys()
             .copied()
-            .collect())
+            .collect();
+        if let Some(cache) = &self.cache {
+            let in_memory: HashSet<ComparisonId> = ids.iter().copied().collect();
+            ids.extend(cache.ids().into_iter().filter(|id| !in_memory.contains(id)));
+        }
+        Ok(ids)
     }
 
     /// Delete a comparison
     #[allow(dead_code)]
     pub fn delete_comparison(&self, id: ComparisonId) -> Result<()> {
-        self.contexts
+        let in_memory = self
+            .contexts
             .write()
             .map_err(|e| anyhow::anyhow!("Lock poisoned: {}", e))?
             .remove(&id)
-            .ok_or_else(|| anyhow::anyhow!("Comparison {} not found", id))?;
+            .is_some();
+        let cached = self.cache.as_ref().is_some_and(|cache| cache.remove(id));
+        if !in_memory && !cached {
+            return Err(anyhow::anyhow!("Comparison {} not found", id));
+        }
         Ok(())
     }
 
     /// Parse a location (file or directory) and extract functions
+    ///
+    /// Also returns the content hash of every parsed file. Files whose hash
+    /// matches `previous` reuse its functions instead of being parsed again.
     async fn parse_location(
         &self,
         path: &str,
         _params: &ComparisonParams,
         base_path: &Path,
-    ) -> Result<Vec<Function>> {
+        previous: Option<&PreviousSide<'_>>,
+    ) -> Result<ParsedLocation> {
         let path = Path::new(path);
 
         if !path.exists() {
             return Err(anyhow::anyhow!("Path does not exist: {}", path.display()));
         }
 
-        let mut all_functions = Vec::new();
+        let mut location = ParsedLocation::default();
 
         if path.is_file() {
             // Parse single file
-            let functions = self.parse_file(path, base_path).await?;
-            all_functions.extend(functions);
+            let parsed = self.parse_file(path, base_path, previous).await?;
+            location.add(parsed);
         } else if path.is_dir() {
             // Parse directory recursively (always recursive for directories)
             for entry in WalkDir::new(path)
@@ -166,40 +674,82 @@ impl ComparisonManager {
                 .into_iter()
                 .filter_map(|e| e.ok())
             {
-                if entry.file_type().is_file() {
-                    if let Some(ext) = entry.path().extension() {
-                        let ext_str = ext.to_str().unwrap_or("");
-                        if self.is_supported_extension(ext_str) {
-                            match self.parse_file(entry.path(), base_path).await {
-                                Ok(functions) => {
-                                    all_functions.extend(functions);
-                                }
-                                Err(e) => {
-                                    warn!("Failed to parse {}: {}", entry.path().display(), e);
-                                }
-                            }
+                if entry.file_type().is_file() && self.is_supported_path(entry.path()) {
+                    match self.parse_file(entry.path(), base_path, previous).await {
+                        Ok(parsed) => location.add(parsed),
+                        Err(e) => {
+                            warn!("Failed to parse {}: {}", entry.path().display(), e);
                         }
                     }
                 }
             }
         }
 
-        Ok(all_functions)
+        Ok(location)
     }
 
     /// Parse a single file and extract functions
-    async fn parse_file(&self, path: &Path, base_path: &Path) -> Result<Vec<Function>> {
-        debug!("Parsing file: {}", path.display());
-
+    async fn parse_file(
+        &self,
+        path: &Path,
+        base_path: &Path,
+        previous: Option<&PreviousSide<'_>>,
+    ) -> Result<ParsedFile> {
         let content = tokio::fs::read_to_string(path)
             .await
             .context("Failed to read file")?;
 
+        self.parse_content(path, relative_path(path, base_path), &content, previous)
+    }
+
+    /// Parse file content and extract functions, keyed by `relative_path`
+    fn parse_content(
+        &self,
+        path: &Path,
+        relative_path: String,
+        content: &str,
+        previous: Option<&PreviousSide<'_>>,
+    ) -> Result<ParsedFile> {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        let content_hash = hasher.finish();
+
+        if let Some(functions) =
+            previous.and_then(|p| p.unchanged_functions(&relative_path, content_hash))
+        {
+            debug!(
+                "Reusing {} functions from {}",
+                functions.len(),
+                path.display()
+            );
+            let imports = previous
+                .map(|p| p.imports_of(&relative_path))
+                .unwrap_or_default();
+            let version = previous
+                .map(|p| p.version_of(&relative_path))
+                .unwrap_or_default();
+            return Ok(ParsedFile {
+                relative_path,
+                content_hash,
+                functions,
+                imports,
+                version,
+            });
+        }
+
+        debug!("Parsing file: {}", path.display());
+
         // Detect language
         let language = LanguageDetector::detect_from_path(path);
 
         if language == Language::Unknown {
-            return Ok(Vec::new());
+            return Ok(ParsedFile {
+                relative_path,
+                content_hash,
+                functions: Vec::new(),
+                imports: Vec::new(),
+                version: CodeVersion::new(),
+            });
         }
 
         // Parse the file
@@ -207,10 +757,10 @@ impl ComparisonManager {
             .parser
             .lock()
             .map_err(|e| anyhow::anyhow!("Lock poisoned: {}", e))?
-            .parse(&content, language)?;
+            .parse(content, language)?;
 
         // Extract functions from AST
-        let functions = self.extract_functions_from_ast(&parse_result.ast, path, base_path)?;
+        let functions = self.extract_functions_from_ast(&parse_result.ast, &relative_path)?;
 
         debug!(
             "Extracted {} functions from {}",
@@ -218,43 +768,49 @@ impl ComparisonManager {
             path.display()
         );
 
-        Ok(functions)
+        let imports = SymbolResolver::with_defaults()
+            .extract_file_imports(&relative_path, &parse_result)
+            .unwrap_or_else(|e| {
+                warn!("Failed to extract imports from {}: {}", path.display(), e);
+                Vec::new()
+            });
+
+        let version = CodeVersion::from_file(&relative_path, &parse_result).unwrap_or_else(|e| {
+            warn!("Failed to extract code of {}: {}", path.display(), e);
+            CodeVersion::new()
+        });
+
+        Ok(ParsedFile {
+            relative_path,
+            content_hash,
+            functions,
+            imports,
+            version,
+        })
     }
 
     /// Extract functions from an AST
     fn extract_functions_from_ast(
         &self,
         ast: &smart_diff_parser::ASTNode,
-        path: &Path,
-        base_path: &Path,
+        file_path: &str,
     ) -> Result<Vec<Function>> {
         use smart_diff_parser::NodeType;
 
         let mut functions = Vec::new();
 
-        // Make path relative to base_path
-        let file_path = if let Ok(rel_path) = path.strip_prefix(base_path) {
-            rel_path.to_string_lossy().to_string()
-        } else {
-            path.to_string_lossy().to_string()
-        };
-
         // Find all function nodes
         let function_nodes = ast.find_by_type(&NodeType::Function);
         let method_nodes = ast.find_by_type(&NodeType::Method);
 
         for node in function_nodes.iter().chain(method_nodes.iter()) {
-            // Only process function_definition nodes, not function_declarator
-            // function_declarator is just the signature without the body
-            if let Some(kind) = node.metadata.attributes.get("kind") {
-                if kind == "function_declarator" {
-                    continue; // Skip declarators, we only want full definitions
-                }
+            if node.is_function_declarator() {
+                continue;
             }
 
             if let Some(name) = node.metadata.attributes.get("name") {
-                let signature = smart_diff_parser::FunctionSignature::new(name.clone());
-                let function = Function::new(signature, (*node).clone(), file_path.clone());
+                let signature = smart_diff_parser::FunctionSignature::from_node(name.clone(), node);
+                let function = Function::new(signature, (*node).clone(), file_path.to_string());
                 functions.push(function);
             }
         }
@@ -262,8 +818,9 @@ impl ComparisonManager {
         Ok(functions)
     }
 
-    /// Check if file extension is supported
-    fn is_supported_extension(&self, ext: &str) -> bool {
+    /// Check if the file has a supported extension
+    fn is_supported_path(&self, path: &Path) -> bool {
+        let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
         matches!(
             ext.to_lowercase().as_str(),
             "rs" | "py" | "js" | "ts" | "java" | "c" | "cpp" | "h" | "hpp"
@@ -276,8 +833,6 @@ impl ComparisonManager {
         match_result: &smart_diff_parser::MatchResult,
         source_functions: &[Function],
         target_functions: &[Function],
-        _source_base: &Path,
-        _target_base: &Path,
     ) -> Result<(Vec<FunctionChange>, usize)> {
         // Create hash maps for quick lookup of full function objects
         let source_map: std::collections::HashMap<_, _> = source_functions
@@ -300,6 +855,22 @@ impl ComparisonManager {
                 let is_renamed = source.name != target.name;
                 let is_modified = similarity < 0.95; // Consider <95% similarity as modified
 
+                // Look up the full function objects to get body content from AST
+                let source_func = source_map.get(&source.hash);
+                let target_func = target_map.get(&target.hash);
+
+                let source_content = source_func.map(|f| f.body.metadata.original_text.clone());
+                let target_content = target_func.map(|f| f.body.metadata.original_text.clone());
+
+                // The name drags the similarity of a pure rename down, so the
+                // matcher may report it as a modification
+                let body_preserved = is_renamed
+                    && matches!(
+                        (&source_content, &target_content),
+                        (Some(old), Some(new))
+                            if same_apart_from_name(old, &source.name, new, &target.name)
+                    );
+
                 // Determine the most appropriate change type
                 // Priority: moved > renamed > modified
                 // Note: A function can be moved AND modified - the similarity score indicates modification level
@@ -318,17 +889,11 @@ impl ComparisonManager {
                             "renamed"
                         }
                     }
+                    _ if body_preserved => "renamed",
                     smart_diff_parser::ChangeType::Modify => "modified",
                     _ => "modified",
                 };
 
-                // Look up the full function objects to get body content from AST
-                let source_func = source_map.get(&source.hash);
-                let target_func = target_map.get(&target.hash);
-
-                let source_content = source_func.map(|f| f.body.metadata.original_text.clone());
-                let target_content = target_func.map(|f| f.body.metadata.original_text.clone());
-
                 // Mark high-similarity moves as unchanged moves (file reorganization)
                 let is_unchanged_move = is_cross_file_move && similarity >= 0.95 && !is_renamed;
                 if is_unchanged_move {
@@ -348,6 +913,11 @@ impl ComparisonManager {
                         "Function moved from {} to {} (unchanged)",
                         source.file_path, target.file_path
                     ))
+                } else if body_preserved {
+                    Some(format!(
+                        "Function renamed from '{}' to '{}' (body unchanged)",
+                        source.name, target.name
+                    ))
                 } else if is_renamed && is_modified {
                     Some(format!(
                         "Function renamed from '{}' to '{}' and modified ({:.0}% similar)",
@@ -376,6 +946,7 @@ impl ComparisonManager {
                     target_end_line: Some(target.end_line),
                     diff_summary,
                     is_unchanged_move,
+                    body_preserved,
                 });
             } else if let Some(source) = &change.source {
                 // Deleted function
@@ -399,6 +970,7 @@ impl ComparisonManager {
                     target_end_line: None,
                     diff_summary: Some("Function deleted".to_string()),
                     is_unchanged_move: false,
+                    body_preserved: false,
                 });
             } else if let Some(target) = &change.target {
                 // Added function
@@ -422,6 +994,7 @@ impl ComparisonManager {
                     target_end_line: Some(target.end_line),
                     diff_summary: Some("Function added".to_string()),
                     is_unchanged_move: false,
+                    body_preserved: false,
                 });
             }
         }
@@ -435,3 +1008,650 @@ impl Default for ComparisonManager {
         Self::new()
     }
 }
+
+/// Functions, imports and code extracted from one file, with the hash of its
+/// content
+struct ParsedFile {
+    relative_path: String,
+    content_hash: u64,
+    functions: Vec<Function>,
+    imports: Vec<ImportInfo>,
+    version: CodeVersion,
+}
+
+/// Everything parsed from one side of a comparison
+#[derive(Default)]
+struct ParsedLocation {
+    functions: Vec<Function>,
+    file_hashes: HashMap<String, u64>,
+    imports: HashMap<String, Vec<ImportInfo>>,
+    versions: HashMap<String, CodeVersion>,
+}
+
+impl ParsedLocation {
+    fn add(&mut self, parsed: ParsedFile) {
+        self.functions.extend(parsed.functions);
+        self.file_hashes
+            .insert(parsed.relative_path.clone(), parsed.content_hash);
+        self.imports
+            .insert(parsed.relative_path.clone(), parsed.imports);
+        self.versions.insert(parsed.relative_path, parsed.version);
+    }
+}
+
+/// One side of a previous comparison, indexed for reuse by file
+struct PreviousSide<'a> {
+    functions_by_file: HashMap<&'a str, Vec<&'a Function>>,
+    file_hashes: &'a HashMap<String, u64>,
+    imports: &'a HashMap<String, Vec<ImportInfo>>,
+    versions: &'a HashMap<String, CodeVersion>,
+}
+
+impl<'a> PreviousSide<'a> {
+    fn new(
+        functions: &'a [Function],
+        file_hashes: &'a HashMap<String, u64>,
+        imports: &'a HashMap<String, Vec<ImportInfo>>,
+        versions: &'a HashMap<String, CodeVersion>,
+    ) -> Self {
+        let mut functions_by_file: HashMap<&str, Vec<&Function>> = HashMap::new();
+        for function in functions {
+            functions_by_file
+                .entry(function.location.file_path.as_str())
+                .or_default()
+                .push(function);
+        }
+
+        Self {
+            functions_by_file,
+            file_hashes,
+            imports,
+            versions,
+        }
+    }
+
+    /// Previously extracted imports of a file
+    fn imports_of(&self, relative_path: &str) -> Vec<ImportInfo> {
+        self.imports.get(relative_path).cloned().unwrap_or_default()
+    }
+
+    /// Previously extracted code of a file
+    fn version_of(&self, relative_path: &str) -> CodeVersion {
+        self.versions
+            .get(relative_path)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Previously extracted functions of a file, if its content has not changed
+    fn unchanged_functions(&self, relative_path: &str, content_hash: u64) -> Option<Vec<Function>> {
+        if self.file_hashes.get(relative_path) != Some(&content_hash) {
+            return None;
+        }
+
+        Some(
+            self.functions_by_file
+                .get(relative_path)
+                .map(|functions| functions.iter().map(|f| (*f).clone()).collect())
+                .unwrap_or_default(),
+        )
+    }
+}
+
+/// Mark modified functions whose only edit is consistently renamed locals as
+/// preserving their body
+///
+/// Telling locals apart takes the symbol tables of the files of both sides,
+/// which the per-file code versions carry.
+fn mark_renamed_locals(context: &mut ComparisonContext) {
+    let ComparisonContext {
+        function_changes,
+        source_versions,
+        target_versions,
+        ..
+    } = context;
+    for change in function_changes
+        .iter_mut()
+        .filter(|change| change.change_type == "modified" && !change.body_preserved)
+    {
+        let versions = change
+            .source_file
+            .as_ref()
+            .and_then(|file| source_versions.get(file))
+            .zip(
+                change
+                    .target_file
+                    .as_ref()
+                    .and_then(|file| target_versions.get(file)),
+            );
+        let Some((source, target)) = versions else {
+            continue;
+        };
+        let (Some((source_signature, source_ast)), Some((target_signature, target_ast))) = (
+            source.function_with_signature(&change.function_name),
+            target.function_with_signature(&change.function_name),
+        ) else {
+            continue;
+        };
+
+        let mut scorer = SimilarityScorer::with_defaults(Language::Unknown);
+        scorer.set_symbol_tables(source.symbols.clone(), target.symbols.clone());
+        if scorer.same_up_to_local_renaming(
+            source_signature,
+            source_ast,
+            target_signature,
+            target_ast,
+        ) {
+            change.body_preserved = true;
+            change.diff_summary = Some(format!(
+                "Function '{}' only renames local variables",
+                change.function_name
+            ));
+        }
+    }
+}
+
+/// Whether two function texts have the same tokens once `old_name` is read as
+/// `new_name`, which also covers recursive calls
+fn same_apart_from_name(old: &str, old_name: &str, new: &str, new_name: &str) -> bool {
+    let old_tokens =
+        tokenize(old)
+            .into_iter()
+            .map(|token| if token == old_name { new_name } else { token });
+    old_tokens.eq(tokenize(new))
+}
+
+/// Path of `path` relative to `base_path`, or `path` itself if it lies outside
+fn relative_path(path: &Path, base_path: &Path) -> String {
+    if let Ok(rel_path) = path.strip_prefix(base_path) {
+        rel_path.to_string_lossy().to_string()
+    } else {
+        path.to_string_lossy().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_sources(dir: &Path, count: usize) {
+        for i in 0..count {
+            std::fs::write(
+                dir.join(format!("module_{i}.c")),
+                format!("int compute_{i}(int x) {{\n    return x * {i} + 1;\n}}\n"),
+            )
+            .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recompare_only_rematches_changed_file() {
+        let source = TempDir::new().unwrap();
+        let target = TempDir::new().unwrap();
+        write_sources(source.path(), 100);
+        write_sources(target.path(), 100);
+
+        let manager = ComparisonManager::new();
+        let params = ComparisonParams {
+            source_path: source.path().to_string_lossy().to_string(),
+            target_path: target.path().to_string_lossy().to_string(),
+            recursive: true,
+            file_patterns: Vec::new(),
+            ignore_patterns: Vec::new(),
+            min_refactoring_confidence: None,
+        };
+        let first_id = manager.create_comparison(params).await.unwrap();
+        let first = manager.get_comparison(first_id).unwrap();
+        assert_eq!(first.source_functions.len(), 100);
+        assert!(first.incremental_stats.is_none());
+
+        std::fs::write(
+            target.path().join("module_42.c"),
+            "int compute_42(int x) {\n    int y = x * 42;\n    return y + 2;\n}\n",
+        )
+        .unwrap();
+
+        let second_id = manager
+            .recompare(first.params.clone(), &first)
+            .await
+            .unwrap();
+        let second = manager.get_comparison(second_id).unwrap();
+        let stats = second.incremental_stats.expect("recompare records stats");
+
+        assert_eq!(stats.rematched_files, 1);
+        assert_eq!(stats.reused_files, 99);
+        assert_eq!(stats.reused_matches, 99);
+        assert_eq!(stats.rematched_source_functions, 1);
+        assert_eq!(stats.rematched_target_functions, 1);
+
+        assert_eq!(second.target_functions.len(), 100);
+        assert_eq!(second.function_changes.len(), 1);
+        assert_eq!(second.function_changes[0].function_name, "compute_42");
+        assert_eq!(second.function_changes[0].change_type, "modified");
+    }
+
+    #[tokio::test]
+    async fn test_lower_refactoring_confidence_reports_more_patterns() {
+        let source = TempDir::new().unwrap();
+        let target = TempDir::new().unwrap();
+        std::fs::write(
+            source.path().join("orders.py"),
+            "def total(items):\n    return sum(i.price for i in items)\n\n\
+             def tax(amount):\n    return amount * 0.2\n\n\
+             def discount(amount):\n    return amount * 0.9\n\n\
+             def fmt(order):\n    label = order.customer + ': '\n    return label + str(order.total)\n",
+        )
+        .unwrap();
+        std::fs::write(
+            target.path().join("orders.py"),
+            "def total(items):\n    subtotal = sum(i.price for i in items)\n    return subtotal * 1.1\n\n\
+             def format_order(order):\n    label = order.customer + ': '\n    return label + str(order.total)\n",
+        )
+        .unwrap();
+
+        let manager = ComparisonManager::new();
+        let compare = |min_refactoring_confidence| {
+            manager.create_comparison(ComparisonParams {
+                source_path: source.path().to_string_lossy().to_string(),
+                target_path: target.path().to_string_lossy().to_string(),
+                recursive: true,
+                file_patterns: Vec::new(),
+                ignore_patterns: Vec::new(),
+                min_refactoring_confidence,
+            })
+        };
+
+        let strict = manager
+            .get_comparison(compare(Some(0.8)).await.unwrap())
+            .unwrap();
+        let lenient = manager
+            .get_comparison(compare(Some(0.0)).await.unwrap())
+            .unwrap();
+
+        assert!(lenient.refactoring_patterns.len() > strict.refactoring_patterns.len());
+        assert!(strict
+            .refactoring_patterns
+            .iter()
+            .all(|p| p.confidence >= 0.8));
+        assert!(lenient
+            .refactoring_patterns
+            .iter()
+            .all(|p| p.confidence >= smart_diff_engine::MIN_CONFIDENCE_FLOOR));
+
+        let summary = lenient.get_summary();
+        assert_eq!(
+            summary.refactorings.iter().map(|r| r.count).sum::<usize>(),
+            lenient.refactoring_patterns.len()
+        );
+    }
+
+    fn git(repo: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args([
+                "-c",
+                "user.name=Fixture",
+                "-c",
+                "user.email=fixture@example.com",
+            ])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    fn commit_all(repo: &Path, message: &str) {
+        git(repo, &["add", "-A"]);
+        git(repo, &["commit", "-q", "-m", message]);
+    }
+
+    /// Repository with a base commit, a feature branch and a merge of it
+    fn fixture_repo() -> TempDir {
+        let repo = TempDir::new().unwrap();
+        let path = repo.path();
+        git(path, &["init", "-q", "-b", "main"]);
+
+        std::fs::write(
+            path.join("math.py"),
+            "def add(a, b):\n    return a + b\n\n\ndef sub(a, b):\n    return a - b\n",
+        )
+        .unwrap();
+        std::fs::write(path.join("util.py"), "def identity(x):\n    return x\n").unwrap();
+        std::fs::write(path.join("README.md"), "fixture\n").unwrap();
+        commit_all(path, "base");
+        git(path, &["tag", "base"]);
+
+        git(path, &["checkout", "-q", "-b", "feature"]);
+        std::fs::write(
+            path.join("math.py"),
+            "def add(a, b):\n    total = a + b\n    return total\n\n\ndef mul(a, b):\n    return a * b\n",
+        )
+        .unwrap();
+        commit_all(path, "feature");
+
+        git(path, &["checkout", "-q", "main"]);
+        std::fs::write(path.join("README.md"), "fixture repository\n").unwrap();
+        commit_all(path, "docs");
+        git(
+            path,
+            &["merge", "-q", "--no-ff", "-m", "merge feature", "feature"],
+        );
+
+        repo
+    }
+
+    #[tokio::test]
+    async fn test_compare_commits_reports_changed_functions() {
+        let repo = fixture_repo();
+        let manager = ComparisonManager::new();
+        let repo_path = repo.path().to_string_lossy().to_string();
+
+        let id = manager
+            .create_git_comparison(GitComparisonParams {
+                repo_path: repo_path.clone(),
+                base_revision: Some("base".to_string()),
+                head_revision: "feature".to_string(),
+                parent: 1,
+                min_refactoring_confidence: None,
+            })
+            .await
+            .unwrap();
+        let context = manager.get_comparison(id).unwrap();
+
+        // util.py is untouched between the commits, so it is never parsed
+        assert_eq!(
+            context.source_file_hashes.keys().collect::<Vec<_>>(),
+            vec!["math.py"]
+        );
+
+        let mut changes: Vec<(&str, &str)> = context
+            .function_changes
+            .iter()
+            .map(|c| (c.function_name.as_str(), c.change_type.as_str()))
+            .collect();
+        changes.sort();
+        assert_eq!(
+            changes,
+            vec![("add", "modified"), ("mul", "added"), ("sub", "deleted")]
+        );
+
+        // A merge commit is compared against its first parent by default, which
+        // brings in the feature branch; its second parent only differs in docs
+        let merge = manager
+            .create_git_comparison(GitComparisonParams {
+                repo_path: repo_path.clone(),
+                base_revision: None,
+                head_revision: "main".to_string(),
+                parent: 1,
+                min_refactoring_confidence: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            manager
+                .get_comparison(merge)
+                .unwrap()
+                .function_changes
+                .len(),
+            3
+        );
+
+        let second_parent = manager
+            .create_git_comparison(GitComparisonParams {
+                repo_path,
+                base_revision: None,
+                head_revision: "main".to_string(),
+                parent: 2,
+                min_refactoring_confidence: None,
+            })
+            .await
+            .unwrap();
+        let context = manager.get_comparison(second_parent).unwrap();
+        assert!(context.function_changes.is_empty());
+        assert!(context.source_file_hashes.is_empty());
+    }
+
+    /// Clock that only moves when advanced
+    struct MockClock(Mutex<Instant>);
+
+    impl MockClock {
+        fn advance(&self, duration: std::time::Duration) {
+            *self.0.lock().unwrap() += duration;
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_idle_comparisons_are_evicted() {
+        use std::time::Duration;
+
+        let source = TempDir::new().unwrap();
+        let target = TempDir::new().unwrap();
+        write_sources(source.path(), 2);
+        write_sources(target.path(), 2);
+
+        let clock = Arc::new(MockClock(Mutex::new(Instant::now())));
+        let manager = ComparisonManager::with_eviction(EvictionConfig {
+            idle_ttl: Duration::from_secs(600),
+            max_comparisons: 3,
+            sweep_interval: Duration::from_secs(60),
+            cache_dir: None,
+        })
+        .with_clock(clock.clone());
+        let params = ComparisonParams {
+            source_path: source.path().to_string_lossy().to_string(),
+            target_path: target.path().to_string_lossy().to_string(),
+            recursive: true,
+            file_patterns: Vec::new(),
+            ignore_patterns: Vec::new(),
+            min_refactoring_confidence: None,
+        };
+
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            ids.push(manager.create_comparison(params.clone()).await.unwrap());
+        }
+        assert_eq!(manager.stats().unwrap().comparisons, 3);
+
+        // Reading one comparison keeps it alive past the others' TTL
+        clock.advance(Duration::from_secs(400));
+        manager.get_comparison(ids[1]).unwrap();
+        clock.advance(Duration::from_secs(400));
+
+        assert_eq!(manager.evict_idle().unwrap(), 2);
+        assert_eq!(manager.list_comparisons().unwrap(), vec![ids[1]]);
+        assert!(manager.get_comparison(ids[0]).is_err());
+        let stats = manager.stats().unwrap();
+        assert_eq!((stats.comparisons, stats.evicted), (1, 2));
+
+        // Above the cap, the least recently accessed comparison goes first
+        let mut newer = Vec::new();
+        for _ in 0..2 {
+            clock.advance(Duration::from_secs(1));
+            newer.push(manager.create_comparison(params.clone()).await.unwrap());
+        }
+        clock.advance(Duration::from_secs(1));
+        manager.get_comparison(ids[1]).unwrap();
+        clock.advance(Duration::from_secs(1));
+        let newest = manager.create_comparison(params).await.unwrap();
+
+        let mut remaining = manager.list_comparisons().unwrap();
+        remaining.sort_by_key(|id| id.to_string());
+        let mut expected = vec![ids[1], newer[1], newest];
+        expected.sort_by_key(|id| id.to_string());
+        assert_eq!(remaining, expected);
+        assert_eq!(manager.stats().unwrap().evicted, 3);
+    }
+
+    #[tokio::test]
+    async fn test_evicted_comparisons_round_trip_through_the_cache() {
+        use smart_diff_engine::binary_format;
+        use std::time::Duration;
+
+        let (source, target, cache) = (
+            TempDir::new().unwrap(),
+            TempDir::new().unwrap(),
+            TempDir::new().unwrap(),
+        );
+        write_sources(source.path(), 2);
+        write_sources(target.path(), 3);
+        let manager = ComparisonManager::with_eviction(EvictionConfig {
+            idle_ttl: Duration::from_secs(600),
+            max_comparisons: 1,
+            sweep_interval: Duration::from_secs(60),
+            cache_dir: Some(cache.path().to_path_buf()),
+        });
+        let params = ComparisonParams {
+            source_path: source.path().to_string_lossy().to_string(),
+            target_path: target.path().to_string_lossy().to_string(),
+            recursive: true,
+            file_patterns: Vec::new(),
+            ignore_patterns: Vec::new(),
+            min_refactoring_confidence: None,
+        };
+
+        let first = manager.create_comparison(params.clone()).await.unwrap();
+        let original = manager.get_comparison(first).unwrap();
+        assert!(!original.function_changes.is_empty());
+        let second = manager.create_comparison(params).await.unwrap();
+        let cached = cache.path().join(format!("{}.sdiffbin", first));
+        assert!(cached.exists());
+        assert_eq!(manager.stats().unwrap().comparisons, 1);
+
+        // Reading it back swaps it with the other comparison
+        let restored = manager.get_comparison(first).unwrap();
+        assert!(!cached.exists());
+        assert_eq!(restored.id, original.id);
+        assert_eq!(restored.params, original.params);
+        assert_eq!(restored.source_functions, original.source_functions);
+        assert_eq!(restored.target_functions, original.target_functions);
+        assert_eq!(restored.function_changes, original.function_changes);
+        assert_eq!(
+            restored.match_result.as_ref().map(|result| &result.changes),
+            original.match_result.as_ref().map(|result| &result.changes)
+        );
+        assert_eq!(restored.source_file_hashes, original.source_file_hashes);
+        assert_eq!(restored.target_imports, original.target_imports);
+        assert_eq!(
+            restored.target_versions["module_2.c"].functions,
+            original.target_versions["module_2.c"].functions
+        );
+        assert_eq!(restored.created_at, original.created_at);
+
+        // A cache written by another version of the format is discarded
+        let evicted = cache.path().join(format!("{}.sdiffbin", second));
+        let mut bytes = std::fs::read(&evicted).unwrap();
+        bytes[binary_format::MAGIC.len()] += 1;
+        std::fs::write(&evicted, bytes).unwrap();
+        assert!(manager.get_comparison(second).is_err());
+        assert!(!evicted.exists());
+        assert_eq!(manager.list_comparisons().unwrap(), vec![first]);
+    }
+
+    #[tokio::test]
+    async fn test_pure_rename_ranks_below_equally_distant_body_change() {
+        let (source, target) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let total = |name: &str| {
+            format!(
+                "int {name}(int count, int unit, int discount) {{\n    int price = count * unit;\n    \
+                 if (discount > 0) {{\n        price = price - discount;\n    }}\n    \
+                 if (price < 0) {{\n        price = 0;\n    }}\n    int tax = price / 10;\n    \
+                 return price + tax;\n}}\n"
+            )
+        };
+        let clamp = |upper: &str| {
+            format!(
+                "int clamp(int value, int lower, int upper) {{\n    int result = value;\n    \
+                 if (result < lower) {{\n        result = lower;\n    }}\n    \
+                 if (result > {upper}) {{\n        result = upper;\n    }}\n    return result;\n}}\n"
+            )
+        };
+        // The rename and the body change each replace the same number of characters
+        std::fs::write(
+            source.path().join("calc.c"),
+            format!("{}\n{}", total("discounted_order_price"), clamp("upper")),
+        )
+        .unwrap();
+        std::fs::write(
+            target.path().join("calc.c"),
+            format!("{}\n{}", total("discounted_order_total"), clamp("lower")),
+        )
+        .unwrap();
+
+        let manager = ComparisonManager::new();
+        let id = manager
+            .create_comparison(ComparisonParams {
+                source_path: source.path().to_string_lossy().to_string(),
+                target_path: target.path().to_string_lossy().to_string(),
+                recursive: true,
+                file_patterns: Vec::new(),
+                ignore_patterns: Vec::new(),
+                min_refactoring_confidence: None,
+            })
+            .await
+            .unwrap();
+        let context = manager.get_comparison(id).unwrap();
+
+        let renamed = context
+            .get_function_change("discounted_order_price")
+            .unwrap();
+        assert_eq!(renamed.change_type, "renamed");
+        assert!(renamed.body_preserved);
+        let modified = context.get_function_change("clamp").unwrap();
+        assert_eq!(modified.change_type, "modified");
+        assert!(
+            renamed.change_magnitude < modified.change_magnitude,
+            "rename {} vs modification {}",
+            renamed.change_magnitude,
+            modified.change_magnitude
+        );
+    }
+
+    #[tokio::test]
+    async fn test_renamed_locals_preserve_the_body() {
+        let (source, target) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let worker = |local: &str, factor: &str| {
+            format!(
+                "public class Worker {{\n    int weight(int item) {{\n        return item * 2;\n    }}\n\n    \
+                 int price(int item) {{\n        return item * 3;\n    }}\n\n    \
+                 int run(int count) {{\n        int {local} = 0;\n        \
+                 for (int i = 0; i < count; i++) {{\n            {local} = {local} + {factor}(i);\n        }}\n        \
+                 return {local};\n    }}\n}}\n"
+            )
+        };
+        std::fs::write(source.path().join("Worker.java"), worker("total", "weight")).unwrap();
+        std::fs::write(target.path().join("Worker.java"), worker("sum", "weight")).unwrap();
+
+        let manager = ComparisonManager::new();
+        let params = |target: &TempDir| ComparisonParams {
+            source_path: source.path().to_string_lossy().to_string(),
+            target_path: target.path().to_string_lossy().to_string(),
+            recursive: true,
+            file_patterns: Vec::new(),
+            ignore_patterns: Vec::new(),
+            min_refactoring_confidence: None,
+        };
+        let id = manager.create_comparison(params(&target)).await.unwrap();
+        let context = manager.get_comparison(id).unwrap();
+        let run = context.get_function_change("run").unwrap();
+        assert_eq!(run.change_type, "modified");
+        assert!(run.body_preserved);
+        assert_eq!(run.change_magnitude, 0.0);
+
+        // A call to another function is a real edit
+        let called = TempDir::new().unwrap();
+        std::fs::write(called.path().join("Worker.java"), worker("total", "price")).unwrap();
+        let id = manager.create_comparison(params(&called)).await.unwrap();
+        let context = manager.get_comparison(id).unwrap();
+        let run = context.get_function_change("run").unwrap();
+        assert!(!run.body_preserved);
+        assert!(run.change_magnitude > 0.0);
+    }
+}
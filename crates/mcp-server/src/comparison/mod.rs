@@ -1,12 +1,17 @@
 //! Comparison context and state management
 
 pub mod binary_comparison;
+pub mod cache;
 pub mod context;
+pub mod eviction;
+pub mod git;
 pub mod manager;
 
 pub use binary_comparison::{
     BinaryComparisonContext, BinaryComparisonId, BinaryComparisonManager, BinaryComparisonParams,
     BinaryComparisonSummary,
 };
-pub use context::{ComparisonId, ComparisonParams};
+pub use context::{ComparisonContext, ComparisonId, ComparisonParams, FunctionChange};
+pub use eviction::EvictionConfig;
+pub use git::GitComparisonParams;
 pub use manager::ComparisonManager;
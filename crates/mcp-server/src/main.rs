@@ -3,12 +3,19 @@
 //! This server implements the Model Context Protocol (MCP) to provide
 //! intelligent code comparison capabilities to AI agents.
 //!
-//! The server uses stdio transport as per MCP specification.
+//! The server uses stdio transport as per MCP specification. With `--sse
+//! [ADDR]` it serves Server-Sent Events over HTTP instead, on 127.0.0.1:8011
+//! unless an address is given. SSE suits long-running operations, as it has
+//! no stdio timeouts, and can stream results while a request runs.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower_http::cors::{Any, CorsLayer};
 
 mod comparison;
 mod mcp;
+mod prompts;
 mod resources;
 mod server;
 mod tools;
@@ -23,6 +30,17 @@ async fn main() -> Result<()> {
         )
         .init();
 
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("--sse") {
+        let addr = match args.next() {
+            Some(addr) => addr
+                .parse()
+                .with_context(|| format!("Invalid SSE address: {}", addr))?,
+            None => SocketAddr::from(([127, 0, 0, 1], 8011)),
+        };
+        return run_sse(addr).await;
+    }
+
     tracing::info!("Starting Smart Diff MCP Server");
 
     // Create and run the MCP server with stdio transport
@@ -31,3 +49,25 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Serve MCP over SSE on `addr`
+async fn run_sse(addr: SocketAddr) -> Result<()> {
+    tracing::info!("Starting Smart Diff MCP Server with SSE transport");
+
+    let server = Arc::new(server::McpServer::new());
+    let _eviction = server.spawn_eviction_task();
+
+    let app = mcp::sse_transport::create_sse_router(server).layer(
+        CorsLayer::new()
+            .allow_origin(Any)
+            .allow_methods(Any)
+            .allow_headers(Any),
+    );
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("SSE endpoint: http://{}/sse", addr);
+    tracing::info!("Message endpoint: http://{}/message", addr);
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
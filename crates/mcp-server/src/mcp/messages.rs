@@ -108,7 +108,6 @@ impl JsonRpcResponse {
 }
 
 impl JsonRpcNotification {
-    #[allow(dead_code)]
     pub fn new(method: String, params: Option<Value>) -> Self {
         Self {
             jsonrpc: "2.0".to_string(),
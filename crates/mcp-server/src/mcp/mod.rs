@@ -5,4 +5,5 @@
 
 pub mod messages;
 pub mod protocol;
+pub mod sse_transport;
 pub mod transport;
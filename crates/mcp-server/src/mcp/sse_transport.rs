@@ -1,10 +1,17 @@
 //! SSE (Server-Sent Events) transport for MCP communication
+//!
+//! Clients hold `/sse` open and post requests to `/message`. Every message
+//! for the client, responses and notifications alike, is sent as a `message`
+//! event whose data is one line of JSON, so the event stream reads as NDJSON.
+//!
+//! Unlike stdio, SSE can deliver results while a request is still being
+//! handled: a `list_changed_functions` call with `"stream": true` sends each
+//! function as its own notification ahead of the response.
 
-use super::messages::{JsonRpcMessage, JsonRpcRequest, JsonRpcResponse};
-use anyhow::Result;
+use super::messages::JsonRpcMessage;
 use axum::{
     extract::State,
-    http::{header, StatusCode},
+    http::StatusCode,
     response::{
         sse::{Event, KeepAlive, Sse},
         IntoResponse, Response,
@@ -12,8 +19,6 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
-use futures::stream::{Stream, StreamExt};
-use serde_json::Value;
 use std::convert::Infallible;
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
@@ -49,9 +54,7 @@ pub fn create_sse_router(server: Arc<McpServer>) -> Router {
 }
 
 /// SSE endpoint handler
-async fn sse_handler(
-    State(state): State<SseTransportState>,
-) -> impl IntoResponse {
+async fn sse_handler(State(state): State<SseTransportState>) -> impl IntoResponse {
     info!("SSE client connected");
 
     let (tx, mut rx) = mpsc::unbounded_channel::<JsonRpcMessage>();
@@ -92,17 +95,21 @@ async fn message_handler(
     debug!("Received message: {:?}", message);
 
     // Handle the request
-    let response = match message {
+    match message {
         JsonRpcMessage::Request(request) => {
-            let response = state.server.handle_request(request).await;
-            
+            let tx = state.response_tx.read().await.clone();
+            let Some(tx) = tx else {
+                // Nothing to stream to without a connected SSE client
+                return Json(state.server.handle_request(request).await).into_response();
+            };
+
+            let response = state.server.handle_request_streaming(request, &tx).await;
+
             // Send response via SSE
-            if let Some(tx) = state.response_tx.read().await.as_ref() {
-                if let Err(e) = tx.send(JsonRpcMessage::Response(response.clone())) {
-                    error!("Failed to send response via SSE: {}", e);
-                }
+            if let Err(e) = tx.send(JsonRpcMessage::Response(response.clone())) {
+                error!("Failed to send response via SSE: {}", e);
             }
-            
+
             // Also return as HTTP response
             Json(response).into_response()
         }
@@ -115,8 +122,184 @@ async fn message_handler(
             error!("Received unexpected response message");
             StatusCode::BAD_REQUEST.into_response()
         }
-    };
-
-    response
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::CHANGED_FUNCTION_NOTIFICATION;
+    use axum::body::{Body, BodyDataStream};
+    use axum::http::Request;
+    use futures::StreamExt;
+    use serde_json::{json, Value};
+    use tempfile::TempDir;
+    use tower::Service;
+
+    /// Events of an SSE response, read as they arrive
+    struct EventReader {
+        body: BodyDataStream,
+        buffer: String,
+    }
+
+    impl EventReader {
+        /// Name and data of the next event
+        async fn next(&mut self) -> (String, String) {
+            loop {
+                if let Some(end) = self.buffer.find("\n\n") {
+                    let event: String = self.buffer.drain(..end + 2).collect();
+                    let field = |name: &str| {
+                        event
+                            .lines()
+                            .find_map(|line| line.strip_prefix(name))
+                            .map(|value| value.trim_start().to_string())
+                            .unwrap_or_default()
+                    };
+                    return (field("event:"), field("data:"));
+                }
+                let chunk = self.body.next().await.expect("SSE stream ended").unwrap();
+                self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+            }
+        }
+
+        async fn next_message(&mut self) -> Value {
+            let (name, data) = self.next().await;
+            assert_eq!(name, "message");
+            assert!(!data.contains('\n'));
+            serde_json::from_str(&data).unwrap()
+        }
+    }
+
+    async fn post(router: &mut Router, message: &Value) -> Value {
+        let request = Request::post("/message")
+            .header("content-type", "application/json")
+            .body(Body::from(message.to_string()))
+            .unwrap();
+        let response = router.call(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn result_text(response: &Value) -> String {
+        response["result"]["content"][0]["text"]
+            .as_str()
+            .unwrap_or_else(|| panic!("no result text: {}", response))
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_streamed_functions_arrive_one_by_one_and_match_batched_list() {
+        let (source, target) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        std::fs::write(
+            source.path().join("calc.c"),
+            "int scale(int x) {\n    return x * 2;\n}\n\n\
+             int legacy_total(int a, int b, int c) {\n    return a + b + c;\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            target.path().join("calc.c"),
+            "int scale(int x) {\n    return x * 3;\n}\n\n\
+             const char *greeting(void) {\n    return \"hello\";\n}\n",
+        )
+        .unwrap();
+
+        let server = Arc::new(McpServer::new());
+        let mut router = create_sse_router(server.clone());
+        let sse = router
+            .call(Request::get("/sse").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let mut events = EventReader {
+            body: sse.into_body().into_data_stream(),
+            buffer: String::new(),
+        };
+        assert_eq!(events.next().await.0, "endpoint");
+
+        let created = post(
+            &mut router,
+            &json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": {
+                    "name": "compare_locations",
+                    "arguments": {
+                        "source_path": source.path(),
+                        "target_path": target.path(),
+                    },
+                },
+            }),
+        )
+        .await;
+        let created_text = result_text(&created);
+        let comparison_id = created_text
+            .lines()
+            .find_map(|line| line.strip_prefix("Comparison ID: "))
+            .unwrap();
+        assert_eq!(events.next_message().await, created);
+
+        let list = |id: i64, stream: bool| {
+            json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "tools/call",
+                "params": {
+                    "name": "list_changed_functions",
+                    "arguments": {"comparison_id": comparison_id, "stream": stream},
+                },
+            })
+        };
+        let batched = post(&mut router, &list(2, false)).await;
+        let batched_text = result_text(&batched);
+        assert_eq!(events.next_message().await, batched);
+
+        let streamed = post(&mut router, &list(3, true)).await;
+
+        // Each function is an event of its own, ahead of the response
+        let mut functions = Vec::new();
+        loop {
+            let message = events.next_message().await;
+            if message["method"] != CHANGED_FUNCTION_NOTIFICATION {
+                assert_eq!(message, streamed);
+                break;
+            }
+            let params = &message["params"];
+            assert_eq!(params["requestId"], 3);
+            assert_eq!(params["tool"], "list_changed_functions");
+            assert_eq!(params["sequence"], functions.len());
+            functions.push(params.clone());
+        }
+        assert!(functions.len() >= 2, "{}", batched_text);
+
+        // Together they list the batched functions, in the same order
+        let streamed_lines: Vec<String> = functions
+            .iter()
+            .map(|event| {
+                let function = &event["function"];
+                format!(
+                    "{}. {} - {} (magnitude: {:.2}, similarity: {:.2})",
+                    event["sequence"].as_u64().unwrap() + 1,
+                    function["function_name"].as_str().unwrap(),
+                    function["change_type"].as_str().unwrap(),
+                    function["change_magnitude"].as_f64().unwrap(),
+                    function["similarity_score"].as_f64().unwrap(),
+                )
+            })
+            .collect();
+        let batched_lines: Vec<String> = batched_text
+            .lines()
+            .filter(|line| line.starts_with(|c: char| c.is_ascii_digit()))
+            .map(String::from)
+            .collect();
+        assert_eq!(streamed_lines, batched_lines);
+        let header = batched_text.lines().next().unwrap().trim_end_matches(':');
+        assert!(result_text(&streamed).starts_with(header));
+
+        // Without a stream to send to, as over stdio, the list is batched
+        let request = serde_json::from_value(list(4, true)).unwrap();
+        let response = serde_json::to_value(server.handle_request(request).await).unwrap();
+        assert_eq!(result_text(&response), batched_text);
+    }
+}
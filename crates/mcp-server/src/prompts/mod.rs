@@ -0,0 +1,386 @@
+//! MCP prompts implementation
+//!
+//! Built-in templates for common review workflows. Each takes the id of a
+//! comparison and renders a single user message grounded in its results, so
+//! the client's model reasons about the actual changed functions rather than
+//! having to fetch them first.
+
+use crate::comparison::{ComparisonContext, ComparisonId, ComparisonManager, FunctionChange};
+use crate::mcp::protocol::{
+    GetPromptResult, PromptArgument, PromptInfo, PromptMessage, PromptRole, ToolContent,
+};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::info;
+
+/// Changed functions listed in a rendered prompt, most changed first
+const MAX_LISTED_FUNCTIONS: usize = 50;
+
+/// Modified functions whose diff is included in the review prompt
+const MAX_REVIEW_DIFFS: usize = 10;
+
+/// Prompt handler
+pub struct PromptHandler {
+    comparison_manager: Arc<ComparisonManager>,
+}
+
+impl PromptHandler {
+    pub fn new(comparison_manager: Arc<ComparisonManager>) -> Self {
+        Self { comparison_manager }
+    }
+
+    /// List all available prompts
+    pub fn list_prompts(&self) -> Vec<PromptInfo> {
+        let comparison_id = || {
+            vec![PromptArgument {
+                name: "comparison_id".to_string(),
+                description: Some(
+                    "ID of a comparison created with compare_locations or compare_commits"
+                        .to_string(),
+                ),
+                required: Some(true),
+            }]
+        };
+
+        vec![
+            PromptInfo {
+                name: "review_changes".to_string(),
+                title: Some("Review Changes".to_string()),
+                description: Some(
+                    "Review the changed functions of a comparison for bugs, risks and missing tests"
+                        .to_string(),
+                ),
+                arguments: comparison_id(),
+            },
+            PromptInfo {
+                name: "summarize_breaking_changes".to_string(),
+                title: Some("Summarize Breaking Changes".to_string()),
+                description: Some(
+                    "Summarize the changes of a comparison that can break callers".to_string(),
+                ),
+                arguments: comparison_id(),
+            },
+            PromptInfo {
+                name: "suggest_refactorings".to_string(),
+                title: Some("Suggest Refactorings".to_string()),
+                description: Some(
+                    "Suggest follow-up refactorings for the functions a comparison changed"
+                        .to_string(),
+                ),
+                arguments: comparison_id(),
+            },
+        ]
+    }
+
+    /// Render a prompt with its arguments
+    pub fn get_prompt(
+        &self,
+        name: &str,
+        arguments: &HashMap<String, String>,
+    ) -> Result<GetPromptResult> {
+        info!("Rendering prompt: {}", name);
+
+        let render: fn(&ComparisonContext) -> String = match name {
+            "review_changes" => render_review,
+            "summarize_breaking_changes" => render_breaking_changes,
+            "suggest_refactorings" => render_refactoring_suggestions,
+            _ => return Err(anyhow::anyhow!("Unknown prompt: {}", name)),
+        };
+
+        let comparison_id_str = arguments
+            .get("comparison_id")
+            .ok_or_else(|| anyhow::anyhow!("Missing comparison_id"))?;
+        let comparison_id: ComparisonId =
+            serde_json::from_str(&format!("\"{}\"", comparison_id_str))?;
+        let context = self.comparison_manager.get_comparison(comparison_id)?;
+
+        let description = self
+            .list_prompts()
+            .into_iter()
+            .find(|prompt| prompt.name == name)
+            .and_then(|prompt| prompt.description);
+
+        Ok(GetPromptResult {
+            description,
+            messages: vec![PromptMessage {
+                role: PromptRole::User,
+                content: ToolContent::Text {
+                    text: render(&context),
+                },
+            }],
+        })
+    }
+}
+
+fn render_review(context: &ComparisonContext) -> String {
+    let changes = listed_changes(context);
+    let mut text = format!(
+        "Review the following code changes between {} and {}. \
+        For each changed function, point out bugs, behavior changes callers may not expect, \
+        and cases that deserve new tests. Finish with an overall assessment.\n\n",
+        context.params.source_path, context.params.target_path
+    );
+    push_summary(&mut text, context);
+    push_function_list(&mut text, &changes);
+
+    let diffs: Vec<&FunctionChange> = changes
+        .iter()
+        .filter(|change| change.change_type == "modified")
+        .take(MAX_REVIEW_DIFFS)
+        .collect();
+    if !diffs.is_empty() {
+        text.push_str("\nDiffs of the most changed functions:\n");
+        for change in diffs {
+            if let (Some(source), Some(target)) = (&change.source_content, &change.target_content) {
+                text.push_str(&format!(
+                    "\n{}:\n```diff\n{}```\n",
+                    change.function_name,
+                    unified_diff(source, target)
+                ));
+            }
+        }
+    }
+
+    text
+}
+
+fn render_breaking_changes(context: &ComparisonContext) -> String {
+    let mut text = format!(
+        "Summarize the breaking changes between {} and {}: changes that force callers to be \
+        updated or that alter behavior they rely on. Group them by severity and suggest a \
+        migration note for each.\n\n",
+        context.params.source_path, context.params.target_path
+    );
+    push_summary(&mut text, context);
+
+    let changes = listed_changes(context);
+    let mut candidates = Vec::new();
+    for change in &changes {
+        let location = change
+            .source_file
+            .as_deref()
+            .or(change.target_file.as_deref())
+            .unwrap_or("unknown file");
+        match change.change_type.as_str() {
+            "deleted" => candidates.push(format!(
+                "- {} ({}): deleted",
+                change.function_name, location
+            )),
+            "renamed" | "moved" => candidates.push(format!(
+                "- {} ({}): {}",
+                change.function_name,
+                location,
+                change
+                    .diff_summary
+                    .as_deref()
+                    .unwrap_or(change.change_type.as_str())
+            )),
+            _ => {}
+        }
+        if let (Some(before), Some(after)) = (&change.source_signature, &change.target_signature) {
+            if before != after {
+                candidates.push(format!(
+                    "- {} ({}): signature changed from `{}` to `{}`",
+                    change.function_name, location, before, after
+                ));
+            }
+        }
+    }
+    for pattern in &context.refactoring_patterns {
+        if pattern.analysis.impact.is_breaking_change {
+            candidates.push(format!(
+                "- {} ({:?}, confidence {:.2})",
+                pattern.description, pattern.pattern_type, pattern.confidence
+            ));
+        }
+    }
+
+    if candidates.is_empty() {
+        text.push_str(
+            "\nNo deleted, renamed or moved functions, signature changes or breaking \
+            refactorings were detected; check the modified functions for behavior changes.\n",
+        );
+        push_function_list(&mut text, &changes);
+    } else {
+        text.push_str("\nPotentially breaking changes:\n");
+        for candidate in candidates {
+            text.push_str(&candidate);
+            text.push('\n');
+        }
+    }
+
+    text
+}
+
+fn render_refactoring_suggestions(context: &ComparisonContext) -> String {
+    let mut text = format!(
+        "Suggest follow-up refactorings for the code changed between {} and {}. \
+        Look for duplication introduced by the changes, functions that grew too large or \
+        complex, and refactorings that were started but not finished. Explain the benefit of \
+        each suggestion.\n\n",
+        context.params.source_path, context.params.target_path
+    );
+    push_summary(&mut text, context);
+    push_function_list(&mut text, &listed_changes(context));
+
+    let groups = context.get_refactoring_groups();
+    if !groups.is_empty() {
+        text.push_str("\nRefactorings already detected in these changes:\n");
+        for (pattern_type, patterns) in &groups {
+            for pattern in patterns {
+                text.push_str(&format!(
+                    "- {}: {} (confidence {:.2})\n",
+                    pattern_type, pattern.description, pattern.confidence
+                ));
+            }
+        }
+    }
+
+    text
+}
+
+/// Changes worth listing: most changed first, without unchanged moves
+fn listed_changes(context: &ComparisonContext) -> Vec<FunctionChange> {
+    let mut changes = context.get_sorted_changes();
+    changes.retain(|change| !change.is_unchanged_move);
+    changes.truncate(MAX_LISTED_FUNCTIONS);
+    changes
+}
+
+fn push_summary(text: &mut String, context: &ComparisonContext) {
+    let summary = context.get_summary();
+    text.push_str(&format!(
+        "Summary: {} functions, {} added, {} deleted, {} modified, {} renamed, {} moved, \
+        {} unchanged.\n",
+        summary.total_functions,
+        summary.added,
+        summary.deleted,
+        summary.modified,
+        summary.renamed,
+        summary.moved,
+        summary.unchanged
+    ));
+}
+
+fn push_function_list(text: &mut String, changes: &[FunctionChange]) {
+    if changes.is_empty() {
+        text.push_str("\nNo functions changed.\n");
+        return;
+    }
+
+    text.push_str("\nChanged functions:\n");
+    for change in changes {
+        let location = change
+            .target_file
+            .as_deref()
+            .or(change.source_file.as_deref())
+            .unwrap_or("unknown file");
+        text.push_str(&format!(
+            "- {} ({}): {}",
+            change.function_name, location, change.change_type
+        ));
+        if let Some(summary) = &change.diff_summary {
+            text.push_str(&format!(" - {}", summary));
+        }
+        text.push('\n');
+    }
+}
+
+fn unified_diff(source: &str, target: &str) -> String {
+    use similar::{ChangeTag, TextDiff};
+
+    let diff = TextDiff::from_lines(source, target);
+    let mut result = String::new();
+    for change in diff.iter_all_changes() {
+        let sign = match change.tag() {
+            ChangeTag::Delete => "-",
+            ChangeTag::Insert => "+",
+            ChangeTag::Equal => " ",
+        };
+        result.push_str(&format!("{}{}", sign, change));
+        if change.missing_newline() {
+            result.push('\n');
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparison::ComparisonParams;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_review_prompt_lists_the_changed_functions() {
+        let (source, target) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        std::fs::write(
+            source.path().join("calc.c"),
+            "int scale(int x) {\n    return x * 2;\n}\n\nint clamp(int x) {\n    return x > 9 ? 9 : x;\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            target.path().join("calc.c"),
+            "int scale(int x) {\n    int factor = 3;\n    return x * factor;\n}\n\nint offset(int x) {\n    return x + 1;\n}\n",
+        )
+        .unwrap();
+
+        let manager = Arc::new(ComparisonManager::new());
+        let id = manager
+            .create_comparison(ComparisonParams {
+                source_path: source.path().display().to_string(),
+                target_path: target.path().display().to_string(),
+                recursive: true,
+                file_patterns: Vec::new(),
+                ignore_patterns: Vec::new(),
+                min_refactoring_confidence: None,
+            })
+            .await
+            .unwrap();
+        let handler = PromptHandler::new(manager);
+
+        let names: Vec<String> = handler
+            .list_prompts()
+            .into_iter()
+            .map(|prompt| prompt.name)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "review_changes",
+                "summarize_breaking_changes",
+                "suggest_refactorings"
+            ]
+        );
+
+        let arguments = HashMap::from([("comparison_id".to_string(), id.as_str())]);
+        let text = |result: GetPromptResult| match &result.messages[0].content {
+            ToolContent::Text { text } => text.clone(),
+            other => panic!("unexpected content: {:?}", other),
+        };
+
+        let review = text(handler.get_prompt("review_changes", &arguments).unwrap());
+        assert!(review.contains("- scale (calc.c): modified"), "{}", review);
+        assert!(review.contains("- offset (calc.c): added"), "{}", review);
+        assert!(review.contains("- clamp (calc.c): deleted"), "{}", review);
+        assert!(review.contains("+    int factor = 3;"), "{}", review);
+
+        let breaking = text(
+            handler
+                .get_prompt("summarize_breaking_changes", &arguments)
+                .unwrap(),
+        );
+        assert!(
+            breaking.contains("- clamp (calc.c): deleted"),
+            "{}",
+            breaking
+        );
+        assert!(!breaking.contains("offset"), "{}", breaking);
+
+        assert!(handler
+            .get_prompt("review_changes", &HashMap::new())
+            .is_err());
+        assert!(handler.get_prompt("write_poem", &arguments).is_err());
+    }
+}
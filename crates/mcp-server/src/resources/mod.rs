@@ -3,9 +3,16 @@
 use crate::comparison::{ComparisonId, ComparisonManager};
 use crate::mcp::protocol::{ResourceContents, ResourceInfo, ResourceTemplate};
 use anyhow::Result;
+use smart_diff_parser::LanguageDetector;
 use std::sync::Arc;
 use tracing::info;
 
+/// Languages the server can parse, with their file extensions
+const LANGUAGES_URI: &str = "codediff://languages";
+
+/// Number of comparisons held in memory and evicted so far
+const STATS_URI: &str = "codediff://stats";
+
 /// Resource handler
 pub struct ResourceHandler {
     comparison_manager: Arc<ComparisonManager>,
@@ -18,25 +25,43 @@ impl ResourceHandler {
 
     /// List all available resources
     pub fn list_resources(&self) -> Result<Vec<ResourceInfo>> {
-        let mut resources = Vec::new();
+        let mut resources =
+            vec![ResourceInfo {
+            uri: LANGUAGES_URI.to_string(),
+            name: "Supported Languages".to_string(),
+            title: Some("Supported Languages".to_string()),
+            description: Some(
+                "Languages that can be compared and the file extensions they are detected from"
+                    .to_string(),
+            ),
+            mime_type: Some("application/json".to_string()),
+        },
+        ResourceInfo {
+            uri: STATS_URI.to_string(),
+            name: "Comparison Stats".to_string(),
+            title: Some("Comparison Stats".to_string()),
+            description: Some(
+                "Comparisons currently held in memory and comparisons evicted as idle"
+                    .to_string(),
+            ),
+            mime_type: Some("application/json".to_string()),
+        }];
 
         // List all comparisons as resources
         let comparison_ids = self.comparison_manager.list_comparisons()?;
 
         for id in comparison_ids {
-            let context = self.comparison_manager.get_comparison(id)?;
+            // Listing does not count as an access that keeps a comparison alive
+            let params = self.comparison_manager.get_comparison_params(id)?;
 
             // Add comparison summary resource
             resources.push(ResourceInfo {
                 uri: format!("codediff://comparison/{}/summary", id),
                 name: format!("Comparison {} Summary", id),
-                title: Some(format!(
-                    "{} vs {}",
-                    context.params.source_path, context.params.target_path
-                )),
+                title: Some(format!("{} vs {}", params.source_path, params.target_path)),
                 description: Some(format!(
                     "Summary of comparison between {} and {}",
-                    context.params.source_path, context.params.target_path
+                    params.source_path, params.target_path
                 )),
                 mime_type: Some("application/json".to_string()),
             });
@@ -86,6 +111,30 @@ impl ResourceHandler {
     pub fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>> {
         info!("Reading resource: {}", uri);
 
+        if uri == LANGUAGES_URI {
+            let json = serde_json::to_string_pretty(&LanguageDetector::supported())?;
+            return Ok(vec![ResourceContents {
+                uri: uri.to_string(),
+                name: "languages.json".to_string(),
+                title: Some("Supported Languages".to_string()),
+                mime_type: Some("application/json".to_string()),
+                text: Some(json),
+                blob: None,
+            }]);
+        }
+
+        if uri == STATS_URI {
+            let json = serde_json::to_string_pretty(&self.comparison_manager.stats()?)?;
+            return Ok(vec![ResourceContents {
+                uri: uri.to_string(),
+                name: "stats.json".to_string(),
+                title: Some("Comparison Stats".to_string()),
+                mime_type: Some("application/json".to_string()),
+                text: Some(json),
+                blob: None,
+            }]);
+        }
+
         // Parse URI
         if !uri.starts_with("codediff://comparison/") {
             return Err(anyhow::anyhow!("Invalid URI scheme"));
@@ -1,47 +1,67 @@
 //! Main MCP server implementation
 
-use crate::comparison::ComparisonManager;
+use crate::comparison::{ComparisonManager, EvictionConfig};
 use crate::mcp::{
-    messages::{ErrorCode, JsonRpcError, JsonRpcMessage, JsonRpcRequest, JsonRpcResponse},
+    messages::{
+        ErrorCode, JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest,
+        JsonRpcResponse,
+    },
     protocol::{
-        CallToolRequest, InitializeParams, InitializeResult, ListResourcesResult, ListToolsResult,
-        ReadResourceRequest, ReadResourceResult, ResourcesCapability, ServerCapabilities,
-        ServerInfo, ToolsCapability,
+        CallToolRequest, GetPromptRequest, InitializeParams, InitializeResult, ListPromptsResult,
+        ListResourcesResult, ListToolsResult, PromptsCapability, ReadResourceRequest,
+        ReadResourceResult, ResourcesCapability, ServerCapabilities, ServerInfo, ToolsCapability,
     },
     transport::StdioTransport,
 };
+use crate::prompts::PromptHandler;
 use crate::resources::ResourceHandler;
 use crate::tools::ToolHandler;
 use anyhow::Result;
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::sync::Arc;
+use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 
+/// Notification carrying one function of a streamed `list_changed_functions`
+pub const CHANGED_FUNCTION_NOTIFICATION: &str = "notifications/changed_function";
+
 /// Main MCP server
 #[allow(dead_code)]
 pub struct McpServer {
     comparison_manager: Arc<ComparisonManager>,
     tool_handler: Arc<ToolHandler>,
     resource_handler: Arc<ResourceHandler>,
+    prompt_handler: Arc<PromptHandler>,
 }
 
 impl McpServer {
     pub fn new() -> Self {
-        let comparison_manager = Arc::new(ComparisonManager::new());
+        let comparison_manager =
+            Arc::new(ComparisonManager::with_eviction(EvictionConfig::from_env()));
         let tool_handler = Arc::new(ToolHandler::new(comparison_manager.clone()));
         let resource_handler = Arc::new(ResourceHandler::new(comparison_manager.clone()));
+        let prompt_handler = Arc::new(PromptHandler::new(comparison_manager.clone()));
 
         Self {
             comparison_manager,
             tool_handler,
             resource_handler,
+            prompt_handler,
         }
     }
 
+    /// Sweep idle comparisons in the background, for transports other than
+    /// stdio, which starts its own sweep
+    pub fn spawn_eviction_task(&self) -> tokio::task::JoinHandle<()> {
+        self.comparison_manager.spawn_eviction_task()
+    }
+
     /// Run the server with stdio transport (MCP standard)
     pub async fn run(self) -> Result<()> {
         info!("MCP Server starting with stdio transport...");
 
+        let _eviction = self.comparison_manager.spawn_eviction_task();
+
         let (mut transport, tx) = StdioTransport::new();
 
         info!("MCP Server ready, waiting for messages...");
@@ -81,6 +101,8 @@ impl McpServer {
             "resources/list" => self.handle_list_resources().await,
             "resources/templates/list" => self.handle_list_resource_templates().await,
             "resources/read" => self.handle_read_resource(request.params).await,
+            "prompts/list" => self.handle_list_prompts().await,
+            "prompts/get" => self.handle_get_prompt(request.params).await,
             _ => Err(JsonRpcError::new(
                 ErrorCode::MethodNotFound,
                 format!("Method not found: {}", request.method),
@@ -93,6 +115,71 @@ impl McpServer {
         }
     }
 
+    /// Handle a JSON-RPC request on a transport that can send notifications
+    /// while a request is handled, such as SSE
+    ///
+    /// A `list_changed_functions` call with `"stream": true` sends each listed
+    /// function to `notifications` as a [`CHANGED_FUNCTION_NOTIFICATION`], in
+    /// list order, before the response is returned. Each notification names
+    /// the request it belongs to. Every other request is handled as by
+    /// [`McpServer::handle_request`].
+    pub async fn handle_request_streaming(
+        &self,
+        request: JsonRpcRequest,
+        notifications: &mpsc::UnboundedSender<JsonRpcMessage>,
+    ) -> JsonRpcResponse {
+        let streamed_call = request
+            .params
+            .as_ref()
+            .filter(|_| request.method == "tools/call")
+            .and_then(|params| serde_json::from_value::<CallToolRequest>(params.clone()).ok())
+            .filter(|call| {
+                call.name == "list_changed_functions"
+                    && call
+                        .arguments
+                        .as_ref()
+                        .is_some_and(|arguments| arguments["stream"] == true)
+            });
+        let Some(call) = streamed_call else {
+            return self.handle_request(request).await;
+        };
+
+        info!("Handling request: {} (streamed)", request.method);
+        let request_id = request.id.clone();
+        let result = self
+            .tool_handler
+            .stream_changed_functions(call.arguments, |mut event| {
+                event["requestId"] = json!(request_id);
+                event["tool"] = json!(call.name);
+                let notification = JsonRpcNotification::new(
+                    CHANGED_FUNCTION_NOTIFICATION.to_string(),
+                    Some(event),
+                );
+                if let Err(e) = notifications.send(JsonRpcMessage::Notification(notification)) {
+                    warn!("Failed to send streamed function: {}", e);
+                }
+            })
+            .map_err(|e| {
+                JsonRpcError::new(
+                    ErrorCode::ToolExecutionError,
+                    format!("Tool execution failed: {}", e),
+                )
+            })
+            .and_then(|result| {
+                serde_json::to_value(result).map_err(|e| {
+                    JsonRpcError::new(
+                        ErrorCode::InternalError,
+                        format!("Failed to serialize result: {}", e),
+                    )
+                })
+            });
+
+        match result {
+            Ok(value) => JsonRpcResponse::success(request.id, value),
+            Err(error) => JsonRpcResponse::error(request.id, error),
+        }
+    }
+
     /// Handle ping request (health check)
     async fn handle_ping(&self) -> Result<Value, JsonRpcError> {
         debug!("Handling ping request");
@@ -125,7 +212,9 @@ impl McpServer {
                     subscribe: Some(false),
                     list_changed: Some(true),
                 }),
-                prompts: None,
+                prompts: Some(PromptsCapability {
+                    list_changed: Some(false),
+                }),
             },
             server_info: ServerInfo {
                 name: "smart-diff-mcp-server".to_string(),
@@ -252,6 +341,52 @@ impl McpServer {
             )
         })
     }
+
+    /// Handle list prompts request
+    async fn handle_list_prompts(&self) -> Result<Value, JsonRpcError> {
+        let result = ListPromptsResult {
+            prompts: self.prompt_handler.list_prompts(),
+            next_cursor: None,
+        };
+
+        serde_json::to_value(result).map_err(|e| {
+            JsonRpcError::new(
+                ErrorCode::InternalError,
+                format!("Failed to serialize result: {}", e),
+            )
+        })
+    }
+
+    /// Handle get prompt request
+    async fn handle_get_prompt(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let get_request: GetPromptRequest = if let Some(p) = params {
+            serde_json::from_value(p).map_err(|e| {
+                JsonRpcError::new(ErrorCode::InvalidParams, format!("Invalid params: {}", e))
+            })?
+        } else {
+            return Err(JsonRpcError::new(
+                ErrorCode::InvalidParams,
+                "Missing prompt name".to_string(),
+            ));
+        };
+
+        let result = self
+            .prompt_handler
+            .get_prompt(&get_request.name, &get_request.arguments)
+            .map_err(|e| {
+                JsonRpcError::new(
+                    ErrorCode::InvalidParams,
+                    format!("Failed to render prompt: {}", e),
+                )
+            })?;
+
+        serde_json::to_value(result).map_err(|e| {
+            JsonRpcError::new(
+                ErrorCode::InternalError,
+                format!("Failed to serialize result: {}", e),
+            )
+        })
+    }
 }
 
 impl Default for McpServer {
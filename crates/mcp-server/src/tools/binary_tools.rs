@@ -4,6 +4,7 @@ use anyhow::Result;
 use serde_json::{json, Value};
 use smart_diff_binary_ninja_client::BinaryNinjaClient;
 use smart_diff_engine::{BinaryFunctionInfo, BinaryFunctionMatch, BinaryFunctionMatcher, BinaryMatcherConfig};
+use smart_diff_parser::{CommentSyntax, Language};
 use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 use tracing::{debug, info};
@@ -593,8 +594,14 @@ impl BinaryToolHandler {
             .as_str()
             .ok_or_else(|| anyhow::anyhow!("Missing function_name"))?;
 
-        let manager = self.comparison_manager.lock().unwrap();
-        let context = manager.get_comparison(comparison_id)?;
+        // Copied out so the lock is not held while functions are decompiled
+        let context = self
+            .comparison_manager
+            .lock()
+            .unwrap()
+            .get_comparison(comparison_id)?
+            .clone();
+        let context = &context;
 
         // First try to find in matched functions
         if let Some(m) = context.get_match_by_name(function_name) {
@@ -875,8 +882,13 @@ impl BinaryToolHandler {
         let comparison_id: BinaryComparisonId =
             serde_json::from_str(&format!("\"{}\"", comparison_id_str))?;
 
-        let manager = self.comparison_manager.lock().unwrap();
-        let context = manager.get_comparison(comparison_id)?;
+        // Copied out so the lock is not held while functions are decompiled
+        let context = self
+            .comparison_manager
+            .lock()
+            .unwrap()
+            .get_comparison(comparison_id)?
+            .clone();
 
         // Find the function match
         let m = context.get_match_by_name(function_name)
@@ -1551,11 +1563,12 @@ impl BinaryToolHandler {
         result
     }
 
-    /// Calculate maximum brace nesting depth
+    /// Calculate maximum brace nesting depth, ignoring braces in comments and strings
     fn calculate_max_brace_depth(&self, code: &str) -> usize {
         let mut max_depth = 0;
         let mut current_depth = 0;
 
+        let code = CommentSyntax::for_language(&Language::C).strip_comments_and_strings(code);
         for ch in code.chars() {
             match ch {
                 '{' => {
@@ -2,14 +2,41 @@
 
 pub mod binary_tools;
 
-use crate::comparison::{ComparisonId, ComparisonManager, ComparisonParams};
+use crate::comparison::{
+    ComparisonContext, ComparisonId, ComparisonManager, ComparisonParams, FunctionChange,
+    GitComparisonParams,
+};
 use crate::mcp::protocol::{CallToolResult, ToolContent, ToolInfo};
 use anyhow::Result;
 use binary_tools::BinaryToolHandler;
 use serde_json::{json, Value};
+use smart_diff_engine::MIN_CONFIDENCE_FLOOR;
 use std::sync::Arc;
 use tracing::{debug, info};
 
+/// Arguments of `list_changed_functions` that narrow down the listed changes
+struct ChangedFunctionFilter {
+    /// Most changes listed
+    limit: usize,
+    min_magnitude: Option<f64>,
+    change_types: Option<Vec<String>>,
+}
+
+impl ChangedFunctionFilter {
+    /// Whether a change is listed, room permitting
+    fn matches(&self, change: &FunctionChange) -> bool {
+        // Unchanged moves (file reorganizations) are left out
+        !change.is_unchanged_move
+            && self
+                .change_types
+                .as_ref()
+                .is_none_or(|types| types.contains(&change.change_type))
+            && self
+                .min_magnitude
+                .is_none_or(|min_magnitude| change.change_magnitude >= min_magnitude)
+    }
+}
+
 /// Tool handler
 pub struct ToolHandler {
     comparison_manager: Arc<ComparisonManager>,
@@ -57,11 +84,61 @@ impl ToolHandler {
                             "items": { "type": "string" },
                             "description": "File patterns to ignore",
                             "default": []
+                        },
+                        "min_refactoring_confidence": {
+                            "type": "number",
+                            "description": format!(
+                                "Minimum confidence (0.0 to 1.0) of reported refactoring patterns; values below {} are raised to it",
+                                MIN_CONFIDENCE_FLOOR
+                            ),
+                            "minimum": 0.0,
+                            "maximum": 1.0
+                        },
+                        "previous_comparison_id": {
+                            "type": "string",
+                            "description": "Comparison ID of an earlier run; results for files unchanged since then are reused"
                         }
                     },
                     "required": ["source_path", "target_path"]
                 }),
             },
+            ToolInfo {
+                name: "compare_commits".to_string(),
+                description: "Compare two commits of a git repository. Only files changed between the commits are parsed. Returns a comparison ID for querying results, like compare_locations.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "repo_path": {
+                            "type": "string",
+                            "description": "Path to the git repository"
+                        },
+                        "head_revision": {
+                            "type": "string",
+                            "description": "Revision with the changes (commit, branch or tag)"
+                        },
+                        "base_revision": {
+                            "type": "string",
+                            "description": "Revision to compare against; defaults to the parent of head_revision"
+                        },
+                        "parent": {
+                            "type": "integer",
+                            "description": "Which parent to compare a merge commit against when base_revision is omitted",
+                            "default": 1,
+                            "minimum": 1
+                        },
+                        "min_refactoring_confidence": {
+                            "type": "number",
+                            "description": format!(
+                                "Minimum confidence (0.0 to 1.0) of reported refactoring patterns; values below {} are raised to it",
+                                MIN_CONFIDENCE_FLOOR
+                            ),
+                            "minimum": 0.0,
+                            "maximum": 1.0
+                        }
+                    },
+                    "required": ["repo_path", "head_revision"]
+                }),
+            },
             ToolInfo {
                 name: "list_changed_functions".to_string(),
                 description: "List all changed functions from a comparison, sorted by change magnitude (most changed first). Includes additions, deletions, modifications, renames, and moves.".to_string(),
@@ -90,6 +167,11 @@ impl ToolHandler {
                             "description": "Minimum change magnitude (0.0 to 1.0)",
                             "minimum": 0.0,
                             "maximum": 1.0
+                        },
+                        "stream": {
+                            "type": "boolean",
+                            "description": "Send each function as a notifications/changed_function notification as soon as it is listed. Only honored over SSE; other transports return the whole list at once",
+                            "default": false
                         }
                     },
                     "required": ["comparison_id"]
@@ -113,14 +195,48 @@ impl ToolHandler {
                             "type": "boolean",
                             "description": "Whether to include full source and target content",
                             "default": true
+                        },
+                        "include_call_context": {
+                            "type": "boolean",
+                            "description": "Whether to list the functions calling this function and the functions it calls",
+                            "default": false
+                        },
+                        "call_context_depth": {
+                            "type": "integer",
+                            "description": "How many calls away callers and callees are listed when include_call_context is set",
+                            "default": 1,
+                            "minimum": 1
                         }
                     },
                     "required": ["comparison_id", "function_name"]
                 }),
             },
+            ToolInfo {
+                name: "get_function_source".to_string(),
+                description: "Get the exact source of a changed function on one side of a comparison, without diff framing. Returns the file and lines it spans, then the content verbatim.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "comparison_id": {
+                            "type": "string",
+                            "description": "The comparison ID"
+                        },
+                        "function_name": {
+                            "type": "string",
+                            "description": "Name of the function"
+                        },
+                        "side": {
+                            "type": "string",
+                            "enum": ["source", "target"],
+                            "description": "Version of the function: source (before) or target (after)"
+                        }
+                    },
+                    "required": ["comparison_id", "function_name", "side"]
+                }),
+            },
             ToolInfo {
                 name: "get_comparison_summary".to_string(),
-                description: "Get summary statistics for a comparison, including counts of added, deleted, modified, renamed, and moved functions.".to_string(),
+                description: "Get summary statistics for a comparison, including counts of added, deleted, modified, renamed, and moved functions, detected refactorings grouped by type, plus imports added, removed or re-aliased per file.".to_string(),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
@@ -148,8 +264,10 @@ impl ToolHandler {
         match name {
             // Source code comparison tools
             "compare_locations" => self.compare_locations(arguments).await,
+            "compare_commits" => self.compare_commits(arguments).await,
             "list_changed_functions" => self.list_changed_functions(arguments).await,
             "get_function_diff" => self.get_function_diff(arguments).await,
+            "get_function_source" => self.get_function_source(arguments).await,
             "get_comparison_summary" => self.get_comparison_summary(arguments).await,
 
             // Binary comparison tools
@@ -205,9 +323,54 @@ impl ToolHandler {
             recursive,
             file_patterns,
             ignore_patterns,
+            min_refactoring_confidence: args["min_refactoring_confidence"].as_f64(),
+        };
+
+        let comparison_id = match args["previous_comparison_id"].as_str() {
+            Some(previous_id_str) => {
+                let previous_id: ComparisonId =
+                    serde_json::from_str(&format!("\"{}\"", previous_id_str))?;
+                let previous = self.comparison_manager.get_comparison(previous_id)?;
+                self.comparison_manager.recompare(params, &previous).await?
+            }
+            None => self.comparison_manager.create_comparison(params).await?,
+        };
+
+        self.comparison_created(comparison_id)
+    }
+
+    /// Compare two commits of a git repository
+    async fn compare_commits(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let args = arguments.ok_or_else(|| anyhow::anyhow!("Missing arguments"))?;
+
+        let repo_path = args["repo_path"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing repo_path"))?
+            .to_string();
+
+        let head_revision = args["head_revision"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing head_revision"))?
+            .to_string();
+
+        let params = GitComparisonParams {
+            repo_path,
+            base_revision: args["base_revision"].as_str().map(String::from),
+            head_revision,
+            parent: args["parent"].as_u64().unwrap_or(1) as usize,
+            min_refactoring_confidence: args["min_refactoring_confidence"].as_f64(),
         };
 
-        let comparison_id = self.comparison_manager.create_comparison(params).await?;
+        let comparison_id = self
+            .comparison_manager
+            .create_git_comparison(params)
+            .await?;
+
+        self.comparison_created(comparison_id)
+    }
+
+    /// Result text announcing a new comparison and its summary
+    fn comparison_created(&self, comparison_id: ComparisonId) -> Result<CallToolResult> {
         let context = self.comparison_manager.get_comparison(comparison_id)?;
         let summary = context.get_summary();
 
@@ -224,7 +387,8 @@ impl ToolHandler {
             - Renamed: {}\n\
             - Moved: {}\n\
             - Unchanged: {}\n\
-            - File reorganizations: {} (functions moved without changes, filtered from results)\n\n\
+            - File reorganizations: {} (functions moved without changes, filtered from results)\n\
+            - Refactorings detected: {}\n\n\
             Use list_changed_functions with this comparison_id to see detailed changes.",
             comparison_id,
             context.params.source_path,
@@ -236,7 +400,8 @@ impl ToolHandler {
             summary.renamed,
             summary.moved,
             summary.unchanged,
-            summary.unchanged_moves
+            summary.unchanged_moves,
+            context.refactoring_patterns.len()
         );
 
         Ok(CallToolResult {
@@ -247,6 +412,90 @@ impl ToolHandler {
 
     /// List changed functions
     async fn list_changed_functions(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let (context, filter) = self.changed_functions_query(arguments)?;
+        let changes: Vec<&FunctionChange> = context
+            .ranked_changes()
+            .into_iter()
+            .filter(|change| filter.matches(change))
+            .take(filter.limit)
+            .collect();
+        let total = context.function_changes.len();
+
+        // Format output
+        let mut result_text = format!(
+            "Changed Functions (showing {} of {}):\n\n",
+            changes.len(),
+            total
+        );
+        for (i, change) in changes.iter().enumerate() {
+            result_text.push_str(&Self::format_changed_function(i, change));
+        }
+
+        Ok(CallToolResult {
+            content: vec![ToolContent::Text { text: result_text }],
+            is_error: Some(false),
+        })
+    }
+
+    /// List changed functions one at a time
+    ///
+    /// Selects the same functions, in the same order, as
+    /// `list_changed_functions`, but hands each one to `emit` as soon as it
+    /// passes the filters, before the next one is looked at. Each event
+    /// carries its position and the function's details, so it can be read on
+    /// its own. The result carries the counts.
+    pub fn stream_changed_functions(
+        &self,
+        arguments: Option<Value>,
+        mut emit: impl FnMut(Value),
+    ) -> Result<CallToolResult> {
+        let (context, filter) = self.changed_functions_query(arguments)?;
+
+        let mut listed = 0;
+        for change in context.ranked_changes() {
+            if listed == filter.limit {
+                break;
+            }
+            if !filter.matches(change) {
+                continue;
+            }
+            emit(json!({
+                "sequence": listed,
+                "function": {
+                    "function_name": change.function_name,
+                    "change_type": change.change_type,
+                    "change_magnitude": change.change_magnitude,
+                    "similarity_score": change.similarity_score,
+                    "source_file": change.source_file,
+                    "source_start_line": change.source_start_line,
+                    "source_end_line": change.source_end_line,
+                    "target_file": change.target_file,
+                    "target_start_line": change.target_start_line,
+                    "target_end_line": change.target_end_line,
+                    "diff_summary": change.diff_summary,
+                },
+            }));
+            listed += 1;
+        }
+
+        Ok(CallToolResult {
+            content: vec![ToolContent::Text {
+                text: format!(
+                    "Changed Functions (showing {} of {}), streamed as notifications\n",
+                    listed,
+                    context.function_changes.len()
+                ),
+            }],
+            is_error: Some(false),
+        })
+    }
+
+    /// Comparison `list_changed_functions` lists from, and the filter the
+    /// listed changes pass
+    fn changed_functions_query(
+        &self,
+        arguments: Option<Value>,
+    ) -> Result<(ComparisonContext, ChangedFunctionFilter)> {
         let args = arguments.ok_or_else(|| anyhow::anyhow!("Missing arguments"))?;
 
         let comparison_id_str = args["comparison_id"]
@@ -256,78 +505,55 @@ impl ToolHandler {
         let comparison_id: ComparisonId =
             serde_json::from_str(&format!("\"{}\"", comparison_id_str))?;
 
-        let limit = args["limit"].as_u64().unwrap_or(100) as usize;
-        let min_magnitude = args["min_magnitude"].as_f64();
-        let change_types: Option<Vec<String>> = args["change_types"].as_array().map(|arr| {
-            arr.iter()
-                .filter_map(|v| v.as_str().map(String::from))
-                .collect()
-        });
+        let filter = ChangedFunctionFilter {
+            limit: args["limit"].as_u64().unwrap_or(100) as usize,
+            min_magnitude: args["min_magnitude"].as_f64(),
+            change_types: args["change_types"].as_array().map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v.as_str().map(String::from))
+                    .collect()
+            }),
+        };
 
         let context = self.comparison_manager.get_comparison(comparison_id)?;
-        let mut changes = context.get_sorted_changes();
-
-        // Filter out unchanged moves (file reorganizations) by default
-        changes.retain(|c| !c.is_unchanged_move);
-
-        // Apply filters
-        if let Some(types) = change_types {
-            changes.retain(|c| types.contains(&c.change_type));
-        }
-
-        if let Some(min_mag) = min_magnitude {
-            changes.retain(|c| c.change_magnitude >= min_mag);
-        }
-
-        // Apply limit
-        changes.truncate(limit);
+        Ok((context, filter))
+    }
 
-        // Format output
-        let mut result_text = format!(
-            "Changed Functions (showing {} of {}):\n\n",
-            changes.len(),
-            context.function_changes.len()
+    /// Entry of the `index`th function in the `list_changed_functions` text
+    fn format_changed_function(index: usize, change: &FunctionChange) -> String {
+        let mut text = format!(
+            "{}. {} - {} (magnitude: {:.2}, similarity: {:.2})\n",
+            index + 1,
+            change.function_name,
+            change.change_type,
+            change.change_magnitude,
+            change.similarity_score
         );
 
-        for (i, change) in changes.iter().enumerate() {
-            result_text.push_str(&format!(
-                "{}. {} - {} (magnitude: {:.2}, similarity: {:.2})\n",
-                i + 1,
-                change.function_name,
-                change.change_type,
-                change.change_magnitude,
-                change.similarity_score
+        if let Some(source_file) = &change.source_file {
+            text.push_str(&format!(
+                "   Source: {} (lines {}-{})\n",
+                source_file,
+                change.source_start_line.unwrap_or(0),
+                change.source_end_line.unwrap_or(0)
             ));
+        }
 
-            if let Some(source_file) = &change.source_file {
-                result_text.push_str(&format!(
-                    "   Source: {} (lines {}-{})\n",
-                    source_file,
-                    change.source_start_line.unwrap_or(0),
-                    change.source_end_line.unwrap_or(0)
-                ));
-            }
-
-            if let Some(target_file) = &change.target_file {
-                result_text.push_str(&format!(
-                    "   Target: {} (lines {}-{})\n",
-                    target_file,
-                    change.target_start_line.unwrap_or(0),
-                    change.target_end_line.unwrap_or(0)
-                ));
-            }
-
-            if let Some(summary) = &change.diff_summary {
-                result_text.push_str(&format!("   Summary: {}\n", summary));
-            }
+        if let Some(target_file) = &change.target_file {
+            text.push_str(&format!(
+                "   Target: {} (lines {}-{})\n",
+                target_file,
+                change.target_start_line.unwrap_or(0),
+                change.target_end_line.unwrap_or(0)
+            ));
+        }
 
-            result_text.push('\n');
+        if let Some(summary) = &change.diff_summary {
+            text.push_str(&format!("   Summary: {}\n", summary));
         }
 
-        Ok(CallToolResult {
-            content: vec![ToolContent::Text { text: result_text }],
-            is_error: Some(false),
-        })
+        text.push('\n');
+        text
     }
 
     /// Get function diff
@@ -346,6 +572,8 @@ impl ToolHandler {
             .ok_or_else(|| anyhow::anyhow!("Missing function_name"))?;
 
         let include_content = args["include_content"].as_bool().unwrap_or(true);
+        let include_call_context = args["include_call_context"].as_bool().unwrap_or(false);
+        let call_context_depth = args["call_context_depth"].as_u64().unwrap_or(1).max(1) as usize;
 
         let context = self.comparison_manager.get_comparison(comparison_id)?;
         let change = context
@@ -393,6 +621,33 @@ impl ToolHandler {
             result_text.push_str(&format!("\nSummary: {}\n", summary));
         }
 
+        if include_call_context {
+            let neighborhood = context.get_call_neighborhood(change, call_context_depth);
+            result_text.push_str(&format!("\nCall Context (depth {}):\n", call_context_depth));
+            for (label, neighbors) in [
+                ("Callers", &neighborhood.callers),
+                ("Callees", &neighborhood.callees),
+            ] {
+                result_text.push_str(&format!("  {}:\n", label));
+                if neighbors.is_empty() {
+                    result_text.push_str("    (none resolved)\n");
+                }
+                for neighbor in neighbors {
+                    result_text.push_str(&format!(
+                        "    - {} ({}:{}){}\n",
+                        neighbor.qualified_name,
+                        neighbor.file_path,
+                        neighbor.line,
+                        if neighbor.distance > 1 {
+                            format!(", {} calls away", neighbor.distance)
+                        } else {
+                            String::new()
+                        }
+                    ));
+                }
+            }
+        }
+
         // Include actual content if requested
         if include_content {
             if let Some(source_content) = &change.source_content {
@@ -419,6 +674,88 @@ impl ToolHandler {
         })
     }
 
+    /// Get the source of a function on one side of a comparison
+    async fn get_function_source(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let args = arguments.ok_or_else(|| anyhow::anyhow!("Missing arguments"))?;
+
+        let comparison_id_str = args["comparison_id"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing comparison_id"))?;
+
+        let comparison_id: ComparisonId =
+            serde_json::from_str(&format!("\"{}\"", comparison_id_str))?;
+
+        let function_name = args["function_name"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing function_name"))?;
+
+        let side = args["side"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing side"))?;
+
+        let context = self.comparison_manager.get_comparison(comparison_id)?;
+        let change = context
+            .get_function_change(function_name)
+            .ok_or_else(|| anyhow::anyhow!("Function not found: {}", function_name))?;
+
+        let (file, start_line, end_line, content) = match side {
+            "source" => (
+                &change.source_file,
+                change.source_start_line,
+                change.source_end_line,
+                &change.source_content,
+            ),
+            "target" => (
+                &change.target_file,
+                change.target_start_line,
+                change.target_end_line,
+                &change.target_content,
+            ),
+            other => {
+                return Err(anyhow::anyhow!(
+                    "Invalid side: {} (expected source or target)",
+                    other
+                ))
+            }
+        };
+        let (Some(file), Some(content)) = (file, content) else {
+            let reason = match change.change_type.as_str() {
+                "added" => "it was added",
+                "deleted" => "it was deleted",
+                _ => "its content was not recorded",
+            };
+            return Err(anyhow::anyhow!(
+                "Function '{}' has no {} version: {}",
+                function_name,
+                side,
+                reason
+            ));
+        };
+
+        let location = format!(
+            "Function: {}\n\
+            Side: {}\n\
+            File: {}\n\
+            Lines: {}-{}",
+            change.function_name,
+            side,
+            file,
+            start_line.unwrap_or(0),
+            end_line.unwrap_or(0)
+        );
+
+        // The content is its own item so that it can be quoted verbatim
+        Ok(CallToolResult {
+            content: vec![
+                ToolContent::Text { text: location },
+                ToolContent::Text {
+                    text: content.clone(),
+                },
+            ],
+            is_error: Some(false),
+        })
+    }
+
     /// Generate a unified diff between two strings
     fn generate_unified_diff(&self, source: &str, target: &str) -> String {
         use similar::{ChangeTag, TextDiff};
@@ -452,7 +789,7 @@ impl ToolHandler {
         let context = self.comparison_manager.get_comparison(comparison_id)?;
         let summary = context.get_summary();
 
-        let result_text = format!(
+        let mut result_text = format!(
             "Comparison Summary\n\
             ==================\n\n\
             Comparison ID: {}\n\
@@ -482,9 +819,267 @@ impl ToolHandler {
             summary.unchanged_moves
         );
 
+        let refactoring_groups = context.get_refactoring_groups();
+        if !refactoring_groups.is_empty() {
+            result_text.push_str(&format!(
+                "\nRefactorings: {}\n",
+                context.refactoring_patterns.len()
+            ));
+            for (pattern_type, patterns) in &refactoring_groups {
+                result_text.push_str(&format!("\n{} ({}):\n", pattern_type, patterns.len()));
+                for pattern in patterns {
+                    result_text.push_str(&format!(
+                        "  - {} (confidence {:.2})\n",
+                        pattern.description, pattern.confidence
+                    ));
+                }
+            }
+        }
+
+        let import_deltas = context.get_import_deltas();
+        if !import_deltas.is_empty() {
+            result_text.push_str(&format!(
+                "\nImport Changes: {} added, {} removed, {} changed\n",
+                summary.imports_added, summary.imports_removed, summary.imports_changed
+            ));
+            for (file, delta) in &import_deltas {
+                result_text.push_str(&format!("\n{}:\n", file));
+                for import in &delta.added {
+                    result_text.push_str(&format!("  + {}\n", import));
+                }
+                for import in &delta.removed {
+                    result_text.push_str(&format!("  - {}\n", import));
+                }
+                for change in &delta.changed {
+                    result_text.push_str(&format!("  ~ {} -> {}\n", change.source, change.target));
+                }
+            }
+        }
+
         Ok(CallToolResult {
             content: vec![ToolContent::Text { text: result_text }],
             is_error: Some(false),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_function_diff_lists_known_caller_in_call_context() {
+        let (source, target) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        std::fs::write(
+            source.path().join("calc.c"),
+            "int scale(int x) {\n    return x * 2;\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            target.path().join("calc.c"),
+            "int scale(int x) {\n    return x * 3;\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            target.path().join("report.c"),
+            "int report(int x) {\n    return scale(x) + 1;\n}\n",
+        )
+        .unwrap();
+
+        let manager = Arc::new(ComparisonManager::new());
+        let id = manager
+            .create_comparison(ComparisonParams {
+                source_path: source.path().display().to_string(),
+                target_path: target.path().display().to_string(),
+                recursive: true,
+                file_patterns: Vec::new(),
+                ignore_patterns: Vec::new(),
+                min_refactoring_confidence: None,
+            })
+            .await
+            .unwrap();
+        let handler = ToolHandler::new(manager);
+
+        let diff_text = |result: CallToolResult| match &result.content[0] {
+            ToolContent::Text { text } => text.clone(),
+            other => panic!("unexpected content: {:?}", other),
+        };
+        let arguments = |include_call_context: bool| {
+            Some(json!({
+                "comparison_id": id.as_str(),
+                "function_name": "scale",
+                "include_content": false,
+                "include_call_context": include_call_context,
+            }))
+        };
+
+        let with_context = diff_text(
+            handler
+                .call_tool("get_function_diff", arguments(true))
+                .await
+                .unwrap(),
+        );
+        assert!(
+            with_context.contains("Callers:\n    - report (report.c:1)"),
+            "{}",
+            with_context
+        );
+        assert!(with_context.contains("Callees:\n    (none resolved)"));
+
+        let without_context = diff_text(
+            handler
+                .call_tool("get_function_diff", arguments(false))
+                .await
+                .unwrap(),
+        );
+        assert!(!without_context.contains("Call Context"));
+    }
+
+    #[tokio::test]
+    async fn test_streamed_functions_follow_the_filters_and_limit() {
+        let (source, target) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let calc = |factor: i32| {
+            format!(
+                "int scale(int x) {{\n    return x * {};\n}}\n\n\
+                 int shift(int x) {{\n    return x + {};\n}}\n\n\
+                 int clamp(int x) {{\n    return x > {} ? {} : x;\n}}\n",
+                factor, factor, factor, factor
+            )
+        };
+        std::fs::write(source.path().join("calc.c"), calc(2)).unwrap();
+        std::fs::write(
+            target.path().join("calc.c"),
+            calc(3) + "\nint extra(int x) {\n    return x;\n}\n",
+        )
+        .unwrap();
+
+        let manager = Arc::new(ComparisonManager::new());
+        let id = manager
+            .create_comparison(ComparisonParams {
+                source_path: source.path().display().to_string(),
+                target_path: target.path().display().to_string(),
+                recursive: true,
+                file_patterns: Vec::new(),
+                ignore_patterns: Vec::new(),
+                min_refactoring_confidence: None,
+            })
+            .await
+            .unwrap();
+        let handler = ToolHandler::new(manager);
+        let arguments = Some(json!({
+            "comparison_id": id.as_str(),
+            "change_types": ["modified"],
+            "limit": 2,
+        }));
+
+        let batched = match &handler
+            .call_tool("list_changed_functions", arguments.clone())
+            .await
+            .unwrap()
+            .content[0]
+        {
+            ToolContent::Text { text } => text.clone(),
+            other => panic!("unexpected content: {:?}", other),
+        };
+        let mut events = Vec::new();
+        handler
+            .stream_changed_functions(arguments, |event| events.push(event))
+            .unwrap();
+
+        let streamed: Vec<String> = events
+            .iter()
+            .map(|event| {
+                format!(
+                    "{}. {} - modified",
+                    event["sequence"].as_u64().unwrap() + 1,
+                    event["function"]["function_name"].as_str().unwrap()
+                )
+            })
+            .collect();
+        let listed: Vec<&str> = batched
+            .lines()
+            .filter(|line| line.starts_with(|c: char| c.is_ascii_digit()))
+            .collect();
+        assert_eq!(streamed.len(), 2, "{}", batched);
+        for (streamed, listed) in streamed.iter().zip(&listed) {
+            assert!(
+                listed.starts_with(streamed.as_str()),
+                "{} vs {}",
+                streamed,
+                listed
+            );
+        }
+        assert_eq!(listed.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_function_source_returns_each_side_verbatim() {
+        let (source, target) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let old_scale = "int scale(int x) {\n    return x * 2;\n}";
+        let new_scale = "int scale(int x) {\n    int factor = 3;\n    return x * factor;\n}";
+        std::fs::write(source.path().join("calc.c"), format!("{}\n", old_scale)).unwrap();
+        std::fs::write(
+            target.path().join("calc.c"),
+            format!(
+                "{}\n\nint offset(int x) {{\n    return x + 1;\n}}\n",
+                new_scale
+            ),
+        )
+        .unwrap();
+
+        let manager = Arc::new(ComparisonManager::new());
+        let id = manager
+            .create_comparison(ComparisonParams {
+                source_path: source.path().display().to_string(),
+                target_path: target.path().display().to_string(),
+                recursive: true,
+                file_patterns: Vec::new(),
+                ignore_patterns: Vec::new(),
+                min_refactoring_confidence: None,
+            })
+            .await
+            .unwrap();
+        let handler = ToolHandler::new(manager);
+        let function_source = |function_name: &str, side: &str| {
+            handler.call_tool(
+                "get_function_source",
+                Some(json!({
+                    "comparison_id": id.as_str(),
+                    "function_name": function_name,
+                    "side": side,
+                })),
+            )
+        };
+        let texts = |result: CallToolResult| -> Vec<String> {
+            result
+                .content
+                .iter()
+                .map(|content| match content {
+                    ToolContent::Text { text } => text.clone(),
+                    other => panic!("unexpected content: {:?}", other),
+                })
+                .collect()
+        };
+
+        let old = texts(function_source("scale", "source").await.unwrap());
+        assert_eq!(
+            old[0],
+            "Function: scale\nSide: source\nFile: calc.c\nLines: 1-3"
+        );
+        assert_eq!(old[1].trim_end(), old_scale);
+
+        let new = texts(function_source("scale", "target").await.unwrap());
+        assert_eq!(
+            new[0],
+            "Function: scale\nSide: target\nFile: calc.c\nLines: 1-4"
+        );
+        assert_eq!(new[1].trim_end(), new_scale);
+
+        let added = function_source("offset", "source").await.unwrap_err();
+        assert_eq!(
+            added.to_string(),
+            "Function 'offset' has no source version: it was added"
+        );
+    }
+}
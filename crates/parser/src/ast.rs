@@ -76,6 +76,10 @@ pub enum NodeType {
     // Exception handling
     ThrowStatement,
 
+    // Documents
+    Section,
+    Heading,
+
     // Other
     Comment,
     Unknown,
@@ -91,11 +95,80 @@ impl std::fmt::Display for NodeType {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct NodeMetadata {
     pub line: usize,
+    /// 1-based column in Unicode scalar values (characters), not bytes
     pub column: usize,
     pub original_text: String,
     pub attributes: HashMap<String, String>,
 }
 
+/// 1-based column of `byte_offset` within its line, counted in characters
+///
+/// Tree-sitter reports columns in bytes, which drifts to the right after any
+/// multibyte character (accented identifiers, CJK comments, emoji) on the line.
+pub fn char_column(source: &str, byte_offset: usize) -> usize {
+    let prefix = source.get(..byte_offset).unwrap_or(source);
+    let line_start = prefix.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    prefix[line_start..].chars().count() + 1
+}
+
+/// Lines of `source`, split on `\n`, `\r\n` and lone `\r`
+///
+/// Matches the line numbers the parser reports, which counts a lone `\r` as a
+/// line break too; `str::lines` would leave such a file as a single line.
+pub fn source_lines(source: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+    let bytes = source.as_bytes();
+
+    for (i, &byte) in bytes.iter().enumerate() {
+        match byte {
+            b'\n' if i > 0 && bytes[i - 1] == b'\r' => line_start = i + 1,
+            b'\n' | b'\r' => {
+                lines.push(&source[line_start..i]);
+                line_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if line_start < source.len() {
+        lines.push(&source[line_start..]);
+    }
+
+    lines
+}
+
+/// Text of the 1-based, inclusive line range, joined with `\n`
+///
+/// The range is clamped to the lines that exist, so a stale or out-of-range
+/// location yields what is left of it rather than nothing.
+pub fn line_range_text(source: &str, start_line: usize, end_line: usize) -> String {
+    let lines = source_lines(source);
+    let start = start_line.max(1) - 1;
+    let end = end_line.min(lines.len());
+    if start >= end {
+        return String::new();
+    }
+    lines[start..end].join("\n")
+}
+
+/// `source` with each lone `\r` replaced by `\n`
+///
+/// Tree-sitter only counts `\n` as a line break. Both are one byte, so byte
+/// offsets into the result are valid in `source` as well.
+pub(crate) fn normalize_lone_carriage_returns(source: &str) -> std::borrow::Cow<'_, str> {
+    let bytes = source.as_bytes();
+    let is_lone = |i: usize| bytes[i] == b'\r' && bytes.get(i + 1) != Some(&b'\n');
+    if !(0..bytes.len()).any(is_lone) {
+        return std::borrow::Cow::Borrowed(source);
+    }
+
+    let normalized: Vec<u8> = (0..bytes.len())
+        .map(|i| if is_lone(i) { b'\n' } else { bytes[i] })
+        .collect();
+    // Only ASCII bytes were replaced, so the text is still valid UTF-8
+    std::borrow::Cow::Owned(String::from_utf8(normalized).expect("valid UTF-8"))
+}
+
 impl ASTNode {
     pub fn new(node_type: NodeType, metadata: NodeMetadata) -> Self {
         Self {
@@ -121,6 +194,12 @@ impl ASTNode {
         result
     }
 
+    /// Whether this is the declarator of a C/C++ function, which holds only
+    /// the signature of the definition around it
+    pub fn is_function_declarator(&self) -> bool {
+        self.metadata.attributes.get("kind").map(String::as_str) == Some("function_declarator")
+    }
+
     /// Calculate a hash for this node based on its structure
     pub fn structural_hash(&self) -> u64 {
         use std::collections::hash_map::DefaultHasher;
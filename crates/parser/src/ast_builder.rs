@@ -1,8 +1,10 @@
 //! AST builder for converting tree-sitter parse trees to normalized AST representation
 
-use crate::ast::{ASTNode, NodeMetadata, NodeType};
+use crate::ast::{char_column, ASTNode, NodeMetadata, NodeType};
+use crate::function::anonymous_function_name;
 use crate::language::Language;
 use crate::language_config::{LanguageConfig, LANGUAGE_CONFIGS};
+use crate::parser::ParseError;
 use std::collections::HashMap;
 use tree_sitter::{Node, Tree};
 
@@ -13,12 +15,28 @@ pub struct ASTBuilderConfig {
     pub include_comments: bool,
     /// Whether to include whitespace-only nodes
     pub include_whitespace: bool,
-    /// Maximum text length to store in node metadata
+    /// Maximum text length to store in node metadata; functions and methods
+    /// always keep their whole text
     pub max_text_length: usize,
     /// Whether to extract detailed function signatures
     pub extract_signatures: bool,
     /// Whether to build symbol tables during AST construction
     pub build_symbol_table: bool,
+    /// Maximum nesting depth of the parse tree before parsing fails
+    pub max_depth: usize,
+    /// How functions without a name, such as callbacks, are named
+    pub anonymous_functions: AnonymousFunctionPolicy,
+}
+
+/// Handling of functions without a name: arrow functions, lambdas, closures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnonymousFunctionPolicy {
+    /// Name them by position, `<anon@enclosing:index>`, so they are compared like
+    /// other functions
+    #[default]
+    Positional,
+    /// Leave them unnamed, so function extraction skips them
+    Skip,
 }
 
 impl Default for ASTBuilderConfig {
@@ -29,6 +47,8 @@ impl Default for ASTBuilderConfig {
             max_text_length: 200,
             extract_signatures: true,
             build_symbol_table: true,
+            max_depth: 1000,
+            anonymous_functions: AnonymousFunctionPolicy::default(),
         }
     }
 }
@@ -72,14 +92,147 @@ impl ASTBuilder {
     }
 
     /// Build AST from tree-sitter tree
-    pub fn build_ast(&mut self, tree: &Tree, source: &str) -> ASTNode {
+    ///
+    /// Fails with [`ParseError::MaxDepthExceeded`] before any recursive conversion
+    /// if the tree is nested deeper than the configured `max_depth`.
+    pub fn build_ast(&mut self, tree: &Tree, source: &str) -> Result<ASTNode, ParseError> {
+        self.check_depth(tree)?;
+
         let root_node = tree.root_node();
         self.stats = ASTBuildStats::default();
 
-        let ast = self.convert_node(&root_node, source, 0);
+        let mut ast = self.convert_node(&root_node, source, 0);
+        Self::mark_anonymous_functions(
+            &mut ast,
+            "top-level",
+            &mut 0,
+            self.config.anonymous_functions,
+        );
+        Self::mark_conditional_functions(&mut ast, &mut Vec::new());
         self.stats.max_depth = ast.depth();
 
-        ast
+        Ok(ast)
+    }
+
+    /// Mark functions without a name as anonymous and, by policy, name them by position
+    ///
+    /// An anonymous function is named after the nearest named function or class
+    /// around it and its preorder index among that scope's anonymous functions,
+    /// so the same structural position gets the same name in every run.
+    fn mark_anonymous_functions(
+        node: &mut ASTNode,
+        scope: &str,
+        index: &mut usize,
+        policy: AnonymousFunctionPolicy,
+    ) {
+        for child in &mut node.children {
+            let is_function = matches!(
+                child.node_type,
+                NodeType::Function | NodeType::Method | NodeType::Constructor
+            );
+            let is_scope = is_function || child.node_type == NodeType::Class;
+            let declarator = child.is_function_declarator();
+            let attributes = &mut child.metadata.attributes;
+
+            match attributes.get("name").cloned() {
+                Some(name) if is_scope => {
+                    Self::mark_anonymous_functions(child, &name, &mut 0, policy);
+                }
+                None if is_function && !declarator => {
+                    attributes.insert("anonymous".to_string(), "true".to_string());
+                    if policy == AnonymousFunctionPolicy::Positional {
+                        attributes
+                            .insert("name".to_string(), anonymous_function_name(scope, *index));
+                    }
+                    *index += 1;
+                    Self::mark_anonymous_functions(child, scope, index, policy);
+                }
+                _ => Self::mark_anonymous_functions(child, scope, index, policy),
+            }
+        }
+    }
+
+    /// Record on each function the preprocessor conditions it is compiled under
+    ///
+    /// A function inside `#ifdef _WIN32` gets the `condition` attribute
+    /// `defined(_WIN32)`; one in its `#else` branch `!defined(_WIN32)`. Nested
+    /// regions are joined with ` && `.
+    fn mark_conditional_functions(node: &mut ASTNode, conditions: &mut Vec<String>) {
+        let is_region = matches!(
+            node.metadata.attributes.get("kind").map(String::as_str),
+            Some("preproc_if" | "preproc_ifdef" | "preproc_elif" | "preproc_elifdef")
+        );
+        let condition = is_region
+            .then(|| node.metadata.attributes.get("condition").cloned())
+            .flatten();
+
+        for child in &mut node.children {
+            let is_alternative = matches!(
+                child.metadata.attributes.get("kind").map(String::as_str),
+                Some("preproc_else" | "preproc_elif" | "preproc_elifdef")
+            );
+            let entered = condition.as_ref().map(|condition| {
+                if is_alternative {
+                    negate_condition(condition)
+                } else {
+                    condition.clone()
+                }
+            });
+            if let Some(entered) = &entered {
+                conditions.push(entered.clone());
+            }
+
+            let is_function = matches!(
+                child.node_type,
+                NodeType::Function | NodeType::Method | NodeType::Constructor
+            );
+            if is_function && !conditions.is_empty() {
+                child
+                    .metadata
+                    .attributes
+                    .insert("condition".to_string(), conditions.join(" && "));
+            }
+            Self::mark_conditional_functions(child, conditions);
+
+            if entered.is_some() {
+                conditions.pop();
+            }
+        }
+    }
+
+    /// Build the AST of a single node and its descendants
+    pub fn build_subtree(&mut self, node: &Node, source: &str) -> ASTNode {
+        self.convert_node(node, source, 0)
+    }
+
+    /// Walk the tree iteratively and reject it if it exceeds the depth limit
+    fn check_depth(&self, tree: &Tree) -> Result<(), ParseError> {
+        let mut cursor = tree.walk();
+        let mut depth = 0;
+
+        loop {
+            if depth > self.config.max_depth {
+                return Err(ParseError::MaxDepthExceeded {
+                    max_depth: self.config.max_depth,
+                    line: cursor.node().start_position().row + 1,
+                });
+            }
+
+            if cursor.goto_first_child() {
+                depth += 1;
+                continue;
+            }
+
+            loop {
+                if cursor.goto_next_sibling() {
+                    break;
+                }
+                if !cursor.goto_parent() {
+                    return Ok(());
+                }
+                depth -= 1;
+            }
+        }
     }
 
     /// Get build statistics
@@ -126,7 +279,7 @@ impl ASTBuilder {
         }
 
         // Create metadata
-        let metadata = self.create_node_metadata(node, source, node_kind, text);
+        let metadata = self.create_node_metadata(node, source, node_kind, &node_type, text);
 
         // Create AST node
         let mut ast_node = ASTNode::new(node_type, metadata);
@@ -143,6 +296,7 @@ impl ASTBuilder {
         node: &Node,
         source: &str,
         node_kind: &str,
+        node_type: &NodeType,
         text: &str,
     ) -> NodeMetadata {
         let mut attributes = HashMap::new();
@@ -153,6 +307,10 @@ impl ASTBuilder {
             "byte_range".to_string(),
             format!("{}..{}", node.start_byte(), node.end_byte()),
         );
+        attributes.insert(
+            "end_line".to_string(),
+            (node.end_position().row + 1).to_string(),
+        );
 
         // Store text if not too long
         let trimmed_text = text.trim();
@@ -168,8 +326,13 @@ impl ASTBuilder {
 
         NodeMetadata {
             line: node.start_position().row + 1,
-            column: node.start_position().column + 1,
-            original_text: if text.len() <= self.config.max_text_length {
+            column: char_column(source, node.start_byte()),
+            // Function text is compared whole, so it is never cut short
+            original_text: if text.len() <= self.config.max_text_length
+                || matches!(
+                    node_type,
+                    NodeType::Function | NodeType::Method | NodeType::Constructor
+                ) {
                 text.to_string()
             } else {
                 // Use Unicode-aware truncation to avoid panics on multi-byte characters
@@ -201,9 +364,24 @@ impl ASTBuilder {
             }
         }
 
+        // `const handler = () => ...` names the function after its variable
+        if !attributes.contains_key("name")
+            && matches!(node_kind, "arrow_function" | "function_expression")
+        {
+            let variable_name = node
+                .parent()
+                .filter(|parent| parent.kind() == "variable_declarator")
+                .and_then(|declarator| declarator.child_by_field_name("name"))
+                .filter(|name| name.kind() == "identifier")
+                .and_then(|name| name.utf8_text(source.as_bytes()).ok());
+            if let Some(name) = variable_name {
+                attributes.insert("name".to_string(), name.to_string());
+            }
+        }
+
         // Node-specific attribute extraction
         match node_kind {
-            "call_expression" | "function_call" | "method_invocation" => {
+            "call_expression" | "call" | "function_call" | "method_invocation" => {
                 self.extract_call_attributes(node, source, attributes);
             }
             "function_declaration" | "method_declaration" | "function_definition" => {
@@ -212,9 +390,18 @@ impl ASTBuilder {
             "class_declaration" | "class_definition" | "interface_declaration" => {
                 self.extract_class_attributes(node, source, attributes);
             }
-            "variable_declaration" | "field_declaration" | "parameter_declaration" => {
+            "variable_declaration"
+            | "local_variable_declaration"
+            | "field_declaration"
+            | "parameter_declaration" => {
                 self.extract_declaration_attributes(node, source, attributes);
             }
+            "atx_heading" | "setext_heading" => {
+                ASTBuilder::extract_heading_attributes(node, source, attributes);
+            }
+            "preproc_if" | "preproc_ifdef" | "preproc_elif" | "preproc_elifdef" => {
+                ASTBuilder::extract_preprocessor_condition(node, source, attributes);
+            }
             _ => {}
         }
     }
@@ -266,16 +453,196 @@ impl ASTBuilder {
             }
         }
 
-        // Count parameters
-        if let Some(params_node) = node.child_by_field_name("parameters") {
-            let param_count = params_node.named_child_count();
-            attributes.insert("parameter_count".to_string(), param_count.to_string());
+        // Count parameters and record their declared types and names, e.g.
+        // `String;int` and `title;width`
+        if let Some(params_node) = ASTBuilder::find_parameters(node) {
+            let mut cursor = params_node.walk();
+            let params: Vec<Node> = params_node
+                .named_children(&mut cursor)
+                .filter(|param| param.kind() != "comment")
+                .collect();
+            let param_types: Vec<String> = params
+                .iter()
+                .map(|param| {
+                    param
+                        .child_by_field_name("type")
+                        .and_then(|type_node| type_node.utf8_text(source.as_bytes()).ok())
+                        .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+                        .unwrap_or_default()
+                })
+                .collect();
+            let param_names: Vec<String> = params
+                .iter()
+                .map(|param| ASTBuilder::declared_name(param, source).unwrap_or_default())
+                .collect();
+            attributes.insert("parameter_count".to_string(), param_types.len().to_string());
+            if !param_types.is_empty() {
+                attributes.insert("parameter_types".to_string(), param_types.join(";"));
+            }
+            if param_names.iter().any(|name| !name.is_empty()) {
+                attributes.insert("parameter_names".to_string(), param_names.join(";"));
+            }
+        }
+
+        // Go method receiver: `func (s *Server[T]) Close()` records `Server`
+        if let Some(receiver) = node.child_by_field_name("receiver") {
+            if let Some(receiver_type) = ASTBuilder::extract_receiver_type(&receiver, source) {
+                attributes.insert("receiver_type".to_string(), receiver_type);
+            }
+        }
+
+        // Go type parameters: `func Map[K comparable, V any]` records `K,V`
+        if let Some(type_params) = node.child_by_field_name("type_parameters") {
+            let mut cursor = type_params.walk();
+            let names: Vec<&str> = type_params
+                .named_children(&mut cursor)
+                .flat_map(|decl| {
+                    let mut decl_cursor = decl.walk();
+                    decl.children_by_field_name("name", &mut decl_cursor)
+                        .filter_map(|name| name.utf8_text(source.as_bytes()).ok())
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            if !names.is_empty() {
+                attributes.insert("type_parameters".to_string(), names.join(","));
+            }
         }
 
         // Extract modifiers (for languages that support them)
         self.extract_modifiers(node, source, attributes);
     }
 
+    /// Base type name of a Go receiver, without pointer or type arguments
+    /// Parameter list of a function, which C/C++ nest inside the declarator
+    fn find_parameters<'tree>(node: &Node<'tree>) -> Option<Node<'tree>> {
+        if let Some(params) = node.child_by_field_name("parameters") {
+            return Some(params);
+        }
+
+        let mut declarator = node.child_by_field_name("declarator");
+        while let Some(current) = declarator {
+            if let Some(params) = current.child_by_field_name("parameters") {
+                return Some(params);
+            }
+            declarator = current.child_by_field_name("declarator");
+        }
+        None
+    }
+
+    /// Declared name of a parameter or variable node, if the grammar exposes one
+    ///
+    /// Python and JavaScript parameters are often bare identifiers, while
+    /// C/C++ wrap the name in pointer and reference declarators and Java in
+    /// variable declarators.
+    fn declared_name(node: &Node, source: &str) -> Option<String> {
+        if node.kind() == "identifier" {
+            return node.utf8_text(source.as_bytes()).ok().map(str::to_string);
+        }
+
+        ["name", "pattern", "declarator"]
+            .into_iter()
+            .find_map(|field| node.child_by_field_name(field))
+            .and_then(|inner| ASTBuilder::declared_name(&inner, source))
+    }
+
+    fn extract_receiver_type(receiver: &Node, source: &str) -> Option<String> {
+        let mut cursor = receiver.walk();
+        let param = receiver.named_children(&mut cursor).next()?;
+        let type_text = param
+            .child_by_field_name("type")?
+            .utf8_text(source.as_bytes())
+            .ok()?;
+        let base = type_text
+            .trim_start_matches('*')
+            .split('[')
+            .next()
+            .unwrap_or_default()
+            .trim();
+
+        if base.is_empty() {
+            None
+        } else {
+            Some(base.to_string())
+        }
+    }
+
+    /// Extract level and title of a Markdown heading
+    fn extract_heading_attributes(
+        node: &Node,
+        source: &str,
+        attributes: &mut HashMap<String, String>,
+    ) {
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            let kind = child.kind();
+            // `atx_h2_marker` and `setext_h2_underline` both give level 2
+            let level = kind
+                .strip_prefix("atx_h")
+                .or_else(|| kind.strip_prefix("setext_h"))
+                .and_then(|rest| rest.chars().next())
+                .and_then(|digit| digit.to_digit(10));
+
+            if let Some(level) = level {
+                attributes.insert("heading_level".to_string(), level.to_string());
+            } else if matches!(kind, "inline" | "paragraph") {
+                if let Ok(title) = child.utf8_text(source.as_bytes()) {
+                    attributes.insert("name".to_string(), title.trim().to_string());
+                }
+            }
+        }
+    }
+
+    /// Extract the condition of a C/C++ `#if`, `#ifdef` or `#elif` directive
+    ///
+    /// `#ifdef X` is recorded as `defined(X)` and `#ifndef X` as
+    /// `!defined(X)`. Include guards, an `#ifndef X` that starts by defining
+    /// `X`, get no condition, so a header's functions are not all gated.
+    fn extract_preprocessor_condition(
+        node: &Node,
+        source: &str,
+        attributes: &mut HashMap<String, String>,
+    ) {
+        let text_of = |node: Node| {
+            node.utf8_text(source.as_bytes())
+                .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+                .ok()
+        };
+
+        if let Some(condition) = node.child_by_field_name("condition").and_then(text_of) {
+            attributes.insert("condition".to_string(), condition);
+            return;
+        }
+        let Some(name_node) = node.child_by_field_name("name") else {
+            return;
+        };
+        let Some(name) = text_of(name_node) else {
+            return;
+        };
+        let negated = node
+            .child(0)
+            .is_some_and(|directive| directive.kind().ends_with("ndef"));
+        let is_include_guard = negated
+            && node.kind() == "preproc_ifdef"
+            && name_node
+                .next_named_sibling()
+                .filter(|sibling| sibling.kind() == "preproc_def")
+                .and_then(|definition| definition.child_by_field_name("name"))
+                .and_then(text_of)
+                .is_some_and(|defined| defined == name);
+        if is_include_guard {
+            return;
+        }
+        let condition = format!("defined({})", name);
+        attributes.insert(
+            "condition".to_string(),
+            if negated {
+                negate_condition(&condition)
+            } else {
+                condition
+            },
+        );
+    }
+
     /// Extract attributes for class declaration nodes
     fn extract_class_attributes(
         &self,
@@ -294,6 +661,24 @@ impl ASTBuilder {
         if let Some(interfaces_node) = node.child_by_field_name("interfaces") {
             let interface_count = interfaces_node.named_child_count();
             attributes.insert("interface_count".to_string(), interface_count.to_string());
+
+            // Java wraps the implemented types in a type list; record their names
+            // without generic arguments
+            let mut cursor = interfaces_node.walk();
+            let interfaces: Vec<&str> = interfaces_node
+                .named_children(&mut cursor)
+                .filter(|child| child.kind() == "type_list")
+                .flat_map(|list| {
+                    let mut cursor = list.walk();
+                    list.named_children(&mut cursor).collect::<Vec<_>>()
+                })
+                .filter_map(|interface| interface.utf8_text(source.as_bytes()).ok())
+                .filter_map(|interface| interface.split('<').next())
+                .map(str::trim)
+                .collect();
+            if !interfaces.is_empty() {
+                attributes.insert("implements".to_string(), interfaces.join(", "));
+            }
         }
 
         // Extract modifiers
@@ -318,6 +703,21 @@ impl ASTBuilder {
         if let Some(_init_node) = node.child_by_field_name("value") {
             attributes.insert("has_initializer".to_string(), "true".to_string());
         }
+
+        // Java locals name their variable in the declarator, e.g. `int total = 0`
+        if !attributes.contains_key("name")
+            && matches!(
+                node.kind(),
+                "variable_declaration" | "local_variable_declaration"
+            )
+        {
+            if let Some(name) = ASTBuilder::declared_name(node, source) {
+                attributes.insert("name".to_string(), name);
+            }
+        }
+
+        // Java and C# fields declare constants with `static final` or `const`
+        self.extract_modifiers(node, source, attributes);
     }
 
     /// Extract modifier information (public, private, static, etc.)
@@ -329,13 +729,21 @@ impl ASTBuilder {
     ) {
         let mut modifiers = Vec::new();
 
-        // Look for modifier nodes
-        for i in 0..node.child_count() {
-            if let Some(child) = node.child(i) {
-                match child.kind() {
+        // Look for modifier nodes, which Java and C# group under a `modifiers` node
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.children(&mut cursor).collect();
+        for child in children {
+            let group: Vec<Node> = if child.kind() == "modifiers" {
+                let mut group_cursor = child.walk();
+                child.children(&mut group_cursor).collect()
+            } else {
+                vec![child]
+            };
+            for modifier_node in group {
+                match modifier_node.kind() {
                     "public" | "private" | "protected" | "static" | "final" | "abstract"
                     | "virtual" | "override" | "async" | "const" => {
-                        if let Ok(modifier) = child.utf8_text(source.as_bytes()) {
+                        if let Ok(modifier) = modifier_node.utf8_text(source.as_bytes()) {
                             modifiers.push(modifier.to_string());
                         }
                     }
@@ -495,6 +903,16 @@ impl ASTBuilder {
     }
 }
 
+/// Condition of an `#else` branch, e.g. `defined(X)` for `!defined(X)`
+fn negate_condition(condition: &str) -> String {
+    let is_atomic = |condition: &str| !condition.contains([' ', '&', '|']);
+    match condition.strip_prefix('!') {
+        Some(operand) if is_atomic(operand) => operand.to_string(),
+        _ if is_atomic(condition) => format!("!{}", condition),
+        _ => format!("!({})", condition),
+    }
+}
+
 /// Builder pattern for AST construction with fluent interface
 pub struct ASTBuilderBuilder {
     config: ASTBuilderConfig,
@@ -532,6 +950,16 @@ impl ASTBuilderBuilder {
         self
     }
 
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.config.max_depth = depth;
+        self
+    }
+
+    pub fn anonymous_functions(mut self, policy: AnonymousFunctionPolicy) -> Self {
+        self.config.anonymous_functions = policy;
+        self
+    }
+
     pub fn build(self, language: Language) -> ASTBuilder {
         ASTBuilder::new(language, self.config)
     }
@@ -0,0 +1,195 @@
+//! Dockerfile parsing
+//!
+//! No tree-sitter grammar is bundled for Dockerfiles, and the format does not
+//! need one: a Dockerfile is a list of instructions, each a keyword followed
+//! by its arguments, continued over several lines by a trailing escape
+//! character. This parser splits a file into instructions and groups them
+//! into build stages, each starting at a `FROM`.
+//!
+//! The AST has a `build_stage` section per stage, holding its instructions in
+//! order, `FROM` first. Instructions before the first `FROM`, i.e. global
+//! `ARG`s, are children of the root. Comment lines are dropped, and here
+//! documents (`RUN <<EOF`) are kept with the instruction that opens them.
+
+use crate::ast::{ASTNode, NodeMetadata, NodeType};
+use std::collections::HashMap;
+
+/// Parse a Dockerfile into build stages and instructions
+pub fn parse(content: &str) -> ASTNode {
+    let lines: Vec<&str> = content.lines().collect();
+    let escape = escape_character(&lines);
+
+    let mut root = node(NodeType::Program, "source_file", 1, 1, content.to_string());
+    let mut stages: Vec<ASTNode> = Vec::new();
+
+    let mut i = 0;
+    while i < lines.len() {
+        let trimmed = lines[i].trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let mut logical = String::new();
+        while i < lines.len() {
+            let line = lines[i].trim();
+            i += 1;
+            // Comments may sit between the lines of a continued instruction
+            if i - 1 > start && line.starts_with('#') {
+                continue;
+            }
+            match line.strip_suffix(escape) {
+                Some(continued) => {
+                    logical.push_str(continued.trim_end());
+                    logical.push(' ');
+                }
+                None => {
+                    logical.push_str(line);
+                    break;
+                }
+            }
+        }
+        for delimiter in heredoc_delimiters(&logical) {
+            while i < lines.len() {
+                i += 1;
+                if lines[i - 1].trim() == delimiter {
+                    break;
+                }
+            }
+        }
+
+        let (keyword, arguments) = match logical.split_once(char::is_whitespace) {
+            Some((keyword, arguments)) => (keyword.to_uppercase(), arguments.trim().to_string()),
+            None => (logical.trim().to_uppercase(), String::new()),
+        };
+        let original_text = lines[start..i].join("\n");
+        let column = lines[start].len() - lines[start].trim_start().len() + 1;
+        let mut instruction = node(
+            NodeType::ExpressionStatement,
+            &format!("{}_instruction", keyword.to_lowercase()),
+            start + 1,
+            column,
+            original_text,
+        );
+        let attributes = &mut instruction.metadata.attributes;
+        attributes.insert("instruction".to_string(), keyword.clone());
+        attributes.insert("arguments".to_string(), collapse_whitespace(&arguments));
+
+        if keyword == "FROM" {
+            stages.push(stage(instruction, &arguments, stages.len()));
+        } else {
+            match stages.last_mut() {
+                Some(stage) => stage.add_child(instruction),
+                None => root.add_child(instruction),
+            }
+        }
+    }
+
+    for mut stage in stages {
+        stage.metadata.original_text = stage
+            .children
+            .iter()
+            .map(|instruction| instruction.metadata.original_text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        root.add_child(stage);
+    }
+    root
+}
+
+/// Build stage opened by a `FROM` instruction with `arguments`
+///
+/// The stage is named by its `AS` alias, or `stage N` counting from 0 like
+/// `COPY --from=N` does.
+fn stage(from: ASTNode, arguments: &str, index: usize) -> ASTNode {
+    let words: Vec<&str> = arguments
+        .split_whitespace()
+        .filter(|word| !word.starts_with("--"))
+        .collect();
+    let base_image = words.first().copied().unwrap_or_default();
+    let alias = match words.as_slice() {
+        [_, keyword, alias, ..] if keyword.eq_ignore_ascii_case("as") => Some(*alias),
+        _ => None,
+    };
+
+    let mut stage = node(
+        NodeType::Section,
+        "build_stage",
+        from.metadata.line,
+        from.metadata.column,
+        String::new(),
+    );
+    let attributes = &mut stage.metadata.attributes;
+    attributes.insert(
+        "name".to_string(),
+        alias.map_or_else(|| format!("stage {}", index), str::to_string),
+    );
+    if let Some(alias) = alias {
+        attributes.insert("alias".to_string(), alias.to_string());
+    }
+    attributes.insert("base_image".to_string(), base_image.to_string());
+    attributes.insert("stage_index".to_string(), index.to_string());
+    stage.add_child(from);
+    stage
+}
+
+fn node(
+    node_type: NodeType,
+    kind: &str,
+    line: usize,
+    column: usize,
+    original_text: String,
+) -> ASTNode {
+    ASTNode::new(
+        node_type,
+        NodeMetadata {
+            line,
+            column,
+            original_text,
+            attributes: HashMap::from([("kind".to_string(), kind.to_string())]),
+        },
+    )
+}
+
+/// Line continuation character, `\` unless an `# escape=` parser directive
+/// at the top of the file says otherwise
+fn escape_character(lines: &[&str]) -> char {
+    for line in lines {
+        let Some(directive) = line.trim().strip_prefix('#') else {
+            break;
+        };
+        let Some((name, value)) = directive.split_once('=') else {
+            break;
+        };
+        if name.trim().eq_ignore_ascii_case("escape") {
+            if let Some(escape) = value.trim().chars().next() {
+                return escape;
+            }
+        }
+    }
+    '\\'
+}
+
+/// Delimiters of the here documents an instruction opens, e.g. `EOF` for `<<-"EOF"`
+fn heredoc_delimiters(instruction: &str) -> Vec<String> {
+    instruction
+        .split_whitespace()
+        .filter_map(|word| word.strip_prefix("<<"))
+        .map(|word| {
+            word.trim_start_matches('-')
+                .trim_matches(|c| c == '"' || c == '\'')
+        })
+        .filter(|delimiter| {
+            !delimiter.is_empty()
+                && delimiter
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,175 @@
+//! User-defined extraction rules based on tree-sitter queries
+//!
+//! The built-in extractor only knows generic functions and classes. Extraction
+//! rules let users pull framework-specific constructs such as route handlers,
+//! views or test cases out of the parse tree with their own S-expression query,
+//! and give them an element type of their choosing. Captured nodes become
+//! [`Function`]s tagged with [`ElementType::Custom`], so they are matched and
+//! diffed like any other function.
+//!
+//! A query marks the extracted node with an `@element` capture and may mark its
+//! name with `@name`:
+//!
+//! ```text
+//! (method_declaration
+//!   (modifiers (marker_annotation name: (identifier) @annotation (#eq? @annotation "Test")))
+//!   name: (identifier) @name) @element
+//! ```
+
+use crate::ast_builder::{ASTBuilder, ASTBuilderConfig};
+use crate::function::{Function, FunctionSignature};
+use crate::language::Language;
+use crate::matching::ElementType;
+use crate::parser::ParseError;
+use crate::tree_sitter::TreeSitterParser;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tree_sitter::{Query, QueryCursor, Tree};
+
+/// Capture marking the node to extract
+const ELEMENT_CAPTURE: &str = "element";
+
+/// Capture marking the name of the extracted node
+const NAME_CAPTURE: &str = "name";
+
+/// An extraction rule as written in configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractionRule {
+    /// Language the query is written for, e.g. `python` or `java`
+    pub language: String,
+    /// Element type given to captured nodes, e.g. `django_view`
+    pub element_type: String,
+    /// Tree-sitter S-expression query with an `@element` capture
+    pub query: String,
+}
+
+/// A rule whose query compiled against its language's grammar
+#[derive(Debug)]
+struct CompiledRule {
+    element_type: String,
+    query: Query,
+    element_capture: u32,
+    name_capture: Option<u32>,
+}
+
+/// Validated extraction rules, grouped by language
+#[derive(Debug, Default)]
+pub struct ExtractionRules {
+    rules: HashMap<Language, Vec<CompiledRule>>,
+}
+
+impl ExtractionRules {
+    /// Compile rules, failing on the first unknown language or invalid query
+    pub fn new(rules: &[ExtractionRule]) -> Result<Self, ParseError> {
+        let mut compiled = Self::default();
+
+        for rule in rules {
+            let invalid = |message: String| ParseError::InvalidExtractionRule {
+                element_type: rule.element_type.clone(),
+                message,
+            };
+
+            if rule.element_type.trim().is_empty() {
+                return Err(invalid("element_type must not be empty".to_string()));
+            }
+
+            let (language, grammar) = Self::grammar_for(&rule.language)
+                .ok_or_else(|| invalid(format!("unsupported language '{}'", rule.language)))?;
+            let query = Query::new(&grammar, &rule.query).map_err(|e| invalid(e.to_string()))?;
+            let element_capture = query
+                .capture_index_for_name(ELEMENT_CAPTURE)
+                .ok_or_else(|| invalid(format!("query has no @{} capture", ELEMENT_CAPTURE)))?;
+            let name_capture = query.capture_index_for_name(NAME_CAPTURE);
+
+            compiled
+                .rules
+                .entry(language)
+                .or_default()
+                .push(CompiledRule {
+                    element_type: rule.element_type.trim().to_string(),
+                    query,
+                    element_capture,
+                    name_capture,
+                });
+        }
+
+        Ok(compiled)
+    }
+
+    /// Whether no rule is defined
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Elements captured by the rules for `language` in a parsed tree
+    pub(crate) fn extract(
+        &self,
+        language: Language,
+        tree: &Tree,
+        source: &str,
+        builder_config: &ASTBuilderConfig,
+    ) -> Vec<Function> {
+        let Some(rules) = self.rules.get(&language) else {
+            return Vec::new();
+        };
+
+        let mut builder = ASTBuilder::new(language, builder_config.clone());
+        let mut cursor = QueryCursor::new();
+        let mut elements = Vec::new();
+
+        for rule in rules {
+            // Several patterns of one query may capture the same node
+            let mut seen = HashSet::new();
+
+            for query_match in cursor.matches(&rule.query, tree.root_node(), source.as_bytes()) {
+                let captured = |index: u32| {
+                    query_match
+                        .captures
+                        .iter()
+                        .find(|capture| capture.index == index)
+                        .map(|capture| capture.node)
+                };
+                let Some(node) = captured(rule.element_capture) else {
+                    continue;
+                };
+                if !seen.insert(node.id()) {
+                    continue;
+                }
+
+                let name = rule
+                    .name_capture
+                    .and_then(captured)
+                    .and_then(|name| name.utf8_text(source.as_bytes()).ok())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| {
+                        format!("{}_{}", rule.element_type, node.start_position().row + 1)
+                    });
+
+                let mut body = builder.build_subtree(&node, source);
+                body.metadata
+                    .attributes
+                    .insert("element_type".to_string(), rule.element_type.clone());
+
+                let signature = FunctionSignature::from_node(name, &body);
+                elements.push(
+                    Function::new(signature, body, String::new())
+                        .with_element_type(ElementType::Custom(rule.element_type.clone())),
+                );
+            }
+        }
+
+        elements
+    }
+
+    /// Resolve a language name or file extension to a supported grammar
+    fn grammar_for(name: &str) -> Option<(Language, tree_sitter::Language)> {
+        let name = name.trim().to_lowercase();
+        let language = match Language::from_extension(&name) {
+            Language::Unknown => TreeSitterParser::supported_languages()
+                .into_iter()
+                .find(|language| language.tree_sitter_name() == Some(name.as_str()))?,
+            language => language,
+        };
+        Some((language, TreeSitterParser::grammar(language)?))
+    }
+}
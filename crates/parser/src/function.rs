@@ -1,7 +1,23 @@
 //! Function and signature definitions
 
 use crate::ast::ASTNode;
+use crate::matching::ElementType;
 use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Positional name of the `index`-th anonymous function in `scope`, e.g. `<anon@render:0>`
+pub fn anonymous_function_name(scope: &str, index: usize) -> String {
+    format!("<anon@{}:{}>", scope, index)
+}
+
+/// Enclosing scope of a positional anonymous function name, `render` for `<anon@render:0>`
+pub fn anonymous_function_scope(name: &str) -> Option<&str> {
+    let (scope, index) = name
+        .strip_prefix("<anon@")?
+        .strip_suffix('>')?
+        .rsplit_once(':')?;
+    index.parse::<usize>().ok().map(|_| scope)
+}
 
 /// Represents a function or method in the code
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -11,6 +27,12 @@ pub struct Function {
     pub dependencies: Vec<String>,
     pub hash: String,
     pub location: FunctionLocation,
+    /// Kind of element, a custom one for elements captured by extraction rules
+    #[serde(default)]
+    pub element_type: ElementType,
+    /// Preprocessor condition the function is compiled under, e.g. `defined(_WIN32)`
+    #[serde(default)]
+    pub condition: Option<String>,
 }
 
 /// Function signature information
@@ -21,6 +43,9 @@ pub struct FunctionSignature {
     pub return_type: Option<Type>,
     pub modifiers: Vec<String>,
     pub generic_parameters: Vec<String>,
+    /// Receiver type of a method declared outside its type (Go), e.g. `Server`
+    #[serde(default)]
+    pub receiver: Option<String>,
 }
 
 /// Function parameter
@@ -56,8 +81,13 @@ impl Function {
     pub fn new(signature: FunctionSignature, body: ASTNode, file_path: String) -> Self {
         let hash = Self::calculate_hash(&signature, &body);
 
-        // Calculate actual end line by finding the maximum line number in the AST subtree
-        let end_line = Self::calculate_end_line(&body);
+        // The node's own end position, or the last line any of its descendants start on
+        let end_line = body
+            .metadata
+            .attributes
+            .get("end_line")
+            .and_then(|line| line.parse().ok())
+            .unwrap_or_else(|| Self::calculate_end_line(&body));
 
         let location = FunctionLocation {
             file_path,
@@ -67,13 +97,51 @@ impl Function {
             end_column: body.metadata.column, // TODO: Calculate actual end column
         };
 
+        let condition = body.metadata.attributes.get("condition").cloned();
+
         Self {
             signature,
             body,
             dependencies: Vec::new(),
             hash,
             location,
+            element_type: ElementType::Function,
+            condition,
+        }
+    }
+
+    /// Tag the function with another element type
+    ///
+    /// The type is mixed into the hash so an element and a plain function with
+    /// the same body are never treated as identical.
+    pub fn with_element_type(mut self, element_type: ElementType) -> Self {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        if let ElementType::Custom(name) = &element_type {
+            let mut hasher = DefaultHasher::new();
+            self.hash.hash(&mut hasher);
+            name.hash(&mut hasher);
+            self.hash = format!("{:x}", hasher.finish());
         }
+        self.element_type = element_type;
+        self
+    }
+
+    /// Byte range of the whole function in its source file
+    pub fn byte_span(&self) -> Option<Range<usize>> {
+        let (start, end) = self
+            .body
+            .metadata
+            .attributes
+            .get("byte_range")?
+            .split_once("..")?;
+        Some(start.parse().ok()?..end.parse().ok()?)
+    }
+
+    /// Exact source text of the function, sliced from the file it was parsed from
+    pub fn body_text<'a>(&self, source: &'a str) -> Option<&'a str> {
+        source.get(self.byte_span()?)
     }
 
     /// Calculate the end line by finding the maximum line number in the AST subtree
@@ -96,7 +164,7 @@ impl Function {
         use std::hash::{Hash, Hasher};
 
         let mut hasher = DefaultHasher::new();
-        signature.name.hash(&mut hasher);
+        signature.qualified_name().hash(&mut hasher);
         signature.parameters.len().hash(&mut hasher);
         body.structural_hash().hash(&mut hasher);
 
@@ -153,6 +221,44 @@ impl FunctionSignature {
             return_type: None,
             modifiers: Vec::new(),
             generic_parameters: Vec::new(),
+            receiver: None,
+        }
+    }
+
+    /// Create a signature from a function node, picking up the parameter types,
+    /// receiver type and type parameters recorded by the AST builder
+    pub fn from_node(name: String, node: &ASTNode) -> Self {
+        let attributes = &node.metadata.attributes;
+        let mut signature = Self::new(name);
+        // Untyped parameters (Python, JavaScript) keep an empty type name
+        signature.parameters = attributes
+            .get("parameter_types")
+            .map(|types| {
+                types
+                    .split(';')
+                    .enumerate()
+                    .map(|(position, param_type)| Parameter {
+                        name: format!("param{}", position),
+                        param_type: Type::new(param_type.to_string()),
+                        default_value: None,
+                        is_variadic: false,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        signature.receiver = attributes.get("receiver_type").cloned();
+        signature.generic_parameters = attributes
+            .get("type_parameters")
+            .map(|params| params.split(',').map(str::to_string).collect())
+            .unwrap_or_default();
+        signature
+    }
+
+    /// Name qualified with the receiver type, e.g. `Server.Close`
+    pub fn qualified_name(&self) -> String {
+        match &self.receiver {
+            Some(receiver) => format!("{}.{}", receiver, self.name),
+            None => self.name.clone(),
         }
     }
 
@@ -161,14 +267,15 @@ impl FunctionSignature {
         let mut score = 0.0;
         let mut total_weight = 0.0;
 
-        // Name similarity (weight: 0.4)
+        // Name similarity (weight: 0.4), methods compared with their receiver
         let name_weight = 0.4;
-        if self.name == other.name {
+        let (name, other_name) = (self.qualified_name(), other.qualified_name());
+        if name == other_name {
             score += name_weight;
         } else {
             // Use edit distance for partial similarity
-            let distance = edit_distance::edit_distance(&self.name, &other.name);
-            let max_len = self.name.len().max(other.name.len());
+            let distance = edit_distance::edit_distance(&name, &other_name);
+            let max_len = name.len().max(other_name.len());
             if max_len > 0 {
                 score += name_weight * (1.0 - (distance as f64 / max_len as f64));
             }
@@ -19,6 +19,8 @@ pub enum Language {
     Ruby,
     PHP,
     Swift,
+    Markdown,
+    Dockerfile,
     Unknown,
 }
 
@@ -36,29 +38,62 @@ impl std::fmt::Display for Language {
             Language::Ruby => write!(f, "Ruby"),
             Language::PHP => write!(f, "PHP"),
             Language::Swift => write!(f, "Swift"),
+            Language::Markdown => write!(f, "Markdown"),
+            Language::Dockerfile => write!(f, "Dockerfile"),
             Language::Unknown => write!(f, "Unknown"),
         }
     }
 }
 
 impl Language {
-    pub fn from_extension(ext: &str) -> Self {
-        match ext.to_lowercase().as_str() {
-            "java" => Language::Java,
-            "py" | "pyw" => Language::Python,
-            "js" | "jsx" => Language::JavaScript,
-            "ts" | "tsx" => Language::TypeScript,
-            "cpp" | "cc" | "cxx" | "c++" | "hpp" => Language::Cpp,
-            "c" | "h" => Language::C,
-            "rs" => Language::Rust,
-            "go" => Language::Go,
-            "rb" | "rake" | "gemspec" => Language::Ruby,
-            "php" | "phtml" | "php3" | "php4" | "php5" | "phps" => Language::PHP,
-            "swift" => Language::Swift,
-            _ => Language::Unknown,
+    /// Every known language, i.e. every variant except `Unknown`
+    pub fn all() -> &'static [Language] {
+        &[
+            Language::Java,
+            Language::Python,
+            Language::JavaScript,
+            Language::TypeScript,
+            Language::Cpp,
+            Language::C,
+            Language::Rust,
+            Language::Go,
+            Language::Ruby,
+            Language::PHP,
+            Language::Swift,
+            Language::Markdown,
+            Language::Dockerfile,
+        ]
+    }
+
+    /// Lowercase file extensions, without the dot, detected as this language
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Language::Java => &["java"],
+            Language::Python => &["py", "pyw"],
+            Language::JavaScript => &["js", "jsx"],
+            Language::TypeScript => &["ts", "tsx"],
+            Language::Cpp => &["cpp", "cc", "cxx", "c++", "hpp"],
+            Language::C => &["c", "h"],
+            Language::Rust => &["rs"],
+            Language::Go => &["go"],
+            Language::Ruby => &["rb", "rake", "gemspec"],
+            Language::PHP => &["php", "phtml", "php3", "php4", "php5", "phps"],
+            Language::Swift => &["swift"],
+            Language::Markdown => &["md", "markdown"],
+            Language::Dockerfile => &["dockerfile", "containerfile"],
+            Language::Unknown => &[],
         }
     }
 
+    pub fn from_extension(ext: &str) -> Self {
+        let ext = ext.to_lowercase();
+        Self::all()
+            .iter()
+            .copied()
+            .find(|language| language.extensions().contains(&ext.as_str()))
+            .unwrap_or(Language::Unknown)
+    }
+
     pub fn tree_sitter_name(&self) -> Option<&'static str> {
         match self {
             Language::Java => Some("java"),
@@ -72,16 +107,52 @@ impl Language {
             Language::Ruby => Some("ruby"),
             Language::PHP => Some("php"),
             Language::Swift => Some("swift"),
-            Language::Unknown => None,
+            Language::Markdown => Some("markdown"),
+            Language::Dockerfile | Language::Unknown => None,
         }
     }
 }
 
+/// A known language, the file extensions it is detected from and whether it can be parsed
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct LanguageSupport {
+    pub language: Language,
+    /// Display name, e.g. `C++`
+    pub name: String,
+    pub extensions: &'static [&'static str],
+    /// Whether a tree-sitter grammar is bundled, or, for Dockerfiles, a
+    /// line-based parser; files of other languages are detected but cannot be
+    /// diffed structurally
+    pub parsable: bool,
+}
+
 /// Language detector that identifies programming language from file path and content
 pub struct LanguageDetector;
 
 impl LanguageDetector {
+    /// Every language that can be detected, with its extensions
+    pub fn supported() -> Vec<LanguageSupport> {
+        let parsable = crate::tree_sitter::TreeSitterParser::supported_languages();
+        Language::all()
+            .iter()
+            .map(|&language| LanguageSupport {
+                language,
+                name: language.to_string(),
+                extensions: language.extensions(),
+                parsable: parsable.contains(&language),
+            })
+            .collect()
+    }
+
     pub fn detect_from_path<P: AsRef<Path>>(path: P) -> Language {
+        // Dockerfiles are named rather than given an extension: `Dockerfile`,
+        // `Dockerfile.dev`, `Containerfile`
+        if let Some(name) = path.as_ref().file_name().and_then(|name| name.to_str()) {
+            let stem = name.split('.').next().unwrap_or(name).to_lowercase();
+            if stem == "dockerfile" || stem == "containerfile" {
+                return Language::Dockerfile;
+            }
+        }
         if let Some(ext) = path.as_ref().extension() {
             if let Some(ext_str) = ext.to_str() {
                 return Language::from_extension(ext_str);
@@ -159,6 +159,32 @@ pub static LANGUAGE_CONFIGS: Lazy<HashMap<Language, LanguageConfig>> = Lazy::new
         },
     );
 
+    // Markdown configuration
+    configs.insert(
+        Language::Markdown,
+        LanguageConfig {
+            name: "markdown",
+            file_extensions: vec!["md", "markdown"],
+            function_node_types: vec![],
+            class_node_types: vec![],
+            comment_node_types: vec![],
+            identifier_field_names: vec![],
+        },
+    );
+
+    // Dockerfiles are parsed line by line, without a tree-sitter grammar
+    configs.insert(
+        Language::Dockerfile,
+        LanguageConfig {
+            name: "dockerfile",
+            file_extensions: vec!["dockerfile", "containerfile"],
+            function_node_types: vec![],
+            class_node_types: vec![],
+            comment_node_types: vec![],
+            identifier_field_names: vec![],
+        },
+    );
+
     configs
 });
 
@@ -182,6 +208,11 @@ pub static NODE_TYPE_MAPPINGS: Lazy<HashMap<&'static str, NodeType>> = Lazy::new
     mappings.insert("union_specifier", NodeType::Class);
     mappings.insert("interface_declaration", NodeType::Interface);
     mappings.insert("interface_definition", NodeType::Interface);
+    mappings.insert("enum_declaration", NodeType::Enum);
+    mappings.insert("enum_item", NodeType::Enum);
+    mappings.insert("enum_constant", NodeType::EnumValue);
+    mappings.insert("enum_variant", NodeType::EnumValue);
+    mappings.insert("enum_member_declaration", NodeType::EnumValue);
 
     // Functions and methods
     mappings.insert("function_declaration", NodeType::Function);
@@ -239,6 +270,11 @@ pub static NODE_TYPE_MAPPINGS: Lazy<HashMap<&'static str, NodeType>> = Lazy::new
     mappings.insert("line_comment", NodeType::Comment);
     mappings.insert("block_comment", NodeType::Comment);
 
+    // Documents
+    mappings.insert("section", NodeType::Section);
+    mappings.insert("atx_heading", NodeType::Heading);
+    mappings.insert("setext_heading", NodeType::Heading);
+
     mappings
 });
 
@@ -271,3 +307,482 @@ impl LanguageConfig {
             .unwrap_or(NodeType::Unknown)
     }
 }
+
+/// How a string literal is delimited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StringDelimiter {
+    pub open: &'static str,
+    pub close: &'static str,
+    /// Whether a backslash escapes the next character
+    pub escapes: bool,
+    /// Whether the literal may span lines; an unterminated single-line
+    /// literal ends at the end of its line
+    pub multiline: bool,
+}
+
+impl StringDelimiter {
+    const fn quoted(quote: &'static str, escapes: bool, multiline: bool) -> Self {
+        Self {
+            open: quote,
+            close: quote,
+            escapes,
+            multiline,
+        }
+    }
+
+    /// Length of a literal's body and closing delimiter, `body` starting
+    /// right after the opening delimiter
+    fn body_len(&self, body: &str) -> usize {
+        let mut escaped = false;
+        for (offset, ch) in body.char_indices() {
+            if escaped {
+                escaped = false;
+            } else if self.escapes && ch == '\\' {
+                escaped = true;
+            } else if !self.multiline && ch == '\n' {
+                return offset;
+            } else if body[offset..].starts_with(self.close) {
+                return offset + self.close.len();
+            }
+        }
+        body.len()
+    }
+}
+
+/// Raw string literals, whose closing delimiter is chosen by the author
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawStringStyle {
+    None,
+    /// C++ `R"delim( ... )delim"`, optionally prefixed by `u8`, `u`, `U` or `L`
+    Cpp,
+    /// Rust `r"..."` and `r#"..."#`, optionally prefixed by `b`
+    Rust,
+}
+
+/// Comment and string literal syntax of a language
+///
+/// Text-level passes that run without a syntax tree, such as comment
+/// stripping, brace counting or telling formatting-only edits apart, scan
+/// source with this table so that comment markers inside strings and quotes
+/// inside comments are not mistaken for the real thing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommentSyntax {
+    pub line_comments: &'static [&'static str],
+    pub block_comments: &'static [(&'static str, &'static str)],
+    /// Comment openers that mark documentation, e.g. `/**`
+    pub doc_comments: &'static [&'static str],
+    /// String delimiters, longest opener first
+    pub strings: &'static [StringDelimiter],
+    pub raw_strings: RawStringStyle,
+    /// Whether a string literal standing as a statement documents the code,
+    /// as Python docstrings do
+    pub docstrings: bool,
+}
+
+/// What a span of source text is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxSpanKind {
+    Code,
+    Comment,
+    String,
+}
+
+/// A span of source text, in byte offsets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyntaxSpan {
+    pub kind: SyntaxSpanKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+const C_STRINGS: &[StringDelimiter] = &[
+    StringDelimiter::quoted("\"", true, false),
+    StringDelimiter::quoted("'", true, false),
+];
+
+const JAVA_STRINGS: &[StringDelimiter] = &[
+    StringDelimiter::quoted("\"\"\"", true, true),
+    StringDelimiter::quoted("\"", true, false),
+    StringDelimiter::quoted("'", true, false),
+];
+
+const JAVASCRIPT_STRINGS: &[StringDelimiter] = &[
+    StringDelimiter::quoted("\"", true, false),
+    StringDelimiter::quoted("'", true, false),
+    StringDelimiter::quoted("`", true, true),
+];
+
+const PYTHON_STRINGS: &[StringDelimiter] = &[
+    StringDelimiter::quoted("\"\"\"", true, true),
+    StringDelimiter::quoted("'''", true, true),
+    StringDelimiter::quoted("\"", true, false),
+    StringDelimiter::quoted("'", true, false),
+];
+
+const GO_STRINGS: &[StringDelimiter] = &[
+    StringDelimiter::quoted("\"", true, false),
+    StringDelimiter::quoted("'", true, false),
+    StringDelimiter::quoted("`", false, true),
+];
+
+// Char literals are left out: a lone `'` is far more often a lifetime
+const RUST_STRINGS: &[StringDelimiter] = &[StringDelimiter::quoted("\"", true, true)];
+
+const SCRIPT_STRINGS: &[StringDelimiter] = &[
+    StringDelimiter::quoted("\"", true, true),
+    StringDelimiter::quoted("'", true, true),
+];
+
+const SWIFT_STRINGS: &[StringDelimiter] = &[
+    StringDelimiter::quoted("\"\"\"", true, true),
+    StringDelimiter::quoted("\"", true, false),
+];
+
+const C_BLOCK_COMMENTS: &[(&str, &str)] = &[("/*", "*/")];
+
+static C_FAMILY_SYNTAX: CommentSyntax = CommentSyntax {
+    line_comments: &["//"],
+    block_comments: C_BLOCK_COMMENTS,
+    doc_comments: &["/**", "///"],
+    strings: C_STRINGS,
+    raw_strings: RawStringStyle::Cpp,
+    docstrings: false,
+};
+
+static JAVA_SYNTAX: CommentSyntax = CommentSyntax {
+    line_comments: &["//"],
+    block_comments: C_BLOCK_COMMENTS,
+    doc_comments: &["/**"],
+    strings: JAVA_STRINGS,
+    raw_strings: RawStringStyle::None,
+    docstrings: false,
+};
+
+static JAVASCRIPT_SYNTAX: CommentSyntax = CommentSyntax {
+    line_comments: &["//"],
+    block_comments: C_BLOCK_COMMENTS,
+    doc_comments: &["/**"],
+    strings: JAVASCRIPT_STRINGS,
+    raw_strings: RawStringStyle::None,
+    docstrings: false,
+};
+
+static PYTHON_SYNTAX: CommentSyntax = CommentSyntax {
+    line_comments: &["#"],
+    block_comments: &[],
+    doc_comments: &[],
+    strings: PYTHON_STRINGS,
+    raw_strings: RawStringStyle::None,
+    docstrings: true,
+};
+
+static GO_SYNTAX: CommentSyntax = CommentSyntax {
+    line_comments: &["//"],
+    block_comments: C_BLOCK_COMMENTS,
+    doc_comments: &[],
+    strings: GO_STRINGS,
+    raw_strings: RawStringStyle::None,
+    docstrings: false,
+};
+
+static RUST_SYNTAX: CommentSyntax = CommentSyntax {
+    line_comments: &["//"],
+    block_comments: C_BLOCK_COMMENTS,
+    doc_comments: &["///", "//!", "/**", "/*!"],
+    strings: RUST_STRINGS,
+    raw_strings: RawStringStyle::Rust,
+    docstrings: false,
+};
+
+static RUBY_SYNTAX: CommentSyntax = CommentSyntax {
+    line_comments: &["#"],
+    block_comments: &[],
+    doc_comments: &[],
+    strings: SCRIPT_STRINGS,
+    raw_strings: RawStringStyle::None,
+    docstrings: false,
+};
+
+static PHP_SYNTAX: CommentSyntax = CommentSyntax {
+    line_comments: &["//", "#"],
+    block_comments: C_BLOCK_COMMENTS,
+    doc_comments: &["/**"],
+    strings: SCRIPT_STRINGS,
+    raw_strings: RawStringStyle::None,
+    docstrings: false,
+};
+
+static SWIFT_SYNTAX: CommentSyntax = CommentSyntax {
+    line_comments: &["//"],
+    block_comments: C_BLOCK_COMMENTS,
+    doc_comments: &["///", "/**"],
+    strings: SWIFT_STRINGS,
+    raw_strings: RawStringStyle::None,
+    docstrings: false,
+};
+
+static PLAIN_SYNTAX: CommentSyntax = CommentSyntax {
+    line_comments: &[],
+    block_comments: &[],
+    doc_comments: &[],
+    strings: &[],
+    raw_strings: RawStringStyle::None,
+    docstrings: false,
+};
+
+impl CommentSyntax {
+    /// Comment and string syntax of a language; text in a language without
+    /// comments, such as Markdown, scans as code, and so does a Dockerfile,
+    /// whose `#` only starts a comment at the beginning of a line
+    pub fn for_language(language: &Language) -> &'static CommentSyntax {
+        match language {
+            Language::C | Language::Cpp => &C_FAMILY_SYNTAX,
+            Language::Java => &JAVA_SYNTAX,
+            Language::JavaScript | Language::TypeScript => &JAVASCRIPT_SYNTAX,
+            Language::Python => &PYTHON_SYNTAX,
+            Language::Go => &GO_SYNTAX,
+            Language::Rust => &RUST_SYNTAX,
+            Language::Ruby => &RUBY_SYNTAX,
+            Language::PHP => &PHP_SYNTAX,
+            Language::Swift => &SWIFT_SYNTAX,
+            Language::Markdown | Language::Dockerfile | Language::Unknown => &PLAIN_SYNTAX,
+        }
+    }
+
+    /// Split text into consecutive code, comment and string spans
+    ///
+    /// Unterminated comments and multi-line strings run to the end of the
+    /// text. A line comment's span stops before its newline.
+    pub fn spans(&self, text: &str) -> Vec<SyntaxSpan> {
+        let bytes = text.as_bytes();
+        let mut spans = Vec::new();
+        let mut code_start = 0;
+        let mut offset = 0;
+
+        while offset < text.len() {
+            let rest = &text[offset..];
+            let literal = if is_identifier_byte(bytes[offset]) {
+                // Whole words are skipped so string prefixes such as `R` or
+                // `r#` are only recognized at the start of a word
+                let word_len = rest.bytes().take_while(|&b| is_identifier_byte(b)).count();
+                match self.raw_string_len(rest, word_len) {
+                    Some(len) => Some((SyntaxSpanKind::String, len)),
+                    None => {
+                        offset += word_len;
+                        continue;
+                    }
+                }
+            } else {
+                self.literal_at(rest)
+            };
+
+            match literal {
+                Some((kind, len)) => {
+                    if code_start < offset {
+                        spans.push(SyntaxSpan {
+                            kind: SyntaxSpanKind::Code,
+                            start: code_start,
+                            end: offset,
+                        });
+                    }
+                    spans.push(SyntaxSpan {
+                        kind,
+                        start: offset,
+                        end: offset + len,
+                    });
+                    offset += len;
+                    code_start = offset;
+                }
+                None => offset += rest.chars().next().map_or(1, char::len_utf8),
+            }
+        }
+
+        if code_start < text.len() {
+            spans.push(SyntaxSpan {
+                kind: SyntaxSpanKind::Code,
+                start: code_start,
+                end: text.len(),
+            });
+        }
+        spans
+    }
+
+    /// Text with comments blanked out, keeping newlines so line and column
+    /// numbers stay valid
+    pub fn strip_comments(&self, text: &str) -> String {
+        self.blank(text, &[SyntaxSpanKind::Comment])
+    }
+
+    /// Text with comments and string literals blanked out, leaving only
+    /// code, e.g. for counting braces
+    pub fn strip_comments_and_strings(&self, text: &str) -> String {
+        self.blank(text, &[SyntaxSpanKind::Comment, SyntaxSpanKind::String])
+    }
+
+    /// Whether a comment, delimiters included, is documentation
+    pub fn is_doc_comment(&self, comment: &str) -> bool {
+        let empty_block = self
+            .block_comments
+            .iter()
+            .any(|(open, close)| comment.strip_prefix(open) == Some(close));
+        !empty_block
+            && self
+                .doc_comments
+                .iter()
+                .any(|prefix| comment.starts_with(prefix))
+    }
+
+    /// Spans of documentation: doc comments, and for languages with
+    /// docstrings, string literals that make up a statement of their own
+    pub fn doc_spans(&self, text: &str) -> Vec<SyntaxSpan> {
+        self.spans(text)
+            .into_iter()
+            .filter(|span| match span.kind {
+                SyntaxSpanKind::Comment => self.is_doc_comment(&text[span.start..span.end]),
+                SyntaxSpanKind::String if self.docstrings => {
+                    let line_start = text[..span.start].rfind('\n').map_or(0, |i| i + 1);
+                    let line_end = text[span.end..]
+                        .find('\n')
+                        .map_or(text.len(), |i| span.end + i);
+                    text[line_start..span.start].trim().is_empty()
+                        && self
+                            .strip_comments(&text[span.end..line_end])
+                            .trim()
+                            .is_empty()
+                }
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// Whether two texts differ only in whitespace and comments
+    ///
+    /// Whitespace inside string literals counts, as does whitespace that
+    /// separates two words.
+    pub fn differs_only_in_formatting(&self, old: &str, new: &str) -> bool {
+        self.code_fingerprint(old) == self.code_fingerprint(new)
+    }
+
+    fn code_fingerprint(&self, text: &str) -> String {
+        let mut fingerprint = String::with_capacity(text.len());
+        let mut pending_space = false;
+        for span in self.spans(text) {
+            let slice = &text[span.start..span.end];
+            match span.kind {
+                SyntaxSpanKind::Comment => pending_space = true,
+                SyntaxSpanKind::String => {
+                    fingerprint.push_str(slice);
+                    pending_space = false;
+                }
+                SyntaxSpanKind::Code => {
+                    for ch in slice.chars() {
+                        if ch.is_whitespace() {
+                            pending_space = true;
+                            continue;
+                        }
+                        let separates_words = fingerprint
+                            .chars()
+                            .last()
+                            .is_some_and(|last| is_word_char(last) && is_word_char(ch));
+                        if pending_space && separates_words {
+                            fingerprint.push(' ');
+                        }
+                        fingerprint.push(ch);
+                        pending_space = false;
+                    }
+                }
+            }
+        }
+        fingerprint
+    }
+
+    fn blank(&self, text: &str, kinds: &[SyntaxSpanKind]) -> String {
+        let mut blanked = String::with_capacity(text.len());
+        for span in self.spans(text) {
+            let slice = &text[span.start..span.end];
+            if kinds.contains(&span.kind) {
+                blanked.extend(slice.chars().map(|ch| if ch == '\n' { '\n' } else { ' ' }));
+            } else {
+                blanked.push_str(slice);
+            }
+        }
+        blanked
+    }
+
+    /// Comment or string literal starting at the beginning of `rest`
+    fn literal_at(&self, rest: &str) -> Option<(SyntaxSpanKind, usize)> {
+        for (open, close) in self.block_comments {
+            if let Some(body) = rest.strip_prefix(open) {
+                let len = body.find(close).map_or(body.len(), |i| i + close.len());
+                return Some((SyntaxSpanKind::Comment, open.len() + len));
+            }
+        }
+        if self
+            .line_comments
+            .iter()
+            .any(|marker| rest.starts_with(marker))
+        {
+            return Some((
+                SyntaxSpanKind::Comment,
+                rest.find('\n').unwrap_or(rest.len()),
+            ));
+        }
+        self.strings
+            .iter()
+            .find(|delimiter| rest.starts_with(delimiter.open))
+            .map(|delimiter| {
+                let body = &rest[delimiter.open.len()..];
+                (
+                    SyntaxSpanKind::String,
+                    delimiter.open.len() + delimiter.body_len(body),
+                )
+            })
+    }
+
+    /// Length of a raw string literal whose prefix is the `word_len` long
+    /// word at the start of `rest`
+    fn raw_string_len(&self, rest: &str, word_len: usize) -> Option<usize> {
+        let word = &rest[..word_len];
+        let after = &rest[word_len..];
+        let close = match self.raw_strings {
+            RawStringStyle::None => return None,
+            RawStringStyle::Cpp => {
+                if !matches!(word, "R" | "LR" | "uR" | "UR" | "u8R") {
+                    return None;
+                }
+                let quoted = after.strip_prefix('"')?;
+                let delimiter = &quoted[..quoted.find('(')?];
+                if delimiter.len() > 16
+                    || delimiter
+                        .chars()
+                        .any(|ch| ch.is_whitespace() || matches!(ch, ')' | '\\'))
+                {
+                    return None;
+                }
+                format!("){}\"", delimiter)
+            }
+            RawStringStyle::Rust => {
+                if !matches!(word, "r" | "br") {
+                    return None;
+                }
+                let hashes = after.bytes().take_while(|&b| b == b'#').count();
+                if !after[hashes..].starts_with('"') {
+                    return None;
+                }
+                format!("\"{}", "#".repeat(hashes))
+            }
+        };
+        // Both openers mirror their closer: `R"delim(` and `)delim"`, `r#"` and `"#`
+        let open_len = word_len + close.len();
+        let body = &rest[open_len..];
+        Some(open_len + body.find(&close).map_or(body.len(), |i| i + close.len()))
+    }
+}
+
+fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
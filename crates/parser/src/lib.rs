@@ -6,24 +6,38 @@
 pub mod ast;
 pub mod ast_builder;
 pub mod ast_processor;
+pub mod dockerfile;
+pub mod extraction_rules;
 pub mod function;
 pub mod language;
 pub mod language_config;
 pub mod matching;
+pub mod normalization;
+pub mod notebook;
 pub mod parser;
+pub mod parser_pool;
 pub mod tree_sitter;
 
-pub use ast::{ASTNode, NodeMetadata, NodeType};
-pub use ast_builder::{ASTBuilder, ASTBuilderBuilder, ASTBuilderConfig};
+pub use ast::{char_column, line_range_text, source_lines, ASTNode, NodeMetadata, NodeType};
+pub use ast_builder::{ASTBuilder, ASTBuilderBuilder, ASTBuilderConfig, AnonymousFunctionPolicy};
 pub use ast_processor::{
     ASTAnalysis, ASTProcessor, FunctionSignatureInfo, Symbol, SymbolTable, SymbolType,
 };
-pub use function::{Function, FunctionLocation, FunctionSignature, Parameter, Type};
-pub use language::{Language, LanguageDetector};
+pub use extraction_rules::{ExtractionRule, ExtractionRules};
+pub use function::{
+    anonymous_function_name, anonymous_function_scope, Function, FunctionLocation,
+    FunctionSignature, Parameter, Type,
+};
+pub use language::{Language, LanguageDetector, LanguageSupport};
+pub use language_config::{
+    CommentSyntax, RawStringStyle, StringDelimiter, SyntaxSpan, SyntaxSpanKind,
+};
 pub use matching::{
     Change, ChangeDetail, ChangeType, CodeElement, ElementType, MatchResult, RefactoringType,
 };
+pub use normalization::{NormalizationRule, Normalizer};
 pub use parser::{ParseError, ParseResult, Parser};
+pub use parser_pool::{ParserPool, PooledParser, PARSER_POOL_SIZE_ENV};
 
 /// Re-export commonly used types
 pub type Result<T> = std::result::Result<T, ParseError>;
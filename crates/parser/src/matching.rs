@@ -59,8 +59,9 @@ pub struct CodeElement {
 }
 
 /// Types of code elements
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub enum ElementType {
+    #[default]
     Function,
     Method,
     Class,
@@ -68,6 +69,14 @@ pub enum ElementType {
     Module,
     Variable,
     Constant,
+    /// Element captured by a user-defined extraction rule
+    Custom(String),
+    /// Document section: a heading and the content under it
+    Section,
+    /// Dockerfile build stage: a `FROM` and the instructions up to the next
+    BuildStage,
+    /// Dockerfile instruction, e.g. a `RUN` or `COPY`
+    Instruction,
 }
 
 /// Detailed information about a change
@@ -95,6 +104,19 @@ pub enum RefactoringType {
     InlineVariable,
     RenameVariable,
     ChangeSignature,
+    IntroduceParameterObject,
+    /// Nested conditionals replaced by early returns
+    IntroduceGuardClauses,
+    /// Method moved from a subclass to its superclass
+    PullUpMethod,
+    /// Method moved from a superclass to a subclass
+    PushDownMethod,
+    /// Function made asynchronous or synchronous, changing how callers invoke it
+    SyncAsyncConversion,
+    /// Constants or literals replaced by an enum or a dedicated type
+    ReplaceConstantsWithType,
+    /// Project-specific pattern reported by a custom refactoring rule
+    Custom(String),
 }
 
 impl Default for MatchResult {
@@ -218,9 +240,14 @@ impl Change {
 
 impl CodeElement {
     pub fn from_function(function: &Function) -> Self {
+        let id = match &function.element_type {
+            ElementType::Custom(element_type) => format!("{}_{}", element_type, function.hash),
+            _ => format!("func_{}", function.hash),
+        };
+
         Self {
-            id: format!("func_{}", function.hash),
-            element_type: ElementType::Function,
+            id,
+            element_type: function.element_type.clone(),
             name: function.signature.name.clone(),
             file_path: function.location.file_path.clone(),
             start_line: function.location.start_line,
@@ -0,0 +1,235 @@
+//! Normalization of predictable churn before comparison
+//!
+//! Some tools rewrite files in ways that carry no meaning: an IDE re-sorts
+//! imports, a code generator stamps the date into its header. Normalization
+//! rules, enabled per language in configuration, rewrite both versions of a
+//! file the same way before they are parsed, so that churn is not reported.
+//!
+//! No rule runs unless it is configured, each rule only touches the lines it
+//! documents, and no rule adds or removes lines, so reported line numbers
+//! still point into the original files.
+
+use crate::language::Language;
+use crate::language_config::{CommentSyntax, SyntaxSpanKind};
+use crate::parser::ParseError;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Dates such as `2024-01-31`, `2024/01/31 10:15:00Z` or `31.01.2024`, and times of day
+static DATE_OR_TIME: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"\d{4}[-/.]\d{1,2}[-/.]\d{1,2}(?:[T ]\d{1,2}:\d{2}(?::\d{2}(?:\.\d+)?)?(?:Z|[+-]\d{2}:?\d{2})?)?|\d{1,2}[-/.]\d{1,2}[-/.]\d{4}|\d{1,2}:\d{2}(?::\d{2})?",
+    )
+    .expect("valid date pattern")
+});
+
+/// A rewrite applied to both versions of a file before comparison
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NormalizationRule {
+    /// Sort each run of consecutive single-line imports
+    ///
+    /// Java `import` declarations, Go import specs (inside `import ( … )` or
+    /// on an `import` line of their own) and Rust `use` declarations. Any
+    /// other line, blank lines and comments included, ends a run, so import
+    /// groups stay where they are. Only available for languages whose import
+    /// order has no effect.
+    SortImports,
+    /// Remove dates and times from the file's header comment lines that
+    /// mention "generated"
+    ///
+    /// The header is the comments before the first line of code. The rest of
+    /// each such line is kept, so a changed generator version still shows.
+    StripGeneratedHeaderDates,
+}
+
+impl NormalizationRule {
+    /// Whether the rule can be enabled for `language`
+    pub fn supports(self, language: Language) -> bool {
+        match self {
+            NormalizationRule::SortImports => {
+                matches!(language, Language::Java | Language::Go | Language::Rust)
+            }
+            NormalizationRule::StripGeneratedHeaderDates => {
+                !matches!(language, Language::Markdown | Language::Unknown)
+            }
+        }
+    }
+
+    fn apply(self, language: Language, source: &str) -> String {
+        match self {
+            NormalizationRule::SortImports => sort_imports(language, source),
+            NormalizationRule::StripGeneratedHeaderDates => {
+                strip_generated_header_dates(language, source)
+            }
+        }
+    }
+}
+
+/// Validated normalization rules, grouped by language
+#[derive(Debug, Clone, Default)]
+pub struct Normalizer {
+    rules: HashMap<Language, Vec<NormalizationRule>>,
+}
+
+impl Normalizer {
+    /// Validate rules keyed by language name, e.g. `java` or `go`
+    ///
+    /// Fails on an unknown language or a rule the language does not support.
+    pub fn new(rules: &HashMap<String, Vec<NormalizationRule>>) -> Result<Self, ParseError> {
+        let mut normalizer = Self::default();
+
+        for (name, language_rules) in rules {
+            let invalid = |message: String| ParseError::InvalidNormalizationRule {
+                language: name.clone(),
+                message,
+            };
+            let language = Self::language_for(name)
+                .ok_or_else(|| invalid(format!("unsupported language '{}'", name)))?;
+            for rule in language_rules {
+                if !rule.supports(language) {
+                    return Err(invalid(format!(
+                        "{:?} is not available for {}",
+                        rule, language
+                    )));
+                }
+            }
+            normalizer
+                .rules
+                .entry(language)
+                .or_default()
+                .extend(language_rules);
+        }
+
+        Ok(normalizer)
+    }
+
+    /// Whether no rule is enabled
+    pub fn is_empty(&self) -> bool {
+        self.rules.values().all(Vec::is_empty)
+    }
+
+    /// Rules enabled for `language`, in the order they are applied
+    pub fn rules(&self, language: Language) -> &[NormalizationRule] {
+        self.rules.get(&language).map_or(&[], Vec::as_slice)
+    }
+
+    /// Source with the rules enabled for `language` applied
+    pub fn normalize<'a>(&self, language: Language, source: &'a str) -> Cow<'a, str> {
+        let mut normalized = Cow::Borrowed(source);
+        for rule in self.rules(language) {
+            normalized = Cow::Owned(rule.apply(language, &normalized));
+        }
+        normalized
+    }
+
+    fn language_for(name: &str) -> Option<Language> {
+        let name = name.trim().to_lowercase();
+        match Language::from_extension(&name) {
+            Language::Unknown => Language::all()
+                .iter()
+                .copied()
+                .find(|language| language.tree_sitter_name() == Some(name.as_str())),
+            language => Some(language),
+        }
+    }
+}
+
+fn sort_imports(language: Language, source: &str) -> String {
+    let mut lines: Vec<&str> = source.split('\n').collect();
+    let mut in_go_block = false;
+    let mut run_start = None;
+
+    for index in 0..=lines.len() {
+        let is_import = lines.get(index).is_some_and(|line| {
+            let line = line.trim();
+            match language {
+                Language::Java => line.starts_with("import ") && line.ends_with(';'),
+                Language::Rust => {
+                    let line = strip_visibility(line);
+                    line.starts_with("use ") && line.ends_with(';')
+                }
+                Language::Go => {
+                    if line == "import (" {
+                        in_go_block = true;
+                        false
+                    } else if in_go_block && line == ")" {
+                        in_go_block = false;
+                        false
+                    } else {
+                        let spec = if in_go_block {
+                            Some(line)
+                        } else {
+                            line.strip_prefix("import ")
+                        };
+                        // `"path"`, or `name "path"` with an alias, `_` or `.`
+                        spec.is_some_and(|spec| {
+                            spec.ends_with('"') && (spec.starts_with('"') || spec.contains(" \""))
+                        })
+                    }
+                }
+                _ => false,
+            }
+        });
+
+        match (is_import, run_start) {
+            (true, None) => run_start = Some(index),
+            (false, Some(start)) => {
+                lines[start..index].sort_by_key(|line| import_sort_key(language, line));
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Imports sort by path, as rustfmt and gofmt do, not by visibility or alias
+fn import_sort_key(language: Language, line: &str) -> &str {
+    let line = line.trim();
+    match language {
+        Language::Rust => strip_visibility(line),
+        Language::Go => line.find('"').map_or(line, |quote| &line[quote..]),
+        _ => line,
+    }
+}
+
+fn strip_visibility(line: &str) -> &str {
+    line.strip_prefix("pub(crate) ")
+        .or_else(|| line.strip_prefix("pub "))
+        .unwrap_or(line)
+}
+
+fn strip_generated_header_dates(language: Language, source: &str) -> String {
+    let header_end = CommentSyntax::for_language(&language)
+        .spans(source)
+        .into_iter()
+        .find_map(|span| {
+            let text = &source[span.start..span.end];
+            let code = text.trim_start();
+            (span.kind != SyntaxSpanKind::Comment && !code.is_empty())
+                .then(|| span.start + text.len() - code.len())
+        })
+        .unwrap_or(source.len());
+    // Whole lines only, so a line holding both comment and code is left alone
+    let header_end = source[..header_end]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+
+    let header: Vec<Cow<str>> = source[..header_end]
+        .split_inclusive('\n')
+        .map(|line| {
+            if line.to_lowercase().contains("generated") {
+                DATE_OR_TIME.replace_all(line, "")
+            } else {
+                Cow::Borrowed(line)
+            }
+        })
+        .collect();
+
+    header.concat() + &source[header_end..]
+}
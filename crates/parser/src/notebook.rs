@@ -0,0 +1,132 @@
+//! Jupyter notebook code extraction
+//!
+//! A notebook is a JSON document: a list of cells with their source, plus
+//! outputs, execution counts and metadata that change on every run. Only the
+//! code matters to a structural comparison, so [`extract_code`] joins the code
+//! cells into one source file, in notebook order, and records the lines each
+//! cell landed on so changes found in that source can be traced back to it.
+//!
+//! IPython syntax is not valid Python, so magics (`%timeit`, whole `%%bash`
+//! cells) and shell escapes (`!pip install`) are commented out in place,
+//! keeping every other line where it is.
+
+use crate::language::Language;
+use crate::parser::ParseError;
+use serde_json::Value;
+use std::path::Path;
+
+/// Where a code cell sits in the extracted source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotebookCell {
+    /// Position of the cell in the notebook, from 1, counting every cell
+    pub index: usize,
+    /// First line of the cell in the extracted source
+    pub start_line: usize,
+    /// Last line of the cell in the extracted source
+    pub end_line: usize,
+}
+
+/// Code of a notebook, as one source file
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotebookSource {
+    /// Kernel language, Python unless the notebook says otherwise
+    pub language: Language,
+    /// Code cells in order, separated by a blank line
+    pub source: String,
+    pub cells: Vec<NotebookCell>,
+}
+
+impl NotebookSource {
+    /// Cell holding `line` of the extracted source
+    pub fn cell_at(&self, line: usize) -> Option<&NotebookCell> {
+        self.cells
+            .iter()
+            .find(|cell| (cell.start_line..=cell.end_line).contains(&line))
+    }
+}
+
+/// Whether `path` names a notebook, by its `.ipynb` extension
+pub fn is_notebook(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("ipynb"))
+}
+
+/// Extract the code cells of a notebook in nbformat 4
+pub fn extract_code(json: &str) -> crate::Result<NotebookSource> {
+    let notebook: Value = serde_json::from_str(json)
+        .map_err(|e| ParseError::ParseFailed(format!("Invalid notebook JSON: {}", e)))?;
+    let cells = notebook
+        .get("cells")
+        .and_then(Value::as_array)
+        .ok_or_else(|| ParseError::ParseFailed("Notebook has no cell list".to_string()))?;
+
+    let mut source = String::new();
+    let mut code_cells = Vec::new();
+    let mut next_line = 1;
+    for (position, cell) in cells.iter().enumerate() {
+        if cell.get("cell_type").and_then(Value::as_str) != Some("code") {
+            continue;
+        }
+        let text = cell_source(cell);
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        let cell_magic = text.trim_start().starts_with("%%");
+        let start_line = next_line;
+        for line in text.lines() {
+            if cell_magic || is_ipython_line(line) {
+                source.push_str("# ");
+            }
+            source.push_str(line);
+            source.push('\n');
+            next_line += 1;
+        }
+        code_cells.push(NotebookCell {
+            index: position + 1,
+            start_line,
+            end_line: next_line - 1,
+        });
+
+        // Keeps the last statement of a cell from running into the next cell
+        source.push('\n');
+        next_line += 1;
+    }
+
+    Ok(NotebookSource {
+        language: kernel_language(&notebook),
+        source,
+        cells: code_cells,
+    })
+}
+
+/// Source of a cell, stored either as one string or as a list of lines
+fn cell_source(cell: &Value) -> String {
+    match cell.get("source") {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+/// Whether `line` is a line magic or a shell escape
+fn is_ipython_line(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with('%') || line.starts_with('!')
+}
+
+fn kernel_language(notebook: &Value) -> Language {
+    let metadata = notebook.get("metadata");
+    let name = metadata
+        .and_then(|metadata| metadata.pointer("/kernelspec/language"))
+        .or_else(|| metadata.and_then(|metadata| metadata.pointer("/language_info/name")))
+        .and_then(Value::as_str);
+    name.and_then(|name| {
+        Language::all()
+            .iter()
+            .copied()
+            .find(|language| language.to_string().eq_ignore_ascii_case(name))
+    })
+    .unwrap_or(Language::Python)
+}
@@ -1,6 +1,7 @@
 //! Core parser interface and error types
 
 use crate::ast::ASTNode;
+use crate::function::Function;
 use crate::language::Language;
 use thiserror::Error;
 
@@ -18,6 +19,18 @@ pub enum ParseError {
 
     #[error("Tree-sitter error: {0}")]
     TreeSitterError(String),
+
+    #[error("Maximum AST depth of {max_depth} exceeded at line {line}")]
+    MaxDepthExceeded { max_depth: usize, line: usize },
+
+    #[error("Invalid extraction rule '{element_type}': {message}")]
+    InvalidExtractionRule {
+        element_type: String,
+        message: String,
+    },
+
+    #[error("Invalid normalization rules for '{language}': {message}")]
+    InvalidNormalizationRule { language: String, message: String },
 }
 
 /// Result of parsing operation
@@ -27,6 +40,8 @@ pub struct ParseResult {
     pub language: Language,
     pub errors: Vec<String>,
     pub warnings: Vec<String>,
+    /// Elements captured by the parser's extraction rules
+    pub custom_elements: Vec<Function>,
 }
 
 /// Main parser interface
@@ -0,0 +1,182 @@
+//! Pool of tree-sitter parsers shared between `TreeSitterParser`s
+//!
+//! A tree-sitter parser is `Send` but not `Sync`, so each parse needs one to
+//! itself. Rather than every `TreeSitterParser` creating a parser per
+//! language up front, parsers are created on first use, checked out for the
+//! duration of a parse and returned afterwards. At most `size` parsers exist
+//! per language; a parse that finds them all in use waits for one.
+
+use crate::language::Language;
+use crate::parser::ParseError;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Environment variable overriding the size of the global pool
+pub const PARSER_POOL_SIZE_ENV: &str = "SMART_DIFF_PARSER_POOL_SIZE";
+
+static GLOBAL_POOL: Lazy<Arc<ParserPool>> = Lazy::new(|| Arc::new(ParserPool::from_env()));
+
+#[derive(Default)]
+struct LanguageParsers {
+    idle: Vec<tree_sitter::Parser>,
+    /// Parsers of the language in existence, idle or checked out
+    created: usize,
+}
+
+/// Bounded pool of tree-sitter parsers, per language
+pub struct ParserPool {
+    size: usize,
+    languages: Mutex<HashMap<Language, LanguageParsers>>,
+    returned: Condvar,
+    created: AtomicUsize,
+}
+
+impl ParserPool {
+    /// Pool keeping at most `size` parsers per language, at least one
+    pub fn new(size: usize) -> Self {
+        Self {
+            size: size.max(1),
+            languages: Mutex::new(HashMap::new()),
+            returned: Condvar::new(),
+            created: AtomicUsize::new(0),
+        }
+    }
+
+    /// Pool sized by [`PARSER_POOL_SIZE_ENV`], or by the available parallelism
+    ///
+    /// An unparsable value is ignored with a warning.
+    pub fn from_env() -> Self {
+        let default_size = std::thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1);
+        let size = match std::env::var(PARSER_POOL_SIZE_ENV) {
+            Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+                tracing::warn!(
+                    "Ignoring {}={:?}: not a whole number",
+                    PARSER_POOL_SIZE_ENV,
+                    value
+                );
+                default_size
+            }),
+            Err(_) => default_size,
+        };
+        Self::new(size)
+    }
+
+    /// The pool shared by every `TreeSitterParser` not given its own
+    pub fn global() -> Arc<Self> {
+        GLOBAL_POOL.clone()
+    }
+
+    /// Most parsers kept per language
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Number of parsers created so far, over all languages
+    pub fn parsers_created(&self) -> usize {
+        self.created.load(Ordering::Relaxed)
+    }
+
+    /// Check out a parser for `language`, waiting while all of its parsers are in use
+    pub fn checkout(&self, language: Language) -> Result<PooledParser<'_>, ParseError> {
+        let mut languages = self.languages.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            let parsers = languages.entry(language).or_default();
+            if let Some(parser) = parsers.idle.pop() {
+                return Ok(PooledParser::new(self, language, parser));
+            }
+            if parsers.created < self.size {
+                parsers.created += 1;
+                break;
+            }
+            languages = self
+                .returned
+                .wait(languages)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        drop(languages);
+
+        match Self::create(language) {
+            Ok(parser) => {
+                self.created.fetch_add(1, Ordering::Relaxed);
+                Ok(PooledParser::new(self, language, parser))
+            }
+            Err(error) => {
+                self.release(language, None);
+                Err(error)
+            }
+        }
+    }
+
+    fn create(language: Language) -> Result<tree_sitter::Parser, ParseError> {
+        let grammar = crate::tree_sitter::TreeSitterParser::grammar(language)
+            .ok_or(ParseError::UnsupportedLanguage(language))?;
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(&grammar).map_err(|e| {
+            ParseError::TreeSitterError(format!("Failed to set language {:?}: {}", language, e))
+        })?;
+        Ok(parser)
+    }
+
+    /// Return a checked-out parser, or give up its slot if it is `None`
+    fn release(&self, language: Language, parser: Option<tree_sitter::Parser>) {
+        let mut languages = self.languages.lock().unwrap_or_else(|e| e.into_inner());
+        let parsers = languages.entry(language).or_default();
+        match parser {
+            Some(parser) => parsers.idle.push(parser),
+            None => parsers.created -= 1,
+        }
+        drop(languages);
+        self.returned.notify_one();
+    }
+}
+
+/// A parser checked out of a [`ParserPool`], returned to it when dropped
+pub struct PooledParser<'a> {
+    pool: &'a ParserPool,
+    language: Language,
+    parser: Option<tree_sitter::Parser>,
+}
+
+impl<'a> PooledParser<'a> {
+    fn new(pool: &'a ParserPool, language: Language, parser: tree_sitter::Parser) -> Self {
+        Self {
+            pool,
+            language,
+            parser: Some(parser),
+        }
+    }
+}
+
+impl Deref for PooledParser<'_> {
+    type Target = tree_sitter::Parser;
+
+    fn deref(&self) -> &Self::Target {
+        self.parser
+            .as_ref()
+            .expect("parser is present until dropped")
+    }
+}
+
+impl DerefMut for PooledParser<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.parser
+            .as_mut()
+            .expect("parser is present until dropped")
+    }
+}
+
+impl Drop for PooledParser<'_> {
+    fn drop(&mut self) {
+        let mut parser = self.parser.take();
+        // A parse interrupted by a panic may leave state behind
+        if let Some(parser) = &mut parser {
+            parser.reset();
+        }
+        self.pool.release(self.language, parser);
+    }
+}
@@ -185,6 +185,38 @@ if __name__ == "__main__":
         );
     }
 
+    #[test]
+    fn test_supported_languages_round_trip_through_detection() {
+        use crate::language::LanguageDetector;
+
+        assert!(!Language::all().contains(&Language::Unknown));
+        assert!(Language::Unknown.extensions().is_empty());
+
+        let supported = LanguageDetector::supported();
+        assert_eq!(supported.len(), Language::all().len());
+        for support in supported {
+            assert!(
+                !support.extensions.is_empty(),
+                "{} has no extensions",
+                support.name
+            );
+            for extension in support.extensions {
+                assert_eq!(
+                    LanguageDetector::detect_from_path(format!("file.{}", extension)),
+                    support.language
+                );
+                assert_eq!(
+                    Language::from_extension(&extension.to_uppercase()),
+                    support.language
+                );
+            }
+            assert_eq!(
+                support.parsable,
+                TreeSitterParser::supported_languages().contains(&support.language)
+            );
+        }
+    }
+
     #[test]
     fn test_tree_sitter_parser_creation() {
         let parser = TreeSitterParser::new();
@@ -699,4 +731,800 @@ function test() {
         let result = parser.parse(simple_code, Language::C);
         assert!(result.is_ok(), "Should parse simple C code");
     }
+
+    #[test]
+    fn test_deeply_nested_expression_exceeds_max_depth() {
+        use crate::parser::ParseError;
+
+        let nesting = 10_000;
+        let code = format!(
+            "int main() {{ return {}1{}; }}",
+            "(".repeat(nesting),
+            ")".repeat(nesting)
+        );
+
+        let parser = TreeSitterParser::new().expect("Failed to create parser");
+        match parser.parse(&code, Language::C) {
+            Err(ParseError::MaxDepthExceeded { max_depth, line }) => {
+                assert_eq!(max_depth, 1000);
+                assert_eq!(line, 1);
+            }
+            other => panic!(
+                "Expected MaxDepthExceeded, got {:?}",
+                other.map(|r| r.language)
+            ),
+        }
+
+        // The limit is configurable, and normal nesting stays within it
+        let shallow = format!(
+            "int main() {{ return {}1{}; }}",
+            "(".repeat(50),
+            ")".repeat(50)
+        );
+        let strict = TreeSitterParser::builder()
+            .max_depth(20)
+            .build()
+            .expect("Should build parser successfully");
+        assert!(matches!(
+            strict.parse(&shallow, Language::C),
+            Err(ParseError::MaxDepthExceeded { max_depth: 20, .. })
+        ));
+        assert!(parser.parse(&shallow, Language::C).is_ok());
+    }
+
+    #[test]
+    fn test_parameter_names_are_recorded() {
+        let parser = TreeSitterParser::new().expect("Failed to create parser");
+        let parameter_names = |code: &str, language: Language| {
+            let result = parser
+                .parse(code, language)
+                .expect("Parsing should succeed");
+            let mut functions = result.ast.find_by_type(&crate::ast::NodeType::Function);
+            functions.extend(result.ast.find_by_type(&crate::ast::NodeType::Method));
+            functions
+                .into_iter()
+                .find_map(|n| n.metadata.attributes.get("parameter_names").cloned())
+        };
+
+        assert_eq!(
+            parameter_names(
+                "class Canvas { void render(String title, int width) {} }",
+                Language::Java
+            )
+            .as_deref(),
+            Some("title;width")
+        );
+        assert_eq!(
+            parameter_names("def render(title, width):\n    pass\n", Language::Python).as_deref(),
+            Some("title;width")
+        );
+        assert_eq!(
+            parameter_names(
+                "int render(char *title, int width) { return 0; }",
+                Language::C
+            )
+            .as_deref(),
+            Some("title;width")
+        );
+    }
+
+    #[test]
+    fn test_go_method_receiver_and_type_parameters() {
+        use crate::function::FunctionSignature;
+
+        let parser = TreeSitterParser::new().expect("Failed to create parser");
+
+        let go_code = r#"
+package store
+
+func (s *Cache[K, V]) Get(key K) (V, bool) {
+    v, ok := s.items[key]
+    return v, ok
+}
+
+func Map[T any, U comparable](items []T, f func(T) U) []U {
+    return nil
+}
+"#;
+
+        let result = parser
+            .parse(go_code, Language::Go)
+            .expect("Parsing should succeed");
+
+        let method = result
+            .ast
+            .find_by_type(&crate::ast::NodeType::Method)
+            .into_iter()
+            .find(|n| n.metadata.attributes.get("name").map(String::as_str) == Some("Get"))
+            .expect("Should find method Get");
+        let signature = FunctionSignature::from_node("Get".to_string(), method);
+        assert_eq!(signature.receiver.as_deref(), Some("Cache"));
+        assert_eq!(signature.qualified_name(), "Cache.Get");
+
+        let function = result
+            .ast
+            .find_by_type(&crate::ast::NodeType::Function)
+            .into_iter()
+            .find(|n| n.metadata.attributes.get("name").map(String::as_str) == Some("Map"))
+            .expect("Should find function Map");
+        let signature = FunctionSignature::from_node("Map".to_string(), function);
+        assert_eq!(signature.receiver, None);
+        assert_eq!(signature.generic_parameters, vec!["T", "U"]);
+    }
+
+    #[test]
+    fn test_columns_count_characters_not_bytes() {
+        let code =
+            "const s = \"h\u{e9}llo \u{65e5}\u{672c}\"; function add(a, b) { return a + b; }\n";
+        let expected_column = code[..code.find("function").unwrap()].chars().count() + 1;
+        assert_eq!(expected_column, 23);
+
+        let parser = TreeSitterParser::new().expect("Failed to create parser");
+        let parse_result = parser
+            .parse(code, Language::JavaScript)
+            .expect("Should parse JavaScript");
+
+        let functions = parse_result
+            .ast
+            .find_by_type(&crate::ast::NodeType::Function);
+        let add = functions
+            .iter()
+            .find(|f| f.metadata.attributes.get("name").map(String::as_str) == Some("add"))
+            .expect("Should find function add");
+
+        assert_eq!(add.metadata.line, 1);
+        assert_eq!(add.metadata.column, expected_column);
+        assert_eq!(
+            crate::ast::char_column(code, code.find("function").unwrap()),
+            23
+        );
+    }
+
+    #[test]
+    fn test_extraction_rules_tag_annotated_methods() {
+        use crate::extraction_rules::{ExtractionRule, ExtractionRules};
+        use crate::matching::{CodeElement, ElementType};
+        use std::sync::Arc;
+
+        let rules = ExtractionRules::new(&[ExtractionRule {
+            language: "java".to_string(),
+            element_type: "test_case".to_string(),
+            query: r#"
+                (method_declaration
+                  (modifiers (marker_annotation name: (identifier) @annotation (#eq? @annotation "Test")))
+                  name: (identifier) @name) @element
+            "#
+            .to_string(),
+        }])
+        .expect("Rule should compile");
+
+        let parser = TreeSitterParser::builder()
+            .extraction_rules(Arc::new(rules))
+            .build()
+            .expect("Should build parser successfully");
+
+        let java_code = r#"
+public class CalculatorTest {
+    @Test
+    public void addsNumbers() {
+        assertEquals(3, add(1, 2));
+    }
+
+    @Override
+    public String toString() {
+        return "CalculatorTest";
+    }
+
+    @Test
+    public void subtractsNumbers() {
+        assertEquals(1, sub(2, 1));
+    }
+
+    private int helper() {
+        return 0;
+    }
+}
+"#;
+
+        let result = parser
+            .parse(java_code, Language::Java)
+            .expect("Parsing should succeed");
+
+        let names: Vec<&str> = result
+            .custom_elements
+            .iter()
+            .map(|element| element.signature.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["addsNumbers", "subtractsNumbers"]);
+
+        let element = CodeElement::from_function(&result.custom_elements[0]);
+        assert_eq!(
+            element.element_type,
+            ElementType::Custom("test_case".to_string())
+        );
+        assert!(element.id.starts_with("test_case_"));
+        assert_eq!(element.start_line, 3);
+
+        // Rules only apply to their own language
+        let python = parser
+            .parse("def addsNumbers():\n    pass\n", Language::Python)
+            .expect("Parsing should succeed");
+        assert!(python.custom_elements.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_extraction_rules_are_rejected() {
+        use crate::extraction_rules::{ExtractionRule, ExtractionRules};
+        use crate::parser::ParseError;
+
+        let rule = |language: &str, query: &str| ExtractionRule {
+            language: language.to_string(),
+            element_type: "route".to_string(),
+            query: query.to_string(),
+        };
+
+        let error = ExtractionRules::new(&[rule("python", "(function_definition @element")])
+            .expect_err("Unbalanced query should be rejected");
+        assert!(matches!(
+            &error,
+            ParseError::InvalidExtractionRule { element_type, .. } if element_type == "route"
+        ));
+        assert!(error.to_string().contains("Invalid syntax"));
+
+        assert!(ExtractionRules::new(&[rule("python", "(no_such_node) @element")]).is_err());
+        assert!(
+            ExtractionRules::new(&[rule("python", "(function_definition) @function")]).is_err()
+        );
+        assert!(ExtractionRules::new(&[rule("cobol", "(program) @element")]).is_err());
+        assert!(ExtractionRules::new(&[rule("py", "(function_definition) @element")]).is_ok());
+    }
+
+    #[test]
+    fn test_function_span_ignores_braces_in_string_literals() {
+        use crate::function::{Function, FunctionSignature};
+
+        let parser = TreeSitterParser::new().expect("Failed to create parser");
+        let c_code = r#"#include <stdio.h>
+
+int print_block(int depth)
+{
+    printf("} closing brace {{ }\n");
+    if (depth > 0) {
+        return print_block(depth - 1);
+    }
+    return 0;
+}
+
+int after(void) { return 1; }
+"#;
+
+        let result = parser
+            .parse(c_code, Language::C)
+            .expect("Parsing should succeed");
+        let functions: Vec<Function> = result
+            .ast
+            .find_by_type(&crate::ast::NodeType::Function)
+            .into_iter()
+            .filter(|n| {
+                n.metadata.attributes.get("kind").map(String::as_str) == Some("function_definition")
+            })
+            .map(|n| {
+                let name = n
+                    .metadata
+                    .attributes
+                    .get("name")
+                    .cloned()
+                    .unwrap_or_default();
+                Function::new(
+                    FunctionSignature::from_node(name.clone(), n),
+                    n.clone(),
+                    String::new(),
+                )
+            })
+            .collect();
+
+        let function = functions
+            .iter()
+            .find(|f| f.signature.name == "print_block")
+            .expect("Should find print_block");
+        let body = function
+            .body_text(c_code)
+            .expect("Span should be in bounds");
+        assert!(body.starts_with("int print_block(int depth)"));
+        assert!(body.ends_with("    return 0;\n}"));
+        assert_eq!(function.location.start_line, 3);
+        assert_eq!(function.location.end_line, 10);
+
+        let after = functions
+            .iter()
+            .find(|f| f.signature.name == "after")
+            .expect("Should find after");
+        assert_eq!(
+            after.body_text(c_code),
+            Some("int after(void) { return 1; }")
+        );
+        assert_eq!(after.location.end_line, 12);
+    }
+
+    #[test]
+    fn test_mixed_line_endings_and_multibyte_lines() {
+        use crate::ast::{line_range_text, source_lines};
+
+        let source = "// h\u{e9}llo\r\nint a(void) { return 1; }\rint b(void) {\n    return 2; // \u{65e5}\u{672c}\r\n}\n";
+        assert_eq!(
+            source_lines(source),
+            vec![
+                "// h\u{e9}llo",
+                "int a(void) { return 1; }",
+                "int b(void) {",
+                "    return 2; // \u{65e5}\u{672c}",
+                "}"
+            ]
+        );
+        assert_eq!(source_lines("a\r\r\nb\n\n"), vec!["a", "", "b", ""]);
+
+        let parser = TreeSitterParser::new().expect("Failed to create parser");
+        let parse_result = parser.parse(source, Language::C).expect("Should parse C");
+        let functions = parse_result
+            .ast
+            .find_by_type(&crate::ast::NodeType::Function);
+        let b = functions
+            .iter()
+            .find(|f| f.metadata.attributes.get("name").map(String::as_str) == Some("b"))
+            .expect("Should find function b");
+
+        // The lone `\r` after `a` starts a new line for the parser too
+        assert_eq!(b.metadata.line, 3);
+        assert_eq!(
+            line_range_text(source, 3, 5),
+            "int b(void) {\n    return 2; // \u{65e5}\u{672c}\n}"
+        );
+        assert_eq!(line_range_text(source, 5, 99), "}");
+        assert_eq!(line_range_text(source, 0, 1), "// h\u{e9}llo");
+        assert_eq!(line_range_text(source, 9, 12), "");
+    }
+
+    #[test]
+    fn test_anonymous_functions_get_stable_positional_names() {
+        use crate::ast::NodeType;
+        use crate::ast_builder::AnonymousFunctionPolicy;
+
+        let names = |parser: &TreeSitterParser, code: &str| -> Vec<String> {
+            let result = parser.parse(code, Language::JavaScript).unwrap();
+            result
+                .ast
+                .find_by_type(&NodeType::Function)
+                .iter()
+                .filter_map(|f| f.metadata.attributes.get("name").cloned())
+                .collect()
+        };
+        let code = "function render(items) {\n  items.forEach((item) => {\n    items.map(function (x) { return x; });\n  });\n}\nconst handler = () => 1;\nsetTimeout(() => go(), 10);\n";
+
+        let parser = TreeSitterParser::new().unwrap();
+        assert_eq!(
+            names(&parser, code),
+            vec![
+                "render",
+                "<anon@render:0>",
+                "<anon@render:1>",
+                "handler",
+                "<anon@top-level:0>"
+            ]
+        );
+        // Code added in another scope does not shift the names
+        let shifted = format!("function setup() {{ run(() => 0); }}\n{}", code);
+        assert_eq!(names(&parser, &shifted)[3], "<anon@render:0>");
+        assert_eq!(
+            crate::function::anonymous_function_scope("<anon@render:1>"),
+            Some("render")
+        );
+        assert_eq!(crate::function::anonymous_function_scope("render"), None);
+
+        let skipping = TreeSitterParser::builder()
+            .anonymous_functions(AnonymousFunctionPolicy::Skip)
+            .build()
+            .unwrap();
+        assert_eq!(names(&skipping, code), vec!["render", "handler"]);
+    }
+
+    fn literal_spans(
+        language: Language,
+        text: &str,
+    ) -> Vec<(crate::language_config::SyntaxSpanKind, &str)> {
+        use crate::language_config::{CommentSyntax, SyntaxSpanKind};
+
+        CommentSyntax::for_language(&language)
+            .spans(text)
+            .into_iter()
+            .filter(|span| span.kind != SyntaxSpanKind::Code)
+            .map(|span| (span.kind, &text[span.start..span.end]))
+            .collect()
+    }
+
+    #[test]
+    fn test_comment_syntax_finds_comment_and_string_boundaries() {
+        use crate::language_config::SyntaxSpanKind::{Comment, String};
+
+        let cpp = "auto s = R\"x(/* not a comment */ \")\" {)x\"; // closes {\nchar q = '\"'; /* a\nb */ int y;";
+        assert_eq!(
+            literal_spans(Language::Cpp, cpp),
+            vec![
+                (String, "R\"x(/* not a comment */ \")\" {)x\""),
+                (Comment, "// closes {"),
+                (String, "'\"'"),
+                (Comment, "/* a\nb */"),
+            ]
+        );
+        // `R` inside a word is no raw string prefix
+        assert_eq!(
+            literal_spans(Language::C, "FOOR\"(x\"; // c"),
+            vec![(String, "\"(x\""), (Comment, "// c")]
+        );
+
+        let python = "x = '''it's # not a comment\n\"\"\" still'''  # real\ny = \"#\" # also real";
+        assert_eq!(
+            literal_spans(Language::Python, python),
+            vec![
+                (String, "'''it's # not a comment\n\"\"\" still'''"),
+                (Comment, "# real"),
+                (String, "\"#\""),
+                (Comment, "# also real"),
+            ]
+        );
+
+        let javascript = "const u = `http://example.com/${id}`; // link";
+        assert_eq!(
+            literal_spans(Language::JavaScript, javascript),
+            vec![(String, "`http://example.com/${id}`"), (Comment, "// link")]
+        );
+
+        let go = "p := `C:\\dir\\` // path";
+        assert_eq!(
+            literal_spans(Language::Go, go),
+            vec![(String, "`C:\\dir\\`"), (Comment, "// path")]
+        );
+
+        let rust = "let s = r#\"say \"hi\" // no\"#; // yes";
+        assert_eq!(
+            literal_spans(Language::Rust, rust),
+            vec![(String, "r#\"say \"hi\" // no\"#"), (Comment, "// yes")]
+        );
+
+        // An unterminated single-line string stops at the end of its line
+        assert_eq!(
+            literal_spans(Language::C, "s = \"open\n// next"),
+            vec![(String, "\"open"), (Comment, "// next")]
+        );
+    }
+
+    #[test]
+    fn test_comment_syntax_strips_and_classifies() {
+        use crate::language_config::CommentSyntax;
+
+        let c = CommentSyntax::for_language(&Language::C);
+        let code = "int f() { /* { */ return g(\"}\"); }";
+        let stripped = c.strip_comments_and_strings(code);
+        assert_eq!(stripped.len(), code.len());
+        assert_eq!(stripped.matches('{').count(), 1);
+        assert_eq!(stripped.matches('}').count(), 1);
+        assert_eq!(
+            c.strip_comments("a; // x\nb;"),
+            "a;     \nb;",
+            "newlines survive stripping"
+        );
+
+        assert!(c.differs_only_in_formatting(
+            "int f(int a){return a+1;}",
+            "int f(int a) {\n    // increment\n    return a + 1;\n}"
+        ));
+        assert!(!c.differs_only_in_formatting("return \"a b\";", "return \"a  b\";"));
+        assert!(!c.differs_only_in_formatting("int a;", "inta;"));
+
+        assert!(c.is_doc_comment("/** Adds. */"));
+        assert!(!c.is_doc_comment("/**/"));
+        assert!(!c.is_doc_comment("/* plain */"));
+
+        let python = CommentSyntax::for_language(&Language::Python);
+        let function = "def f():\n    '''Docs.'''\n    x = 'value'\n    return x\n";
+        let docs: Vec<&str> = python
+            .doc_spans(function)
+            .iter()
+            .map(|span| &function[span.start..span.end])
+            .collect();
+        assert_eq!(docs, vec!["'''Docs.'''"]);
+
+        let markdown = CommentSyntax::for_language(&Language::Markdown);
+        assert!(literal_spans(Language::Markdown, "# Title // 'x'").is_empty());
+        assert_eq!(markdown.strip_comments("# Title"), "# Title");
+    }
+
+    #[test]
+    fn test_functions_record_preprocessor_conditions() {
+        let parser = TreeSitterParser::new().expect("Failed to create parser");
+        let code = r#"#ifndef PLATFORM_H
+#define PLATFORM_H
+
+int common(void) { return 0; }
+
+#ifdef _WIN32
+int open_port(void) { return 1; }
+#elif defined(__linux__)
+int open_port(void) { return 2; }
+#else
+#ifndef NO_FALLBACK
+int open_port(void) { return 3; }
+#endif
+#endif
+
+#endif
+"#;
+        let parse_result = parser.parse(code, Language::C).unwrap();
+        let conditions: Vec<(String, Option<String>)> = parse_result
+            .ast
+            .find_by_type(&crate::ast::NodeType::Function)
+            .into_iter()
+            .map(|node| &node.metadata.attributes)
+            .filter(|attributes| attributes["kind"] == "function_definition")
+            .map(|attributes| {
+                (
+                    attributes.get("name").cloned().unwrap_or_default(),
+                    attributes.get("condition").cloned(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            conditions,
+            vec![
+                ("common".to_string(), None),
+                ("open_port".to_string(), Some("defined(_WIN32)".to_string())),
+                (
+                    "open_port".to_string(),
+                    Some("!defined(_WIN32) && defined(__linux__)".to_string())
+                ),
+                (
+                    "open_port".to_string(),
+                    Some(
+                        "!defined(_WIN32) && !defined(__linux__) && !defined(NO_FALLBACK)"
+                            .to_string()
+                    )
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parsers_are_reused_from_the_pool() {
+        use crate::parser_pool::ParserPool;
+        use std::sync::Arc;
+
+        let code = "int add(int a, int b) {\n    return a + b;\n}\n";
+        let pool = Arc::new(ParserPool::new(2));
+        let first = TreeSitterParser::builder()
+            .parser_pool(pool.clone())
+            .build()
+            .unwrap();
+        let second = TreeSitterParser::builder()
+            .parser_pool(pool.clone())
+            .build()
+            .unwrap();
+        assert_eq!(pool.parsers_created(), 0);
+
+        let expected = first.parse(code, Language::C).unwrap();
+        for parser in [&first, &second, &first] {
+            let result = parser.parse(code, Language::C).unwrap();
+            assert_eq!(result.ast.structural_hash(), expected.ast.structural_hash());
+            assert_eq!(result.errors, expected.errors);
+        }
+        assert_eq!(pool.parsers_created(), 1);
+
+        // Concurrent parses never hold more parsers than the pool size
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..10 {
+                        let result = first.parse(code, Language::C).unwrap();
+                        assert_eq!(result.ast.structural_hash(), expected.ast.structural_hash());
+                    }
+                });
+            }
+        });
+        assert!(pool.parsers_created() <= 2);
+
+        first
+            .parse("def f():\n    pass\n", Language::Python)
+            .unwrap();
+        assert!(pool.parsers_created() <= 3);
+    }
+
+    #[test]
+    fn test_import_order_is_normalized_away_when_configured() {
+        use crate::normalization::{NormalizationRule, Normalizer};
+        use std::collections::HashMap;
+
+        let config = |language: &str, rules: &[NormalizationRule]| {
+            HashMap::from([(language.to_string(), rules.to_vec())])
+        };
+        let sort_imports = Normalizer::new(&config(
+            "java",
+            &[
+                NormalizationRule::SortImports,
+                NormalizationRule::StripGeneratedHeaderDates,
+            ],
+        ))
+        .unwrap();
+
+        let before = "// Generated by protoc on 2024-01-31 10:15:00\npackage shop;\n\nimport java.util.Map;\nimport java.util.List;\n\nimport shop.model.Order;\n\nclass Cart {}\n";
+        let after = "// Generated by protoc on 2024-02-07 09:00:12\npackage shop;\n\nimport java.util.List;\nimport java.util.Map;\n\nimport shop.model.Order;\n\nclass Cart {}\n";
+        assert_ne!(before, after);
+        assert_eq!(
+            sort_imports.normalize(Language::Java, before),
+            sort_imports.normalize(Language::Java, after)
+        );
+        assert_eq!(
+            sort_imports
+                .normalize(Language::Java, after)
+                .lines()
+                .count(),
+            after.lines().count()
+        );
+        // Nothing is normalized for a language without rules
+        assert_eq!(sort_imports.normalize(Language::Go, before), before);
+
+        // Moving an import to another group is a real change
+        let regrouped = "package shop;\n\nimport java.util.List;\nimport shop.model.Order;\n\nimport java.util.Map;\n";
+        assert_ne!(
+            sort_imports.normalize(Language::Java, regrouped),
+            sort_imports.normalize(Language::Java, "package shop;\n\nimport java.util.List;\nimport java.util.Map;\n\nimport shop.model.Order;\n")
+        );
+
+        let go = Normalizer::new(&config("go", &[NormalizationRule::SortImports])).unwrap();
+        assert_eq!(
+            go.normalize(
+                Language::Go,
+                "package main\n\nimport (\n\t\"os\"\n\tlog \"github.com/sirupsen/logrus\"\n\t\"fmt\"\n)\n"
+            ),
+            "package main\n\nimport (\n\t\"fmt\"\n\tlog \"github.com/sirupsen/logrus\"\n\t\"os\"\n)\n"
+        );
+
+        let rust = Normalizer::new(&config("rs", &[NormalizationRule::SortImports])).unwrap();
+        assert_eq!(
+            rust.normalize(
+                Language::Rust,
+                "use std::fmt;\npub use crate::b::B;\nuse crate::a::A;\n"
+            ),
+            "use crate::a::A;\npub use crate::b::B;\nuse std::fmt;\n"
+        );
+
+        let error = Normalizer::new(&config("python", &[NormalizationRule::SortImports]))
+            .expect_err("Python import order has side effects");
+        assert!(matches!(
+            &error,
+            crate::parser::ParseError::InvalidNormalizationRule { language, .. } if language == "python"
+        ));
+        assert!(Normalizer::new(&config("cobol", &[])).is_err());
+    }
+
+    #[test]
+    fn test_dockerfile_stages_and_instructions() {
+        use crate::ast::ASTNode;
+        use crate::language::LanguageDetector;
+
+        for name in [
+            "Dockerfile",
+            "docker/Dockerfile.dev",
+            "Containerfile",
+            "app.dockerfile",
+        ] {
+            assert_eq!(
+                LanguageDetector::detect_from_path(name),
+                Language::Dockerfile,
+                "{}",
+                name
+            );
+        }
+
+        let dockerfile = "# syntax=docker/dockerfile:1\n\
+            ARG BASE=alpine:3.19\n\
+            FROM --platform=$BUILDPLATFORM ${BASE} AS build\n\
+            RUN apk add --no-cache \\\n    # compilers\n    gcc \\\n    musl-dev\n\
+            RUN <<EOF\nset -e\nmake\nEOF\n\
+            \n\
+            FROM scratch\n\
+            COPY --from=build /out/app /app\n";
+        let parser = TreeSitterParser::new().unwrap();
+        let ast = parser.parse(dockerfile, Language::Dockerfile).unwrap().ast;
+
+        let kind = |node: &ASTNode| node.metadata.attributes["kind"].clone();
+        let root: Vec<String> = ast.children.iter().map(kind).collect();
+        assert_eq!(root, ["arg_instruction", "build_stage", "build_stage"]);
+
+        let build = &ast.children[1];
+        assert_eq!(build.metadata.attributes["name"], "build");
+        assert_eq!(build.metadata.attributes["base_image"], "${BASE}");
+        let instructions: Vec<String> = build.children.iter().map(kind).collect();
+        assert_eq!(
+            instructions,
+            ["from_instruction", "run_instruction", "run_instruction"]
+        );
+        let install = &build.children[1].metadata;
+        assert_eq!(install.line, 4);
+        assert_eq!(
+            install.attributes["arguments"],
+            "apk add --no-cache gcc musl-dev"
+        );
+        assert_eq!(build.children[2].metadata.original_text.lines().count(), 4);
+
+        let scratch = &ast.children[2];
+        assert_eq!(scratch.metadata.attributes["name"], "stage 1");
+        assert_eq!(scratch.metadata.line, 13);
+    }
+
+    #[test]
+    fn test_notebook_code_cells_keep_their_lines() {
+        use crate::notebook::{extract_code, NotebookCell};
+
+        let notebook = serde_json::json!({
+            "nbformat": 4,
+            "metadata": {"kernelspec": {"language": "python", "name": "python3"}},
+            "cells": [
+                {"cell_type": "markdown", "metadata": {}, "source": ["# Load\n"]},
+                {
+                    "cell_type": "code",
+                    "execution_count": 3,
+                    "metadata": {},
+                    "outputs": [{"output_type": "stream", "text": ["ok\n"]}],
+                    "source": ["%matplotlib inline\n", "import csv\n", "!pip install pandas"]
+                },
+                {"cell_type": "code", "metadata": {}, "outputs": [], "source": ""},
+                {
+                    "cell_type": "code",
+                    "metadata": {},
+                    "outputs": [],
+                    "source": "%%bash\nls data"
+                },
+                {
+                    "cell_type": "code",
+                    "metadata": {},
+                    "outputs": [],
+                    "source": "def load(path):\n    return open(path).read()\n"
+                }
+            ]
+        })
+        .to_string();
+
+        let code = extract_code(&notebook).unwrap();
+        assert_eq!(code.language, Language::Python);
+        assert_eq!(
+            code.source,
+            "# %matplotlib inline\nimport csv\n# !pip install pandas\n\n\
+             # %%bash\n# ls data\n\n\
+             def load(path):\n    return open(path).read()\n\n"
+        );
+        assert_eq!(
+            code.cells,
+            [
+                NotebookCell {
+                    index: 2,
+                    start_line: 1,
+                    end_line: 3
+                },
+                NotebookCell {
+                    index: 4,
+                    start_line: 5,
+                    end_line: 6
+                },
+                NotebookCell {
+                    index: 5,
+                    start_line: 8,
+                    end_line: 9
+                },
+            ]
+        );
+        assert_eq!(code.cell_at(9).map(|cell| cell.index), Some(5));
+        assert_eq!(code.cell_at(4), None);
+
+        let parser = TreeSitterParser::new().unwrap();
+        assert!(parser.parse(&code.source, code.language).is_ok());
+        assert!(extract_code("{\"cells\": 3}").is_err());
+    }
 }
@@ -1,13 +1,15 @@
 //! Tree-sitter integration for multi-language parsing
 
-use crate::ast::{ASTNode, NodeMetadata, NodeType};
-use crate::ast_builder::{ASTBuilder, ASTBuilderConfig};
+use crate::ast::{char_column, normalize_lone_carriage_returns, ASTNode, NodeMetadata, NodeType};
+use crate::ast_builder::{ASTBuilder, ASTBuilderConfig, AnonymousFunctionPolicy};
 use crate::ast_processor::ASTProcessor;
+use crate::extraction_rules::ExtractionRules;
 use crate::language::Language;
 use crate::parser::{ParseError, ParseResult, Parser};
+use crate::parser_pool::ParserPool;
 use once_cell::sync::Lazy;
-use std::cell::RefCell;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 // Helper function to convert Swift's LanguageFn to tree_sitter::Language
 fn swift_language() -> tree_sitter::Language {
@@ -17,12 +19,25 @@ fn swift_language() -> tree_sitter::Language {
     }
 }
 
+// Markdown's block grammar ships as a LanguageFn like Swift's
+fn markdown_language() -> tree_sitter::Language {
+    unsafe {
+        let raw_fn = tree_sitter_md::LANGUAGE.into_raw();
+        tree_sitter::Language::from_raw(raw_fn() as *const tree_sitter::ffi::TSLanguage)
+    }
+}
+
 /// Tree-sitter based parser implementation
+///
+/// Tree-sitter parsers are checked out of a [`ParserPool`] for each parse, the
+/// global one unless the builder is given another, so creating a
+/// `TreeSitterParser` is cheap and it can be shared between threads.
 pub struct TreeSitterParser {
-    parsers: HashMap<Language, RefCell<tree_sitter::Parser>>,
+    pool: Arc<ParserPool>,
     builder_config: ASTBuilderConfig,
     enable_optimization: bool,
     enable_analysis: bool,
+    extraction_rules: Option<Arc<ExtractionRules>>,
 }
 
 /// Global tree-sitter language configurations
@@ -65,6 +80,10 @@ static TREE_SITTER_CONFIGS: Lazy<HashMap<Language, fn() -> tree_sitter::Language
             Language::Swift,
             swift_language as fn() -> tree_sitter::Language,
         );
+        configs.insert(
+            Language::Markdown,
+            markdown_language as fn() -> tree_sitter::Language,
+        );
         configs
     });
 
@@ -74,23 +93,12 @@ impl TreeSitterParser {
     }
 
     pub fn with_config(builder_config: ASTBuilderConfig) -> Result<Self, ParseError> {
-        let mut parsers = HashMap::new();
-
-        // Initialize parsers for supported languages
-        for (&language, language_fn) in TREE_SITTER_CONFIGS.iter() {
-            let mut parser = tree_sitter::Parser::new();
-            let lang = language_fn();
-            parser.set_language(&lang).map_err(|e| {
-                ParseError::TreeSitterError(format!("Failed to set language {:?}: {}", language, e))
-            })?;
-            parsers.insert(language, RefCell::new(parser));
-        }
-
         Ok(Self {
-            parsers,
+            pool: ParserPool::global(),
             builder_config,
             enable_optimization: true,
             enable_analysis: true,
+            extraction_rules: None,
         })
     }
 
@@ -109,9 +117,24 @@ impl TreeSitterParser {
         self.enable_analysis = enabled;
     }
 
-    /// Get available languages
+    /// Capture extra elements with user-defined query rules on every parse
+    pub fn set_extraction_rules(&mut self, rules: Arc<ExtractionRules>) {
+        self.extraction_rules = Some(rules);
+    }
+
+    /// Get available languages: those with a grammar, plus Dockerfiles,
+    /// which are parsed line by line
     pub fn supported_languages() -> Vec<Language> {
-        TREE_SITTER_CONFIGS.keys().cloned().collect()
+        let mut languages: Vec<Language> = TREE_SITTER_CONFIGS.keys().cloned().collect();
+        languages.push(Language::Dockerfile);
+        languages
+    }
+
+    /// Tree-sitter grammar of a supported language
+    pub(crate) fn grammar(language: Language) -> Option<tree_sitter::Language> {
+        TREE_SITTER_CONFIGS
+            .get(&language)
+            .map(|language_fn| language_fn())
     }
 
     #[allow(dead_code)]
@@ -134,7 +157,7 @@ impl TreeSitterParser {
 
         let metadata = NodeMetadata {
             line: node.start_position().row + 1, // Convert to 1-based line numbers
-            column: node.start_position().column + 1, // Convert to 1-based column numbers
+            column: char_column(source, node.start_byte()), // 1-based, in characters
             // Store full original text for function nodes, even if long
             original_text: text.to_string(),
             attributes,
@@ -177,7 +200,7 @@ impl TreeSitterParser {
             errors.push(format!(
                 "Parse error at line {}, column {}: {}",
                 node.start_position().row + 1,
-                node.start_position().column + 1,
+                char_column(source, node.start_byte()),
                 text
             ));
         }
@@ -186,7 +209,7 @@ impl TreeSitterParser {
             errors.push(format!(
                 "Missing node at line {}, column {}",
                 node.start_position().row + 1,
-                node.start_position().column + 1
+                char_column(source, node.start_byte())
             ));
         }
 
@@ -287,14 +310,24 @@ impl TreeSitterParser {
 
 impl Parser for TreeSitterParser {
     fn parse(&self, content: &str, language: Language) -> Result<ParseResult, ParseError> {
-        let parser_cell = self
-            .parsers
-            .get(&language)
-            .ok_or(ParseError::UnsupportedLanguage(language))?;
+        // Count lone `\r` line endings as line breaks, like `source_lines` does
+        let content = normalize_lone_carriage_returns(content);
+        let content = content.as_ref();
+
+        if language == Language::Dockerfile {
+            return Ok(ParseResult {
+                ast: crate::dockerfile::parse(content),
+                language,
+                errors: Vec::new(),
+                warnings: Vec::new(),
+                custom_elements: Vec::new(),
+            });
+        }
 
-        // Parse the content
-        let tree = parser_cell
-            .borrow_mut()
+        // Parse the content, holding a pooled parser only as long as needed
+        let tree = self
+            .pool
+            .checkout(language)?
             .parse(content, None)
             .ok_or_else(|| ParseError::ParseFailed("Failed to parse content".to_string()))?;
 
@@ -302,7 +335,7 @@ impl Parser for TreeSitterParser {
 
         // Build AST using the enhanced AST builder
         let mut ast_builder = ASTBuilder::new(language, self.builder_config.clone());
-        let mut ast = ast_builder.build_ast(&tree, content);
+        let mut ast = ast_builder.build_ast(&tree, content)?;
 
         // Optimize AST if enabled
         if self.enable_optimization {
@@ -335,11 +368,17 @@ impl Parser for TreeSitterParser {
             }
         }
 
+        let custom_elements = match &self.extraction_rules {
+            Some(rules) => rules.extract(language, &tree, content, &self.builder_config),
+            None => Vec::new(),
+        };
+
         Ok(ParseResult {
             ast,
             language,
             errors,
             warnings,
+            custom_elements,
         })
     }
 
@@ -359,6 +398,8 @@ pub struct TreeSitterParserBuilder {
     builder_config: ASTBuilderConfig,
     enable_optimization: bool,
     enable_analysis: bool,
+    extraction_rules: Option<Arc<ExtractionRules>>,
+    pool: Option<Arc<ParserPool>>,
 }
 
 impl TreeSitterParserBuilder {
@@ -367,6 +408,8 @@ impl TreeSitterParserBuilder {
             builder_config: ASTBuilderConfig::default(),
             enable_optimization: true,
             enable_analysis: true,
+            extraction_rules: None,
+            pool: None,
         }
     }
 
@@ -395,6 +438,16 @@ impl TreeSitterParserBuilder {
         self
     }
 
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.builder_config.max_depth = depth;
+        self
+    }
+
+    pub fn anonymous_functions(mut self, policy: AnonymousFunctionPolicy) -> Self {
+        self.builder_config.anonymous_functions = policy;
+        self
+    }
+
     pub fn enable_optimization(mut self, enable: bool) -> Self {
         self.enable_optimization = enable;
         self
@@ -405,10 +458,25 @@ impl TreeSitterParserBuilder {
         self
     }
 
+    pub fn extraction_rules(mut self, rules: Arc<ExtractionRules>) -> Self {
+        self.extraction_rules = Some(rules);
+        self
+    }
+
+    /// Check parsers out of `pool` instead of the global pool
+    pub fn parser_pool(mut self, pool: Arc<ParserPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
     pub fn build(self) -> Result<TreeSitterParser, ParseError> {
         let mut parser = TreeSitterParser::with_config(self.builder_config)?;
         parser.enable_optimization = self.enable_optimization;
         parser.enable_analysis = self.enable_analysis;
+        parser.extraction_rules = self.extraction_rules;
+        if let Some(pool) = self.pool {
+            parser.pool = pool;
+        }
         Ok(parser)
     }
 }
@@ -146,3 +146,36 @@ fn test_swift_file_extension() {
     assert_eq!(LanguageDetector::detect_from_path("test.swift"), Language::Swift);
 }
 
+
+#[test]
+fn test_markdown_file_extensions() {
+    assert_eq!(LanguageDetector::detect_from_path("README.md"), Language::Markdown);
+    assert_eq!(LanguageDetector::detect_from_path("guide.markdown"), Language::Markdown);
+}
+
+#[test]
+fn test_markdown_headings() {
+    let markdown = "# Guide\n\n## Install\n\n```sh\n# not a heading\n```\n\nUsage\n-----\n";
+    let parser = TreeSitterParser::new().expect("Failed to create parser");
+    let parse_result = parser
+        .parse(markdown, Language::Markdown)
+        .expect("Failed to parse Markdown");
+
+    let headings: Vec<(String, String)> = parse_result
+        .ast
+        .find_by_type(&smart_diff_parser::NodeType::Heading)
+        .into_iter()
+        .map(|heading| {
+            let attributes = &heading.metadata.attributes;
+            (attributes["heading_level"].clone(), attributes["name"].clone())
+        })
+        .collect();
+    assert_eq!(
+        headings,
+        vec![
+            ("1".to_string(), "Guide".to_string()),
+            ("2".to_string(), "Install".to_string()),
+            ("2".to_string(), "Usage".to_string()),
+        ]
+    );
+}
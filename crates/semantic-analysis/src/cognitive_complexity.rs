@@ -0,0 +1,248 @@
+//! Cognitive complexity of a function
+//!
+//! Cyclomatic complexity counts paths through a function, so a chain of flat
+//! conditions costs as much as the same conditions nested inside each other.
+//! Cognitive complexity, as defined by SonarSource, measures how hard the
+//! code is to follow instead:
+//!
+//! - each break in the linear flow costs 1: a condition, loop, switch,
+//!   `catch`, conditional expression, `else`, jump to a label, recursive call
+//!   or sequence of like boolean operators;
+//! - conditions, loops, switches, `catch` clauses and conditional expressions
+//!   also cost their nesting level, so nested logic costs more the deeper it
+//!   sits;
+//! - nested functions and lambdas add a level of nesting but cost nothing
+//!   themselves.
+//!
+//! Node kinds of every supported grammar are recognized. In Python
+//! comprehensions each `for` clause counts as a loop and each `if` clause as
+//! a condition; guards on match arms count as conditions.
+
+use smart_diff_parser::{ASTNode, NodeType};
+
+/// Conditional statements, also `else if` when nested in an else clause
+const IF_KINDS: &[&str] = &["if_statement", "if_expression", "if_let_expression"];
+
+const LOOP_KINDS: &[&str] = &[
+    "for_statement",
+    "for_in_statement",
+    "for_of_statement",
+    "enhanced_for_statement",
+    "foreach_statement",
+    "for_range_loop",
+    "while_statement",
+    "do_statement",
+    "for_expression",
+    "while_expression",
+    "while_let_expression",
+    "loop_expression",
+    "for_in_clause",
+];
+
+const SWITCH_KINDS: &[&str] = &[
+    "switch_statement",
+    "switch_expression",
+    "expression_switch_statement",
+    "type_switch_statement",
+    "select_statement",
+    "match_statement",
+    "match_expression",
+    "when_expression",
+];
+
+const CATCH_KINDS: &[&str] = &["catch_clause", "except_clause", "rescue"];
+
+const CONDITIONAL_EXPRESSION_KINDS: &[&str] = &["conditional_expression", "ternary_expression"];
+
+/// Conditions that add no nesting: comprehension filters and match guards
+const GUARD_KINDS: &[&str] = &["if_clause", "match_guard"];
+
+const JUMP_KINDS: &[&str] = &[
+    "break_statement",
+    "continue_statement",
+    "break_expression",
+    "continue_expression",
+];
+
+/// Labels that make a `break` or `continue` jump somewhere other than the
+/// innermost loop
+const LABEL_KINDS: &[&str] = &["label_name", "statement_identifier", "label", "lifetime"];
+
+const CALL_KINDS: &[&str] = &[
+    "call",
+    "call_expression",
+    "function_call",
+    "method_invocation",
+];
+
+const NESTED_FUNCTION_KINDS: &[&str] = &[
+    "lambda",
+    "lambda_expression",
+    "arrow_function",
+    "function_expression",
+    "anonymous_function",
+    "closure_expression",
+    "func_literal",
+];
+
+/// Cognitive complexity of a function, or of any other node as a function body
+pub fn cognitive_complexity(function: &ASTNode) -> usize {
+    let mut counter = Counter {
+        name: function.metadata.attributes.get("name").map(String::as_str),
+        total: 0,
+    };
+    for child in &function.children {
+        counter.visit(child, 0, None);
+    }
+    counter.total
+}
+
+struct Counter<'a> {
+    /// Name of the function, to count recursive calls
+    name: Option<&'a str>,
+    total: usize,
+}
+
+impl Counter<'_> {
+    /// Count `node` at `nesting`, `operator` being the boolean operator of
+    /// the expression it is an operand of
+    fn visit(&mut self, node: &ASTNode, nesting: usize, operator: Option<&str>) {
+        let kind = kind(node);
+
+        if IF_KINDS.contains(&kind) {
+            self.visit_if(node, nesting, false);
+            return;
+        }
+
+        let nests = if LOOP_KINDS.contains(&kind)
+            || SWITCH_KINDS.contains(&kind)
+            || CATCH_KINDS.contains(&kind)
+            || CONDITIONAL_EXPRESSION_KINDS.contains(&kind)
+            || matches!(
+                node.node_type,
+                NodeType::ForLoop
+                    | NodeType::WhileLoop
+                    | NodeType::DoWhileLoop
+                    | NodeType::SwitchStatement
+            ) {
+            self.total += 1 + nesting;
+            true
+        } else {
+            // Guards, jumps to a label and recursion break the flow without nesting
+            if GUARD_KINDS.contains(&kind)
+                || is_match_guard(node)
+                || (JUMP_KINDS.contains(&kind) && has_label(node))
+                || kind == "goto_statement"
+                || (CALL_KINDS.contains(&kind) && self.is_recursive(node))
+            {
+                self.total += 1;
+            }
+            NESTED_FUNCTION_KINDS.contains(&kind)
+                || matches!(
+                    node.node_type,
+                    NodeType::Function | NodeType::Method | NodeType::Constructor
+                )
+        };
+
+        // A run of the same operator is one sequence, `a && b || c` is two
+        let own_operator = boolean_operator(node);
+        if let Some(own) = own_operator {
+            if operator != Some(own) {
+                self.total += 1;
+            }
+        }
+
+        let nesting = if nests { nesting + 1 } else { nesting };
+        for child in &node.children {
+            self.visit(child, nesting, own_operator);
+        }
+    }
+
+    /// Count an `if` and its `else if` and `else` branches
+    ///
+    /// Only the first `if` of a chain costs its nesting; the branches of the
+    /// chain all sit one level deeper than the chain.
+    fn visit_if(&mut self, node: &ASTNode, nesting: usize, else_if: bool) {
+        self.total += if else_if { 1 } else { 1 + nesting };
+
+        // Java, Go and C# put the alternative right after an `else` keyword
+        let mut after_else = false;
+        for child in &node.children {
+            match kind(child) {
+                "else" => after_else = true,
+                "else_clause" => self.visit_else(child.children.iter(), nesting),
+                "elif_clause" => {
+                    self.total += 1;
+                    for grandchild in &child.children {
+                        self.visit(grandchild, nesting + 1, None);
+                    }
+                }
+                _ if after_else => {
+                    after_else = false;
+                    self.visit_else(std::iter::once(child), nesting);
+                }
+                _ => self.visit(child, nesting + 1, None),
+            }
+        }
+    }
+
+    /// Count the `else` branch of an `if` at `nesting`
+    fn visit_else<'n>(&mut self, branch: impl Iterator<Item = &'n ASTNode>, nesting: usize) {
+        let branch: Vec<_> = branch.collect();
+        match branch.iter().find(|node| IF_KINDS.contains(&kind(node))) {
+            Some(else_if) => self.visit_if(else_if, nesting, true),
+            None => {
+                self.total += 1;
+                for node in branch {
+                    self.visit(node, nesting + 1, None);
+                }
+            }
+        }
+    }
+
+    fn is_recursive(&self, call: &ASTNode) -> bool {
+        let attributes = &call.metadata.attributes;
+        // Java names the method of an invocation, other grammars the called expression
+        let callee = attributes
+            .get("function_name")
+            .or_else(|| attributes.get("name"));
+        let (Some(name), Some(callee)) = (self.name, callee) else {
+            return false;
+        };
+        let callee = callee
+            .strip_prefix("self.")
+            .or_else(|| callee.strip_prefix("this."))
+            .unwrap_or(callee);
+        callee == name
+    }
+}
+
+fn kind(node: &ASTNode) -> &str {
+    node.metadata
+        .attributes
+        .get("kind")
+        .map_or("", String::as_str)
+}
+
+/// `&&`/`and` or `||`/`or` of a boolean expression
+fn boolean_operator(node: &ASTNode) -> Option<&str> {
+    if !matches!(kind(node), "boolean_operator" | "binary_expression") {
+        return None;
+    }
+    node.children.iter().find_map(|child| match kind(child) {
+        "&&" | "and" => Some("&&"),
+        "||" | "or" => Some("||"),
+        _ => None,
+    })
+}
+
+/// A Rust match arm pattern with an `if` guard
+fn is_match_guard(node: &ASTNode) -> bool {
+    kind(node) == "match_pattern" && node.children.iter().any(|child| kind(child) == "if")
+}
+
+fn has_label(jump: &ASTNode) -> bool {
+    jump.children
+        .iter()
+        .any(|child| LABEL_KINDS.contains(&kind(child)))
+}
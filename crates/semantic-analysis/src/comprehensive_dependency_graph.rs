@@ -147,6 +147,23 @@ pub enum ExportType {
     Module,
 }
 
+/// A function reached from another through calls
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallNeighbor {
+    pub qualified_name: String,
+    pub file_path: String,
+    pub line: usize,
+    /// Calls between the two functions; 1 for direct callers and callees
+    pub distance: usize,
+}
+
+/// Callers and callees of a function, nearest first
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallNeighborhood {
+    pub callers: Vec<CallNeighbor>,
+    pub callees: Vec<CallNeighbor>,
+}
+
 /// Comprehensive dependency analysis result
 #[derive(Debug)]
 pub struct ComprehensiveDependencyAnalysis {
@@ -1068,6 +1085,97 @@ impl ComprehensiveDependencyGraphBuilder {
         hotspots
     }
 
+    /// Call sites of each function (its fan-in), keyed by function name
+    ///
+    /// Same-named functions in different scopes or files are summed, since call
+    /// sites only record the callee's name.
+    pub fn function_fan_in(&self) -> HashMap<String, usize> {
+        let mut fan_in = HashMap::new();
+        let mut counted = HashSet::new();
+        for function in self.file_contexts.values().flat_map(|c| &c.functions) {
+            if !counted.insert(&function.qualified_name) {
+                continue;
+            }
+            let calls = self
+                .dependency_graph
+                .incoming_edge_count(&function.qualified_name, &DependencyEdgeType::Calls);
+            *fan_in.entry(function.name.clone()).or_insert(0) += calls;
+        }
+        fan_in
+    }
+
+    /// Functions calling `function` and functions it calls, up to `depth` calls away
+    ///
+    /// `function` is a name or qualified name. When some function of that name
+    /// lies in `file_path`, same-named functions in other files are left out.
+    /// Calls that could not be resolved to a function leave the neighborhood
+    /// empty rather than failing.
+    pub fn call_neighborhood(
+        &self,
+        function: &str,
+        file_path: Option<&str>,
+        depth: usize,
+    ) -> CallNeighborhood {
+        let candidates: Vec<(&str, &FunctionInfo)> = self
+            .file_contexts
+            .values()
+            .flat_map(|context| {
+                context
+                    .functions
+                    .iter()
+                    .map(move |info| (context.file_path.as_str(), info))
+            })
+            .filter(|(_, info)| info.name == function || info.qualified_name == function)
+            .collect();
+        let in_file = candidates.iter().any(|(path, _)| Some(*path) == file_path);
+        let starts: Vec<String> = candidates
+            .into_iter()
+            .filter(|(path, _)| !in_file || Some(*path) == file_path)
+            .map(|(_, info)| info.qualified_name.clone())
+            .collect();
+
+        CallNeighborhood {
+            callers: self.call_neighbors(&starts, depth, true),
+            callees: self.call_neighbors(&starts, depth, false),
+        }
+    }
+
+    /// Breadth-first walk of call edges, towards callers or callees
+    fn call_neighbors(&self, starts: &[String], depth: usize, callers: bool) -> Vec<CallNeighbor> {
+        let mut seen: HashSet<String> = starts.iter().cloned().collect();
+        let mut frontier = starts.to_vec();
+        let mut neighbors = Vec::new();
+
+        for distance in 1..=depth {
+            let mut next = Vec::new();
+            for id in &frontier {
+                let nodes = if callers {
+                    self.dependency_graph
+                        .dependents_of_type(id, &DependencyEdgeType::Calls)
+                } else {
+                    self.dependency_graph
+                        .dependencies_of_type(id, &DependencyEdgeType::Calls)
+                };
+                for node in nodes {
+                    if seen.insert(node.id.clone()) {
+                        next.push(node.id.clone());
+                        neighbors.push(CallNeighbor {
+                            qualified_name: node.id.clone(),
+                            file_path: node.file_path.clone(),
+                            line: node.line,
+                            distance,
+                        });
+                    }
+                }
+            }
+            frontier = next;
+        }
+
+        neighbors
+            .sort_by(|a, b| (a.distance, &a.qualified_name).cmp(&(b.distance, &b.qualified_name)));
+        neighbors
+    }
+
     /// Get the underlying dependency graph
     pub fn get_dependency_graph(&self) -> &DependencyGraph {
         &self.dependency_graph
@@ -1,9 +1,11 @@
 //! Dependency graph construction and analysis
 
 use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
 use petgraph::{Directed, Graph};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
 
 /// Dependency graph representing relationships between code elements
 #[derive(Debug, Clone)]
@@ -50,6 +52,15 @@ pub enum DependencyEdgeType {
     Contains,
 }
 
+/// Options for rendering a graph with [`DependencyGraph::to_dot`]
+#[derive(Debug, Clone, Default)]
+pub struct DotOptions {
+    /// Render only the nodes within `hops` edges of this node, matched by id or name
+    pub focus: Option<String>,
+    /// Edges followed from `focus`, in either direction
+    pub hops: usize,
+}
+
 impl Default for DependencyGraph {
     fn default() -> Self {
         Self::new()
@@ -108,6 +119,59 @@ impl DependencyGraph {
         }
     }
 
+    /// Nodes this node depends on through edges of one type, e.g. the functions it calls
+    pub fn dependencies_of_type(
+        &self,
+        node_id: &str,
+        edge_type: &DependencyEdgeType,
+    ) -> Vec<&DependencyNode> {
+        self.neighbors_of_type(node_id, edge_type, petgraph::Direction::Outgoing)
+    }
+
+    /// Nodes depending on this node through edges of one type, e.g. its callers
+    pub fn dependents_of_type(
+        &self,
+        node_id: &str,
+        edge_type: &DependencyEdgeType,
+    ) -> Vec<&DependencyNode> {
+        self.neighbors_of_type(node_id, edge_type, petgraph::Direction::Incoming)
+    }
+
+    fn neighbors_of_type(
+        &self,
+        node_id: &str,
+        edge_type: &DependencyEdgeType,
+        direction: petgraph::Direction,
+    ) -> Vec<&DependencyNode> {
+        let Some(&node_index) = self.node_map.get(node_id) else {
+            return Vec::new();
+        };
+        let mut seen = std::collections::HashSet::new();
+        self.graph
+            .edges_directed(node_index, direction)
+            .filter(|edge| &edge.weight().edge_type == edge_type)
+            .map(|edge| match direction {
+                petgraph::Direction::Outgoing => edge.target(),
+                petgraph::Direction::Incoming => edge.source(),
+            })
+            .filter(|&index| seen.insert(index))
+            .map(|index| &self.graph[index])
+            .collect()
+    }
+
+    /// Number of incoming edges of one type, e.g. the call sites of a function
+    pub fn incoming_edge_count(&self, node_id: &str, edge_type: &DependencyEdgeType) -> usize {
+        self.node_map
+            .get(node_id)
+            .map(|&node_index| {
+                self.graph
+                    .edges_directed(node_index, petgraph::Direction::Incoming)
+                    .filter(|edge| &edge.weight().edge_type == edge_type)
+                    .count()
+            })
+            .unwrap_or(0)
+    }
+
     /// Find strongly connected components
     pub fn find_cycles(&self) -> Vec<Vec<String>> {
         use petgraph::algo::kosaraju_scc;
@@ -158,6 +222,126 @@ impl DependencyGraph {
     pub fn get_node(&self, node_index: NodeIndex) -> Option<&DependencyNode> {
         self.graph.node_weight(node_index)
     }
+
+    /// Render the graph in GraphViz DOT
+    ///
+    /// Nodes are grouped into a cluster per file and shaped by node type;
+    /// edges are styled by edge type and drawn thicker the stronger they are.
+    /// Fails when `options.focus` matches no node.
+    pub fn to_dot(&self, options: &DotOptions) -> crate::Result<String> {
+        let included = match &options.focus {
+            Some(focus) => self.neighborhood(focus, options.hops)?,
+            None => self.graph.node_indices().collect(),
+        };
+
+        let mut files: BTreeMap<&str, Vec<NodeIndex>> = BTreeMap::new();
+        for index in self.graph.node_indices().filter(|i| included.contains(i)) {
+            files
+                .entry(self.graph[index].file_path.as_str())
+                .or_default()
+                .push(index);
+        }
+
+        let mut dot = String::from("digraph dependencies {\n");
+        dot.push_str("    rankdir=LR;\n");
+        dot.push_str("    node [fontname=\"Helvetica\", style=filled];\n");
+        dot.push_str("    edge [fontname=\"Helvetica\", fontsize=10];\n");
+        for (cluster, (file, nodes)) in files.iter().enumerate() {
+            let indent = if file.is_empty() {
+                "    "
+            } else {
+                let _ = writeln!(dot, "    subgraph cluster_{} {{", cluster);
+                let _ = writeln!(dot, "        label={};", dot_string(file));
+                "        "
+            };
+            for &index in nodes {
+                let node = &self.graph[index];
+                let (shape, fill) = match node.node_type {
+                    DependencyNodeType::Function => ("box", "#dbe9f6"),
+                    DependencyNodeType::Class => ("box3d", "#fde5c4"),
+                    DependencyNodeType::Module => ("tab", "#e3d7f0"),
+                    DependencyNodeType::Variable => ("ellipse", "#e8f3dc"),
+                    DependencyNodeType::File => ("note", "#eeeeee"),
+                };
+                let focused = options
+                    .focus
+                    .as_deref()
+                    .is_some_and(|focus| node.id == focus || node.name == focus);
+                let _ = writeln!(
+                    dot,
+                    "{}{} [label={}, shape={}, fillcolor=\"{}\"{}];",
+                    indent,
+                    dot_string(&node.id),
+                    dot_string(&node.name),
+                    shape,
+                    fill,
+                    if focused { ", penwidth=3" } else { "" }
+                );
+            }
+            if !file.is_empty() {
+                dot.push_str("    }\n");
+            }
+        }
+
+        for edge in self
+            .graph
+            .edge_references()
+            .filter(|edge| included.contains(&edge.source()) && included.contains(&edge.target()))
+        {
+            let weight = edge.weight();
+            let (label, style, color) = match weight.edge_type {
+                DependencyEdgeType::Calls => ("calls", "solid", "#1f5fa8"),
+                DependencyEdgeType::Inherits => ("inherits", "bold", "#b35900"),
+                DependencyEdgeType::Implements => ("implements", "dashed", "#b35900"),
+                DependencyEdgeType::Uses => ("uses", "dotted", "#555555"),
+                DependencyEdgeType::Imports => ("imports", "dashed", "#6a3d9a"),
+                DependencyEdgeType::Contains => ("contains", "solid", "#999999"),
+            };
+            let _ = writeln!(
+                dot,
+                "    {} -> {} [label=\"{}\", style={}, color=\"{}\", penwidth={:.1}];",
+                dot_string(&self.graph[edge.source()].id),
+                dot_string(&self.graph[edge.target()].id),
+                label,
+                style,
+                color,
+                1.0 + 2.0 * weight.strength.clamp(0.0, 1.0)
+            );
+        }
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+
+    /// Nodes within `hops` edges of the nodes matching `focus`, following edges both ways
+    fn neighborhood(&self, focus: &str, hops: usize) -> crate::Result<HashSet<NodeIndex>> {
+        let mut included: HashSet<NodeIndex> = self
+            .graph
+            .node_indices()
+            .filter(|&index| self.graph[index].id == focus || self.graph[index].name == focus)
+            .collect();
+        if included.is_empty() {
+            return Err(crate::AnalysisError::SymbolNotFound(focus.to_string()));
+        }
+
+        let mut frontier: Vec<NodeIndex> = included.iter().copied().collect();
+        for _ in 0..hops {
+            let mut next = Vec::new();
+            for index in frontier {
+                for neighbor in self.graph.neighbors_undirected(index) {
+                    if included.insert(neighbor) {
+                        next.push(neighbor);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        Ok(included)
+    }
+}
+
+/// Quote `text` as a DOT string
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
 }
 
 /// Coupling metrics for a node
@@ -1,5 +1,6 @@
 //! Comprehensive function signature extraction and analysis
 
+use crate::cognitive_complexity::cognitive_complexity;
 use crate::{TypeEquivalence, TypeSignature, Visibility};
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
@@ -127,6 +128,8 @@ pub enum FunctionType {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FunctionComplexityMetrics {
     pub cyclomatic_complexity: usize,
+    /// How hard the function is to follow, with nested logic costing more;
+    /// see [`cognitive_complexity`](crate::cognitive_complexity)
     pub cognitive_complexity: usize,
     pub lines_of_code: usize,
     pub parameter_count: usize,
@@ -290,6 +293,10 @@ impl FunctionSignatureExtractor {
         scope_path: Vec<String>,
     ) -> Result<()> {
         match node.node_type {
+            // C/C++ declarators are only the signature part of a definition
+            NodeType::Function
+                if node.metadata.attributes.get("kind").map(String::as_str)
+                    == Some("function_declarator") => {}
             NodeType::Function | NodeType::Method | NodeType::Constructor => {
                 if let Some(signature) = self.extract_function_signature(node, &scope_path)? {
                     if self.should_include_function(&signature) {
@@ -421,6 +428,33 @@ impl FunctionSignatureExtractor {
             }
         }
 
+        // Grammars without a parameter list node record the declared types on the function
+        if parameters.is_empty() {
+            if let Some(types) = node.metadata.attributes.get("parameter_types") {
+                let names: Vec<&str> = node
+                    .metadata
+                    .attributes
+                    .get("parameter_names")
+                    .map(|names| names.split(';').collect())
+                    .unwrap_or_default();
+                for (position, type_str) in types.split(';').enumerate() {
+                    let name = match names.get(position) {
+                        Some(name) if !name.is_empty() => name.to_string(),
+                        _ => format!("param{}", position),
+                    };
+                    parameters.push(FunctionParameter {
+                        name,
+                        param_type: self.parse_type_signature(type_str)?,
+                        default_value: None,
+                        is_optional: false,
+                        is_varargs: false,
+                        annotations: Vec::new(),
+                        position,
+                    });
+                }
+            }
+        }
+
         Ok(parameters)
     }
 
@@ -536,11 +570,16 @@ impl FunctionSignatureExtractor {
 
     /// Extract visibility from function node
     fn extract_visibility(&self, node: &ASTNode) -> Visibility {
+        let modifiers = self.extract_modifiers(node);
+        let declared = ["public", "private", "protected", "internal"]
+            .into_iter()
+            .find(|visibility| modifiers.iter().any(|m| m == visibility));
         match node
             .metadata
             .attributes
             .get("visibility")
             .map(|s| s.as_str())
+            .or(declared)
         {
             Some("public") => Visibility::Public,
             Some("private") => Visibility::Private,
@@ -612,9 +651,11 @@ impl FunctionSignatureExtractor {
     }
 
     /// Calculate complexity metrics for a function
-    fn calculate_complexity_metrics(&self, node: &ASTNode) -> Result<FunctionComplexityMetrics> {
+    pub fn calculate_complexity_metrics(
+        &self,
+        node: &ASTNode,
+    ) -> Result<FunctionComplexityMetrics> {
         let mut cyclomatic_complexity = 1; // Base complexity
-        let mut cognitive_complexity = 0;
         let mut nesting_depth = 0;
         let mut max_nesting_depth = 0;
         let mut branch_count = 0;
@@ -625,7 +666,6 @@ impl FunctionSignatureExtractor {
         self.calculate_complexity_recursive(
             node,
             &mut cyclomatic_complexity,
-            &mut cognitive_complexity,
             &mut nesting_depth,
             &mut max_nesting_depth,
             &mut branch_count,
@@ -648,7 +688,7 @@ impl FunctionSignatureExtractor {
 
         Ok(FunctionComplexityMetrics {
             cyclomatic_complexity,
-            cognitive_complexity,
+            cognitive_complexity: cognitive_complexity(node),
             lines_of_code,
             parameter_count,
             nesting_depth: max_nesting_depth,
@@ -665,7 +705,6 @@ impl FunctionSignatureExtractor {
         &self,
         node: &ASTNode,
         cyclomatic: &mut usize,
-        cognitive: &mut usize,
         current_depth: &mut usize,
         max_depth: &mut usize,
         branches: &mut usize,
@@ -676,12 +715,10 @@ impl FunctionSignatureExtractor {
             // Control flow nodes increase complexity
             NodeType::IfStatement => {
                 *cyclomatic += 1;
-                *cognitive += 1 + *current_depth;
                 *branches += 1;
             }
             NodeType::WhileLoop | NodeType::ForLoop | NodeType::DoWhileLoop => {
                 *cyclomatic += 1;
-                *cognitive += 1 + *current_depth;
                 *loops += 1;
                 *current_depth += 1;
             }
@@ -694,11 +731,9 @@ impl FunctionSignatureExtractor {
                     .filter(|child| child.node_type == NodeType::CaseStatement)
                     .count();
                 *cyclomatic += case_count.max(1);
-                *cognitive += case_count + *current_depth;
             }
             NodeType::TryStatement => {
                 *cyclomatic += 1;
-                *cognitive += 1 + *current_depth;
                 *current_depth += 1;
             }
             NodeType::CallExpression => {
@@ -716,7 +751,6 @@ impl FunctionSignatureExtractor {
             self.calculate_complexity_recursive(
                 child,
                 cyclomatic,
-                cognitive,
                 current_depth,
                 max_depth,
                 branches,
@@ -0,0 +1,74 @@
+//! Import and dependency changes between two versions of a file
+//!
+//! Compares the imports extracted by the symbol resolver so reviewers can see
+//! when a change pulls in a new dependency or drops one. Imports are keyed by
+//! module, imported name and wildcard-ness; an import whose alias changed is
+//! reported as changed rather than as a removal plus an addition.
+
+use crate::symbol_resolver::{ImportInfo, SymbolResolver};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use smart_diff_parser::ParseResult;
+
+/// Imports added, removed or changed in a single file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportDelta {
+    pub added: Vec<ImportInfo>,
+    pub removed: Vec<ImportInfo>,
+    pub changed: Vec<ImportChange>,
+}
+
+/// The same import in both versions, with a different alias
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportChange {
+    pub source: ImportInfo,
+    pub target: ImportInfo,
+}
+
+impl ImportDelta {
+    /// Compare the imports of two versions of a file
+    pub fn between(source: &[ImportInfo], target: &[ImportInfo]) -> Self {
+        let mut delta = Self::default();
+        let mut unmatched: Vec<&ImportInfo> = target.iter().collect();
+
+        for import in source {
+            // An identical import first, so duplicates pair up one to one
+            if let Some(pos) = unmatched
+                .iter()
+                .position(|t| Self::same_import(import, t) && t.alias == import.alias)
+            {
+                unmatched.remove(pos);
+            } else if let Some(pos) = unmatched.iter().position(|t| Self::same_import(import, t)) {
+                delta.changed.push(ImportChange {
+                    source: import.clone(),
+                    target: unmatched.remove(pos).clone(),
+                });
+            } else {
+                delta.removed.push(import.clone());
+            }
+        }
+
+        delta.added = unmatched.into_iter().cloned().collect();
+        delta
+    }
+
+    /// Extract and compare the imports of two parsed versions of a file
+    pub fn from_parse_results(source: &ParseResult, target: &ParseResult) -> Result<Self> {
+        let mut resolver = SymbolResolver::with_defaults();
+        let source_imports = resolver.extract_file_imports("source", source)?;
+        let target_imports = resolver.extract_file_imports("target", target)?;
+
+        Ok(Self::between(&source_imports, &target_imports))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Whether two imports bring in the same thing, ignoring alias and position
+    fn same_import(a: &ImportInfo, b: &ImportInfo) -> bool {
+        a.imported_name == b.imported_name
+            && a.source_path == b.source_path
+            && a.is_wildcard == b.is_wildcard
+    }
+}
@@ -4,9 +4,11 @@
 //! and extracts type information from parsed ASTs.
 
 pub mod analyzer;
+pub mod cognitive_complexity;
 pub mod comprehensive_dependency_graph;
 pub mod dependency_graph;
 pub mod function_signature_extractor;
+pub mod import_delta;
 pub mod scope_manager;
 pub mod symbol_resolver;
 pub mod symbol_table;
@@ -15,13 +17,15 @@ pub mod type_extractor;
 pub mod type_system;
 
 pub use analyzer::{AnalysisError, AnalysisResult, SemanticAnalyzer};
+pub use cognitive_complexity::cognitive_complexity;
 pub use comprehensive_dependency_graph::{
-    CallType, ClassInfo, ComprehensiveCouplingMetrics, ComprehensiveDependencyAnalysis,
-    ComprehensiveDependencyGraphBuilder, DependencyAnalysisConfig, DependencyHotspot,
-    FileAnalysisContext, FunctionCallInfo, FunctionInfo, VariableInfo,
+    CallNeighbor, CallNeighborhood, CallType, ClassInfo, ComprehensiveCouplingMetrics,
+    ComprehensiveDependencyAnalysis, ComprehensiveDependencyGraphBuilder, DependencyAnalysisConfig,
+    DependencyHotspot, FileAnalysisContext, FunctionCallInfo, FunctionInfo, VariableInfo,
 };
 pub use dependency_graph::{
     DependencyEdge, DependencyEdgeType, DependencyGraph, DependencyNode, DependencyNodeType,
+    DotOptions,
 };
 pub use function_signature_extractor::{
     EnhancedFunctionSignature, ExtractionStats, FunctionComplexityMetrics, FunctionParameter,
@@ -29,6 +33,7 @@ pub use function_signature_extractor::{
     FunctionSignatureSimilarity, FunctionType, GenericParameter, GenericVariance,
     SimilarityBreakdown,
 };
+pub use import_delta::{ImportChange, ImportDelta};
 pub use scope_manager::{ScopeAnalysis, ScopeManager, ScopeResolution};
 pub use symbol_resolver::{FileContext, ImportInfo, SymbolResolver, SymbolResolverConfig};
 pub use symbol_table::{
@@ -42,8 +47,8 @@ pub use type_extractor::{
     ExtractedTypeInfo, TypeExtractionResult, TypeExtractor, TypeExtractorConfig,
 };
 pub use type_system::{
-    FieldInfo, MethodInfo, TypeEquivalence, TypeInfo, TypeKind, TypeResolver, TypeSignature,
-    Visibility,
+    FieldInfo, MethodInfo, TypeChangeKind, TypeEquivalence, TypeInfo, TypeKind, TypeResolver,
+    TypeSignature, Visibility,
 };
 
 /// Re-export commonly used types
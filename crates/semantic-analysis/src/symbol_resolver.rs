@@ -4,8 +4,10 @@ use crate::symbol_table::{
     ReferenceType, ScopeId, ScopeType, Symbol, SymbolKind, SymbolReference, SymbolTable,
 };
 use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
 use smart_diff_parser::{ASTNode, Language, NodeType, ParseResult};
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::path::{Path, PathBuf};
 
 /// Configuration for symbol resolution
@@ -43,8 +45,20 @@ impl Default for SymbolResolverConfig {
     }
 }
 
+/// Tree-sitter node kinds holding an import, include, from-import or use
+///
+/// A Go import declaration and a Rust use declaration may hold several
+/// imports at once. Imports of Ruby, PHP and Swift are not extracted.
+const IMPORT_NODE_KINDS: &[&str] = &[
+    "import_declaration",
+    "import_statement",
+    "import_from_statement",
+    "preproc_include",
+    "use_declaration",
+];
+
 /// Import information extracted from code
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ImportInfo {
     pub imported_name: String,
     pub source_path: Option<String>,
@@ -54,6 +68,29 @@ pub struct ImportInfo {
     pub column: usize,
 }
 
+impl fmt::Display for ImportInfo {
+    /// Short form for reports, e.g. `numpy as np`, `java.io.*`, `std::io::*`,
+    /// `* from collections`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.imported_name)?;
+        if self.is_wildcard && self.imported_name != "*" {
+            let separator = if self.imported_name.contains("::") {
+                "::"
+            } else {
+                "."
+            };
+            write!(f, "{}*", separator)?;
+        }
+        if let Some(alias) = &self.alias {
+            write!(f, " as {}", alias)?;
+        }
+        match &self.source_path {
+            Some(path) if *path != self.imported_name => write!(f, " from {}", path),
+            _ => Ok(()),
+        }
+    }
+}
+
 /// Symbol resolution context for a single file
 #[derive(Debug, Clone)]
 pub struct FileContext {
@@ -71,6 +108,9 @@ pub struct SymbolResolver {
     file_contexts: HashMap<String, FileContext>,
     resolution_cache: HashMap<String, Option<Symbol>>,
     import_graph: HashMap<String, Vec<String>>, // file -> imported files
+    /// References whose symbol has not been declared yet, such as calls to
+    /// functions defined further down or in a file processed later
+    pending_references: Vec<(String, SymbolReference)>,
 }
 
 impl SymbolResolver {
@@ -81,6 +121,7 @@ impl SymbolResolver {
             file_contexts: HashMap::new(),
             resolution_cache: HashMap::new(),
             import_graph: HashMap::new(),
+            pending_references: Vec::new(),
         }
     }
 
@@ -113,6 +154,7 @@ impl SymbolResolver {
 
         // Process symbols in the AST
         self.process_ast_node(&parse_result.ast, &mut file_context, file_scope_id)?;
+        self.attach_pending_references();
 
         // Store file context
         self.file_contexts
@@ -121,6 +163,25 @@ impl SymbolResolver {
         Ok(())
     }
 
+    /// Extract the imports of a file without adding it to the resolver
+    pub fn extract_file_imports(
+        &mut self,
+        file_path: &str,
+        parse_result: &ParseResult,
+    ) -> Result<Vec<ImportInfo>> {
+        let mut file_context = FileContext {
+            file_path: file_path.to_string(),
+            language: parse_result.language,
+            imports: Vec::new(),
+            exports: Vec::new(),
+            local_scope_stack: Vec::new(),
+        };
+
+        self.extract_imports(&parse_result.ast, &mut file_context)?;
+
+        Ok(file_context.imports)
+    }
+
     /// Process multiple files in dependency order
     pub fn process_files(&mut self, files: Vec<(String, ParseResult)>) -> Result<()> {
         // First pass: extract all symbols and imports
@@ -138,13 +199,23 @@ impl SymbolResolver {
 
     /// Extract import statements from AST
     fn extract_imports(&mut self, node: &ASTNode, file_context: &mut FileContext) -> Result<()> {
-        if node.node_type == NodeType::Module {
+        let is_import = node
+            .metadata
+            .attributes
+            .get("kind")
+            .is_some_and(|kind| IMPORT_NODE_KINDS.contains(&kind.as_str()));
+
+        if is_import {
             // Handle different import patterns based on language
             match file_context.language {
                 Language::Java => self.extract_java_imports(node, file_context)?,
                 Language::Python => self.extract_python_imports(node, file_context)?,
-                Language::JavaScript => self.extract_js_imports(node, file_context)?,
+                Language::JavaScript | Language::TypeScript => {
+                    self.extract_js_imports(node, file_context)?
+                }
                 Language::Cpp | Language::C => self.extract_c_includes(node, file_context)?,
+                Language::Go => self.extract_go_imports(node, file_context),
+                Language::Rust => self.extract_rust_uses(node, file_context),
                 _ => {}
             }
         }
@@ -216,6 +287,126 @@ impl SymbolResolver {
         Ok(())
     }
 
+    /// Extract Go imports, one per import spec of a declaration
+    fn extract_go_imports(&mut self, node: &ASTNode, file_context: &mut FileContext) {
+        let Some(import_text) = node.metadata.attributes.get("text") else {
+            return;
+        };
+        let Some(specs) = import_text.trim().strip_prefix("import") else {
+            return;
+        };
+
+        let specs = specs.trim().trim_start_matches('(').trim_end_matches(')');
+        for spec in specs.lines().flat_map(|line| line.split(';')) {
+            let spec = spec.split("//").next().unwrap_or_default().trim();
+            let Some(quote) = spec.find(['"', '`']) else {
+                continue;
+            };
+            let path = spec[quote..].trim_matches(['"', '`']).to_string();
+            // `.` imports every exported name, `_` only runs the package's init
+            let (alias, is_wildcard) = match spec[..quote].trim() {
+                "" => (None, false),
+                "." => (None, true),
+                alias => (Some(alias.to_string()), false),
+            };
+
+            file_context.imports.push(ImportInfo {
+                imported_name: path.clone(),
+                source_path: Some(path),
+                alias,
+                is_wildcard,
+                line: node.metadata.line,
+                column: node.metadata.column,
+            });
+        }
+    }
+
+    /// Extract Rust use declarations, one import per used path
+    fn extract_rust_uses(&mut self, node: &ASTNode, file_context: &mut FileContext) {
+        let Some(use_text) = node.metadata.attributes.get("text") else {
+            return;
+        };
+        let trimmed = use_text.trim();
+        let trimmed = match trimmed.strip_prefix("pub") {
+            // `pub(crate) use`, `pub use`
+            Some(rest) => rest.trim_start().trim_start_matches(|c| c != 'u'),
+            None => trimmed,
+        };
+        let Some(tree) = trimmed.strip_prefix("use") else {
+            return;
+        };
+
+        let mut imports = Vec::new();
+        Self::collect_rust_use_tree(
+            "",
+            tree.trim().trim_end_matches(';'),
+            node.metadata.line,
+            node.metadata.column,
+            &mut imports,
+        );
+        file_context.imports.extend(imports);
+    }
+
+    /// Add the paths of a Rust use tree such as `a::{b, c::*, d as e}`
+    fn collect_rust_use_tree(
+        prefix: &str,
+        tree: &str,
+        line: usize,
+        column: usize,
+        imports: &mut Vec<ImportInfo>,
+    ) {
+        let tree = tree.trim();
+        if tree.is_empty() {
+            return;
+        }
+        let join = |path: &str| match (prefix.is_empty(), path.is_empty()) {
+            (true, _) => path.to_string(),
+            (false, true) => prefix.to_string(),
+            (false, false) => format!("{}::{}", prefix, path),
+        };
+
+        if let Some(open) = tree.find('{') {
+            let path = join(tree[..open].trim().trim_end_matches("::"));
+            let group = tree[open + 1..].trim_end().trim_end_matches('}');
+            let mut depth = 0;
+            let mut start = 0;
+            for (i, c) in group.char_indices() {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    ',' if depth == 0 => {
+                        Self::collect_rust_use_tree(&path, &group[start..i], line, column, imports);
+                        start = i + 1;
+                    }
+                    _ => {}
+                }
+            }
+            Self::collect_rust_use_tree(&path, &group[start..], line, column, imports);
+            return;
+        }
+
+        let (path, alias) = match tree.split_once(" as ") {
+            Some((path, alias)) => (path.trim(), Some(alias.trim().to_string())),
+            None => (tree, None),
+        };
+        let is_wildcard = path == "*" || path.ends_with("::*");
+        let path = join(path.trim_end_matches('*').trim_end_matches("::"));
+        // `self` in a group names the module of the group itself
+        let path = path
+            .strip_suffix("::self")
+            .map(str::to_string)
+            .unwrap_or(path);
+
+        imports.push(ImportInfo {
+            imported_name: path,
+            source_path: None, // Like Java, Rust uses module paths
+            alias,
+            is_wildcard,
+            line,
+            column,
+        });
+    }
+
     /// Process AST node and extract symbols
     fn process_ast_node(
         &mut self,
@@ -234,7 +425,7 @@ impl SymbolResolver {
                         ScopeType::Class,
                         file_context.file_path.clone(),
                         node.metadata.line,
-                        node.metadata.line + 100, // Estimate end line
+                        Self::end_line(node, 100),
                     );
 
                     // Add class symbol
@@ -263,7 +454,7 @@ impl SymbolResolver {
                         ScopeType::Function,
                         file_context.file_path.clone(),
                         node.metadata.line,
-                        node.metadata.line + 50, // Estimate end line
+                        Self::end_line(node, 50),
                     );
 
                     // Add function symbol
@@ -323,7 +514,8 @@ impl SymbolResolver {
                         reference_type: ReferenceType::Call,
                     };
 
-                    self.symbol_table.add_reference(function_name, reference);
+                    self.pending_references
+                        .push((function_name.clone(), reference));
                 }
             }
             NodeType::Identifier => {
@@ -337,7 +529,7 @@ impl SymbolResolver {
                             reference_type: ReferenceType::Usage,
                         };
 
-                        self.symbol_table.add_reference(name, reference);
+                        self.pending_references.push((name.clone(), reference));
                     }
                 }
             }
@@ -352,6 +544,28 @@ impl SymbolResolver {
         Ok(())
     }
 
+    /// Last line of `node` as recorded by the parser, or an estimate `lines`
+    /// past its first line
+    fn end_line(node: &ASTNode, lines: usize) -> usize {
+        node.metadata
+            .attributes
+            .get("end_line")
+            .and_then(|end_line| end_line.parse().ok())
+            .unwrap_or(node.metadata.line + lines)
+    }
+
+    /// Attach references whose symbol is now declared; the rest stay pending
+    fn attach_pending_references(&mut self) {
+        let pending = std::mem::take(&mut self.pending_references);
+        for (name, reference) in pending {
+            if self.symbol_table.has_symbol(&name) {
+                self.symbol_table.add_reference(&name, reference);
+            } else {
+                self.pending_references.push((name, reference));
+            }
+        }
+    }
+
     /// Parse Java import statement
     fn parse_java_import(
         &self,
@@ -460,6 +674,7 @@ impl SymbolResolver {
                 let import_part = &trimmed[6..from_pos].trim(); // Skip "import"
                 let module_part = &trimmed[from_pos + 6..]
                     .trim()
+                    .trim_end_matches(';')
                     .trim_matches('\'')
                     .trim_matches('"');
 
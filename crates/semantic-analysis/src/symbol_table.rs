@@ -182,6 +182,15 @@ impl SymbolTable {
         self.file_symbols.get(file_path)
     }
 
+    /// Whether a symbol of this name is declared in any file
+    pub fn has_symbol(&self, name: &str) -> bool {
+        self.global_symbols.contains_key(name)
+            || self
+                .file_symbols
+                .values()
+                .any(|symbols| symbols.contains_key(name))
+    }
+
     /// Add a reference to a symbol
     pub fn add_reference(&mut self, symbol_name: &str, reference: SymbolReference) {
         // Find the symbol and add the reference
@@ -1,14 +1,17 @@
 //! Tests for semantic analysis components
 
 use crate::{
-    CallType, ComprehensiveDependencyGraphBuilder, DependencyAnalysisConfig,
-    FunctionSignatureConfig, FunctionSignatureExtractor, FunctionType, GenericVariance,
-    ReferenceType, ScopeManager, ScopeType, Symbol, SymbolKind, SymbolReference, SymbolResolver,
-    SymbolResolverConfig, SymbolTable, TypeDependencyGraphBuilder, TypeEquivalence, TypeExtractor,
-    TypeExtractorConfig, TypeRelationshipType, TypeSignature,
+    CallNeighbor, CallNeighborhood, CallType, ComprehensiveDependencyGraphBuilder,
+    DependencyAnalysisConfig, DotOptions, FunctionSignatureConfig, FunctionSignatureExtractor,
+    FunctionType, GenericVariance, ImportDelta, ReferenceType, ScopeManager, ScopeType, Symbol,
+    SymbolKind, SymbolReference, SymbolResolver, SymbolResolverConfig, SymbolTable,
+    TypeDependencyGraphBuilder, TypeEquivalence, TypeExtractor, TypeExtractorConfig,
+    TypeRelationshipType, TypeSignature,
 };
-use smart_diff_parser::{tree_sitter::TreeSitterParser, Language, Parser};
-use std::collections::HashSet;
+use smart_diff_parser::{
+    tree_sitter::TreeSitterParser, ASTNode, Language, NodeMetadata, NodeType, ParseResult, Parser,
+};
+use std::collections::{HashMap, HashSet};
 
 #[cfg(test)]
 mod symbol_resolver_tests {
@@ -77,6 +80,136 @@ public class Calculator {
     }
 }
 
+#[cfg(test)]
+mod import_delta_tests {
+    use super::*;
+
+    #[test]
+    fn test_import_delta_reports_added_and_removed_imports(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let parser = TreeSitterParser::new()?;
+
+        let source = parser.parse(
+            "import os\nimport requests\nfrom collections import *\nimport numpy as np\n",
+            Language::Python,
+        )?;
+        let target = parser.parse(
+            "import os\nimport httpx\nfrom collections import OrderedDict\nimport numpy as npy\n",
+            Language::Python,
+        )?;
+
+        let delta = ImportDelta::from_parse_results(&source, &target)?;
+
+        let names = |imports: &[crate::ImportInfo]| -> Vec<String> {
+            imports.iter().map(|i| i.to_string()).collect()
+        };
+        // A wildcard import and a named import from the same module are distinct
+        assert_eq!(
+            names(&delta.added),
+            vec!["httpx", "OrderedDict from collections"]
+        );
+        assert_eq!(
+            names(&delta.removed),
+            vec!["requests", "* from collections"]
+        );
+
+        // Only the alias changed, so numpy is neither added nor removed
+        assert_eq!(delta.changed.len(), 1);
+        assert_eq!(delta.changed[0].source.alias.as_deref(), Some("np"));
+        assert_eq!(delta.changed[0].target.alias.as_deref(), Some("npy"));
+
+        assert!(ImportDelta::from_parse_results(&source, &source)?.is_empty());
+
+        Ok(())
+    }
+
+    fn import_names(parse_result: &ParseResult) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let imports = SymbolResolver::with_defaults().extract_file_imports("file", parse_result)?;
+        Ok(imports.iter().map(|i| i.to_string()).collect())
+    }
+
+    /// File of import nodes built by hand, for languages without a grammar here
+    fn import_nodes(language: Language, kind: &str, texts: &[&str]) -> ParseResult {
+        let metadata = |attributes: HashMap<String, String>| NodeMetadata {
+            line: 1,
+            column: 1,
+            original_text: String::new(),
+            attributes,
+        };
+        let mut ast = ASTNode::new(NodeType::Program, metadata(HashMap::new()));
+        for text in texts {
+            let attributes = HashMap::from([
+                ("kind".to_string(), kind.to_string()),
+                ("text".to_string(), text.to_string()),
+            ]);
+            ast.add_child(ASTNode::new(NodeType::Unknown, metadata(attributes)));
+        }
+        ParseResult {
+            ast,
+            language,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            custom_elements: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_go_import_declarations_hold_every_spec() -> Result<(), Box<dyn std::error::Error>> {
+        let parser = TreeSitterParser::new()?;
+        let parsed = parser.parse(
+            "package main\n\nimport (\n\t\"fmt\"\n\tm \"math\" // maths\n\t. \"strings\"\n)\n\n\
+             import \"os\"\n\nfunc main() {}\n",
+            Language::Go,
+        )?;
+
+        assert_eq!(
+            import_names(&parsed)?,
+            vec!["fmt", "math as m", "strings.*", "os"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rust_use_trees_are_split_into_paths() -> Result<(), Box<dyn std::error::Error>> {
+        let parsed = import_nodes(
+            Language::Rust,
+            "use_declaration",
+            &[
+                "use std::collections::{HashMap, btree_map::{self, Entry as BTreeEntry}};",
+                "pub(crate) use std::io::*;",
+                "use anyhow::Result as AnyResult;",
+            ],
+        );
+
+        assert_eq!(
+            import_names(&parsed)?,
+            vec![
+                "std::collections::HashMap",
+                "std::collections::btree_map",
+                "std::collections::btree_map::Entry as BTreeEntry",
+                "std::io::*",
+                "anyhow::Result as AnyResult",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_typescript_imports_are_extracted() -> Result<(), Box<dyn std::error::Error>> {
+        let parsed = import_nodes(
+            Language::TypeScript,
+            "import_statement",
+            &["import { Injectable } from '@angular/core';"],
+        );
+
+        assert_eq!(
+            import_names(&parsed)?,
+            vec!["Injectable from @angular/core"]
+        );
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod scope_manager_tests {
     use super::*;
@@ -563,6 +696,122 @@ mod comprehensive_dependency_graph_tests {
         assert_eq!(builder.get_file_contexts().len(), 0);
     }
 
+    #[test]
+    fn test_function_fan_in_counts_call_sites() -> Result<(), Box<dyn std::error::Error>> {
+        let parser = TreeSitterParser::new()?;
+        let core = parser.parse(
+            "int validate(int x) { return x > 0; }\nint helper(int x) { return x; }\n",
+            Language::C,
+        )?;
+        let app = parser.parse(
+            "int load(int x) {\n    if (validate(x)) {\n        return helper(x);\n    }\n    return validate(-x);\n}\nint save(int x) { return validate(x); }\n",
+            Language::C,
+        )?;
+
+        let mut builder = ComprehensiveDependencyGraphBuilder::with_defaults();
+        builder.build_comprehensive_graph(vec![
+            ("core.c".to_string(), core),
+            ("app.c".to_string(), app),
+        ])?;
+        let fan_in = builder.function_fan_in();
+
+        assert_eq!(fan_in.get("validate"), Some(&3));
+        assert_eq!(fan_in.get("helper"), Some(&1));
+        assert_eq!(fan_in.get("save"), Some(&0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_call_neighborhood_walks_callers_and_callees() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let parser = TreeSitterParser::new()?;
+        let code = parser.parse(
+            "int leaf(int x) { return x; }\nint middle(int x) { return leaf(x) + leaf(1); }\nint top(int x) { return middle(x); }\n",
+            Language::C,
+        )?;
+        let mut builder = ComprehensiveDependencyGraphBuilder::with_defaults();
+        builder.build_comprehensive_graph(vec![("calls.c".to_string(), code)])?;
+
+        let names = |neighbors: &[CallNeighbor]| -> Vec<(String, usize)> {
+            neighbors
+                .iter()
+                .map(|n| (n.qualified_name.clone(), n.distance))
+                .collect()
+        };
+        let direct = builder.call_neighborhood("middle", Some("calls.c"), 1);
+        assert_eq!(names(&direct.callers), vec![("top".to_string(), 1)]);
+        assert_eq!(names(&direct.callees), vec![("leaf".to_string(), 1)]);
+
+        let deeper = builder.call_neighborhood("leaf", None, 2);
+        assert_eq!(
+            names(&deeper.callers),
+            vec![("middle".to_string(), 1), ("top".to_string(), 2)]
+        );
+        assert!(deeper.callees.is_empty());
+        assert_eq!(
+            builder.call_neighborhood("missing", None, 1),
+            CallNeighborhood::default()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dependency_graph_renders_as_dot() -> Result<(), Box<dyn std::error::Error>> {
+        let parser = TreeSitterParser::new()?;
+        let code = parser.parse(
+            "int leaf(int x) { return x; }\nint middle(int x) { return leaf(x) + leaf(1); }\nint top(int x) { return middle(x); }\n",
+            Language::C,
+        )?;
+        let mut builder = ComprehensiveDependencyGraphBuilder::with_defaults();
+        builder.build_comprehensive_graph(vec![("calls.c".to_string(), code)])?;
+        let graph = builder.get_dependency_graph();
+
+        // Every statement is terminated, and braces and quotes are balanced
+        let assert_valid = |dot: &str| {
+            assert!(dot.starts_with("digraph dependencies {\n"), "{}", dot);
+            assert!(dot.ends_with("}\n"), "{}", dot);
+            assert_eq!(dot.matches('{').count(), dot.matches('}').count());
+            for line in dot.lines() {
+                assert_eq!(line.matches('"').count() % 2, 0, "{}", line);
+                assert!(
+                    line.ends_with(';') || line.ends_with('{') || line.ends_with('}'),
+                    "{}",
+                    line
+                );
+            }
+        };
+
+        let dot = graph.to_dot(&DotOptions::default())?;
+        assert_valid(&dot);
+        assert!(dot.contains("subgraph cluster_0 {"));
+        assert!(dot.contains("label=\"calls.c\";"));
+        for function in ["leaf", "middle", "top"] {
+            assert!(
+                dot.contains(&format!("\"{0}\" [label=\"{0}\", shape=box", function)),
+                "{}",
+                dot
+            );
+        }
+        assert!(dot.contains("\"middle\" -> \"leaf\" [label=\"calls\""));
+        assert!(dot.contains("\"top\" -> \"middle\" [label=\"calls\""));
+
+        let focused = graph.to_dot(&DotOptions {
+            focus: Some("leaf".to_string()),
+            hops: 1,
+        })?;
+        assert_valid(&focused);
+        assert!(focused.contains("\"middle\" -> \"leaf\""));
+        assert!(!focused.contains("\"top\""));
+
+        assert!(graph
+            .to_dot(&DotOptions {
+                focus: Some("missing".to_string()),
+                hops: 1,
+            })
+            .is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_call_type_variants() {
         // Test that all call types are properly defined
@@ -972,3 +1221,75 @@ mod function_signature_extractor_tests {
         assert_eq!(stats.complex_functions, 3);
     }
 }
+
+#[cfg(test)]
+mod cognitive_complexity_tests {
+    use super::*;
+    use crate::{cognitive_complexity, FunctionComplexityMetrics, FunctionSignatureExtractor};
+    use smart_diff_parser::NodeType;
+
+    fn function_metrics(code: &str, language: Language) -> FunctionComplexityMetrics {
+        let parser = TreeSitterParser::new().unwrap();
+        let result = parser.parse(code, language).unwrap();
+        let function = [NodeType::Function, NodeType::Method]
+            .iter()
+            .find_map(|node_type| result.ast.find_by_type(node_type).first().copied())
+            .unwrap();
+        FunctionSignatureExtractor::with_defaults(language)
+            .calculate_complexity_metrics(function)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_nesting_costs_more_than_equal_cyclomatic_complexity() {
+        let flat = function_metrics(
+            "def flat(a, b, c):\n    if a:\n        log(a)\n    if b:\n        log(b)\n    if c:\n        log(c)\n",
+            Language::Python,
+        );
+        let nested = function_metrics(
+            "def nested(a, b, c):\n    if a:\n        if b:\n            if c:\n                log(c)\n",
+            Language::Python,
+        );
+
+        assert_eq!(flat.cyclomatic_complexity, nested.cyclomatic_complexity);
+        assert_eq!(flat.cognitive_complexity, 3);
+        // 1, then 1 + 1, then 1 + 2
+        assert_eq!(nested.cognitive_complexity, 6);
+    }
+
+    #[test]
+    fn test_cognitive_complexity_of_language_constructs() {
+        // `else if` and `else` cost 1 whatever their depth; a loop nested in
+        // the `else` costs its nesting, and so does the ternary inside it
+        let java = function_metrics(
+            "class A {\n  int f(int a, int[] xs) {\n    if (a > 0 && a < 10 || a == 42) {\n      return 1;\n    } else if (a < 0) {\n      return 2;\n    } else {\n      for (int x : xs) {\n        a += x > 0 ? x : -x;\n      }\n    }\n    return a;\n  }\n}\n",
+            Language::Java,
+        );
+        // if 1, && and || 2, else if 1, else 1, for 1 + 1, ternary 1 + 2
+        assert_eq!(java.cognitive_complexity, 10);
+
+        // Comprehension clauses count as a loop and a condition, a lambda nests
+        let python = function_metrics(
+            "def evens(xs):\n    return [x for x in xs if x % 2 == 0]\n",
+            Language::Python,
+        );
+        assert_eq!(python.cognitive_complexity, 2);
+        let python = function_metrics(
+            "def handlers(xs):\n    return [lambda: 1 if x else 2 for x in xs]\n",
+            Language::Python,
+        );
+        // for 1, conditional expression 1 + 1 inside the lambda
+        assert_eq!(python.cognitive_complexity, 3);
+
+        let parser = TreeSitterParser::new().unwrap();
+        let result = parser
+            .parse(
+                "def fact(n):\n    return 1 if n < 2 else n * fact(n - 1)\n",
+                Language::Python,
+            )
+            .unwrap();
+        let function = result.ast.find_by_type(&NodeType::Function)[0];
+        // conditional expression 1, recursive call 1
+        assert_eq!(cognitive_complexity(function), 2);
+    }
+}
@@ -470,4 +470,49 @@ impl TypeDependencyGraphBuilder {
     pub fn get_type_info_map(&self) -> &HashMap<String, ExtractedTypeInfo> {
         &self.type_info_map
     }
+
+    /// Types each type directly extends or implements, keyed by the subtype
+    ///
+    /// Unlike `inheritance_relationships`, supertypes defined outside the
+    /// graph, such as library interfaces, are kept.
+    pub fn supertypes(&self) -> HashMap<String, HashSet<String>> {
+        self.type_info_map
+            .values()
+            .map(|extracted_type| {
+                let parents = extracted_type
+                    .inheritance
+                    .iter()
+                    .chain(&extracted_type.implementations)
+                    .filter(|parent| **parent != extracted_type.type_info.name)
+                    .cloned()
+                    .collect();
+                (extracted_type.type_info.name.clone(), parents)
+            })
+            .collect()
+    }
+
+    /// Inheritance relationships (subtype -> supertype) between types in the graph
+    pub fn inheritance_relationships(&self) -> Vec<TypeRelationship> {
+        let mut relationships: Vec<TypeRelationship> = self
+            .type_info_map
+            .values()
+            .flat_map(|extracted_type| {
+                let type_name = &extracted_type.type_info.name;
+                extracted_type
+                    .inheritance
+                    .iter()
+                    .filter(move |parent| {
+                        *parent != type_name && self.type_nodes.contains_key(*parent)
+                    })
+                    .map(move |parent| TypeRelationship {
+                        from_type: type_name.clone(),
+                        to_type: parent.clone(),
+                        relationship_type: TypeRelationshipType::Inheritance,
+                        strength: 1.0,
+                    })
+            })
+            .collect();
+        relationships.sort_by(|a, b| (&a.from_type, &a.to_type).cmp(&(&b.from_type, &b.to_type)));
+        relationships
+    }
 }
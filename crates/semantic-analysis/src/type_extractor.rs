@@ -3,6 +3,7 @@
 use crate::type_system::ParameterInfo;
 use crate::{FieldInfo, MethodInfo, TypeInfo, TypeKind, TypeResolver, TypeSignature, Visibility};
 use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
 use smart_diff_parser::{ASTNode, Language, NodeType, ParseResult};
 use std::collections::{HashMap, HashSet};
 
@@ -42,7 +43,7 @@ pub struct TypeExtractor {
 }
 
 /// Extracted type information with relationships
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExtractedTypeInfo {
     pub type_info: TypeInfo,
     pub inheritance: Vec<String>,
@@ -338,16 +339,42 @@ impl TypeExtractor {
         Ok(())
     }
 
+    /// Name of the first superclass in a superclass clause, without generic arguments
+    fn superclass_name(clause: &str) -> Option<String> {
+        let clause = clause.trim();
+        let clause = clause.strip_prefix("extends").unwrap_or(clause);
+        let name = clause
+            .trim_matches(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == ':')
+            .split(',')
+            .next()?
+            .split('<')
+            .next()?
+            .trim();
+        (!name.is_empty()).then(|| name.to_string())
+    }
+
     /// Extract inheritance information
     fn extract_inheritance_info(
         &mut self,
         node: &ASTNode,
         extracted: &mut ExtractedTypeInfo,
     ) -> Result<()> {
-        // Look for inheritance/extends clauses
-        if let Some(extends) = node.metadata.attributes.get("extends") {
+        // Look for inheritance/extends clauses; the parser records the raw
+        // superclass clause, e.g. `extends Animal` in Java or `(Animal)` in Python
+        let extends = node
+            .metadata
+            .attributes
+            .get("extends")
+            .cloned()
+            .or_else(|| {
+                node.metadata
+                    .attributes
+                    .get("superclass")
+                    .and_then(|clause| Self::superclass_name(clause))
+            });
+        if let Some(extends) = extends {
             extracted.inheritance.push(extends.clone());
-            extracted.dependencies.insert(extends.clone());
+            extracted.dependencies.insert(extends);
         }
 
         // Look for interface implementations
@@ -366,13 +393,35 @@ impl TypeExtractor {
         Ok(())
     }
 
+    /// Members declared in a type, looking through body wrappers such as Java's `class_body`
+    fn type_members(node: &ASTNode) -> Vec<&ASTNode> {
+        let mut members = Vec::new();
+        for child in &node.children {
+            if matches!(child.node_type, NodeType::Unknown | NodeType::Block) {
+                members.extend(Self::type_members(child));
+            } else {
+                members.push(child);
+            }
+        }
+        members
+    }
+
+    /// Whether `node` carries `modifier`, as its own attribute or in its modifier list
+    fn has_modifier(node: &ASTNode, modifier: &str) -> bool {
+        let attributes = &node.metadata.attributes;
+        attributes.contains_key(modifier)
+            || attributes
+                .get("modifiers")
+                .is_some_and(|modifiers| modifiers.split(',').any(|m| m == modifier))
+    }
+
     /// Extract class fields
     fn extract_class_fields(
         &mut self,
         node: &ASTNode,
         extracted: &mut ExtractedTypeInfo,
     ) -> Result<()> {
-        for child in &node.children {
+        for child in Self::type_members(node) {
             if child.node_type == NodeType::FieldDeclaration {
                 if let Some(field_info) = self.extract_field_info(child)? {
                     extracted.type_info.fields.push(field_info);
@@ -397,7 +446,7 @@ impl TypeExtractor {
     fn extract_enum_values(&mut self, node: &ASTNode) -> Result<Vec<FieldInfo>> {
         let mut values = Vec::new();
 
-        for child in &node.children {
+        for child in Self::type_members(node) {
             if child.node_type == NodeType::EnumValue {
                 if let Some(name) = child.metadata.attributes.get("name") {
                     let field_info = FieldInfo {
@@ -417,11 +466,24 @@ impl TypeExtractor {
 
     /// Extract field information from a field declaration node
     fn extract_field_info(&mut self, node: &ASTNode) -> Result<Option<FieldInfo>> {
-        let name = node
+        // Java's `field_declaration` names its field in a nested declarator
+        let declarator_name = || {
+            node.children
+                .iter()
+                .find(|child| {
+                    child.metadata.attributes.get("kind").map(String::as_str)
+                        == Some("variable_declarator")
+                })
+                .and_then(|declarator| declarator.metadata.attributes.get("name"))
+        };
+        let Some(name) = node
             .metadata
             .attributes
             .get("name")
-            .ok_or_else(|| anyhow!("Field node missing name"))?;
+            .or_else(declarator_name)
+        else {
+            return Ok(None);
+        };
 
         let field_type = node
             .metadata
@@ -431,9 +493,8 @@ impl TypeExtractor {
             .clone();
 
         let visibility = self.extract_visibility(node);
-        let is_static = node.metadata.attributes.contains_key("static");
-        let is_final = node.metadata.attributes.contains_key("final")
-            || node.metadata.attributes.contains_key("const");
+        let is_static = Self::has_modifier(node, "static");
+        let is_final = Self::has_modifier(node, "final") || Self::has_modifier(node, "const");
 
         let field_info = FieldInfo {
             name: name.clone(),
@@ -452,7 +513,7 @@ impl TypeExtractor {
         node: &ASTNode,
         extracted: &mut ExtractedTypeInfo,
     ) -> Result<()> {
-        for child in &node.children {
+        for child in Self::type_members(node) {
             if matches!(child.node_type, NodeType::Method | NodeType::Constructor) {
                 if let Some(method_info) = self.extract_method_info(child)? {
                     extracted.type_info.methods.push(method_info);
@@ -469,7 +530,7 @@ impl TypeExtractor {
         node: &ASTNode,
         extracted: &mut ExtractedTypeInfo,
     ) -> Result<()> {
-        for child in &node.children {
+        for child in Self::type_members(node) {
             if child.node_type == NodeType::Method {
                 if let Some(method_info) = self.extract_method_info(child)? {
                     extracted.type_info.methods.push(method_info);
@@ -1,7 +1,7 @@
 //! Type system and type resolution
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Type information extracted from code
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -78,6 +78,19 @@ pub struct TypeResolver {
 /// Type equivalence checker
 pub struct TypeEquivalence;
 
+/// How a type changed between two versions, in terms of subtyping
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TypeChangeKind {
+    /// Same type, possibly spelled differently
+    Equivalent,
+    /// The new type is a supertype of the old one, or admits null where the old did not
+    Widening,
+    /// The new type is a subtype of the old one, or no longer admits null
+    Narrowing,
+    /// Neither type is a subtype of the other
+    Unrelated,
+}
+
 impl Default for TypeResolver {
     fn default() -> Self {
         Self::new()
@@ -186,6 +199,73 @@ impl TypeEquivalence {
         (base_similarity * 0.6) + (generic_similarity * 0.3) + (array_similarity * 0.1)
     }
 
+    /// Classify the change of a type from `from` to `to`
+    ///
+    /// `supertypes` maps each type to the types it directly extends or
+    /// implements, as recorded in a type dependency graph. Generic arguments
+    /// and array dimensions must be unchanged for the types to be related, as
+    /// neither is covariant in general.
+    pub fn classify_change(
+        from: &TypeSignature,
+        to: &TypeSignature,
+        supertypes: &HashMap<String, HashSet<String>>,
+    ) -> TypeChangeKind {
+        let same_arguments = from.generic_params.len() == to.generic_params.len()
+            && from
+                .generic_params
+                .iter()
+                .zip(&to.generic_params)
+                .all(|(a, b)| {
+                    Self::classify_change(a, b, supertypes) == TypeChangeKind::Equivalent
+                })
+            && from.array_dimensions == to.array_dimensions;
+        if !same_arguments {
+            return TypeChangeKind::Unrelated;
+        }
+
+        if Self::are_equivalent(&from.base_type, &to.base_type) {
+            return match (from.is_nullable, to.is_nullable) {
+                (false, true) => TypeChangeKind::Widening,
+                (true, false) => TypeChangeKind::Narrowing,
+                _ => TypeChangeKind::Equivalent,
+            };
+        }
+
+        // Dropping null from a wider type is still no longer a supertype
+        if Self::is_subtype(&from.base_type, &to.base_type, supertypes)
+            && !(from.is_nullable && !to.is_nullable)
+        {
+            TypeChangeKind::Widening
+        } else if Self::is_subtype(&to.base_type, &from.base_type, supertypes)
+            && !(to.is_nullable && !from.is_nullable)
+        {
+            TypeChangeKind::Narrowing
+        } else {
+            TypeChangeKind::Unrelated
+        }
+    }
+
+    /// Whether `subtype` extends or implements `supertype`, directly or not
+    fn is_subtype(
+        subtype: &str,
+        supertype: &str,
+        supertypes: &HashMap<String, HashSet<String>>,
+    ) -> bool {
+        let mut visited = HashSet::new();
+        let mut pending = vec![subtype];
+        while let Some(type_name) = pending.pop() {
+            for parent in supertypes.get(type_name).into_iter().flatten() {
+                if parent == supertype {
+                    return true;
+                }
+                if visited.insert(parent.as_str()) {
+                    pending.push(parent);
+                }
+            }
+        }
+        false
+    }
+
     /// Check if two types are related (inheritance, interface implementation, etc.)
     fn are_related_types(type1: &str, type2: &str) -> bool {
         // Check for common inheritance patterns
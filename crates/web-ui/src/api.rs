@@ -6,6 +6,8 @@ use axum::{
 };
 use tower_http::cors::CorsLayer;
 
+use crate::state::AppState;
+
 /// Configure CORS for the API
 #[allow(dead_code)]
 pub fn cors_layer() -> CorsLayer {
@@ -20,7 +22,7 @@ pub fn cors_layer() -> CorsLayer {
 
 /// Create the main API router
 #[allow(dead_code)]
-pub fn create_router() -> Router {
+pub fn create_router(state: AppState) -> Router {
     Router::new()
         .route("/", axum::routing::get(crate::handlers::root))
         .route("/api/health", axum::routing::get(crate::handlers::health))
@@ -29,4 +31,5 @@ pub fn create_router() -> Router {
             axum::routing::post(crate::handlers::compare),
         )
         .layer(cors_layer())
+        .with_state(state)
 }
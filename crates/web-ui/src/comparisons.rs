@@ -0,0 +1,217 @@
+//! In-memory storage for computed comparisons
+//!
+//! Mirrors the MCP server's comparison manager: every `/api/compare` result is
+//! kept under a UUID so a frontend can run the expensive comparison once and
+//! then fetch the full result or individual function diffs by id.
+
+use smart_diff_parser::Language;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::models::FileInfo;
+
+/// Environment variable overriding [`StoreLimits::idle_ttl`], in seconds
+pub const IDLE_TTL_ENV: &str = "SMART_DIFF_COMPARISON_TTL_SECS";
+
+/// Environment variable overriding [`StoreLimits::max_comparisons`]
+pub const MAX_COMPARISONS_ENV: &str = "SMART_DIFF_MAX_COMPARISONS";
+
+/// A comparison kept for later retrieval
+#[derive(Debug, Clone)]
+pub struct StoredComparison {
+    pub id: Uuid,
+    /// Language both files were parsed as
+    pub language: Language,
+    pub source: FileInfo,
+    pub target: FileInfo,
+    /// Serialized `CompareResponse` returned when the comparison was created
+    pub result: serde_json::Value,
+}
+
+/// How many comparisons are kept, and for how long
+///
+/// Same limits, and same environment variables, as the MCP server's
+/// comparison manager.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoreLimits {
+    /// Comparisons not accessed for this long are evicted
+    pub idle_ttl: Duration,
+    /// Most comparisons kept, at least one; the least recently accessed are
+    /// evicted first
+    pub max_comparisons: usize,
+}
+
+impl Default for StoreLimits {
+    fn default() -> Self {
+        Self {
+            idle_ttl: Duration::from_secs(60 * 60),
+            max_comparisons: 100,
+        }
+    }
+}
+
+impl StoreLimits {
+    /// Defaults overridden by [`IDLE_TTL_ENV`] and [`MAX_COMPARISONS_ENV`]
+    ///
+    /// Unparsable values are ignored with a warning.
+    pub fn from_env() -> Self {
+        let mut limits = Self::default();
+        if let Some(seconds) = env_number(IDLE_TTL_ENV) {
+            limits.idle_ttl = Duration::from_secs(seconds);
+        }
+        if let Some(max) = env_number(MAX_COMPARISONS_ENV) {
+            limits.max_comparisons = max as usize;
+        }
+        limits
+    }
+}
+
+/// Whole number set in the environment variable `name`, if any
+///
+/// An unparsable value is ignored with a warning.
+pub(crate) fn env_number(name: &str) -> Option<u64> {
+    let value = std::env::var(name).ok()?;
+    match value.trim().parse() {
+        Ok(number) => Some(number),
+        Err(_) => {
+            tracing::warn!("Ignoring {}={:?}: not a whole number", name, value);
+            None
+        }
+    }
+}
+
+/// A stored comparison and when it was last inserted or read
+#[derive(Debug, Clone)]
+struct Entry {
+    comparison: StoredComparison,
+    last_accessed: Instant,
+}
+
+/// Shared store of comparisons, keyed by comparison id
+///
+/// Idle comparisons are dropped as new ones come in, and the least recently
+/// accessed ones whenever the count exceeds the cap, so a long-running server
+/// does not keep every comparison it ever made.
+#[derive(Debug, Clone, Default)]
+pub struct ComparisonStore {
+    comparisons: Arc<RwLock<HashMap<Uuid, Entry>>>,
+    limits: StoreLimits,
+}
+
+impl ComparisonStore {
+    /// Store limited as configured by the environment
+    pub fn new() -> Self {
+        Self::with_limits(StoreLimits::from_env())
+    }
+
+    pub fn with_limits(limits: StoreLimits) -> Self {
+        Self {
+            comparisons: Arc::default(),
+            limits,
+        }
+    }
+
+    /// Store a comparison, replacing any previous entry with the same id
+    pub fn insert(&self, comparison: StoredComparison) {
+        self.insert_at(comparison, Instant::now());
+    }
+
+    /// Get a stored comparison by id, unless it has been evicted
+    pub fn get(&self, id: Uuid) -> Option<StoredComparison> {
+        self.get_at(id, Instant::now())
+    }
+
+    fn insert_at(&self, comparison: StoredComparison, now: Instant) {
+        let mut comparisons = self
+            .comparisons
+            .write()
+            .expect("comparison store lock poisoned");
+        comparisons.retain(|_, entry| !self.is_idle(entry, now));
+        comparisons.insert(
+            comparison.id,
+            Entry {
+                comparison,
+                last_accessed: now,
+            },
+        );
+
+        let max_comparisons = self.limits.max_comparisons.max(1);
+        if comparisons.len() > max_comparisons {
+            let mut by_access: Vec<(Instant, Uuid)> = comparisons
+                .iter()
+                .map(|(id, entry)| (entry.last_accessed, *id))
+                .collect();
+            by_access.sort_by_key(|(last_accessed, _)| *last_accessed);
+            let excess = comparisons.len() - max_comparisons;
+            for (_, id) in by_access.into_iter().take(excess) {
+                comparisons.remove(&id);
+            }
+        }
+    }
+
+    fn get_at(&self, id: Uuid, now: Instant) -> Option<StoredComparison> {
+        let mut comparisons = self
+            .comparisons
+            .write()
+            .expect("comparison store lock poisoned");
+        let entry = comparisons.get_mut(&id)?;
+        if self.is_idle(entry, now) {
+            comparisons.remove(&id);
+            return None;
+        }
+        entry.last_accessed = now;
+        Some(entry.comparison.clone())
+    }
+
+    fn is_idle(&self, entry: &Entry, now: Instant) -> bool {
+        now.saturating_duration_since(entry.last_accessed) >= self.limits.idle_ttl
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comparison() -> StoredComparison {
+        let file = FileInfo {
+            path: "a.py".to_string(),
+            content: "x = 1\n".to_string(),
+        };
+        StoredComparison {
+            id: Uuid::new_v4(),
+            language: Language::Python,
+            source: file.clone(),
+            target: file,
+            result: serde_json::Value::Null,
+        }
+    }
+
+    #[test]
+    fn test_store_evicts_idle_and_least_recently_used_comparisons() {
+        let store = ComparisonStore::with_limits(StoreLimits {
+            idle_ttl: Duration::from_secs(600),
+            max_comparisons: 2,
+        });
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+        let stored = || store.comparisons.read().unwrap().len();
+
+        let (first, second, third) = (comparison(), comparison(), comparison());
+        store.insert_at(first.clone(), at(0));
+        store.insert_at(second.clone(), at(1));
+        // Reading the first makes the second the least recently used
+        assert!(store.get_at(first.id, at(2)).is_some());
+        store.insert_at(third.clone(), at(3));
+        assert_eq!(stored(), 2);
+        assert!(store.get_at(second.id, at(4)).is_none());
+        assert!(store.get_at(first.id, at(4)).is_some());
+
+        // Comparisons not read within the TTL are gone
+        assert!(store.get_at(third.id, at(3 + 600)).is_none());
+        store.insert_at(comparison(), at(4 + 600));
+        assert_eq!(stored(), 1);
+        assert!(store.get_at(first.id, at(4 + 600)).is_none());
+    }
+}
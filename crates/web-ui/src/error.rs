@@ -0,0 +1,166 @@
+//! Errors returned by the HTTP API
+//!
+//! Every failing endpoint answers with a status code for the error's category
+//! and an [`ErrorResponse`] body, so a frontend can tell a missing path from
+//! an unsupported language or a server bug.
+
+use axum::{
+    async_trait,
+    extract::{rejection::JsonRejection, FromRequest, Json, Request},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use smart_diff_parser::ParseError;
+
+use crate::models::ErrorResponse;
+
+/// Error of an API request, by category
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    /// The request is malformed or asks for something impossible
+    #[error("{message}")]
+    BadRequest {
+        message: String,
+        details: Option<String>,
+    },
+
+    /// A path or stored comparison does not exist
+    #[error("{0}")]
+    NotFound(String),
+
+    /// The content is in a language without a parser
+    #[error("{0}")]
+    UnsupportedLanguage(String),
+
+    /// The content could not be parsed
+    #[error("{0}")]
+    Unparseable(String),
+
+    /// A failure that is not the client's fault
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::BadRequest {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::BadRequest { .. } => StatusCode::BAD_REQUEST,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::UnsupportedLanguage(_) | Self::Unparseable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Machine-readable category, the body's `error_code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::BadRequest { .. } => "bad_request",
+            Self::NotFound(_) => "not_found",
+            Self::UnsupportedLanguage(_) => "unsupported_language",
+            Self::Unparseable(_) => "parse_error",
+            Self::Internal(_) => "internal_error",
+        }
+    }
+
+    /// Categorize an error raised below the handlers; anything unrecognized
+    /// is internal
+    fn categorize(error: &(dyn std::error::Error + 'static)) -> Self {
+        if let Some(parse_error) = error.downcast_ref::<ParseError>() {
+            match parse_error {
+                ParseError::UnsupportedLanguage(_) => {
+                    return Self::UnsupportedLanguage(error.to_string())
+                }
+                ParseError::ParseFailed(_)
+                | ParseError::TreeSitterError(_)
+                | ParseError::MaxDepthExceeded { .. } => {
+                    return Self::Unparseable(error.to_string())
+                }
+                ParseError::IoError(_)
+                | ParseError::InvalidExtractionRule { .. }
+                | ParseError::InvalidNormalizationRule { .. } => {}
+            }
+        }
+        let io_error = error.downcast_ref::<std::io::Error>();
+        if io_error.is_some_and(|io_error| io_error.kind() == std::io::ErrorKind::NotFound) {
+            return Self::NotFound(error.to_string());
+        }
+        Self::Internal(error.to_string())
+    }
+}
+
+impl From<ParseError> for ApiError {
+    fn from(error: ParseError) -> Self {
+        Self::categorize(&error)
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        match error.downcast::<ApiError>() {
+            Ok(api_error) => api_error,
+            Err(error) => Self::categorize(error.as_ref()),
+        }
+    }
+}
+
+impl From<Box<dyn std::error::Error + Send + Sync>> for ApiError {
+    fn from(error: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        match error.downcast::<ApiError>() {
+            Ok(api_error) => *api_error,
+            Err(error) => Self::categorize(error.as_ref()),
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::BadRequest {
+            message: "Invalid JSON request body".to_string(),
+            details: Some(rejection.body_text()),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            tracing::error!("Request failed: {}", self);
+        }
+        let error_code = Some(self.code().to_string());
+        let (error, details) = match self {
+            Self::BadRequest { message, details } => (message, details),
+            other => (other.to_string(), None),
+        };
+        let body = ErrorResponse {
+            error,
+            details,
+            error_code,
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+/// JSON request body whose rejection is an [`ApiError`] with a JSON body
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(request, state).await?;
+        Ok(Self(value))
+    }
+}
@@ -3,25 +3,34 @@
 #![allow(clippy::all, dead_code, unused_imports)]
 
 use axum::{
-    extract::Json,
-    http::StatusCode,
+    extract::{Json, Path as UrlPath, State},
+    http::{header, StatusCode},
     response::{Html, Json as ResponseJson},
 };
 use serde_json::json;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use sysinfo::System;
 
 use smart_diff_engine::{
-    ChangeClassifier, DiffEngine, FunctionMatcher, RefactoringDetector, SimilarityScorer,
+    CancellationToken, ChangeClassificationConfig, ChangeClassifier, CloneDetector, CodeVersion,
+    DiffEngine, FunctionMatcher, RefactoringDetectionConfig, RefactoringDetector, SimilarityScorer,
     TreeEditDistance, ZhangShashaConfig,
 };
 use smart_diff_parser::{
-    tree_sitter::TreeSitterParser, Language, LanguageDetector, ParseResult, Parser,
+    line_range_text, source_lines, tree_sitter::TreeSitterParser, CommentSyntax, Language,
+    LanguageDetector, ParseError, ParseResult, Parser,
 };
 use smart_diff_semantic::SemanticAnalyzer;
 use tracing::{info, warn};
+use uuid::Uuid;
 
+use crate::comparisons::{ComparisonStore, StoredComparison};
+use crate::error::{ApiError, ApiJson};
+use crate::metrics::ServerMetrics;
 use crate::models::*;
+use crate::state::AppState;
 
 /// Root handler - serves basic info about the API
 pub async fn root() -> Html<&'static str> {
@@ -44,6 +53,15 @@ pub async fn root() -> Html<&'static str> {
         <div class="endpoint">
             <strong>GET /api/health</strong> - Health check and system status
         </div>
+        <div class="endpoint">
+            <strong>GET /api/health/live</strong> - Liveness probe
+        </div>
+        <div class="endpoint">
+            <strong>GET /api/health/ready</strong> - Readiness probe (parser and grammars available)
+        </div>
+        <div class="endpoint">
+            <strong>GET /metrics</strong> - Prometheus metrics
+        </div>
         <div class="endpoint">
             <strong>POST /api/compare</strong> - Compare two code files with comprehensive analysis
         </div>
@@ -82,175 +100,490 @@ curl -X POST http://localhost:3000/api/compare \
 }
 
 /// Health check endpoint
-pub async fn health() -> ResponseJson<HealthResponse> {
-    let uptime = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
+///
+/// Reports component status and memory usage. The overall status is
+/// "unhealthy" when the readiness check fails.
+pub async fn health(State(state): State<AppState>) -> ResponseJson<HealthResponse> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let readiness = (state.readiness)();
 
     let mut components = HashMap::new();
-
-    // Check parser component
     components.insert(
         "parser".to_string(),
-        ComponentHealth {
-            status: "healthy".to_string(),
-            last_check: chrono::Utc::now().to_rfc3339(),
-            details: Some("Parser engine operational".to_string()),
-        },
-    );
-
-    // Check semantic analyzer
-    components.insert(
-        "semantic".to_string(),
-        ComponentHealth {
-            status: "healthy".to_string(),
-            last_check: chrono::Utc::now().to_rfc3339(),
-            details: Some("Semantic analyzer operational".to_string()),
-        },
-    );
-
-    // Check diff engine
-    components.insert(
-        "diff_engine".to_string(),
-        ComponentHealth {
-            status: "healthy".to_string(),
-            last_check: chrono::Utc::now().to_rfc3339(),
-            details: Some("Diff engine operational".to_string()),
+        match &readiness {
+            Ok(grammars) => ComponentHealth {
+                status: "healthy".to_string(),
+                last_check: now.clone(),
+                details: Some(format!("{} language grammars loaded", grammars)),
+            },
+            Err(e) => ComponentHealth {
+                status: "unhealthy".to_string(),
+                last_check: now.clone(),
+                details: Some(e.clone()),
+            },
         },
     );
 
     let response = HealthResponse {
-        status: "healthy".to_string(),
+        status: if readiness.is_ok() {
+            "healthy"
+        } else {
+            "unhealthy"
+        }
+        .to_string(),
         service: "smart-code-diff".to_string(),
-        version: "0.1.0".to_string(),
-        uptime_seconds: uptime,
-        memory_usage: MemoryUsage {
-            used_mb: 0.0, // Would implement actual memory tracking
-            available_mb: 0.0,
-            peak_mb: 0.0,
-        },
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        uptime_seconds: state.started_at.elapsed().as_secs(),
+        memory_usage: current_memory_usage(),
         components,
     };
 
     ResponseJson(response)
 }
 
+/// Liveness probe - the process is up and serving requests
+pub async fn health_live() -> StatusCode {
+    StatusCode::OK
+}
+
+/// Readiness probe - the server can parse and compare files
+pub async fn health_ready(State(state): State<AppState>) -> (StatusCode, String) {
+    match (state.readiness)() {
+        Ok(grammars) => (
+            StatusCode::OK,
+            format!("ready: {} language grammars loaded", grammars),
+        ),
+        Err(e) => {
+            warn!("Readiness check failed: {}", e);
+            (StatusCode::SERVICE_UNAVAILABLE, format!("not ready: {}", e))
+        }
+    }
+}
+
+/// Prometheus metrics endpoint
+pub async fn metrics(
+    State(metrics): State<ServerMetrics>,
+) -> ([(header::HeaderName, &'static str); 1], String) {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(),
+    )
+}
+
+/// Resident memory of this process and memory available on the host
+fn current_memory_usage() -> MemoryUsage {
+    static PEAK_BYTES: AtomicU64 = AtomicU64::new(0);
+    const MB: f64 = 1024.0 * 1024.0;
+
+    let mut system = System::new();
+    system.refresh_memory();
+    let used = sysinfo::get_current_pid()
+        .ok()
+        .filter(|&pid| system.refresh_process(pid))
+        .and_then(|pid| system.process(pid))
+        .map(|process| process.memory())
+        .unwrap_or(0);
+    let peak = PEAK_BYTES.fetch_max(used, Ordering::Relaxed).max(used);
+
+    MemoryUsage {
+        used_mb: used as f64 / MB,
+        available_mb: system.available_memory() as f64 / MB,
+        peak_mb: peak as f64 / MB,
+    }
+}
+
 /// Compare endpoint - main functionality
+///
+/// The result is stored so it can be fetched again by its `comparison_id`.
 pub async fn compare(
-    Json(request): Json<CompareRequest>,
-) -> Result<ResponseJson<CompareResponse>, StatusCode> {
-    let start_time = Instant::now();
-
+    State(state): State<AppState>,
+    ApiJson(request): ApiJson<CompareRequest>,
+) -> Result<ResponseJson<CompareResponse>, ApiError> {
     tracing::info!(
         "Received compare request for {} and {}",
         request.file1.path,
         request.file2.path
     );
 
-    // Perform the actual comparison using our diff engine
-    match perform_comparison(&request.file1, &request.file2, &request.options).await {
-        Ok(analysis) => {
-            let execution_time = start_time.elapsed().as_millis() as u64;
-
-            let response = CompareResponse {
-                similarity: analysis.files.similarity.overall,
-                analysis,
-                execution_time_ms: execution_time,
-            };
-
-            Ok(ResponseJson(response))
-        }
+    match compare_and_store(&state, request).await {
+        Ok(response) => Ok(ResponseJson(response)),
         Err(e) => {
             tracing::error!("Comparison failed: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
 
-/// Perform the actual file comparison
-async fn perform_comparison(
-    file1: &FileInfo,
-    file2: &FileInfo,
-    _options: &CompareOptions,
-) -> anyhow::Result<AnalysisResult> {
-    // Initialize components
-    let _language_detector = LanguageDetector;
-    let parser_engine = TreeSitterParser::new()?;
-    let mut semantic_analyzer = SemanticAnalyzer::new();
-    let _diff_engine = DiffEngine::new();
+/// Batch compare endpoint - compares many pairs with bounded parallelism
+///
+/// A failing pair is reported in its own result entry and does not fail the batch.
+/// Batches over the configured size are rejected whole. The parsing and
+/// matching of each pair run on the blocking pool, at most as many pairs at
+/// once, across all batches, as the batch limits allow.
+pub async fn compare_batch(
+    State(state): State<AppState>,
+    ApiJson(request): ApiJson<BatchCompareRequest>,
+) -> Result<ResponseJson<BatchCompareResponse>, ApiError> {
+    let start_time = Instant::now();
 
-    // Detect language
-    let language = {
-        let path_lang = LanguageDetector::detect_from_path(&file1.path);
-        if path_lang != Language::Unknown {
-            path_lang
-        } else {
-            LanguageDetector::detect_from_content(&file1.content)
-        }
-    };
+    tracing::info!(
+        "Received batch compare request for {} pairs",
+        request.pairs.len()
+    );
+    if request.pairs.len() > state.batch.max_pairs {
+        return Err(ApiError::bad_request(format!(
+            "Batch of {} pairs exceeds the limit of {}",
+            request.pairs.len(),
+            state.batch.max_pairs
+        )));
+    }
+
+    let tasks: Vec<_> = request
+        .pairs
+        .into_iter()
+        .map(|pair| {
+            let state = state.clone();
+            let permits = state.batch.permits.clone();
+            tokio::spawn(async move {
+                let _permit = permits
+                    .acquire_owned()
+                    .await
+                    .map_err(|e| anyhow::anyhow!(e))?;
+                compare_and_store(&state, pair).await
+            })
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(tasks.len());
+    for (index, task) in tasks.into_iter().enumerate() {
+        let outcome = match task.await {
+            Ok(outcome) => outcome,
+            Err(e) => Err(anyhow::anyhow!("Comparison task failed: {}", e)),
+        };
+
+        results.push(match outcome {
+            Ok(response) => BatchCompareItem {
+                index,
+                result: Some(response),
+                error: None,
+            },
+            Err(e) => {
+                warn!("Batch comparison {} failed: {}", index, e);
+                BatchCompareItem {
+                    index,
+                    result: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        });
+    }
+
+    let succeeded = results.iter().filter(|item| item.result.is_some()).count();
 
-    // Parse both files
-    let parse_result1 = parser_engine.parse(&file1.content, language)?;
-    let parse_result2 = parser_engine.parse(&file2.content, language)?;
+    Ok(ResponseJson(BatchCompareResponse {
+        failed: results.len() - succeeded,
+        succeeded,
+        results,
+        execution_time_ms: start_time.elapsed().as_millis() as u64,
+    }))
+}
 
-    // Perform semantic analysis
-    let semantic1 = semantic_analyzer.analyze(&parse_result1)?;
-    let semantic2 = semantic_analyzer.analyze(&parse_result2)?;
+/// Compare one pair and keep the result in the store
+async fn compare_and_store(
+    state: &AppState,
+    request: CompareRequest,
+) -> anyhow::Result<CompareResponse> {
+    let start_time = Instant::now();
 
-    // Extract ASTs for structure comparison
-    let ast1 = &parse_result1.ast;
-    let ast2 = &parse_result2.ast;
+    // Perform the actual comparison using our diff engine
+    let (analysis, truncated) =
+        perform_comparison(&request.file1, &request.file2, &request.options)
+            .await
+            .inspect_err(|e| {
+                if e.downcast_ref::<ParseError>().is_some() {
+                    state.metrics.record_parse_failure();
+                }
+            })?;
+    let elapsed = start_time.elapsed();
+    state.metrics.record_comparison(elapsed);
+    let execution_time = elapsed.as_millis() as u64;
+    let id = Uuid::new_v4();
+    let language = detect_file_language(&request.file1);
+
+    let response = CompareResponse {
+        comparison_id: id.to_string(),
+        similarity: analysis.files.similarity.overall,
+        analysis,
+        execution_time_ms: execution_time,
+        truncated,
+    };
 
-    // Initialize components that need language
-    let function_matcher = FunctionMatcher::new(0.7); // threshold
-    let _similarity_scorer = SimilarityScorer::new(
+    state.comparisons.insert(StoredComparison {
+        id,
         language,
-        smart_diff_engine::SimilarityScoringConfig::default(),
+        source: request.file1,
+        target: request.file2,
+        result: serde_json::to_value(&response)?,
+    });
+
+    Ok(response)
+}
+
+/// Fetch a previously computed comparison by id
+pub async fn get_comparison(
+    State(store): State<ComparisonStore>,
+    UrlPath(id): UrlPath<Uuid>,
+) -> Result<ResponseJson<serde_json::Value>, ApiError> {
+    store
+        .get(id)
+        .map(|comparison| ResponseJson(comparison.result))
+        .ok_or_else(|| ApiError::NotFound(format!("No comparison with id {}", id)))
+}
+
+/// Diff a single function of a previously computed comparison
+pub async fn get_function_diff(
+    State(store): State<ComparisonStore>,
+    UrlPath((id, name)): UrlPath<(Uuid, String)>,
+    request: Option<Json<FunctionDiffRequest>>,
+) -> Result<ResponseJson<FunctionDiffResponse>, ApiError> {
+    let comparison = store
+        .get(id)
+        .ok_or_else(|| ApiError::NotFound(format!("No comparison with id {}", id)))?;
+    let Json(request) = request.unwrap_or_default();
+
+    let find_function = |file: &FileInfo| {
+        extract_function_infos(&file.content, comparison.language, &file.path)
+            .into_iter()
+            .find(|function| function.name == name)
+    };
+    let source_function = find_function(&comparison.source);
+    let target_function = find_function(&comparison.target);
+
+    let (change_type, similarity) = match (&source_function, &target_function) {
+        (Some(source), Some(target)) if source.content == target.content => ("unchanged", 1.0),
+        (Some(source), Some(target)) => (
+            "modified",
+            calculate_content_similarity(&source.content, &target.content),
+        ),
+        (Some(_), None) => ("deleted", 0.0),
+        (None, Some(_)) => ("added", 0.0),
+        (None, None) => {
+            return Err(ApiError::NotFound(format!(
+                "No function named '{}' in either file",
+                name
+            )))
+        }
+    };
+
+    let content = |function: &Option<FunctionInfo>| {
+        function
+            .as_ref()
+            .map(|f| f.content.clone())
+            .unwrap_or_default()
+    };
+    let line_mappings = generate_lcs_line_mappings(
+        &content(&source_function),
+        &content(&target_function),
+        request.ignore_whitespace,
     );
-    let _change_classifier = ChangeClassifier::new(language);
-    let refactoring_detector = RefactoringDetector::new(language);
+    let summary = calculate_ast_diff_summary(&line_mappings);
+
+    Ok(ResponseJson(FunctionDiffResponse {
+        comparison_id: id.to_string(),
+        function_name: name,
+        change_type: change_type.to_string(),
+        similarity,
+        source_function,
+        target_function,
+        line_mappings,
+        summary,
+    }))
+}
+
+/// Language of a compared file, from its path or else its content
+fn detect_file_language(file: &FileInfo) -> Language {
+    match LanguageDetector::detect_from_path(&file.path) {
+        Language::Unknown => LanguageDetector::detect_from_content(&file.content),
+        language => language,
+    }
+}
+
+/// Functions and summary figures of one analyzed file
+struct AnalyzedFile {
+    functions: Vec<smart_diff_parser::Function>,
+    classes: usize,
+    complexity: usize,
+    version: CodeVersion,
+}
+
+impl AnalyzedFile {
+    fn analyze(
+        parser_engine: &TreeSitterParser,
+        semantic_analyzer: &mut SemanticAnalyzer,
+        path: &str,
+        content: &str,
+        language: Language,
+    ) -> anyhow::Result<Self> {
+        let parse_result = parser_engine.parse(content, language)?;
+        let semantic = semantic_analyzer.analyze(&parse_result)?;
+        let version = CodeVersion::from_file(path, &parse_result).unwrap_or_else(|e| {
+            warn!(
+                "Refactoring detection limited to the matched changes: {}",
+                e
+            );
+            CodeVersion::new()
+        });
+
+        Ok(Self {
+            functions: extract_functions_from_symbol_table(&semantic.symbol_table),
+            classes: count_classes_from_symbol_table(&semantic.symbol_table),
+            complexity: calculate_complexity_from_symbol_table(&semantic.symbol_table),
+            version,
+        })
+    }
+
+    fn metadata(analyzed: Option<&Self>, file: &FileInfo) -> FileMetadata {
+        FileMetadata {
+            path: file.path.clone(),
+            lines: file.content.lines().count(),
+            functions: analyzed.map_or(0, |a| a.functions.len()),
+            classes: analyzed.map_or(0, |a| a.classes),
+            complexity: analyzed.map_or(0.0, |a| a.complexity as f64),
+        }
+    }
+}
+
+/// Run a blocking comparison stage, aborting it once `deadline` passes
+///
+/// Returns `None` when the deadline passed first. The stage is then told to
+/// stop through `cancellation`, which its matcher and detector check between
+/// units of work, so it leaves the blocking pool soon after.
+async fn run_before_deadline<T, F>(
+    deadline: Option<Instant>,
+    cancellation: &CancellationToken,
+    stage: F,
+) -> anyhow::Result<Option<T>>
+where
+    T: Send + 'static,
+    F: FnOnce() -> anyhow::Result<T> + Send + 'static,
+{
+    let Some(deadline) = deadline else {
+        return Ok(Some(tokio::task::spawn_blocking(stage).await??));
+    };
+    if Instant::now() >= deadline {
+        cancellation.cancel();
+        return Ok(None);
+    }
 
-    // Extract functions from symbol tables
-    let functions1 = extract_functions_from_symbol_table(&semantic1.symbol_table);
-    let functions2 = extract_functions_from_symbol_table(&semantic2.symbol_table);
+    match tokio::time::timeout_at(deadline.into(), tokio::task::spawn_blocking(stage)).await {
+        Ok(joined) => Ok(Some(joined??)),
+        Err(_) => {
+            cancellation.cancel();
+            Ok(None)
+        }
+    }
+}
+
+/// Perform the actual file comparison
+///
+/// With `timeout_ms` set, stages still running at the deadline are aborted
+/// and later ones skipped. The analysis then holds what the finished stages
+/// produced, and the returned flag is set. The similarity score is kept as
+/// long as function matching finished.
+async fn perform_comparison(
+    file1: &FileInfo,
+    file2: &FileInfo,
+    options: &CompareOptions,
+) -> anyhow::Result<(AnalysisResult, bool)> {
+    let deadline = options
+        .timeout_ms
+        .map(|timeout| Instant::now() + std::time::Duration::from_millis(timeout));
+    let language = detect_file_language(file1);
+    let cancellation = CancellationToken::new();
+
+    // Parse both files and extract functions from their symbol tables
+    let (file1_owned, file2_owned) = (file1.clone(), file2.clone());
+    let parse_cancellation = cancellation.clone();
+    let analyzed = run_before_deadline(deadline, &cancellation, move || {
+        let parser_engine = TreeSitterParser::new()?;
+        let mut semantic_analyzer = SemanticAnalyzer::new();
+        let mut analyze = |file: &FileInfo| {
+            AnalyzedFile::analyze(
+                &parser_engine,
+                &mut semantic_analyzer,
+                &file.path,
+                &file.content,
+                language,
+            )
+        };
+        let analyzed1 = analyze(&file1_owned)?;
+        if parse_cancellation.is_cancelled() {
+            anyhow::bail!("Comparison cancelled");
+        }
+        Ok((analyzed1, analyze(&file2_owned)?))
+    })
+    .await?;
+
+    let source = AnalyzedFile::metadata(analyzed.as_ref().map(|(a, _)| a), file1);
+    let target = AnalyzedFile::metadata(analyzed.as_ref().map(|(_, a)| a), file2);
 
     // Match functions
-    let function_matches = function_matcher.match_functions(&functions1, &functions2);
+    let (function_matches, versions) = match analyzed {
+        Some((analyzed1, analyzed2)) => {
+            let versions = (analyzed1.version, analyzed2.version);
+            let (functions1, functions2) = (analyzed1.functions, analyzed2.functions);
+            let mut function_matcher = FunctionMatcher::new(0.7); // threshold
+            function_matcher.set_cancellation(cancellation.clone());
+            let matches = run_before_deadline(deadline, &cancellation, move || {
+                Ok(function_matcher.match_functions(&functions1, &functions2))
+            })
+            .await?;
+            (matches, Some(versions))
+        }
+        None => (None, None),
+    };
 
-    // Calculate basic similarity scores (simplified for web API)
-    let overall_similarity = if !functions1.is_empty() && !functions2.is_empty() {
-        function_matches.similarity
-    } else {
-        0.0
+    // Detect refactoring patterns in the changes from function matching, and
+    // score the paired functions
+    let detected = match (&function_matches, versions) {
+        (Some(matches), Some((source_version, target_version))) => {
+            let refactoring_detector = build_refactoring_detector(language, options)
+                .with_versions(source_version.clone(), target_version.clone())
+                .with_cancellation(cancellation.clone());
+            let changes = matches.changes.clone();
+            let detect_cancellation = cancellation.clone();
+            run_before_deadline(deadline, &cancellation, move || {
+                let patterns = refactoring_detector.detect_patterns(&changes);
+                if detect_cancellation.is_cancelled() {
+                    anyhow::bail!("Comparison cancelled");
+                }
+                let semantic = calculate_semantic_similarity(
+                    language,
+                    &source_version,
+                    &target_version,
+                    &changes,
+                )?;
+                Ok((patterns, semantic))
+            })
+            .await?
+        }
+        _ => None,
     };
+    let truncated = detected.is_none();
+    // Calculate basic similarity scores (simplified for web API)
+    let overall_similarity = match_similarity(&source, &target, function_matches.as_ref());
+    let function_matches = function_matches.unwrap_or_else(smart_diff_parser::MatchResult::new);
+    let (refactoring_patterns, semantic) = detected.unwrap_or_default();
+
     let structure_similarity = overall_similarity; // Simplified
     let content_similarity = overall_similarity; // Simplified
-    let semantic_similarity = overall_similarity; // Simplified
-
-    // Use changes from function matching
-    let changes = function_matches.changes.clone();
-
-    // Detect refactoring patterns
-    let refactoring_patterns = refactoring_detector.detect_patterns(&changes);
+    let semantic_similarity = semantic.unwrap_or(overall_similarity);
 
     // Build response
     let analysis = AnalysisResult {
         files: FileComparison {
-            source: FileMetadata {
-                path: file1.path.clone(),
-                lines: file1.content.lines().count(),
-                functions: functions1.len(),
-                classes: count_classes_from_symbol_table(&semantic1.symbol_table),
-                complexity: calculate_complexity_from_symbol_table(&semantic1.symbol_table) as f64,
-            },
-            target: FileMetadata {
-                path: file2.path.clone(),
-                lines: file2.content.lines().count(),
-                functions: functions2.len(),
-                classes: count_classes_from_symbol_table(&semantic2.symbol_table),
-                complexity: calculate_complexity_from_symbol_table(&semantic2.symbol_table) as f64,
-            },
+            source,
+            target,
             language: language.to_string(),
             similarity: SimilarityScore {
                 overall: overall_similarity,
@@ -260,15 +593,73 @@ async fn perform_comparison(
             },
         },
         functions: build_function_analysis(&function_matches),
-        changes: build_change_analysis(&changes),
+        changes: build_change_analysis(&function_matches.changes),
         refactoring_patterns: build_refactoring_patterns(&refactoring_patterns),
-        structure: build_structure_comparison(&ast1, &ast2),
+        refactoring_summary: build_refactoring_summary(&refactoring_patterns),
+        structure: build_structure_comparison(),
     };
 
-    Ok(analysis)
+    Ok((analysis, truncated))
+}
+
+/// Average similarity of the paired functions of both versions, if any
+///
+/// Locals are told apart from calls and types with the symbol tables of both
+/// versions, so consistently renamed locals do not lower the score.
+fn calculate_semantic_similarity(
+    language: Language,
+    source: &CodeVersion,
+    target: &CodeVersion,
+    changes: &[smart_diff_parser::Change],
+) -> anyhow::Result<Option<f64>> {
+    let mut scorer = SimilarityScorer::with_defaults(language);
+    scorer.set_symbol_tables(source.symbols.clone(), target.symbols.clone());
+
+    let mut scores = Vec::new();
+    for (source_name, target_name) in source.function_pairs(target, changes) {
+        if let (Some((source_signature, source_ast)), Some((target_signature, target_ast))) = (
+            source.function_with_signature(source_name),
+            target.function_with_signature(target_name),
+        ) {
+            let score = scorer.calculate_comprehensive_similarity(
+                source_signature,
+                source_ast,
+                target_signature,
+                target_ast,
+            )?;
+            scores.push(score.overall_similarity);
+        }
+    }
+
+    Ok((!scores.is_empty()).then(|| scores.iter().sum::<f64>() / scores.len() as f64))
+}
+
+/// Refactoring detector honouring the requested minimum confidence
+fn build_refactoring_detector(language: Language, options: &CompareOptions) -> RefactoringDetector {
+    match options.min_refactoring_confidence {
+        Some(threshold) => RefactoringDetector::with_config(
+            language,
+            RefactoringDetectionConfig::with_min_confidence(threshold),
+        ),
+        None => RefactoringDetector::new(language),
+    }
 }
 
 /// Build function analysis from match result
+/// Similarity of two files by their matched functions
+///
+/// Zero when either file has no functions or matching didn't finish.
+fn match_similarity(
+    source: &FileMetadata,
+    target: &FileMetadata,
+    matches: Option<&smart_diff_parser::MatchResult>,
+) -> f64 {
+    match matches {
+        Some(matches) if source.functions > 0 && target.functions > 0 => matches.similarity,
+        _ => 0.0,
+    }
+}
+
 fn build_function_analysis(match_result: &smart_diff_parser::MatchResult) -> FunctionAnalysis {
     // Simplified function analysis based on MatchResult
     FunctionAnalysis {
@@ -343,11 +734,34 @@ fn build_refactoring_patterns(
         .collect()
 }
 
-/// Build structure comparison from ASTs
-fn build_structure_comparison(
-    _ast1: &smart_diff_parser::ASTNode,
-    _ast2: &smart_diff_parser::ASTNode,
-) -> StructureComparison {
+/// Count detected patterns per refactoring type
+fn build_refactoring_summary(
+    patterns: &[smart_diff_engine::RefactoringPattern],
+) -> Vec<RefactoringGroup> {
+    let mut groups: Vec<RefactoringGroup> = Vec::new();
+
+    for pattern in patterns {
+        let pattern_type = format!("{:?}", pattern.pattern_type);
+        match groups.iter_mut().find(|g| g.pattern_type == pattern_type) {
+            Some(group) => {
+                group.count += 1;
+                group.max_confidence = group.max_confidence.max(pattern.confidence);
+            }
+            None => groups.push(RefactoringGroup {
+                pattern_type,
+                count: 1,
+                max_confidence: pattern.confidence,
+            }),
+        }
+    }
+
+    // Stable sort keeps first-seen (most confident) order among equal counts
+    groups.sort_by_key(|group| std::cmp::Reverse(group.count));
+    groups
+}
+
+/// Build structure comparison
+fn build_structure_comparison() -> StructureComparison {
     // This would be implemented with actual AST traversal
     // For now, return a simplified structure
     StructureComparison {
@@ -371,8 +785,8 @@ fn build_structure_comparison(
 
 /// Multi-file analysis endpoint
 pub async fn analyze(
-    Json(request): Json<AnalyzeRequest>,
-) -> Result<ResponseJson<AnalyzeResponse>, StatusCode> {
+    ApiJson(request): ApiJson<AnalyzeRequest>,
+) -> Result<ResponseJson<AnalyzeResponse>, ApiError> {
     let start_time = Instant::now();
 
     tracing::info!("Received analyze request for {} files", request.files.len());
@@ -392,7 +806,7 @@ pub async fn analyze(
         }
         Err(e) => {
             tracing::error!("Multi-file analysis failed: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
@@ -408,6 +822,7 @@ async fn perform_multi_file_analysis(
 
     let mut file_results = Vec::new();
     let mut all_functions = Vec::new();
+    let mut clone_candidates = Vec::new();
     let mut total_complexity = 0.0;
 
     // Analyze each file
@@ -422,6 +837,10 @@ async fn perform_multi_file_analysis(
         };
         let parse_result = parser_engine.parse(&file.content, language)?;
         let semantic = semantic_analyzer.analyze(&parse_result)?;
+        clone_candidates.extend(
+            extract_functions_from_ast(&parse_result.ast, &file.path)
+                .map_err(|e| anyhow::anyhow!(e))?,
+        );
 
         let functions = extract_functions_from_symbol_table(&semantic.symbol_table);
         let complexity = calculate_complexity_from_symbol_table(&semantic.symbol_table);
@@ -490,7 +909,7 @@ async fn perform_multi_file_analysis(
     }
 
     // Cross-file analysis
-    let cross_file_analysis = perform_cross_file_analysis(&all_functions, files)?;
+    let cross_file_analysis = perform_cross_file_analysis(&clone_candidates);
 
     let summary = AnalysisSummary {
         total_files: files.len(),
@@ -516,8 +935,8 @@ async fn perform_multi_file_analysis(
 
 /// Configuration endpoint
 pub async fn configure(
-    Json(request): Json<ConfigRequest>,
-) -> Result<ResponseJson<ConfigResponse>, StatusCode> {
+    ApiJson(request): ApiJson<ConfigRequest>,
+) -> Result<ResponseJson<ConfigResponse>, ApiError> {
     tracing::info!("Received configuration update request");
 
     // In a real implementation, this would update the actual configuration
@@ -632,62 +1051,32 @@ fn detect_issues(symbol_table: &smart_diff_semantic::SymbolTable) -> Vec<String>
     vec![]
 }
 
-fn perform_cross_file_analysis(
-    functions: &[smart_diff_parser::Function],
-    _files: &[FileInfo],
-) -> anyhow::Result<CrossFileAnalysis> {
-    // Detect duplicate functions
-    let mut duplicate_functions = Vec::new();
-    let mut seen_signatures: HashMap<String, Vec<ChangeLocation>> = HashMap::new();
-
-    for function in functions {
-        let signature_str = format!(
-            "{}({})",
-            function.signature.name,
-            function
-                .signature
-                .parameters
-                .iter()
-                .map(|p| format!("{}: {}", p.name, p.param_type.name))
-                .collect::<Vec<_>>()
-                .join(", ")
-        );
-
-        if let Some(existing_locations) = seen_signatures.get_mut(&signature_str) {
-            existing_locations.push(ChangeLocation {
-                file: "unknown".to_string(), // Would need to track file association
-                start_line: function.location.start_line,
-                end_line: function.location.end_line,
-                function: Some(function.signature.name.clone()),
-            });
-        } else {
-            seen_signatures.insert(
-                signature_str,
-                vec![ChangeLocation {
-                    file: "unknown".to_string(),
-                    start_line: function.location.start_line,
-                    end_line: function.location.end_line,
-                    function: Some(function.signature.name.clone()),
-                }],
-            );
-        }
-    }
-
-    for (signature, locations) in seen_signatures {
-        if locations.len() > 1 {
-            duplicate_functions.push(DuplicateFunction {
-                signature,
-                locations,
-                similarity: 1.0, // Exact duplicates
-            });
-        }
-    }
+/// Clone clusters among the functions of all files, as duplicates
+fn perform_cross_file_analysis(functions: &[smart_diff_parser::Function]) -> CrossFileAnalysis {
+    let duplicate_functions = CloneDetector::default()
+        .detect(functions)
+        .into_iter()
+        .map(|cluster| DuplicateFunction {
+            signature: cluster.suggested_signature,
+            locations: cluster
+                .instances
+                .into_iter()
+                .map(|instance| ChangeLocation {
+                    file: instance.file_path,
+                    start_line: instance.start_line,
+                    end_line: instance.end_line,
+                    function: Some(instance.name),
+                })
+                .collect(),
+            similarity: cluster.similarity,
+        })
+        .collect();
 
-    Ok(CrossFileAnalysis {
+    CrossFileAnalysis {
         duplicate_functions,
         moved_functions: vec![],  // Would implement moved function detection
         dependency_graph: vec![], // Would implement dependency analysis
-    })
+    }
 }
 
 fn calculate_duplicate_rate(
@@ -721,6 +1110,7 @@ fn extract_functions_from_symbol_table(
             return_type: Some(Type::new("void".to_string())),
             modifiers: Vec::new(),
             generic_parameters: Vec::new(),
+            receiver: None,
         };
 
         let location = FunctionLocation {
@@ -746,6 +1136,8 @@ fn extract_functions_from_symbol_table(
             location,
             dependencies: Vec::new(),
             hash: format!("hash_{}", symbol.name),
+            element_type: smart_diff_parser::ElementType::Function,
+            condition: None,
         };
 
         functions.push(function);
@@ -776,8 +1168,8 @@ fn calculate_complexity_from_symbol_table(
 
 /// Browse directory contents
 pub async fn browse_directory(
-    Json(request): Json<BrowseDirectoryRequest>,
-) -> Result<ResponseJson<BrowseDirectoryResponse>, StatusCode> {
+    ApiJson(request): ApiJson<BrowseDirectoryRequest>,
+) -> Result<ResponseJson<BrowseDirectoryResponse>, ApiError> {
     let start_time = Instant::now();
 
     tracing::info!("Browsing directory: {}", request.path);
@@ -791,15 +1183,15 @@ pub async fn browse_directory(
         }
         Err(e) => {
             tracing::error!("Directory browse failed: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
 
 /// Read file content
 pub async fn read_file(
-    Json(request): Json<ReadFileRequest>,
-) -> Result<ResponseJson<ReadFileResponse>, StatusCode> {
+    ApiJson(request): ApiJson<ReadFileRequest>,
+) -> Result<ResponseJson<ReadFileResponse>, ApiError> {
     let start_time = Instant::now();
 
     tracing::info!("Reading file: {}", request.path);
@@ -813,15 +1205,15 @@ pub async fn read_file(
         }
         Err(e) => {
             tracing::error!("File read failed: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
 
 /// Read multiple files
 pub async fn read_multiple_files(
-    Json(request): Json<ReadMultipleFilesRequest>,
-) -> Result<ResponseJson<ReadMultipleFilesResponse>, StatusCode> {
+    ApiJson(request): ApiJson<ReadMultipleFilesRequest>,
+) -> Result<ResponseJson<ReadMultipleFilesResponse>, ApiError> {
     let start_time = Instant::now();
 
     tracing::info!("Reading {} files", request.paths.len());
@@ -835,15 +1227,15 @@ pub async fn read_multiple_files(
         }
         Err(e) => {
             tracing::error!("Multiple file read failed: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
 
 /// Search files
 pub async fn search_files(
-    Json(request): Json<SearchFilesRequest>,
-) -> Result<ResponseJson<SearchFilesResponse>, StatusCode> {
+    ApiJson(request): ApiJson<SearchFilesRequest>,
+) -> Result<ResponseJson<SearchFilesResponse>, ApiError> {
     let start_time = Instant::now();
 
     tracing::info!("Searching files with query: {}", request.query);
@@ -857,7 +1249,7 @@ pub async fn search_files(
         }
         Err(e) => {
             tracing::error!("File search failed: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
@@ -879,11 +1271,15 @@ async fn perform_directory_browse(
     let path = Path::new(&request.path);
 
     if !path.exists() {
-        return Err("Directory does not exist".into());
+        return Err(
+            ApiError::NotFound(format!("Directory does not exist: {}", request.path)).into(),
+        );
     }
 
     if !path.is_dir() {
-        return Err("Path is not a directory".into());
+        return Err(
+            ApiError::bad_request(format!("Path is not a directory: {}", request.path)).into(),
+        );
     }
 
     let mut entries = Vec::new();
@@ -1096,11 +1492,11 @@ async fn perform_file_read(
     let path = Path::new(&request.path);
 
     if !path.exists() {
-        return Err("File does not exist".into());
+        return Err(ApiError::NotFound(format!("File does not exist: {}", request.path)).into());
     }
 
     if !path.is_file() {
-        return Err("Path is not a file".into());
+        return Err(ApiError::bad_request(format!("Path is not a file: {}", request.path)).into());
     }
 
     let metadata = fs::metadata(path)?;
@@ -1109,7 +1505,11 @@ async fn perform_file_read(
     // Check file size limit
     if let Some(max_size) = request.max_size {
         if file_size > max_size as u64 {
-            return Err(format!("File too large: {} bytes (max: {})", file_size, max_size).into());
+            return Err(ApiError::bad_request(format!(
+                "File too large: {} bytes (max: {})",
+                file_size, max_size
+            ))
+            .into());
         }
     }
 
@@ -1184,7 +1584,9 @@ async fn perform_file_search(
 
     let root_path = Path::new(&request.root_path);
     if !root_path.exists() {
-        return Err("Root path does not exist".into());
+        return Err(
+            ApiError::NotFound(format!("Root path does not exist: {}", request.root_path)).into(),
+        );
     }
 
     search_directory_recursive(
@@ -1318,20 +1720,26 @@ fn search_in_text(text: &str, query: &str, case_sensitive: bool) -> bool {
     }
 }
 
-/// Find the column position of a match
+/// Find the 1-based column of a match, counted in characters rather than bytes
 fn find_match_column(text: &str, query: &str, case_sensitive: bool) -> Option<usize> {
-    let search_text = if case_sensitive {
-        text
-    } else {
-        &text.to_lowercase()
-    };
-    let search_query = if case_sensitive {
-        query
-    } else {
-        &query.to_lowercase()
-    };
+    if case_sensitive {
+        return text.find(query).map(|pos| text[..pos].chars().count() + 1);
+    }
+
+    // Lowercasing can change a character's length, so remember which original
+    // character each byte of the lowercased text came from
+    let mut lowered = String::with_capacity(text.len());
+    let mut char_indices = Vec::with_capacity(text.len());
+    for (index, c) in text.chars().enumerate() {
+        for lower in c.to_lowercase() {
+            lowered.push(lower);
+            char_indices.resize(lowered.len(), index);
+        }
+    }
 
-    search_text.find(search_query).map(|pos| pos + 1)
+    lowered
+        .find(&query.to_lowercase())
+        .map(|pos| char_indices.get(pos).map_or(1, |index| index + 1))
 }
 
 // ============================================================================
@@ -1339,7 +1747,7 @@ fn find_match_column(text: &str, query: &str, case_sensitive: bool) -> Option<us
 // ============================================================================
 
 /// Get home directory
-pub async fn get_home_directory() -> Result<ResponseJson<serde_json::Value>, StatusCode> {
+pub async fn get_home_directory() -> Result<ResponseJson<serde_json::Value>, ApiError> {
     match dirs::home_dir() {
         Some(home_path) => {
             let path_str = home_path.to_string_lossy().to_string();
@@ -1347,15 +1755,17 @@ pub async fn get_home_directory() -> Result<ResponseJson<serde_json::Value>, Sta
         }
         None => {
             tracing::warn!("Could not determine home directory");
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(ApiError::Internal(
+                "Could not determine home directory".to_string(),
+            ))
         }
     }
 }
 
 /// Compare two directories
 pub async fn compare_directories(
-    Json(request): Json<crate::models::CompareDirectoriesRequest>,
-) -> Result<ResponseJson<crate::models::CompareDirectoriesResponse>, StatusCode> {
+    ApiJson(request): ApiJson<crate::models::CompareDirectoriesRequest>,
+) -> Result<ResponseJson<crate::models::CompareDirectoriesResponse>, ApiError> {
     let start_time = Instant::now();
 
     tracing::info!(
@@ -1373,7 +1783,7 @@ pub async fn compare_directories(
         }
         Err(e) => {
             tracing::error!("Directory comparison failed: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
@@ -1385,64 +1795,124 @@ async fn perform_directory_comparison(
     use crate::models::*;
 
     // Scan source directory
-    let source_files = scan_directory_for_comparison(&request.source_path, &request.options)?;
-    let target_files = scan_directory_for_comparison(&request.target_path, &request.options)?;
+    let source_scan = scan_directory_for_comparison(&request.source_path, &request.options)?;
+    let target_scan = scan_directory_for_comparison(&request.target_path, &request.options)?;
+    let skipped_files = source_scan.skipped_files + target_scan.skipped_files;
+    let (source_files, target_files) = (source_scan.files, target_scan.files);
 
     tracing::info!(
         "Scanned directories: {} source files, {} target files",
         source_files.len(),
         target_files.len()
     );
+    if skipped_files > 0 {
+        warn!(
+            "Directory limits left {} files out of the comparison (max_depth {}, max_total_files {:?})",
+            skipped_files, request.options.max_depth, request.options.max_total_files
+        );
+    }
 
     // Analyze file changes
     let file_changes = analyze_file_changes(&source_files, &target_files);
 
+    // Identical files cannot contain changed functions, so in quiet mode
+    // they are not parsed at all
+    let (source_files, target_files) = if request.options.quiet_unchanged_files {
+        without_identical_files(source_files, target_files)
+    } else {
+        (source_files, target_files)
+    };
+
     // Extract and match functions
-    let function_matches = analyze_function_changes(
+    let mut function_matches = analyze_function_changes(
         &source_files,
         &target_files,
         request.options.similarity_threshold,
     )
     .await?;
 
-    // Generate summary
+    // Generate summary before filtering, so unchanged functions still count
     let summary = generate_comparison_summary(&file_changes, &function_matches);
+    if request.options.exclude_unchanged {
+        function_matches.retain(|m| !is_unchanged_match(m));
+    }
 
     Ok(CompareDirectoriesResponse {
         summary,
         file_changes,
         function_matches,
         execution_time_ms: 0, // Will be set by caller
+        truncated: skipped_files > 0,
+        skipped_files,
     })
 }
 
+/// Whether a function match reports no change to the function
+fn is_unchanged_match(function_match: &crate::models::FunctionMatch) -> bool {
+    function_match.match_type == "unchanged" || function_match.match_type == "identical"
+}
+
 /// Scan directory for comparison
+///
+/// Entries are visited in file name order, so the files kept under
+/// `max_total_files` are the same on every run. Matching files past either
+/// limit are counted rather than read.
 fn scan_directory_for_comparison(
     dir_path: &str,
     options: &crate::models::DirectoryCompareOptions,
-) -> Result<Vec<ComparisonFileInfo>, Box<dyn std::error::Error + Send + Sync>> {
+) -> Result<DirectoryScan, Box<dyn std::error::Error + Send + Sync>> {
     use std::time::SystemTime;
     use walkdir::WalkDir;
 
     let mut files = Vec::new();
+    let mut skipped_files = 0;
     let base_path = Path::new(dir_path);
 
     if !base_path.exists() {
-        return Err(format!("Directory does not exist: {}", dir_path).into());
+        return Err(ApiError::NotFound(format!("Directory does not exist: {}", dir_path)).into());
     }
 
-    for entry in WalkDir::new(base_path)
-        .max_depth(options.max_depth)
+    // Canonical paths already scanned, so a symlink cycle or a second link to
+    // the same directory or file is visited only once
+    let mut visited_dirs = std::collections::HashSet::new();
+    let mut visited_files = std::collections::HashSet::new();
+
+    let walker = WalkDir::new(base_path)
+        .sort_by_file_name()
+        .follow_links(options.follow_symlinks)
         .into_iter()
-        .filter_map(|e| e.ok())
-    {
+        .filter_entry(|entry| {
+            if !entry.file_type().is_dir() {
+                return true;
+            }
+            match entry.path().canonicalize() {
+                Ok(canonical) => visited_dirs.insert(canonical),
+                Err(_) => false,
+            }
+        });
+
+    for entry in walker.filter_map(|e| e.ok()) {
         let path = entry.path();
 
+        if entry.path_is_symlink() && !options.follow_symlinks {
+            info!("Skipping symbolic link: {}", path.display());
+            continue;
+        }
+
         // Skip directories
-        if path.is_dir() {
+        if entry.file_type().is_dir() {
             continue;
         }
 
+        if options.follow_symlinks {
+            let first_visit = path
+                .canonicalize()
+                .is_ok_and(|canonical| visited_files.insert(canonical));
+            if !first_visit {
+                continue;
+            }
+        }
+
         // Skip hidden files if not requested
         if !options.include_hidden {
             if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
@@ -1467,6 +1937,15 @@ fn scan_directory_for_comparison(
             }
         }
 
+        let beyond_limits = entry.depth() > options.max_depth
+            || options
+                .max_total_files
+                .is_some_and(|max_total_files| files.len() >= max_total_files);
+        if beyond_limits {
+            skipped_files += 1;
+            continue;
+        }
+
         // Read file content
         if let Ok(content) = fs::read_to_string(path) {
             let relative_path = path
@@ -1479,6 +1958,7 @@ fn scan_directory_for_comparison(
             let file_info = ComparisonFileInfo {
                 path: path.to_string_lossy().to_string(),
                 relative_path,
+                content_hash: blake3::hash(content.as_bytes()),
                 content,
                 size: metadata.len(),
                 modified: metadata
@@ -1501,7 +1981,17 @@ fn scan_directory_for_comparison(
         }
     }
 
-    Ok(files)
+    Ok(DirectoryScan {
+        files,
+        skipped_files,
+    })
+}
+
+/// Files found by a directory scan
+struct DirectoryScan {
+    files: Vec<ComparisonFileInfo>,
+    /// Matching files deeper than `max_depth` or past `max_total_files`
+    skipped_files: usize,
 }
 
 /// File info for comparison
@@ -1510,6 +2000,8 @@ struct ComparisonFileInfo {
     path: String,
     relative_path: String,
     content: String,
+    /// BLAKE3 hash of `content`, equal only for byte-identical files
+    content_hash: blake3::Hash,
     size: u64,
     modified: Option<String>,
     language: Option<String>,
@@ -1545,8 +2037,13 @@ fn analyze_file_changes(
     // Check source files for deletions and modifications
     for source_file in source_files {
         if let Some(target_file) = target_map.remove(&source_file.relative_path) {
-            // File exists in both - check if modified
-            let similarity = calculate_file_similarity(&source_file.content, &target_file.content);
+            // File exists in both - check if modified, comparing lines only
+            // when the content hashes differ
+            let similarity = if source_file.content_hash == target_file.content_hash {
+                1.0
+            } else {
+                calculate_file_similarity(&source_file.content, &target_file.content)
+            };
 
             let change_type = if similarity >= 0.99 {
                 "unchanged"
@@ -1584,14 +2081,43 @@ fn analyze_file_changes(
     changes
 }
 
+/// Drop the files whose content is identical at the same relative path on both sides
+fn without_identical_files(
+    source_files: Vec<ComparisonFileInfo>,
+    target_files: Vec<ComparisonFileInfo>,
+) -> (Vec<ComparisonFileInfo>, Vec<ComparisonFileInfo>) {
+    use std::collections::{HashMap, HashSet};
+
+    let source_hashes: HashMap<&str, blake3::Hash> = source_files
+        .iter()
+        .map(|file| (file.relative_path.as_str(), file.content_hash))
+        .collect();
+    let identical: HashSet<String> = target_files
+        .iter()
+        .filter(|file| source_hashes.get(file.relative_path.as_str()) == Some(&file.content_hash))
+        .map(|file| file.relative_path.clone())
+        .collect();
+    if !identical.is_empty() {
+        tracing::info!("Skipping {} identical files", identical.len());
+    }
+
+    let changed = |files: Vec<ComparisonFileInfo>| {
+        files
+            .into_iter()
+            .filter(|file| !identical.contains(&file.relative_path))
+            .collect()
+    };
+    (changed(source_files), changed(target_files))
+}
+
 /// Calculate simple file similarity
 fn calculate_file_similarity(content1: &str, content2: &str) -> f64 {
     if content1 == content2 {
         return 1.0;
     }
 
-    let lines1: Vec<&str> = content1.lines().collect();
-    let lines2: Vec<&str> = content2.lines().collect();
+    let lines1 = source_lines(content1);
+    let lines2 = source_lines(content2);
 
     if lines1.is_empty() && lines2.is_empty() {
         return 1.0;
@@ -1608,27 +2134,68 @@ fn calculate_file_similarity(content1: &str, content2: &str) -> f64 {
     common_lines as f64 / total_lines as f64
 }
 
-/// Analyze function changes between directories using advanced AST-based matching
-async fn analyze_function_changes(
-    source_files: &[ComparisonFileInfo],
-    target_files: &[ComparisonFileInfo],
-    similarity_threshold: f64,
-) -> Result<Vec<crate::models::FunctionMatch>, Box<dyn std::error::Error + Send + Sync>> {
-    use crate::models::{FunctionMatch, SimilarityScore};
-    use smart_diff_engine::{SmartMatcher, SmartMatcherConfig};
-    use smart_diff_parser::{
-        tree_sitter::TreeSitterParser, Function, Language, LanguageDetector, Parser,
-    };
-    use std::collections::HashMap;
+#[cfg(test)]
+thread_local! {
+    /// Files parsed by [`extract_functions_from_files`] on this thread
+    static FILES_PARSED: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
 
-    let mut matches = Vec::new();
+/// Parse the files in a language with a grammar and extract their functions
+fn extract_functions_from_files(
+    parser: &smart_diff_parser::tree_sitter::TreeSitterParser,
+    files: &[ComparisonFileInfo],
+) -> Result<Vec<smart_diff_parser::Function>, Box<dyn std::error::Error + Send + Sync>> {
+    use smart_diff_parser::Parser;
 
-    // Create parser with same config as MCP server
-    let parser = TreeSitterParser::builder()
-        .max_text_length(1_000_000)
-        .include_comments(true)
-        .extract_signatures(true)
-        .build_symbol_table(true)
+    let mut functions = Vec::new();
+    for file in files {
+        let Some(language_str) = &file.language else {
+            continue;
+        };
+        let language = match language_str.to_lowercase().as_str() {
+            "javascript" => Language::JavaScript,
+            "typescript" => Language::TypeScript,
+            "python" => Language::Python,
+            "java" => Language::Java,
+            "c" => Language::C,
+            "cpp" | "c++" => Language::Cpp,
+            "rust" => Language::Rust,
+            _ => Language::Unknown,
+        };
+        if language == Language::Unknown {
+            continue;
+        }
+
+        #[cfg(test)]
+        FILES_PARSED.with(|count| count.set(count.get() + 1));
+        if let Ok(parse_result) = parser.parse(&file.content, language) {
+            functions.extend(extract_functions_from_ast(&parse_result.ast, &file.path)?);
+        }
+    }
+    Ok(functions)
+}
+
+/// Analyze function changes between directories using advanced AST-based matching
+async fn analyze_function_changes(
+    source_files: &[ComparisonFileInfo],
+    target_files: &[ComparisonFileInfo],
+    similarity_threshold: f64,
+) -> Result<Vec<crate::models::FunctionMatch>, Box<dyn std::error::Error + Send + Sync>> {
+    use crate::models::{FunctionMatch, SimilarityScore};
+    use smart_diff_engine::{CrossFilePenaltyCurve, SmartMatcher, SmartMatcherConfig};
+    use smart_diff_parser::{
+        tree_sitter::TreeSitterParser, Function, Language, LanguageDetector, Parser,
+    };
+    use std::collections::HashMap;
+
+    let mut matches = Vec::new();
+
+    // Create parser with same config as MCP server
+    let parser = TreeSitterParser::builder()
+        .max_text_length(1_000_000)
+        .include_comments(true)
+        .extract_signatures(true)
+        .build_symbol_table(true)
         .enable_optimization(true)
         .enable_analysis(false)
         .build()
@@ -1638,9 +2205,14 @@ async fn analyze_function_changes(
     let config = SmartMatcherConfig {
         similarity_threshold,
         enable_cross_file_matching: true,
-        cross_file_penalty: 0.5,
+        cross_file_penalty_fn: CrossFilePenaltyCurve::default(),
+        enable_exact_match_prepass: true,
+        normalize_identifiers: false,
+        content_similarity: Default::default(),
     };
     let smart_matcher = SmartMatcher::new(config);
+    let classification_config = classification_config();
+    let mut match_ids = MatchIdGenerator::default();
 
     // Build file content lookup maps
     let mut source_file_contents: HashMap<String, String> = HashMap::new();
@@ -1654,53 +2226,9 @@ async fn analyze_function_changes(
         target_file_contents.insert(file.path.clone(), file.content.clone());
     }
 
-    // Parse source files and extract functions
-    let mut source_functions: Vec<Function> = Vec::new();
-    for file in source_files {
-        if let Some(language_str) = &file.language {
-            let language = match language_str.to_lowercase().as_str() {
-                "javascript" => Language::JavaScript,
-                "typescript" => Language::TypeScript,
-                "python" => Language::Python,
-                "java" => Language::Java,
-                "c" => Language::C,
-                "cpp" | "c++" => Language::Cpp,
-                "rust" => Language::Rust,
-                _ => Language::Unknown,
-            };
-
-            if language != Language::Unknown {
-                if let Ok(parse_result) = parser.parse(&file.content, language) {
-                    let file_functions = extract_functions_from_ast(&parse_result.ast, &file.path)?;
-                    source_functions.extend(file_functions);
-                }
-            }
-        }
-    }
-
-    // Parse target files and extract functions
-    let mut target_functions: Vec<Function> = Vec::new();
-    for file in target_files {
-        if let Some(language_str) = &file.language {
-            let language = match language_str.to_lowercase().as_str() {
-                "javascript" => Language::JavaScript,
-                "typescript" => Language::TypeScript,
-                "python" => Language::Python,
-                "java" => Language::Java,
-                "c" => Language::C,
-                "cpp" | "c++" => Language::Cpp,
-                "rust" => Language::Rust,
-                _ => Language::Unknown,
-            };
-
-            if language != Language::Unknown {
-                if let Ok(parse_result) = parser.parse(&file.content, language) {
-                    let file_functions = extract_functions_from_ast(&parse_result.ast, &file.path)?;
-                    target_functions.extend(file_functions);
-                }
-            }
-        }
-    }
+    // Parse source and target files and extract functions
+    let source_functions = extract_functions_from_files(&parser, source_files)?;
+    let target_functions = extract_functions_from_files(&parser, target_files)?;
 
     tracing::info!(
         "Extracted functions using AST: {} source, {} target",
@@ -1722,10 +2250,11 @@ async fn analyze_function_changes(
 
         if let (Some(source), Some(target)) = (&change.source, &change.target) {
             // Matched function (modified, moved, or renamed)
-            let change_type = determine_change_type(source, target, similarity);
+            let change_type =
+                determine_change_type(source, target, similarity, &classification_config);
 
             matches.push(FunctionMatch {
-                id: uuid::Uuid::new_v4().to_string(),
+                id: match_ids.next(Some(source), Some(target)),
                 source_function: Some(convert_function_to_info(source, &source_file_contents)),
                 target_function: Some(convert_function_to_info(target, &target_file_contents)),
                 similarity: SimilarityScore {
@@ -1740,7 +2269,7 @@ async fn analyze_function_changes(
         } else if let Some(source) = &change.source {
             // Deleted function
             matches.push(FunctionMatch {
-                id: uuid::Uuid::new_v4().to_string(),
+                id: match_ids.next(Some(source), None),
                 source_function: Some(convert_function_to_info(source, &source_file_contents)),
                 target_function: None,
                 similarity: SimilarityScore {
@@ -1755,7 +2284,7 @@ async fn analyze_function_changes(
         } else if let Some(target) = &change.target {
             // Added function
             matches.push(FunctionMatch {
-                id: uuid::Uuid::new_v4().to_string(),
+                id: match_ids.next(None, Some(target)),
                 source_function: None,
                 target_function: Some(convert_function_to_info(target, &target_file_contents)),
                 similarity: SimilarityScore {
@@ -1778,6 +2307,69 @@ async fn analyze_function_changes(
     Ok(matches)
 }
 
+/// Deterministic ids for function matches
+///
+/// Ids are derived from the matched elements' file paths, names and line
+/// ranges, so comparing the same inputs twice yields the same ids and reports
+/// can be diffed or cached. The key is hashed with 128-bit FNV-1a, which is
+/// stable across Rust versions unlike `DefaultHasher`, and formatted as a
+/// version 8 UUID. A key seen twice in one comparison gets an occurrence
+/// suffix so distinct matches never share an id.
+#[derive(Debug, Default)]
+struct MatchIdGenerator {
+    occurrences: HashMap<u128, usize>,
+}
+
+impl MatchIdGenerator {
+    const FNV_OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const FNV_PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    fn next(
+        &mut self,
+        source: Option<&smart_diff_parser::CodeElement>,
+        target: Option<&smart_diff_parser::CodeElement>,
+    ) -> String {
+        let mut key = Vec::new();
+        for element in [source, target] {
+            match element {
+                Some(element) => {
+                    for field in [
+                        element.file_path.as_str(),
+                        element.name.as_str(),
+                        &element.start_line.to_string(),
+                        &element.end_line.to_string(),
+                    ] {
+                        key.extend_from_slice(field.as_bytes());
+                        key.push(0);
+                    }
+                }
+                None => key.push(1),
+            }
+        }
+
+        let hash = Self::fnv1a(&key);
+        let occurrence = self.occurrences.entry(hash).or_insert(0);
+        *occurrence += 1;
+        let hash = match *occurrence {
+            1 => hash,
+            n => {
+                key.extend_from_slice(n.to_string().as_bytes());
+                Self::fnv1a(&key)
+            }
+        };
+
+        uuid::Builder::from_custom_bytes(hash.to_be_bytes())
+            .into_uuid()
+            .to_string()
+    }
+
+    fn fnv1a(bytes: &[u8]) -> u128 {
+        bytes.iter().fold(Self::FNV_OFFSET_BASIS, |hash, &byte| {
+            (hash ^ u128::from(byte)).wrapping_mul(Self::FNV_PRIME)
+        })
+    }
+}
+
 /// Extract functions from an AST (same approach as MCP server)
 fn extract_functions_from_ast(
     ast: &smart_diff_parser::ASTNode,
@@ -1792,15 +2384,12 @@ fn extract_functions_from_ast(
     let method_nodes = ast.find_by_type(&NodeType::Method);
 
     for node in function_nodes.iter().chain(method_nodes.iter()) {
-        // Skip function declarators (just signatures without bodies)
-        if let Some(kind) = node.metadata.attributes.get("kind") {
-            if kind == "function_declarator" {
-                continue;
-            }
+        if node.is_function_declarator() {
+            continue;
         }
 
         if let Some(name) = node.metadata.attributes.get("name") {
-            let signature = FunctionSignature::new(name.clone());
+            let signature = FunctionSignature::from_node(name.clone(), node);
             let function = Function::new(signature, (*node).clone(), file_path.to_string());
             functions.push(function);
         }
@@ -1816,16 +2405,17 @@ fn convert_function_to_info(
 ) -> FunctionInfo {
     // Extract function content from file
     let content = if let Some(file_content) = file_contents.get(&element.file_path) {
-        let lines: Vec<&str> = file_content.lines().collect();
-        if element.start_line > 0 && element.end_line <= lines.len() {
-            let extracted = lines[(element.start_line - 1)..element.end_line].join("\n");
+        let line_count = source_lines(file_content).len();
+        if element.start_line > 0 && element.start_line <= line_count {
+            let extracted =
+                extract_content_from_lines(file_content, element.start_line, element.end_line);
             tracing::debug!(
                 "Extracting function '{}' from lines {}-{} (total {} lines): {} chars",
                 element.name,
                 element.start_line,
                 element.end_line,
                 element.end_line - element.start_line + 1,
-                extracted.len()
+                extracted.chars().count()
             );
             extracted
         } else {
@@ -1834,7 +2424,7 @@ fn convert_function_to_info(
                 element.name,
                 element.start_line,
                 element.end_line,
-                lines.len()
+                line_count
             );
             String::new()
         }
@@ -1863,11 +2453,23 @@ fn convert_function_to_info(
     }
 }
 
+/// Thresholds the directory comparison classifies matched functions with
+///
+/// Renames need a similarity of 0.85 here, above the engine's default of 0.8,
+/// as the web UI has always asked of them.
+fn classification_config() -> ChangeClassificationConfig {
+    ChangeClassificationConfig {
+        rename_min_similarity: 0.85,
+        ..Default::default()
+    }
+}
+
 /// Determine change type based on function properties and similarity
 fn determine_change_type(
     source: &smart_diff_parser::CodeElement,
     target: &smart_diff_parser::CodeElement,
     similarity: f64,
+    config: &ChangeClassificationConfig,
 ) -> String {
     use std::path::Path;
 
@@ -1884,440 +2486,75 @@ fn determine_change_type(
     let is_cross_file_move = source_filename != target_filename;
     let is_renamed = source.name != target.name;
 
-    if similarity >= 0.99 {
+    if similarity >= config.identical_min_similarity {
         "identical".to_string()
     } else if is_cross_file_move {
         "moved".to_string()
-    } else if is_renamed && similarity >= 0.85 {
+    } else if is_renamed && similarity >= config.rename_min_similarity {
         "renamed".to_string()
     } else {
         "modified".to_string()
     }
 }
 
-/// Extract functions from files
-async fn extract_functions_from_files(
-    files: &[ComparisonFileInfo],
-) -> Result<Vec<FunctionInfo>, Box<dyn std::error::Error + Send + Sync>> {
-    let mut all_functions = Vec::new();
-
-    for file in files {
-        if let Some(language_str) = &file.language {
-            tracing::info!(
-                "Extracting functions from {} (language: {})",
-                file.relative_path,
-                language_str
-            );
-            // Simple function extraction using regex patterns
-            let functions =
-                extract_functions_simple(&file.content, language_str, &file.relative_path);
-            tracing::info!(
-                "Extracted {} functions from {}",
-                functions.len(),
-                file.relative_path
+/// Functions of a file with their exact source text, as extracted by the parser
+fn extract_function_infos(content: &str, language: Language, file_path: &str) -> Vec<FunctionInfo> {
+    let parse_result = match TreeSitterParser::new()
+        .map_err(|e| e.to_string())
+        .and_then(|parser| parser.parse(content, language).map_err(|e| e.to_string()))
+    {
+        Ok(parse_result) => parse_result,
+        Err(e) => {
+            warn!(
+                "Failed to parse {} for function extraction: {}",
+                file_path, e
             );
-            all_functions.extend(functions);
-        } else {
-            tracing::warn!("No language detected for file: {}", file.relative_path);
-        }
-    }
-
-    Ok(all_functions)
-}
-
-/// Simple function extraction using regex
-fn extract_functions_simple(content: &str, language: &str, file_path: &str) -> Vec<FunctionInfo> {
-    let mut functions = Vec::new();
-
-    // Use specialized extractors for C/C++ to handle multi-line signatures
-    match language.to_lowercase().as_str() {
-        "c" => return extract_c_functions(content, file_path),
-        "cpp" | "c++" => return extract_cpp_functions(content, file_path),
-        _ => {}
-    }
-
-    let lines: Vec<&str> = content.lines().collect();
-
-    // Simple regex patterns for other languages
-    let pattern = match language.to_lowercase().as_str() {
-        "javascript" | "typescript" => {
-            r"(?:function\s+(\w+)|const\s+(\w+)\s*=.*=>|(\w+)\s*\([^)]*\)\s*\{)"
-        }
-        "python" => r"def\s+(\w+)\s*\(",
-        "java" => {
-            r"(?:public|private|protected)?\s*(?:static\s+)?(?:\w+\s+)+(\w+)\s*\([^)]*\)\s*\{"
+            return Vec::new();
         }
-        "rust" => r"fn\s+(\w+)\s*\(",
-        _ => return functions,
     };
 
-    if let Ok(regex) = regex::Regex::new(pattern) {
-        for (line_num, line) in lines.iter().enumerate() {
-            if let Some(captures) = regex.captures(line) {
-                if let Some(name_match) = captures
-                    .get(1)
-                    .or_else(|| captures.get(2))
-                    .or_else(|| captures.get(3))
-                {
-                    let function_name = name_match.as_str().to_string();
-
-                    // Skip common keywords
-                    if !["if", "for", "while", "switch", "return"].contains(&function_name.as_str())
-                    {
-                        // Extract function content by finding the function body
-                        let (end_line, function_content) =
-                            extract_function_body(&lines, line_num, language);
-
-                        functions.push(FunctionInfo {
-                            name: function_name.clone(),
-                            signature: line.trim().to_string(),
-                            start_line: line_num + 1,
-                            end_line,
-                            complexity: 1,
-                            parameters: Vec::new(), // Simplified
-                            return_type: "unknown".to_string(),
-                            content: function_content,
-                            file_path: file_path.to_string(),
-                        });
-                    }
-                }
-            }
-        }
-    }
-
-    functions
-}
-
-/// Extract C functions with better handling of multi-line signatures and K&R style
-fn extract_c_functions(content: &str, file_path: &str) -> Vec<FunctionInfo> {
-    let mut functions = Vec::new();
-    let lines: Vec<&str> = content.lines().collect();
-
-    // Pattern to match C function signatures (more flexible)
-    // Matches: [static] [inline] [const] return_type [*] function_name (
-    let func_pattern = regex::Regex::new(
-        r"^(?:static\s+)?(?:inline\s+)?(?:const\s+)?(?:unsigned\s+)?(?:signed\s+)?(?:struct\s+)?(?:enum\s+)?(\w+)\s+(\**)(\w+)\s*\("
-    ).unwrap();
-
-    let mut i = 0;
-    while i < lines.len() {
-        let line = lines[i].trim();
-
-        // Skip preprocessor directives, comments, and empty lines
-        if line.starts_with('#')
-            || line.starts_with("//")
-            || line.starts_with("/*")
-            || line.is_empty()
-        {
-            i += 1;
-            continue;
-        }
-
-        // Try to match function signature
-        if let Some(captures) = func_pattern.captures(line) {
-            let return_type = captures.get(1).map(|m| m.as_str()).unwrap_or("void");
-            let function_name = captures
-                .get(3)
-                .map(|m| m.as_str().to_string())
-                .unwrap_or_default();
-
-            // Skip common keywords and type definitions
-            if [
-                "if", "for", "while", "switch", "return", "sizeof", "typedef",
-            ]
-            .contains(&function_name.as_str())
-            {
-                i += 1;
-                continue;
-            }
-
-            // Build full signature (may span multiple lines)
-            let mut signature = String::new();
-            let mut sig_line = i;
-            let mut found_closing_paren = false;
-
-            while sig_line < lines.len() && sig_line < i + 10 {
-                let current_line = lines[sig_line].trim();
-                signature.push_str(current_line);
-                signature.push(' ');
-
-                if current_line.contains(')') {
-                    found_closing_paren = true;
-                    break;
-                }
-                sig_line += 1;
-            }
-
-            if !found_closing_paren {
-                i += 1;
-                continue;
-            }
-
-            // Find the opening brace (may be on next line for K&R style)
-            let mut brace_line = sig_line;
-            let mut found_brace = false;
-
-            while brace_line < lines.len() && brace_line < sig_line + 20 {
-                let current_line = lines[brace_line].trim();
-
-                // Skip old K&R style parameter declarations
-                if current_line.contains('{') {
-                    found_brace = true;
-                    break;
-                }
-
-                // If we hit a semicolon, it's a declaration, not a definition
-                if current_line.ends_with(';') {
-                    break;
-                }
-
-                brace_line += 1;
-            }
-
-            if found_brace {
-                // Extract function body
-                let (end_line, function_content) = extract_function_body(&lines, brace_line, "c");
-
-                functions.push(FunctionInfo {
-                    name: function_name.clone(),
-                    signature: signature.trim().to_string(),
-                    start_line: i + 1,
-                    end_line,
-                    complexity: 1,
-                    parameters: Vec::new(),
-                    return_type: return_type.to_string(),
-                    content: function_content,
-                    file_path: file_path.to_string(),
-                });
-
-                // Skip to end of function
-                i = end_line;
-            } else {
-                i += 1;
-            }
-        } else {
-            i += 1;
-        }
-    }
-
-    tracing::info!(
-        "Extracted {} C functions from {}",
-        functions.len(),
-        file_path
-    );
-    functions
-}
-
-/// Extract C++ functions with better handling
-fn extract_cpp_functions(content: &str, file_path: &str) -> Vec<FunctionInfo> {
-    let mut functions = Vec::new();
-    let lines: Vec<&str> = content.lines().collect();
-
-    // Pattern for C++ functions (includes access modifiers and templates)
-    let func_pattern = regex::Regex::new(
-        r"^(?:public|private|protected)?\s*:?\s*(?:static\s+)?(?:virtual\s+)?(?:inline\s+)?(?:const\s+)?(?:unsigned\s+)?(?:signed\s+)?(?:struct\s+)?(?:class\s+)?(\w+(?:<[^>]+>)?)\s+(\**)(\w+)\s*\("
-    ).unwrap();
-
-    let mut i = 0;
-    while i < lines.len() {
-        let line = lines[i].trim();
-
-        // Skip preprocessor, comments, empty lines
-        if line.starts_with('#')
-            || line.starts_with("//")
-            || line.starts_with("/*")
-            || line.is_empty()
-        {
-            i += 1;
-            continue;
-        }
-
-        if let Some(captures) = func_pattern.captures(line) {
-            let return_type = captures.get(1).map(|m| m.as_str()).unwrap_or("void");
-            let function_name = captures
-                .get(3)
-                .map(|m| m.as_str().to_string())
-                .unwrap_or_default();
-
-            if ["if", "for", "while", "switch", "return", "sizeof"]
-                .contains(&function_name.as_str())
-            {
-                i += 1;
-                continue;
-            }
-
-            // Build signature
-            let mut signature = String::new();
-            let mut sig_line = i;
-            let mut found_closing_paren = false;
-
-            while sig_line < lines.len() && sig_line < i + 10 {
-                let current_line = lines[sig_line].trim();
-                signature.push_str(current_line);
-                signature.push(' ');
-
-                if current_line.contains(')') {
-                    found_closing_paren = true;
-                    break;
-                }
-                sig_line += 1;
-            }
-
-            if !found_closing_paren {
-                i += 1;
-                continue;
-            }
-
-            // Find opening brace
-            let mut brace_line = sig_line;
-            let mut found_brace = false;
-
-            while brace_line < lines.len() && brace_line < sig_line + 5 {
-                let current_line = lines[brace_line].trim();
-
-                if current_line.contains('{') {
-                    found_brace = true;
-                    break;
-                }
-
-                if current_line.ends_with(';') {
-                    break;
-                }
-
-                brace_line += 1;
-            }
-
-            if found_brace {
-                let (end_line, function_content) = extract_function_body(&lines, brace_line, "cpp");
-
-                functions.push(FunctionInfo {
-                    name: function_name.clone(),
-                    signature: signature.trim().to_string(),
-                    start_line: i + 1,
-                    end_line,
-                    complexity: 1,
-                    parameters: Vec::new(),
-                    return_type: return_type.to_string(),
-                    content: function_content,
-                    file_path: file_path.to_string(),
-                });
-
-                i = end_line;
-            } else {
-                i += 1;
-            }
-        } else {
-            i += 1;
-        }
-    }
-
-    tracing::info!(
-        "Extracted {} C++ functions from {}",
-        functions.len(),
-        file_path
-    );
-    functions
-}
-
-/// Extract function body content from lines starting at the given line
-fn extract_function_body(lines: &[&str], start_line: usize, language: &str) -> (usize, String) {
-    let mut content = Vec::new();
-    let mut brace_count = 0;
-    let mut in_function = false;
-    let mut end_line = start_line + 1;
-
-    // Different languages have different block delimiters
-    let (open_char, close_char) = match language.to_lowercase().as_str() {
-        "python" => {
-            // For Python, we need to handle indentation-based blocks
-            return extract_python_function_body(lines, start_line);
-        }
-        _ => ('{', '}'), // Most C-style languages
-    };
-
-    for (i, line) in lines.iter().enumerate().skip(start_line) {
-        content.push(line.to_string());
-        end_line = i + 1;
-
-        // Count braces to find function end
-        for ch in line.chars() {
-            if ch == open_char {
-                brace_count += 1;
-                in_function = true;
-            } else if ch == close_char && in_function {
-                brace_count -= 1;
-                if brace_count == 0 {
-                    return (end_line, content.join("\n"));
-                }
+    extract_functions_from_ast(&parse_result.ast, file_path)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|function| {
+            let content = function.body_text(content).unwrap_or_default().to_string();
+            let attributes = &function.body.metadata.attributes;
+            FunctionInfo {
+                name: function.signature.name.clone(),
+                signature: content
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+                start_line: function.location.start_line,
+                end_line: function.location.end_line,
+                // One path through the function plus one per branch or loop
+                complexity: 1 + function.complexity_score(),
+                parameters: attributes
+                    .get("parameter_names")
+                    .map(|names| names.split(';').map(str::to_string).collect())
+                    .unwrap_or_default(),
+                return_type: attributes
+                    .get("return_type")
+                    .cloned()
+                    .unwrap_or_else(|| "unknown".to_string()),
+                content,
+                file_path: file_path.to_string(),
             }
-        }
-
-        // Safety limit to prevent infinite loops
-        if i - start_line > 100 {
-            break;
-        }
-    }
-
-    (end_line, content.join("\n"))
-}
-
-/// Extract Python function body based on indentation
-fn extract_python_function_body(lines: &[&str], start_line: usize) -> (usize, String) {
-    let mut content = Vec::new();
-    let mut end_line = start_line + 1;
-
-    if start_line >= lines.len() {
-        return (start_line + 1, String::new());
-    }
-
-    // Get the indentation level of the function definition
-    let def_line = lines[start_line];
-    let def_indent = def_line.len() - def_line.trim_start().len();
-
-    content.push(def_line.to_string());
-
-    // Look for the function body (next lines with greater indentation)
-    for (i, line) in lines.iter().enumerate().skip(start_line + 1) {
-        if line.trim().is_empty() {
-            content.push(line.to_string());
-            continue;
-        }
-
-        let line_indent = line.len() - line.trim_start().len();
-
-        // If we find a line with same or less indentation, function is done
-        if line_indent <= def_indent {
-            break;
-        }
-
-        content.push(line.to_string());
-        end_line = i + 1;
-
-        // Safety limit
-        if i - start_line > 100 {
-            break;
-        }
-    }
-
-    (end_line, content.join("\n"))
+        })
+        .collect()
 }
 
 /// Extract content from file content using line numbers (1-based)
+///
+/// Lines end at `\n`, `\r\n` or a lone `\r`, as they do for the parser, so
+/// the extracted text never carries stray carriage returns.
 fn extract_content_from_lines(file_content: &str, start_line: usize, end_line: usize) -> String {
-    let lines: Vec<&str> = file_content.lines().collect();
-
-    if start_line == 0 || start_line > lines.len() {
+    if start_line == 0 {
         return String::new();
     }
-
-    let start_idx = start_line - 1; // Convert to 0-based
-    let end_idx = std::cmp::min(end_line, lines.len());
-
-    if start_idx >= end_idx {
-        return String::new();
-    }
-
-    lines[start_idx..end_idx].join("\n")
+    line_range_text(file_content, start_line, end_line)
 }
 
 /// Calculate function similarity with smart matching rules
@@ -2331,13 +2568,13 @@ fn calculate_function_similarity(func1: &FunctionInfo, func2: &FunctionInfo) ->
         if func1.content == func2.content {
             return 1.0; // Identical
         } else {
-            let content_sim = calculate_content_similarity_fast(&func1.content, &func2.content);
+            let content_sim = calculate_content_similarity(&func1.content, &func2.content);
             return 0.7 + (content_sim * 0.3); // Minimum 70% for same name, up to 100%
         }
     }
 
     // Rule 2: Don't match simple functions unless they're identical
-    if is_simple_function(&func1.content) || is_simple_function(&func2.content) {
+    if is_simple_function(func1) || is_simple_function(func2) {
         if func1.content == func2.content && same_name {
             return 1.0; // Only match if identical
         } else {
@@ -2354,7 +2591,7 @@ fn calculate_function_similarity(func1: &FunctionInfo, func2: &FunctionInfo) ->
     if same_name {
         score += name_weight;
     } else {
-        let name_sim = calculate_simple_string_similarity(&func1.name, &func2.name);
+        let name_sim = calculate_word_similarity(&func1.name, &func2.name);
         score += name_weight * name_sim * 0.5; // Reduced credit for similar names
     }
     weight += name_weight;
@@ -2364,7 +2601,7 @@ fn calculate_function_similarity(func1: &FunctionInfo, func2: &FunctionInfo) ->
     if func1.signature == func2.signature {
         score += sig_weight;
     } else {
-        let sig_sim = calculate_simple_string_similarity(&func1.signature, &func2.signature);
+        let sig_sim = calculate_word_similarity(&func1.signature, &func2.signature);
         score += sig_weight * sig_sim * 0.7;
     }
     weight += sig_weight;
@@ -2375,7 +2612,7 @@ fn calculate_function_similarity(func1: &FunctionInfo, func2: &FunctionInfo) ->
         if func1.content == func2.content {
             score += content_weight;
         } else {
-            let content_sim = calculate_content_similarity_fast(&func1.content, &func2.content);
+            let content_sim = calculate_content_similarity(&func1.content, &func2.content);
             score += content_weight * content_sim * 0.8;
         }
     } else if func1.content.is_empty() && func2.content.is_empty() {
@@ -2387,24 +2624,28 @@ fn calculate_function_similarity(func1: &FunctionInfo, func2: &FunctionInfo) ->
 
     let final_score = if weight > 0.0 { score / weight } else { 0.0 };
 
-    // Apply cross-file penalty
-    if !same_file {
-        if final_score < 0.9 {
-            final_score * 0.5 // Heavy penalty for cross-file matches
-        } else {
-            final_score * 0.8
-        }
+    // Apply cross-file penalty, scaled by how far apart the two files are.
+    // A near-identical move keeps at least its old 0.8 multiplier, so only
+    // dissimilar pairs are pushed further apart by distance
+    let penalty = smart_diff_engine::CrossFilePenaltyCurve::default()
+        .penalty(&func1.file_path, &func2.file_path);
+    let penalty = if final_score >= 0.9 {
+        penalty.min(0.2)
     } else {
-        final_score
-    }
+        penalty
+    };
+    final_score * (1.0 - penalty)
 }
 
 /// Check if a function is "simple" (just returns a constant or has very few lines)
-fn is_simple_function(content: &str) -> bool {
-    let lines: Vec<&str> = content
+fn is_simple_function(func: &FunctionInfo) -> bool {
+    let content = func.content.as_str();
+    let code = CommentSyntax::for_language(&LanguageDetector::detect_from_path(&func.file_path))
+        .strip_comments(content);
+    let lines: Vec<&str> = code
         .lines()
         .map(|line| line.trim())
-        .filter(|line| !line.is_empty() && !line.starts_with("//") && !line.starts_with("/*"))
+        .filter(|line| !line.is_empty())
         .collect();
 
     // Consider it simple if:
@@ -2426,56 +2667,21 @@ fn is_simple_function(content: &str) -> bool {
     false
 }
 
-/// Fast string similarity using character overlap
-fn calculate_simple_string_similarity(s1: &str, s2: &str) -> f64 {
-    if s1 == s2 {
-        return 1.0;
-    }
-
-    if s1.is_empty() || s2.is_empty() {
-        return 0.0;
-    }
-
-    // Use character set overlap for fast similarity
-    let chars1: std::collections::HashSet<char> = s1.chars().collect();
-    let chars2: std::collections::HashSet<char> = s2.chars().collect();
-
-    let intersection = chars1.intersection(&chars2).count();
-    let union = chars1.union(&chars2).count();
-
-    if union == 0 {
-        0.0
-    } else {
-        intersection as f64 / union as f64
-    }
+/// Similarity of names or signatures by the identifier words they share, in any order
+fn calculate_word_similarity(s1: &str, s2: &str) -> f64 {
+    let words = smart_diff_engine::TokenSimilarity::new(smart_diff_engine::TokenSimilarityConfig {
+        ngram_size: 1,
+        ignore_case: true,
+    });
+    words.similarity(
+        &smart_diff_engine::identifier_words(s1).join(" "),
+        &smart_diff_engine::identifier_words(s2).join(" "),
+    )
 }
 
-/// Fast content similarity using basic metrics
-fn calculate_content_similarity_fast(content1: &str, content2: &str) -> f64 {
-    if content1 == content2 {
-        return 1.0;
-    }
-
-    let lines1 = content1.lines().count();
-    let lines2 = content2.lines().count();
-    let len1 = content1.len();
-    let len2 = content2.len();
-
-    // Line count similarity (50%)
-    let line_sim = if lines1.max(lines2) == 0 {
-        1.0
-    } else {
-        1.0 - ((lines1 as i32 - lines2 as i32).abs() as f64 / lines1.max(lines2) as f64)
-    };
-
-    // Length similarity (50%)
-    let len_sim = if len1.max(len2) == 0 {
-        1.0
-    } else {
-        1.0 - ((len1 as i32 - len2 as i32).abs() as f64 / len1.max(len2) as f64)
-    };
-
-    (line_sim + len_sim) / 2.0
+/// Content similarity by shared token bigrams, so reordered code scores below identical code
+fn calculate_content_similarity(content1: &str, content2: &str) -> f64 {
+    smart_diff_engine::TokenSimilarity::default().similarity(content1, content2)
 }
 
 /// Generate comparison summary
@@ -2537,8 +2743,8 @@ fn generate_comparison_summary(
 
 /// AST-powered diff handler
 pub async fn ast_diff(
-    Json(request): Json<ASTDiffRequest>,
-) -> Result<ResponseJson<ASTDiffResponse>, StatusCode> {
+    ApiJson(request): ApiJson<ASTDiffRequest>,
+) -> Result<ResponseJson<ASTDiffResponse>, ApiError> {
     info!(
         "Received AST diff request for {} vs {}",
         request.source_file_path, request.target_file_path
@@ -2579,7 +2785,10 @@ pub async fn ast_diff(
         Ok(p) => p,
         Err(e) => {
             warn!("Failed to create parser: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ApiError::Internal(format!(
+                "Failed to create parser: {}",
+                e
+            )));
         }
     };
 
@@ -2587,7 +2796,7 @@ pub async fn ast_diff(
         Ok(ast) => ast,
         Err(e) => {
             warn!("Failed to parse source content: {}", e);
-            return Err(StatusCode::BAD_REQUEST);
+            return Err(e.into());
         }
     };
 
@@ -2595,7 +2804,7 @@ pub async fn ast_diff(
         Ok(ast) => ast,
         Err(e) => {
             warn!("Failed to parse target content: {}", e);
-            return Err(StatusCode::BAD_REQUEST);
+            return Err(e.into());
         }
     };
 
@@ -2617,8 +2826,6 @@ pub async fn ast_diff(
                 generate_lcs_line_mappings(
                     &request.source_content,
                     &request.target_content,
-                    &source_ast,
-                    &target_ast,
                     request.options.ignore_whitespace,
                 )
             }
@@ -2656,12 +2863,10 @@ pub async fn ast_diff(
 fn generate_lcs_line_mappings(
     source_content: &str,
     target_content: &str,
-    _source_ast: &ParseResult,
-    _target_ast: &ParseResult,
     ignore_whitespace: bool,
 ) -> Vec<ASTLineMapping> {
-    let source_lines: Vec<&str> = source_content.lines().collect();
-    let target_lines: Vec<&str> = target_content.lines().collect();
+    let source_lines = smart_diff_parser::source_lines(source_content);
+    let target_lines = smart_diff_parser::source_lines(target_content);
 
     // Use LCS (Longest Common Subsequence) based diff algorithm
     let diff_ops = compute_diff_operations(&source_lines, &target_lines, ignore_whitespace);
@@ -2683,6 +2888,7 @@ fn generate_lcs_line_mappings(
                     similarity: Some(1.0),
                     is_structural_change: false,
                     semantic_changes: Vec::new(),
+                    inline_changes: Vec::new(),
                 });
                 source_idx += 1;
                 target_idx += 1;
@@ -2698,6 +2904,7 @@ fn generate_lcs_line_mappings(
                     similarity: None,
                     is_structural_change: true,
                     semantic_changes: Vec::new(),
+                    inline_changes: Vec::new(),
                 });
                 source_idx += 1;
             }
@@ -2712,6 +2919,7 @@ fn generate_lcs_line_mappings(
                     similarity: None,
                     is_structural_change: true,
                     semantic_changes: Vec::new(),
+                    inline_changes: Vec::new(),
                 });
                 target_idx += 1;
             }
@@ -2727,6 +2935,7 @@ fn generate_lcs_line_mappings(
                     similarity: Some(similarity),
                     is_structural_change: similarity < 0.5,
                     semantic_changes: detect_semantic_changes(&src_line, &tgt_line),
+                    inline_changes: compute_inline_changes(&src_line, &tgt_line),
                 });
                 source_idx += 1;
                 target_idx += 1;
@@ -2753,13 +2962,8 @@ fn generate_ast_aware_line_mappings(
     // New approach: Use LCS for reliable line-by-line mapping, then enhance with AST info
 
     // Step 1: Get base LCS line mappings (reliable and complete)
-    let base_mappings = generate_lcs_line_mappings(
-        source_content,
-        target_content,
-        source_ast,
-        target_ast,
-        options.ignore_whitespace,
-    );
+    let base_mappings =
+        generate_lcs_line_mappings(source_content, target_content, options.ignore_whitespace);
 
     // Step 2: Build AST node lookup by line number for enrichment
     let source_nodes = extract_nodes_with_lines(&source_ast.ast);
@@ -3027,7 +3231,33 @@ fn generate_ast_operations(
     Vec::new()
 }
 
-/// Calculate line similarity
+/// Keywords shared by the supported languages, weighted like operators in line similarity
+const LINE_KEYWORDS: &str = "if else elif for while do switch case default break continue \
+    return yield fn def function func class struct enum impl trait interface let var const \
+    static public private protected new delete try catch except finally throw throws raise \
+    import from use async await match in is not and or null nil None true false True False";
+
+/// Weight of a token in line similarity
+///
+/// Keywords and operators decide what a line does, so they count twice as
+/// much as identifiers, literals and brackets.
+fn line_token_weight(token: &str) -> f64 {
+    let is_word = token.chars().all(|c| c.is_alphanumeric() || c == '_');
+    let significant = if is_word {
+        LINE_KEYWORDS
+            .split_whitespace()
+            .any(|keyword| keyword == token)
+    } else {
+        !matches!(token, "(" | ")" | "[" | "]" | "{" | "}" | "," | ";")
+    };
+    if significant {
+        2.0
+    } else {
+        1.0
+    }
+}
+
+/// Calculate line similarity from the weighted common subsequence of the lines' tokens
 fn calculate_line_similarity(line1: &str, line2: &str) -> f64 {
     let trimmed1 = line1.trim();
     let trimmed2 = line2.trim();
@@ -3036,22 +3266,114 @@ fn calculate_line_similarity(line1: &str, line2: &str) -> f64 {
         return 1.0;
     }
 
-    if trimmed1.is_empty() || trimmed2.is_empty() {
+    let tokens1 = smart_diff_engine::tokenize(trimmed1);
+    let tokens2 = smart_diff_engine::tokenize(trimmed2);
+    if tokens1.is_empty() || tokens2.is_empty() {
         return 0.0;
     }
 
-    // Simple character-based similarity
-    let max_len = trimmed1.len().max(trimmed2.len());
-    let min_len = trimmed1.len().min(trimmed2.len());
+    let mut common = vec![vec![0.0; tokens2.len() + 1]; tokens1.len() + 1];
+    for (i, token1) in tokens1.iter().enumerate() {
+        for (j, token2) in tokens2.iter().enumerate() {
+            common[i + 1][j + 1] = if token1 == token2 {
+                common[i][j] + line_token_weight(token1)
+            } else {
+                f64::max(common[i][j + 1], common[i + 1][j])
+            };
+        }
+    }
 
-    let mut matches = 0;
-    for (c1, c2) in trimmed1.chars().zip(trimmed2.chars()) {
-        if c1 == c2 {
-            matches += 1;
+    let total: f64 = tokens1
+        .iter()
+        .chain(&tokens2)
+        .map(|token| line_token_weight(token))
+        .sum();
+    2.0 * common[tokens1.len()][tokens2.len()] / total
+}
+
+/// Changed token runs between two versions of a line
+///
+/// Tokens are aligned by their longest common subsequence. Consecutive
+/// unaligned tokens on one side form one run, spanning the whitespace
+/// between them, so `foo(a, b)` -> `foo(a, c)` marks only `b` and `c`.
+fn compute_inline_changes(line1: &str, line2: &str) -> Vec<InlineChange> {
+    let tokens1 = token_offsets(line1);
+    let tokens2 = token_offsets(line2);
+
+    let mut common = vec![vec![0usize; tokens2.len() + 1]; tokens1.len() + 1];
+    for i in (0..tokens1.len()).rev() {
+        for j in (0..tokens2.len()).rev() {
+            common[i][j] = if tokens1[i].0 == tokens2[j].0 {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
         }
     }
 
-    (matches as f64) / (max_len as f64) * (min_len as f64) / (max_len as f64)
+    let mut aligned1 = vec![false; tokens1.len()];
+    let mut aligned2 = vec![false; tokens2.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < tokens1.len() && j < tokens2.len() {
+        if tokens1[i].0 == tokens2[j].0 {
+            aligned1[i] = true;
+            aligned2[j] = true;
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    let mut changes = inline_runs(&tokens1, &aligned1, "deleted");
+    changes.extend(inline_runs(&tokens2, &aligned2, "added"));
+    changes
+}
+
+/// Tokens of a line with their character offsets
+fn token_offsets(line: &str) -> Vec<(&str, usize, usize)> {
+    let mut byte_offset = 0;
+    let mut char_offset = 0;
+    smart_diff_engine::tokenize(line)
+        .into_iter()
+        .map(|token| {
+            // Only whitespace separates consecutive tokens
+            let start = byte_offset + line[byte_offset..].find(token).unwrap_or(0);
+            char_offset += line[byte_offset..start].chars().count();
+            byte_offset = start + token.len();
+            let char_start = char_offset;
+            char_offset += token.chars().count();
+            (token, char_start, char_offset)
+        })
+        .collect()
+}
+
+/// Merge consecutive unaligned tokens into runs
+fn inline_runs(
+    tokens: &[(&str, usize, usize)],
+    aligned: &[bool],
+    change_type: &str,
+) -> Vec<InlineChange> {
+    let mut runs: Vec<InlineChange> = Vec::new();
+    let mut extend_last = false;
+    for (&(_, start, end), &is_aligned) in tokens.iter().zip(aligned) {
+        if is_aligned {
+            extend_last = false;
+            continue;
+        }
+        match runs.last_mut() {
+            Some(run) if extend_last => run.end = end,
+            _ => runs.push(InlineChange {
+                change_type: change_type.to_string(),
+                start,
+                end,
+            }),
+        }
+        extend_last = true;
+    }
+    runs
 }
 
 /// Detect semantic changes between two lines
@@ -3109,3 +3431,595 @@ fn calculate_ast_diff_summary(line_mappings: &[ASTLineMapping]) -> ASTDiffSummar
         semantic_changes,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_match_column_counts_characters() {
+        let line = "// Café 日本語 🚀 let résumé = parse();";
+
+        assert_eq!(find_match_column(line, "parse", true), Some(28));
+        assert_eq!(find_match_column(line, "RÉSUMÉ", false), Some(19));
+        assert_eq!(find_match_column(line, "missing", true), None);
+    }
+
+    #[test]
+    fn test_line_similarity_weights_operators_over_identifiers() {
+        let renamed = calculate_line_similarity("if (x > 0) {", "if (y > 0) {");
+        let operator_changed = calculate_line_similarity("if (x > 0) {", "if (x < 0) {");
+        assert!(
+            renamed > operator_changed,
+            "renamed {} vs operator changed {}",
+            renamed,
+            operator_changed
+        );
+        assert_eq!(calculate_line_similarity("  return a;", "return a;"), 1.0);
+        assert_eq!(calculate_line_similarity("return a;", ""), 0.0);
+
+        let operations = compute_diff_operations(
+            &["    total = price * qty;"],
+            &["    sum = price * qty;"],
+            false,
+        );
+        assert!(matches!(operations.as_slice(), [DiffOp::Replace(_, _)]));
+    }
+
+    #[test]
+    fn test_modified_line_marks_only_the_changed_argument() {
+        let mappings = generate_lcs_line_mappings(
+            "fn main() {\n    draw(canvas, origin, \"café\", 12);\n}\n",
+            "fn main() {\n    draw(canvas, center, \"café\", 12);\n}\n",
+            false,
+        );
+        let modified: Vec<&ASTLineMapping> = mappings
+            .iter()
+            .filter(|m| m.change_type == "modified")
+            .collect();
+        assert_eq!(modified.len(), 1);
+
+        let inline = &modified[0].inline_changes;
+        let span = |content: &str, change: &InlineChange| -> String {
+            content
+                .chars()
+                .skip(change.start)
+                .take(change.end - change.start)
+                .collect()
+        };
+        assert_eq!(inline.len(), 2, "{:?}", inline);
+        assert_eq!(inline[0].change_type, "deleted");
+        let source = modified[0].source_content.as_deref().unwrap();
+        assert_eq!(span(source, &inline[0]), "origin");
+        assert_eq!(inline[1].change_type, "added");
+        let target = modified[0].target_content.as_deref().unwrap();
+        assert_eq!(span(target, &inline[1]), "center");
+
+        assert!(mappings
+            .iter()
+            .filter(|m| m.change_type == "unchanged")
+            .all(|m| m.inline_changes.is_empty()));
+    }
+
+    #[test]
+    fn test_function_content_extraction_with_crlf_and_multibyte_lines() {
+        let content = "// Café\r\nint größe(void) {\r\n    return 1; // 日本\r\n}\rint next(void) { return 2; }";
+        let element = |name: &str, start_line, end_line| smart_diff_parser::CodeElement {
+            id: name.to_string(),
+            name: name.to_string(),
+            file_path: "src/größe.c".to_string(),
+            start_line,
+            end_line,
+            element_type: smart_diff_parser::ElementType::Function,
+            signature: None,
+            hash: String::new(),
+        };
+        let files = HashMap::from([("src/größe.c".to_string(), content.to_string())]);
+
+        let info = convert_function_to_info(&element("größe", 2, 4), &files);
+        assert_eq!(info.content, "int größe(void) {\n    return 1; // 日本\n}");
+        let info = convert_function_to_info(&element("next", 5, 7), &files);
+        assert_eq!(info.content, "int next(void) { return 2; }");
+        let info = convert_function_to_info(&element("gone", 9, 12), &files);
+        assert_eq!(info.content, "");
+
+        assert_eq!(
+            extract_content_from_lines(content, 3, 3),
+            "    return 1; // 日本"
+        );
+        assert_eq!(extract_content_from_lines(content, 0, 2), "");
+    }
+
+    #[test]
+    fn test_identical_move_to_sibling_file_keeps_old_multiplier() {
+        let function = |file_path: &str, content: &str| FunctionInfo {
+            name: "checksum".to_string(),
+            signature: "checksum(data: &[u8]) -> u32".to_string(),
+            start_line: 1,
+            end_line: 8,
+            complexity: 1,
+            parameters: Vec::new(),
+            return_type: "u32".to_string(),
+            content: content.to_string(),
+            file_path: file_path.to_string(),
+        };
+        let body = "fn checksum(data: &[u8]) -> u32 {\n    let mut sum = 0u32;\n    \
+                    for byte in data {\n        sum = sum.wrapping_mul(31);\n        \
+                    sum = sum.wrapping_add(*byte as u32);\n    }\n    sum\n}\n";
+
+        let moved = calculate_function_similarity(
+            &function("src/io/read.rs", body),
+            &function("src/io/write.rs", body),
+        );
+        assert!(
+            (moved - 0.8).abs() < 1e-9,
+            "identical move scored {}",
+            moved
+        );
+
+        // Dissimilar pairs are still penalized by distance
+        let other = body.replace("wrapping_mul(31)", "rotate_left(5)");
+        let near = calculate_function_similarity(
+            &function("src/io/read.rs", body),
+            &function("src/io/write.rs", &other),
+        );
+        let far = calculate_function_similarity(
+            &function("src/io/read.rs", body),
+            &function("tests/fixtures/data/write.rs", &other),
+        );
+        assert!(near > far, "sibling {} vs distant {}", near, far);
+    }
+
+    #[test]
+    fn test_function_infos_read_parameters_and_return_type_from_the_ast() {
+        let c_code = "\
+static double scale(double value, int factor)
+{
+    if (factor > 1) {
+        for (int i = 0; i < factor; i++) {
+            value = value * 2;
+        }
+    }
+    return value;
+}
+
+void reset(void) {}
+";
+        let functions = extract_function_infos(c_code, Language::C, "src/scale.c");
+        let function = |name: &str| {
+            functions
+                .iter()
+                .find(|function| function.name == name)
+                .unwrap_or_else(|| panic!("{} not extracted: {:#?}", name, functions))
+        };
+
+        let scale = function("scale");
+        assert_eq!(scale.parameters, vec!["value", "factor"]);
+        assert_eq!(scale.return_type, "double");
+        assert_eq!(scale.complexity, 3);
+        assert_eq!(
+            scale.signature,
+            "static double scale(double value, int factor)"
+        );
+        assert_eq!((scale.start_line, scale.end_line), (1, 9));
+
+        let reset = function("reset");
+        assert!(reset.parameters.is_empty(), "{:?}", reset.parameters);
+        assert_eq!(reset.return_type, "void");
+        assert_eq!(reset.complexity, 1);
+    }
+
+    #[test]
+    fn test_determine_change_type_uses_configured_rename_threshold() {
+        let element = |name: &str| smart_diff_parser::CodeElement {
+            id: name.to_string(),
+            name: name.to_string(),
+            file_path: "src/billing.c".to_string(),
+            start_line: 1,
+            end_line: 10,
+            element_type: smart_diff_parser::ElementType::Function,
+            signature: None,
+            hash: String::new(),
+        };
+        let source = element("compute_total");
+        let target = element("compute_sum");
+
+        let lenient = ChangeClassificationConfig {
+            rename_min_similarity: 0.8,
+            ..Default::default()
+        };
+        let strict = ChangeClassificationConfig {
+            rename_min_similarity: 0.9,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            determine_change_type(&source, &target, 0.85, &lenient),
+            "renamed"
+        );
+        assert_eq!(
+            determine_change_type(&source, &target, 0.85, &strict),
+            "modified"
+        );
+
+        // Between the engine's default and the web UI's own threshold
+        assert_eq!(
+            determine_change_type(&source, &target, 0.82, &Default::default()),
+            "renamed"
+        );
+        assert_eq!(
+            determine_change_type(&source, &target, 0.82, &classification_config()),
+            "modified"
+        );
+        assert_eq!(
+            determine_change_type(&source, &target, 0.85, &classification_config()),
+            "renamed"
+        );
+    }
+
+    #[test]
+    fn test_lower_refactoring_confidence_reports_more_patterns() {
+        use smart_diff_parser::{Change, ChangeType, CodeElement, ElementType};
+
+        let element = |name: &str, line: usize| CodeElement {
+            id: name.to_string(),
+            name: name.to_string(),
+            file_path: "src/orders.py".to_string(),
+            start_line: line,
+            end_line: line + 5,
+            element_type: ElementType::Function,
+            signature: None,
+            hash: String::new(),
+        };
+        let changes = vec![
+            Change::new(ChangeType::Modify, "total changed".to_string())
+                .with_elements(Some(element("total", 1)), Some(element("total", 1))),
+            Change::new(ChangeType::Delete, "tax deleted".to_string())
+                .with_elements(Some(element("tax", 10)), None),
+            Change::new(ChangeType::Delete, "discount deleted".to_string())
+                .with_elements(Some(element("discount", 15)), None),
+            Change::new(ChangeType::Rename, "fmt renamed".to_string())
+                .with_elements(Some(element("fmt", 20)), Some(element("format_order", 20))),
+        ];
+        let detect = |min_refactoring_confidence: Option<f64>| {
+            let options = CompareOptions {
+                min_refactoring_confidence,
+                ..Default::default()
+            };
+            build_refactoring_detector(Language::Python, &options).detect_patterns(&changes)
+        };
+
+        let strict = detect(Some(0.85));
+        let lenient = detect(Some(0.0));
+        assert!(!strict.is_empty());
+        assert!(lenient.len() > strict.len());
+        assert!(strict.iter().all(|p| p.confidence >= 0.85));
+        assert!(lenient
+            .iter()
+            .all(|p| p.confidence >= smart_diff_engine::MIN_CONFIDENCE_FLOOR));
+        assert!(lenient
+            .windows(2)
+            .all(|pair| pair[0].confidence >= pair[1].confidence));
+
+        let summary = build_refactoring_summary(&lenient);
+        assert_eq!(
+            summary.iter().map(|group| group.count).sum::<usize>(),
+            lenient.len()
+        );
+        assert!(summary
+            .windows(2)
+            .all(|pair| pair[0].count >= pair[1].count));
+    }
+
+    #[test]
+    fn test_match_similarity_needs_finished_matching() {
+        let metadata = |functions| FileMetadata {
+            path: "lib.py".to_string(),
+            lines: 10,
+            functions,
+            classes: 0,
+            complexity: 1.0,
+        };
+        let mut matches = smart_diff_parser::MatchResult::new();
+        matches.similarity = 0.8;
+
+        assert_eq!(
+            match_similarity(&metadata(2), &metadata(3), Some(&matches)),
+            0.8
+        );
+        assert_eq!(match_similarity(&metadata(2), &metadata(3), None), 0.0);
+        assert_eq!(
+            match_similarity(&metadata(0), &metadata(3), Some(&matches)),
+            0.0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_semantic_similarity_ignores_renamed_locals() {
+        let worker = |local: &str, callee: &str| {
+            FileInfo {
+            path: "Worker.java".to_string(),
+            content: format!(
+                "class Worker {{\n    int run(int[] items) {{\n        int {l} = 0;\n        \
+                 for (int item : items) {{\n            {l} = {l} + {c}(item);\n        }}\n        \
+                 return {l};\n    }}\n}}\n",
+                l = local,
+                c = callee
+            ),
+        }
+        };
+        let semantic = |local: &'static str, callee: &'static str| async move {
+            let (analysis, _) = perform_comparison(
+                &worker("total", "weight"),
+                &worker(local, callee),
+                &CompareOptions::default(),
+            )
+            .await
+            .unwrap();
+            analysis.files.similarity.semantic
+        };
+
+        let renamed = semantic("sum", "weight").await;
+        assert!((renamed - semantic("total", "weight").await).abs() < 1e-9);
+        assert!(semantic("total", "price").await < renamed);
+    }
+
+    #[tokio::test]
+    async fn test_function_match_ids_are_deterministic() {
+        let file = |content: &str| ComparisonFileInfo {
+            path: "src/lib.py".to_string(),
+            relative_path: "lib.py".to_string(),
+            content: content.to_string(),
+            content_hash: blake3::hash(content.as_bytes()),
+            size: content.len() as u64,
+            modified: None,
+            language: Some("python".to_string()),
+            functions: Vec::new(),
+        };
+        let source = vec![file(
+            "def keep(a):\n    return a\n\ndef old(b):\n    return b * 2\n",
+        )];
+        let target = vec![file(
+            "def keep(a):\n    return a + 1\n\ndef new(c):\n    return [c]\n",
+        )];
+
+        let ids = || async {
+            analyze_function_changes(&source, &target, 0.7)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|m| m.id)
+                .collect::<std::collections::BTreeSet<_>>()
+        };
+        let first = ids().await;
+        let second = ids().await;
+
+        assert!(first.len() >= 2);
+        assert_eq!(first, second);
+
+        // Identical keys within one comparison still get distinct ids
+        let mut generator = MatchIdGenerator::default();
+        assert_ne!(generator.next(None, None), generator.next(None, None));
+        assert!(first.iter().all(|id| Uuid::parse_str(id).is_ok()));
+    }
+
+    fn scanned_paths(dir: &Path, follow_symlinks: bool) -> Vec<String> {
+        let options = crate::models::DirectoryCompareOptions {
+            max_depth: 10,
+            follow_symlinks,
+            ..Default::default()
+        };
+        let mut paths: Vec<_> = scan_directory_for_comparison(dir.to_str().unwrap(), &options)
+            .unwrap()
+            .files
+            .into_iter()
+            .map(|file| file.relative_path)
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_cycle_terminates() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub/app.py"), "x = 1\n").unwrap();
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("sub/loop")).unwrap();
+
+        assert_eq!(scanned_paths(dir.path(), true), vec!["sub/app.py"]);
+        assert_eq!(scanned_paths(dir.path(), false), vec!["sub/app.py"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_followed_symlink_target_included_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("real")).unwrap();
+        std::fs::write(dir.path().join("real/lib.py"), "x = 1\n").unwrap();
+        std::fs::write(outside.path().join("ext.py"), "y = 2\n").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("real"), dir.path().join("alias")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("external")).unwrap();
+
+        let followed = scanned_paths(dir.path(), true);
+        assert_eq!(followed.len(), 2);
+        assert_eq!(followed.iter().filter(|p| p.ends_with("lib.py")).count(), 1);
+        assert!(followed.contains(&"external/ext.py".to_string()));
+
+        assert_eq!(scanned_paths(dir.path(), false), vec!["real/lib.py"]);
+    }
+
+    #[tokio::test]
+    async fn test_exclude_unchanged_keeps_unchanged_functions_in_summary() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let keep = "function keep(items) {\n    let total = 0;\n    for (const item of items) {\n        total += item.price * item.quantity;\n    }\n    return total;\n}\n";
+        let edit = |tax: &str| {
+            format!(
+                "function edit(order) {{\n    let subtotal = keep(order.items);\n    if (order.coupon) {{\n        subtotal -= order.coupon.amount;\n    }}\n    return subtotal * {};\n}}\n",
+                tax
+            )
+        };
+        std::fs::write(
+            source.path().join("cart.js"),
+            format!("{}\n{}", keep, edit("1.2")),
+        )
+        .unwrap();
+        std::fs::write(
+            target.path().join("cart.js"),
+            format!("{}\n{}", keep, edit("(1 + order.taxRate)")),
+        )
+        .unwrap();
+
+        let compare = |exclude_unchanged| {
+            let request = crate::models::CompareDirectoriesRequest {
+                source_path: source.path().to_string_lossy().into_owned(),
+                target_path: target.path().to_string_lossy().into_owned(),
+                options: crate::models::DirectoryCompareOptions {
+                    max_depth: 10,
+                    similarity_threshold: 0.5,
+                    exclude_unchanged,
+                    ..Default::default()
+                },
+            };
+            async move { perform_directory_comparison(&request).await.unwrap() }
+        };
+        let names = |response: &crate::models::CompareDirectoriesResponse| -> Vec<String> {
+            response
+                .function_matches
+                .iter()
+                .filter_map(|m| m.source_function.as_ref().map(|f| f.name.clone()))
+                .collect()
+        };
+
+        let all = compare(false).await;
+        assert!(names(&all).contains(&"keep".to_string()));
+
+        let filtered = compare(true).await;
+        assert_eq!(names(&filtered), vec!["edit"]);
+        assert!(filtered
+            .function_matches
+            .iter()
+            .all(|m| !is_unchanged_match(m)));
+        assert_eq!(filtered.summary.total_functions, 2);
+        assert_eq!(
+            filtered.summary.total_functions,
+            all.summary.total_functions
+        );
+    }
+
+    #[tokio::test]
+    async fn test_quiet_unchanged_files_skips_parsing_identical_files() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let stable = "def stable(a):\n    return a\n";
+        std::fs::write(source.path().join("stable.py"), stable).unwrap();
+        std::fs::write(target.path().join("stable.py"), stable).unwrap();
+        std::fs::write(
+            source.path().join("edit.py"),
+            "def edit(b):\n    return b\n",
+        )
+        .unwrap();
+        std::fs::write(
+            target.path().join("edit.py"),
+            "def edit(b):\n    return b * 2\n",
+        )
+        .unwrap();
+
+        let compare = |quiet_unchanged_files| {
+            let request = crate::models::CompareDirectoriesRequest {
+                source_path: source.path().to_string_lossy().into_owned(),
+                target_path: target.path().to_string_lossy().into_owned(),
+                options: crate::models::DirectoryCompareOptions {
+                    max_depth: 10,
+                    similarity_threshold: 0.5,
+                    quiet_unchanged_files,
+                    ..Default::default()
+                },
+            };
+            async move {
+                let parsed_before = FILES_PARSED.with(|count| count.get());
+                let response = perform_directory_comparison(&request).await.unwrap();
+                (
+                    response,
+                    FILES_PARSED.with(|count| count.get()) - parsed_before,
+                )
+            }
+        };
+        let change_type = |response: &crate::models::CompareDirectoriesResponse, path: &str| {
+            response
+                .file_changes
+                .iter()
+                .find(|change| change.source_path.as_deref() == Some(path))
+                .map(|change| change.change_type.clone())
+                .unwrap()
+        };
+
+        let (all, parsed) = compare(false).await;
+        assert_eq!(parsed, 4);
+        assert_eq!(change_type(&all, "stable.py"), "unchanged");
+
+        let (quiet, parsed) = compare(true).await;
+        assert_eq!(parsed, 2);
+        assert_eq!(change_type(&quiet, "stable.py"), "unchanged");
+        assert_eq!(change_type(&quiet, "edit.py"), "modified");
+        assert!(quiet.function_matches.iter().all(|m| m
+            .source_function
+            .as_ref()
+            .map(|f| f.name.as_str())
+            != Some("stable")));
+        assert_eq!(quiet.summary.unchanged_files, 1);
+    }
+
+    #[tokio::test]
+    async fn test_file_limits_report_truncation_and_skipped_count() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        for dir in [source.path(), target.path()] {
+            for name in ["e.py", "c.py", "a.py", "d.py", "b.py"] {
+                std::fs::write(dir.join(name), "def f(x):\n    return x\n").unwrap();
+            }
+            std::fs::create_dir_all(dir.join("deep/er")).unwrap();
+            std::fs::write(dir.join("deep/er/g.py"), "y = 1\n").unwrap();
+        }
+
+        let options = |max_total_files| crate::models::DirectoryCompareOptions {
+            max_depth: 2,
+            max_total_files,
+            ..Default::default()
+        };
+        let scan =
+            scan_directory_for_comparison(source.path().to_str().unwrap(), &options(Some(3)))
+                .unwrap();
+        let kept: Vec<&str> = scan
+            .files
+            .iter()
+            .map(|file| file.relative_path.as_str())
+            .collect();
+        assert_eq!(kept, vec!["a.py", "b.py", "c.py"]);
+        assert_eq!(scan.skipped_files, 3);
+
+        let request = crate::models::CompareDirectoriesRequest {
+            source_path: source.path().to_string_lossy().into_owned(),
+            target_path: target.path().to_string_lossy().into_owned(),
+            options: options(Some(3)),
+        };
+        let response = perform_directory_comparison(&request).await.unwrap();
+        assert!(response.truncated);
+        assert_eq!(response.skipped_files, 6);
+        assert_eq!(response.file_changes.len(), 3);
+
+        let request = crate::models::CompareDirectoriesRequest {
+            options: crate::models::DirectoryCompareOptions {
+                max_depth: 10,
+                ..options(None)
+            },
+            ..request
+        };
+        let response = perform_directory_comparison(&request).await.unwrap();
+        assert!(!response.truncated);
+        assert_eq!(response.skipped_files, 0);
+        assert_eq!(response.file_changes.len(), 6);
+    }
+}
@@ -13,18 +13,47 @@ use tower_http::cors::CorsLayer;
 use tracing_subscriber;
 
 mod api;
+mod comparisons;
+mod error;
 mod handlers;
+mod metrics;
 mod models;
+mod state;
+
+use state::AppState;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize tracing
     tracing_subscriber::fmt::init();
 
-    let app = Router::new()
+    let app = create_app(AppState::new());
+
+    let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await?;
+
+    tracing::info!("Smart Diff Server listening on http://0.0.0.0:8080");
+
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+/// Build the application router
+fn create_app(state: AppState) -> Router {
+    Router::new()
         // Core API endpoints
         .route("/api/health", get(handlers::health))
+        .route("/api/health/live", get(handlers::health_live))
+        .route("/api/health/ready", get(handlers::health_ready))
+        .route("/metrics", get(handlers::metrics))
         .route("/api/compare", post(handlers::compare))
+        .route("/api/compare/batch", post(handlers::compare_batch))
+        // Stored comparison endpoints
+        .route("/api/comparison/:id", get(handlers::get_comparison))
+        .route(
+            "/api/comparison/:id/function/:name",
+            post(handlers::get_function_diff),
+        )
         .route("/api/analyze", post(handlers::analyze))
         .route("/api/configure", post(handlers::configure))
         // File system API endpoints
@@ -44,13 +73,374 @@ async fn main() -> Result<()> {
         // AST diff endpoint
         .route("/api/ast/diff", post(handlers::ast_diff))
         // CORS for Next.js frontend
-        .layer(CorsLayer::permissive());
+        .layer(CorsLayer::permissive())
+        .with_state(state)
+}
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
 
-    tracing::info!("Smart Diff Server listening on http://0.0.0.0:8080");
+    async fn spawn_server() -> String {
+        spawn_server_with_state(AppState::new()).await
+    }
 
-    axum::serve(listener, app).await?;
+    async fn spawn_server_with_state(state: AppState) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, create_app(state)).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
 
-    Ok(())
+    #[tokio::test]
+    async fn test_batch_compare_reports_per_pair_errors() {
+        let base = spawn_server().await;
+        let pair = |path: &str, old: &str, new: &str| {
+            json!({
+                "file1": { "path": path, "content": old },
+                "file2": { "path": path, "content": new }
+            })
+        };
+
+        let response: Value = reqwest::Client::new()
+            .post(format!("{}/api/compare/batch", base))
+            .json(&json!({
+                "pairs": [
+                    pair("a.py", "def f():\n    return 1\n", "def f():\n    return 2\n"),
+                    pair("notes.txt", "meeting notes", "updated meeting notes"),
+                    pair("b.c", "int g(void) { return 1; }\n", "int g(void) { return 3; }\n"),
+                ]
+            }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        assert_eq!(response["succeeded"], 2);
+        assert_eq!(response["failed"], 1);
+        assert!(response["execution_time_ms"].is_u64());
+
+        let results = response["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results[0]["result"]["comparison_id"].is_string());
+        assert!(results[1]["result"].is_null());
+        assert!(results[1]["error"].is_string());
+        assert_eq!(results[1]["index"], 1);
+        assert!(results[2]["error"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_batch_compare_rejects_oversized_batches() {
+        let mut state = AppState::new();
+        state.batch = state::BatchLimits::new(2, 1);
+        let base = spawn_server_with_state(state).await;
+        let pair = json!({
+            "file1": { "path": "a.py", "content": "def f():\n    return 1\n" },
+            "file2": { "path": "a.py", "content": "def f():\n    return 2\n" }
+        });
+        let client = reqwest::Client::new();
+
+        let rejected = client
+            .post(format!("{}/api/compare/batch", base))
+            .json(&json!({ "pairs": [pair, pair, pair] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(rejected.status(), reqwest::StatusCode::BAD_REQUEST);
+        let body: Value = rejected.json().await.unwrap();
+        assert_eq!(body["error_code"], "bad_request");
+
+        let accepted: Value = client
+            .post(format!("{}/api/compare/batch", base))
+            .json(&json!({ "pairs": [pair, pair] }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(accepted["succeeded"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_function_diff_by_comparison_id() {
+        let base = spawn_server().await;
+        let client = reqwest::Client::new();
+
+        let compare: Value = client
+            .post(format!("{}/api/compare", base))
+            .json(&json!({
+                "file1": {
+                    "path": "math.py",
+                    "content": "def add(a, b):\n    return a + b\n\ndef sub(a, b):\n    return a - b\n"
+                },
+                "file2": {
+                    "path": "math.py",
+                    "content": "def add(a, b):\n    total = a + b\n    return total\n\ndef sub(a, b):\n    return a - b\n"
+                }
+            }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let id = compare["comparison_id"].as_str().unwrap().to_string();
+
+        let stored: Value = client
+            .get(format!("{}/api/comparison/{}", base, id))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(stored, compare);
+
+        let diff: Value = client
+            .post(format!("{}/api/comparison/{}/function/add", base, id))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(diff["comparison_id"], id.as_str());
+        assert_eq!(diff["change_type"], "modified");
+        assert_eq!(diff["summary"]["added_lines"], 1);
+        assert!(diff["summary"]["modified_lines"].as_u64().unwrap() >= 1);
+
+        let unchanged: Value = client
+            .post(format!("{}/api/comparison/{}/function/sub", base, id))
+            .json(&json!({ "ignore_whitespace": true }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(unchanged["change_type"], "unchanged");
+
+        let missing = client
+            .post(format!("{}/api/comparison/{}/function/mul", base, id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+
+        let unknown = client
+            .get(format!("{}/api/comparison/{}", base, uuid::Uuid::new_v4()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unknown.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_compare_timeout_returns_truncated_result() {
+        let base = spawn_server().await;
+        let client = reqwest::Client::new();
+        let compare = |content: String, options: Value| {
+            client
+                .post(format!("{}/api/compare", base))
+                .json(&json!({
+                    "file1": { "path": "funcs.py", "content": content },
+                    "file2": { "path": "funcs.py", "content": content.replace('+', "-") },
+                    "options": options
+                }))
+                .send()
+        };
+        let functions = |count: usize| -> String {
+            (0..count)
+                .map(|i| format!("def f{}(x):\n    return x + {}\n\n", i, i))
+                .collect()
+        };
+
+        let response = compare(functions(300), json!({ "timeout_ms": 1 }))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let partial: Value = response.json().await.unwrap();
+        assert_eq!(partial["truncated"], true);
+        assert!(partial["comparison_id"].is_string());
+        assert_eq!(partial["analysis"]["files"]["source"]["lines"], 900);
+        assert_eq!(partial["analysis"]["refactoring_patterns"], json!([]));
+
+        let response = compare(functions(3), json!({})).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let complete: Value = response.json().await.unwrap();
+        assert_eq!(complete["truncated"], false);
+        assert!(complete["analysis"]["files"]["source"]["functions"].as_u64() > Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint_reports_prometheus_text() {
+        let base = spawn_server().await;
+        let client = reqwest::Client::new();
+
+        for (path, content) in [("a.py", "def f():\n    return 1\n"), ("notes.txt", "notes")] {
+            client
+                .post(format!("{}/api/compare", base))
+                .json(&json!({
+                    "file1": { "path": path, "content": content },
+                    "file2": { "path": path, "content": content }
+                }))
+                .send()
+                .await
+                .unwrap();
+        }
+
+        let response = client
+            .get(format!("{}/metrics", base))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert!(response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain"));
+        let body = response.text().await.unwrap();
+
+        // Every sample line is `name{labels} value` and belongs to a declared metric
+        let mut declared = Vec::new();
+        for line in body.lines() {
+            if let Some(declaration) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = declaration.split_once(' ').unwrap();
+                assert!(["counter", "histogram"].contains(&kind));
+                declared.push(name.to_string());
+            } else if !line.starts_with("# HELP ") {
+                let (series, value) = line.rsplit_once(' ').unwrap();
+                assert!(value.parse::<f64>().is_ok(), "bad sample: {}", line);
+                let name = series.split('{').next().unwrap();
+                assert!(
+                    declared.iter().any(|d| name.starts_with(d.as_str())),
+                    "undeclared metric: {}",
+                    line
+                );
+            }
+        }
+
+        assert!(body.contains("smartdiff_comparisons_total 1\n"));
+        assert!(body.contains("smartdiff_parse_failures_total 1\n"));
+        assert!(body.contains("smartdiff_comparison_duration_seconds_bucket{le=\"+Inf\"} 1\n"));
+        assert!(body.contains("smartdiff_comparison_duration_seconds_count 1\n"));
+    }
+
+    #[tokio::test]
+    async fn test_health_reflects_parser_failure() {
+        let client = reqwest::Client::new();
+
+        let base = spawn_server().await;
+        let health: Value = client
+            .get(format!("{}/api/health", base))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(health["status"], "healthy");
+        assert_eq!(health["components"]["parser"]["status"], "healthy");
+        assert!(health["memory_usage"]["used_mb"].as_f64().unwrap() > 0.0);
+        let ready = client
+            .get(format!("{}/api/health/ready", base))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(ready.status(), reqwest::StatusCode::OK);
+
+        let broken =
+            spawn_server_with_state(AppState::with_readiness_check(std::sync::Arc::new(|| {
+                Err("grammar failed to load".to_string())
+            })))
+            .await;
+        let health: Value = client
+            .get(format!("{}/api/health", broken))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(health["status"], "unhealthy");
+        assert_eq!(health["components"]["parser"]["status"], "unhealthy");
+        assert_eq!(
+            health["components"]["parser"]["details"],
+            "grammar failed to load"
+        );
+
+        let ready = client
+            .get(format!("{}/api/health/ready", broken))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(ready.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let live = client
+            .get(format!("{}/api/health/live", broken))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(live.status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_errors_map_to_status_codes_with_json_bodies() {
+        let client = reqwest::Client::new();
+        let base = spawn_server().await;
+        let error_of = |response: reqwest::Response| async move {
+            let status = response.status();
+            let body: Value = response.json().await.unwrap();
+            (status, body)
+        };
+
+        let missing = tempfile::tempdir().unwrap().path().join("missing.py");
+        let (status, body) = error_of(
+            client
+                .post(format!("{}/api/filesystem/read", base))
+                .json(&json!({ "path": missing }))
+                .send()
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+        assert_eq!(body["error_code"], "not_found");
+        assert!(body["error"].as_str().unwrap().contains("missing.py"));
+
+        let (status, body) = error_of(
+            client
+                .post(format!("{}/api/compare", base))
+                .json(&json!({
+                    "file1": { "path": "notes.txt", "content": "meeting notes" },
+                    "file2": { "path": "notes.txt", "content": "updated meeting notes" }
+                }))
+                .send()
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error_code"], "unsupported_language");
+
+        let (status, body) = error_of(
+            client
+                .post(format!("{}/api/compare", base))
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body("{\"file1\": ")
+                .send()
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, reqwest::StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "bad_request");
+        assert_eq!(body["error"], "Invalid JSON request body");
+        assert!(body["details"].is_string());
+    }
 }
@@ -0,0 +1,96 @@
+//! Server metrics in Prometheus text format
+//!
+//! Counters are plain atomics shared by every handler, so recording a
+//! comparison never contends with a concurrent scrape of `/metrics`.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Upper bounds, in seconds, of the comparison duration histogram buckets
+const DURATION_BUCKETS: [f64; 10] = [0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Shared counters for comparisons served by the web API
+#[derive(Debug, Clone, Default)]
+pub struct ServerMetrics {
+    inner: Arc<MetricsInner>,
+}
+
+#[derive(Debug, Default)]
+struct MetricsInner {
+    comparisons: AtomicU64,
+    parse_failures: AtomicU64,
+    /// Non-cumulative count per bucket, with a final `+Inf` bucket
+    duration_buckets: [AtomicU64; DURATION_BUCKETS.len() + 1],
+    duration_sum_micros: AtomicU64,
+}
+
+impl ServerMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a completed comparison and how long it took
+    pub fn record_comparison(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let bucket = DURATION_BUCKETS
+            .iter()
+            .position(|&bound| seconds <= bound)
+            .unwrap_or(DURATION_BUCKETS.len());
+
+        self.inner.comparisons.fetch_add(1, Ordering::Relaxed);
+        self.inner.duration_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.inner
+            .duration_sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Record a comparison that failed because an input could not be parsed
+    pub fn record_parse_failure(&self) {
+        self.inner.parse_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let comparisons = self.inner.comparisons.load(Ordering::Relaxed);
+
+        write_counter(
+            &mut out,
+            "smartdiff_comparisons_total",
+            "Comparisons performed",
+            comparisons,
+        );
+        write_counter(
+            &mut out,
+            "smartdiff_parse_failures_total",
+            "Comparisons that failed to parse an input file",
+            self.inner.parse_failures.load(Ordering::Relaxed),
+        );
+
+        let name = "smartdiff_comparison_duration_seconds";
+        let _ = writeln!(out, "# HELP {} Time taken by comparisons", name);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+
+        let mut cumulative = 0;
+        for (i, bound) in DURATION_BUCKETS.iter().enumerate() {
+            cumulative += self.inner.duration_buckets[i].load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+        }
+        cumulative += self.inner.duration_buckets[DURATION_BUCKETS.len()].load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, cumulative);
+
+        let sum_seconds = self.inner.duration_sum_micros.load(Ordering::Relaxed) as f64 / 1e6;
+        let _ = writeln!(out, "{}_sum {}", name, sum_seconds);
+        let _ = writeln!(out, "{}_count {}", name, cumulative);
+
+        out
+    }
+}
+
+fn write_counter(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
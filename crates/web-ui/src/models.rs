@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// File information for comparison
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FileInfo {
     pub path: String,
     pub content: String,
@@ -41,6 +41,16 @@ pub struct CompareOptions {
     #[serde(default)]
     #[allow(dead_code)]
     pub detect_moves: bool,
+
+    /// Minimum confidence (0.0-1.0) of reported refactoring patterns;
+    /// raised to the detector's floor when lower
+    #[serde(default)]
+    pub min_refactoring_confidence: Option<f64>,
+
+    /// Time budget in milliseconds; stages still running when it runs out are
+    /// aborted and the response is marked truncated
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 fn default_threshold() -> f64 {
@@ -50,6 +60,9 @@ fn default_threshold() -> f64 {
 /// Response from file comparison
 #[derive(Debug, Serialize)]
 pub struct CompareResponse {
+    /// Id for fetching this comparison again via `/api/comparison/{id}`
+    pub comparison_id: String,
+
     /// Overall similarity score (0.0-1.0)
     pub similarity: f64,
 
@@ -58,6 +71,56 @@ pub struct CompareResponse {
 
     /// Execution time in milliseconds
     pub execution_time_ms: u64,
+
+    /// Whether the time budget ran out and the analysis is partial
+    pub truncated: bool,
+}
+
+/// Request to compare many file pairs at once
+#[derive(Debug, Deserialize)]
+pub struct BatchCompareRequest {
+    pub pairs: Vec<CompareRequest>,
+}
+
+/// Response from a batch comparison
+#[derive(Debug, Serialize)]
+pub struct BatchCompareResponse {
+    /// Per-pair results, in request order
+    pub results: Vec<BatchCompareItem>,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Wall-clock time for the whole batch in milliseconds
+    pub execution_time_ms: u64,
+}
+
+/// Outcome of one pair in a batch comparison
+#[derive(Debug, Serialize)]
+pub struct BatchCompareItem {
+    /// Position of the pair in the request
+    pub index: usize,
+    pub result: Option<CompareResponse>,
+    pub error: Option<String>,
+}
+
+/// Request for a single function's diff within a stored comparison
+#[derive(Debug, Deserialize, Default)]
+pub struct FunctionDiffRequest {
+    /// Whether to ignore whitespace when comparing lines
+    #[serde(default)]
+    pub ignore_whitespace: bool,
+}
+
+/// Diff of a single function within a stored comparison
+#[derive(Debug, Serialize)]
+pub struct FunctionDiffResponse {
+    pub comparison_id: String,
+    pub function_name: String,
+    pub change_type: String, // "added", "deleted", "modified", "unchanged"
+    pub similarity: f64,
+    pub source_function: Option<FunctionInfo>,
+    pub target_function: Option<FunctionInfo>,
+    pub line_mappings: Vec<ASTLineMapping>,
+    pub summary: ASTDiffSummary,
 }
 
 /// Comprehensive analysis result
@@ -72,9 +135,12 @@ pub struct AnalysisResult {
     /// Change classification
     pub changes: ChangeAnalysis,
 
-    /// Refactoring patterns detected
+    /// Refactoring patterns detected, most confident first
     pub refactoring_patterns: Vec<RefactoringPattern>,
 
+    /// Detected refactoring patterns counted per type, most frequent first
+    pub refactoring_summary: Vec<RefactoringGroup>,
+
     /// AST structure comparison
     pub structure: StructureComparison,
 }
@@ -189,6 +255,14 @@ pub struct RefactoringPattern {
     pub impact: String,
 }
 
+/// Refactoring patterns of one type
+#[derive(Debug, Serialize)]
+pub struct RefactoringGroup {
+    pub pattern_type: String,
+    pub count: usize,
+    pub max_confidence: f64,
+}
+
 /// Structure comparison
 #[derive(Debug, Serialize)]
 pub struct StructureComparison {
@@ -373,7 +447,6 @@ pub struct ComponentHealth {
 
 /// Error response
 #[derive(Debug, Serialize)]
-#[allow(dead_code)]
 pub struct ErrorResponse {
     pub error: String,
     pub details: Option<String>,
@@ -552,15 +625,37 @@ pub struct DirectoryCompareOptions {
     #[serde(default = "default_max_depth")]
     pub max_depth: usize,
 
+    /// Most files read from each directory, in file name order; `null` for no limit
+    #[serde(default = "default_max_total_files")]
+    pub max_total_files: Option<usize>,
+
+    /// Follow symbolic links; symlinked entries are skipped otherwise
+    #[serde(default)]
+    pub follow_symlinks: bool,
+
     /// Minimum similarity threshold for function matching
     #[serde(default = "default_threshold")]
     pub similarity_threshold: f64,
+
+    /// Leave unchanged and identical functions out of `function_matches`;
+    /// the summary still counts them
+    #[serde(default)]
+    pub exclude_unchanged: bool,
+
+    /// Skip parsing and function matching of files whose content is identical
+    /// on both sides; they are only reported as unchanged files
+    #[serde(default)]
+    pub quiet_unchanged_files: bool,
 }
 
 fn default_max_depth() -> usize {
     10
 }
 
+fn default_max_total_files() -> Option<usize> {
+    Some(10_000)
+}
+
 /// Response from directory comparison
 #[derive(Debug, Serialize)]
 pub struct CompareDirectoriesResponse {
@@ -568,6 +663,10 @@ pub struct CompareDirectoriesResponse {
     pub file_changes: Vec<FileChange>,
     pub function_matches: Vec<FunctionMatch>,
     pub execution_time_ms: u64,
+    /// Whether `max_depth` or `max_total_files` left files out of the comparison
+    pub truncated: bool,
+    /// Files left out by those limits, in both directories together
+    pub skipped_files: usize,
 }
 
 /// Summary of directory comparison
@@ -692,6 +791,20 @@ pub struct ASTLineMapping {
     pub similarity: Option<f64>,
     pub is_structural_change: bool,
     pub semantic_changes: Vec<String>,
+    /// Changed token runs of a modified line; empty for other lines
+    #[serde(default)]
+    pub inline_changes: Vec<InlineChange>,
+}
+
+/// A run of changed tokens within a modified line
+///
+/// Offsets are character offsets into the line's content: `source_content`
+/// for deletions, `target_content` for additions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InlineChange {
+    pub change_type: String, // "added", "deleted"
+    pub start: usize,
+    pub end: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
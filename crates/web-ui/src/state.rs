@@ -0,0 +1,109 @@
+//! Shared application state
+//!
+//! Handlers extract only the part they need (`State<ComparisonStore>`,
+//! `State<ServerMetrics>`) through `FromRef`, so most of them stay unaware of
+//! the rest of the state.
+
+use axum::extract::FromRef;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Semaphore;
+
+use smart_diff_parser::tree_sitter::TreeSitterParser;
+
+use crate::comparisons::{env_number, ComparisonStore};
+use crate::metrics::ServerMetrics;
+
+/// Environment variable overriding [`BatchLimits::max_pairs`]
+pub const MAX_BATCH_PAIRS_ENV: &str = "SMART_DIFF_MAX_BATCH_PAIRS";
+
+/// Environment variable overriding how many pairs are compared at once
+pub const BATCH_CONCURRENCY_ENV: &str = "SMART_DIFF_BATCH_CONCURRENCY";
+
+/// Limits of `/api/compare/batch`
+///
+/// The permits are shared by every batch request, so concurrent batches
+/// together never compare more pairs at once than configured.
+#[derive(Debug, Clone)]
+pub struct BatchLimits {
+    /// Most pairs accepted in one batch; larger batches are rejected
+    pub max_pairs: usize,
+    /// Permits for the pairs compared at once, across all batches
+    pub permits: Arc<Semaphore>,
+}
+
+impl BatchLimits {
+    pub fn new(max_pairs: usize, concurrency: usize) -> Self {
+        Self {
+            max_pairs,
+            permits: Arc::new(Semaphore::new(concurrency.max(1))),
+        }
+    }
+
+    /// 100 pairs per batch and 8 at once, overridden by [`MAX_BATCH_PAIRS_ENV`]
+    /// and [`BATCH_CONCURRENCY_ENV`]
+    pub fn from_env() -> Self {
+        Self::new(
+            env_number(MAX_BATCH_PAIRS_ENV).map_or(100, |max| max as usize),
+            env_number(BATCH_CONCURRENCY_ENV).map_or(8, |max| max as usize),
+        )
+    }
+}
+
+/// Readiness probe: number of loaded grammars, or why the parser is unusable
+pub type ReadinessCheck = Arc<dyn Fn() -> Result<usize, String> + Send + Sync>;
+
+/// State shared by every request handler
+#[derive(Clone)]
+pub struct AppState {
+    pub comparisons: ComparisonStore,
+    pub batch: BatchLimits,
+    pub metrics: ServerMetrics,
+    pub readiness: ReadinessCheck,
+    pub started_at: Instant,
+}
+
+impl AppState {
+    pub fn new() -> Self {
+        Self::with_readiness_check(Arc::new(check_parser))
+    }
+
+    /// State whose readiness is decided by `readiness` instead of the parser check
+    pub fn with_readiness_check(readiness: ReadinessCheck) -> Self {
+        Self {
+            comparisons: ComparisonStore::new(),
+            batch: BatchLimits::from_env(),
+            metrics: ServerMetrics::new(),
+            readiness,
+            started_at: Instant::now(),
+        }
+    }
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FromRef<AppState> for ComparisonStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.comparisons.clone()
+    }
+}
+
+impl FromRef<AppState> for ServerMetrics {
+    fn from_ref(state: &AppState) -> Self {
+        state.metrics.clone()
+    }
+}
+
+/// The server can compare files once a parser with at least one grammar can be built
+fn check_parser() -> Result<usize, String> {
+    TreeSitterParser::new().map_err(|e| e.to_string())?;
+
+    match TreeSitterParser::supported_languages().len() {
+        0 => Err("No language grammars loaded".to_string()),
+        grammars => Ok(grammars),
+    }
+}
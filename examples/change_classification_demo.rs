@@ -213,35 +213,41 @@ fn demo_configuration_customization() -> Result<()> {
     // Test different configurations
     let configs = vec![
         ("Conservative", ChangeClassificationConfig {
-            modification_threshold: 0.9,
-            rename_threshold: 0.95,
-            move_threshold: 0.98,
+            modify_min_similarity: 0.9,
+            rename_min_similarity: 0.95,
+            move_min_similarity: 0.98,
+            identical_min_similarity: 0.99,
             enable_ast_analysis: true,
             enable_semantic_analysis: true,
             enable_confidence_scoring: true,
             max_ast_depth: 30,
             enable_impact_analysis: true,
+            normalize_identifiers: false,
         }),
         ("Balanced", ChangeClassificationConfig::default()),
         ("Aggressive", ChangeClassificationConfig {
-            modification_threshold: 0.5,
-            rename_threshold: 0.6,
-            move_threshold: 0.7,
+            modify_min_similarity: 0.5,
+            rename_min_similarity: 0.6,
+            move_min_similarity: 0.7,
+            identical_min_similarity: 0.99,
             enable_ast_analysis: true,
             enable_semantic_analysis: true,
             enable_confidence_scoring: true,
             max_ast_depth: 15,
             enable_impact_analysis: true,
+            normalize_identifiers: false,
         }),
         ("Performance Optimized", ChangeClassificationConfig {
-            modification_threshold: 0.7,
-            rename_threshold: 0.8,
-            move_threshold: 0.9,
+            modify_min_similarity: 0.7,
+            rename_min_similarity: 0.8,
+            move_min_similarity: 0.9,
+            identical_min_similarity: 0.99,
             enable_ast_analysis: false,
             enable_semantic_analysis: false,
             enable_confidence_scoring: false,
             max_ast_depth: 10,
             enable_impact_analysis: false,
+            normalize_identifiers: false,
         }),
     ];
 
@@ -254,8 +260,8 @@ fn demo_configuration_customization() -> Result<()> {
         
         println!("🔧 {} Configuration:", config_name);
         println!("  • Change Type: {:?}", change_type);
-        println!("  • Modification Threshold: {:.2}", classifier.get_config().modification_threshold);
-        println!("  • Rename Threshold: {:.2}", classifier.get_config().rename_threshold);
+        println!("  • Modification Threshold: {:.2}", classifier.get_config().modify_min_similarity);
+        println!("  • Rename Threshold: {:.2}", classifier.get_config().rename_min_similarity);
         println!("  • AST Analysis: {}", classifier.get_config().enable_ast_analysis);
         println!("  • Semantic Analysis: {}", classifier.get_config().enable_semantic_analysis);
         println!();
@@ -338,14 +344,14 @@ fn demo_advanced_features() -> Result<()> {
 
     // Test configuration updates
     println!("\n🔧 Dynamic Configuration Updates:");
-    let original_threshold = classifier.get_config().modification_threshold;
+    let original_threshold = classifier.get_config().modify_min_similarity;
     println!("  • Original modification threshold: {:.2}", original_threshold);
     
     let mut new_config = classifier.get_config().clone();
-    new_config.modification_threshold = 0.85;
+    new_config.modify_min_similarity = 0.85;
     classifier.set_config(new_config);
     
-    println!("  • Updated modification threshold: {:.2}", classifier.get_config().modification_threshold);
+    println!("  • Updated modification threshold: {:.2}", classifier.get_config().modify_min_similarity);
 
     // Test evidence strength analysis
     println!("\n📊 Evidence Strength Analysis:");
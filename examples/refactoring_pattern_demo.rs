@@ -374,6 +374,8 @@ fn demo_configuration_customization() -> Result<()> {
             enable_extract_class: true,
             enable_inline_class: false, // Disabled for conservative approach
             enable_change_signature: false, // Disabled for conservative approach
+            enable_introduce_parameter_object: false, // Disabled for conservative approach
+            enable_guard_clause_detection: true,
             max_related_distance: 25,
             enable_complex_patterns: false,
         }),
@@ -387,6 +389,8 @@ fn demo_configuration_customization() -> Result<()> {
             enable_extract_class: true,
             enable_inline_class: true,
             enable_change_signature: true,
+            enable_introduce_parameter_object: true,
+            enable_guard_clause_detection: true,
             max_related_distance: 100,
             enable_complex_patterns: true,
         }),
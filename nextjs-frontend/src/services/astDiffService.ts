@@ -8,6 +8,15 @@ export interface ASTDiffLine {
   similarity?: number;
   isStructuralChange?: boolean;
   semanticChanges?: string[];
+  inlineChanges?: ASTInlineChange[];
+}
+
+// Changed token run within a modified line, in character offsets into
+// sourceContent (deleted) or targetContent (added)
+export interface ASTInlineChange {
+  type: 'added' | 'deleted';
+  start: number;
+  end: number;
 }
 
 export interface ASTDiffResult {
@@ -107,6 +116,11 @@ export class ASTDiffService {
           similarity: mapping.similarity,
           isStructuralChange: mapping.is_structural_change,
           semanticChanges: mapping.semantic_changes || [],
+          inlineChanges: (mapping.inline_changes || []).map((change: any) => ({
+            type: change.change_type,
+            start: change.start,
+            end: change.end,
+          })),
         });
       });
     }